@@ -0,0 +1,19 @@
+"""aios_core Python 绑定的类型存根"""
+
+from typing import Any, Optional
+
+def init() -> None:
+    """初始化数据库连接（按 DB_OPTION_FILE 配置）"""
+
+def get_pe(refno: str) -> Optional[dict[str, Any]]: ...
+def get_attmap(refno: str) -> dict[str, Any]: ...
+def get_children(refno: str) -> list[str]: ...
+def get_descendants(refno: str) -> list[str]: ...
+def get_world_transform(refno: str) -> Optional[list[float]]:
+    """列主序 4x4 矩阵，共 16 个元素"""
+
+def query_insts(refnos: list[str], enable_holes: bool = False) -> list[dict[str, Any]]: ...
+def get_material_lists(major: str, site: str) -> dict[str, list[dict[str, Any]]]:
+    """major 为专业代码：T/I/V/E/W/EQUI/N"""
+
+def run_doctor() -> dict[str, Any]: ...
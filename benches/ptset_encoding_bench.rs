@@ -0,0 +1,80 @@
+//! ptset 存储格式对比：压缩 JSON 与 CBOR 的编码/解码耗时及体积
+//!
+//! ```bash
+//! cargo bench --bench ptset_encoding_bench --features ptset-cbor
+//! ```
+
+use aios_core::RefnoEnum;
+use aios_core::parsed_data::CateAxisParam;
+use aios_core::shape::pdms_shape::RsVec3;
+use aios_core::vec3_pool::{
+    compress_ptset, decode_ptset_cbor, encode_ptset_cbor, parse_ptset_auto,
+};
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use glam::Vec3;
+
+/// 生成典型的元件点集：多数为轴向，少数为斜向并带连接类型
+fn generate_ptset(count: usize) -> Vec<CateAxisParam> {
+    (0..count)
+        .map(|i| {
+            let oblique = i % 4 == 3;
+            CateAxisParam {
+                refno: RefnoEnum::default(),
+                number: i as i32 + 1,
+                pt: RsVec3(Vec3::new(i as f32 * 25.0, 0.0, (i % 3) as f32 * 10.0)),
+                dir: Some(RsVec3(if oblique {
+                    Vec3::new(0.6, 0.8, 0.0)
+                } else {
+                    Vec3::X
+                })),
+                dir_flag: 1.0,
+                ref_dir: Some(RsVec3(Vec3::Z)),
+                pbore: if i < 2 { 100.0 } else { 0.0 },
+                pwidth: 0.0,
+                pheight: 0.0,
+                pconnect: if i < 2 {
+                    "BWD".to_string()
+                } else {
+                    String::new()
+                },
+            }
+        })
+        .collect()
+}
+
+fn bench_ptset_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ptset_encoding");
+    for count in [4, 16, 64] {
+        let params = generate_ptset(count);
+        let json = serde_json::to_string(&compress_ptset(&params, false)).unwrap();
+        let cbor = encode_ptset_cbor(&params, false).unwrap();
+        println!(
+            "ptset {} 点: 压缩 JSON {} 字节, CBOR {} 字节 ({:.1}%)",
+            count,
+            json.len(),
+            cbor.len(),
+            cbor.len() as f64 * 100.0 / json.len() as f64
+        );
+
+        group.bench_with_input(BenchmarkId::new("encode_json", count), &params, |b, p| {
+            b.iter(|| serde_json::to_string(&compress_ptset(black_box(p), false)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("encode_cbor", count), &params, |b, p| {
+            b.iter(|| encode_ptset_cbor(black_box(p), false).unwrap())
+        });
+
+        let json_value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("decode_json", count),
+            &json_value,
+            |b, v| b.iter(|| parse_ptset_auto(black_box(v)).unwrap()),
+        );
+        group.bench_with_input(BenchmarkId::new("decode_cbor", count), &cbor, |b, bytes| {
+            b.iter(|| decode_ptset_cbor(black_box(bytes)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ptset_encoding);
+criterion_main!(benches);
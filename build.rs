@@ -0,0 +1,26 @@
+#[cfg(feature = "typed-attrs")]
+#[path = "build/typed_attrs.rs"]
+mod typed_attrs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // 按 noun 生成的类型化属性结构体（src/typed_attrs）
+    #[cfg(feature = "typed-attrs")]
+    {
+        println!("cargo:rerun-if-changed=build/typed_attrs.rs");
+        println!("cargo:rerun-if-changed=all_attr_info.json");
+        let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("typed_attrs.rs");
+        typed_attrs::generate(std::path::Path::new("all_attr_info.json"), &out)
+            .expect("生成类型化属性结构体失败");
+    }
+
+    // gRPC 服务代码（src/service），需要本机安装 protoc
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/aios_core.proto");
+        tonic_build::configure()
+            .compile_protos(&["proto/aios_core.proto"], &["proto"])
+            .expect("编译 proto/aios_core.proto 失败");
+    }
+}
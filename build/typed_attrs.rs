@@ -0,0 +1,219 @@
+//! 类型化属性结构体生成（`typed-attrs` 特性）
+//!
+//! 读取 `all_attr_info.json`（由 attlib.dat 导出）中每个 noun 的属性定义，
+//! 为每个 noun 生成 `XxxxAttrs` 结构体与 `From<&NamedAttrMap>` 转换，输出到 `OUT_DIR/typed_attrs.rs`，
+//! 由 `src/typed_attrs/mod.rs` include。字段类型按属性默认值的类型确定，缺失时取 attlib 中的默认值。
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+/// 与 `tool::db_tool::db1_dehash` 相同的 noun 哈希反查
+fn dehash(hash: u32) -> String {
+    let mut result = String::new();
+    if hash <= 0x81BF1 || hash > 0x171FAD39 {
+        return result;
+    }
+    let mut k = hash - 0x81BF1;
+    while k > 0 {
+        result.push((k % 27 + 64) as u8 as char);
+        k /= 27;
+    }
+    result
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn field_ident(attr: &str) -> String {
+    let mut ident = attr
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn struct_ident(noun: &str) -> String {
+    let mut chars = noun.chars().filter(|c| c.is_ascii_alphanumeric());
+    let mut ident = chars
+        .next()
+        .map(|c| c.to_ascii_uppercase().to_string())
+        .unwrap_or_default();
+    ident.extend(chars.map(|c| c.to_ascii_lowercase()));
+    ident.push_str("Attrs");
+    ident
+}
+
+fn f32_lit(v: &Value) -> String {
+    format!("{:?}f32", v.as_f64().unwrap_or_default() as f32)
+}
+
+/// (字段类型, 取值表达式)；无法确定类型的属性返回 None
+fn field_spec(attr: &str, default: &Value) -> Option<(String, String)> {
+    let (kind, val) = default.as_object()?.iter().next()?;
+    let key = format!("{:?}", attr);
+    Some(match kind.as_str() {
+        "ElementType" => (
+            "Option<RefnoEnum>".into(),
+            format!("m.get_foreign_refno({key})"),
+        ),
+        "StringType" | "WordType" => (
+            "String".into(),
+            format!(
+                "m.get_str({key}).unwrap_or({:?}).to_string()",
+                val.as_str().unwrap_or_default()
+            ),
+        ),
+        "BoolType" => (
+            "bool".into(),
+            format!(
+                "m.get_bool({key}).unwrap_or({})",
+                val.as_bool().unwrap_or_default()
+            ),
+        ),
+        "IntegerType" => (
+            "i32".into(),
+            format!(
+                "m.get_i32({key}).unwrap_or({})",
+                val.as_i64().unwrap_or_default()
+            ),
+        ),
+        "DoubleType" => (
+            "f32".into(),
+            format!("m.get_f32({key}).unwrap_or({})", f32_lit(val)),
+        ),
+        "Vec3Type" => {
+            let xyz = val.as_array().cloned().unwrap_or_default();
+            let c = |i: usize| f32_lit(xyz.get(i).unwrap_or(&Value::Null));
+            (
+                "Vec3".into(),
+                format!(
+                    "m.get_vec3({key}).unwrap_or(Vec3::new({}, {}, {}))",
+                    c(0),
+                    c(1),
+                    c(2)
+                ),
+            )
+        }
+        "IntArrayType" => (
+            "Vec<i32>".into(),
+            format!("m.get_i32_vec({key}).unwrap_or_default()"),
+        ),
+        "DoubleArrayType" => (
+            "Vec<f32>".into(),
+            format!("m.get_f32_vec({key}).unwrap_or_default()"),
+        ),
+        "RefU64Array" => (
+            "Vec<RefnoEnum>".into(),
+            format!("m.get_refno_vec({key}).unwrap_or_default()"),
+        ),
+        "StringArrayType" => (
+            "Vec<String>".into(),
+            format!(
+                "match m.get_val({key}) {{ Some(NamedAttrValue::StringArrayType(v)) => v.clone(), _ => vec![] }}"
+            ),
+        ),
+        _ => return None,
+    })
+}
+
+/// 生成代码，返回 noun 数量
+pub fn generate(json_path: &Path, out_path: &Path) -> std::io::Result<usize> {
+    let json: Value = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+    let empty = serde_json::Map::new();
+    let nouns = json["noun_attr_info_map"].as_object().unwrap_or(&empty);
+
+    // noun 名称 -> (属性名 -> 默认值)，按名称排序保证输出稳定
+    let mut defs: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+    for (hash, attrs) in nouns {
+        let noun = dehash(hash.parse().unwrap_or_default());
+        if noun.is_empty() {
+            continue;
+        }
+        let entry = defs.entry(noun).or_default();
+        for info in attrs.as_object().unwrap_or(&empty).values() {
+            if let Some(name) = info["name"].as_str() {
+                entry.insert(name.to_string(), info["default_val"].clone());
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let mut seen = BTreeSet::new();
+    for (noun, attrs) in &defs {
+        let ident = struct_ident(noun);
+        if !seen.insert(ident.clone()) {
+            continue;
+        }
+        let mut fields = vec![];
+        let mut used = BTreeSet::new();
+        for (attr, default) in attrs {
+            let Some((ty, expr)) = field_spec(attr, default) else {
+                continue;
+            };
+            let field = field_ident(attr);
+            if used.insert(field.clone()) {
+                fields.push((attr, field, ty, expr));
+            }
+        }
+
+        writeln!(out, "/// {noun} 的属性").unwrap();
+        writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+        writeln!(out, "pub struct {ident} {{").unwrap();
+        for (attr, field, ty, _) in &fields {
+            writeln!(out, "    /// {attr}\n    pub {field}: {ty},").unwrap();
+        }
+        writeln!(out, "}}\n").unwrap();
+
+        writeln!(out, "impl From<&NamedAttrMap> for {ident} {{").unwrap();
+        let m = if fields.is_empty() { "_m" } else { "m" };
+        writeln!(
+            out,
+            "    fn from({m}: &NamedAttrMap) -> Self {{\n        Self {{"
+        )
+        .unwrap();
+        for (_, field, _, expr) in &fields {
+            writeln!(out, "            {field}: {expr},").unwrap();
+        }
+        writeln!(out, "        }}\n    }}\n}}\n").unwrap();
+
+        writeln!(
+            out,
+            "impl From<NamedAttrMap> for {ident} {{\n    fn from(m: NamedAttrMap) -> Self {{\n        Self::from(&m)\n    }}\n}}\n"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "impl Default for {ident} {{\n    fn default() -> Self {{\n        Self::from(&NamedAttrMap::default())\n    }}\n}}\n"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "impl TypedAttrs for {ident} {{\n    const NOUN: &'static str = {noun:?};\n}}\n"
+        )
+        .unwrap();
+    }
+
+    writeln!(out, "/// 全部已生成结构体的 noun").unwrap();
+    writeln!(out, "pub const TYPED_NOUNS: &[&str] = &[").unwrap();
+    for noun in defs.keys() {
+        writeln!(out, "    {noun:?},").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    std::fs::write(out_path, out)?;
+    Ok(defs.len())
+}
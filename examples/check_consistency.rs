@@ -0,0 +1,39 @@
+use aios_core::sync::consistency::{self, ConsistencyOptions};
+use aios_core::*;
+use anyhow::Result;
+
+/// 检查几何实例数据与 pe 层级的一致性
+///
+/// 用法: cargo run --example check_consistency -- [--repair] [--dbnum 17496]
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_surreal().await?;
+
+    let args = std::env::args().collect::<Vec<_>>();
+    let repair = args.iter().any(|a| a == "--repair");
+    let dbnum = args
+        .iter()
+        .position(|a| a == "--dbnum")
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.parse::<u32>())
+        .transpose()?;
+
+    let report = consistency::check(ConsistencyOptions {
+        repair,
+        dbnum,
+        ..Default::default()
+    })
+    .await?;
+
+    println!("{}", report.summary());
+    for refno in report.dangling_cata_hashes.iter().take(20) {
+        println!("  悬空 cata_hash: {}", refno);
+    }
+    for refno in report.pe_missing_geometry.iter().take(20) {
+        println!("  无几何 pe: {}", refno);
+    }
+    if !repair && !report.is_consistent() {
+        println!("使用 --repair 修复以上问题");
+    }
+    Ok(())
+}
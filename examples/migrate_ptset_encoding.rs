@@ -0,0 +1,58 @@
+//! 把 inst_info 的 ptset 原地转换为 CBOR bytes（或用 `--to compact` 转回）
+//!
+//! ```bash
+//! cargo run --example migrate_ptset_encoding --features ptset-cbor -- --dry-run
+//! cargo run --example migrate_ptset_encoding --features ptset-cbor -- --to compact
+//! ```
+
+use aios_core::rs_surreal::ptset_migration::{PtsetMigrationOptions, migrate_inst_info_ptset};
+use aios_core::vec3_pool::PtsetEncoding;
+use aios_core::*;
+use anyhow::{Result, bail};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut options = PtsetMigrationOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => options.dry_run = true,
+            "--to" => {
+                options.target = match args.next().as_deref() {
+                    Some("cbor") => PtsetEncoding::Cbor,
+                    Some("compact") => PtsetEncoding::Compact,
+                    other => bail!("未知的目标格式: {:?}", other),
+                }
+            }
+            "--chunk" => options.chunk_size = args.next().unwrap_or_default().parse()?,
+            other => bail!("未知参数: {}", other),
+        }
+    }
+
+    init_surreal().await?;
+    let report = migrate_inst_info_ptset(&options).await?;
+
+    println!(
+        "{} inst_info: 共 {} 个, 转换 {} 个, 跳过 {} 个",
+        if options.dry_run {
+            "试运行"
+        } else {
+            "已迁移"
+        },
+        report.scanned,
+        report.converted,
+        report.skipped.len()
+    );
+    if report.bytes_before > 0 {
+        println!(
+            "ptset 占用: {} -> {} 字节 ({:.1}%)",
+            report.bytes_before,
+            report.bytes_after,
+            report.bytes_after as f64 * 100.0 / report.bytes_before as f64
+        );
+    }
+    for (key, reason) in report.skipped.iter().take(20) {
+        println!("  跳过 {}: {}", key, reason);
+    }
+    Ok(())
+}
@@ -1,5 +1,4 @@
 use crate::geometry::PlantGeoData;
-use crate::shape::pdms_shape::PlantMesh;
 use crate::{GeomInstQuery, SUL_DB, types::*};
 use approx::{AbsDiffEq, abs_diff_ne, assert_abs_diff_eq};
 use bevy_ecs::prelude::Resource;
@@ -194,6 +193,12 @@ impl AccelerationTree {
         self.tree.size() == 0
     }
 
+    /// 查找元素的包围盒
+    pub fn get_bbox(&self, refno: RefnoEnum) -> Option<&RStarBoundingBox> {
+        let refno = refno.refno();
+        self.tree.iter().find(|bb| bb.refno == refno)
+    }
+
     /// 加载包围盒
     pub fn load(mut bounding_boxes: Vec<RStarBoundingBox>) -> Self {
         Self {
@@ -281,12 +286,13 @@ impl AccelerationTree {
         }
         let geom_insts = crate::query_insts(&[refno], true).await.ok()?;
         // dbg!(geom_insts.len());
+        let store = crate::mesh_store::mesh_store();
         let mut meshes = vec![];
         for g in geom_insts {
             // dbg!(&g);
             for inst in &g.insts {
-                let Ok(mesh) =
-                    PlantMesh::des_mesh_file(&format!("assets/meshes/{}.mesh", inst.geo_hash))
+                let Ok(Some(mesh)) =
+                    crate::mesh_store::load_mesh(store.as_ref(), &inst.geo_hash, "").await
                 else {
                     continue;
                 };
@@ -322,13 +322,13 @@ impl PdmsDataInterface for AiosDBMgr {
 
 impl AiosDBMgr {
     ///获得默认的连接字符串
-    pub fn default_mysql_conn_str() -> String {
-        let d = get_db_option();
+    pub fn default_mysql_conn_str() -> anyhow::Result<String> {
+        let d = get_db_option()?;
         let user = d.user.as_str();
         let pwd = urlencoding::encode(&d.password);
         let ip = d.ip.as_str();
         let port = d.port.as_str();
-        format!("mysql://{user}:{pwd}@{ip}:{port}")
+        Ok(format!("mysql://{user}:{pwd}@{ip}:{port}"))
     }
 
     pub fn puhua_conn_str(&self) -> String {
@@ -342,7 +342,7 @@ impl AiosDBMgr {
     #[cfg(feature = "sql")]
     /// 获取项目配置信息pool
     pub async fn get_global_pool() -> anyhow::Result<Pool<MySql>> {
-        let connection_str = Self::default_mysql_conn_str();
+        let connection_str = Self::default_mysql_conn_str()?;
         let url = &format!("{connection_str}/{}", GLOBAL_DATABASE);
         PoolOptions::new()
             .max_connections(500)
@@ -355,8 +355,8 @@ impl AiosDBMgr {
     #[cfg(feature = "sql")]
     /// 获取项目pool
     pub async fn get_project_pool() -> anyhow::Result<Pool<MySql>> {
-        let connection_str = Self::default_mysql_conn_str();
-        let db_option = get_db_option();
+        let connection_str = Self::default_mysql_conn_str()?;
+        let db_option = get_db_option()?;
         let url = &format!("{connection_str}/{}", db_option.project_name);
         PoolOptions::new()
             .max_connections(500)
@@ -369,7 +369,7 @@ impl AiosDBMgr {
     #[cfg(feature = "sql")]
     /// 获取include_projects不同项目对应的pool
     pub async fn get_project_pools(&self) -> anyhow::Result<HashMap<String, Pool<MySql>>> {
-        let connection_str = Self::default_mysql_conn_str();
+        let connection_str = Self::default_mysql_conn_str()?;
         let mut map = HashMap::new();
         for project in &self.db_option.included_projects {
             let url = format!("{connection_str}/{}", project);
@@ -0,0 +1,8 @@
+//! 工程分析：重量、重心等
+
+pub mod weight;
+
+pub use weight::{
+    ElementMass, WeightOptions, WeightRollup, compute_element_masses, save_weight_rollups,
+    update_weight_rollup, weight_rollup,
+};
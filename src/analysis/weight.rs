@@ -0,0 +1,372 @@
+//! 重量与重心汇总
+//!
+//! 单个元素的体积和形心由其几何实例的 mesh（世界坐标）按有向四面体求和
+//! 得到，密度取材料（`MATR` 引用的材料元素的 `DENS`，单位 kg/m³），元素本身
+//! 没有材料时再看其规格组件（`SPRE`）上的材料，都没有时用选项中的默认密度。
+//!
+//! 汇总时把 BRAN / EQUI / ZONE / SITE 等层级下所有元素的重量相加，重心按
+//! 重量加权平均。结果写入 [`WEIGHT_TABLE`] 表。
+
+use crate::rs_surreal::transaction::Transaction;
+use crate::shape::pdms_shape::PlantMesh;
+use crate::mesh_store::{load_mesh_cached, mesh_store};
+use crate::{
+    RefnoEnum, get_named_attmap, query_deep_children_refnos, query_filter_deep_children,
+    query_insts,
+};
+use glam::{DMat4, DVec3, Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 重量汇总结果表
+pub const WEIGHT_TABLE: &str = "weight_rollup";
+
+/// 每个事务写入的记录数
+const SAVE_CHUNK: usize = 500;
+
+/// mm³ 到 m³
+const MM3_PER_M3: f64 = 1e9;
+
+/// 重量计算选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightOptions {
+    /// 找不到材料密度时使用的密度（kg/m³），为 None 时这类元素不计重量
+    pub default_density: Option<f64>,
+    /// 需要汇总的层级类型
+    pub rollup_nouns: Vec<String>,
+}
+
+impl Default for WeightOptions {
+    fn default() -> Self {
+        Self {
+            // 碳钢
+            default_density: Some(7850.0),
+            rollup_nouns: ["BRAN", "EQUI", "ZONE", "SITE"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// 体积与形心
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MassProperties {
+    /// 体积（mm³）
+    pub volume: f64,
+    /// 形心（世界坐标，mm）
+    pub centroid: DVec3,
+}
+
+impl MassProperties {
+    /// 计算封闭 mesh 在给定变换下的体积和形心，三角形需朝外
+    pub fn of_mesh(mesh: &PlantMesh, transform: DMat4) -> Self {
+        let vertices: Vec<DVec3> = mesh
+            .vertices
+            .iter()
+            .map(|v| transform.transform_point3(v.as_dvec3()))
+            .collect();
+        let mut volume = 0.0;
+        let mut moment = DVec3::ZERO;
+        for t in mesh.indices.chunks_exact(3) {
+            let (Some(a), Some(b), Some(c)) = (
+                vertices.get(t[0] as usize),
+                vertices.get(t[1] as usize),
+                vertices.get(t[2] as usize),
+            ) else {
+                continue;
+            };
+            // 以原点为顶点的有向四面体
+            let v = a.dot(b.cross(*c)) / 6.0;
+            volume += v;
+            moment += v * (*a + *b + *c) / 4.0;
+        }
+        let centroid = if volume.abs() > f64::EPSILON {
+            moment / volume
+        } else {
+            DVec3::ZERO
+        };
+        Self { volume, centroid }
+    }
+
+    /// 合并两部分体积
+    pub fn merge(&self, other: &Self) -> Self {
+        let volume = self.volume + other.volume;
+        let centroid = if volume.abs() > f64::EPSILON {
+            (self.centroid * self.volume + other.centroid * other.volume) / volume
+        } else {
+            self.centroid
+        };
+        Self { volume, centroid }
+    }
+}
+
+/// 单个元素的质量属性
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementMass {
+    pub refno: RefnoEnum,
+    /// 构件类型
+    pub noun: String,
+    /// 体积（m³）
+    pub volume: f64,
+    /// 密度（kg/m³），找不到材料且没有默认密度时为 None
+    pub density: Option<f64>,
+    /// 重量（kg）
+    pub weight: f64,
+    /// 重心（mm）
+    pub cog: Vec3,
+}
+
+/// 层级的重量汇总
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct WeightRollup {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub name: String,
+    /// 总重量（kg）
+    pub weight: f64,
+    /// 重心（mm）
+    pub cog: [f64; 3],
+    /// 参与计算的元素数
+    pub element_count: usize,
+    /// 没有密度、未计入重量的元素数
+    pub missing_density: usize,
+}
+
+/// 材料密度查询，同一个材料只查一次
+#[derive(Debug, Default)]
+pub struct DensityResolver {
+    materials: HashMap<RefnoEnum, Option<f64>>,
+}
+
+impl DensityResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn material_density(&mut self, matr: RefnoEnum) -> Option<f64> {
+        if let Some(density) = self.materials.get(&matr) {
+            return *density;
+        }
+        let density = get_named_attmap(matr)
+            .await
+            .ok()
+            .and_then(|att| att.get_f64("DENS"))
+            .filter(|d| *d > 0.0);
+        self.materials.insert(matr, density);
+        density
+    }
+
+    /// 元素的密度（kg/m³），依次看元素和其规格组件上的材料
+    pub async fn density_of(&mut self, refno: RefnoEnum) -> anyhow::Result<Option<f64>> {
+        let att = get_named_attmap(refno).await?;
+        if let Some(matr) = att.get_refno_by_att("MATR")
+            && let Some(density) = self.material_density(matr).await
+        {
+            return Ok(Some(density));
+        }
+        if let Some(spre) = att.get_refno_by_att("SPRE") {
+            let spco = get_named_attmap(spre).await?;
+            if let Some(matr) = spco.get_refno_by_att("MATR") {
+                return Ok(self.material_density(matr).await);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 计算一批元素的体积、重量和重心，没有几何的元素不出现在结果中
+pub async fn compute_element_masses(
+    refnos: &[RefnoEnum],
+    options: &WeightOptions,
+) -> anyhow::Result<Vec<ElementMass>> {
+    let insts = query_insts(refnos, true).await?;
+    let store = mesh_store();
+    let mut mesh_cache: HashMap<String, Option<PlantMesh>> = HashMap::new();
+    let mut densities = DensityResolver::new();
+    let mut result = vec![];
+    for geom_inst in insts {
+        let mut props = MassProperties::default();
+        for inst in &geom_inst.insts {
+            let Some(mesh) =
+                load_mesh_cached(store.as_ref(), &mut mesh_cache, &inst.geo_hash, "L0").await
+            else {
+                continue;
+            };
+            let trans: Mat4 = (geom_inst.world_trans * &inst.transform).to_matrix();
+            props = props.merge(&MassProperties::of_mesh(mesh, trans.as_dmat4()));
+        }
+        if props.volume <= 0.0 {
+            continue;
+        }
+        let density = densities
+            .density_of(geom_inst.refno)
+            .await?
+            .or(options.default_density);
+        let volume = props.volume / MM3_PER_M3;
+        result.push(ElementMass {
+            refno: geom_inst.refno,
+            noun: geom_inst.generic,
+            volume,
+            density,
+            weight: density.map_or(0.0, |d| d * volume),
+            cog: props.centroid.as_vec3(),
+        });
+    }
+    Ok(result)
+}
+
+/// 把一组元素的质量按重量加权汇总
+pub fn rollup<'a>(
+    refno: RefnoEnum,
+    noun: &str,
+    name: &str,
+    masses: impl IntoIterator<Item = &'a ElementMass>,
+) -> WeightRollup {
+    let mut weight = 0.0;
+    let mut moment = DVec3::ZERO;
+    let mut element_count = 0;
+    let mut missing_density = 0;
+    for m in masses {
+        element_count += 1;
+        if m.density.is_none() {
+            missing_density += 1;
+            continue;
+        }
+        weight += m.weight;
+        moment += m.cog.as_dvec3() * m.weight;
+    }
+    let cog = if weight > 0.0 {
+        moment / weight
+    } else {
+        DVec3::ZERO
+    };
+    WeightRollup {
+        refno,
+        noun: noun.to_string(),
+        name: name.to_string(),
+        weight,
+        cog: cog.to_array(),
+        element_count,
+        missing_density,
+    }
+}
+
+/// 汇总 `root` 及其下各层级（见 [`WeightOptions::rollup_nouns`]）的重量和重心
+///
+/// 返回结果中 `root` 排在第一个，不论它是否属于汇总层级。
+pub async fn weight_rollup(
+    root: RefnoEnum,
+    options: &WeightOptions,
+) -> anyhow::Result<Vec<WeightRollup>> {
+    let mut elements = query_deep_children_refnos(root).await?;
+    elements.push(root);
+    let masses: HashMap<RefnoEnum, ElementMass> = compute_element_masses(&elements, options)
+        .await?
+        .into_iter()
+        .map(|m| (m.refno, m))
+        .collect();
+
+    let nouns: Vec<&str> = options.rollup_nouns.iter().map(|s| s.as_str()).collect();
+    let mut nodes = vec![root];
+    nodes.extend(
+        query_filter_deep_children(root, &nouns)
+            .await?
+            .into_iter()
+            .filter(|r| *r != root),
+    );
+
+    let mut result = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let att = get_named_attmap(node).await?;
+        let mut members = if node == root {
+            elements.clone()
+        } else {
+            query_deep_children_refnos(node).await?
+        };
+        if node != root {
+            members.push(node);
+        }
+        result.push(rollup(
+            node,
+            att.get_type_str(),
+            &att.get_name_or_default(),
+            members.iter().filter_map(|r| masses.get(r)),
+        ));
+    }
+    Ok(result)
+}
+
+/// 保存汇总结果
+pub async fn save_weight_rollups(rollups: &[WeightRollup]) -> anyhow::Result<()> {
+    for chunk in rollups.chunks(SAVE_CHUNK) {
+        let mut tx = Transaction::new();
+        for r in chunk {
+            let pe_key = r.refno.to_pe_key();
+            tx.push(format!(
+                "UPSERT {WEIGHT_TABLE}:[{pe_key}] CONTENT {{ refno: {pe_key}, noun: {}, name: {}, weight: {}, cog: {:?}, element_count: {}, missing_density: {} }}",
+                serde_json::to_string(&r.noun)?,
+                serde_json::to_string(&r.name)?,
+                r.weight,
+                r.cog,
+                r.element_count,
+                r.missing_density,
+            ));
+        }
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// 计算并保存 `root` 下各层级的重量汇总
+pub async fn update_weight_rollup(root: RefnoEnum) -> anyhow::Result<Vec<WeightRollup>> {
+    let rollups = weight_rollup(root, &WeightOptions::default()).await?;
+    save_weight_rollups(&rollups).await?;
+    Ok(rollups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::csg::unit_box_mesh;
+
+    #[test]
+    fn test_mass_properties() {
+        let mesh = unit_box_mesh();
+        let trans = DMat4::from_translation(DVec3::new(10.0, 0.0, 5.0))
+            * DMat4::from_scale(DVec3::new(2.0, 3.0, 4.0));
+        let props = MassProperties::of_mesh(&mesh, trans);
+        assert!((props.volume - 24.0).abs() < 1e-9);
+        assert!(props.centroid.distance(DVec3::new(10.0, 0.0, 5.0)) < 1e-9);
+
+        let other = MassProperties {
+            volume: 8.0,
+            centroid: DVec3::new(-10.0, 0.0, 5.0),
+        };
+        let merged = props.merge(&other);
+        assert_eq!(merged.volume, 32.0);
+        assert!(merged.centroid.distance(DVec3::new(5.0, 0.0, 5.0)) < 1e-9);
+    }
+
+    #[test]
+    fn test_rollup() {
+        let mass = |n: u32, weight: f64, x: f32, density: Option<f64>| ElementMass {
+            refno: RefU64::from_two_nums(1, n).into(),
+            noun: "EQUI".into(),
+            volume: 0.0,
+            density,
+            weight,
+            cog: Vec3::new(x, 0.0, 0.0),
+        };
+        let masses = [
+            mass(1, 300.0, 0.0, Some(7850.0)),
+            mass(2, 100.0, 4000.0, Some(7850.0)),
+            mass(3, 0.0, 1e6, None),
+        ];
+        let r = rollup(RefU64::from_two_nums(1, 0).into(), "ZONE", "Z1", &masses);
+        assert_eq!(r.weight, 400.0);
+        assert_eq!(r.cog, [1000.0, 0.0, 0.0]);
+        assert_eq!(r.element_count, 3);
+        assert_eq!(r.missing_density, 1);
+    }
+}
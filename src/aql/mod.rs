@@ -0,0 +1,209 @@
+//! AQL 查询执行
+//!
+//! 把 [`AqlQuery`] 编译为 SurrealQL 并执行，返回匹配的 refno：
+//!
+//! - 类型过滤：`noun in [...]`
+//! - 属性条件：比较 pe 上的 `refno.<ATTR>`
+//! - 层级范围：先定位范围节点，再用 `fn::collect_descendant_ids_by_types` 取子孙
+//! - 名称通配符：转为正则后匹配 `name`
+//!
+//! 文本形式的选择语句（`ALL BRAN WITH TEMP > 100 FOR SITE /A01`）由 [`parse`] 解析。
+//!
+//! ```rust,ignore
+//! let query = AqlQuery::new()
+//!     .of_types(["VALV"])
+//!     .with("BORE", AqlOp::Ge, 100)
+//!     .within("ZONE", "/Z1")
+//!     .named("/V-*");
+//! let refnos = aql::execute(query).await?;
+//! ```
+//!
+//! 只编译为 SurrealQL：`KuzuQueryProvider` 中只有层级数据，无法求值属性条件。
+
+pub mod parse;
+pub mod types;
+
+pub use parse::{AqlParseError, parse};
+pub use types::*;
+
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+
+/// 执行查询
+pub async fn execute(query: AqlQuery) -> anyhow::Result<Vec<RefnoEnum>> {
+    let (sql, index) = compile(&query)?;
+    let refnos: Vec<RefnoEnum> = SUL_DB.query_take(&sql, index).await?;
+    Ok(refnos)
+}
+
+/// 编译为 SurrealQL，返回 (语句, 结果所在的语句序号)
+pub fn compile(query: &AqlQuery) -> anyhow::Result<(String, usize)> {
+    let mut conds = vec![];
+    for pred in &query.predicates {
+        conds.push(predicate_sql(pred)?);
+    }
+    if let Some(pattern) = &query.name {
+        conds.push(format!(
+            "string::matches(name ?? '', {})",
+            quote(&wildcard_regex(pattern))
+        ));
+    }
+    let limit = query
+        .limit
+        .map(|n| format!(" LIMIT {n}"))
+        .unwrap_or_default();
+
+    let Some(scope) = &query.scope else {
+        if !query.types.is_empty() {
+            conds.insert(0, format!("noun in [{}]", types_sql(&query.types)?));
+        }
+        let where_sql = where_clause(&conds);
+        return Ok((format!("SELECT VALUE id FROM pe{where_sql}{limit};"), 0));
+    };
+
+    // 子孙已按类型过滤，这里只需要其余条件
+    let roots = match scope {
+        AqlScope::Named { noun, name } => format!(
+            "SELECT VALUE id FROM pe WHERE noun = {} AND name = {}",
+            quote(&check_ident(noun)?.to_uppercase()),
+            quote(&with_slash(name))
+        ),
+        AqlScope::Name(name) => format!(
+            "SELECT VALUE id FROM pe WHERE name = {}",
+            quote(&with_slash(name))
+        ),
+        AqlScope::Refno(refno) => format!("[{}]", refno.to_pe_key()),
+    };
+    let sql = format!(
+        r#"
+        LET $roots = {roots};
+        LET $ids = array::distinct(array::filter(array::flatten(array::map($roots, |$refno|
+            fn::collect_descendant_ids_by_types($refno, [{}], none, "..")
+        )), |$v| $v != none));
+        SELECT VALUE id FROM $ids{}{limit};
+        "#,
+        types_sql(&query.types)?,
+        where_clause(&conds)
+    );
+    Ok((sql, 2))
+}
+
+fn where_clause(conds: &[String]) -> String {
+    if conds.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conds.join(" AND "))
+    }
+}
+
+fn types_sql(types: &[String]) -> anyhow::Result<String> {
+    Ok(types
+        .iter()
+        .map(|t| check_ident(t).map(|t| quote(&t.to_uppercase())))
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .join(","))
+}
+
+fn predicate_sql(pred: &AqlPredicate) -> anyhow::Result<String> {
+    let field = format!("refno.{}", check_ident(&pred.attr)?.to_uppercase());
+    let value = value_sql(&pred.value);
+    Ok(match pred.op {
+        AqlOp::Eq => format!("{field} = {value}"),
+        AqlOp::Ne => format!("{field} != {value}"),
+        AqlOp::Lt => format!("{field} < {value}"),
+        AqlOp::Le => format!("{field} <= {value}"),
+        AqlOp::Gt => format!("{field} > {value}"),
+        AqlOp::Ge => format!("{field} >= {value}"),
+        AqlOp::Contains => format!(
+            "string::contains(string::lowercase(<string> ({field} ?? '')), string::lowercase({value}))"
+        ),
+        AqlOp::Exists => format!("{field} != NONE"),
+    })
+}
+
+fn value_sql(value: &AqlValue) -> String {
+    match value {
+        AqlValue::Number(v) => v.to_string(),
+        AqlValue::Text(s) => quote(s),
+        AqlValue::Bool(b) => b.to_string(),
+        AqlValue::Refno(r) => r.to_pe_key(),
+    }
+}
+
+/// 属性名和类型名直接拼入语句，只允许字母、数字和下划线
+fn check_ident(ident: &str) -> anyhow::Result<&str> {
+    if ident.is_empty() || !ident.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        anyhow::bail!("AQL 中无效的名称: {ident:?}");
+    }
+    Ok(ident)
+}
+
+/// 字符串字面量
+fn quote(s: &str) -> String {
+    serde_json::Value::String(s.to_string()).to_string()
+}
+
+/// pe 的 name 带前导 `/`
+fn with_slash(name: &str) -> String {
+    if name.starts_with('/') {
+        name.to_string()
+    } else {
+        format!("/{name}")
+    }
+}
+
+/// 名称通配符转为整串匹配的正则
+fn wildcard_regex(pattern: &str) -> String {
+    let mut re = String::from("^");
+    for c in with_slash(pattern).chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c if "\\.+()[]{}|^$".contains(c) => {
+                re.push('\\');
+                re.push(c);
+            }
+            c => re.push(c),
+        }
+    }
+    re.push('$');
+    re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let query = AqlQuery::new()
+            .of_types(["pipe"])
+            .with("BORE", AqlOp::Gt, 100)
+            .named("P1-*");
+        let (sql, index) = compile(&query).unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(
+            sql,
+            r#"SELECT VALUE id FROM pe WHERE noun in ["PIPE"] AND refno.BORE > 100 AND string::matches(name ?? '', "^/P1-.*$");"#
+        );
+
+        let query = AqlQuery::new()
+            .of_types(["VALV"])
+            .within("ZONE", "/Z.1")
+            .limit(10);
+        let (sql, index) = compile(&query).unwrap();
+        assert_eq!(index, 2);
+        assert!(sql.contains(r#"WHERE noun = "ZONE" AND name = "/Z.1""#));
+        assert!(
+            sql.contains(r#"fn::collect_descendant_ids_by_types($refno, ["VALV"], none, "..")"#)
+        );
+        assert!(sql.contains("SELECT VALUE id FROM $ids LIMIT 10;"));
+
+        let bad = AqlQuery::new().with("BORE; DELETE pe", AqlOp::Eq, 1);
+        assert!(compile(&bad).is_err());
+    }
+
+    #[test]
+    fn test_wildcard_regex() {
+        assert_eq!(wildcard_regex("/A?.B*"), r"^/A.\.B.*$");
+    }
+}
@@ -0,0 +1,481 @@
+//! PML 风格的选择语句解析
+//!
+//! 把工程师熟悉的选择写法解析为 [`AqlQuery`]：
+//!
+//! ```text
+//! ALL BRAN WITH TEMP > 100 FOR SITE /A01
+//! ALL VALV GATE WITH BORE >= 80 AND SPRE SET WITHIN ZONE /Z1 NAMED /V-* LIMIT 50
+//! ALL EQUI WITH NAME EQ '/P-10?' FOR =17496/1024
+//! ```
+//!
+//! 语法（关键字不区分大小写）：
+//!
+//! ```text
+//! query     := [ALL] noun* [(WITH|WHERE) cond (AND cond)*] [(FOR|WITHIN) [noun] target]
+//!              [NAMED pattern] [LIMIT n]
+//! cond      := attr op value | attr CONTAINS value | attr SET | '(' cond ')'
+//! op        := = | == | != | <> | < | <= | > | >= | EQ | NE | LT | LE | GT | GE
+//! value     := 数字 | '字符串' | "字符串" | TRUE | FALSE | /名称 | =参考号
+//! target    := /名称 | =参考号
+//! ```
+//!
+//! `NAME EQ <pattern>` 等价于 `NAMED <pattern>`。
+
+use super::types::*;
+use crate::RefnoEnum;
+use std::fmt;
+use thiserror::Error;
+
+/// 解析错误，`offset` 为出错位置在输入中的字节偏移
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("第 {} 列: {message}", .offset + 1)]
+pub struct AqlParseError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl AqlParseError {
+    fn new(message: impl Into<String>, offset: usize) -> Self {
+        Self {
+            message: message.into(),
+            offset,
+        }
+    }
+
+    /// 带原文和位置标记的多行提示，用于 CLI 输出
+    pub fn display_with(&self, input: &str) -> String {
+        let col = input[..self.offset.min(input.len())].chars().count();
+        format!("{input}\n{}^ {}", " ".repeat(col), self.message)
+    }
+}
+
+/// 解析选择语句
+pub fn parse(input: &str) -> Result<AqlQuery, AqlParseError> {
+    let tokens = tokenize(input)?;
+    Parser {
+        tokens,
+        pos: 0,
+        end: input.len(),
+    }
+    .query()
+}
+
+impl std::str::FromStr for AqlQuery {
+    type Err = AqlParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    /// `/` 开头的名称
+    Name(String),
+    /// `=` 开头的参考号
+    Refno(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tok::Ident(s) => write!(f, "{s}"),
+            Tok::Number(n) => write!(f, "{n}"),
+            Tok::Str(s) => write!(f, "'{s}'"),
+            Tok::Name(s) => write!(f, "{s}"),
+            Tok::Refno(s) => write!(f, "={s}"),
+            Tok::Op(op) => write!(f, "{op}"),
+            Tok::LParen => write!(f, "("),
+            Tok::RParen => write!(f, ")"),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "ALL", "WITH", "WHERE", "AND", "OR", "FOR", "WITHIN", "NAMED", "LIMIT",
+];
+
+fn tokenize(input: &str) -> Result<Vec<(Tok, usize)>, AqlParseError> {
+    let bytes = input.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    // 名称、参考号一直到空白或括号为止
+    let word_end = |start: usize| {
+        input[start..]
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .map_or(input.len(), |n| start + n)
+    };
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let next = bytes.get(i + 1).copied();
+        let tok = match c {
+            c if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'(' => {
+                i += 1;
+                Tok::LParen
+            }
+            b')' => {
+                i += 1;
+                Tok::RParen
+            }
+            b'\'' | b'"' => {
+                let Some(len) = input[i + 1..].find(c as char) else {
+                    return Err(AqlParseError::new("字符串缺少结束引号", start));
+                };
+                i += len + 2;
+                Tok::Str(input[start + 1..i - 1].to_string())
+            }
+            b'/' => {
+                i = word_end(start);
+                if i == start + 1 {
+                    return Err(AqlParseError::new("`/` 后缺少名称", start));
+                }
+                Tok::Name(input[start..i].to_string())
+            }
+            b'=' if next.is_some_and(|n| n.is_ascii_digit()) && {
+                let end = word_end(start);
+                input[start..end].contains('/')
+            } =>
+            {
+                i = word_end(start);
+                Tok::Refno(input[start + 1..i].to_string())
+            }
+            b'=' | b'!' | b'<' | b'>' => {
+                let two = input.get(i..i + 2).unwrap_or("");
+                let op = match two {
+                    "==" => "=",
+                    "!=" | "<>" => "!=",
+                    "<=" => "<=",
+                    ">=" => ">=",
+                    _ => "",
+                };
+                if !op.is_empty() {
+                    i += 2;
+                    Tok::Op(op)
+                } else {
+                    i += 1;
+                    match c {
+                        b'=' => Tok::Op("="),
+                        b'<' => Tok::Op("<"),
+                        b'>' => Tok::Op(">"),
+                        _ => return Err(AqlParseError::new("无法识别的运算符 `!`", start)),
+                    }
+                }
+            }
+            c if c.is_ascii_digit()
+                || ((c == b'-' || c == b'+' || c == b'.')
+                    && next.is_some_and(|n| n.is_ascii_digit() || n == b'.')) =>
+            {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                let text = &input[start..i];
+                let n = text
+                    .parse::<f64>()
+                    .map_err(|_| AqlParseError::new(format!("无效的数字 `{text}`"), start))?;
+                Tok::Number(n)
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                Tok::Ident(input[start..i].to_uppercase())
+            }
+            _ => {
+                let ch = input[start..].chars().next().unwrap_or_default();
+                return Err(AqlParseError::new(format!("无法识别的字符 `{ch}`"), start));
+            }
+        };
+        tokens.push((tok, start));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Tok, usize)>,
+    pos: usize,
+    /// 输入长度，用于“意外结束”的位置
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, o)| *o)
+    }
+
+    fn bump(&mut self) -> Option<Tok> {
+        let tok = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        tok
+    }
+
+    fn is_keyword(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(s)) if s == kw)
+    }
+
+    fn eat_keyword(&mut self, kw: &str) -> bool {
+        let hit = self.is_keyword(kw);
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    /// 在当前位置报错，`expected` 描述期望的内容
+    fn expected<T>(&self, expected: &str) -> Result<T, AqlParseError> {
+        let found = match self.peek() {
+            Some(tok) => format!("`{tok}`"),
+            None => "语句结束".to_string(),
+        };
+        Err(AqlParseError::new(
+            format!("期望{expected}，实际为 {found}"),
+            self.offset(),
+        ))
+    }
+
+    /// 非关键字的标识符
+    fn ident(&mut self, expected: &str) -> Result<String, AqlParseError> {
+        match self.peek() {
+            Some(Tok::Ident(s)) if !KEYWORDS.contains(&s.as_str()) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => self.expected(expected),
+        }
+    }
+
+    fn query(mut self) -> Result<AqlQuery, AqlParseError> {
+        let mut query = AqlQuery::new();
+        self.eat_keyword("ALL");
+        while let Some(Tok::Ident(s)) = self.peek() {
+            if KEYWORDS.contains(&s.as_str()) {
+                break;
+            }
+            let noun = s.clone();
+            self.pos += 1;
+            query.types.push(noun);
+        }
+
+        if self.eat_keyword("WITH") || self.eat_keyword("WHERE") {
+            loop {
+                self.condition(&mut query)?;
+                if self.is_keyword("OR") {
+                    return Err(AqlParseError::new(
+                        "暂不支持 OR，请拆分为多条选择",
+                        self.offset(),
+                    ));
+                }
+                if !self.eat_keyword("AND") {
+                    break;
+                }
+            }
+        }
+
+        if self.eat_keyword("FOR") || self.eat_keyword("WITHIN") {
+            let noun = match self.peek() {
+                Some(Tok::Ident(_)) => Some(self.ident("范围类型")?),
+                _ => None,
+            };
+            query.scope = Some(match (self.bump(), noun) {
+                (Some(Tok::Name(name)), Some(noun)) => AqlScope::Named { noun, name },
+                (Some(Tok::Name(name)), None) => AqlScope::Name(name),
+                (Some(Tok::Refno(r)), _) => AqlScope::Refno(self.refno(&r)?),
+                _ => {
+                    self.pos -= 1;
+                    return self.expected("范围名称（如 /A01）或参考号（如 =17496/1024）");
+                }
+            });
+        }
+
+        if self.eat_keyword("NAMED") {
+            query.name = Some(self.pattern()?);
+        }
+
+        if self.eat_keyword("LIMIT") {
+            match self.peek() {
+                Some(Tok::Number(n)) if n.fract() == 0.0 && *n > 0.0 => {
+                    query.limit = Some(*n as usize);
+                    self.pos += 1;
+                }
+                _ => return self.expected("正整数"),
+            }
+        }
+
+        if self.peek().is_some() {
+            let hint = if query.types.is_empty() && self.pos == 0 {
+                "类型名"
+            } else {
+                " WITH、FOR、NAMED、LIMIT 或语句结束"
+            };
+            return self.expected(hint);
+        }
+        Ok(query)
+    }
+
+    fn condition(&mut self, query: &mut AqlQuery) -> Result<(), AqlParseError> {
+        if self.peek() == Some(&Tok::LParen) {
+            self.pos += 1;
+            self.condition(query)?;
+            if self.bump() != Some(Tok::RParen) {
+                self.pos -= 1;
+                return self.expected(" `)`");
+            }
+            return Ok(());
+        }
+
+        let attr = self.ident("属性名")?;
+        if self.eat_keyword("SET") {
+            query.predicates.push(AqlPredicate {
+                attr,
+                op: AqlOp::Exists,
+                value: AqlValue::Bool(true),
+            });
+            return Ok(());
+        }
+        let op = match self.peek() {
+            Some(Tok::Op(op)) => match *op {
+                "=" => AqlOp::Eq,
+                "!=" => AqlOp::Ne,
+                "<" => AqlOp::Lt,
+                "<=" => AqlOp::Le,
+                ">" => AqlOp::Gt,
+                _ => AqlOp::Ge,
+            },
+            Some(Tok::Ident(s)) => match s.as_str() {
+                "EQ" => AqlOp::Eq,
+                "NE" | "NEQ" => AqlOp::Ne,
+                "LT" => AqlOp::Lt,
+                "LE" | "LEQ" => AqlOp::Le,
+                "GT" => AqlOp::Gt,
+                "GE" | "GEQ" => AqlOp::Ge,
+                "CONTAINS" => AqlOp::Contains,
+                _ => return self.expected("比较运算符（如 >、EQ、CONTAINS）或 SET"),
+            },
+            _ => return self.expected("比较运算符（如 >、EQ、CONTAINS）或 SET"),
+        };
+        self.pos += 1;
+
+        if attr == "NAME" {
+            if op != AqlOp::Eq {
+                self.pos -= 1;
+                return self.expected(" NAME 后的 EQ");
+            }
+            query.name = Some(self.pattern()?);
+            return Ok(());
+        }
+
+        let value = match self.bump() {
+            Some(Tok::Number(n)) => AqlValue::Number(n),
+            Some(Tok::Str(s)) | Some(Tok::Name(s)) => AqlValue::Text(s),
+            Some(Tok::Refno(r)) => AqlValue::Refno(self.refno(&r)?),
+            Some(Tok::Ident(s)) if s == "TRUE" => AqlValue::Bool(true),
+            Some(Tok::Ident(s)) if s == "FALSE" => AqlValue::Bool(false),
+            _ => {
+                self.pos -= 1;
+                return self.expected(&format!(" {attr} 的比较值"));
+            }
+        };
+        if op == AqlOp::Contains && !matches!(value, AqlValue::Text(_)) {
+            self.pos -= 1;
+            return self.expected(" CONTAINS 后的字符串");
+        }
+        query.predicates.push(AqlPredicate { attr, op, value });
+        Ok(())
+    }
+
+    /// 名称通配符，可以是 `/名称` 或引号字符串
+    fn pattern(&mut self) -> Result<String, AqlParseError> {
+        match self.bump() {
+            Some(Tok::Name(s)) | Some(Tok::Str(s)) => Ok(s),
+            _ => {
+                self.pos -= 1;
+                self.expected("名称（如 /P1-*）")
+            }
+        }
+    }
+
+    /// 刚消费的参考号
+    fn refno(&self, text: &str) -> Result<RefnoEnum, AqlParseError> {
+        let offset = self.tokens[self.pos - 1].1;
+        let nums = text.split('/').collect::<Vec<_>>();
+        if nums.len() != 2 || nums.iter().any(|n| n.parse::<u32>().is_err()) {
+            return Err(AqlParseError::new(
+                format!("无效的参考号 `={text}`，应为 =数字/数字"),
+                offset,
+            ));
+        }
+        Ok(RefnoEnum::from(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    #[test]
+    fn test_parse() {
+        let query = parse("ALL BRAN WITH TEMP > 100 FOR SITE /A01").unwrap();
+        assert_eq!(
+            query,
+            AqlQuery::new()
+                .of_types(["BRAN"])
+                .with("TEMP", AqlOp::Gt, 100)
+                .within("SITE", "/A01")
+        );
+
+        let query: AqlQuery =
+            "all valv gate where (bore ge 80) and spre set and desc contains 'gate' \
+             within /Z1 named /V-* limit 5"
+                .parse()
+                .unwrap();
+        assert_eq!(query.types, vec!["VALV", "GATE"]);
+        assert_eq!(query.predicates.len(), 3);
+        assert_eq!(query.predicates[1].op, AqlOp::Exists);
+        assert_eq!(query.scope, Some(AqlScope::Name("/Z1".into())));
+        assert_eq!(query.name.as_deref(), Some("/V-*"));
+        assert_eq!(query.limit, Some(5));
+
+        let query = parse("ALL EQUI WITH NAME EQ '/P-10?' AND OWNER = =17496/1024").unwrap();
+        assert_eq!(query.name.as_deref(), Some("/P-10?"));
+        assert_eq!(
+            query.predicates[0].value,
+            AqlValue::Refno(RefU64::from_two_nums(17496, 1024).into())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse("ALL BRAN WITH TEMP 100").unwrap_err();
+        assert_eq!(err.offset, 19);
+        assert!(err.message.contains("比较运算符"));
+        assert_eq!(
+            err.display_with("ALL BRAN WITH TEMP 100").lines().nth(1),
+            Some(format!("{}^ {}", " ".repeat(19), err.message).as_str())
+        );
+
+        let err = parse("ALL BRAN FOR SITE").unwrap_err();
+        assert!(err.message.contains("语句结束"));
+        assert_eq!(err.offset, 17);
+
+        assert!(parse("ALL BRAN WITH TEMP > 1 OR TEMP < 0").is_err());
+        assert!(parse("ALL BRAN WITH DESC = 'x").is_err());
+        assert!(parse("ALL BRAN LIMIT 0").is_err());
+    }
+}
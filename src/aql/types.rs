@@ -0,0 +1,158 @@
+//! AQL 查询语法树
+
+use crate::RefnoEnum;
+use serde::{Deserialize, Serialize};
+
+/// 一条 AQL 查询，各条件之间为 AND 关系
+///
+/// 对应文本形式 `ALL PIPE VALV WITH BORE > 100 WITHIN ZONE /Z1 NAMED /P1-*`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AqlQuery {
+    /// 类型过滤（noun），为空时不限类型
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// 属性条件
+    #[serde(default)]
+    pub predicates: Vec<AqlPredicate>,
+    /// 层级范围，只在该节点的子孙中查找
+    #[serde(default)]
+    pub scope: Option<AqlScope>,
+    /// 名称通配符，`*` 匹配任意字符串，`?` 匹配单个字符
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 最多返回的条数
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AqlQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按类型过滤
+    pub fn of_types<I, S>(mut self, types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.types.extend(types.into_iter().map(Into::into));
+        self
+    }
+
+    /// 增加属性条件
+    pub fn with(mut self, attr: impl Into<String>, op: AqlOp, value: impl Into<AqlValue>) -> Self {
+        self.predicates.push(AqlPredicate {
+            attr: attr.into(),
+            op,
+            value: value.into(),
+        });
+        self
+    }
+
+    /// `WITHIN <noun> <name>`
+    pub fn within(mut self, noun: impl Into<String>, name: impl Into<String>) -> Self {
+        self.scope = Some(AqlScope::Named {
+            noun: noun.into(),
+            name: name.into(),
+        });
+        self
+    }
+
+    /// 在指定节点的子孙中查找
+    pub fn within_refno(mut self, refno: RefnoEnum) -> Self {
+        self.scope = Some(AqlScope::Refno(refno));
+        self
+    }
+
+    pub fn named(mut self, pattern: impl Into<String>) -> Self {
+        self.name = Some(pattern.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// 属性条件 `<attr> <op> <value>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AqlPredicate {
+    /// 属性名，如 `BORE`、`SPRE`
+    pub attr: String,
+    pub op: AqlOp,
+    /// `Exists` 时忽略
+    pub value: AqlValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AqlOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// 字符串包含（忽略大小写）
+    Contains,
+    /// 属性存在且不为空
+    Exists,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AqlValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Refno(RefnoEnum),
+}
+
+impl From<f64> for AqlValue {
+    fn from(v: f64) -> Self {
+        AqlValue::Number(v)
+    }
+}
+
+impl From<i32> for AqlValue {
+    fn from(v: i32) -> Self {
+        AqlValue::Number(v as f64)
+    }
+}
+
+impl From<bool> for AqlValue {
+    fn from(v: bool) -> Self {
+        AqlValue::Bool(v)
+    }
+}
+
+impl From<&str> for AqlValue {
+    fn from(v: &str) -> Self {
+        AqlValue::Text(v.to_string())
+    }
+}
+
+impl From<String> for AqlValue {
+    fn from(v: String) -> Self {
+        AqlValue::Text(v)
+    }
+}
+
+impl From<RefnoEnum> for AqlValue {
+    fn from(v: RefnoEnum) -> Self {
+        AqlValue::Refno(v)
+    }
+}
+
+/// 层级范围
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AqlScope {
+    /// 按类型和名称定位，如 `WITHIN ZONE /Z1`，同名节点有多个时都作为范围
+    Named {
+        noun: String,
+        name: String,
+    },
+    /// 只按名称定位，如 `FOR /A01`
+    Name(String),
+    Refno(RefnoEnum),
+}
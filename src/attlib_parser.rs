@@ -1,7 +1,11 @@
+use cached::{Cached, SizedCache};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub mod diff;
 
 const PAGE_SIZE: usize = 2048;
 const WORDS_PER_PAGE: usize = 512;
@@ -11,6 +15,10 @@ const PAGE_SWITCH_MARK: u32 = 0x00000000;
 const SEGMENT_END_MARK: u32 = 0xFFFFFFFF;
 const DATA_REGION_START: u64 = 0x1000;
 const SEGMENT_POINTERS_OFFSET: u64 = 0x0800;
+/// 默认页缓存容量（页数，约 8MB）
+const DEFAULT_PAGE_CACHE_PAGES: usize = 4096;
+/// 默认惰性定义缓存容量（条数）
+const DEFAULT_DEFINITION_CACHE: usize = 1024;
 
 struct WordCursor {
     page_num: u32,
@@ -28,6 +36,19 @@ impl WordCursor {
         })
     }
 
+    /// 定位到指定页内的字偏移
+    fn at(parser: &mut AttlibParser, page_num: u32, word_idx: usize) -> std::io::Result<Self> {
+        let mut cursor = Self::new(parser, page_num)?;
+        cursor.word_idx = word_idx;
+        Ok(cursor)
+    }
+
+    /// 下一个字的位置（页号, 页内偏移）
+    #[inline]
+    fn position(&self) -> (u32, usize) {
+        (self.page_num, self.word_idx)
+    }
+
     fn next_word(&mut self, parser: &mut AttlibParser) -> std::io::Result<u32> {
         if self.word_idx >= WORDS_PER_PAGE {
             self.advance_page(parser)?;
@@ -45,7 +66,7 @@ impl WordCursor {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AttlibAttrIndex {
     pub attr_hash: u32,
     pub combined: u32,
@@ -69,7 +90,7 @@ pub struct AttlibAttrDefinition {
     pub default_value: AttlibDefaultValue,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AttlibDefaultValue {
     None,
     Scalar(u32),
@@ -98,47 +119,40 @@ pub struct AttlibSyntaxEntry {
 /// Noun -> 属性哈希列表映射
 pub type NounAttrMapping = HashMap<u32, Vec<u32>>;
 
-/// Base27 常量
-const BASE27_OFFSET: u32 = 0x81BF1;
+pub use crate::noun::{decode as decode_base27, hash as encode_base27};
+
+/// [`AttlibParser`] 构建器，用于配置缓存容量
+///
+/// ```ignore
+/// let mut parser = AttlibParser::builder("attlib.dat")
+///     .page_cache_pages(256)
+///     .definition_cache(512)
+///     .build()?;
+/// let def = parser.get_attribute_lazy(encode_base27("BORE"))?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct AttlibParserBuilder {
+    file_path: String,
+    page_cache_pages: usize,
+    definition_cache: usize,
+}
 
-/// 将哈希值解码为属性/类型名称
-pub fn decode_base27(hash: u32) -> String {
-    if hash < MIN_HASH || hash > MAX_HASH {
-        return String::new();
+impl AttlibParserBuilder {
+    /// 页缓存容量（页数，每页 2KB），超出后按 LRU 淘汰
+    pub fn page_cache_pages(mut self, pages: usize) -> Self {
+        self.page_cache_pages = pages.max(1);
+        self
     }
 
-    let mut k = (hash - BASE27_OFFSET) as i64;
-    let mut chars = Vec::new();
-
-    while k > 0 {
-        let c = (k % 27) as u8;
-        if c == 0 {
-            chars.push(b' ');
-        } else {
-            chars.push(c + 64); // A=65, B=66, ...
-        }
-        k /= 27;
+    /// 惰性读取的属性定义缓存容量（条数），超出后按 LRU 淘汰
+    pub fn definition_cache(mut self, entries: usize) -> Self {
+        self.definition_cache = entries.max(1);
+        self
     }
 
-    String::from_utf8(chars).unwrap_or_default()
-}
-
-/// 将名称编码为哈希值
-pub fn encode_base27(name: &str) -> u32 {
-    let mut hash = BASE27_OFFSET;
-    let mut mul = 1u32;
-
-    for ch in name.chars().take(6) {
-        let v = if ch == ' ' {
-            0
-        } else {
-            (ch.to_ascii_uppercase() as u32).saturating_sub(64)
-        };
-        hash = hash.wrapping_add(mul.wrapping_mul(v));
-        mul = mul.wrapping_mul(27);
+    pub fn build(self) -> std::io::Result<AttlibParser> {
+        AttlibParser::open(&self.file_path, self.page_cache_pages, self.definition_cache)
     }
-
-    hash
 }
 
 pub struct AttlibParser {
@@ -146,11 +160,35 @@ pub struct AttlibParser {
     attr_index: HashMap<u32, AttlibAttrIndex>,
     attr_definitions: HashMap<u32, AttlibAttrDefinition>,
     segment_pointers: [u32; 8],
-    page_cache: HashMap<u32, Vec<u32>>,
+    page_cache: SizedCache<u32, Vec<u32>>,
+    /// 惰性读取的属性定义
+    lazy_definitions: SizedCache<u32, AttlibAttrDefinition>,
+    /// 已扫描到的 ATGTDF 记录位置：hash -> (页号, 页内偏移)
+    record_offsets: HashMap<u32, (u32, usize)>,
+    /// ATGTDF 增量扫描的续扫位置，`None` 表示已扫描到段尾
+    scan_resume: Option<(u32, usize)>,
+    /// [`AttlibWriter`] 写出的文件在文件头标记中记录的 ATGTIX 起始页，原始文件为 `None`
+    writer_atgtix_page: Option<u32>,
 }
 
 impl AttlibParser {
     pub fn new(file_path: &str) -> std::io::Result<Self> {
+        Self::open(file_path, DEFAULT_PAGE_CACHE_PAGES, DEFAULT_DEFINITION_CACHE)
+    }
+
+    pub fn builder(file_path: impl Into<String>) -> AttlibParserBuilder {
+        AttlibParserBuilder {
+            file_path: file_path.into(),
+            page_cache_pages: DEFAULT_PAGE_CACHE_PAGES,
+            definition_cache: DEFAULT_DEFINITION_CACHE,
+        }
+    }
+
+    fn open(
+        file_path: &str,
+        page_cache_pages: usize,
+        definition_cache: usize,
+    ) -> std::io::Result<Self> {
         let mut file = File::open(file_path)?;
         let mut segment_pointers = [0u32; 8];
 
@@ -169,12 +207,22 @@ impl AttlibParser {
             eprintln!("  段 {}: 0x{:08X} (页号: {})", i, ptr, ptr);
         }
 
+        file.seek(SeekFrom::Start(WRITER_MARK_OFFSET))?;
+        let mut mark_buf = [0u8; 8];
+        file.read_exact(&mut mark_buf)?;
+        let writer_atgtix_page = (mark_buf[..4] == WRITER_MARK)
+            .then(|| u32::from_be_bytes([mark_buf[4], mark_buf[5], mark_buf[6], mark_buf[7]]));
+
         Ok(AttlibParser {
             file,
             attr_index: HashMap::new(),
             attr_definitions: HashMap::new(),
             segment_pointers,
-            page_cache: HashMap::new(),
+            page_cache: SizedCache::with_size(page_cache_pages),
+            lazy_definitions: SizedCache::with_size(definition_cache),
+            record_offsets: HashMap::new(),
+            scan_resume: Some((0, 0)),
+            writer_atgtix_page,
         })
     }
 
@@ -188,7 +236,7 @@ impl AttlibParser {
 
     /// 读取指定页号的页面（页号是相对于 DATA_REGION_START 的）
     fn read_page(&mut self, page_num: u32) -> std::io::Result<Vec<u32>> {
-        if let Some(cached) = self.page_cache.get(&page_num) {
+        if let Some(cached) = self.page_cache.cache_get(&page_num) {
             return Ok(cached.clone());
         }
 
@@ -205,14 +253,14 @@ impl AttlibParser {
             words.push(word);
         }
 
-        self.page_cache.insert(page_num, words.clone());
+        self.page_cache.cache_set(page_num, words.clone());
         Ok(words)
     }
 
     fn load_atgtix(&mut self) -> std::io::Result<()> {
-        // 直接从页 0 开始（0x1000），忽略段指针
-        let start_page = 0;
-        eprintln!("  起始页号: {} (直接从 0x1000 开始)", start_page);
+        // 原始文件直接从页 0 开始（0x1000），忽略段指针；AttlibWriter 写出的文件在文件头标记中记录起始页
+        let start_page = self.writer_atgtix_page.unwrap_or(0);
+        eprintln!("  起始页号: {}", start_page);
         eprintln!("  段指针表: {:?}", self.segment_pointers);
 
         let mut cursor = WordCursor::new(self, start_page)?;
@@ -262,7 +310,24 @@ impl AttlibParser {
         let mut cursor = WordCursor::new(self, start_page)?;
         let mut record_count = 0;
 
+        while let Some((_, def)) = self.next_definition(&mut cursor)? {
+            if record_count < 5 {
+                eprintln!("    [{}] hash=0x{:08X}", record_count, def.attr_hash);
+            }
+            self.attr_definitions.insert(def.attr_hash, def);
+            record_count += 1;
+        }
+        eprintln!("  ATGTDF 加载完成，共 {} 条记录", record_count);
+        Ok(())
+    }
+
+    /// 读取下一条 ATGTDF 记录及其位置，到达段尾时返回 `None`
+    fn next_definition(
+        &mut self,
+        cursor: &mut WordCursor,
+    ) -> std::io::Result<Option<((u32, usize), AttlibAttrDefinition)>> {
         loop {
+            let position = cursor.position();
             let word = cursor.next_word(self)?;
 
             if word == PAGE_SWITCH_MARK {
@@ -271,34 +336,33 @@ impl AttlibParser {
             }
 
             if word == SEGMENT_END_MARK {
-                eprintln!("  ATGTDF 加载完成，共 {} 条记录", record_count);
-                return Ok(());
+                return Ok(None);
             }
 
             if word < MIN_HASH || word > MAX_HASH {
                 continue;
             }
 
+            // 记录恰好从页首开始时，position 指向上一页末尾之后
+            let position = if position.1 >= WORDS_PER_PAGE {
+                (position.0 + 1, 0)
+            } else {
+                position
+            };
             let attr_hash = word;
             let data_type = cursor.next_word(self)?;
             let default_flag = cursor.next_word(self)?;
+            let default_value = self.read_default_value(cursor, data_type, default_flag)?;
 
-            let default_value = self.read_default_value(&mut cursor, data_type, default_flag)?;
-
-            self.attr_definitions.insert(
-                attr_hash,
+            return Ok(Some((
+                position,
                 AttlibAttrDefinition {
                     attr_hash,
                     data_type,
                     default_flag,
                     default_value,
                 },
-            );
-
-            if record_count < 5 {
-                eprintln!("    [{}] hash=0x{:08X}", record_count, attr_hash);
-            }
-            record_count += 1;
+            )));
         }
     }
 
@@ -343,6 +407,57 @@ impl AttlibParser {
         self.attr_definitions.values().collect()
     }
 
+    /// 按需读取单个属性定义，不加载整个 ATGTDF 段
+    ///
+    /// 已扫描过的记录直接按位置定位读取；否则从上次扫描停止处继续向后扫描，
+    /// 沿途只记录各记录的位置。读取到的定义放入 LRU 缓存。
+    pub fn get_attribute_lazy(
+        &mut self,
+        hash: u32,
+    ) -> std::io::Result<Option<AttlibAttrDefinition>> {
+        if let Some(def) = self.attr_definitions.get(&hash) {
+            return Ok(Some(def.clone()));
+        }
+        if let Some(def) = self.lazy_definitions.cache_get(&hash) {
+            return Ok(Some(def.clone()));
+        }
+
+        if let Some(&(page, idx)) = self.record_offsets.get(&hash) {
+            let mut cursor = WordCursor::at(self, page, idx)?;
+            if let Some((_, def)) = self.next_definition(&mut cursor)? {
+                if def.attr_hash == hash {
+                    self.lazy_definitions.cache_set(hash, def.clone());
+                    return Ok(Some(def));
+                }
+            }
+        }
+
+        let Some((page, idx)) = self.scan_resume else {
+            return Ok(None);
+        };
+        let mut cursor = WordCursor::at(self, page, idx)?;
+        while let Some((position, def)) = self.next_definition(&mut cursor)? {
+            self.record_offsets.insert(def.attr_hash, position);
+            if def.attr_hash == hash {
+                self.scan_resume = Some(cursor.position());
+                self.lazy_definitions.cache_set(hash, def.clone());
+                return Ok(Some(def));
+            }
+        }
+        self.scan_resume = None;
+        Ok(None)
+    }
+
+    /// 逐条读取 ATGTDF 段的属性定义，不缓存结果
+    pub fn iter_attributes(&mut self) -> std::io::Result<AttlibAttrIter<'_>> {
+        let cursor = WordCursor::new(self, 0)?;
+        Ok(AttlibAttrIter {
+            parser: self,
+            cursor,
+            done: false,
+        })
+    }
+
     /// 加载 ATGTSX 段（类型-属性语法映射）
     ///
     /// ATGTSX 段存储了 NOUN（类型）和 属性 的映射关系，
@@ -443,6 +558,259 @@ impl AttlibParser {
     }
 }
 
+/// [`AttlibParser::iter_attributes`] 返回的迭代器
+pub struct AttlibAttrIter<'a> {
+    parser: &'a mut AttlibParser,
+    cursor: WordCursor,
+    done: bool,
+}
+
+impl Iterator for AttlibAttrIter<'_> {
+    type Item = std::io::Result<AttlibAttrDefinition>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.parser.next_definition(&mut self.cursor) {
+            Ok(Some((position, def))) => {
+                self.parser.record_offsets.insert(def.attr_hash, position);
+                Some(Ok(def))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// ATGTSX 段在段指针表中的位置
+const ATGTSX_SEGMENT: usize = 3;
+/// [`AttlibWriter`] 写出文件的标记在文件头中的偏移
+///
+/// 原始文件的段指针表（0x0800..0x0820）之后到数据区之前全部为 0。写出器把 ATGTDF 写在页 0，
+/// 因此在这里写入 [`WRITER_MARK`] 和 ATGTIX 的起始页（大端），段指针表保持不变；
+/// 没有标记的原始文件仍从页 0 读取 ATGTIX
+const WRITER_MARK_OFFSET: u64 = 0x0FF8;
+const WRITER_MARK: [u8; 4] = *b"AWIX";
+
+/// 按页写出一个段的字
+struct SegmentWriter {
+    words: Vec<u32>,
+}
+
+impl SegmentWriter {
+    fn new() -> Self {
+        Self { words: Vec::new() }
+    }
+
+    #[inline]
+    fn push(&mut self, word: u32) {
+        self.words.push(word);
+    }
+
+    /// 写入段结束标记并补齐到整页
+    fn finish(mut self) -> Vec<u32> {
+        self.words.push(SEGMENT_END_MARK);
+        let pages = self.words.len().div_ceil(WORDS_PER_PAGE);
+        self.words.resize(pages * WORDS_PER_PAGE, 0);
+        self.words
+    }
+}
+
+/// attlib.dat 写出器
+///
+/// 将内存中的属性定义、默认值、索引与语法表序列化为 attlib.dat：
+/// - 文件头（0x0000..0x1000）沿用源文件，段指针表按新的布局重写
+/// - ATGTDF 段从数据区页 0 开始（与 [`AttlibParser`] 的读取位置一致）
+/// - ATGTIX、ATGTSX 段依次写在后续页，ATGTIX 起始页写入文件头标记，ATGTSX 起始页写入段指针表槽位 3
+pub struct AttlibWriter {
+    header: Vec<u8>,
+    segment_pointers: [u32; 8],
+    definitions: BTreeMap<u32, AttlibAttrDefinition>,
+    index: BTreeMap<u32, AttlibAttrIndex>,
+    syntax: Vec<AttlibSyntaxEntry>,
+}
+
+impl Default for AttlibWriter {
+    fn default() -> Self {
+        Self {
+            header: vec![0u8; DATA_REGION_START as usize],
+            segment_pointers: [0; 8],
+            definitions: BTreeMap::new(),
+            index: BTreeMap::new(),
+            syntax: Vec::new(),
+        }
+    }
+}
+
+impl AttlibWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从解析器读取全部内容（会触发 `load_all` 和 ATGTSX 加载）
+    pub fn from_parser(parser: &mut AttlibParser) -> std::io::Result<Self> {
+        if parser.attr_definitions.is_empty() {
+            parser.load_all()?;
+        }
+        let mut header = vec![0u8; DATA_REGION_START as usize];
+        parser.file.seek(SeekFrom::Start(0))?;
+        parser.file.read_exact(&mut header)?;
+        let syntax = if parser.segment_pointers[ATGTSX_SEGMENT] != 0 {
+            parser.load_atgtsx()?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            header,
+            segment_pointers: parser.segment_pointers,
+            definitions: parser
+                .attr_definitions
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            index: parser
+                .attr_index
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            syntax,
+        })
+    }
+
+    /// 新增或替换属性定义
+    pub fn upsert_definition(&mut self, def: AttlibAttrDefinition) {
+        self.definitions.insert(def.attr_hash, def);
+    }
+
+    pub fn remove_definition(&mut self, attr_hash: u32) -> Option<AttlibAttrDefinition> {
+        self.index.remove(&attr_hash);
+        self.definitions.remove(&attr_hash)
+    }
+
+    pub fn definition_mut(&mut self, attr_hash: u32) -> Option<&mut AttlibAttrDefinition> {
+        self.definitions.get_mut(&attr_hash)
+    }
+
+    /// 修改属性默认值，属性不存在时返回 false
+    pub fn set_default(&mut self, attr_hash: u32, value: AttlibDefaultValue) -> bool {
+        let Some(def) = self.definitions.get_mut(&attr_hash) else {
+            return false;
+        };
+        def.default_flag = if matches!(value, AttlibDefaultValue::None) { 1 } else { 2 };
+        def.default_value = value;
+        true
+    }
+
+    pub fn upsert_index(&mut self, index: AttlibAttrIndex) {
+        self.index.insert(index.attr_hash, index);
+    }
+
+    pub fn set_syntax(&mut self, entries: Vec<AttlibSyntaxEntry>) {
+        self.syntax = entries;
+    }
+
+    fn write_definitions(&self) -> Vec<u32> {
+        let mut seg = SegmentWriter::new();
+        for def in self.definitions.values() {
+            seg.push(def.attr_hash);
+            seg.push(def.data_type);
+            match &def.default_value {
+                AttlibDefaultValue::None => {
+                    // 保留原始的"无默认值"标志（非 2 即视为无默认值）
+                    seg.push(if def.default_flag == 2 { 1 } else { def.default_flag });
+                }
+                AttlibDefaultValue::Scalar(v) => {
+                    seg.push(2);
+                    seg.push(*v);
+                }
+                AttlibDefaultValue::Text(words) => {
+                    seg.push(2);
+                    if def.data_type == 4 {
+                        seg.push(words.len() as u32);
+                        words.iter().for_each(|w| seg.push(*w));
+                    } else {
+                        // 非 TEXT 类型只能存一个标量
+                        seg.push(words.first().copied().unwrap_or_default());
+                    }
+                }
+            }
+        }
+        seg.finish()
+    }
+
+    fn write_index(&self) -> Vec<u32> {
+        let mut seg = SegmentWriter::new();
+        for idx in self.index.values() {
+            seg.push(idx.attr_hash);
+            seg.push(idx.combined);
+        }
+        seg.finish()
+    }
+
+    fn write_syntax(&self) -> Vec<u32> {
+        let mut seg = SegmentWriter::new();
+        for entry in &self.syntax {
+            seg.push(entry.attr_hash);
+            seg.push(entry.noun_hash);
+            seg.push(entry.extra_info);
+        }
+        seg.finish()
+    }
+
+    /// 序列化为 attlib.dat 字节
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let df = self.write_definitions();
+        let ix = self.write_index();
+        let sx = self.write_syntax();
+
+        let mut pointers = self.segment_pointers;
+        let ix_page = (df.len() / WORDS_PER_PAGE) as u32;
+        let sx_page = ix_page + (ix.len() / WORDS_PER_PAGE) as u32;
+        pointers[ATGTSX_SEGMENT] = if self.syntax.is_empty() { 0 } else { sx_page };
+
+        let mut header = self.header.clone();
+        header.resize(DATA_REGION_START as usize, 0);
+        for (i, p) in pointers.iter().enumerate() {
+            let offset = SEGMENT_POINTERS_OFFSET as usize + i * 4;
+            header[offset..offset + 4].copy_from_slice(&p.to_be_bytes());
+        }
+        let mark = WRITER_MARK_OFFSET as usize;
+        header[mark..mark + 4].copy_from_slice(&WRITER_MARK);
+        header[mark + 4..mark + 8].copy_from_slice(&ix_page.to_be_bytes());
+
+        let mut bytes = header;
+        let data = if self.syntax.is_empty() {
+            [df, ix].concat()
+        } else {
+            [df, ix, sx].concat()
+        };
+        bytes.reserve(data.len() * 4);
+        for word in data {
+            bytes.extend_from_slice(&word.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// 写出到文件
+    pub fn write_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+impl AttlibParser {
+    /// 将当前解析结果写回 attlib.dat
+    pub fn write_to(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        AttlibWriter::from_parser(self)?.write_to(path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -474,4 +842,144 @@ mod tests {
             assert_eq!(decoded, name, "Roundtrip failed for {}", name);
         }
     }
+
+    #[test]
+    fn test_writer_roundtrip() {
+        let mut writer = AttlibWriter::new();
+        let name = encode_base27("NAME");
+        let bore = encode_base27("BORE");
+        writer.upsert_definition(AttlibAttrDefinition {
+            attr_hash: name,
+            data_type: 4,
+            default_flag: 2,
+            default_value: AttlibDefaultValue::Text(vec![1, 2, 3]),
+        });
+        writer.upsert_definition(AttlibAttrDefinition {
+            attr_hash: bore,
+            data_type: 2,
+            default_flag: 1,
+            default_value: AttlibDefaultValue::None,
+        });
+        assert!(writer.set_default(bore, AttlibDefaultValue::Scalar(100)));
+        let pipe = encode_base27("PIPE");
+        writer.set_syntax(vec![
+            AttlibSyntaxEntry { attr_hash: name, noun_hash: pipe, extra_info: 0 },
+            AttlibSyntaxEntry { attr_hash: bore, noun_hash: pipe, extra_info: 0 },
+        ]);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        writer.write_to(file.path()).unwrap();
+
+        let mut parser = AttlibParser::new(file.path().to_str().unwrap()).unwrap();
+        parser.load_all().unwrap();
+        assert!(matches!(
+            parser.get_attribute(bore).unwrap().default_value,
+            AttlibDefaultValue::Scalar(100)
+        ));
+        assert!(matches!(
+            &parser.get_attribute(name).unwrap().default_value,
+            AttlibDefaultValue::Text(t) if t == &vec![1, 2, 3]
+        ));
+        assert_eq!(
+            parser.get_noun_attribute_names("PIPE").unwrap(),
+            vec!["NAME".to_string(), "BORE".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_writer_index_roundtrip() {
+        let mut writer = AttlibWriter::new();
+        // 与 data/attlib.dat 相同的段指针表，槽位 7 指向真实数据而非空闲槽位
+        writer.segment_pointers = [3, 4, 1415, 1433, 1466, 1467, 1923, 1929];
+        let hashes: Vec<u32> = ["NAME", "BORE", "TEMP"]
+            .iter()
+            .map(|n| encode_base27(n))
+            .collect();
+        for (i, &attr_hash) in hashes.iter().enumerate() {
+            writer.upsert_definition(AttlibAttrDefinition {
+                attr_hash,
+                data_type: 2,
+                default_flag: 2,
+                default_value: AttlibDefaultValue::Scalar(i as u32),
+            });
+            writer.upsert_index(AttlibAttrIndex {
+                attr_hash,
+                combined: 512 * (i as u32 + 1) + 7,
+            });
+        }
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        writer.write_to(file.path()).unwrap();
+
+        let mut parser = AttlibParser::new(file.path().to_str().unwrap()).unwrap();
+        // 除 ATGTSX 外的段指针不被覆盖
+        for (i, (&read, &written)) in parser
+            .segment_pointers
+            .iter()
+            .zip(writer.segment_pointers.iter())
+            .enumerate()
+        {
+            if i != ATGTSX_SEGMENT {
+                assert_eq!(read, written, "段指针 {} 被覆盖", i);
+            }
+        }
+        assert!(parser.writer_atgtix_page.is_some());
+        parser.load_all().unwrap();
+        assert_eq!(parser.attr_index.len(), hashes.len());
+        for idx in writer.index.values() {
+            assert_eq!(parser.attr_index.get(&idx.attr_hash), Some(idx));
+        }
+        assert_eq!(parser.attr_definitions.len(), hashes.len());
+    }
+
+    #[test]
+    fn test_load_shipped_attlib_index() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/attlib.dat");
+        let mut parser = AttlibParser::new(path).unwrap();
+        assert_eq!(parser.segment_pointers, [3, 4, 1415, 1433, 1466, 1467, 1923, 1929]);
+        // 原始文件没有写出器标记，ATGTIX 仍从页 0 读取
+        assert_eq!(parser.writer_atgtix_page, None);
+        parser.load_atgtix().unwrap();
+        assert!(!parser.attr_index.is_empty());
+        assert_eq!(parser.attr_index[&encode_base27("SIZE")].combined, 3);
+        assert_eq!(parser.attr_index[&encode_base27("DTYP")].combined, 3);
+        // 页 1929（段指针槽位 7）的首字不是属性哈希
+        assert!(!parser.attr_index.contains_key(&2750517));
+    }
+
+    #[test]
+    fn test_lazy_attribute_access() {
+        let mut writer = AttlibWriter::new();
+        let names = ["NAME", "BORE", "TEMP", "PURP"];
+        for (i, n) in names.iter().enumerate() {
+            writer.upsert_definition(AttlibAttrDefinition {
+                attr_hash: encode_base27(n),
+                data_type: 2,
+                default_flag: 2,
+                default_value: AttlibDefaultValue::Scalar(i as u32 + 1),
+            });
+        }
+        let file = tempfile::NamedTempFile::new().unwrap();
+        writer.write_to(file.path()).unwrap();
+
+        let mut parser = AttlibParser::builder(file.path().to_str().unwrap())
+            .page_cache_pages(1)
+            .definition_cache(2)
+            .build()
+            .unwrap();
+        let temp = parser.get_attribute_lazy(encode_base27("TEMP")).unwrap().unwrap();
+        assert!(matches!(temp.default_value, AttlibDefaultValue::Scalar(3)));
+        // 已扫描过的记录按位置读取
+        let name = parser.get_attribute_lazy(encode_base27("NAME")).unwrap().unwrap();
+        assert!(matches!(name.default_value, AttlibDefaultValue::Scalar(1)));
+        assert!(parser.get_attribute_lazy(encode_base27("XXXX")).unwrap().is_none());
+        assert!(parser.attr_definitions.is_empty());
+
+        let all = parser
+            .iter_attributes()
+            .unwrap()
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(all.len(), names.len());
+    }
 }
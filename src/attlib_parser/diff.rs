@@ -0,0 +1,284 @@
+//! 两个版本 attlib.dat 的差异比较
+//!
+//! 用于跟踪 PDMS 版本升级带来的属性变化：新增/删除的属性、数据类型变化、默认值变化，
+//! 同时按 Noun（ATGTSX 语法表）统计每个类型的属性增减。报告可直接序列化为 JSON 供 CI 检查。
+
+use super::{
+    AttlibAttrDefinition, AttlibDefaultValue, AttlibParser, NounAttrMapping, decode_base27,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// 数据类型变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttrTypeChange {
+    pub name: String,
+    pub old_type: u32,
+    pub new_type: u32,
+}
+
+/// 默认值变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttrDefaultChange {
+    pub name: String,
+    pub old_default: AttlibDefaultValue,
+    pub new_default: AttlibDefaultValue,
+}
+
+/// 一组属性的变化
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttrChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub retyped: Vec<AttrTypeChange>,
+    pub defaults_changed: Vec<AttrDefaultChange>,
+}
+
+impl AttrChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retyped.is_empty()
+            && self.defaults_changed.is_empty()
+    }
+}
+
+/// 差异报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttlibDiff {
+    /// 全部属性定义（ATGTDF）的变化
+    pub attributes: AttrChanges,
+    pub added_nouns: Vec<String>,
+    pub removed_nouns: Vec<String>,
+    /// 两个版本都存在的 Noun 的属性变化，只包含有变化的 Noun
+    pub nouns: BTreeMap<String, AttrChanges>,
+}
+
+impl AttlibDiff {
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+            && self.added_nouns.is_empty()
+            && self.removed_nouns.is_empty()
+            && self.nouns.is_empty()
+    }
+
+    /// 比较两个 attlib.dat，未加载的属性定义会先加载
+    ///
+    /// 缺少 ATGTSX 段时只比较属性定义，不输出 Noun 级别的变化。
+    pub fn compare(old: &mut AttlibParser, new: &mut AttlibParser) -> std::io::Result<Self> {
+        let old_mapping = load_for_diff(old)?;
+        let new_mapping = load_for_diff(new)?;
+        Ok(Self::compare_tables(
+            &old.attr_definitions,
+            &old_mapping,
+            &new.attr_definitions,
+            &new_mapping,
+        ))
+    }
+
+    /// 按已加载的属性定义与 Noun 映射比较
+    pub fn compare_tables(
+        old_defs: &HashMap<u32, AttlibAttrDefinition>,
+        old_mapping: &NounAttrMapping,
+        new_defs: &HashMap<u32, AttlibAttrDefinition>,
+        new_mapping: &NounAttrMapping,
+    ) -> Self {
+        let attributes = diff_attrs(
+            &old_defs.keys().copied().collect(),
+            &new_defs.keys().copied().collect(),
+            old_defs,
+            new_defs,
+        );
+
+        let old_nouns: BTreeSet<u32> = old_mapping.keys().copied().collect();
+        let new_nouns: BTreeSet<u32> = new_mapping.keys().copied().collect();
+        let mut nouns = BTreeMap::new();
+        for noun in old_nouns.intersection(&new_nouns) {
+            let changes = diff_attrs(
+                &old_mapping[noun].iter().copied().collect(),
+                &new_mapping[noun].iter().copied().collect(),
+                old_defs,
+                new_defs,
+            );
+            if !changes.is_empty() {
+                nouns.insert(decode_base27(*noun), changes);
+            }
+        }
+
+        Self {
+            attributes,
+            added_nouns: sorted_names(new_nouns.difference(&old_nouns)),
+            removed_nouns: sorted_names(old_nouns.difference(&new_nouns)),
+            nouns,
+        }
+    }
+}
+
+fn load_for_diff(parser: &mut AttlibParser) -> std::io::Result<NounAttrMapping> {
+    if parser.attr_definitions.is_empty() {
+        parser.load_all()?;
+    }
+    match parser.build_noun_attr_mapping() {
+        Ok(mapping) => Ok(mapping),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(NounAttrMapping::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn sorted_names<'a>(hashes: impl Iterator<Item = &'a u32>) -> Vec<String> {
+    let mut names = hashes.map(|h| decode_base27(*h)).collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn diff_attrs(
+    old: &BTreeSet<u32>,
+    new: &BTreeSet<u32>,
+    old_defs: &HashMap<u32, AttlibAttrDefinition>,
+    new_defs: &HashMap<u32, AttlibAttrDefinition>,
+) -> AttrChanges {
+    let mut changes = AttrChanges {
+        added: sorted_names(new.difference(old)),
+        removed: sorted_names(old.difference(new)),
+        ..Default::default()
+    };
+    for hash in old.intersection(new) {
+        let (Some(o), Some(n)) = (old_defs.get(hash), new_defs.get(hash)) else {
+            continue;
+        };
+        let name = decode_base27(*hash);
+        if o.data_type != n.data_type {
+            changes.retyped.push(AttrTypeChange {
+                name: name.clone(),
+                old_type: o.data_type,
+                new_type: n.data_type,
+            });
+        }
+        if o.default_value != n.default_value {
+            changes.defaults_changed.push(AttrDefaultChange {
+                name,
+                old_default: o.default_value.clone(),
+                new_default: n.default_value.clone(),
+            });
+        }
+    }
+    changes.retyped.sort_by(|a, b| a.name.cmp(&b.name));
+    changes.defaults_changed.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AttlibSyntaxEntry, AttlibWriter, encode_base27};
+    use super::*;
+
+    fn def(name: &str, data_type: u32, default_value: AttlibDefaultValue) -> AttlibAttrDefinition {
+        let default_flag = if matches!(default_value, AttlibDefaultValue::None) {
+            1
+        } else {
+            2
+        };
+        AttlibAttrDefinition {
+            attr_hash: encode_base27(name),
+            data_type,
+            default_flag,
+            default_value,
+        }
+    }
+
+    fn write_attlib(
+        defs: Vec<AttlibAttrDefinition>,
+        syntax: &[(&str, &str)],
+    ) -> tempfile::NamedTempFile {
+        let mut writer = AttlibWriter::new();
+        defs.into_iter().for_each(|d| writer.upsert_definition(d));
+        writer.set_syntax(
+            syntax
+                .iter()
+                .map(|(attr, noun)| AttlibSyntaxEntry {
+                    attr_hash: encode_base27(attr),
+                    noun_hash: encode_base27(noun),
+                    extra_info: 0,
+                })
+                .collect(),
+        );
+        let file = tempfile::NamedTempFile::new().unwrap();
+        writer.write_to(file.path()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_compare_added_removed_changed() {
+        let old_file = write_attlib(
+            vec![
+                def("NAME", 4, AttlibDefaultValue::None),
+                def("BORE", 2, AttlibDefaultValue::Scalar(100)),
+                def("TEMP", 2, AttlibDefaultValue::None),
+                def("DESC", 4, AttlibDefaultValue::None),
+            ],
+            &[
+                ("NAME", "PIPE"),
+                ("BORE", "PIPE"),
+                ("DESC", "PIPE"),
+                ("NAME", "ELBO"),
+            ],
+        );
+        let new_file = write_attlib(
+            vec![
+                def("NAME", 4, AttlibDefaultValue::None),
+                def("BORE", 2, AttlibDefaultValue::Scalar(150)),
+                def("TEMP", 3, AttlibDefaultValue::None),
+                def("SPRE", 1, AttlibDefaultValue::None),
+            ],
+            &[
+                ("NAME", "PIPE"),
+                ("BORE", "PIPE"),
+                ("SPRE", "PIPE"),
+                ("NAME", "BRAN"),
+            ],
+        );
+
+        let mut old = AttlibParser::new(old_file.path().to_str().unwrap()).unwrap();
+        let mut new = AttlibParser::new(new_file.path().to_str().unwrap()).unwrap();
+        let diff = AttlibDiff::compare(&mut old, &mut new).unwrap();
+        assert!(!diff.is_empty());
+
+        assert_eq!(diff.attributes.added, vec!["SPRE"]);
+        assert_eq!(diff.attributes.removed, vec!["DESC"]);
+        assert_eq!(diff.attributes.retyped.len(), 1);
+        assert_eq!(diff.attributes.retyped[0].name, "TEMP");
+        assert_eq!(
+            (
+                diff.attributes.retyped[0].old_type,
+                diff.attributes.retyped[0].new_type
+            ),
+            (2, 3)
+        );
+        assert_eq!(diff.attributes.defaults_changed.len(), 1);
+        let bore = &diff.attributes.defaults_changed[0];
+        assert_eq!(bore.name, "BORE");
+        assert_eq!(bore.old_default, AttlibDefaultValue::Scalar(100));
+        assert_eq!(bore.new_default, AttlibDefaultValue::Scalar(150));
+
+        assert_eq!(diff.added_nouns, vec!["BRAN"]);
+        assert_eq!(diff.removed_nouns, vec!["ELBO"]);
+        assert_eq!(diff.nouns.len(), 1);
+        let pipe = &diff.nouns["PIPE"];
+        assert_eq!(pipe.added, vec!["SPRE"]);
+        assert_eq!(pipe.removed, vec!["DESC"]);
+        assert_eq!(pipe.defaults_changed.len(), 1);
+        assert!(pipe.retyped.is_empty());
+    }
+
+    #[test]
+    fn test_compare_tables_identical() {
+        let defs: HashMap<u32, AttlibAttrDefinition> =
+            [def("BORE", 2, AttlibDefaultValue::Scalar(100))]
+                .into_iter()
+                .map(|d| (d.attr_hash, d))
+                .collect();
+        let mapping: NounAttrMapping =
+            HashMap::from([(encode_base27("PIPE"), vec![encode_base27("BORE")])]);
+        assert!(AttlibDiff::compare_tables(&defs, &mapping, &defs, &mapping).is_empty());
+    }
+}
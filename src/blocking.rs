@@ -0,0 +1,102 @@
+//! 同步调用门面
+//!
+//! 部分宿主程序（如通过 FFI 调用的 C++/Qt 程序）不方便运行 tokio。
+//! [`BlockingClient`] 内部持有一个 tokio 运行时，对常用的查询/几何接口提供同步封装。
+//!
+//! 注意：不要在 tokio 运行时内部调用这些同步方法，否则 `block_on` 会 panic。
+
+use crate::facade::{DoctorReport, FacadeScope};
+use crate::pe::SPdmsElement;
+use crate::rs_surreal::inst::GeomInstQuery;
+use crate::{NamedAttrMap, RefnoEnum};
+use bevy_transform::components::Transform;
+use once_cell::sync::OnceCell;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tokio::runtime::Runtime;
+
+/// 持有独立运行时的同步客户端
+pub struct BlockingClient {
+    rt: Runtime,
+}
+
+impl BlockingClient {
+    /// 创建运行时并按配置文件（`DB_OPTION_FILE`）初始化数据库连接
+    pub fn new() -> anyhow::Result<Self> {
+        let client = Self::without_init()?;
+        client.block_on(crate::init_surreal())?;
+        Ok(client)
+    }
+
+    /// 仅创建运行时，不初始化数据库（连接已由其他方式建立时使用）
+    pub fn without_init() -> anyhow::Result<Self> {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("aios-blocking")
+            .build()?;
+        Ok(Self { rt })
+    }
+
+    /// 在内部运行时上执行 future
+    #[inline]
+    pub fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        self.rt.block_on(fut)
+    }
+
+    pub fn get_pe(&self, refno: RefnoEnum) -> anyhow::Result<Option<SPdmsElement>> {
+        self.block_on(crate::get_pe(refno))
+    }
+
+    pub fn get_named_attmap(&self, refno: RefnoEnum) -> anyhow::Result<NamedAttrMap> {
+        self.block_on(crate::get_named_attmap(refno))
+    }
+
+    pub fn get_children_refnos(&self, refno: RefnoEnum) -> anyhow::Result<Vec<RefnoEnum>> {
+        self.block_on(crate::get_children_refnos(refno))
+    }
+
+    pub fn query_deep_children_refnos(&self, refno: RefnoEnum) -> anyhow::Result<Vec<RefnoEnum>> {
+        self.block_on(crate::query_deep_children_refnos(refno))
+    }
+
+    pub fn get_world_transform(&self, refno: RefnoEnum) -> anyhow::Result<Option<Transform>> {
+        self.block_on(crate::get_world_transform(refno))
+    }
+
+    pub fn query_insts(&self, refnos: &[RefnoEnum], enable_holes: bool) -> anyhow::Result<Vec<GeomInstQuery>> {
+        self.block_on(crate::rs_surreal::inst::query_insts(refnos, enable_holes))
+    }
+
+    pub fn run_doctor(&self) -> anyhow::Result<DoctorReport> {
+        self.block_on(crate::facade::run_doctor(None))
+    }
+
+    /// 查询指定专业、指定 site 的材料表单
+    pub fn get_material_lists(
+        &self,
+        major: &str,
+        site: RefnoEnum,
+    ) -> anyhow::Result<Vec<(String, Vec<serde_json::Value>)>> {
+        let lists = self.block_on(crate::material::get_major_material_lists(major, site.refno()))?;
+        Ok(lists
+            .into_iter()
+            .map(|(table, rows)| (table.list_name().to_string(), rows))
+            .collect())
+    }
+
+    /// 导出 XKT，需先注册几何后端
+    pub fn export_xkt(&self, refnos: Vec<RefnoEnum>, path: &Path) -> anyhow::Result<PathBuf> {
+        self.block_on(crate::facade::export_xkt(
+            &FacadeScope::Refnos(refnos),
+            path,
+            None,
+        ))
+    }
+}
+
+static GLOBAL_CLIENT: OnceCell<BlockingClient> = OnceCell::new();
+
+/// 全局同步客户端，首次调用时初始化
+pub fn global_client() -> anyhow::Result<&'static BlockingClient> {
+    GLOBAL_CLIENT.get_or_try_init(BlockingClient::new)
+}
@@ -0,0 +1,309 @@
+//! 带容量上限和过期时间的并发缓存
+//!
+//! 按 key 分片存储（[`DashMap`]），读写不经过全局锁，可在 async 代码中直接使用。
+//! 超过容量时先清理过期条目，仍然不足则在一小批条目中淘汰最早写入的一个（近似 LRU）。
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 淘汰时抽样的条目数
+const EVICTION_SAMPLE: usize = 16;
+
+/// 缓存配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AttrCacheConfig {
+    /// 最大条目数，0 表示不缓存
+    pub max_capacity: usize,
+    /// 条目存活时间（秒），None 表示不过期
+    pub ttl_secs: Option<u64>,
+}
+
+impl Default for AttrCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_capacity: 10000,
+            ttl_secs: Some(600),
+        }
+    }
+}
+
+impl AttrCacheConfig {
+    pub fn new(max_capacity: usize, ttl_secs: Option<u64>) -> Self {
+        Self {
+            max_capacity,
+            ttl_secs,
+        }
+    }
+
+    #[inline]
+    fn ttl(&self) -> Option<Duration> {
+        self.ttl_secs.map(Duration::from_secs)
+    }
+}
+
+/// 缓存统计快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    pub invalidations: u64,
+    pub size: usize,
+}
+
+impl CacheStats {
+    /// 命中率，无读取时为 0
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    expirations: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<V> {
+    value: V,
+    inserted: Instant,
+}
+
+/// 并发缓存
+#[derive(Debug)]
+pub struct AttrCache<K: Eq + Hash, V> {
+    name: &'static str,
+    config: RwLock<AttrCacheConfig>,
+    map: DashMap<K, Entry<V>>,
+    counters: Counters,
+}
+
+impl<K, V> AttrCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new(name: &'static str, config: AttrCacheConfig) -> Self {
+        Self {
+            name,
+            config: RwLock::new(config),
+            map: DashMap::new(),
+            counters: Counters::default(),
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    #[inline]
+    pub fn config(&self) -> AttrCacheConfig {
+        *self.config.read()
+    }
+
+    /// 修改配置，容量变小时立即淘汰多出的条目
+    pub fn set_config(&self, config: AttrCacheConfig) {
+        *self.config.write() = config;
+        self.shrink_to(config.max_capacity);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    fn is_expired(&self, entry: &Entry<V>, ttl: Option<Duration>) -> bool {
+        ttl.is_some_and(|ttl| entry.inserted.elapsed() >= ttl)
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let ttl = self.config().ttl();
+        let hit = self
+            .map
+            .get(key)
+            .and_then(|e| (!self.is_expired(&e, ttl)).then(|| e.value.clone()));
+        match hit {
+            Some(v) => {
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                Some(v)
+            }
+            None => {
+                if self
+                    .map
+                    .remove_if(key, |_, e| self.is_expired(e, ttl))
+                    .is_some()
+                {
+                    self.counters.expirations.fetch_add(1, Ordering::Relaxed);
+                }
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let capacity = self.config().max_capacity;
+        if capacity == 0 {
+            return;
+        }
+        if !self.map.contains_key(&key) && self.map.len() >= capacity {
+            self.shrink_to(capacity - 1);
+        }
+        self.map.insert(
+            key,
+            Entry {
+                value,
+                inserted: Instant::now(),
+            },
+        );
+    }
+
+    /// 先查缓存，未命中时执行 `init` 并写入；`init` 出错时不缓存
+    ///
+    /// 同一个 key 的并发未命中会各自执行一次 `init`，以最后写入的结果为准。
+    pub async fn try_get_with<F>(&self, key: K, init: F) -> anyhow::Result<V>
+    where
+        F: Future<Output = anyhow::Result<V>>,
+    {
+        if let Some(v) = self.get(&key) {
+            return Ok(v);
+        }
+        let value = init.await?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        if self.map.remove(key).is_some() {
+            self.counters.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn invalidate_all(&self) {
+        let n = self.map.len() as u64;
+        self.map.clear();
+        self.counters.invalidations.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// 淘汰条目直到不超过 `target` 条
+    fn shrink_to(&self, target: usize) {
+        if self.map.len() <= target {
+            return;
+        }
+        if let Some(ttl) = self.config().ttl() {
+            let before = self.map.len();
+            self.map.retain(|_, e| e.inserted.elapsed() < ttl);
+            let expired = before.saturating_sub(self.map.len()) as u64;
+            self.counters
+                .expirations
+                .fetch_add(expired, Ordering::Relaxed);
+        }
+        while self.map.len() > target {
+            let oldest = self
+                .map
+                .iter()
+                .take(EVICTION_SAMPLE)
+                .min_by_key(|e| e.inserted)
+                .map(|e| e.key().clone());
+            let Some(key) = oldest else {
+                break;
+            };
+            if self.map.remove(&key).is_some() {
+                self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            expirations: self.counters.expirations.load(Ordering::Relaxed),
+            invalidations: self.counters.invalidations.load(Ordering::Relaxed),
+            size: self.map.len(),
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        for c in [
+            &self.counters.hits,
+            &self.counters.misses,
+            &self.counters.evictions,
+            &self.counters.expirations,
+            &self.counters.invalidations,
+        ] {
+            c.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capacity_bound() {
+        let cache = AttrCache::new("test", AttrCacheConfig::new(3, None));
+        for i in 0..10 {
+            cache.insert(i, i * 10);
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.stats().evictions, 7);
+        assert_eq!(cache.get(&9), Some(90));
+    }
+
+    #[test]
+    fn test_ttl_and_stats() {
+        let cache = AttrCache::new("test", AttrCacheConfig::new(10, Some(0)));
+        cache.insert(1, "a");
+        assert_eq!(cache.get(&1), None);
+        let stats = cache.stats();
+        assert_eq!((stats.misses, stats.expirations, stats.size), (1, 1, 0));
+
+        cache.set_config(AttrCacheConfig::new(10, None));
+        cache.insert(1, "a");
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.stats().hit_rate(), 0.5);
+        cache.invalidate(&1);
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_try_get_with() {
+        let cache = AttrCache::new("test", AttrCacheConfig::default());
+        let v = cache.try_get_with(1, async { Ok(5) }).await.unwrap();
+        assert_eq!(v, 5);
+        let v = cache
+            .try_get_with(1, async { anyhow::bail!("不应执行") })
+            .await
+            .unwrap();
+        assert_eq!(v, 5);
+        assert!(
+            cache
+                .try_get_with(2, async { anyhow::bail!("加载失败") })
+                .await
+                .is_err()
+        );
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,422 @@
+//! 属性与世界变换的磁盘缓存
+//!
+//! 每次启动都要重新查询属性、重新计算世界变换，模型较大时启动很慢。
+//! 启用 [`DiskCacheConfig`] 后，这两类结果按 `(refno, sesno)` 写入本地 redb 文件，
+//! 下次运行时先读磁盘（read-through），未命中再走数据库并写回。
+//!
+//! - 属性按元素自身的 sesno 存储，元素被修改后 sesno 变化，旧条目自然失效
+//! - 世界变换受祖先影响，按元素所在 db 的最新 sesno 存储，db 有新会话即整体失效
+//! - 历史版本（`RefnoEnum::SesRef`）直接使用其 sesno
+//! - 增量同步写入新会话后调用 [`refresh_sessions`] 重新查询 db 的 sesno；
+//!   移动元素等本地修改不产生新会话，由 [`invalidate_world_trans`] 直接删除相关条目
+//!
+//! 需要启用 `redb` 特性，未启用时配置无效，读取直接回落到数据库。
+
+use crate::{NamedAttrMap, RefnoEnum};
+use glam::DMat4;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// 磁盘缓存配置，对应 DbOption 中的 `[disk_cache]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 缓存文件路径
+    #[serde(default = "default_disk_cache_path")]
+    pub path: String,
+    /// 是否缓存属性
+    #[serde(default = "default_true")]
+    pub attmaps: bool,
+    /// 是否缓存世界变换
+    #[serde(default = "default_true")]
+    pub world_trans: bool,
+}
+
+fn default_disk_cache_path() -> String {
+    "cache/attr_cache.redb".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for DiskCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_disk_cache_path(),
+            attmaps: true,
+            world_trans: true,
+        }
+    }
+}
+
+/// 缓存键：参考号 + 会话号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub refno: u64,
+    pub sesno: u32,
+}
+
+impl SessionKey {
+    #[inline]
+    pub fn new(refno: RefnoEnum, sesno: u32) -> Self {
+        Self {
+            refno: refno.refno().0,
+            sesno,
+        }
+    }
+}
+
+#[cfg(any(feature = "redb", test))]
+#[inline]
+fn mat4_to_bytes(m: &DMat4) -> Vec<u8> {
+    m.to_cols_array()
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+#[cfg(any(feature = "redb", test))]
+fn mat4_from_bytes(bytes: &[u8]) -> Option<DMat4> {
+    if bytes.len() != 16 * 8 {
+        return None;
+    }
+    let mut cols = [0.0f64; 16];
+    for (v, chunk) in cols.iter_mut().zip(bytes.chunks_exact(8)) {
+        *v = f64::from_le_bytes(chunk.try_into().ok()?);
+    }
+    Some(DMat4::from_cols_array(&cols))
+}
+
+#[cfg(feature = "redb")]
+mod store {
+    use super::SessionKey;
+    use redb::{Database, ReadableTable, TableDefinition};
+    use std::path::Path;
+
+    pub(super) const ATTMAPS: TableDefinition<(u64, u32), &[u8]> = TableDefinition::new("attmap");
+    pub(super) const WORLD_TRANS: TableDefinition<(u64, u32), &[u8]> =
+        TableDefinition::new("world_trans");
+
+    pub struct DiskStore {
+        db: Database,
+    }
+
+    impl DiskStore {
+        pub fn open(path: &Path) -> anyhow::Result<Self> {
+            if let Some(dir) = path.parent()
+                && !dir.as_os_str().is_empty()
+            {
+                std::fs::create_dir_all(dir)?;
+            }
+            let db = Database::create(path)?;
+            // 先建表，读事务打开不存在的表会报错
+            let txn = db.begin_write()?;
+            txn.open_table(ATTMAPS)?;
+            txn.open_table(WORLD_TRANS)?;
+            txn.commit()?;
+            Ok(Self { db })
+        }
+
+        pub fn get(
+            &self,
+            table: TableDefinition<(u64, u32), &[u8]>,
+            key: SessionKey,
+        ) -> anyhow::Result<Option<Vec<u8>>> {
+            let txn = self.db.begin_read()?;
+            let table = txn.open_table(table)?;
+            Ok(table
+                .get((key.refno, key.sesno))?
+                .map(|v| v.value().to_vec()))
+        }
+
+        pub fn put(
+            &self,
+            table: TableDefinition<(u64, u32), &[u8]>,
+            key: SessionKey,
+            value: &[u8],
+        ) -> anyhow::Result<()> {
+            let txn = self.db.begin_write()?;
+            {
+                let mut table = txn.open_table(table)?;
+                table.insert((key.refno, key.sesno), value)?;
+            }
+            txn.commit()?;
+            Ok(())
+        }
+
+        /// 删除某个参考号在 `sesno` 之前的旧条目
+        pub fn purge_older(
+            &self,
+            table: TableDefinition<(u64, u32), &[u8]>,
+            key: SessionKey,
+        ) -> anyhow::Result<()> {
+            let txn = self.db.begin_write()?;
+            {
+                let mut table = txn.open_table(table)?;
+                table.retain_in((key.refno, 0)..(key.refno, key.sesno), |_, _| false)?;
+            }
+            txn.commit()?;
+            Ok(())
+        }
+
+        /// 删除某个参考号的全部条目
+        pub fn remove_refno(
+            &self,
+            table: TableDefinition<(u64, u32), &[u8]>,
+            refno: u64,
+        ) -> anyhow::Result<()> {
+            let txn = self.db.begin_write()?;
+            {
+                let mut table = txn.open_table(table)?;
+                table.retain_in((refno, 0)..=(refno, u32::MAX), |_, _| false)?;
+            }
+            txn.commit()?;
+            Ok(())
+        }
+
+        pub fn clear(&self) -> anyhow::Result<()> {
+            let txn = self.db.begin_write()?;
+            for table in [ATTMAPS, WORLD_TRANS] {
+                txn.delete_table(table)?;
+                txn.open_table(table)?;
+            }
+            txn.commit()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "redb")]
+mod active {
+    use super::store::{ATTMAPS, DiskStore, WORLD_TRANS};
+    use super::*;
+    use dashmap::DashMap;
+    use once_cell::sync::{Lazy, OnceCell};
+
+    static DISK_STORE: OnceCell<Option<DiskStore>> = OnceCell::new();
+
+    /// db 的最新 sesno，进程内只查一次
+    static DB_SESNOS: Lazy<DashMap<u32, u32>> = Lazy::new(DashMap::new);
+
+    /// 读不到配置时按缺省值（不启用磁盘缓存）
+    fn config() -> &'static DiskCacheConfig {
+        static DEFAULT: Lazy<DiskCacheConfig> = Lazy::new(DiskCacheConfig::default);
+        match crate::get_db_option() {
+            Ok(option) => &option.disk_cache,
+            Err(_) => &DEFAULT,
+        }
+    }
+
+    fn disk_store() -> Option<&'static DiskStore> {
+        DISK_STORE
+            .get_or_init(|| {
+                let config = config();
+                if !config.enabled {
+                    return None;
+                }
+                match DiskStore::open(std::path::Path::new(&config.path)) {
+                    Ok(store) => Some(store),
+                    Err(e) => {
+                        log::warn!("打开磁盘缓存 {} 失败，已禁用: {}", config.path, e);
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+
+    async fn db_sesno(dbnum: u32) -> anyhow::Result<u32> {
+        if let Some(sesno) = DB_SESNOS.get(&dbnum) {
+            return Ok(*sesno);
+        }
+        let sesno = crate::rs_surreal::e3d_db::query_latest_sesno(dbnum).await?;
+        DB_SESNOS.insert(dbnum, sesno);
+        Ok(sesno)
+    }
+
+    /// 属性键：历史版本用自身 sesno，否则用 pe 当前 sesno
+    async fn attmap_key(refno: RefnoEnum) -> anyhow::Result<Option<SessionKey>> {
+        if let Some(sesno) = refno.sesno() {
+            return Ok(Some(SessionKey::new(refno, sesno)));
+        }
+        Ok(crate::get_pe(refno)
+            .await?
+            .filter(|pe| pe.sesno > 0)
+            .map(|pe| SessionKey::new(refno, pe.sesno as u32)))
+    }
+
+    /// 世界变换键：历史版本用自身 sesno，否则用所在 db 的最新 sesno
+    async fn world_key(refno: RefnoEnum) -> anyhow::Result<Option<SessionKey>> {
+        if let Some(sesno) = refno.sesno() {
+            return Ok(Some(SessionKey::new(refno, sesno)));
+        }
+        let Some(pe) = crate::get_pe(refno).await? else {
+            return Ok(None);
+        };
+        let sesno = db_sesno(pe.dbnum as u32).await?;
+        Ok((sesno > 0).then(|| SessionKey::new(refno, sesno)))
+    }
+
+    pub async fn read_through_attmap<F>(refno: RefnoEnum, load: F) -> anyhow::Result<NamedAttrMap>
+    where
+        F: Future<Output = anyhow::Result<NamedAttrMap>>,
+    {
+        let Some(store) = disk_store().filter(|_| config().attmaps) else {
+            return load.await;
+        };
+        let Some(key) = attmap_key(refno).await? else {
+            return load.await;
+        };
+        if let Ok(Some(bytes)) = store.get(ATTMAPS, key)
+            && let Ok(attmap) = serde_json::from_slice::<NamedAttrMap>(&bytes)
+        {
+            return Ok(attmap);
+        }
+        let attmap = load.await?;
+        if !attmap.map.is_empty() {
+            let write = serde_json::to_vec(&attmap)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| store.put(ATTMAPS, key, &bytes))
+                .and_then(|_| store.purge_older(ATTMAPS, key));
+            if let Err(e) = write {
+                log::debug!("写入属性磁盘缓存失败 {}: {}", refno, e);
+            }
+        }
+        Ok(attmap)
+    }
+
+    pub async fn read_through_world_mat4<F>(
+        refno: RefnoEnum,
+        load: F,
+    ) -> anyhow::Result<Option<DMat4>>
+    where
+        F: Future<Output = anyhow::Result<Option<DMat4>>>,
+    {
+        let Some(store) = disk_store().filter(|_| config().world_trans) else {
+            return load.await;
+        };
+        let Some(key) = world_key(refno).await? else {
+            return load.await;
+        };
+        if let Ok(Some(bytes)) = store.get(WORLD_TRANS, key)
+            && let Some(mat) = mat4_from_bytes(&bytes)
+        {
+            return Ok(Some(mat));
+        }
+        let mat = load.await?;
+        if let Some(m) = &mat {
+            let write = store
+                .put(WORLD_TRANS, key, &mat4_to_bytes(m))
+                .and_then(|_| store.purge_older(WORLD_TRANS, key));
+            if let Err(e) = write {
+                log::debug!("写入世界变换磁盘缓存失败 {}: {}", refno, e);
+            }
+        }
+        Ok(mat)
+    }
+
+    pub fn clear_disk_cache() -> anyhow::Result<()> {
+        DB_SESNOS.clear();
+        match disk_store() {
+            Some(store) => store.clear(),
+            None => Ok(()),
+        }
+    }
+
+    /// 数据库有新会话时调用，使世界变换按新的 sesno 取键
+    pub fn refresh_sessions() {
+        DB_SESNOS.clear();
+    }
+
+    /// 删除这些参考号缓存的世界变换（不论 sesno）
+    pub fn invalidate_world_trans(refnos: &[RefnoEnum]) {
+        let Some(store) = disk_store() else {
+            return;
+        };
+        for refno in refnos {
+            if let Err(e) = store.remove_refno(WORLD_TRANS, refno.refno().0) {
+                log::warn!("删除世界变换磁盘缓存失败 {}: {}", refno, e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redb")]
+pub use active::{
+    clear_disk_cache, invalidate_world_trans, read_through_attmap, read_through_world_mat4,
+    refresh_sessions,
+};
+
+/// 未启用 `redb` 特性时直接读取数据库
+#[cfg(not(feature = "redb"))]
+pub async fn read_through_attmap<F>(_refno: RefnoEnum, load: F) -> anyhow::Result<NamedAttrMap>
+where
+    F: Future<Output = anyhow::Result<NamedAttrMap>>,
+{
+    load.await
+}
+
+#[cfg(not(feature = "redb"))]
+pub async fn read_through_world_mat4<F>(_refno: RefnoEnum, load: F) -> anyhow::Result<Option<DMat4>>
+where
+    F: Future<Output = anyhow::Result<Option<DMat4>>>,
+{
+    load.await
+}
+
+#[cfg(not(feature = "redb"))]
+pub fn clear_disk_cache() -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(feature = "redb"))]
+pub fn refresh_sessions() {}
+
+#[cfg(not(feature = "redb"))]
+pub fn invalidate_world_trans(_refnos: &[RefnoEnum]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mat4_bytes_roundtrip() {
+        let m = DMat4::from_cols_array(&std::array::from_fn(|i| i as f64 * 0.5 - 3.0));
+        assert_eq!(mat4_from_bytes(&mat4_to_bytes(&m)), Some(m));
+        assert_eq!(mat4_from_bytes(&[0u8; 10]), None);
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: DiskCacheConfig = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+        assert!(config.enabled && config.attmaps && config.world_trans);
+        assert_eq!(config.path, "cache/attr_cache.redb");
+    }
+
+    #[cfg(feature = "redb")]
+    #[test]
+    fn test_store_session_invalidation() {
+        use super::store::{ATTMAPS, DiskStore};
+        let dir = std::env::temp_dir().join(format!("aios_disk_cache_{}", std::process::id()));
+        let store = DiskStore::open(&dir.join("cache.redb")).unwrap();
+        let old = SessionKey { refno: 7, sesno: 1 };
+        let new = SessionKey { refno: 7, sesno: 2 };
+        store.put(ATTMAPS, old, b"old").unwrap();
+        store.put(ATTMAPS, new, b"new").unwrap();
+        store.purge_older(ATTMAPS, new).unwrap();
+        assert_eq!(store.get(ATTMAPS, old).unwrap(), None);
+        assert_eq!(
+            store.get(ATTMAPS, new).unwrap().as_deref(),
+            Some(&b"new"[..])
+        );
+        store.put(ATTMAPS, SessionKey { refno: 8, sesno: 2 }, b"other").unwrap();
+        store.remove_refno(ATTMAPS, 7).unwrap();
+        assert_eq!(store.get(ATTMAPS, new).unwrap(), None);
+        assert!(store.get(ATTMAPS, SessionKey { refno: 8, sesno: 2 }).unwrap().is_some());
+        drop(store);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,135 @@
+//! 进程内属性缓存
+//!
+//! [`ATTR_CACHES`] 集中管理 pe、属性、元件库属性等常用读取的缓存，
+//! 每类缓存都有容量和过期时间上限，并记录命中统计。
+//! 数据变更后调用 [`invalidate_refno`] 统一失效（`clear_all_caches` 即委托给它），
+//! 其中也会清除仍由 `#[cached]` 宏管理的其它查询缓存。
+//! 跨进程的磁盘缓存见 [`disk`]，按会话号失效，不受 [`invalidate_refno`] 影响。
+
+pub mod attr_cache;
+pub mod disk;
+
+pub use attr_cache::{AttrCache, AttrCacheConfig, CacheStats};
+pub use disk::{DiskCacheConfig, clear_disk_cache};
+
+use crate::graph::QUERY_DEEP_CHILDREN_REFNOS;
+use crate::pe::SPdmsElement;
+use crate::rs_surreal::query::{
+    GET_CAT_REFNO, GET_CHILDREN_PES, GET_CHILDREN_REFNOS, GET_NAMED_ATTMAP_WITH_UDA, GET_SIBLINGS,
+    GET_TYPE_NAME, QUERY_ANCESTOR_REFNOS,
+};
+use crate::{NamedAttrMap, RefnoEnum};
+use cached::Cached;
+use once_cell::sync::Lazy;
+
+/// 各类属性缓存
+pub struct AttrCaches {
+    pub pes: AttrCache<RefnoEnum, Option<SPdmsElement>>,
+    pub attmaps: AttrCache<RefnoEnum, NamedAttrMap>,
+    pub cat_attmaps: AttrCache<RefnoEnum, NamedAttrMap>,
+    pub children_attmaps: AttrCache<RefnoEnum, Vec<NamedAttrMap>>,
+}
+
+impl AttrCaches {
+    pub fn new(config: AttrCacheConfig) -> Self {
+        let children = AttrCacheConfig {
+            max_capacity: config.max_capacity / 2,
+            ..config
+        };
+        Self {
+            pes: AttrCache::new("pe", config),
+            attmaps: AttrCache::new("attmap", config),
+            cat_attmaps: AttrCache::new("cat_attmap", config),
+            children_attmaps: AttrCache::new("children_attmaps", children),
+        }
+    }
+
+    /// 统一修改各类缓存的配置
+    pub fn configure(&self, config: AttrCacheConfig) {
+        self.pes.set_config(config);
+        self.attmaps.set_config(config);
+        self.cat_attmaps.set_config(config);
+        self.children_attmaps.set_config(AttrCacheConfig {
+            max_capacity: config.max_capacity / 2,
+            ..config
+        });
+    }
+
+    /// 各类缓存的统计
+    pub fn stats(&self) -> Vec<(&'static str, CacheStats)> {
+        vec![
+            (self.pes.name(), self.pes.stats()),
+            (self.attmaps.name(), self.attmaps.stats()),
+            (self.cat_attmaps.name(), self.cat_attmaps.stats()),
+            (self.children_attmaps.name(), self.children_attmaps.stats()),
+        ]
+    }
+
+    pub fn reset_stats(&self) {
+        self.pes.reset_stats();
+        self.attmaps.reset_stats();
+        self.cat_attmaps.reset_stats();
+        self.children_attmaps.reset_stats();
+    }
+
+    fn invalidate(&self, refno: &RefnoEnum) {
+        self.pes.invalidate(refno);
+        self.attmaps.invalidate(refno);
+        self.cat_attmaps.invalidate(refno);
+        self.children_attmaps.invalidate(refno);
+    }
+
+    fn invalidate_all(&self) {
+        self.pes.invalidate_all();
+        self.attmaps.invalidate_all();
+        self.cat_attmaps.invalidate_all();
+        self.children_attmaps.invalidate_all();
+    }
+}
+
+impl Default for AttrCaches {
+    fn default() -> Self {
+        Self::new(AttrCacheConfig::default())
+    }
+}
+
+pub static ATTR_CACHES: Lazy<AttrCaches> = Lazy::new(AttrCaches::default);
+
+/// 失效某个参考号的所有缓存
+pub async fn invalidate_refno(refno: RefnoEnum) {
+    ATTR_CACHES.invalidate(&refno);
+    if let Err(e) = crate::rs_surreal::tiered_cache::TIERED_CACHE
+        .invalidate(refno)
+        .await
+    {
+        log::warn!("KV 缓存失效失败 {}: {}", refno, e);
+    }
+
+    QUERY_ANCESTOR_REFNOS.lock().await.cache_remove(&refno);
+    QUERY_DEEP_CHILDREN_REFNOS.lock().await.cache_remove(&refno);
+    GET_TYPE_NAME.lock().await.cache_remove(&refno);
+    GET_SIBLINGS.lock().await.cache_remove(&refno);
+    GET_NAMED_ATTMAP_WITH_UDA.lock().await.cache_remove(&refno);
+    GET_CHILDREN_REFNOS.lock().await.cache_remove(&refno);
+    GET_CAT_REFNO.lock().await.cache_remove(&refno);
+    GET_CHILDREN_PES.lock().await.cache_remove(&refno);
+
+    crate::transform::strategies::invalidate_endatu_index(refno);
+}
+
+/// 清空所有属性缓存
+pub async fn invalidate_all() {
+    ATTR_CACHES.invalidate_all();
+    if let Err(e) = crate::rs_surreal::tiered_cache::TIERED_CACHE.clear().await {
+        log::warn!("KV 缓存清空失败: {}", e);
+    }
+
+    QUERY_ANCESTOR_REFNOS.lock().await.cache_clear();
+    QUERY_DEEP_CHILDREN_REFNOS.lock().await.cache_clear();
+    GET_TYPE_NAME.lock().await.cache_clear();
+    GET_SIBLINGS.lock().await.cache_clear();
+    GET_NAMED_ATTMAP_WITH_UDA.lock().await.cache_clear();
+    GET_CHILDREN_REFNOS.lock().await.cache_clear();
+    GET_CAT_REFNO.lock().await.cache_clear();
+    GET_CHILDREN_PES.lock().await.cache_clear();
+}
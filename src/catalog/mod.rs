@@ -0,0 +1,12 @@
+//! 元件库（CATA / SPEC）
+//!
+//! 规格选择，规格组件、元件的参数求值与缓存。
+
+pub mod resolve;
+pub mod spec;
+
+pub use resolve::{
+    DesignParams, DetailText, MaterialText, ResolvedCatalogItem, clear_catalog_cache,
+    resolve_component, resolve_design_component,
+};
+pub use spec::{SpecAnswer, SpecCandidate, SpecQuestion, spec_select};
@@ -0,0 +1,350 @@
+//! 元件库组件求值
+//!
+//! 由规格组件（SPCO）或元件（SCOM）加一组设计参数，沿 SCOM → GMSE（几何集）、
+//! NGMR（负实体集）、PTSE（点集）、PLIN（形集）、DTSE（数据集）求出几何参数
+//! 和 p 点，再带上 SPCO 的详细描述（DETR → SDTE）和材料描述（MATX → SMTE）。
+//!
+//! SCOM 的结构只读一次，缓存在 [`SCOM_INFO_MAP`]；求值结果按
+//! （SCOM，设计参数哈希）缓存，同一规格下同一尺寸的组件只算一次。
+
+use crate::expression::query_cata::{query_axis_params, query_gm_param, resolve_cata_comp};
+use crate::expression::resolve::SCOM_INFO_MAP;
+use crate::parsed_data::{CateAxisParam, CateGeomsInfo};
+use crate::pdms_data::{AxisParam, PlinParam, ScomInfo};
+use crate::{
+    CataContext, DDANGLE_STR, DDHEIGHT_STR, DDRADIUS_STR, NamedAttrMap, RefnoEnum,
+    get_children_named_attmaps, get_named_attmap,
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// 求值结果缓存，键为（SCOM，设计参数哈希）
+static RESOLVED_CACHE: Lazy<DashMap<(RefnoEnum, u64), Arc<CateGeomsInfo>>> =
+    Lazy::new(DashMap::new);
+
+/// 设计参数
+///
+/// `desp` 对应设计元素的 DESP，`attrs` 为表达式中可能引用的其它设计属性
+/// （如 `DDHEIGHT`、`JUSL`），键为大写属性名。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DesignParams {
+    pub desp: Vec<f32>,
+    pub attrs: BTreeMap<String, String>,
+}
+
+impl DesignParams {
+    pub fn new(desp: Vec<f32>) -> Self {
+        Self {
+            desp,
+            attrs: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_attr(mut self, key: impl AsRef<str>, value: impl ToString) -> Self {
+        self.attrs
+            .insert(key.as_ref().to_uppercase(), value.to_string());
+        self
+    }
+
+    /// 取设计元素上参与元件库求值的属性
+    pub fn from_attmap(att: &NamedAttrMap) -> Self {
+        let mut params = Self::new(att.get_f32_vec("DESP").unwrap_or_default());
+        for (att_name, key) in [
+            ("HEIG", DDHEIGHT_STR),
+            ("ANGL", DDANGLE_STR),
+            ("RADI", DDRADIUS_STR),
+            ("JUSL", "JUSL"),
+        ] {
+            if let Some(v) = att.get_as_string(att_name) {
+                params.attrs.insert(key.to_string(), v);
+            }
+        }
+        params
+    }
+
+    /// 参数哈希，浮点数按位参与
+    pub fn cache_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for d in &self.desp {
+            d.to_bits().hash(&mut hasher);
+        }
+        self.attrs.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// 详细描述（SDTE）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetailText {
+    pub refno: RefnoEnum,
+    pub skey: String,
+    pub rtext: String,
+    pub stext: String,
+    pub ttext: String,
+}
+
+/// 材料描述（SMTE）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaterialText {
+    pub refno: RefnoEnum,
+    pub xtext: String,
+    pub ytext: String,
+    pub ztext: String,
+}
+
+/// 求值后的元件库组件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResolvedCatalogItem {
+    /// 规格组件，直接由 SCOM 求值时为 None
+    pub spco: Option<RefnoEnum>,
+    pub scom: RefnoEnum,
+    /// 元件类型（GTYP）
+    pub gtype: String,
+    /// 元件参数（PARA）
+    pub params: Vec<f32>,
+    /// 几何体、负实体和 p 点
+    pub geometry: Arc<CateGeomsInfo>,
+    pub detail: Option<DetailText>,
+    pub material: Option<MaterialText>,
+}
+
+impl ResolvedCatalogItem {
+    /// 按编号取 p 点（元件坐标系）
+    pub fn ppoint(&self, number: i32) -> Option<&CateAxisParam> {
+        self.geometry.axis_map.get(&number)
+    }
+
+    pub fn ppoints(&self) -> &BTreeMap<i32, CateAxisParam> {
+        &self.geometry.axis_map
+    }
+}
+
+/// 取引用属性，未设置（空引用）时为 None
+fn foreign_refno(att: &NamedAttrMap, key: &str) -> Option<RefnoEnum> {
+    att.get_foreign_refno(key).filter(|r| r.is_valid())
+}
+
+/// 读取 SCOM 的几何集、点集、形集
+pub async fn load_scom_info(scom: RefnoEnum) -> anyhow::Result<ScomInfo> {
+    if let Some(info) = SCOM_INFO_MAP.get(&scom) {
+        return Ok(info.clone());
+    }
+    let attr_map = get_named_attmap(scom).await?;
+    let mut gm_params = vec![];
+    if let Some(gmre) = foreign_refno(&attr_map, "GMRE") {
+        for child in get_children_named_attmaps(gmre).await? {
+            gm_params.extend(query_gm_param(&child, true).await);
+        }
+    }
+    let mut ngm_params = vec![];
+    if let Some(ngmr) = foreign_refno(&attr_map, "NGMR") {
+        for child in get_children_named_attmaps(ngmr).await? {
+            ngm_params.extend(query_gm_param(&child, true).await);
+        }
+    }
+    let (axis_param_numbers, axis_params): (Vec<i32>, Vec<AxisParam>) =
+        match foreign_refno(&attr_map, "PTRE") {
+            Some(ptre) => query_axis_params(ptre).await?.into_iter().unzip(),
+            None => (vec![], vec![]),
+        };
+    let mut plin_map = HashMap::new();
+    let pstr = foreign_refno(&attr_map, "PSTR").or(foreign_refno(&attr_map, "PTSS"));
+    if let Some(pstr) = pstr {
+        for a in get_children_named_attmaps(pstr).await? {
+            let Some(p_key) = a.get_as_string("PKEY") else {
+                continue;
+            };
+            plin_map.insert(
+                p_key,
+                PlinParam {
+                    vxy: [
+                        a.get_as_string("PX").unwrap_or("0".to_string()),
+                        a.get_as_string("PY").unwrap_or("0".to_string()),
+                    ],
+                    dxy: [
+                        a.get_as_string("DX").unwrap_or("0".to_string()),
+                        a.get_as_string("DY").unwrap_or("0".to_string()),
+                    ],
+                    plax: a.get_as_string("PLAX").unwrap_or("unset".to_string()),
+                },
+            );
+        }
+    }
+    let info = ScomInfo {
+        gtype: attr_map.get_as_string("GTYP").unwrap_or_default(),
+        params: attr_map.get_as_string("PARA").unwrap_or_default(),
+        attr_map,
+        gm_params,
+        ngm_params,
+        axis_params,
+        axis_param_numbers,
+        plin_map,
+        ..Default::default()
+    };
+    SCOM_INFO_MAP.insert(scom, info.clone());
+    Ok(info)
+}
+
+/// 由 SCOM 属性和设计参数建立求值上下文，变量命名同
+/// [`crate::get_or_create_cata_context`]
+pub async fn build_cata_context(
+    scom_att: &NamedAttrMap,
+    params: &DesignParams,
+) -> anyhow::Result<CataContext> {
+    let context = CataContext::default();
+    for key in [DDHEIGHT_STR, DDANGLE_STR, DDRADIUS_STR] {
+        context.insert(key, "0.0");
+    }
+    for (i, d) in params.desp.iter().enumerate() {
+        context.insert(format!("DESI{}", i + 1), d.to_string());
+        context.insert(format!("DESP{}", i + 1), d.to_string());
+    }
+    for (k, v) in &params.attrs {
+        context.insert(k.clone(), v.clone());
+    }
+
+    context.insert("RS_CATR_REFNO", scom_att.get_refno_or_default().to_string());
+    let cpars = scom_att.get_f32_vec("PARA").unwrap_or_default();
+    for (i, p) in cpars.iter().enumerate() {
+        context.insert(format!("CPAR{}", i + 1), p.to_string());
+        context.insert(format!("PARA{}", i + 1), p.to_string());
+        context.insert(format!("PARAM{}", i + 1), p.to_string());
+        context.insert(format!("IPARA{}", i + 1), "0");
+        context.insert(format!("IPAR{}", i + 1), "0");
+    }
+
+    if let Some(dtre) = foreign_refno(scom_att, "DTRE") {
+        for child in get_children_named_attmaps(dtre).await? {
+            let Some(k) = child.get_as_string("DKEY") else {
+                continue;
+            };
+            let key = format!("RPRO_{}", &k);
+            context.insert(
+                format!("{key}_default_expr"),
+                child.get_as_string("DPRO").unwrap_or_default(),
+            );
+            context.insert(
+                format!("{key}_default_type"),
+                child.get_as_string("PTYP").unwrap_or_default(),
+            );
+            context.insert(key, child.get_as_string("PPRO").unwrap_or_default());
+        }
+    }
+    Ok(context)
+}
+
+async fn query_detail_text(spco_att: &NamedAttrMap) -> anyhow::Result<Option<DetailText>> {
+    let Some(detr) = foreign_refno(spco_att, "DETR") else {
+        return Ok(None);
+    };
+    let att = get_named_attmap(detr).await?;
+    Ok(Some(DetailText {
+        refno: detr,
+        skey: att.get_as_string("SKEY").unwrap_or_default(),
+        rtext: att.get_as_string("RTEX").unwrap_or_default(),
+        stext: att.get_as_string("STEX").unwrap_or_default(),
+        ttext: att.get_as_string("TTEX").unwrap_or_default(),
+    }))
+}
+
+async fn query_material_text(spco_att: &NamedAttrMap) -> anyhow::Result<Option<MaterialText>> {
+    let Some(matx) = foreign_refno(spco_att, "MATX") else {
+        return Ok(None);
+    };
+    let att = get_named_attmap(matx).await?;
+    Ok(Some(MaterialText {
+        refno: matx,
+        xtext: att.get_as_string("XTEX").unwrap_or_default(),
+        ytext: att.get_as_string("YTEX").unwrap_or_default(),
+        ztext: att.get_as_string("ZTEX").unwrap_or_default(),
+    }))
+}
+
+/// 求值元件库组件
+///
+/// `spre_refno` 可以是规格组件（取其 CATR 指向的元件），也可以直接是元件。
+pub async fn resolve_component(
+    spre_refno: RefnoEnum,
+    params: &DesignParams,
+) -> anyhow::Result<ResolvedCatalogItem> {
+    let spre_att = get_named_attmap(spre_refno).await?;
+    let (spco_att, scom) = if spre_att.get_type_str() == "SPCO" {
+        let scom = foreign_refno(&spre_att, "CATR")
+            .ok_or_else(|| anyhow::anyhow!("规格组件 {} 没有 CATR", spre_refno))?;
+        (Some(spre_att), scom)
+    } else {
+        (None, spre_refno)
+    };
+
+    let scom_info = load_scom_info(scom).await?;
+    let key = (scom, params.cache_key());
+    let geometry = match RESOLVED_CACHE.get(&key) {
+        Some(g) => g.clone(),
+        None => {
+            let context = build_cata_context(&scom_info.attr_map, params).await?;
+            let geometry = Arc::new(resolve_cata_comp(
+                &NamedAttrMap::default(),
+                &scom_info,
+                Some(context),
+            )?);
+            RESOLVED_CACHE.insert(key, geometry.clone());
+            geometry
+        }
+    };
+
+    let (detail, material) = match &spco_att {
+        Some(att) => (
+            query_detail_text(att).await?,
+            query_material_text(att).await?,
+        ),
+        None => (None, None),
+    };
+    Ok(ResolvedCatalogItem {
+        spco: spco_att.as_ref().and_then(|a| a.get_refno()),
+        scom,
+        gtype: scom_info.gtype.clone(),
+        params: scom_info.attr_map.get_f32_vec("PARA").unwrap_or_default(),
+        geometry,
+        detail,
+        material,
+    })
+}
+
+/// 按设计元素的 SPRE 和设计参数求值
+pub async fn resolve_design_component(
+    desi_refno: RefnoEnum,
+) -> anyhow::Result<ResolvedCatalogItem> {
+    let att = get_named_attmap(desi_refno).await?;
+    let spre =
+        foreign_refno(&att, "SPRE").ok_or_else(|| anyhow::anyhow!("{} 没有 SPRE", desi_refno))?;
+    resolve_component(spre, &DesignParams::from_attmap(&att)).await
+}
+
+/// 清空缓存，元件库数据更新后调用
+pub fn clear_catalog_cache() {
+    RESOLVED_CACHE.clear();
+    SCOM_INFO_MAP.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_design_params_cache_key() {
+        let a = DesignParams::new(vec![100.0, 50.0]).with_attr("jusl", "TOS");
+        let b = DesignParams::new(vec![100.0, 50.0]).with_attr("JUSL", "TOS");
+        assert_eq!(a, b);
+        assert_eq!(a.cache_key(), b.cache_key());
+
+        let c = DesignParams::new(vec![100.0, 50.5]).with_attr("JUSL", "TOS");
+        assert_ne!(a.cache_key(), c.cache_key());
+        let d = DesignParams::new(vec![100.0, 50.0]);
+        assert_ne!(a.cache_key(), d.cache_key());
+    }
+}
@@ -0,0 +1,351 @@
+//! 规格（SPEC）选择
+//!
+//! 规格是一棵问答树：SPEC 和每层 SELE 用 `QUES` 提出一个问题（如 `TYPE`、
+//! `PBOR0`、`STYP`），其子节点用 `TANS`（文字）或 `ANSW`/`MAXA`（数值或
+//! 数值区间）作答，叶子为 SPCO。选择时逐层用提问条件回答问题，条件里没有
+//! 的问题先取该层的默认答案（`TDEF`/`DEFA`），仍没有时该层不做筛选，所有
+//! 分支都作为候选。
+
+use crate::{NamedAttrMap, RefnoEnum, get_children_named_attmaps, get_named_attmap};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 数值答案的比较容差
+const ANSWER_TOLERANCE: f32 = 1e-3;
+
+/// 问题的答案
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SpecAnswer {
+    Text(String),
+    Number(f32),
+}
+
+impl std::fmt::Display for SpecAnswer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecAnswer::Text(s) => write!(f, "{s}"),
+            SpecAnswer::Number(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// 选择条件，键为问题名（大写）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpecQuestion {
+    pub answers: BTreeMap<String, SpecAnswer>,
+}
+
+impl SpecQuestion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, question: impl AsRef<str>, answer: SpecAnswer) -> Self {
+        self.answers
+            .insert(question.as_ref().to_uppercase(), answer);
+        self
+    }
+
+    pub fn with_text(self, question: impl AsRef<str>, answer: impl Into<String>) -> Self {
+        self.with(question, SpecAnswer::Text(answer.into()))
+    }
+
+    pub fn with_number(self, question: impl AsRef<str>, answer: f32) -> Self {
+        self.with(question, SpecAnswer::Number(answer))
+    }
+
+    /// 组件类型，如 `ELBO`、`VALV`
+    pub fn component_type(self, ty: impl Into<String>) -> Self {
+        self.with_text("TYPE", ty)
+    }
+
+    /// 主管径（PBOR0）
+    pub fn bore(self, bore: f32) -> Self {
+        self.with_number("PBOR0", bore)
+    }
+
+    pub fn stype(self, stype: impl Into<String>) -> Self {
+        self.with_text("STYP", stype)
+    }
+
+    pub fn rating(self, rating: impl Into<String>) -> Self {
+        self.with_text("RATI", rating)
+    }
+
+    /// 取问题的答案，`PBORn` 没有给出时用主管径
+    pub fn answer(&self, question: &str) -> Option<&SpecAnswer> {
+        let question = question.to_uppercase();
+        self.answers.get(&question).or_else(|| {
+            question
+                .starts_with("PBOR")
+                .then(|| self.answers.get("PBOR0"))
+                .flatten()
+        })
+    }
+}
+
+/// 节点对上层问题的回答
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NodeAnswer {
+    Text(String),
+    /// 数值区间 [min, max]，单值时 min == max
+    Range(f32, f32),
+}
+
+impl NodeAnswer {
+    pub fn matches(&self, answer: &SpecAnswer) -> bool {
+        match (self, answer) {
+            (NodeAnswer::Text(t), SpecAnswer::Text(a)) => t.trim().eq_ignore_ascii_case(a.trim()),
+            (NodeAnswer::Text(t), SpecAnswer::Number(v)) => t
+                .trim()
+                .parse::<f32>()
+                .is_ok_and(|t| (t - v).abs() <= ANSWER_TOLERANCE),
+            (NodeAnswer::Range(min, max), SpecAnswer::Number(v)) => {
+                *v >= min - ANSWER_TOLERANCE && *v <= max + ANSWER_TOLERANCE
+            }
+            (NodeAnswer::Range(min, max), SpecAnswer::Text(t)) => t
+                .trim()
+                .parse::<f32>()
+                .is_ok_and(|v| v >= min - ANSWER_TOLERANCE && v <= max + ANSWER_TOLERANCE),
+        }
+    }
+}
+
+impl std::fmt::Display for NodeAnswer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeAnswer::Text(s) => write!(f, "{s}"),
+            NodeAnswer::Range(min, max) if min == max => write!(f, "{min}"),
+            NodeAnswer::Range(min, max) => write!(f, "{min}-{max}"),
+        }
+    }
+}
+
+/// 规格树中的节点（SPEC / SELE / SPCO）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpecNode {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub name: String,
+    /// 本层提出的问题
+    pub question: Option<String>,
+    /// 本层问题的默认答案
+    pub default: Option<SpecAnswer>,
+    /// 对上层问题的回答
+    pub answer: Option<NodeAnswer>,
+    /// SPCO 的元件（CATR）
+    pub catref: Option<RefnoEnum>,
+    /// SPCO 的详细描述（DETR）
+    pub detref: Option<RefnoEnum>,
+    /// SPCO 的材料描述（MATX）
+    pub matref: Option<RefnoEnum>,
+    pub children: Vec<SpecNode>,
+}
+
+impl SpecNode {
+    /// 由属性构建节点，不含子节点
+    pub fn from_attmap(att: &NamedAttrMap) -> Self {
+        let text = |key: &str| att.get_as_string(key).filter(|s| !s.trim().is_empty());
+        let foreign = |key: &str| att.get_foreign_refno(key).filter(|r| r.is_valid());
+
+        let question = text("QUES").or_else(|| text("TQUES"));
+        let default = text("TDEF").map(SpecAnswer::Text).or_else(|| {
+            att.get_f32("DEFA")
+                .filter(|v| *v != 0.0)
+                .map(SpecAnswer::Number)
+        });
+        let answer = text("TANS").map(NodeAnswer::Text).or_else(|| {
+            let min = att.get_f32("ANSW")?;
+            let max = att.get_f32("MAXA").filter(|m| *m > min).unwrap_or(min);
+            Some(NodeAnswer::Range(min, max))
+        });
+        Self {
+            refno: att.get_refno_or_default(),
+            noun: att.get_type_str().to_string(),
+            name: att.get_name_or_default(),
+            question,
+            default,
+            answer,
+            catref: foreign("CATR"),
+            detref: foreign("DETR"),
+            matref: foreign("MATX"),
+            children: vec![],
+        }
+    }
+
+    pub fn is_component(&self) -> bool {
+        self.noun == "SPCO"
+    }
+}
+
+/// 选出的候选规格组件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecCandidate {
+    pub spco: RefnoEnum,
+    pub name: String,
+    pub scom: Option<RefnoEnum>,
+    pub detail: Option<RefnoEnum>,
+    pub material: Option<RefnoEnum>,
+    /// 从 SPEC 到 SPCO 一路的（问题，答案）
+    pub path: Vec<(String, String)>,
+}
+
+/// 读取整棵规格树
+pub async fn load_spec_tree(spec_refno: RefnoEnum) -> anyhow::Result<SpecNode> {
+    let mut root = SpecNode::from_attmap(&get_named_attmap(spec_refno).await?);
+    // 逐层展开，栈中存从根到节点的子节点下标路径
+    let mut stack: Vec<Vec<usize>> = vec![vec![]];
+    while let Some(path) = stack.pop() {
+        let node = path.iter().fold(&mut root, |n, &i| &mut n.children[i]);
+        if node.is_component() {
+            continue;
+        }
+        node.children = get_children_named_attmaps(node.refno)
+            .await?
+            .iter()
+            .map(SpecNode::from_attmap)
+            .filter(|c| matches!(c.noun.as_str(), "SELE" | "SPCO"))
+            .collect();
+        for i in 0..node.children.len() {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            stack.push(child_path);
+        }
+    }
+    Ok(root)
+}
+
+/// 在已加载的规格树中选择
+pub fn select_in(root: &SpecNode, question: &SpecQuestion) -> Vec<SpecCandidate> {
+    let mut result = vec![];
+    let mut path = vec![];
+    select_node(root, question, &mut path, &mut result);
+    result
+}
+
+fn select_node(
+    node: &SpecNode,
+    question: &SpecQuestion,
+    path: &mut Vec<(String, String)>,
+    result: &mut Vec<SpecCandidate>,
+) {
+    if node.is_component() {
+        result.push(SpecCandidate {
+            spco: node.refno,
+            name: node.name.clone(),
+            scom: node.catref,
+            detail: node.detref,
+            material: node.matref,
+            path: path.clone(),
+        });
+        return;
+    }
+    let ques = node.question.as_deref().unwrap_or_default();
+    let answer = question.answer(ques).or(node.default.as_ref());
+    for child in &node.children {
+        if let (Some(answer), Some(child_answer)) = (answer, &child.answer)
+            && !child_answer.matches(answer)
+        {
+            continue;
+        }
+        let step = child
+            .answer
+            .as_ref()
+            .filter(|_| !ques.is_empty())
+            .map(|a| (ques.to_string(), a.to_string()));
+        let pushed = step.is_some();
+        path.extend(step);
+        select_node(child, question, path, result);
+        if pushed {
+            path.pop();
+        }
+    }
+}
+
+/// 按提问条件在规格中选择规格组件
+///
+/// 返回所有满足条件的 SPCO，条件不足以唯一确定时会有多个候选。
+pub async fn spec_select(
+    spec_refno: RefnoEnum,
+    question: &SpecQuestion,
+) -> anyhow::Result<Vec<SpecCandidate>> {
+    let tree = load_spec_tree(spec_refno).await?;
+    Ok(select_in(&tree, question))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn node(n: u32, noun: &str, question: Option<&str>, answer: Option<NodeAnswer>) -> SpecNode {
+        SpecNode {
+            refno: RefU64::from_two_nums(1, n).into(),
+            noun: noun.into(),
+            name: format!("N{n}"),
+            question: question.map(String::from),
+            answer,
+            ..Default::default()
+        }
+    }
+
+    fn spec() -> SpecNode {
+        let text = |s: &str| Some(NodeAnswer::Text(s.into()));
+        let mut elbo = node(2, "SELE", Some("PBOR0"), text("ELBO"));
+        elbo.children = vec![
+            node(3, "SPCO", None, Some(NodeAnswer::Range(15.0, 50.0))),
+            node(4, "SPCO", None, Some(NodeAnswer::Range(80.0, 80.0))),
+            node(5, "SPCO", None, Some(NodeAnswer::Range(100.0, 100.0))),
+        ];
+        let mut valv = node(6, "SELE", Some("PBOR0"), text("VALV"));
+        let mut valv_100 = node(
+            7,
+            "SELE",
+            Some("STYP"),
+            Some(NodeAnswer::Range(100.0, 100.0)),
+        );
+        valv_100.default = Some(SpecAnswer::Text("GATE".into()));
+        valv_100.children = vec![
+            node(8, "SPCO", None, text("GATE")),
+            node(9, "SPCO", None, text("GLOB")),
+        ];
+        valv.children = vec![valv_100];
+        let mut root = node(1, "SPEC", Some("TYPE"), None);
+        root.children = vec![elbo, valv];
+        root
+    }
+
+    fn selected(question: &SpecQuestion) -> Vec<u32> {
+        select_in(&spec(), question)
+            .iter()
+            .map(|c| c.spco.refno().get_1())
+            .collect()
+    }
+
+    #[test]
+    fn test_spec_select() {
+        let q = SpecQuestion::new().component_type("elbo").bore(25.0);
+        assert_eq!(selected(&q), vec![3]);
+        let q = SpecQuestion::new().component_type("ELBO").bore(100.0);
+        assert_eq!(selected(&q), vec![5]);
+        // 没有给出管径时所有弯头都是候选
+        let q = SpecQuestion::new().component_type("ELBO");
+        assert_eq!(selected(&q), vec![3, 4, 5]);
+        // STYP 未给出时取默认答案
+        let q = SpecQuestion::new().component_type("VALV").bore(100.0);
+        assert_eq!(selected(&q), vec![8]);
+        let q = q.stype("GLOB");
+        let candidates = select_in(&spec(), &q);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].path,
+            vec![
+                ("TYPE".to_string(), "VALV".to_string()),
+                ("PBOR0".to_string(), "100".to_string()),
+                ("STYP".to_string(), "GLOB".to_string()),
+            ]
+        );
+        let q = SpecQuestion::new().component_type("TEE");
+        assert!(selected(&q).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use super::ConfigError;
+use crate::cache::disk::DiskCacheConfig;
+use crate::mesh_precision::MeshPrecisionSettings;
+use crate::options::{DbOption, ProjectDbOption};
+
+/// 以代码方式构造 [`DbOption`]
+///
+/// ```no_run
+/// use aios_core::config::{DbOptionBuilder, set_db_option};
+///
+/// let option = DbOptionBuilder::new()
+///     .surreal("127.0.0.1", 8020)
+///     .credentials("root", "root")
+///     .namespace("1516")
+///     .project("AvevaMarineSample", "1516")
+///     .build()
+///     .unwrap();
+/// set_db_option(option).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DbOptionBuilder {
+    option: DbOption,
+}
+
+impl Default for DbOptionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DbOptionBuilder {
+    /// 从缺省值开始（取 DbOption.toml 模板中的常用值）
+    pub fn new() -> Self {
+        Self::from_option(DbOption {
+            sync_versioned: Some(true),
+            export_parquet: true,
+            mdb_name: "ALL".to_string(),
+            module: "DESI".to_string(),
+            mem_kv_ip: "localhost".to_string(),
+            mem_kv_port: "8011".to_string(),
+            mem_kv_user: "root".to_string(),
+            mem_kv_password: "root".to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// 在已有配置（例如从文件读取的）基础上修改
+    pub fn from_option(option: DbOption) -> Self {
+        Self { option }
+    }
+
+    /// 版本库地址
+    pub fn surreal(mut self, ip: impl Into<String>, port: u16) -> Self {
+        self.option.v_ip = ip.into();
+        self.option.v_port = port;
+        self
+    }
+
+    pub fn credentials(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.option.v_user = user.into();
+        self.option.v_password = password.into();
+        self
+    }
+
+    pub fn namespace(mut self, ns: impl Into<String>) -> Self {
+        self.option.surreal_ns = ns.into();
+        self
+    }
+
+    pub fn project(mut self, name: impl Into<String>, code: impl Into<String>) -> Self {
+        self.option.project_name = name.into();
+        self.option.project_code = code.into();
+        self
+    }
+
+    pub fn mdb(mut self, mdb_name: impl Into<String>, module: impl Into<String>) -> Self {
+        self.option.mdb_name = mdb_name.into();
+        self.option.module = module.into();
+        self
+    }
+
+    pub fn included_projects(mut self, projects: Vec<String>) -> Self {
+        self.option.included_projects = projects;
+        self
+    }
+
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.option.surreal_pool_size = Some(size);
+        self
+    }
+
+    pub fn mesh_precision(mut self, precision: MeshPrecisionSettings) -> Self {
+        self.option.mesh_precision = precision;
+        self
+    }
+
+    pub fn disk_cache(mut self, disk_cache: DiskCacheConfig) -> Self {
+        self.option.disk_cache = disk_cache;
+        self
+    }
+
+    /// 追加一个项目连接，见 `with_project`
+    pub fn add_project(mut self, project: ProjectDbOption) -> Self {
+        self.option.projects.push(project);
+        self
+    }
+
+    /// 修改构造器未覆盖的字段
+    pub fn with(mut self, f: impl FnOnce(&mut DbOption)) -> Self {
+        f(&mut self.option);
+        self
+    }
+
+    /// 校验并生成配置
+    pub fn build(self) -> Result<DbOption, ConfigError> {
+        self.option.validate()?;
+        Ok(self.option)
+    }
+
+    /// 不校验直接生成
+    pub fn build_unchecked(self) -> DbOption {
+        self.option
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let option = DbOptionBuilder::new()
+            .surreal("127.0.0.1", 8020)
+            .credentials("root", "root")
+            .namespace("1516")
+            .project("AvevaMarineSample", "1516")
+            .with(|o| o.location = "SJZ1".into())
+            .build()
+            .unwrap();
+        assert_eq!(option.get_surreal_script_dir(), "resource/surreal");
+        assert_eq!(option.location, "SJZ1");
+        assert!(option.export_parquet);
+
+        let err = DbOptionBuilder::new()
+            .surreal("127.0.0.1", 8020)
+            .pool_size(0)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("surreal_pool_size"));
+    }
+}
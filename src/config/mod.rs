@@ -0,0 +1,299 @@
+//! DbOption 配置加载
+//!
+//! 加载顺序：配置文件 → 环境变量覆盖（`AIOS_` 前缀，嵌套字段用 `__` 分隔，
+//! 如 `AIOS_V_IP`、`AIOS_DISK_CACHE__ENABLED`）→ 校验。
+//! 配置文件路径依次取参数、`DB_OPTION_FILE` 环境变量、缺省的 `DbOption`（不带扩展名），
+//! 省略扩展名时依次查找 `.toml`、`.json`、`.yaml` 等格式。
+//!
+//! 嵌入到其它服务、磁盘上没有配置文件时，用 [`DbOptionBuilder`] 构造，
+//! 再通过 [`set_db_option`] 设置为全局配置。
+
+pub mod builder;
+
+pub use builder::DbOptionBuilder;
+
+use crate::options::DbOption;
+use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 指定配置文件的环境变量
+pub const CONFIG_FILE_ENV: &str = "DB_OPTION_FILE";
+/// 缺省配置文件名（不带扩展名）
+pub const DEFAULT_CONFIG_FILE: &str = "DbOption";
+/// 覆盖配置项的环境变量前缀
+pub const ENV_PREFIX: &str = "AIOS";
+
+/// 配置错误
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("找不到配置文件 {path}（可通过 {CONFIG_FILE_ENV} 环境变量指定）")]
+    NotFound { path: PathBuf },
+    #[error("解析配置文件 {path} 失败: {msg}")]
+    Parse { path: PathBuf, msg: String },
+    #[error("配置校验失败:\n{}", .0.iter().map(|i| format!("  - {i}")).collect::<Vec<_>>().join("\n"))]
+    Invalid(Vec<ConfigIssue>),
+    #[error("全局配置已初始化，不能重复设置")]
+    AlreadySet,
+}
+
+/// 单个校验问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// 字段名，与配置文件中的键一致
+    pub field: &'static str,
+    pub msg: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.msg)
+    }
+}
+
+/// 解析配置文件路径：参数 → `DB_OPTION_FILE` → `DbOption`
+pub fn resolve_config_path(path: Option<&Path>) -> PathBuf {
+    match path {
+        Some(p) => p.to_path_buf(),
+        None => std::env::var(CONFIG_FILE_ENV)
+            .unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string())
+            .into(),
+    }
+}
+
+/// 省略扩展名时依次尝试的格式（config 支持的全部格式）
+const CONFIG_EXTENSIONS: [&str; 7] = ["toml", "json", "yaml", "yml", "ini", "ron", "json5"];
+
+/// 查找实际的配置文件：路径本身存在时直接使用，否则按 [`CONFIG_EXTENSIONS`] 补全扩展名
+pub fn find_config_file(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|p| p.is_file())
+}
+
+/// 按扩展名识别格式，无法识别的扩展名（如 `.conf`）按 TOML 解析
+fn config_file_source(
+    path: &Path,
+) -> ::config::File<::config::FileSourceFile, ::config::FileFormat> {
+    let known = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| CONFIG_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    let file = ::config::File::from(path);
+    if known {
+        file
+    } else {
+        file.format(::config::FileFormat::Toml)
+    }
+}
+
+/// 把 serde 的 `missing field` 错误改写成更易懂的提示
+fn describe_parse_error(msg: String) -> String {
+    match msg
+        .split("missing field `")
+        .nth(1)
+        .and_then(|s| s.split('`').next())
+    {
+        Some(field) => format!(
+            "缺少必填字段 `{field}`，请在配置文件中添加或设置环境变量 {ENV_PREFIX}_{}",
+            field.to_uppercase()
+        ),
+        None => msg,
+    }
+}
+
+impl DbOption {
+    /// 读取配置文件并应用环境变量覆盖，不做校验
+    ///
+    /// 支持 config 的全部文件格式，扩展名可省略；无法识别的扩展名按 TOML 解析。
+    pub fn read(path: Option<&Path>) -> Result<DbOption, ConfigError> {
+        use ::config::{Config, Environment};
+
+        let path = resolve_config_path(path);
+        let Some(path) = find_config_file(&path) else {
+            return Err(ConfigError::NotFound { path });
+        };
+        let parse_err = |e: ::config::ConfigError| ConfigError::Parse {
+            path: path.clone(),
+            msg: describe_parse_error(e.to_string()),
+        };
+        Config::builder()
+            .add_source(config_file_source(&path))
+            .add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .build()
+            .map_err(parse_err)?
+            .try_deserialize::<DbOption>()
+            .map_err(parse_err)
+    }
+
+    /// 读取并校验配置
+    ///
+    /// `path` 为 None 时使用 `DB_OPTION_FILE` 环境变量或缺省的 `DbOption.toml`。
+    pub fn load(path: Option<&Path>) -> Result<DbOption, ConfigError> {
+        let option = Self::read(path)?;
+        option.validate()?;
+        Ok(option)
+    }
+
+    /// 不依赖配置文件构造
+    #[inline]
+    pub fn builder() -> DbOptionBuilder {
+        DbOptionBuilder::new()
+    }
+
+    /// 列出所有校验问题
+    pub fn validation_issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut check = |ok: bool, field: &'static str, msg: &str| {
+            if !ok {
+                issues.push(ConfigIssue {
+                    field,
+                    msg: msg.to_string(),
+                });
+            }
+        };
+        check(!self.v_ip.trim().is_empty(), "v_ip", "数据库地址不能为空");
+        check(self.v_port != 0, "v_port", "数据库端口不能为 0");
+        check(!self.v_user.is_empty(), "v_user", "数据库用户名不能为空");
+        check(
+            !self.surreal_ns.is_empty(),
+            "surreal_ns",
+            "命名空间不能为空",
+        );
+        check(
+            !self.project_name.is_empty(),
+            "project_name",
+            "项目名称不能为空",
+        );
+        check(
+            self.surreal_pool_size != Some(0),
+            "surreal_pool_size",
+            "连接池大小至少为 1",
+        );
+        check(
+            self.mesh_tol_ratio.is_none_or(|r| r > 0.0),
+            "mesh_tol_ratio",
+            "网格容差比例必须大于 0",
+        );
+        check(
+            !self.disk_cache.enabled || !self.disk_cache.path.trim().is_empty(),
+            "disk_cache.path",
+            "启用磁盘缓存时必须指定缓存文件路径",
+        );
+        issues
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let issues = self.validation_issues();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(issues))
+        }
+    }
+}
+
+static DB_OPTION: OnceCell<DbOption> = OnceCell::new();
+
+fn install(option: &DbOption) {
+    crate::mesh_precision::set_active_precision(option.mesh_precision.clone());
+}
+
+/// 设置全局配置，必须在第一次 [`get_db_option`](crate::get_db_option) 之前调用
+///
+/// 已设置时返回 [`ConfigError::AlreadySet`]，不会改动当前生效的配置。
+pub fn set_db_option(option: DbOption) -> Result<(), ConfigError> {
+    let mut set = false;
+    DB_OPTION.get_or_init(|| {
+        set = true;
+        install(&option);
+        option
+    });
+    if set {
+        Ok(())
+    } else {
+        Err(ConfigError::AlreadySet)
+    }
+}
+
+/// 获取全局配置，未设置时按缺省路径读取
+///
+/// 校验问题只记录警告，不阻止读取（旧配置文件常有未填写的连接项）。
+pub fn try_get_db_option() -> Result<&'static DbOption, ConfigError> {
+    DB_OPTION.get_or_try_init(|| {
+        let option = DbOption::read(None)?;
+        for issue in option.validation_issues() {
+            log::warn!("配置项 {}", issue);
+        }
+        install(&option);
+        Ok(option)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_missing_field() {
+        let msg = describe_parse_error("missing field `v_ip`".to_string());
+        assert!(msg.contains("`v_ip`") && msg.contains("AIOS_V_IP"));
+        assert_eq!(describe_parse_error("其它错误".into()), "其它错误");
+    }
+
+    #[test]
+    fn test_validation_lists_all_issues() {
+        let err = DbOption::default().validate().unwrap_err();
+        let ConfigError::Invalid(issues) = &err else {
+            panic!("应为校验错误");
+        };
+        let fields = issues.iter().map(|i| i.field).collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            ["v_ip", "v_port", "v_user", "surreal_ns", "project_name"]
+        );
+        assert!(err.to_string().contains("  - v_port: 数据库端口不能为 0"));
+    }
+
+    #[test]
+    fn test_read_non_toml_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("DbOption.json");
+        std::fs::write(
+            &json,
+            r#"{"v_ip": "10.0.0.1", "v_port": 8009, "v_user": "root", "project_name": "AvevaMarineSample"}"#,
+        )
+        .unwrap();
+        // 省略扩展名时按格式查找
+        assert_eq!(
+            find_config_file(&dir.path().join("DbOption")),
+            Some(json.clone())
+        );
+        let err_or_option = DbOption::read(Some(&dir.path().join("DbOption")));
+        assert!(!matches!(err_or_option, Err(ConfigError::NotFound { .. })));
+
+        // 无法识别的扩展名按 TOML 解析
+        let conf = dir.path().join("db.conf");
+        std::fs::write(&conf, "v_ip = \"10.0.0.1\"\n").unwrap();
+        let err_or_option = DbOption::read(Some(&conf));
+        assert!(!matches!(err_or_option, Err(ConfigError::NotFound { .. })));
+        if let Err(ConfigError::Parse { msg, .. }) = &err_or_option {
+            assert!(!msg.contains("registered file format"), "{msg}");
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = DbOption::read(Some(Path::new("__no_such_config__"))).unwrap_err();
+        assert!(matches!(err, ConfigError::NotFound { .. }));
+    }
+}
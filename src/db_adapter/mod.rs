@@ -1,13 +1,19 @@
 //! 数据库适配器模块
 //!
-//! 提供统一的数据库接口抽象，目前仅支持 SurrealDB 后端
+//! 提供统一的数据库接口抽象，支持 SurrealDB 后端和（`postgres` feature）PostgreSQL 后端
 
 pub mod traits;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod surreal_adapter;
 
+#[cfg(feature = "postgres")]
+pub mod postgres_adapter;
+
 pub use traits::*;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use surreal_adapter::SurrealAdapter;
+
+#[cfg(feature = "postgres")]
+pub use postgres_adapter::PostgresAdapter;
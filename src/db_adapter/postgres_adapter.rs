@@ -0,0 +1,473 @@
+//! PostgreSQL 适配器实现
+//!
+//! 表结构见 [`PostgresAdapter::ensure_schema`]：
+//!
+//! - `aios_pe`：每个元素一行，只保存最新版本，`seq` 为在父元素下的插入顺序；
+//! - `aios_attmap`：属性以 JSON 保存，UDA 单独一列；
+//! - `aios_relation`：`(from_refno, to_refno, rel_type)` 三元组。
+//!
+//! 参考号以 `RefU64` 的整数值（`bigint`）保存，不区分 sesno。
+
+use super::traits::*;
+use crate::types::*;
+use async_trait::async_trait;
+use sqlx::postgres::{PgArguments, PgPool, PgPoolOptions, PgRow};
+use sqlx::query::Query;
+use sqlx::{Postgres, Row};
+use std::time::Duration;
+
+const SCHEMA_SQL: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS aios_pe (
+        refno BIGINT PRIMARY KEY,
+        owner BIGINT NOT NULL DEFAULT 0,
+        seq INTEGER NOT NULL DEFAULT 0,
+        name TEXT NOT NULL DEFAULT '',
+        noun TEXT NOT NULL DEFAULT '',
+        dbnum INTEGER NOT NULL DEFAULT 0,
+        sesno INTEGER NOT NULL DEFAULT 0,
+        status_code TEXT NOT NULL DEFAULT '',
+        cata_hash TEXT NOT NULL DEFAULT '',
+        lock BOOLEAN NOT NULL DEFAULT FALSE,
+        deleted BOOLEAN NOT NULL DEFAULT FALSE,
+        typex INTEGER
+    )",
+    "CREATE INDEX IF NOT EXISTS aios_pe_owner_idx ON aios_pe (owner, seq)",
+    "CREATE INDEX IF NOT EXISTS aios_pe_noun_idx ON aios_pe (noun)",
+    "CREATE TABLE IF NOT EXISTS aios_attmap (
+        refno BIGINT PRIMARY KEY,
+        attrs JSONB NOT NULL,
+        uda JSONB
+    )",
+    "CREATE TABLE IF NOT EXISTS aios_relation (
+        from_refno BIGINT NOT NULL,
+        to_refno BIGINT NOT NULL,
+        rel_type TEXT NOT NULL,
+        PRIMARY KEY (from_refno, rel_type, to_refno)
+    )",
+];
+
+const PE_COLUMNS: &str =
+    "refno, owner, name, noun, dbnum, sesno, status_code, cata_hash, lock, deleted, typex";
+
+/// PostgreSQL 适配器
+#[derive(Debug, Clone)]
+pub struct PostgresAdapter {
+    name: String,
+    pool: PgPool,
+}
+
+#[inline]
+fn key(refno: RefnoEnum) -> i64 {
+    refno.refno().0 as i64
+}
+
+#[inline]
+fn refno_of(v: i64) -> RefnoEnum {
+    RefU64(v as u64).into()
+}
+
+/// 新元素追加到父元素的子元素末尾，已有元素保持原顺序
+const UPSERT_PE_SQL: &str = "INSERT INTO aios_pe
+        (refno, owner, seq, name, noun, dbnum, sesno, status_code, cata_hash, lock, deleted, typex)
+    VALUES ($1, $2, (SELECT COALESCE(MAX(seq) + 1, 0) FROM aios_pe WHERE owner = $2),
+        $3, $4, $5, $6, $7, $8, $9, $10, $11)
+    ON CONFLICT (refno) DO UPDATE SET
+        owner = EXCLUDED.owner, name = EXCLUDED.name, noun = EXCLUDED.noun,
+        dbnum = EXCLUDED.dbnum, sesno = EXCLUDED.sesno, status_code = EXCLUDED.status_code,
+        cata_hash = EXCLUDED.cata_hash, lock = EXCLUDED.lock, deleted = EXCLUDED.deleted,
+        typex = EXCLUDED.typex";
+
+fn upsert_pe(pe: &SPdmsElement) -> Query<'_, Postgres, PgArguments> {
+    sqlx::query(UPSERT_PE_SQL)
+        .bind(key(pe.refno))
+        .bind(key(pe.owner))
+        .bind(&pe.name)
+        .bind(&pe.noun)
+        .bind(pe.dbnum)
+        .bind(pe.sesno)
+        .bind(&pe.status_code)
+        .bind(&pe.cata_hash)
+        .bind(pe.lock)
+        .bind(pe.deleted)
+        .bind(pe.typex)
+}
+
+fn pe_from_row(row: &PgRow) -> anyhow::Result<SPdmsElement> {
+    Ok(SPdmsElement {
+        refno: refno_of(row.try_get("refno")?),
+        owner: refno_of(row.try_get("owner")?),
+        name: row.try_get("name")?,
+        noun: row.try_get("noun")?,
+        dbnum: row.try_get("dbnum")?,
+        sesno: row.try_get("sesno")?,
+        status_code: row.try_get("status_code")?,
+        cata_hash: row.try_get("cata_hash")?,
+        lock: row.try_get("lock")?,
+        deleted: row.try_get("deleted")?,
+        typex: row.try_get("typex")?,
+        ..Default::default()
+    })
+}
+
+impl PostgresAdapter {
+    /// 连接数据库并创建表结构
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(32)
+            .acquire_timeout(Duration::from_secs(30))
+            .connect(url)
+            .await
+            .map_err(|e| AdapterError::ConnectionError(e.to_string()))?;
+        let adapter = Self::from_pool(pool);
+        adapter.ensure_schema().await?;
+        Ok(adapter)
+    }
+
+    /// 使用已有的连接池，不创建表结构
+    pub fn from_pool(pool: PgPool) -> Self {
+        Self {
+            name: "PostgreSQL".to_string(),
+            pool,
+        }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// 创建表和索引（已存在时跳过）
+    pub async fn ensure_schema(&self) -> anyhow::Result<()> {
+        for sql in SCHEMA_SQL {
+            sqlx::query(sql).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn refnos(&self, sql: &str, refno: RefnoEnum) -> anyhow::Result<Vec<RefnoEnum>> {
+        let rows: Vec<(i64,)> = sqlx::query_as(sql)
+            .bind(key(refno))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AdapterError::QueryError(e.to_string()))?;
+        Ok(rows.into_iter().map(|(v,)| refno_of(v)).collect())
+    }
+}
+
+#[async_trait]
+impl DatabaseAdapter for PostgresAdapter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> DatabaseCapabilities {
+        DatabaseCapabilities {
+            supports_graph_traversal: false,
+            supports_transactions: true,
+            supports_versioning: false,
+            supports_live_queries: false,
+            supports_full_text_search: false,
+            supports_vector_index: false,
+        }
+    }
+
+    async fn health_check(&self) -> anyhow::Result<bool> {
+        match sqlx::query("SELECT 1").execute(&self.pool).await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                log::warn!("PostgreSQL 健康检查失败: {}", e);
+                Ok(false)
+            }
+        }
+    }
+
+    // ==================== PE 操作 ====================
+
+    async fn get_pe(
+        &self,
+        refno: RefnoEnum,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Option<SPdmsElement>> {
+        let sql = format!("SELECT {PE_COLUMNS} FROM aios_pe WHERE refno = $1");
+        let row = sqlx::query(&sql)
+            .bind(key(refno))
+            .fetch_optional(&self.pool)
+            .await?;
+        row.as_ref().map(pe_from_row).transpose()
+    }
+
+    async fn get_pe_batch(
+        &self,
+        refnos: &[RefnoEnum],
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Vec<SPdmsElement>> {
+        let keys: Vec<i64> = refnos.iter().map(|r| key(*r)).collect();
+        let sql = format!("SELECT {PE_COLUMNS} FROM aios_pe WHERE refno = ANY($1)");
+        let rows = sqlx::query(&sql).bind(&keys).fetch_all(&self.pool).await?;
+        let mut by_refno = std::collections::HashMap::with_capacity(rows.len());
+        for row in &rows {
+            let pe = pe_from_row(row)?;
+            by_refno.insert(key(pe.refno), pe);
+        }
+        // 保持输入顺序
+        Ok(keys.iter().filter_map(|k| by_refno.remove(k)).collect())
+    }
+
+    async fn query_children(
+        &self,
+        refno: RefnoEnum,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Vec<RefnoEnum>> {
+        self.refnos(
+            "SELECT refno FROM aios_pe WHERE owner = $1 AND NOT deleted ORDER BY seq, refno",
+            refno,
+        )
+        .await
+    }
+
+    async fn query_ancestors(
+        &self,
+        refno: RefnoEnum,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Vec<RefnoEnum>> {
+        // 与 SurrealAdapter 一致：根节点在前，不含自身
+        self.refnos(
+            "WITH RECURSIVE anc(refno, owner, depth) AS (
+                SELECT refno, owner, 0 FROM aios_pe WHERE refno = $1
+                UNION ALL
+                SELECT p.refno, p.owner, a.depth + 1
+                FROM aios_pe p JOIN anc a ON p.refno = a.owner
+                WHERE a.owner <> 0 AND a.depth < 64
+            )
+            SELECT refno FROM anc WHERE depth > 0 ORDER BY depth DESC",
+            refno,
+        )
+        .await
+    }
+
+    async fn save_pe(&self, pe: &SPdmsElement) -> anyhow::Result<()> {
+        upsert_pe(pe).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn save_pe_batch(&self, pes: Vec<SPdmsElement>) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for pe in &pes {
+            upsert_pe(pe).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_pe(&self, refno: RefnoEnum) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for sql in [
+            "DELETE FROM aios_pe WHERE refno = $1",
+            "DELETE FROM aios_attmap WHERE refno = $1",
+            "DELETE FROM aios_relation WHERE from_refno = $1 OR to_refno = $1",
+        ] {
+            sqlx::query(sql).bind(key(refno)).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // ==================== 属性操作 ====================
+
+    async fn get_attmap(
+        &self,
+        refno: RefnoEnum,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<NamedAttrMap> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT attrs::text FROM aios_attmap WHERE refno = $1")
+                .bind(key(refno))
+                .fetch_optional(&self.pool)
+                .await?;
+        let (json,) = row.ok_or_else(|| AdapterError::NotFound(refno.to_string()))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    async fn get_attmap_with_uda(
+        &self,
+        refno: RefnoEnum,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<NamedAttrMap> {
+        let row: Option<(String, Option<String>)> =
+            sqlx::query_as("SELECT attrs::text, uda::text FROM aios_attmap WHERE refno = $1")
+                .bind(key(refno))
+                .fetch_optional(&self.pool)
+                .await?;
+        let (json, uda) = row.ok_or_else(|| AdapterError::NotFound(refno.to_string()))?;
+        let mut attmap: NamedAttrMap = serde_json::from_str(&json)?;
+        if let Some(uda) = uda {
+            let uda: NamedAttrMap = serde_json::from_str(&uda)?;
+            attmap.map.extend(uda.map);
+        }
+        Ok(attmap)
+    }
+
+    async fn save_attmap(&self, refno: RefnoEnum, attmap: &NamedAttrMap) -> anyhow::Result<()> {
+        // UDA（以 `:` 开头的属性）单独保存，与 get_attmap / get_attmap_with_uda 对应
+        let (uda, attrs): (Vec<_>, Vec<_>) =
+            attmap.map.iter().partition(|(k, _)| k.starts_with(':'));
+        let attrs = NamedAttrMap {
+            map: attrs
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        };
+        let uda = (!uda.is_empty()).then(|| NamedAttrMap {
+            map: uda
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        });
+        sqlx::query(
+            "INSERT INTO aios_attmap (refno, attrs, uda) VALUES ($1, $2::jsonb, $3::jsonb)
+             ON CONFLICT (refno) DO UPDATE SET attrs = EXCLUDED.attrs, uda = EXCLUDED.uda",
+        )
+        .bind(key(refno))
+        .bind(serde_json::to_string(&attrs)?)
+        .bind(uda.map(|u| serde_json::to_string(&u)).transpose()?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // ==================== 关系操作 ====================
+
+    async fn create_relation(
+        &self,
+        from: RefnoEnum,
+        to: RefnoEnum,
+        rel_type: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO aios_relation (from_refno, to_refno, rel_type) VALUES ($1, $2, $3)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(key(from))
+        .bind(key(to))
+        .bind(rel_type)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn query_related(
+        &self,
+        refno: RefnoEnum,
+        rel_type: &str,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Vec<RefnoEnum>> {
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            "SELECT to_refno FROM aios_relation WHERE from_refno = $1 AND rel_type = $2 ORDER BY to_refno",
+        )
+        .bind(key(refno))
+        .bind(rel_type)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|(v,)| refno_of(v)).collect())
+    }
+
+    async fn delete_relation(
+        &self,
+        from: RefnoEnum,
+        to: RefnoEnum,
+        rel_type: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "DELETE FROM aios_relation WHERE from_refno = $1 AND to_refno = $2 AND rel_type = $3",
+        )
+        .bind(key(from))
+        .bind(key(to))
+        .bind(rel_type)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // ==================== 图遍历操作 ====================
+
+    async fn query_subtree(
+        &self,
+        refno: RefnoEnum,
+        max_depth: usize,
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Vec<RefnoEnum>> {
+        // 单次递归查询，按层输出，与默认实现的顺序一致
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            "WITH RECURSIVE sub(refno, depth, path) AS (
+                SELECT $1::bigint, 0, ARRAY[]::integer[]
+                UNION ALL
+                SELECT p.refno, s.depth + 1, s.path || p.seq
+                FROM aios_pe p JOIN sub s ON p.owner = s.refno
+                WHERE s.depth < $2 AND NOT p.deleted
+            )
+            SELECT refno FROM sub ORDER BY depth, path",
+        )
+        .bind(key(refno))
+        .bind(max_depth.min(i32::MAX as usize) as i32)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|(v,)| refno_of(v)).collect())
+    }
+
+    // ==================== 批量操作 ====================
+
+    async fn query_children_batch(
+        &self,
+        refnos: &[RefnoEnum],
+        _ctx: Option<QueryContext>,
+    ) -> anyhow::Result<Vec<Vec<RefnoEnum>>> {
+        let keys: Vec<i64> = refnos.iter().map(|r| key(*r)).collect();
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT owner, refno FROM aios_pe WHERE owner = ANY($1) AND NOT deleted ORDER BY seq, refno",
+        )
+        .bind(&keys)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut by_owner: std::collections::HashMap<i64, Vec<RefnoEnum>> = Default::default();
+        for (owner, refno) in rows {
+            by_owner.entry(owner).or_default().push(refno_of(refno));
+        }
+        Ok(keys
+            .iter()
+            .map(|k| by_owner.get(k).cloned().unwrap_or_default())
+            .collect())
+    }
+
+    // ==================== 统计和元数据 ====================
+
+    /// `filter` 为类型名（noun）
+    async fn count_elements(&self, filter: Option<&str>) -> anyhow::Result<u64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM aios_pe WHERE NOT deleted AND ($1::text IS NULL OR noun = $1)",
+        )
+        .bind(filter)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn count_relations(&self, rel_type: Option<&str>) -> anyhow::Result<u64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM aios_relation WHERE $1::text IS NULL OR rel_type = $1",
+        )
+        .bind(rel_type)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refno_key_roundtrip() {
+        let refno: RefnoEnum = RefU64::from_two_nums(17496, 266203).into();
+        assert_eq!(refno_of(key(refno)), refno);
+    }
+}
@@ -96,8 +96,10 @@ impl DatabaseAdapter for SurrealAdapter {
     }
 
     async fn save_pe(&self, pe: &SPdmsElement) -> anyhow::Result<()> {
-        // TODO: 实现 PE 保存逻辑
-        log::debug!("SurrealAdapter: 保存 PE {:?}", pe.refno);
+        // UPSERT 保证重复写入（如断点续传重放最后一批）不会产生重复记录
+        let key = pe.refno.to_pe_key();
+        let sql = format!("UPSERT {} CONTENT {}", key, pe.gen_sur_json(Some(key.clone())));
+        rs_surreal::SUL_DB.query_response(&sql).await?;
         Ok(())
     }
 
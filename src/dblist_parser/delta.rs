@@ -0,0 +1,303 @@
+//! 增量 dblist 解析
+//!
+//! 增量 dblist 只包含发生变化的元素与属性，与全量导出的区别是元素必须显式定位：
+//!
+//! ```text
+//! DBNO 17496
+//! OLD PANEL =17496/1201        ! 修改已有元素，可用 =参考号 或 /名称 定位
+//!   DESC 'new description'
+//!   NAME /PANEL-1-NEW          ! 修改名称记为重命名
+//!   NEW PLOOP /PLOOP-9         ! 在 OLD 元素下新建子元素
+//!     HEIG 12
+//!   END
+//! END
+//! DELETE GENSEC /BEAM-3        ! 删除标记
+//! ```
+//!
+//! 解析结果为 [`ElementChangeSet`]，按文件中的顺序记录变更，供同步层依次应用。
+
+use crate::RefU64;
+use crate::dblist_parser::attr_converter::AttrConverter;
+use crate::types::named_attmap::NamedAttrMap;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 元素定位方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementRef {
+    /// `=dbno/elno`
+    Refno(RefU64),
+    /// `/NAME`（不含前导 `/`）
+    Name(String),
+    /// 同一变更集中第 n 条新建变更产生的元素（未命名的新元素）
+    New(usize),
+}
+
+impl ElementRef {
+    fn parse(token: &str) -> Result<Self> {
+        if let Some(name) = token.strip_prefix('/') {
+            Ok(Self::Name(name.to_string()))
+        } else if token.starts_with('=') {
+            token
+                .parse::<RefU64>()
+                .map(Self::Refno)
+                .map_err(|_| anyhow::anyhow!("无效的参考号: {}", token))
+        } else {
+            Err(anyhow::anyhow!("无法识别的元素定位: {}", token))
+        }
+    }
+}
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// 单个元素的变更
+#[derive(Debug, Clone)]
+pub struct ElementChange {
+    pub kind: ChangeKind,
+    pub noun: String,
+    pub target: ElementRef,
+    /// 新建元素的父元素；`OLD` / `DELETE` 为 None
+    pub parent: Option<ElementRef>,
+    /// 新建元素的全部属性或修改的属性，不含 NAME（见 `rename`）
+    pub attributes: NamedAttrMap,
+    /// 修改后的名称（不含前导 `/`）；新建元素的名称也记录在这里
+    pub rename: Option<String>,
+}
+
+impl ElementChange {
+    fn new(kind: ChangeKind, noun: &str, target: ElementRef, parent: Option<ElementRef>) -> Self {
+        Self {
+            kind,
+            noun: noun.to_uppercase(),
+            target,
+            parent,
+            attributes: NamedAttrMap::default(),
+            rename: None,
+        }
+    }
+
+    /// 是否为重命名（已有元素的 NAME 被修改）
+    pub fn is_rename(&self) -> bool {
+        self.kind == ChangeKind::Modify && self.rename.is_some()
+    }
+}
+
+/// 增量 dblist 的解析结果
+#[derive(Debug, Clone, Default)]
+pub struct ElementChangeSet {
+    /// 文件中声明的数据库编号
+    pub dbno: Option<i32>,
+    pub changes: Vec<ElementChange>,
+}
+
+impl ElementChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn iter_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &ElementChange> {
+        self.changes.iter().filter(move |c| c.kind == kind)
+    }
+
+    /// 重命名列表：(定位, 新名称)
+    pub fn renames(&self) -> impl Iterator<Item = (&ElementRef, &str)> {
+        self.changes
+            .iter()
+            .filter(|c| c.is_rename())
+            .filter_map(|c| c.rename.as_deref().map(|n| (&c.target, n)))
+    }
+}
+
+/// 增量 dblist 解析器
+#[derive(Default)]
+pub struct DeltaDblistParser {
+    attr_converter: AttrConverter,
+    set: ElementChangeSet,
+    /// 当前所在的 NEW / OLD 块，元素为变更在 `set.changes` 中的下标
+    stack: Vec<usize>,
+}
+
+impl DeltaDblistParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从文件解析增量 dblist
+    pub fn parse_file<P: AsRef<Path>>(&mut self, file_path: P) -> Result<ElementChangeSet> {
+        let path = file_path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取增量 dblist 失败: {}", path.display()))?;
+        self.parse_str(&content)
+    }
+
+    /// 解析增量 dblist 文本
+    pub fn parse_str(&mut self, content: &str) -> Result<ElementChangeSet> {
+        for (i, line) in content.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.process_line(line)
+                .with_context(|| format!("第 {} 行: {}", i + 1, line))?;
+        }
+        if !self.stack.is_empty() {
+            log::warn!("增量 dblist 有 {} 个块缺少 END", self.stack.len());
+            self.stack.clear();
+        }
+        Ok(std::mem::take(&mut self.set))
+    }
+
+    fn process_line(&mut self, line: &str) -> Result<()> {
+        let mut parts = line.split_whitespace();
+        let keyword = parts.next().unwrap_or_default().to_uppercase();
+        match keyword.as_str() {
+            "DBNO" => {
+                self.set.dbno = parts.next().and_then(|s| s.parse().ok());
+            }
+            "NEW" => {
+                let noun = parts.next().context("NEW 缺少元素类型")?;
+                let index = self.set.changes.len();
+                let name = parts.next().and_then(|t| t.strip_prefix('/'));
+                let target = match name {
+                    Some(name) => ElementRef::Name(name.to_string()),
+                    None => ElementRef::New(index),
+                };
+                let mut change =
+                    ElementChange::new(ChangeKind::Create, noun, target, self.current_ref());
+                change.rename = name.map(str::to_string);
+                self.push_block(change);
+            }
+            "OLD" => {
+                let noun = parts.next().context("OLD 缺少元素类型")?;
+                let target = ElementRef::parse(parts.next().context("OLD 缺少元素定位")?)?;
+                self.push_block(ElementChange::new(ChangeKind::Modify, noun, target, None));
+            }
+            "DELETE" => {
+                let noun = parts.next().context("DELETE 缺少元素类型")?;
+                let target = ElementRef::parse(parts.next().context("DELETE 缺少元素定位")?)?;
+                self.set
+                    .changes
+                    .push(ElementChange::new(ChangeKind::Delete, noun, target, None));
+            }
+            "END" => {
+                self.stack.pop();
+            }
+            _ => {
+                let Some((key, value)) = line.split_once(char::is_whitespace) else {
+                    return Ok(());
+                };
+                self.add_attribute(key.trim(), value.trim())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn current_ref(&self) -> Option<ElementRef> {
+        self.stack
+            .last()
+            .map(|&i| self.set.changes[i].target.clone())
+    }
+
+    fn push_block(&mut self, change: ElementChange) {
+        self.stack.push(self.set.changes.len());
+        self.set.changes.push(change);
+    }
+
+    fn add_attribute(&mut self, key: &str, value: &str) -> Result<()> {
+        let Some(&index) = self.stack.last() else {
+            anyhow::bail!("属性不在任何 NEW / OLD 块内");
+        };
+        let change = &mut self.set.changes[index];
+        if key.eq_ignore_ascii_case("NAME") {
+            let name = value.trim_start_matches('/').to_string();
+            if change.target != ElementRef::Name(name.clone()) {
+                change.rename = Some(name);
+            }
+            return Ok(());
+        }
+        let raw = BTreeMap::from([(key.to_string(), value.to_string())]);
+        let converted = self.attr_converter.convert_attributes(&change.noun, &raw)?;
+        change.attributes.map.extend(converted.map);
+        Ok(())
+    }
+}
+
+/// 去掉 `!` 之后的注释（引号内的 `!` 保留）
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '!' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA: &str = r#"
+DBNO 17496
+OLD PANEL =17496/1201   ! 修改
+  NAME /PANEL-1-NEW
+  NEW PLOOP
+  END
+END
+NEW FRMWORK /FRM-2
+END
+DELETE GENSEC /BEAM-3
+"#;
+
+    #[test]
+    fn test_parse_delta() {
+        let set = DeltaDblistParser::new().parse_str(DELTA).unwrap();
+        assert_eq!(set.dbno, Some(17496));
+        assert_eq!(set.changes.len(), 4);
+
+        let old = &set.changes[0];
+        assert_eq!(old.kind, ChangeKind::Modify);
+        assert_eq!(
+            old.target,
+            ElementRef::Refno(RefU64::from_two_nums(17496, 1201))
+        );
+        assert!(old.is_rename());
+
+        let ploop = &set.changes[1];
+        assert_eq!(ploop.kind, ChangeKind::Create);
+        assert_eq!(ploop.target, ElementRef::New(1));
+        assert_eq!(ploop.parent, Some(old.target.clone()));
+
+        let frm = &set.changes[2];
+        assert_eq!(frm.target, ElementRef::Name("FRM-2".into()));
+        assert_eq!(frm.parent, None);
+        assert!(!frm.is_rename());
+
+        let del = &set.changes[3];
+        assert_eq!(del.kind, ChangeKind::Delete);
+        assert_eq!(del.noun, "GENSEC");
+        assert_eq!(set.renames().count(), 1);
+    }
+
+    #[test]
+    fn test_invalid_ref() {
+        let err = DeltaDblistParser::new()
+            .parse_str("DELETE PANEL PANEL-1")
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("无法识别的元素定位"));
+    }
+
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(strip_comment("DESC 'a!b' ! note"), "DESC 'a!b' ");
+    }
+}
@@ -1,11 +1,13 @@
 //! dblist 文件解析器
 //!
-//! 基于 NamedAttrMap 和属性模板的 PDMS dblist 文件解析器
+//! 基于 NamedAttrMap 和属性模板的 PDMS dblist 文件解析器，支持全量导出与增量 dblist
 
 pub mod attr_converter;
+pub mod delta;
 pub mod element;
 pub mod parser;
 
 pub use attr_converter::AttrConverter;
+pub use delta::{ChangeKind, DeltaDblistParser, ElementChange, ElementChangeSet, ElementRef};
 pub use element::{ElementType, PdmsElement};
 pub use parser::DblistParser;
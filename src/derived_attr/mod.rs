@@ -0,0 +1,307 @@
+//! 派生属性引擎
+//!
+//! 报表经常重复推导相同的值（长度、重量、房间、标高、系统等）。本模块提供：
+//! - 按名称注册计算函数，并声明其依赖（属性或几何）
+//! - 按元素缓存计算结果，依赖变化时精确失效：属性写入（`set_attrs`、`move_element` 等）调用
+//!   [`DerivedAttrRegistry::invalidate_attrs`]，几何生成或删除调用 [`DerivedAttrRegistry::invalidate_geometry`]
+//! - 在 `NamedAttrMap` 中以 `:` 前缀的伪属性形式暴露，如 `:LENGTH`
+//!
+//! ```ignore
+//! register_derived_attr("LENGTH", vec![DerivedDependency::Geometry], |refno| {
+//!     async move { Ok(NamedAttrValue::F32Type(compute_length(refno).await?)) }.boxed()
+//! });
+//! let v = get_derived_attr(refno, ":LENGTH").await?;
+//! ```
+
+pub mod system_tag;
+
+use crate::types::named_attmap::NamedAttrMap;
+use crate::types::named_attvalue::NamedAttrValue;
+use crate::RefnoEnum;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// 伪属性名前缀
+pub const DERIVED_ATTR_PREFIX: char = ':';
+
+/// 派生属性的依赖
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DerivedDependency {
+    /// 依赖元素自身的某个属性
+    Attr(String),
+    /// 依赖元素的几何（inst_relate / tubi_relate）
+    Geometry,
+}
+
+/// 派生属性计算函数
+pub type DerivedAttrFn =
+    Arc<dyn Fn(RefnoEnum) -> BoxFuture<'static, anyhow::Result<NamedAttrValue>> + Send + Sync>;
+
+/// 派生属性定义
+#[derive(Clone)]
+pub struct DerivedAttrDef {
+    pub name: String,
+    pub deps: Vec<DerivedDependency>,
+    /// 为空表示该值由外部作业写入（见 [`DerivedAttrRegistry::set_value`]）
+    pub func: Option<DerivedAttrFn>,
+}
+
+impl DerivedAttrDef {
+    #[inline]
+    fn depends_on_attr(&self, attr: &str) -> bool {
+        self.deps
+            .iter()
+            .any(|d| matches!(d, DerivedDependency::Attr(a) if a == attr))
+    }
+
+    #[inline]
+    fn depends_on_geometry(&self) -> bool {
+        self.deps.contains(&DerivedDependency::Geometry)
+    }
+}
+
+/// 判断是否为派生属性名（以 `:` 开头）
+#[inline]
+pub fn is_derived_attr_name(name: &str) -> bool {
+    name.starts_with(DERIVED_ATTR_PREFIX)
+}
+
+/// 去掉前缀并转为大写，`:length` -> `LENGTH`
+#[inline]
+pub fn normalize_derived_attr_name(name: &str) -> String {
+    name.trim_start_matches(DERIVED_ATTR_PREFIX).to_uppercase()
+}
+
+/// 派生属性注册表与缓存
+#[derive(Default)]
+pub struct DerivedAttrRegistry {
+    defs: DashMap<String, DerivedAttrDef>,
+    cache: DashMap<(RefnoEnum, String), NamedAttrValue>,
+}
+
+impl DerivedAttrRegistry {
+    /// 注册计算函数，同名定义会被替换并清除其缓存
+    pub fn register<F>(&self, name: &str, deps: Vec<DerivedDependency>, func: F)
+    where
+        F: Fn(RefnoEnum) -> BoxFuture<'static, anyhow::Result<NamedAttrValue>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.insert_def(DerivedAttrDef {
+            name: normalize_derived_attr_name(name),
+            deps,
+            func: Some(Arc::new(func)),
+        });
+    }
+
+    /// 注册由外部作业写入的派生属性（没有计算函数）
+    pub fn register_stored(&self, name: &str, deps: Vec<DerivedDependency>) {
+        self.insert_def(DerivedAttrDef {
+            name: normalize_derived_attr_name(name),
+            deps,
+            func: None,
+        });
+    }
+
+    fn insert_def(&self, def: DerivedAttrDef) {
+        let name = def.name.clone();
+        self.defs.insert(name.clone(), def);
+        self.cache.retain(|(_, n), _| n != &name);
+    }
+
+    /// 已注册的派生属性名（不含前缀）
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.defs.iter().map(|d| d.key().clone()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.defs.contains_key(&normalize_derived_attr_name(name))
+    }
+
+    /// 直接写入派生值（用于传播类作业的结果）
+    pub fn set_value(&self, refno: RefnoEnum, name: &str, value: NamedAttrValue) {
+        self.cache
+            .insert((refno, normalize_derived_attr_name(name)), value);
+    }
+
+    /// 仅读取缓存，不触发计算
+    pub fn cached_value(&self, refno: RefnoEnum, name: &str) -> Option<NamedAttrValue> {
+        self.cache
+            .get(&(refno, normalize_derived_attr_name(name)))
+            .map(|v| v.clone())
+    }
+
+    /// 获取派生属性值，未命中缓存时计算并缓存
+    pub async fn get(&self, refno: RefnoEnum, name: &str) -> anyhow::Result<Option<NamedAttrValue>> {
+        let name = normalize_derived_attr_name(name);
+        if let Some(v) = self.cache.get(&(refno, name.clone())) {
+            return Ok(Some(v.clone()));
+        }
+        let Some(func) = self.defs.get(&name).and_then(|d| d.func.clone()) else {
+            return Ok(None);
+        };
+        let value = func(refno).await?;
+        self.cache.insert((refno, name), value.clone());
+        Ok(Some(value))
+    }
+
+    /// 元素属性变化时，失效依赖这些属性的派生值
+    pub fn invalidate_attrs(&self, refno: RefnoEnum, changed_attrs: &[&str]) {
+        let affected = self
+            .defs
+            .iter()
+            .filter(|d| changed_attrs.iter().any(|a| d.depends_on_attr(a)))
+            .map(|d| d.key().clone())
+            .collect::<HashSet<_>>();
+        self.cache
+            .retain(|(r, n), _| !(r == &refno && affected.contains(n)));
+    }
+
+    /// 元素几何变化时，失效依赖几何的派生值
+    pub fn invalidate_geometry(&self, refno: RefnoEnum) {
+        let affected = self
+            .defs
+            .iter()
+            .filter(|d| d.depends_on_geometry())
+            .map(|d| d.key().clone())
+            .collect::<HashSet<_>>();
+        self.cache
+            .retain(|(r, n), _| !(r == &refno && affected.contains(n)));
+    }
+
+    /// 全部几何被清除时，失效所有依赖几何的派生值
+    pub fn invalidate_all_geometry(&self) {
+        let affected = self
+            .defs
+            .iter()
+            .filter(|d| d.depends_on_geometry())
+            .map(|d| d.key().clone())
+            .collect::<HashSet<_>>();
+        self.cache.retain(|(_, n), _| !affected.contains(n));
+    }
+
+    /// 失效元素的全部派生值
+    pub fn invalidate_element(&self, refno: RefnoEnum) {
+        self.cache.retain(|(r, _), _| r != &refno);
+    }
+
+    /// 清空全部缓存
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+
+    /// 将派生属性以 `:NAME` 键写入属性 map
+    ///
+    /// `names` 可以带或不带 `:` 前缀；未注册或无法得到值的名称会被跳过。
+    pub async fn fill_attmap(
+        &self,
+        refno: RefnoEnum,
+        attmap: &mut NamedAttrMap,
+        names: &[&str],
+    ) -> anyhow::Result<()> {
+        for name in names {
+            let norm = normalize_derived_attr_name(name);
+            if let Some(value) = self.get(refno, &norm).await? {
+                attmap
+                    .map
+                    .insert(format!("{}{}", DERIVED_ATTR_PREFIX, norm), value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 全局派生属性注册表
+pub static DERIVED_ATTRS: Lazy<DerivedAttrRegistry> = Lazy::new(DerivedAttrRegistry::default);
+
+/// 在全局注册表中注册派生属性
+pub fn register_derived_attr<F>(name: &str, deps: Vec<DerivedDependency>, func: F)
+where
+    F: Fn(RefnoEnum) -> BoxFuture<'static, anyhow::Result<NamedAttrValue>> + Send + Sync + 'static,
+{
+    DERIVED_ATTRS.register(name, deps, func);
+}
+
+/// 从全局注册表获取派生属性值
+#[inline]
+pub async fn get_derived_attr(
+    refno: RefnoEnum,
+    name: &str,
+) -> anyhow::Result<Option<NamedAttrValue>> {
+    DERIVED_ATTRS.get(refno, name).await
+}
+
+/// 查询属性 map，并附加请求的派生伪属性
+///
+/// `names` 中不以 `:` 开头的名称会被忽略，便于直接传入用户输入的属性列表。
+pub async fn get_named_attmap_with_derived(
+    refno: RefnoEnum,
+    names: &[&str],
+) -> anyhow::Result<NamedAttrMap> {
+    let mut attmap = crate::rs_surreal::get_named_attmap(refno).await?;
+    let derived = names
+        .iter()
+        .copied()
+        .filter(|n| is_derived_attr_name(n))
+        .collect::<Vec<_>>();
+    DERIVED_ATTRS.fill_attmap(refno, &mut attmap, &derived).await?;
+    Ok(attmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use futures::FutureExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_cache_and_invalidate() {
+        let registry = DerivedAttrRegistry::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        registry.register(
+            "length",
+            vec![DerivedDependency::Attr("POSS".into()), DerivedDependency::Geometry],
+            move |_| {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(NamedAttrValue::F32Type(n as f32)) }.boxed()
+            },
+        );
+        let refno: RefnoEnum = RefU64::from_two_nums(1, 2).into();
+
+        assert_eq!(
+            registry.get(refno, ":LENGTH").await.unwrap(),
+            Some(NamedAttrValue::F32Type(0.0))
+        );
+        registry.get(refno, "LENGTH").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 无关属性变化不应失效
+        registry.invalidate_attrs(refno, &["NAME"]);
+        registry.get(refno, ":LENGTH").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        registry.invalidate_attrs(refno, &["POSS"]);
+        registry.get(refno, ":LENGTH").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        registry.invalidate_geometry(refno);
+        let mut attmap = NamedAttrMap::default();
+        registry
+            .fill_attmap(refno, &mut attmap, &[":LENGTH", ":UNKNOWN"])
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(attmap.map.len(), 1);
+        assert!(attmap.map.contains_key(":LENGTH"));
+    }
+}
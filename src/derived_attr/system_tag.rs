@@ -0,0 +1,278 @@
+//! 系统/介质代号沿管道连接关系传播
+//!
+//! 系统代号通常只维护在 PIPE 层级，但阀门、仪表和支架也需要它。传播分两步：
+//! 1. PIPE 的代号直接下发给其下所有 BRAN 及元件（层级归属）
+//! 2. 沿连接关系（任意元素的 HREF/TREF/CREF）扩散到未归属的元素（如支架），并继续读取这些元素的连接
+//!
+//! 两个不同系统相接的位置会记录为冲突。结果写入派生属性 `:SYSTEM`，
+//! 可用于过滤和着色规则。
+
+use crate::derived_attr::{DERIVED_ATTRS, DerivedDependency};
+use crate::types::named_attvalue::NamedAttrValue;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, join_pe_keys};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 系统代号派生属性名
+pub const SYSTEM_DERIVED_ATTR: &str = "SYSTEM";
+
+/// 传播使用的连接图
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SystemTagGraph {
+    /// 通过层级归属直接获得代号的元素
+    pub owned: HashMap<RefnoEnum, String>,
+    /// 无向连接关系
+    pub edges: HashMap<RefnoEnum, Vec<RefnoEnum>>,
+}
+
+impl SystemTagGraph {
+    pub fn add_owned(&mut self, refno: RefnoEnum, code: &str) {
+        self.owned.insert(refno, code.to_string());
+    }
+
+    pub fn add_edge(&mut self, a: RefnoEnum, b: RefnoEnum) {
+        if a == b || a.is_unset() || b.is_unset() {
+            return;
+        }
+        self.edges.entry(a).or_default().push(b);
+        self.edges.entry(b).or_default().push(a);
+    }
+
+    /// 执行传播
+    ///
+    /// 未归属的元素按广度优先取最近系统的代号；任意一条连接两端代号不同时记为冲突。
+    pub fn propagate(&self) -> SystemPropagation {
+        let mut tags: HashMap<RefnoEnum, (String, usize)> = self
+            .owned
+            .iter()
+            .map(|(r, c)| (*r, (c.clone(), 0)))
+            .collect();
+        let mut conflicts: BTreeMap<(RefnoEnum, RefnoEnum), SystemConflict> = BTreeMap::new();
+        let mut queue = self
+            .owned
+            .keys()
+            .copied()
+            .collect::<VecDeque<_>>();
+
+        while let Some(cur) = queue.pop_front() {
+            let (code, dist) = tags[&cur].clone();
+            let Some(neighbours) = self.edges.get(&cur) else {
+                continue;
+            };
+            for &n in neighbours {
+                match tags.get(&n) {
+                    None => {
+                        tags.insert(n, (code.clone(), dist + 1));
+                        queue.push_back(n);
+                    }
+                    Some((other, _)) if other != &code => {
+                        // 同一条连接上的冲突只记录一次
+                        let key = if cur < n { (cur, n) } else { (n, cur) };
+                        let mut codes = vec![code.clone(), other.clone()];
+                        codes.sort();
+                        conflicts.entry(key).or_insert(SystemConflict {
+                            at: n,
+                            from: cur,
+                            codes,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        SystemPropagation {
+            tags: tags.into_iter().map(|(r, (c, _))| (r, c)).collect(),
+            conflicts: conflicts.into_values().collect(),
+        }
+    }
+}
+
+/// 两个系统相接的位置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemConflict {
+    pub at: RefnoEnum,
+    pub from: RefnoEnum,
+    /// 相接的系统代号，已排序
+    pub codes: Vec<String>,
+}
+
+/// 传播结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SystemPropagation {
+    pub tags: HashMap<RefnoEnum, String>,
+    pub conflicts: Vec<SystemConflict>,
+}
+
+impl SystemPropagation {
+    /// 将结果写入派生属性 `:SYSTEM`
+    pub fn store(&self, code_attr: &str) {
+        DERIVED_ATTRS.register_stored(
+            SYSTEM_DERIVED_ATTR,
+            vec![DerivedDependency::Attr(code_attr.to_string())],
+        );
+        for (refno, code) in &self.tags {
+            DERIVED_ATTRS.set_value(
+                *refno,
+                SYSTEM_DERIVED_ATTR,
+                NamedAttrValue::StringType(code.clone()),
+            );
+        }
+    }
+
+    /// 按系统代号过滤元素
+    pub fn filter_by_system<'a>(
+        &self,
+        refnos: impl IntoIterator<Item = &'a RefnoEnum>,
+        code: &str,
+    ) -> Vec<RefnoEnum> {
+        refnos
+            .into_iter()
+            .filter(|r| self.tags.get(r).map(|c| c == code).unwrap_or(false))
+            .copied()
+            .collect()
+    }
+
+    /// 为每个系统代号分配稳定的颜色，用于着色规则
+    pub fn color_map(&self) -> BTreeMap<String, [u8; 4]> {
+        let mut codes = self.tags.values().cloned().collect::<Vec<_>>();
+        codes.sort();
+        codes.dedup();
+        codes
+            .into_iter()
+            .map(|c| {
+                let color = system_color(&c);
+                (c, color)
+            })
+            .collect()
+    }
+}
+
+/// 由系统代号计算稳定颜色（HSV 色相取自哈希）
+pub fn system_color(code: &str) -> [u8; 4] {
+    let hash = crate::tool::db_tool::db1_hash(code);
+    let h = (hash % 360) as f32 / 60.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let to_u8 = |v: f32| (55.0 + v * 200.0) as u8;
+    [to_u8(r), to_u8(g), to_u8(b), 255]
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct ConnectRow {
+    id: RefnoEnum,
+    href: Option<RefnoEnum>,
+    tref: Option<RefnoEnum>,
+    cref: Option<RefnoEnum>,
+}
+
+/// 读取元素的连接引用（HREF/TREF/CREF，不限元素类型）并加入连接图，返回连接到的元素
+async fn load_edges(
+    graph: &mut SystemTagGraph,
+    refnos: &[RefnoEnum],
+) -> anyhow::Result<Vec<RefnoEnum>> {
+    let mut targets = vec![];
+    for chunk in refnos.chunks(1000) {
+        let sql = format!(
+            r#"SELECT id, refno.HREF as href, refno.TREF as tref, refno.CREF as cref
+               FROM [{}]"#,
+            join_pe_keys(chunk.iter())
+        );
+        let rows: Vec<ConnectRow> = SUL_DB.query_take(&sql, 0).await?;
+        for row in rows {
+            for target in [row.href, row.tref, row.cref].into_iter().flatten() {
+                graph.add_edge(row.id, target);
+                targets.push(target);
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// 从数据库构建 PIPE 集合的传播图
+///
+/// `code_attr` 为 PIPE 上存放系统代号的属性名（可以是 UDA）。
+/// 先读取 PIPE 下所有元素的连接，再逐层读取被连接到的元素（支架等）自身的连接，直到没有新元素。
+pub async fn build_system_tag_graph(
+    pipes: &[RefnoEnum],
+    code_attr: &str,
+) -> anyhow::Result<SystemTagGraph> {
+    let mut graph = SystemTagGraph::default();
+    let mut visited = HashSet::new();
+    let mut frontier = vec![];
+    for &pipe in pipes {
+        let attmap = crate::rs_surreal::get_named_attmap(pipe).await?;
+        let Some(code) = attmap.get_as_string(code_attr).filter(|c| !c.is_empty()) else {
+            continue;
+        };
+        let mut members = crate::rs_surreal::query_deep_children_refnos(pipe).await?;
+        members.push(pipe);
+        for &m in &members {
+            graph.add_owned(m, &code);
+            if visited.insert(m) {
+                frontier.push(m);
+            }
+        }
+    }
+    while !frontier.is_empty() {
+        frontier = load_edges(&mut graph, &frontier)
+            .await?
+            .into_iter()
+            .filter(|t| !t.is_unset() && visited.insert(*t))
+            .collect();
+    }
+    Ok(graph)
+}
+
+/// 传播作业：构建连接图、传播并写入派生属性
+pub async fn propagate_system_codes(
+    pipes: &[RefnoEnum],
+    code_attr: &str,
+) -> anyhow::Result<SystemPropagation> {
+    let graph = build_system_tag_graph(pipes, code_attr).await?;
+    let result = graph.propagate();
+    result.store(code_attr);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(1, n).into()
+    }
+
+    #[test]
+    fn test_propagate_to_supports_and_detect_conflict() {
+        let mut g = SystemTagGraph::default();
+        // 系统 A：bran 1 + 元件 2；系统 B：bran 3
+        g.add_owned(r(1), "A");
+        g.add_owned(r(2), "A");
+        g.add_owned(r(3), "B");
+        // 元件 2 通过 CREF 连接支架 10，支架 10 再连接支架 11
+        g.add_edge(r(2), r(10));
+        g.add_edge(r(10), r(11));
+        // bran 3 的 HREF 连到系统 A 的元件 2
+        g.add_edge(r(3), r(2));
+
+        let res = g.propagate();
+        assert_eq!(res.tags[&r(10)], "A");
+        assert_eq!(res.tags[&r(11)], "A");
+        assert_eq!(res.tags[&r(3)], "B");
+        assert_eq!(res.conflicts.len(), 1);
+        assert_eq!(res.conflicts[0].codes, vec!["A".to_string(), "B".to_string()]);
+        assert_eq!(res.filter_by_system([r(1), r(3), r(11)].iter(), "A"), vec![r(1), r(11)]);
+        assert_eq!(system_color("A"), system_color("A"));
+    }
+}
@@ -0,0 +1,238 @@
+//! 单元素全流程诊断
+//!
+//! 排查单个元素（如某个显示异常的阀门）时，需要依次查看属性、元件库、变换、几何和布尔运算的结果。
+//! [`diagnose_element`] 对一个元素依次执行这些阶段，记录每个阶段的耗时、错误和中间结果，
+//! 汇总成可序列化的 [`DiagnoseBundle`]，可直接写成 JSON 附到工单里。
+//!
+//! 某个阶段失败不会中断后续阶段。
+
+use crate::facade::geometry_backend;
+use crate::utils::with_refno_span;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::future::Future;
+use std::path::Path;
+use std::time::Instant;
+
+/// 诊断阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnoseStage {
+    /// 元素与属性
+    Attributes,
+    /// 元件库（CATR）解析
+    Catalogue,
+    /// 局部/世界变换
+    Transform,
+    /// 几何实例（可选重新生成）
+    Geometry,
+    /// 负实体与布尔运算
+    Boolean,
+}
+
+impl DiagnoseStage {
+    pub const ALL: [DiagnoseStage; 5] = [
+        DiagnoseStage::Attributes,
+        DiagnoseStage::Catalogue,
+        DiagnoseStage::Transform,
+        DiagnoseStage::Geometry,
+        DiagnoseStage::Boolean,
+    ];
+}
+
+/// 诊断选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnoseOptions {
+    /// 要执行的阶段，按给定顺序执行
+    pub stages: Vec<DiagnoseStage>,
+    /// 计算变换前清除世界变换缓存
+    pub fresh_transform: bool,
+    /// 通过已注册的几何后端重新生成几何，未注册时该阶段报错
+    pub regen_geometry: bool,
+    /// 是否在报告中保留中间结果，关闭时只保留耗时和错误
+    pub include_artifacts: bool,
+}
+
+impl Default for DiagnoseOptions {
+    fn default() -> Self {
+        Self {
+            stages: DiagnoseStage::ALL.to_vec(),
+            fresh_transform: true,
+            regen_geometry: false,
+            include_artifacts: true,
+        }
+    }
+}
+
+/// 单个阶段的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageReport {
+    pub stage: DiagnoseStage,
+    pub ok: bool,
+    pub elapsed_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 中间结果
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub artifact: Value,
+}
+
+/// 诊断报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnoseBundle {
+    pub refno: RefnoEnum,
+    pub noun: Option<String>,
+    pub created_at: String,
+    pub total_ms: f64,
+    pub options: DiagnoseOptions,
+    pub stages: Vec<StageReport>,
+}
+
+impl DiagnoseBundle {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.stages.iter().all(|s| s.ok)
+    }
+
+    pub fn stage(&self, stage: DiagnoseStage) -> Option<&StageReport> {
+        self.stages.iter().find(|s| s.stage == stage)
+    }
+
+    pub fn to_json_pretty(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 写出 JSON 文件
+    pub fn write_to(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_json_pretty()?)?;
+        Ok(())
+    }
+}
+
+async fn run_stage<F>(stage: DiagnoseStage, keep_artifact: bool, fut: F) -> StageReport
+where
+    F: Future<Output = anyhow::Result<Value>>,
+{
+    let start = Instant::now();
+    let result = fut.await;
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+    match result {
+        Ok(artifact) => StageReport {
+            stage,
+            ok: true,
+            elapsed_ms,
+            error: None,
+            artifact: if keep_artifact { artifact } else { Value::Null },
+        },
+        Err(e) => {
+            log::warn!("诊断阶段 {:?} 失败: {:#}", stage, e);
+            StageReport {
+                stage,
+                ok: false,
+                elapsed_ms,
+                error: Some(format!("{:#}", e)),
+                artifact: Value::Null,
+            }
+        }
+    }
+}
+
+async fn diagnose_attributes(refno: RefnoEnum) -> anyhow::Result<Value> {
+    let pe = crate::get_pe(refno)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("元素不存在: {}", refno))?;
+    let attmap = crate::get_named_attmap(refno).await?;
+    Ok(json!({ "pe": pe, "attmap": attmap }))
+}
+
+async fn diagnose_catalogue(refno: RefnoEnum) -> anyhow::Result<Value> {
+    let cat_refno = crate::get_cat_refno(refno).await?;
+    let cat_attmap = match cat_refno {
+        Some(_) => Some(crate::get_cat_attmap(refno).await?),
+        None => None,
+    };
+    Ok(json!({ "cat_refno": cat_refno, "cat_attmap": cat_attmap }))
+}
+
+async fn diagnose_transform(refno: RefnoEnum, fresh: bool) -> anyhow::Result<Value> {
+    if fresh {
+        crate::transform::invalidate_world_trans_cache(refno).await?;
+    }
+    let local = crate::transform::get_local_mat4(refno).await?;
+    let world = crate::transform::get_world_mat4(refno, false).await?;
+    if world.is_none() {
+        anyhow::bail!("无法计算世界变换");
+    }
+    Ok(json!({
+        "local": local.map(|m| m.to_cols_array()),
+        "world": world.map(|m| m.to_cols_array()),
+    }))
+}
+
+async fn diagnose_geometry(refno: RefnoEnum, regen: bool) -> anyhow::Result<Value> {
+    if regen {
+        geometry_backend()?.regen(&[refno], None).await?;
+    }
+    let insts = crate::rs_surreal::inst::query_insts(&[refno], true).await?;
+    if insts.is_empty() {
+        anyhow::bail!("没有几何实例");
+    }
+    Ok(json!({ "insts": insts }))
+}
+
+async fn diagnose_boolean(refno: RefnoEnum) -> anyhow::Result<Value> {
+    let negatives = crate::rs_surreal::boolean_query::query_negative_entities(refno).await?;
+    let cata_neg_groups =
+        crate::rs_surreal::boolean_query::query_cata_neg_boolean_groups(&[refno], true).await?;
+    let sql = format!(
+        "select value bool_status from {}->inst_relate",
+        refno.to_pe_key()
+    );
+    let bool_status: Vec<Option<String>> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(json!({
+        "negatives": negatives,
+        "cata_neg_groups": cata_neg_groups,
+        "bool_status": bool_status,
+    }))
+}
+
+/// 按默认选项诊断单个元素
+pub async fn diagnose_element(refno: RefnoEnum) -> DiagnoseBundle {
+    diagnose_element_with(refno, &DiagnoseOptions::default()).await
+}
+
+/// 按指定选项诊断单个元素
+pub async fn diagnose_element_with(refno: RefnoEnum, options: &DiagnoseOptions) -> DiagnoseBundle {
+    with_refno_span(refno, async move {
+        let start = Instant::now();
+        let keep = options.include_artifacts;
+        let mut stages = Vec::with_capacity(options.stages.len());
+        for &stage in &options.stages {
+            let report = match stage {
+                DiagnoseStage::Attributes => {
+                    run_stage(stage, keep, diagnose_attributes(refno)).await
+                }
+                DiagnoseStage::Catalogue => run_stage(stage, keep, diagnose_catalogue(refno)).await,
+                DiagnoseStage::Transform => {
+                    run_stage(stage, keep, diagnose_transform(refno, options.fresh_transform)).await
+                }
+                DiagnoseStage::Geometry => {
+                    run_stage(stage, keep, diagnose_geometry(refno, options.regen_geometry)).await
+                }
+                DiagnoseStage::Boolean => run_stage(stage, keep, diagnose_boolean(refno)).await,
+            };
+            stages.push(report);
+        }
+        let noun = crate::get_pe(refno).await.ok().flatten().map(|pe| pe.noun);
+        DiagnoseBundle {
+            refno,
+            noun,
+            created_at: chrono::Local::now().to_rfc3339(),
+            total_ms: start.elapsed().as_secs_f64() * 1000.0,
+            options: options.clone(),
+            stages,
+        }
+    })
+    .await
+}
@@ -0,0 +1,8 @@
+//! 电气专业功能
+
+pub mod tray_fill;
+
+pub use tray_fill::{
+    CableSource, RoutedCable, TRAY_FILL_REVIEW_TABLE, TrayFill, TrayFillLimits, TrayFillReview,
+    TraySection, save_tray_fill_review, tray_fill,
+};
@@ -0,0 +1,384 @@
+//! 电缆托盘填充率校核
+//!
+//! 托盘段（BRAN / GENSEC）的截面取 UDA `/BranWidth`、`/BranHigh`（元素上没有时取其 owner），
+//! 敷设电缆来自托盘上的 UDA 或外部电缆清单 CSV。填充率为电缆截面积之和
+//! 与托盘截面积之比（%），超过限值的托盘段写入 [`TRAY_FILL_REVIEW_TABLE`]。
+
+use crate::{
+    NamedAttrMap, RefnoEnum, SUL_DB, SurrealQueryExt, get_named_attmap, get_uda_value,
+    insert_into_table_with_chunks, query_filter_deep_children,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 填充率超限记录表
+pub const TRAY_FILL_REVIEW_TABLE: &str = "tray_fill_review";
+
+/// 参与校核的托盘类型
+pub const TRAY_NOUNS: [&str; 2] = ["BRAN", "GENSEC"];
+
+/// 电缆来源
+#[derive(Debug, Clone)]
+pub enum CableSource {
+    /// 托盘上的 UDA，格式 `电缆号:外径;电缆号:外径`，外径单位 mm
+    Attribute(String),
+    /// 外部电缆清单，列为 `托盘段号,电缆号,外径`，首行为表头
+    Csv(PathBuf),
+}
+
+impl Default for CableSource {
+    fn default() -> Self {
+        Self::Attribute("/Cables".to_string())
+    }
+}
+
+/// 填充率限值（%）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrayFillLimits {
+    pub default_limit: f32,
+    /// 按托盘类型覆盖，例如 梯架 / 实底托盘
+    #[serde(default)]
+    pub by_type: HashMap<String, f32>,
+}
+
+impl Default for TrayFillLimits {
+    fn default() -> Self {
+        Self {
+            default_limit: 40.0,
+            by_type: HashMap::new(),
+        }
+    }
+}
+
+impl TrayFillLimits {
+    pub fn limit_for(&self, tray_type: Option<&str>) -> f32 {
+        tray_type
+            .and_then(|t| self.by_type.get(t))
+            .copied()
+            .unwrap_or(self.default_limit)
+    }
+}
+
+/// 托盘段截面
+#[derive(Debug, Clone, Default)]
+pub struct TraySection {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    /// 托盘段号（去掉前导 `/`）
+    pub name: String,
+    pub tray_type: Option<String>,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TraySection {
+    #[inline]
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+}
+
+/// 敷设在托盘中的电缆
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutedCable {
+    pub cable: String,
+    /// 外径 (mm)
+    pub diameter: f32,
+}
+
+impl RoutedCable {
+    #[inline]
+    pub fn area(&self) -> f32 {
+        PI * self.diameter * self.diameter / 4.0
+    }
+}
+
+/// 单个托盘段的填充结果
+#[derive(Debug, Clone)]
+pub struct TrayFill {
+    pub section: TraySection,
+    pub cables: Vec<RoutedCable>,
+    pub cable_area: f32,
+    /// 填充率 (%)，托盘截面无效时为 None
+    pub fill: Option<f32>,
+    pub limit: f32,
+}
+
+impl TrayFill {
+    pub fn compute(
+        section: TraySection,
+        cables: Vec<RoutedCable>,
+        limits: &TrayFillLimits,
+    ) -> Self {
+        let cable_area = cables.iter().map(RoutedCable::area).sum::<f32>();
+        let area = section.area();
+        let fill = (area > f32::EPSILON).then(|| cable_area / area * 100.0);
+        let limit = limits.limit_for(section.tray_type.as_deref());
+        Self {
+            section,
+            cables,
+            cable_area,
+            fill,
+            limit,
+        }
+    }
+
+    /// 有电缆但截面缺失，或填充率超过限值
+    pub fn is_violation(&self) -> bool {
+        match self.fill {
+            Some(fill) => fill > self.limit,
+            None => !self.cables.is_empty(),
+        }
+    }
+
+    fn review_row(&self, root: RefnoEnum) -> TrayFillReview {
+        TrayFillReview {
+            id: self.section.refno.refno().to_slash_string(),
+            root: root.refno().to_slash_string(),
+            name: self.section.name.clone(),
+            noun: self.section.noun.clone(),
+            tray_type: self.section.tray_type.clone().unwrap_or_default(),
+            width: self.section.width,
+            height: self.section.height,
+            cable_count: self.cables.len() as i32,
+            cable_area: self.cable_area,
+            fill: self.fill.unwrap_or(-1.0),
+            limit: self.limit,
+            cables: self.cables.iter().map(|c| c.cable.clone()).collect(),
+        }
+    }
+}
+
+/// 填充率超限记录
+#[derive(Debug, Clone, Serialize, Deserialize, SurrealValue)]
+pub struct TrayFillReview {
+    pub id: String,
+    pub root: String,
+    pub name: String,
+    pub noun: String,
+    pub tray_type: String,
+    pub width: f32,
+    pub height: f32,
+    pub cable_count: i32,
+    pub cable_area: f32,
+    /// 填充率 (%)，截面缺失时为 -1
+    pub fill: f32,
+    pub limit: f32,
+    pub cables: Vec<String>,
+}
+
+/// 解析 UDA 中的电缆列表，无法解析的条目跳过
+pub fn parse_cable_list(text: &str) -> Vec<RoutedCable> {
+    text.split([';', '\n'])
+        .filter_map(|item| {
+            let (cable, dia) = item.split_once(':')?;
+            let diameter = dia.trim().parse::<f32>().ok()?;
+            let cable = cable.trim();
+            (!cable.is_empty() && diameter > 0.0).then(|| RoutedCable {
+                cable: cable.to_string(),
+                diameter,
+            })
+        })
+        .collect()
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// 解析电缆清单 CSV，按托盘段号分组（段号去掉前导 `/`）
+pub fn parse_cable_csv(text: &str) -> anyhow::Result<HashMap<String, Vec<RoutedCable>>> {
+    let mut map: HashMap<String, Vec<RoutedCable>> = HashMap::new();
+    let text = text.trim_start_matches('\u{feff}');
+    for (i, line) in text.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        let [tray, cable, dia, ..] = fields.as_slice() else {
+            anyhow::bail!("电缆清单第 {} 行列数不足: {}", i + 1, line);
+        };
+        let diameter = dia
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| anyhow::anyhow!("电缆清单第 {} 行外径无效: {}", i + 1, dia))?;
+        map.entry(tray.trim().trim_start_matches('/').to_string())
+            .or_default()
+            .push(RoutedCable {
+                cable: cable.trim().to_string(),
+                diameter,
+            });
+    }
+    Ok(map)
+}
+
+pub fn load_cable_csv(path: &Path) -> anyhow::Result<HashMap<String, Vec<RoutedCable>>> {
+    parse_cable_csv(&std::fs::read_to_string(path)?)
+}
+
+async fn uda_f32(refno: RefnoEnum, uda: &str) -> anyhow::Result<Option<f32>> {
+    Ok(get_uda_value(refno.refno(), uda)
+        .await?
+        .and_then(|v| v.trim().parse::<f32>().ok())
+        .filter(|v| *v > 0.0))
+}
+
+/// 读取托盘段截面
+pub async fn load_tray_section(refno: RefnoEnum) -> anyhow::Result<TraySection> {
+    let att: NamedAttrMap = get_named_attmap(refno).await?;
+    let owner = att.get_owner();
+    let mut dims = [0.0f32; 2];
+    for (dim, uda) in dims.iter_mut().zip(["/BranWidth", "/BranHigh"]) {
+        *dim = match uda_f32(refno, uda).await? {
+            Some(v) => v,
+            None if owner.is_valid() => uda_f32(owner, uda).await?.unwrap_or_default(),
+            None => 0.0,
+        };
+    }
+    let tray_type: Option<String> = SUL_DB
+        .query_take(
+            &format!("return fn::get_tray_type({})", refno.to_pe_key()),
+            0,
+        )
+        .await
+        .ok()
+        .flatten()
+        .filter(|t: &String| !t.is_empty());
+    Ok(TraySection {
+        refno,
+        noun: att.get_type_str().to_string(),
+        name: att
+            .get_name_or_default()
+            .trim_start_matches('/')
+            .to_string(),
+        tray_type,
+        width: dims[0],
+        height: dims[1],
+    })
+}
+
+/// 计算 `root` 下所有托盘段的填充率
+pub async fn tray_fill(
+    root: RefnoEnum,
+    source: &CableSource,
+    limits: &TrayFillLimits,
+) -> anyhow::Result<Vec<TrayFill>> {
+    let mut csv = match source {
+        CableSource::Csv(path) => load_cable_csv(path)?,
+        CableSource::Attribute(_) => HashMap::new(),
+    };
+    let mut fills = Vec::new();
+    for refno in query_filter_deep_children(root, &TRAY_NOUNS).await? {
+        let section = load_tray_section(refno).await?;
+        let cables = match source {
+            CableSource::Attribute(uda) => get_uda_value(refno.refno(), uda)
+                .await?
+                .map(|v| parse_cable_list(&v))
+                .unwrap_or_default(),
+            CableSource::Csv(_) => csv
+                .remove(&section.name)
+                .or_else(|| csv.remove(&refno.refno().to_slash_string()))
+                .unwrap_or_default(),
+        };
+        fills.push(TrayFill::compute(section, cables, limits));
+    }
+    Ok(fills)
+}
+
+/// 校核填充率并把超限的托盘段写入 [`TRAY_FILL_REVIEW_TABLE`]，返回超限记录
+pub async fn save_tray_fill_review(
+    root: RefnoEnum,
+    source: &CableSource,
+    limits: &TrayFillLimits,
+) -> anyhow::Result<Vec<TrayFillReview>> {
+    let rows: Vec<TrayFillReview> = tray_fill(root, source, limits)
+        .await?
+        .iter()
+        .filter(|f| f.is_violation())
+        .map(|f| f.review_row(root))
+        .collect();
+    SUL_DB
+        .query_response(&format!(
+            "DELETE {TRAY_FILL_REVIEW_TABLE} WHERE root = {}",
+            serde_json::to_string(&root.refno().to_slash_string())?
+        ))
+        .await?;
+    if !rows.is_empty() {
+        insert_into_table_with_chunks(&SUL_DB, TRAY_FILL_REVIEW_TABLE, rows.clone()).await?;
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(width: f32, height: f32, tray_type: Option<&str>) -> TraySection {
+        TraySection {
+            name: "T-001".into(),
+            tray_type: tray_type.map(String::from),
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_cable_list() {
+        let cables = parse_cable_list("C1:25.4; C2:18 ;bad;C3:x");
+        assert_eq!(cables.len(), 2);
+        assert_eq!(cables[1].cable, "C2");
+        assert_eq!(cables[1].diameter, 18.0);
+    }
+
+    #[test]
+    fn test_parse_cable_csv() {
+        let text =
+            "\u{feff}托盘段号,电缆号,外径\n/T-001,C1,20\n\"T-001\",\"C,2\",30\n\nT-002,C3,10\n";
+        let map = parse_cable_csv(text).unwrap();
+        assert_eq!(map["T-001"].len(), 2);
+        assert_eq!(map["T-001"][1].cable, "C,2");
+        assert_eq!(map["T-002"][0].diameter, 10.0);
+        assert!(parse_cable_csv("h\nT-001,C1,abc\n").is_err());
+    }
+
+    #[test]
+    fn test_fill_against_limits() {
+        let mut limits = TrayFillLimits::default();
+        limits.by_type.insert("梯架".into(), 50.0);
+        // 10 根 φ40 电缆 ≈ 12566 mm²，托盘 300x100 → 41.9%
+        let cables = (0..10)
+            .map(|i| RoutedCable {
+                cable: format!("C{i}"),
+                diameter: 40.0,
+            })
+            .collect::<Vec<_>>();
+        let fill = TrayFill::compute(section(300.0, 100.0, None), cables.clone(), &limits);
+        assert!((fill.fill.unwrap() - 41.89).abs() < 0.01);
+        assert!(fill.is_violation());
+        let fill = TrayFill::compute(section(300.0, 100.0, Some("梯架")), cables.clone(), &limits);
+        assert!(!fill.is_violation());
+        let fill = TrayFill::compute(section(0.0, 100.0, None), cables, &limits);
+        assert!(fill.fill.is_none() && fill.is_violation());
+    }
+}
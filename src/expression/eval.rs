@@ -0,0 +1,442 @@
+//! 基于属性表的 PDMS 表达式求值
+//!
+//! [`eval_with_attmap`] 把 [`NamedAttrMap`] 的属性和 [`CataParams`] 的元件库参数（PARA/IPAR/OPAR/APAR）
+//! 填入 [`CataContext`]，再交给 [`eval_str_to_f64`] 求解，语法与元件库表达式一致：
+//! 三角函数使用角度，数组下标从 1 开始、小数向下取整，`IFTRUE`、比较与逻辑运算由 `tiny_expr` 求解。
+//!
+//! 单个属性引用返回属性原本的类型，比较与逻辑表达式返回 [`AttrVal::BoolType`]，其余返回数值。
+//! 未知属性或缺少的参数在 [`ExprError`] 中给出其在表达式中的位置。
+
+use crate::attval::AttrVal;
+use crate::rs_surreal::resolve::{
+    DDANGLE_STR, DDHEIGHT_STR, DDRADIUS_STR, INTERNAL_PDMS_EXPRESS, eval_str_to_f64,
+};
+use crate::tiny_expr::expr_eval::is_builtin;
+use crate::{CataContext, NamedAttrMap, NamedAttrValue};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::ops::Range;
+
+/// 表达式求值错误，`span` 为出错部分在表达式中的字节范围
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{message} (位置 {}..{})", span.start, span.end)]
+pub struct ExprError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl ExprError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// 生成带 `^` 标记的错误提示，便于在日志中定位
+    pub fn render(&self, expr: &str) -> String {
+        let start = expr[..self.span.start.min(expr.len())].chars().count();
+        let width = expr
+            .get(self.span.clone())
+            .map(|s| s.chars().count())
+            .unwrap_or(1)
+            .max(1);
+        format!(
+            "{}\n{}{} {}",
+            expr,
+            " ".repeat(start),
+            "^".repeat(width),
+            self.message
+        )
+    }
+}
+
+pub type ExprResult<T> = Result<T, ExprError>;
+
+/// 元件库参数
+#[derive(Debug, Clone, Default)]
+pub struct CataParams {
+    /// 元件库参数 PARA / PARAM / CPAR
+    pub para: Vec<f64>,
+    /// 保温参数 IPAR / IPARA
+    pub ipar: Vec<f64>,
+    /// owner 的元件库参数 OPAR
+    pub opar: Vec<f64>,
+    /// 连接元件的元件库参数 APAR
+    pub apar: Vec<f64>,
+}
+
+impl CataParams {
+    /// 从元件库元素（SCOM/SPRF 等）的属性中读取 PARA
+    pub fn from_cata_attmap(cata_att: &NamedAttrMap) -> Self {
+        Self {
+            para: to_f64_vec(cata_att.get_f32_vec("PARA").unwrap_or_default()),
+            ..Default::default()
+        }
+    }
+
+    /// 按 `get_or_create_cata_context` 的键名写入上下文
+    fn fill_context(&self, context: &CataContext) {
+        let groups: [(&[f64], &[&str]); 4] = [
+            (&self.para, &["PARA", "PARAM", "CPAR"]),
+            (&self.ipar, &["IPAR", "IPARA", "IPARAM"]),
+            (&self.opar, &["OPAR"]),
+            (&self.apar, &["APAR"]),
+        ];
+        for (values, names) in groups {
+            for (i, v) in values.iter().enumerate() {
+                for name in names {
+                    context.insert(format!("{name}{}", i + 1), v.to_string());
+                }
+            }
+        }
+    }
+}
+
+impl From<&CataContext> for CataParams {
+    fn from(context: &CataContext) -> Self {
+        let collect = |prefix: &str| {
+            (1..)
+                .map_while(|i| context.get(format!("{prefix}{i}")))
+                .map(|v| v.parse::<f64>().unwrap_or_default())
+                .collect()
+        };
+        Self {
+            para: collect("PARA"),
+            ipar: collect("IPAR"),
+            opar: collect("OPAR"),
+            apar: collect("APAR"),
+        }
+    }
+}
+
+fn to_f64_vec(v: Vec<f32>) -> Vec<f64> {
+    v.into_iter().map(|x| x as f64).collect()
+}
+
+/// 由属性表和元件库参数构建 [`eval_str_to_f64`] 使用的上下文
+///
+/// 数值属性按名称写入，数组与坐标按 `名称+序号` 写入（`DESP` 另有别名 `DESI`），逻辑值写为 1/0。
+pub fn attmap_context(attmap: &NamedAttrMap, cata_params: &CataParams) -> CataContext {
+    let context = CataContext::default();
+    let insert_array = |name: &str, values: Vec<String>| {
+        for (i, v) in values.into_iter().enumerate() {
+            if name == "DESP" {
+                context.insert(format!("DESI{}", i + 1), v.clone());
+            }
+            context.insert(format!("{name}{}", i + 1), v);
+        }
+    };
+    for (key, value) in &attmap.map {
+        let name = key.to_uppercase();
+        match value {
+            NamedAttrValue::IntegerType(v) => {
+                context.insert(name, v.to_string());
+            }
+            NamedAttrValue::LongType(v) => {
+                context.insert(name, v.to_string());
+            }
+            NamedAttrValue::F32Type(v) => {
+                context.insert(name, v.to_string());
+            }
+            NamedAttrValue::BoolType(v) => {
+                context.insert(name, if *v { "1" } else { "0" }.to_string());
+            }
+            NamedAttrValue::F32VecType(v) => {
+                insert_array(&name, v.iter().map(f32::to_string).collect())
+            }
+            NamedAttrValue::IntArrayType(v) => {
+                insert_array(&name, v.iter().map(i32::to_string).collect())
+            }
+            NamedAttrValue::Vec3Type(v) => {
+                insert_array(&name, v.to_array().iter().map(f32::to_string).collect())
+            }
+            _ => {}
+        }
+    }
+    for (key, attr) in [
+        (DDHEIGHT_STR, "HEIG"),
+        (DDANGLE_STR, "ANGL"),
+        (DDRADIUS_STR, "RADI"),
+    ] {
+        let v = context.get(attr).unwrap_or_else(|| "0.0".to_string());
+        context.insert(key.to_string(), v);
+    }
+    context.insert(
+        "RS_DES_REFNO".to_string(),
+        attmap.get_refno_or_default().to_string(),
+    );
+    cata_params.fill_context(&context);
+    context
+}
+
+/// 用属性表和元件库参数求解表达式
+pub fn eval_with_attmap(
+    expr: &str,
+    attmap: &NamedAttrMap,
+    cata_params: &CataParams,
+) -> ExprResult<AttrVal> {
+    // 只改 ASCII 大小写，保证错误位置与原表达式一致
+    let upper = expr.to_ascii_uppercase();
+    if let Some(v) = plain_attr(&upper, attmap) {
+        return Ok(v);
+    }
+    let context = attmap_context(attmap, cata_params);
+    check_names(&upper, &context)?;
+    let v = eval_str_to_f64(upper.trim(), &context, "")
+        .map_err(|e| ExprError::new(e.to_string(), 0..expr.len()))?;
+    Ok(if is_predicate(&upper) {
+        AttrVal::BoolType(v != 0.0)
+    } else {
+        AttrVal::DoubleType(v)
+    })
+}
+
+/// 求解为数值
+pub fn eval_with_attmap_to_f64(
+    expr: &str,
+    attmap: &NamedAttrMap,
+    cata_params: &CataParams,
+) -> ExprResult<f64> {
+    match eval_with_attmap(expr, attmap, cata_params)? {
+        AttrVal::DoubleType(d) => Ok(d),
+        AttrVal::IntegerType(i) => Ok(i as f64),
+        AttrVal::BoolType(b) => Ok(if b { 1.0 } else { 0.0 }),
+        _ => Err(ExprError::new("表达式的值不是数值", 0..expr.len())),
+    }
+}
+
+static NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Z_:][A-Z0-9_:]*").unwrap());
+
+/// 名称后的下标：`[1]`、`[1.5]` 或 `PARAM 2` 里空格加序号的写法
+static INDEX_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\s*\[\s*(\d+(?:\.\d+)?)\s*\]|\s+(\d+(?:\.\d+)?))").unwrap());
+
+/// 可以带下标引用的参数
+const INDEXED_PARAMS: [&str; 13] = [
+    "PARA", "PARAM", "CPAR", "IPAR", "IPARA", "IPARAM", "OPAR", "APAR", "DESP", "DESI", "ODES",
+    "ADES", "DDES",
+];
+
+/// 运算符、关键字等不是属性的名称
+const KEYWORDS: [&str; 17] = [
+    "ATTRIB",
+    "DESIGN",
+    "IFTRUE",
+    "LT",
+    "GT",
+    "LE",
+    "GE",
+    "EQ",
+    "NE",
+    "TWICE",
+    "DIFFERENCE",
+    "SUBTRACT",
+    "MINUS",
+    "RPRO",
+    DDHEIGHT_STR,
+    DDRADIUS_STR,
+    DDANGLE_STR,
+];
+
+/// 单个属性引用（可带 `ATTRIB` 前缀），直接返回属性原本的类型
+fn plain_attr(upper: &str, attmap: &NamedAttrMap) -> Option<AttrVal> {
+    let name = upper.trim();
+    let name = name.strip_prefix("ATTRIB ").unwrap_or(name).trim();
+    if NAME_RE
+        .find(name)
+        .is_none_or(|m| m.range() != (0..name.len()))
+    {
+        return None;
+    }
+    attmap.get_val(name).map(to_attr_val)
+}
+
+/// 检查表达式中的属性与参数都能在上下文中找到，找不到时报告其位置
+fn check_names(upper: &str, context: &CataContext) -> ExprResult<()> {
+    let mut after_rpro = false;
+    let mut after_design = false;
+    for m in NAME_RE.find_iter(upper) {
+        let word = m.as_str();
+        // `RPRO XXX` 的属性名由 eval_str_to_f64 查找
+        if std::mem::replace(&mut after_rpro, word == "RPRO") {
+            continue;
+        }
+        // OF 引用需要查询其他元素
+        if word == "OF" {
+            return Err(ExprError::new(
+                "不支持 OF 引用，请先用 query_attr_path 取值",
+                m.range(),
+            ));
+        }
+        // DESIGN PARAM 即 DESP
+        let design = std::mem::replace(&mut after_design, word == "DESIGN");
+        if context.contains_key(word)
+            || KEYWORDS.contains(&word)
+            || INTERNAL_PDMS_EXPRESS.contains(&word)
+            || is_builtin(&word.to_ascii_lowercase())
+        {
+            continue;
+        }
+        let base = word.trim_end_matches(|c: char| c.is_ascii_digit());
+        let is_param = INDEXED_PARAMS.contains(&base);
+        // `PARA3` 把序号写在名字里，`PARA[3]` / `PARAM 3` 写在名字后；其他数组属性只支持 `[3]`
+        let indexed = if is_param && base.len() < word.len() {
+            Some((base, word[base.len()..].parse::<f64>().ok(), m.end()))
+        } else {
+            INDEX_RE
+                .captures(&upper[m.end()..])
+                .filter(|c| is_param || c.get(2).is_some())
+                .map(|c| {
+                    let index = c.get(2).or(c.get(3)).and_then(|x| x.as_str().parse().ok());
+                    (word, index, m.end() + c[0].len())
+                })
+        };
+        let Some((name, index, end)) = indexed else {
+            return Err(ExprError::new(format!("未知属性 {word}"), m.range()));
+        };
+        let name = if design { "DESP" } else { name };
+        if !index.is_some_and(|i: f64| context.contains_key(format!("{name}{}", i.floor()))) {
+            return Err(ExprError::new(
+                format!("{word} 缺少序号或序号越界"),
+                m.start()..end,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 顶层（括号外）是否为比较或逻辑运算
+fn is_predicate(upper: &str) -> bool {
+    let mut depth = 0usize;
+    let mut top = String::with_capacity(upper.len());
+    for c in upper.chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                top.push(' ');
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                top.push(' ');
+            }
+            _ if depth == 0 => top.push(c),
+            _ => {}
+        }
+    }
+    top.contains(['<', '>', '=', '!'])
+        || top.split_whitespace().any(|w| {
+            matches!(
+                w,
+                "LT" | "GT" | "LE" | "GE" | "EQ" | "NE" | "AND" | "OR" | "NOT"
+            )
+        })
+}
+
+fn to_attr_val(v: &NamedAttrValue) -> AttrVal {
+    match v {
+        NamedAttrValue::InvalidType => AttrVal::InvalidType,
+        NamedAttrValue::IntegerType(i) => AttrVal::IntegerType(*i),
+        NamedAttrValue::LongType(i) => AttrVal::DoubleType(*i as f64),
+        NamedAttrValue::F32Type(d) => AttrVal::DoubleType(*d as f64),
+        NamedAttrValue::F32VecType(d) => AttrVal::DoubleArrayType(to_f64_vec(d.clone())),
+        NamedAttrValue::Vec3Type(d) => AttrVal::Vec3Type(d.as_dvec3().to_array()),
+        NamedAttrValue::StringType(s) => AttrVal::StringType(s.clone()),
+        NamedAttrValue::WordType(s) => AttrVal::WordType(s.clone()),
+        NamedAttrValue::ElementType(s) => AttrVal::ElementType(s.clone()),
+        NamedAttrValue::StringArrayType(s) => AttrVal::StringArrayType(s.clone()),
+        NamedAttrValue::BoolArrayType(b) => AttrVal::BoolArrayType(b.clone()),
+        NamedAttrValue::IntArrayType(i) => AttrVal::IntArrayType(i.clone()),
+        NamedAttrValue::BoolType(b) => AttrVal::BoolType(*b),
+        NamedAttrValue::RefU64Type(r) => AttrVal::RefU64Type(*r),
+        NamedAttrValue::RefnoEnumType(r) => AttrVal::RefU64Type(r.refno()),
+        NamedAttrValue::RefU64Array(r) => {
+            AttrVal::RefU64Array(r.iter().map(|x| x.refno()).collect::<Vec<_>>().into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    fn attmap() -> NamedAttrMap {
+        let mut att = NamedAttrMap::default();
+        att.insert(
+            "DESP".to_string(),
+            NamedAttrValue::F32VecType(vec![50.0, 2.5, 10.0]),
+        );
+        att.insert("HEIG".to_string(), NamedAttrValue::F32Type(300.0));
+        att.insert("NUMB".to_string(), NamedAttrValue::IntegerType(4));
+        att.insert(
+            "FUNC".to_string(),
+            NamedAttrValue::StringType("PIPE".into()),
+        );
+        att.insert(
+            "POS".to_string(),
+            NamedAttrValue::Vec3Type(Vec3::new(1.0, 2.0, 3.0)),
+        );
+        att
+    }
+
+    fn eval(expr: &str) -> ExprResult<AttrVal> {
+        let params = CataParams {
+            para: vec![100.0, 20.0],
+            ..Default::default()
+        };
+        eval_with_attmap(expr, &attmap(), &params)
+    }
+
+    fn num(expr: &str) -> f64 {
+        let params = CataParams {
+            para: vec![100.0, 20.0],
+            ..Default::default()
+        };
+        eval_with_attmap_to_f64(expr, &attmap(), &params).unwrap()
+    }
+
+    #[test]
+    fn test_eval_numbers() {
+        assert_eq!(num("ATTRIB DESP[1] * 2 + 100"), 200.0);
+        assert_eq!(num("DESIGN PARAM 2 * 4"), 10.0);
+        assert_eq!(num("PARA[2] + PARAM 1 + CPAR2"), 140.0);
+        assert_eq!(num("MAX(DESP[1.7], HEIG / 10)"), 50.0);
+        assert_eq!(num("IFTRUE(HEIG GT 100, 1, 2)"), 1.0);
+        assert_eq!(num("POS[3] * NUMB"), 12.0);
+        assert!((num("SIN(30)") - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_eval_typed() {
+        assert!(matches!(eval("FUNC").unwrap(), AttrVal::StringType(s) if s == "PIPE"));
+        assert!(matches!(eval("NUMB").unwrap(), AttrVal::IntegerType(4)));
+        assert!(matches!(eval("DESP").unwrap(), AttrVal::DoubleArrayType(v) if v.len() == 3));
+        assert!(matches!(
+            eval("HEIG GT 100 AND NUMB EQ 4").unwrap(),
+            AttrVal::BoolType(true)
+        ));
+        assert!(matches!(
+            eval("DESP[1] <= 10").unwrap(),
+            AttrVal::BoolType(false)
+        ));
+        assert!(matches!(
+            eval("(HEIG GT 100) * 3").unwrap(),
+            AttrVal::DoubleType(3.0)
+        ));
+    }
+
+    #[test]
+    fn test_eval_error_spans() {
+        let expr = "DESP[1] + XXXX * 2";
+        let err = eval(expr).unwrap_err();
+        assert_eq!(&expr[err.span.clone()], "XXXX");
+        assert!(err.render(expr).contains("          ^^^^"));
+
+        let expr = "PARA[5] + 1";
+        assert_eq!(&expr[eval(expr).unwrap_err().span], "PARA[5]");
+
+        let expr = "DESIGN PARAM 9 + 1";
+        assert_eq!(&expr[eval(expr).unwrap_err().span], "PARAM 9");
+    }
+}
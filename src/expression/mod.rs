@@ -1,4 +1,7 @@
+pub mod eval;
 pub mod polish_notation;
 pub mod query_cata;
 pub mod resolve;
 pub mod resolve_helper;
+
+pub use eval::{CataParams, ExprError, eval_with_attmap, eval_with_attmap_to_f64};
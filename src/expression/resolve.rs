@@ -514,11 +514,7 @@ pub fn parse_to_f32(input: &[u8]) -> f32 {
 
 #[inline]
 pub fn parse_to_f64(input: &[u8]) -> f64 {
-    return if let [a, b, c, d, e, f, g, h] = input[..8] {
-        (f64::from_be_bytes([e, f, g, h, a, b, c, d]) * 100.0).round() / 100.0
-    } else {
-        0.0
-    };
+    (crate::helper::current_float_decoder().decode_f64_lossy(input) * 100.0).round() / 100.0
 }
 
 #[inline]
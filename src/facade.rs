@@ -0,0 +1,300 @@
+//! 运维常用操作的一键入口
+//!
+//! 运维脚本包装本 crate 时不应关心内部模块划分。这里提供一组一次调用即可完成的异步函数，
+//! 供下游仓库的轻量 CLI 直接调用：
+//!
+//! - [`resync_project`]：全量重新同步项目数据
+//! - [`regen_geometry`]：按范围重新生成几何并回填包围盒
+//! - [`run_material`]：生成指定专业、指定 site 的材料表单
+//! - [`export_xkt`]：按范围导出 XKT
+//! - [`run_doctor`]：检查数据库连接与数据完整性
+//!
+//! 每个函数都接受可选的进度回调。几何生成与 XKT 导出由下游的模型生成程序实现，
+//! 需先通过 [`set_geometry_backend`] 注册。
+
+use crate::db_adapter::DatabaseAdapter;
+use crate::sync::{SyncFilter, SyncManagerBuilder, SyncMode, SyncStatistics, SyncStrategy};
+use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt};
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 进度信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FacadeProgress {
+    /// 当前阶段
+    pub stage: String,
+    pub done: usize,
+    pub total: usize,
+    pub message: String,
+}
+
+/// 进度回调
+pub type ProgressCallback = Arc<dyn Fn(&FacadeProgress) + Send + Sync>;
+
+#[inline]
+fn report(
+    progress: &Option<ProgressCallback>,
+    stage: &str,
+    done: usize,
+    total: usize,
+    message: impl Into<String>,
+) {
+    if let Some(cb) = progress {
+        cb(&FacadeProgress {
+            stage: stage.to_string(),
+            done,
+            total,
+            message: message.into(),
+        });
+    }
+}
+
+/// 操作范围
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FacadeScope {
+    /// 全部 SITE
+    All,
+    /// 指定数据库编号下的全部 SITE
+    Dbnums(Vec<u32>),
+    /// 指定元素（含其子孙）
+    Refnos(Vec<RefnoEnum>),
+}
+
+impl FacadeScope {
+    /// 展开为根元素列表
+    pub async fn roots(&self) -> anyhow::Result<Vec<RefnoEnum>> {
+        match self {
+            FacadeScope::All => crate::rs_surreal::query_type_refnos_by_dbnums(&["SITE"], &[]).await,
+            FacadeScope::Dbnums(dbnums) => {
+                crate::rs_surreal::query_type_refnos_by_dbnums(&["SITE"], dbnums).await
+            }
+            FacadeScope::Refnos(refnos) => Ok(refnos.clone()),
+        }
+    }
+
+    /// 展开为根元素及其全部子孙
+    pub async fn resolve(&self) -> anyhow::Result<Vec<RefnoEnum>> {
+        let mut result = vec![];
+        for root in self.roots().await? {
+            result.push(root);
+            result.extend(crate::rs_surreal::query_deep_children_refnos(root).await?);
+        }
+        Ok(result)
+    }
+}
+
+/// 几何生成与导出后端，由下游的模型生成程序实现
+#[async_trait]
+pub trait GeometryBackend: Send + Sync {
+    /// 重新生成元素几何（inst_relate / geo_relate 等）
+    async fn regen(
+        &self,
+        refnos: &[RefnoEnum],
+        progress: Option<ProgressCallback>,
+    ) -> anyhow::Result<()>;
+
+    /// 将元素导出为 XKT 文件
+    async fn export_xkt(
+        &self,
+        refnos: &[RefnoEnum],
+        path: &Path,
+        progress: Option<ProgressCallback>,
+    ) -> anyhow::Result<()>;
+}
+
+static GEOMETRY_BACKEND: OnceCell<Arc<dyn GeometryBackend>> = OnceCell::new();
+
+/// 注册几何后端，只能注册一次
+pub fn set_geometry_backend(backend: Arc<dyn GeometryBackend>) -> anyhow::Result<()> {
+    GEOMETRY_BACKEND
+        .set(backend)
+        .map_err(|_| anyhow::anyhow!("几何后端已注册"))
+}
+
+pub(crate) fn geometry_backend() -> anyhow::Result<&'static Arc<dyn GeometryBackend>> {
+    GEOMETRY_BACKEND
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("未注册几何后端，请先调用 set_geometry_backend"))
+}
+
+/// 全量重新同步项目数据
+pub async fn resync_project(
+    source: Arc<dyn DatabaseAdapter>,
+    target: Arc<dyn DatabaseAdapter>,
+    progress: Option<ProgressCallback>,
+) -> anyhow::Result<SyncStatistics> {
+    report(&progress, "resync", 0, 1, format!("{} -> {}", source.name(), target.name()));
+    let manager = SyncManagerBuilder::new()
+        .source(source)
+        .target(target)
+        .strategy(SyncStrategy {
+            mode: SyncMode::Full,
+            ..Default::default()
+        })
+        .filter(SyncFilter::default())
+        .build()?;
+    let stats = manager.sync().await?;
+    report(
+        &progress,
+        "resync",
+        1,
+        1,
+        format!("成功 {}，失败 {}", stats.successful_records, stats.failed_records),
+    );
+    Ok(stats)
+}
+
+/// 按范围重新生成几何
+///
+/// 先清除世界变换缓存，再调用几何后端生成，最后回填 inst_relate 的包围盒。
+///
+/// 本 crate 不内置几何后端：未通过 [`set_geometry_backend`] 注册时，在展开范围之前即返回错误。
+pub async fn regen_geometry(
+    scope: &FacadeScope,
+    progress: Option<ProgressCallback>,
+) -> anyhow::Result<usize> {
+    let backend = geometry_backend()?;
+    report(&progress, "resolve", 0, 0, "展开范围");
+    let refnos = scope.resolve().await?;
+    let total = refnos.len();
+
+    for (i, &refno) in refnos.iter().enumerate() {
+        crate::transform::invalidate_world_trans_cache(refno).await?;
+        if i % 1000 == 0 {
+            report(&progress, "invalidate", i, total, "清除变换缓存");
+        }
+    }
+    backend.regen(&refnos, progress.clone()).await?;
+    for &refno in &refnos {
+        crate::derived_attr::DERIVED_ATTRS.invalidate_geometry(refno);
+    }
+    report(&progress, "aabb", 0, total, "回填包围盒");
+    crate::rs_surreal::update_inst_relate_aabbs_by_refnos(&refnos, true).await?;
+    report(&progress, "aabb", total, total, "完成");
+    Ok(total)
+}
+
+/// 生成指定专业、指定 site 的材料表单
+pub async fn run_material(
+    major: &str,
+    site: RefnoEnum,
+    progress: Option<ProgressCallback>,
+) -> anyhow::Result<()> {
+    report(&progress, "prepare", 0, 1, "定义材料函数");
+    crate::material::define_material_surreal_funtions(SUL_DB.clone()).await?;
+    let site: RefU64 = site.refno();
+    report(&progress, "material", 0, 1, format!("专业 {major}，site {site}"));
+    let handles = crate::material::save_major_material(major, site).await;
+    let total = handles.len();
+    for (i, handle) in handles.into_iter().enumerate() {
+        handle.await?;
+        report(&progress, "material", i + 1, total, "保存材料表单");
+    }
+    Ok(())
+}
+
+/// 按范围导出 XKT
+///
+/// 与 [`regen_geometry`] 相同，需要先注册几何后端，否则直接返回错误。
+pub async fn export_xkt(
+    scope: &FacadeScope,
+    path: impl Into<PathBuf>,
+    progress: Option<ProgressCallback>,
+) -> anyhow::Result<PathBuf> {
+    let backend = geometry_backend()?;
+    let path = path.into();
+    report(&progress, "resolve", 0, 0, "展开范围");
+    let refnos = scope.resolve().await?;
+    backend.export_xkt(&refnos, &path, progress.clone()).await?;
+    report(&progress, "export", 1, 1, path.display().to_string());
+    Ok(path)
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// 检查报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.ok)
+    }
+}
+
+async fn count_rows(sql: &str) -> anyhow::Result<i64> {
+    let count: Option<i64> = SUL_DB.query_take(sql, 0).await?;
+    Ok(count.unwrap_or_default())
+}
+
+/// 检查数据库连接与数据完整性
+pub async fn run_doctor(progress: Option<ProgressCallback>) -> anyhow::Result<DoctorReport> {
+    let checks: [(&str, &str, fn(i64) -> bool); 4] = [
+        ("数据库连接", "RETURN 1", |_| true),
+        (
+            "pe 元素数",
+            "RETURN (SELECT count() FROM pe GROUP ALL)[0].count",
+            |n| n > 0,
+        ),
+        (
+            "缺少包围盒的实例",
+            "RETURN (SELECT count() FROM inst_relate WHERE aabb = NONE GROUP ALL)[0].count",
+            |n| n == 0,
+        ),
+        (
+            "缺少父节点的元素",
+            "RETURN (SELECT count() FROM pe WHERE owner = NONE AND noun != 'WORL' AND deleted != true GROUP ALL)[0].count",
+            |n| n == 0,
+        ),
+    ];
+    let total = checks.len();
+    let mut report_data = DoctorReport::default();
+    for (i, (name, sql, pass)) in checks.into_iter().enumerate() {
+        report(&progress, "doctor", i, total, name);
+        let check = match count_rows(sql).await {
+            Ok(n) => DoctorCheck {
+                name: name.to_string(),
+                ok: pass(n),
+                detail: n.to_string(),
+            },
+            Err(e) => DoctorCheck {
+                name: name.to_string(),
+                ok: false,
+                detail: e.to_string(),
+            },
+        };
+        let failed_connection = i == 0 && !check.ok;
+        report_data.checks.push(check);
+        // 连接失败时后续检查没有意义
+        if failed_connection {
+            break;
+        }
+    }
+    report(&progress, "doctor", total, total, "完成");
+    Ok(report_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_regen_without_backend() {
+        let err = regen_geometry(&FacadeScope::Refnos(vec![]), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("set_geometry_backend"));
+    }
+}
@@ -2,9 +2,16 @@ use std::path::Path;
 use serde_json::json;
 use anyhow::Result;
 use crate::shape::pdms_shape::PlantMesh;
+use super::mesh_compress::{CompressionStats, MeshCompressionProfile, compress_mesh};
 
 /// 导出单个 PlantMesh 到 GLB 文件
 pub fn export_single_mesh_to_glb(mesh: &PlantMesh, output_path: &Path) -> Result<()> {
+    let (gltf, buffer_data) = single_mesh_gltf(mesh);
+    write_glb_binary(&gltf, &buffer_data, output_path)
+}
+
+/// 单个 PlantMesh 的 glTF JSON 与 BIN 数据（f32 位置 + u32 索引）
+fn single_mesh_gltf(mesh: &PlantMesh) -> (serde_json::Value, Vec<u8>) {
     // 转换 Vec3 为 f32 数组
     let positions: Vec<f32> = mesh.vertices.iter().flat_map(|v| [v.x, v.y, v.z]).collect();
 
@@ -86,10 +93,180 @@ pub fn export_single_mesh_to_glb(mesh: &PlantMesh, output_path: &Path) -> Result
         ]
     });
 
-    write_glb_binary(&gltf, &buffer_data, output_path)
+    (gltf, buffer_data)
+}
+
+/// 按导出配置压缩后导出单个 PlantMesh 到 GLB 文件
+///
+/// 启用位置量化时写入 `KHR_mesh_quantization` 扩展，反量化通过节点的
+/// scale/translation 完成；`quantize_normals` 关闭时法线保留为 f32。
+/// 返回该分块的压缩统计，`compressed_bytes` 为实际写入的 BIN 大小。
+pub fn export_single_mesh_to_glb_with_profile(
+    mesh: &PlantMesh,
+    output_path: &Path,
+    profile: &MeshCompressionProfile,
+) -> Result<CompressionStats> {
+    let chunk = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (optimized, quantized, stats) = compress_mesh(mesh, profile, &chunk);
+    let Some(quantized) = quantized else {
+        let (gltf, buffer_data) = single_mesh_gltf(&optimized);
+        write_glb_binary(&gltf, &buffer_data, output_path)?;
+        return Ok(CompressionStats {
+            compressed_bytes: buffer_data.len(),
+            ..stats
+        });
+    };
+
+    let mut buffer_data = Vec::new();
+    let align4 = |buf: &mut Vec<u8>| {
+        while buf.len() % 4 != 0 {
+            buf.push(0);
+        }
+    };
+
+    // 位置：u16x3，按 4 字节对齐补齐为 8 字节步长
+    let positions_offset = buffer_data.len();
+    for p in &quantized.positions {
+        for c in p {
+            buffer_data.extend_from_slice(&c.to_le_bytes());
+        }
+        buffer_data.extend_from_slice(&[0, 0]);
+    }
+    let positions_len = buffer_data.len() - positions_offset;
+
+    let mut buffer_views = vec![json!({
+        "buffer": 0,
+        "byteOffset": positions_offset,
+        "byteLength": positions_len,
+        "byteStride": 8,
+        "target": 34962
+    })];
+    let mut accessors = vec![json!({
+        "bufferView": 0,
+        "componentType": 5123,
+        "count": quantized.positions.len(),
+        "type": "VEC3",
+        "min": [0, 0, 0],
+        "max": quantized.positions.iter().fold([0u16; 3], |m, p| [m[0].max(p[0]), m[1].max(p[1]), m[2].max(p[2])])
+    })];
+    let mut attributes = json!({ "POSITION": 0 });
+
+    let normals_valid =
+        !quantized.normals.is_empty() && quantized.normals.len() == quantized.positions.len();
+    if normals_valid && !profile.quantize_normals {
+        // 法线：f32x3
+        let normals_offset = buffer_data.len();
+        for n in &optimized.normals {
+            for c in n.normalize_or_zero().to_array() {
+                buffer_data.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": normals_offset,
+            "byteLength": buffer_data.len() - normals_offset,
+            "target": 34962
+        }));
+        accessors.push(json!({
+            "bufferView": buffer_views.len() - 1,
+            "componentType": 5126,
+            "count": optimized.normals.len(),
+            "type": "VEC3"
+        }));
+        attributes["NORMAL"] = json!(accessors.len() - 1);
+    } else if normals_valid {
+        // 法线：i8x3 归一化，补齐为 4 字节步长
+        let normals_offset = buffer_data.len();
+        for n in &quantized.normals {
+            buffer_data.extend(n.iter().map(|c| *c as u8));
+            buffer_data.push(0);
+        }
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": normals_offset,
+            "byteLength": buffer_data.len() - normals_offset,
+            "byteStride": 4,
+            "target": 34962
+        }));
+        accessors.push(json!({
+            "bufferView": buffer_views.len() - 1,
+            "componentType": 5120,
+            "normalized": true,
+            "count": quantized.normals.len(),
+            "type": "VEC3"
+        }));
+        attributes["NORMAL"] = json!(accessors.len() - 1);
+    }
+
+    align4(&mut buffer_data);
+    let indices_offset = buffer_data.len();
+    let use_u16 = quantized.positions.len() <= u16::MAX as usize;
+    for &i in &quantized.indices {
+        if use_u16 {
+            buffer_data.extend_from_slice(&(i as u16).to_le_bytes());
+        } else {
+            buffer_data.extend_from_slice(&i.to_le_bytes());
+        }
+    }
+    buffer_views.push(json!({
+        "buffer": 0,
+        "byteOffset": indices_offset,
+        "byteLength": buffer_data.len() - indices_offset,
+        "target": 34963
+    }));
+    accessors.push(json!({
+        "bufferView": buffer_views.len() - 1,
+        "componentType": if use_u16 { 5123 } else { 5125 },
+        "count": quantized.indices.len(),
+        "type": "SCALAR"
+    }));
+    let indices_accessor = accessors.len() - 1;
+
+    let gltf = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": "AIOS GLB Exporter"
+        },
+        "extensionsUsed": ["KHR_mesh_quantization"],
+        "extensionsRequired": ["KHR_mesh_quantization"],
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [{
+            "mesh": 0,
+            "translation": quantized.offset.to_array(),
+            "scale": quantized.scale.to_array()
+        }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": attributes,
+                "indices": indices_accessor,
+                "mode": 4
+            }]
+        }],
+        "buffers": [{
+            "byteLength": buffer_data.len()
+        }],
+        "bufferViews": buffer_views,
+        "accessors": accessors
+    });
+
+    write_glb_binary(&gltf, &buffer_data, output_path)?;
+    Ok(CompressionStats {
+        compressed_bytes: buffer_data.len(),
+        ..stats
+    })
 }
 
 fn write_glb_binary(gltf: &serde_json::Value, buffer_data: &[u8], output_path: &Path) -> Result<()> {
+    std::fs::write(output_path, glb_bytes(gltf, buffer_data)?)?;
+    Ok(())
+}
+
+/// 按 GLB 2.0 容器格式拼装 JSON 与 BIN 块（各自按 4 字节对齐）
+pub(crate) fn glb_bytes(gltf: &serde_json::Value, buffer_data: &[u8]) -> Result<Vec<u8>> {
     let mut json_bytes = serde_json::to_vec(gltf)?;
     while json_bytes.len() % 4 != 0 {
         json_bytes.push(b' ');
@@ -102,19 +279,53 @@ fn write_glb_binary(gltf: &serde_json::Value, buffer_data: &[u8], output_path: &
 
     let total_length = 12 + 8 + json_bytes.len() + 8 + bin_data.len();
 
-    let mut file = std::fs::File::create(output_path)?;
-    use std::io::Write;
-    file.write_all(b"glTF")?;
-    file.write_all(&2u32.to_le_bytes())?;
-    file.write_all(&(total_length as u32).to_le_bytes())?;
+    let mut out = Vec::with_capacity(total_length);
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(total_length as u32).to_le_bytes());
 
-    file.write_all(&(json_bytes.len() as u32).to_le_bytes())?;
-    file.write_all(&0x4E4F534Au32.to_le_bytes())?;
-    file.write_all(&json_bytes)?;
+    out.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0x4E4F534Au32.to_le_bytes());
+    out.extend_from_slice(&json_bytes);
 
-    file.write_all(&(bin_data.len() as u32).to_le_bytes())?;
-    file.write_all(&0x004E4942u32.to_le_bytes())?;
-    file.write_all(&bin_data)?;
+    out.extend_from_slice(&(bin_data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0x004E4942u32.to_le_bytes());
+    out.extend_from_slice(&bin_data);
 
-    Ok(())
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    fn read_json_chunk(path: &Path) -> serde_json::Value {
+        let glb = std::fs::read(path).unwrap();
+        let json_len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        serde_json::from_slice(&glb[20..20 + json_len]).unwrap()
+    }
+
+    #[test]
+    fn test_quantized_export_keeps_f32_normals() {
+        let mesh = PlantMesh {
+            indices: vec![0, 1, 2],
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            normals: vec![Vec3::Z; 3],
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunk.glb");
+        let profile = MeshCompressionProfile {
+            quantize_normals: false,
+            ..Default::default()
+        };
+        let stats = export_single_mesh_to_glb_with_profile(&mesh, &path, &profile).unwrap();
+
+        let gltf = read_json_chunk(&path);
+        let attributes = &gltf["meshes"][0]["primitives"][0]["attributes"];
+        let normal = attributes["NORMAL"].as_u64().unwrap() as usize;
+        assert_eq!(gltf["accessors"][normal]["componentType"], 5126);
+        assert_eq!(stats.compressed_bytes, gltf["buffers"][0]["byteLength"]);
+    }
 }
@@ -0,0 +1,445 @@
+//! 导出网格的压缩优化
+//!
+//! XKT/glTF 导出体积较大，这里提供 meshoptimizer 风格的处理步骤：
+//! - 顶点缓存优化（Forsyth 算法重排三角形）
+//! - 顶点读取优化（按首次使用顺序重排顶点并剔除未使用顶点）
+//! - 位置/法线量化（配合 glTF `KHR_mesh_quantization`）
+//!
+//! 各步骤由 [`MeshCompressionProfile`] 控制，每个分块输出 [`CompressionStats`]。
+//! 单网格导出见 `export_glb::export_single_mesh_to_glb_with_profile`，实例化 glb 导出通过
+//! [`GltfExportOptions::compression`](crate::geometry::gltf_export::GltfExportOptions::compression) 启用。
+//!
+//! Draco（`KHR_draco_mesh_compression`）暂不支持：目前没有可用的纯 Rust 编码器，
+//! 需要时由下游用 gltf-transform 等工具对导出的 glb 再做一次压缩。
+
+use crate::shape::pdms_shape::PlantMesh;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// 导出压缩配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshCompressionProfile {
+    /// 是否重排三角形以提高顶点缓存命中率
+    pub optimize_vertex_cache: bool,
+    /// 是否按首次使用顺序重排顶点
+    pub optimize_vertex_fetch: bool,
+    /// 位置量化位数（1..=16），None 表示保留 f32；三个轴使用相同的步长，保证法线不受反量化缩放影响
+    pub position_bits: Option<u8>,
+    /// 是否将法线量化为 i8
+    pub quantize_normals: bool,
+    /// 模拟的顶点缓存大小
+    pub cache_size: usize,
+}
+
+impl Default for MeshCompressionProfile {
+    fn default() -> Self {
+        Self {
+            optimize_vertex_cache: true,
+            optimize_vertex_fetch: true,
+            position_bits: Some(14),
+            quantize_normals: true,
+            cache_size: 16,
+        }
+    }
+}
+
+impl MeshCompressionProfile {
+    /// 不做任何处理
+    pub fn none() -> Self {
+        Self {
+            optimize_vertex_cache: false,
+            optimize_vertex_fetch: false,
+            position_bits: None,
+            quantize_normals: false,
+            cache_size: 16,
+        }
+    }
+
+    /// 最大压缩，精度要求较低的预览场景使用
+    pub fn max() -> Self {
+        Self {
+            position_bits: Some(12),
+            ..Default::default()
+        }
+    }
+}
+
+/// 单个分块的压缩统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompressionStats {
+    pub chunk: String,
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    /// 原始 f32 位置 + 法线 + u32 索引的字节数
+    pub raw_bytes: usize,
+    /// 处理后的字节数，导出时为实际写入的缓冲区大小
+    pub compressed_bytes: usize,
+    /// 平均缓存未命中率（每三角形顶点变换次数）
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+}
+
+impl CompressionStats {
+    #[inline]
+    pub fn ratio(&self) -> f32 {
+        if self.raw_bytes == 0 {
+            return 1.0;
+        }
+        self.compressed_bytes as f32 / self.raw_bytes as f32
+    }
+
+    /// 汇总多个分块
+    pub fn total(stats: &[CompressionStats]) -> CompressionStats {
+        let mut total = CompressionStats {
+            chunk: "total".to_string(),
+            ..Default::default()
+        };
+        let mut weighted = (0.0, 0.0);
+        for s in stats {
+            total.vertex_count += s.vertex_count;
+            total.triangle_count += s.triangle_count;
+            total.raw_bytes += s.raw_bytes;
+            total.compressed_bytes += s.compressed_bytes;
+            weighted.0 += s.acmr_before * s.triangle_count as f32;
+            weighted.1 += s.acmr_after * s.triangle_count as f32;
+        }
+        if total.triangle_count > 0 {
+            total.acmr_before = weighted.0 / total.triangle_count as f32;
+            total.acmr_after = weighted.1 / total.triangle_count as f32;
+        }
+        total
+    }
+}
+
+/// 量化后的网格
+#[derive(Debug, Clone, Default)]
+pub struct QuantizedMesh {
+    pub positions: Vec<[u16; 3]>,
+    pub normals: Vec<[i8; 3]>,
+    pub indices: Vec<u32>,
+    /// 反量化：pos = offset + q * scale
+    pub offset: Vec3,
+    pub scale: Vec3,
+}
+
+/// 模拟 FIFO 顶点缓存，计算 ACMR
+pub fn compute_acmr(indices: &[u32], cache_size: usize) -> f32 {
+    let tri_count = indices.len() / 3;
+    if tri_count == 0 {
+        return 0.0;
+    }
+    let mut cache: std::collections::VecDeque<u32> = Default::default();
+    let mut misses = 0usize;
+    for &i in indices {
+        if !cache.contains(&i) {
+            misses += 1;
+            cache.push_back(i);
+            if cache.len() > cache_size {
+                cache.pop_front();
+            }
+        }
+    }
+    misses as f32 / tri_count as f32
+}
+
+fn forsyth_vertex_score(cache_pos: Option<usize>, valence: u32, cache_size: usize) -> f32 {
+    if valence == 0 {
+        return -1.0;
+    }
+    let mut score = 0.0;
+    if let Some(pos) = cache_pos {
+        if pos < 3 {
+            // 刚使用过的三角形顶点，避免立刻复用同一三角形
+            score = 0.75;
+        } else if cache_size > 3 {
+            let scaler = 1.0 / (cache_size - 3) as f32;
+            score = (1.0 - (pos - 3) as f32 * scaler).max(0.0).powf(1.5);
+        }
+    }
+    score + 2.0 * (valence as f32).powf(-0.5)
+}
+
+/// Forsyth 线性时间顶点缓存优化，返回重排后的索引
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize, cache_size: usize) -> Vec<u32> {
+    let tri_count = indices.len() / 3;
+    if tri_count == 0 {
+        return indices.to_vec();
+    }
+    let mut vert_tris: Vec<Vec<usize>> = vec![vec![]; vertex_count];
+    for t in 0..tri_count {
+        for k in 0..3 {
+            vert_tris[indices[t * 3 + k] as usize].push(t);
+        }
+    }
+    let mut valence = vert_tris.iter().map(|v| v.len() as u32).collect::<Vec<_>>();
+    let mut vert_score = (0..vertex_count)
+        .map(|v| forsyth_vertex_score(None, valence[v], cache_size))
+        .collect::<Vec<_>>();
+    let tri_score_of = |t: usize, vert_score: &[f32]| -> f32 {
+        (0..3)
+            .map(|k| vert_score[indices[t * 3 + k] as usize])
+            .sum()
+    };
+    let mut tri_score = (0..tri_count)
+        .map(|t| tri_score_of(t, &vert_score))
+        .collect::<Vec<_>>();
+    let mut emitted = vec![false; tri_count];
+    let mut out = Vec::with_capacity(indices.len());
+    let mut cache: Vec<u32> = Vec::with_capacity(cache_size + 3);
+    let mut fallback_cursor = 0usize;
+
+    for _ in 0..tri_count {
+        // 优先在缓存中顶点的相邻三角形里选择得分最高者
+        let mut best: Option<(usize, f32)> = None;
+        for &v in &cache {
+            for &t in &vert_tris[v as usize] {
+                if !emitted[t] && best.map(|(_, s)| tri_score[t] > s).unwrap_or(true) {
+                    best = Some((t, tri_score[t]));
+                }
+            }
+        }
+        let tri = match best {
+            Some((t, _)) => t,
+            None => {
+                while emitted[fallback_cursor] {
+                    fallback_cursor += 1;
+                }
+                fallback_cursor
+            }
+        };
+
+        emitted[tri] = true;
+        let tri_verts = [indices[tri * 3], indices[tri * 3 + 1], indices[tri * 3 + 2]];
+        out.extend_from_slice(&tri_verts);
+        for &v in &tri_verts {
+            valence[v as usize] -= 1;
+        }
+
+        let mut new_cache: Vec<u32> = tri_verts.to_vec();
+        new_cache.extend(cache.iter().filter(|v| !tri_verts.contains(v)));
+        let evicted = if new_cache.len() > cache_size {
+            new_cache.split_off(cache_size)
+        } else {
+            vec![]
+        };
+        for &v in &evicted {
+            vert_score[v as usize] = forsyth_vertex_score(None, valence[v as usize], cache_size);
+        }
+        for (pos, &v) in new_cache.iter().enumerate() {
+            vert_score[v as usize] =
+                forsyth_vertex_score(Some(pos), valence[v as usize], cache_size);
+        }
+        for &v in new_cache.iter().chain(evicted.iter()) {
+            for &t in &vert_tris[v as usize] {
+                if !emitted[t] {
+                    tri_score[t] = tri_score_of(t, &vert_score);
+                }
+            }
+        }
+        cache = new_cache;
+    }
+    out
+}
+
+/// 按首次使用顺序重排顶点，剔除未被索引引用的顶点
+pub fn optimize_vertex_fetch(mesh: &PlantMesh) -> PlantMesh {
+    let mut remap = vec![u32::MAX; mesh.vertices.len()];
+    let mut order = vec![];
+    let indices = mesh
+        .indices
+        .iter()
+        .map(|&i| {
+            let slot = &mut remap[i as usize];
+            if *slot == u32::MAX {
+                *slot = order.len() as u32;
+                order.push(i as usize);
+            }
+            *slot
+        })
+        .collect::<Vec<_>>();
+    let pick = |src_len: usize| src_len == mesh.vertices.len();
+    PlantMesh {
+        indices,
+        vertices: order.iter().map(|&i| mesh.vertices[i]).collect(),
+        normals: if pick(mesh.normals.len()) {
+            order.iter().map(|&i| mesh.normals[i]).collect()
+        } else {
+            mesh.normals.clone()
+        },
+        uvs: if pick(mesh.uvs.len()) {
+            order.iter().map(|&i| mesh.uvs[i]).collect()
+        } else {
+            mesh.uvs.clone()
+        },
+        wire_vertices: mesh.wire_vertices.clone(),
+        edges: mesh.edges.clone(),
+        aabb: mesh.aabb,
+    }
+}
+
+/// 将位置量化到 `bits` 位无符号整数，法线量化为 i8
+pub fn quantize_mesh(mesh: &PlantMesh, bits: u8) -> QuantizedMesh {
+    let bits = bits.clamp(1, 16);
+    let max_q = ((1u32 << bits) - 1) as f32;
+    let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+    for v in &mesh.vertices {
+        min = min.min(*v);
+        max = max.max(*v);
+    }
+    if mesh.vertices.is_empty() {
+        min = Vec3::ZERO;
+        max = Vec3::ZERO;
+    }
+    // 各轴使用相同的步长：反量化缩放为均匀缩放，法线无需再变换
+    let extent = (max - min).max_element().max(f32::EPSILON);
+    let scale = Vec3::splat(extent / max_q);
+    let positions = mesh
+        .vertices
+        .iter()
+        .map(|v| {
+            let q = ((*v - min) / scale)
+                .round()
+                .clamp(Vec3::ZERO, Vec3::splat(max_q));
+            [q.x as u16, q.y as u16, q.z as u16]
+        })
+        .collect();
+    let normals = mesh
+        .normals
+        .iter()
+        .map(|n| {
+            let q = (n.normalize_or_zero() * 127.0).round();
+            [q.x as i8, q.y as i8, q.z as i8]
+        })
+        .collect();
+    QuantizedMesh {
+        positions,
+        normals,
+        indices: mesh.indices.clone(),
+        offset: min,
+        scale,
+    }
+}
+
+/// 按配置处理单个分块
+///
+/// 返回处理后的网格、可选的量化结果及统计信息。
+pub fn compress_mesh(
+    mesh: &PlantMesh,
+    profile: &MeshCompressionProfile,
+    chunk: &str,
+) -> (PlantMesh, Option<QuantizedMesh>, CompressionStats) {
+    let acmr_before = compute_acmr(&mesh.indices, profile.cache_size);
+    let mut out = mesh.clone();
+    if profile.optimize_vertex_cache {
+        out.indices = optimize_vertex_cache(&out.indices, out.vertices.len(), profile.cache_size);
+    }
+    if profile.optimize_vertex_fetch {
+        out = optimize_vertex_fetch(&out);
+    }
+    let quantized = profile.position_bits.map(|bits| quantize_mesh(&out, bits));
+
+    // 与 glb 导出的布局一致：只有量化时才使用 u16 位置/i8 法线/u16 索引
+    let raw_bytes = mesh.vertices.len() * 12 + mesh.normals.len() * 12 + mesh.indices.len() * 4;
+    let is_quantized = quantized.is_some();
+    let index_bytes = if is_quantized && out.vertices.len() <= u16::MAX as usize {
+        2
+    } else {
+        4
+    };
+    let position_bytes = if is_quantized { 8 } else { 12 };
+    let normal_bytes = if is_quantized && profile.quantize_normals {
+        4
+    } else {
+        12
+    };
+    let compressed_bytes = out.vertices.len() * position_bytes
+        + out.normals.len() * normal_bytes
+        + out.indices.len() * index_bytes;
+
+    let stats = CompressionStats {
+        chunk: chunk.to_string(),
+        vertex_count: out.vertices.len(),
+        triangle_count: out.indices.len() / 3,
+        raw_bytes,
+        compressed_bytes,
+        acmr_before,
+        acmr_after: compute_acmr(&out.indices, profile.cache_size),
+    };
+    (out, quantized, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// n x n 网格平面，行优先的三角形顺序
+    fn grid_mesh(n: u32) -> PlantMesh {
+        let mut mesh = PlantMesh::default();
+        for y in 0..=n {
+            for x in 0..=n {
+                mesh.vertices.push(Vec3::new(x as f32, y as f32, 0.0));
+                mesh.normals.push(Vec3::Z);
+            }
+        }
+        // 故意按列交错的顺序输出三角形，制造较差的缓存局部性
+        for x in 0..n {
+            for y in 0..n {
+                let i = y * (n + 1) + x;
+                mesh.indices
+                    .extend_from_slice(&[i, i + 1, i + n + 1, i + 1, i + n + 2, i + n + 1]);
+            }
+        }
+        mesh
+    }
+
+    /// 以顶点坐标表示的三角形集合，与索引顺序无关
+    fn triangle_set(mesh: &PlantMesh) -> Vec<[(i32, i32); 3]> {
+        let mut tris = mesh
+            .indices
+            .chunks(3)
+            .map(|t| {
+                let mut t = [t[0], t[1], t[2]].map(|i| {
+                    let v = mesh.vertices[i as usize];
+                    (v.x as i32, v.y as i32)
+                });
+                t.sort();
+                t
+            })
+            .collect::<Vec<_>>();
+        tris.sort();
+        tris
+    }
+
+    #[test]
+    fn test_vertex_cache_optimization_keeps_triangles() {
+        let mesh = grid_mesh(20);
+        let (out, quantized, stats) =
+            compress_mesh(&mesh, &MeshCompressionProfile::default(), "chunk0");
+        assert_eq!(stats.triangle_count, mesh.indices.len() / 3);
+        assert!(stats.acmr_after <= stats.acmr_before);
+        assert!(stats.ratio() < 0.7);
+
+        assert_eq!(triangle_set(&mesh), triangle_set(&out));
+
+        let q = quantized.unwrap();
+        let p = q.positions[5];
+        let restored = q.offset + Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32) * q.scale;
+        assert!(restored.distance(out.vertices[5]) < 0.01);
+    }
+
+    #[test]
+    fn test_stats_without_quantization() {
+        let mesh = grid_mesh(4);
+        let profile = MeshCompressionProfile {
+            position_bits: None,
+            ..Default::default()
+        };
+        let (out, quantized, stats) = compress_mesh(&mesh, &profile, "chunk0");
+        assert!(quantized.is_none());
+        // 未量化时位置、法线均为 f32，索引为 u32
+        assert_eq!(
+            stats.compressed_bytes,
+            out.vertices.len() * 12 + out.normals.len() * 12 + out.indices.len() * 4
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod export_glb;
 pub mod export_parquet;
+pub mod mesh_compress;
+pub mod priority;
@@ -0,0 +1,206 @@
+//! 查看器加载优先级元数据
+//!
+//! 为缩短用户感知的加载时间，导出时为每个元素/分块计算优先级：
+//! - 投影尺寸：由 AABB 体积与包围盒对角线估算在典型相机高度下的屏幕占比
+//! - 专业权重：结构、墙板等大体量、遮挡性强的专业优先于小件
+//!
+//! 客户端按优先级降序请求几何，流式加载层也可直接调用 [`order_by_priority`]。
+//! glb 导出（[`GltfInstancingExporter`](crate::geometry::gltf_export::GltfInstancingExporter)）
+//! 按优先级降序排列节点，并将得分写入节点的 `extras.priority`。
+
+use crate::RefnoEnum;
+use crate::pdms_types::PdmsGenericType;
+use parry3d::bounding_volume::Aabb;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 优先级计算参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityConfig {
+    /// 典型相机高度（mm），用于估算观察距离
+    pub camera_heights: Vec<f32>,
+    /// 最小观察距离（mm），避免相机附近的小件得分过高
+    pub min_view_distance: f32,
+    /// 各专业的权重，未配置的类型为 1.0
+    pub discipline_weights: HashMap<PdmsGenericType, f32>,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        use PdmsGenericType::*;
+        let discipline_weights = [
+            (STRU, 1.5),
+            (WALL, 2.0),
+            (STWALL, 2.0),
+            (CWALL, 2.0),
+            (GWALL, 1.5),
+            (CTWALL, 2.0),
+            (FLOOR, 2.0),
+            (CFLOOR, 2.0),
+            (PANE, 1.8),
+            (EQUI, 1.3),
+            (PIPE, 1.0),
+            (HVAC, 1.0),
+            (HANG, 0.6),
+            (HANDRA, 0.5),
+            (ROOM, 0.2),
+            (AREADEF, 0.1),
+        ]
+        .into_iter()
+        .collect();
+        Self {
+            camera_heights: vec![1_700.0, 10_000.0],
+            min_view_distance: 2_000.0,
+            discipline_weights,
+        }
+    }
+}
+
+/// 参与排序的元素
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityInput {
+    pub refno: RefnoEnum,
+    pub generic: PdmsGenericType,
+    pub aabb: Aabb,
+}
+
+/// 元素优先级
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementPriority {
+    pub refno: RefnoEnum,
+    pub score: f32,
+}
+
+/// 分块优先级
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkPriority {
+    pub chunk: String,
+    pub score: f32,
+    /// 分块内元素按优先级降序
+    pub elements: Vec<ElementPriority>,
+}
+
+/// 投影尺寸估计
+///
+/// 对每个相机高度，以包围盒中心到相机平面的高差与包围盒尺寸之和作为观察距离，
+/// 取投影面积（对角线平方近似）与距离平方之比的平均值。
+pub fn projected_size(aabb: &Aabb, config: &PriorityConfig) -> f32 {
+    let extents = aabb.extents();
+    if extents.iter().any(|e| !e.is_finite() || *e < 0.0) {
+        return 0.0;
+    }
+    let diag = extents.norm();
+    let volume = extents.x * extents.y * extents.z;
+    // 细长件（管道、梁）体积小但可见度高，取对角线与体积立方根的较大贡献
+    let size = diag.max(volume.max(0.0).cbrt() * 3.0_f32.sqrt());
+    let center_z = aabb.center().z;
+    let heights = if config.camera_heights.is_empty() {
+        &[1_700.0][..]
+    } else {
+        &config.camera_heights[..]
+    };
+    let sum: f32 = heights
+        .iter()
+        .map(|h| {
+            let dist = ((center_z - h).abs() + diag).max(config.min_view_distance);
+            (size * size) / (dist * dist)
+        })
+        .sum();
+    sum / heights.len() as f32
+}
+
+/// 按包围盒和专业计算优先级得分
+pub fn aabb_priority(aabb: &Aabb, generic: PdmsGenericType, config: &PriorityConfig) -> f32 {
+    let weight = config
+        .discipline_weights
+        .get(&generic)
+        .copied()
+        .unwrap_or(1.0);
+    projected_size(aabb, config) * weight
+}
+
+/// 单个元素的优先级得分
+#[inline]
+pub fn element_priority(input: &PriorityInput, config: &PriorityConfig) -> f32 {
+    aabb_priority(&input.aabb, input.generic, config)
+}
+
+/// 按优先级降序排列元素
+pub fn order_by_priority(inputs: &[PriorityInput], config: &PriorityConfig) -> Vec<ElementPriority> {
+    let mut result = inputs
+        .iter()
+        .map(|i| ElementPriority {
+            refno: i.refno,
+            score: element_priority(i, config),
+        })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.refno.cmp(&b.refno))
+    });
+    result
+}
+
+/// 计算分块优先级并按降序排列
+///
+/// 分块得分为成员得分之和，大分块只要包含重要几何就会靠前。
+pub fn order_chunks_by_priority(
+    chunks: &HashMap<String, Vec<PriorityInput>>,
+    config: &PriorityConfig,
+) -> Vec<ChunkPriority> {
+    let mut result = chunks
+        .iter()
+        .map(|(name, inputs)| {
+            let elements = order_by_priority(inputs, config);
+            ChunkPriority {
+                chunk: name.clone(),
+                score: elements.iter().map(|e| e.score).sum(),
+                elements,
+            }
+        })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.chunk.cmp(&b.chunk)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use nalgebra::Point3;
+
+    fn input(n: u32, generic: PdmsGenericType, size: f32) -> PriorityInput {
+        PriorityInput {
+            refno: RefU64::from_two_nums(1, n).into(),
+            generic,
+            aabb: Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(size, size, size)),
+        }
+    }
+
+    #[test]
+    fn test_larger_and_weighted_elements_first() {
+        let config = PriorityConfig::default();
+        let items = vec![
+            input(1, PdmsGenericType::PIPE, 100.0),
+            input(2, PdmsGenericType::PIPE, 1000.0),
+            input(3, PdmsGenericType::WALL, 1000.0),
+            input(4, PdmsGenericType::HANDRA, 1000.0),
+        ];
+        let ordered = order_by_priority(&items, &config)
+            .into_iter()
+            .map(|e| e.refno)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ordered,
+            vec![items[2].refno, items[1].refno, items[3].refno, items[0].refno]
+        );
+
+        let chunks = HashMap::from([
+            ("small".to_string(), vec![items[0].clone()]),
+            ("walls".to_string(), vec![items[2].clone()]),
+        ]);
+        let ordered = order_chunks_by_priority(&chunks, &config);
+        assert_eq!(ordered[0].chunk, "walls");
+    }
+}
@@ -0,0 +1,124 @@
+//! C 兼容的 FFI 接口（`ffi` 特性）
+//!
+//! 只导出最小的函数集，请求与结果均为 UTF-8 JSON 字符串：
+//!
+//! ```c
+//! int32_t aios_init(void);
+//! char* aios_call(const char* request);   // 返回值需用 aios_free_string 释放
+//! void aios_free_string(char* s);
+//! ```
+//!
+//! 请求格式 `{"method": "get_named_attmap", "refno": "17496/201377"}`，
+//! 结果格式 `{"ok": true, "data": ...}` 或 `{"ok": false, "error": "..."}`。
+//!
+//! 支持的方法：`get_pe`、`get_named_attmap`、`get_children_refnos`、
+//! `query_deep_children_refnos`、`get_world_transform`、`query_insts`（`refnos` 数组）、`run_doctor`。
+
+use crate::RefnoEnum;
+use crate::blocking::global_client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::ffi::{CStr, CString, c_char};
+use std::str::FromStr;
+
+#[derive(Debug, Deserialize)]
+struct FfiRequest {
+    method: String,
+    #[serde(default)]
+    refno: Option<String>,
+    #[serde(default)]
+    refnos: Vec<String>,
+    #[serde(default)]
+    enable_holes: bool,
+}
+
+fn parse_refno(s: &str) -> anyhow::Result<RefnoEnum> {
+    RefnoEnum::from_str(s).map_err(|_| anyhow::anyhow!("无效的参考号: {}", s))
+}
+
+fn dispatch(request: &str) -> anyhow::Result<Value> {
+    let req: FfiRequest = serde_json::from_str(request)?;
+    let client = global_client()?;
+    let refno = || -> anyhow::Result<RefnoEnum> {
+        parse_refno(
+            req.refno
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("缺少 refno"))?,
+        )
+    };
+    let data = match req.method.as_str() {
+        "get_pe" => serde_json::to_value(client.get_pe(refno()?)?)?,
+        "get_named_attmap" => serde_json::to_value(client.get_named_attmap(refno()?)?)?,
+        "get_children_refnos" => serde_json::to_value(client.get_children_refnos(refno()?)?)?,
+        "query_deep_children_refnos" => {
+            serde_json::to_value(client.query_deep_children_refnos(refno()?)?)?
+        }
+        "get_world_transform" => {
+            // 以列主序 4x4 矩阵返回，便于宿主直接使用
+            let mat = client
+                .get_world_transform(refno()?)?
+                .map(|t| t.to_matrix().to_cols_array());
+            serde_json::to_value(mat)?
+        }
+        "query_insts" => {
+            let refnos = req
+                .refnos
+                .iter()
+                .map(|s| parse_refno(s))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            serde_json::to_value(client.query_insts(&refnos, req.enable_holes)?)?
+        }
+        "run_doctor" => serde_json::to_value(client.run_doctor()?)?,
+        other => anyhow::bail!("未知方法: {}", other),
+    };
+    Ok(data)
+}
+
+fn into_c_string(value: Value) -> *mut c_char {
+    // JSON 序列化结果不含内部 NUL
+    CString::new(value.to_string())
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// 初始化数据库连接，成功返回 0
+#[unsafe(no_mangle)]
+pub extern "C" fn aios_init() -> i32 {
+    match global_client() {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("aios_init 失败: {}", e);
+            -1
+        }
+    }
+}
+
+/// 执行 JSON 请求，返回 JSON 结果字符串
+///
+/// # Safety
+/// `request` 必须是有效的、以 NUL 结尾的 UTF-8 字符串。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aios_call(request: *const c_char) -> *mut c_char {
+    if request.is_null() {
+        return into_c_string(json!({"ok": false, "error": "request 为空"}));
+    }
+    let request = unsafe { CStr::from_ptr(request) }.to_string_lossy();
+    let result = std::panic::catch_unwind(|| dispatch(&request));
+    let value = match result {
+        Ok(Ok(data)) => json!({"ok": true, "data": data}),
+        Ok(Err(e)) => json!({"ok": false, "error": e.to_string()}),
+        Err(_) => json!({"ok": false, "error": "内部错误"}),
+    };
+    into_c_string(value)
+}
+
+/// 释放 [`aios_call`] 返回的字符串
+///
+/// # Safety
+/// `s` 必须是 [`aios_call`] 返回的指针，且只能释放一次。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aios_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
@@ -0,0 +1,328 @@
+//! 几何缓存的 rkyv 序列化
+//!
+//! [`ShapeInstancesData`] / [`PlantGeoData`] 以 rkyv 格式写入文件，读取有两种方式：
+//!
+//! - `deserialize_from_bin_file`：整体反序列化为普通结构体
+//! - [`ShapeInstancesData::open_mmap`]（`mmap` 特性）：内存映射文件，只做一次校验，
+//!   之后通过 [`MmapArchive::archived`] 零拷贝访问归档数据，适合查看器打开数 GB 的缓存
+//!
+//! `Transform` 与 `Aabb` 没有 rkyv 实现，字段上通过 [`TransformAsArray`] / [`AabbAsArray`]
+//! 以定长 `f32` 数组归档。
+//!
+//! ```rust,ignore
+//! let cache = ShapeInstancesData::open_mmap("geo_cache.rkyv")?;
+//! for (refno, info) in cache.archived().inst_info_map.iter() {
+//!     let aabb = info.aabb.as_ref().map(aabb_from_archived);
+//! }
+//! ```
+
+use super::{PlantGeoData, ShapeInstancesData};
+use bevy_transform::components::Transform;
+use glam::{Quat, Vec3};
+use nalgebra::Point3;
+use parry3d::bounding_volume::Aabb;
+use rkyv::rancor::{self, Fallible};
+use rkyv::util::AlignedVec;
+use rkyv::with::{ArchiveWith, DeserializeWith, SerializeWith};
+use rkyv::{Archive, Archived, Deserialize, Place, Resolver, Serialize};
+use std::path::Path;
+
+/// `Aabb` 归档为 `[mins, maxs]`
+pub struct AabbAsArray;
+
+fn aabb_to_array(aabb: &Aabb) -> [f32; 6] {
+    [
+        aabb.mins.x,
+        aabb.mins.y,
+        aabb.mins.z,
+        aabb.maxs.x,
+        aabb.maxs.y,
+        aabb.maxs.z,
+    ]
+}
+
+fn aabb_from_array(v: [f32; 6]) -> Aabb {
+    Aabb::new(Point3::new(v[0], v[1], v[2]), Point3::new(v[3], v[4], v[5]))
+}
+
+/// 从归档数据还原 `Aabb`
+pub fn aabb_from_archived(v: &Archived<[f32; 6]>) -> Aabb {
+    aabb_from_array(v.map(|c| c.to_native()))
+}
+
+impl ArchiveWith<Aabb> for AabbAsArray {
+    type Archived = Archived<[f32; 6]>;
+    type Resolver = Resolver<[f32; 6]>;
+
+    fn resolve_with(field: &Aabb, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        aabb_to_array(field).resolve(resolver, out);
+    }
+}
+
+impl<S: Fallible + ?Sized> SerializeWith<Aabb, S> for AabbAsArray {
+    fn serialize_with(field: &Aabb, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        aabb_to_array(field).serialize(serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> DeserializeWith<Archived<[f32; 6]>, Aabb, D> for AabbAsArray {
+    fn deserialize_with(
+        field: &Archived<[f32; 6]>,
+        deserializer: &mut D,
+    ) -> Result<Aabb, D::Error> {
+        Ok(aabb_from_array(field.deserialize(deserializer)?))
+    }
+}
+
+/// `Transform` 归档为 `[translation, rotation(xyzw), scale]`
+pub struct TransformAsArray;
+
+fn transform_to_array(t: &Transform) -> [f32; 10] {
+    let [tx, ty, tz] = t.translation.to_array();
+    let [rx, ry, rz, rw] = t.rotation.to_array();
+    let [sx, sy, sz] = t.scale.to_array();
+    [tx, ty, tz, rx, ry, rz, rw, sx, sy, sz]
+}
+
+fn transform_from_array(v: [f32; 10]) -> Transform {
+    Transform {
+        translation: Vec3::new(v[0], v[1], v[2]),
+        rotation: Quat::from_xyzw(v[3], v[4], v[5], v[6]),
+        scale: Vec3::new(v[7], v[8], v[9]),
+    }
+}
+
+/// 从归档数据还原 `Transform`
+pub fn transform_from_archived(v: &Archived<[f32; 10]>) -> Transform {
+    transform_from_array(v.map(|c| c.to_native()))
+}
+
+impl ArchiveWith<Transform> for TransformAsArray {
+    type Archived = Archived<[f32; 10]>;
+    type Resolver = Resolver<[f32; 10]>;
+
+    fn resolve_with(field: &Transform, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        transform_to_array(field).resolve(resolver, out);
+    }
+}
+
+impl<S: Fallible + ?Sized> SerializeWith<Transform, S> for TransformAsArray {
+    fn serialize_with(field: &Transform, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        transform_to_array(field).serialize(serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> DeserializeWith<Archived<[f32; 10]>, Transform, D> for TransformAsArray {
+    fn deserialize_with(
+        field: &Archived<[f32; 10]>,
+        deserializer: &mut D,
+    ) -> Result<Transform, D::Error> {
+        Ok(transform_from_array(field.deserialize(deserializer)?))
+    }
+}
+
+/// rkyv 要求归档数据按类型对齐，普通 `Vec<u8>` 需要先拷贝到对齐的缓冲区
+fn aligned(bytes: &[u8]) -> AlignedVec {
+    let mut buf = AlignedVec::with_capacity(bytes.len());
+    buf.extend_from_slice(bytes);
+    buf
+}
+
+macro_rules! impl_rkyv_file {
+    ($ty:ty) => {
+        impl $ty {
+            pub fn serialize_to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+                Ok(rkyv::to_bytes::<rancor::Error>(self)?.to_vec())
+            }
+
+            pub fn serialize_to_specify_file(
+                &self,
+                file_path: impl AsRef<Path>,
+            ) -> anyhow::Result<()> {
+                std::fs::write(file_path, rkyv::to_bytes::<rancor::Error>(self)?)?;
+                Ok(())
+            }
+
+            /// 校验并反序列化
+            pub fn deserialize_from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+                Ok(rkyv::from_bytes::<Self, rancor::Error>(&aligned(bytes))?)
+            }
+
+            pub fn deserialize_from_bin_file(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+                Self::deserialize_from_bytes(&std::fs::read(file_path)?)
+            }
+        }
+    };
+}
+
+impl_rkyv_file!(ShapeInstancesData);
+impl_rkyv_file!(PlantGeoData);
+
+#[cfg(feature = "mmap")]
+pub use self::mmap::*;
+
+#[cfg(feature = "mmap")]
+mod mmap {
+    use super::*;
+    use anyhow::Context;
+    use rkyv::api::high::{HighDeserializer, HighValidator};
+    use rkyv::bytecheck::CheckBytes;
+    use std::fs::File;
+    use std::marker::PhantomData;
+
+    /// 内存映射的 rkyv 归档，打开时校验一次，之后只读访问
+    pub struct MmapArchive<T> {
+        mmap: memmap2::Mmap,
+        _marker: PhantomData<T>,
+    }
+
+    /// 内存映射的实例数据缓存
+    pub type MmapShapeInstances = MmapArchive<ShapeInstancesData>;
+
+    impl<T: Archive> MmapArchive<T>
+    where
+        T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>>,
+    {
+        /// 映射并校验 `path`
+        ///
+        /// 映射期间文件不能被截断或改写，缓存文件应只由生成端整体替换。
+        pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+            let path = path.as_ref();
+            let file = File::open(path)
+                .with_context(|| format!("打开几何缓存失败: {}", path.display()))?;
+            // SAFETY: 见上方文档，文件在映射期间保持不变
+            let mmap = unsafe { memmap2::Mmap::map(&file)? };
+            rkyv::access::<T::Archived, rancor::Error>(&mmap)
+                .with_context(|| format!("几何缓存校验失败: {}", path.display()))?;
+            Ok(Self {
+                mmap,
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    impl<T: Archive> MmapArchive<T> {
+        /// 跳过校验直接映射，用于已确认完整的缓存
+        ///
+        /// # Safety
+        ///
+        /// 文件必须是 `T` 的有效归档，且映射期间不被修改。
+        pub unsafe fn open_unchecked(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+            let file = File::open(path.as_ref())?;
+            let mmap = unsafe { memmap2::Mmap::map(&file)? };
+            Ok(Self {
+                mmap,
+                _marker: PhantomData,
+            })
+        }
+
+        /// 只读的归档数据
+        #[inline]
+        pub fn archived(&self) -> &T::Archived {
+            // SAFETY: open 时已校验，或由 open_unchecked 的调用方保证
+            unsafe { rkyv::access_unchecked::<T::Archived>(&self.mmap) }
+        }
+
+        /// 映射的字节数
+        #[inline]
+        pub fn len(&self) -> usize {
+            self.mmap.len()
+        }
+
+        #[inline]
+        pub fn is_empty(&self) -> bool {
+            self.mmap.is_empty()
+        }
+
+        /// 完整反序列化
+        pub fn deserialize(&self) -> anyhow::Result<T>
+        where
+            T::Archived: Deserialize<T, HighDeserializer<rancor::Error>>,
+        {
+            Ok(rkyv::deserialize::<T, rancor::Error>(self.archived())?)
+        }
+    }
+
+    impl ShapeInstancesData {
+        /// 内存映射打开 [`Self::serialize_to_specify_file`] 写出的缓存
+        pub fn open_mmap(path: impl AsRef<Path>) -> anyhow::Result<MmapShapeInstances> {
+            MmapArchive::open(path)
+        }
+    }
+
+    impl PlantGeoData {
+        pub fn open_mmap(path: impl AsRef<Path>) -> anyhow::Result<MmapArchive<Self>> {
+            MmapArchive::open(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{EleGeosInfo, EleInstGeo, EleInstGeosData};
+    use crate::{RefU64, RefnoEnum};
+
+    fn sample() -> ShapeInstancesData {
+        let refno: RefnoEnum = RefU64::from_two_nums(17496, 1).into();
+        let mut data = ShapeInstancesData::default();
+        data.insert_info(
+            refno,
+            EleGeosInfo {
+                refno,
+                cata_hash: Some("100".into()),
+                aabb: Some(aabb_from_array([0.0, 0.0, 0.0, 1.0, 2.0, 3.0])),
+                world_transform: Transform::from_xyz(1.0, 2.0, 3.0),
+                ..Default::default()
+            },
+        );
+        data.insert_geos_data(
+            "100".into(),
+            EleInstGeosData {
+                inst_key: "100".into(),
+                insts: vec![EleInstGeo {
+                    geo_hash: 1,
+                    transform: Transform::from_scale(Vec3::splat(2.0)),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        );
+        data
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let data = sample();
+        let bytes = data.serialize_to_bytes().unwrap();
+        let back = ShapeInstancesData::deserialize_from_bytes(&bytes).unwrap();
+        let info = back.inst_info_map.values().next().unwrap();
+        assert_eq!(info.world_transform, Transform::from_xyz(1.0, 2.0, 3.0));
+        assert_eq!(info.aabb.unwrap().maxs, Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            back.inst_geos_map["100"].insts[0].transform.scale,
+            Vec3::splat(2.0)
+        );
+
+        assert!(ShapeInstancesData::deserialize_from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_open_mmap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shape_insts.rkyv");
+        sample().serialize_to_specify_file(&path).unwrap();
+
+        let cache = ShapeInstancesData::open_mmap(&path).unwrap();
+        let info = cache.archived().inst_info_map.values().next().unwrap();
+        assert_eq!(
+            transform_from_archived(&info.world_transform),
+            Transform::from_xyz(1.0, 2.0, 3.0)
+        );
+        assert_eq!(cache.deserialize().unwrap().inst_geos_map.len(), 1);
+
+        std::fs::write(&path, b"not an archive").unwrap();
+        assert!(ShapeInstancesData::open_mmap(&path).is_err());
+    }
+}
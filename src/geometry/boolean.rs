@@ -0,0 +1,257 @@
+//! 负实体布尔运算
+//!
+//! [`ShapeInstancesData`] 中记录了三类负实体：
+//! - 元件库负实体（同一个 `EleInstGeosData` 中 `geo_type == CataNeg` 的几何）
+//! - Design 负实体（`neg_relate_map`，负实体元素自身的 `Neg` 几何）
+//! - NGMR（`ngmr_neg_relate_map`，其他元素中 `CataCrossNeg` 类型的指定几何）
+//!
+//! [`ShapeInstancesData::evaluate_booleans`] 在元素局部坐标系下用 manifold 从正实体中减去
+//! 这些负实体，结果作为该元素独立的 `GeoBasicType::Compound` 几何保存。
+
+use super::{EleGeosInfo, EleInstGeo, EleInstGeosData, GeoBasicType, ShapeInstancesData};
+use crate::{RefnoEnum, gen_bytes_hash};
+use crate::csg::manifold::ManifoldRust;
+use crate::shape::pdms_shape::PlantMesh;
+use glam::{DMat4, Vec3};
+use std::collections::{BTreeSet, HashMap};
+
+/// 单个元素的布尔运算失败
+#[derive(Debug, Clone)]
+pub struct BooleanFailure {
+    pub refno: RefnoEnum,
+    pub reason: String,
+}
+
+/// 单个元素的布尔运算结果
+#[derive(Debug, Clone)]
+pub struct CompoundResult {
+    /// Compound 几何的 geo_hash，mesh 需按该值保存
+    pub geo_hash: u64,
+    /// 元素局部坐标系下的 mesh
+    pub mesh: PlantMesh,
+    /// 参与运算的负实体数
+    pub neg_count: usize,
+}
+
+/// [`ShapeInstancesData::evaluate_booleans`] 的结果
+#[derive(Debug, Default)]
+pub struct BooleanReport {
+    pub compounds: HashMap<RefnoEnum, CompoundResult>,
+    pub failures: Vec<BooleanFailure>,
+}
+
+impl BooleanReport {
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// 元素 Compound 几何的 geo_hash
+pub fn compound_geo_hash(refno: RefnoEnum) -> u64 {
+    gen_bytes_hash(&(refno.to_string(), "compound"))
+}
+
+#[inline]
+fn is_positive(geo: &EleInstGeo) -> bool {
+    matches!(
+        geo.geo_type,
+        GeoBasicType::Pos | GeoBasicType::DesiPos | GeoBasicType::CatePos
+    )
+}
+
+fn to_manifold(mesh: PlantMesh, mat: DMat4) -> ManifoldRust {
+    ManifoldRust::convert_to_manifold(mesh, mat, false)
+}
+
+impl ShapeInstancesData {
+    /// 需要做布尔运算的元素
+    pub fn boolean_refnos(&self) -> BTreeSet<RefnoEnum> {
+        let mut refnos: BTreeSet<RefnoEnum> = self
+            .neg_relate_map
+            .keys()
+            .chain(self.ngmr_neg_relate_map.keys())
+            .copied()
+            .collect();
+        refnos.extend(
+            self.inst_info_map
+                .iter()
+                .filter(|(_, info)| {
+                    info.has_cata_neg
+                        || self
+                            .get_inst_geos_data(info)
+                            .is_some_and(|g| g.has_cata_neg())
+                })
+                .map(|(refno, _)| *refno),
+        );
+        refnos
+    }
+
+    /// 负实体元素在 `ele_world` 坐标系下的 manifold 列表
+    fn collect_neg_manifolds(
+        &self,
+        refno: RefnoEnum,
+        ele_world_inv: DMat4,
+    ) -> anyhow::Result<Vec<ManifoldRust>> {
+        let mut negs = vec![];
+        let mut push_negs = |owner: RefnoEnum, filter: &dyn Fn(&EleInstGeo) -> bool| {
+            let info = self
+                .get_inst_info(owner)
+                .ok_or_else(|| anyhow::anyhow!("负实体 {} 缺少 inst_info", owner))?;
+            let geos = self
+                .get_inst_geos_data(info)
+                .ok_or_else(|| anyhow::anyhow!("负实体 {} 缺少几何数据", owner))?;
+            let mat = ele_world_inv * info.world_transform.to_matrix().as_dmat4();
+            for geo in geos.insts.iter().filter(|g| filter(g)) {
+                let mesh = geo.build_csg_shape()?;
+                negs.push(to_manifold(mesh.as_ref().clone(), mat));
+            }
+            anyhow::Ok(())
+        };
+        for &neg_refno in self.neg_relate_map.get(&refno).into_iter().flatten() {
+            push_negs(neg_refno, &|g| g.geo_type == GeoBasicType::Neg)?;
+        }
+        for &(ele_refno, geom_refno) in self.ngmr_neg_relate_map.get(&refno).into_iter().flatten()
+        {
+            push_negs(ele_refno, &|g| {
+                g.geo_type == GeoBasicType::CataCrossNeg && g.refno == geom_refno
+            })?;
+        }
+        Ok(negs)
+    }
+
+    /// 计算单个元素的布尔结果（元素局部坐标系）
+    fn evaluate_element_boolean(
+        &self,
+        refno: RefnoEnum,
+        info: &EleGeosInfo,
+        geos: &EleInstGeosData,
+    ) -> anyhow::Result<CompoundResult> {
+        let ele_world_inv = info.world_transform.to_matrix().as_dmat4().inverse();
+        let design_negs = self.collect_neg_manifolds(refno, ele_world_inv)?;
+        let cata_negs: Vec<(&EleInstGeo, ManifoldRust)> = geos
+            .insts
+            .iter()
+            .filter(|g| g.is_cata_neg())
+            .map(|g| {
+                let mesh = g.build_csg_shape()?;
+                anyhow::Ok((g, to_manifold(mesh.as_ref().clone(), DMat4::IDENTITY)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let positives: Vec<&EleInstGeo> = geos.insts.iter().filter(|g| is_positive(g)).collect();
+        if positives.is_empty() {
+            anyhow::bail!("没有正实体");
+        }
+
+        let mut mesh = PlantMesh::default();
+        let mut neg_count = design_negs.len();
+        for pos in positives {
+            // 元件库负实体只作用于声明了它的正实体，未声明时作用于所有正实体
+            let mut negs = design_negs.clone();
+            for (neg, manifold) in &cata_negs {
+                if pos.cata_neg_refnos.is_empty() || pos.cata_neg_refnos.contains(&neg.refno) {
+                    negs.push(manifold.clone());
+                }
+            }
+            neg_count += negs.len() - design_negs.len();
+
+            let pos_mesh = pos.build_csg_shape()?;
+            let result = to_manifold(pos_mesh.as_ref().clone(), DMat4::IDENTITY)
+                .batch_boolean_subtract(&negs)
+                .get_mesh();
+            mesh.merge(&PlantMesh {
+                vertices: result
+                    .vertices
+                    .chunks_exact(3)
+                    .map(|v| Vec3::new(v[0], v[1], v[2]))
+                    .collect(),
+                indices: result.indices,
+                ..Default::default()
+            });
+        }
+        if mesh.indices.is_empty() {
+            anyhow::bail!("布尔运算结果为空");
+        }
+        mesh.normals = mesh.vertex_normals();
+        mesh.aabb = mesh.cal_aabb();
+        Ok(CompoundResult {
+            geo_hash: compound_geo_hash(refno),
+            mesh,
+            neg_count,
+        })
+    }
+
+    /// 对所有带负实体的元素做布尔运算，结果保存为 `GeoBasicType::Compound`
+    ///
+    /// 每个元素得到独立的几何数据（键为 `{refno}_{sesno}`）：元件库共享的几何会被复制一份，
+    /// 参与运算的正实体和负实体设为不可见，再追加 Compound 几何。
+    /// Compound 的 mesh 在报告中返回，由调用方按 `geo_hash` 保存。
+    /// 单个元素失败不影响其他元素，失败原因记录在报告中。
+    pub fn evaluate_booleans(&mut self) -> BooleanReport {
+        let mut report = BooleanReport::default();
+        for refno in self.boolean_refnos() {
+            let result = (|| {
+                let info = self
+                    .get_inst_info(refno)
+                    .ok_or_else(|| anyhow::anyhow!("缺少 inst_info"))?;
+                let geos = self
+                    .get_inst_geos_data(info)
+                    .ok_or_else(|| anyhow::anyhow!("缺少几何数据 {}", info.get_inst_key()))?;
+                self.evaluate_element_boolean(refno, info, geos)
+            })();
+            match result {
+                Ok(compound) => {
+                    report.compounds.insert(refno, compound);
+                }
+                Err(e) => {
+                    log::warn!("元素 {} 布尔运算失败: {}", refno, e);
+                    report.failures.push(BooleanFailure {
+                        refno,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        for (refno, compound) in &report.compounds {
+            self.store_compound(*refno, compound);
+        }
+        report
+    }
+
+    fn store_compound(&mut self, refno: RefnoEnum, compound: &CompoundResult) {
+        let Some(info) = self.inst_info_map.get_mut(&refno) else {
+            return;
+        };
+        let shared_key = info.get_inst_key();
+        // 去掉 cata_hash 后键变为元素自身的 `{refno}_{sesno}`
+        info.cata_hash = None;
+        let own_key = info.get_inst_key();
+        let mut geos = if shared_key == own_key {
+            self.inst_geos_map.remove(&own_key).unwrap_or_default()
+        } else {
+            let mut geos = self.inst_geos_map.get(&shared_key).cloned().unwrap_or_default();
+            geos.inst_key = own_key.clone();
+            geos.refno = refno;
+            geos
+        };
+
+        geos.insts.retain(|g| g.geo_type != GeoBasicType::Compound);
+        for geo in geos.insts.iter_mut() {
+            if is_positive(geo) || geo.is_cata_neg() {
+                geo.visible = false;
+            }
+        }
+        geos.insts.push(EleInstGeo {
+            geo_hash: compound.geo_hash,
+            refno,
+            aabb: compound.mesh.aabb,
+            visible: true,
+            geo_type: GeoBasicType::Compound,
+            ..Default::default()
+        });
+        geos.aabb = compound.mesh.aabb;
+        self.inst_geos_map.insert(own_key, geos);
+    }
+}
@@ -0,0 +1,375 @@
+//! 重复元素检测
+//!
+//! 导入过程偶尔会产生几何完全重合的重复元素（几何相同、位置相同、参考号不同）。
+//! 本模块通过 AABB 中心的空间哈希 + geo_hash 签名 + 变换量化来查找候选重复组，
+//! 并提供基于软删除（`pe.deleted`）的合并/删除处理接口。
+
+use crate::rs_surreal::hierarchy::{
+    MAX_ATTEMPTS, guard_children, is_concurrent_modified, place_child, query_child_rows,
+    rewrite_children,
+};
+use crate::rs_surreal::inst::GeomInstQuery;
+use crate::rs_surreal::transaction::Transaction;
+use crate::rs_surreal::version::backup_data;
+use crate::{RefnoEnum, join_pe_keys};
+use bevy_transform::components::Transform;
+use glam::{IVec3, Vec3};
+use parry3d::bounding_volume::Aabb;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// 重复检测的容差配置
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DuplicateTolerance {
+    /// 位置容差（mm），同时作为空间哈希的网格尺寸
+    pub position: f32,
+    /// 旋转容差（四元数分量）
+    pub rotation: f32,
+    /// 缩放容差
+    pub scale: f32,
+}
+
+impl Default for DuplicateTolerance {
+    fn default() -> Self {
+        Self {
+            position: 1.0,
+            rotation: 1.0e-3,
+            scale: 1.0e-3,
+        }
+    }
+}
+
+/// 参与重复检测的单个元素
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateProbe {
+    pub refno: RefnoEnum,
+    /// 排序后的 geo_hash 列表，作为几何签名
+    pub geo_hashes: Vec<String>,
+    pub world_trans: Transform,
+    pub world_aabb: Aabb,
+}
+
+impl DuplicateProbe {
+    /// 从几何实例查询结果构建，没有包围盒的元素返回 None
+    pub fn from_inst_query(inst: &GeomInstQuery) -> Option<Self> {
+        let aabb = inst.world_aabb.as_ref()?.0;
+        let geo_hashes = inst
+            .insts
+            .iter()
+            .map(|x| x.geo_hash.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if geo_hashes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            refno: inst.refno,
+            geo_hashes,
+            world_trans: *inst.world_trans,
+            world_aabb: aabb,
+        })
+    }
+
+    #[inline]
+    fn center(&self) -> Vec3 {
+        Vec3::from(self.world_aabb.center().coords)
+    }
+
+    #[inline]
+    fn cell(&self, cell_size: f32) -> IVec3 {
+        (self.center() / cell_size).floor().as_ivec3()
+    }
+
+    /// 判断两个元素在容差范围内是否重合
+    pub fn coincides_with(&self, other: &Self, tol: &DuplicateTolerance) -> bool {
+        if self.geo_hashes != other.geo_hashes {
+            return false;
+        }
+        if self.center().distance(other.center()) > tol.position {
+            return false;
+        }
+        let (a, b) = (&self.world_trans, &other.world_trans);
+        if a.translation.distance(b.translation) > tol.position {
+            return false;
+        }
+        // q 与 -q 表示同一旋转
+        let dot = a.rotation.dot(b.rotation).abs();
+        if 1.0 - dot > tol.rotation {
+            return false;
+        }
+        (a.scale - b.scale).abs().max_element() <= tol.scale
+    }
+}
+
+/// 一组互为重复的元素
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DuplicateGroup {
+    pub geo_hashes: Vec<String>,
+    /// 组内参考号，已排序
+    pub refnos: Vec<RefnoEnum>,
+}
+
+impl DuplicateGroup {
+    /// 默认保留的元素（参考号最小者，通常为最早创建的）
+    #[inline]
+    pub fn keeper(&self) -> RefnoEnum {
+        self.refnos[0]
+    }
+
+    /// 除保留元素外的重复项
+    #[inline]
+    pub fn redundant(&self) -> &[RefnoEnum] {
+        &self.refnos[1..]
+    }
+}
+
+/// 在给定元素集合中查找重复组
+///
+/// 以 `tol.position` 为网格尺寸对 AABB 中心做空间哈希，只在相邻 27 个网格内比较，
+/// 相互重合的元素通过并查集合并为同一组。
+pub fn find_duplicates(probes: &[DuplicateProbe], tol: &DuplicateTolerance) -> Vec<DuplicateGroup> {
+    let cell_size = tol.position.max(f32::EPSILON);
+    let mut grid: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (i, p) in probes.iter().enumerate() {
+        grid.entry(p.cell(cell_size)).or_default().push(i);
+    }
+
+    let mut parent: Vec<usize> = (0..probes.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for (i, p) in probes.iter().enumerate() {
+        let cell = p.cell(cell_size);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let Some(ids) = grid.get(&(cell + IVec3::new(dx, dy, dz))) else {
+                        continue;
+                    };
+                    for &j in ids {
+                        if j <= i || !p.coincides_with(&probes[j], tol) {
+                            continue;
+                        }
+                        let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                        if ri != rj {
+                            parent[rj] = ri;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..probes.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+    let mut result = groups
+        .into_values()
+        .filter(|ids| ids.len() > 1)
+        .map(|ids| {
+            let mut refnos = ids.iter().map(|&i| probes[i].refno).collect::<Vec<_>>();
+            refnos.sort();
+            refnos.dedup();
+            DuplicateGroup {
+                geo_hashes: probes[ids[0]].geo_hashes.clone(),
+                refnos,
+            }
+        })
+        .filter(|g| g.refnos.len() > 1)
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| a.refnos[0].cmp(&b.refnos[0]));
+    result
+}
+
+/// 查询给定元素的几何实例并检测重复
+pub async fn detect_duplicates(
+    refnos: &[RefnoEnum],
+    tol: &DuplicateTolerance,
+) -> anyhow::Result<Vec<DuplicateGroup>> {
+    let insts = crate::rs_surreal::inst::query_insts(refnos, false).await?;
+    let probes = insts
+        .iter()
+        .filter_map(DuplicateProbe::from_inst_query)
+        .collect::<Vec<_>>();
+    Ok(find_duplicates(&probes, tol))
+}
+
+/// 重复项的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateResolution {
+    /// 仅软删除重复项
+    Delete,
+    /// 将重复项的子节点挂到保留元素下，再软删除重复项
+    Merge,
+}
+
+/// 处理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateResolveReport {
+    pub kept: RefnoEnum,
+    pub deleted: Vec<RefnoEnum>,
+    /// 因锁定或已删除而跳过的元素
+    pub skipped: Vec<RefnoEnum>,
+    /// 合并时迁移的子节点数量
+    pub moved_children: usize,
+}
+
+/// 处理一个重复组
+///
+/// 删除通过 `pe.deleted` 软删除完成，删除前先调用 `fn::backup_data` 备份，
+/// 锁定（`lock`）或已删除的元素会被跳过。`keep` 为空时保留组内参考号最小者。
+/// 合并时子节点按原顺序追加到保留元素的子节点末尾，与软删除在同一事务中提交，
+/// 任一语句失败则整体回滚；子节点列表被并发修改时重新读取并重试。
+pub async fn resolve_duplicate_group(
+    group: &DuplicateGroup,
+    keep: Option<RefnoEnum>,
+    resolution: DuplicateResolution,
+    sesno: u32,
+) -> anyhow::Result<DuplicateResolveReport> {
+    let kept = keep.unwrap_or_else(|| group.keeper());
+    if !group.refnos.contains(&kept) {
+        anyhow::bail!("保留元素 {} 不在重复组内", kept);
+    }
+    let Some(kept_pe) = crate::rs_surreal::get_pe(kept).await? else {
+        anyhow::bail!("保留元素 {} 不存在", kept);
+    };
+    if kept_pe.deleted {
+        anyhow::bail!("保留元素 {} 已被删除", kept);
+    }
+
+    let mut report = DuplicateResolveReport {
+        kept,
+        ..Default::default()
+    };
+    let mut targets = vec![];
+    for &refno in group.refnos.iter().filter(|&&r| r != kept) {
+        match crate::rs_surreal::get_pe(refno).await? {
+            Some(pe) if !pe.deleted && !pe.lock => targets.push(refno),
+            _ => report.skipped.push(refno),
+        }
+    }
+    if targets.is_empty() {
+        return Ok(report);
+    }
+
+    let refus = targets.iter().map(|r| r.refno()).collect::<Vec<_>>();
+    backup_data(refus.iter(), true, sesno).await?;
+
+    let mut attempt = 1;
+    let moved = loop {
+        match try_commit_resolution(&targets, kept, resolution).await {
+            Err(e) if attempt < MAX_ATTEMPTS && is_concurrent_modified(&e) => {
+                log::warn!("{}，第 {} 次重试", e, attempt);
+                attempt += 1;
+            }
+            result => break result?,
+        }
+    };
+
+    let mut touched = targets.clone();
+    if resolution == DuplicateResolution::Merge {
+        touched.push(kept);
+    }
+    for &refno in touched.iter().chain(&moved) {
+        crate::rs_surreal::clear_all_caches(refno).await;
+    }
+    // 迁移的子节点换了父节点，其世界变换需要重新计算
+    for &child in &moved {
+        crate::transform::invalidate_world_trans_cache_recursive(child).await?;
+    }
+    report.moved_children = moved.len();
+    report.deleted = targets;
+    Ok(report)
+}
+
+/// 在一个事务中迁移子节点（合并时）并软删除重复项，返回迁移的子节点
+async fn try_commit_resolution(
+    targets: &[RefnoEnum],
+    kept: RefnoEnum,
+    resolution: DuplicateResolution,
+) -> anyhow::Result<Vec<RefnoEnum>> {
+    let mut tx = Transaction::new();
+    let mut moved = vec![];
+    if resolution == DuplicateResolution::Merge {
+        let mut kept_children = query_child_rows(kept).await?;
+        guard_children(&mut tx, kept, &kept_children);
+        let kept_key = kept.to_pe_key();
+        for &refno in targets {
+            let children = query_child_rows(refno).await?;
+            if children.is_empty() {
+                continue;
+            }
+            guard_children(&mut tx, refno, &children);
+            rewrite_children(&mut tx, refno, &[]);
+            for child in children {
+                let child_key = child.refno.to_pe_key();
+                tx.push(format!("UPDATE {} SET owner = {}", child_key, kept_key));
+                tx.push(format!(
+                    "UPDATE (select value refno from only {} limit 1) SET OWNER = {}",
+                    child_key, kept_key
+                ));
+                place_child(&mut kept_children, child.refno, usize::MAX);
+                moved.push(child.refno);
+            }
+        }
+        rewrite_children(&mut tx, kept, &kept_children);
+    }
+
+    // 子节点迁移与软删除在同一事务中提交，避免子节点已迁移而重复项未删除
+    tx.push(format!(
+        "UPDATE [{}] SET deleted = true",
+        join_pe_keys(targets.iter())
+    ));
+    tx.commit().await?;
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use glam::Quat;
+
+    fn probe(refno: u64, geo: &str, pos: Vec3) -> DuplicateProbe {
+        DuplicateProbe {
+            refno: RefnoEnum::Refno(RefU64::from_two_nums(1, refno as u32)),
+            geo_hashes: vec![geo.to_string()],
+            world_trans: Transform::from_translation(pos),
+            world_aabb: Aabb::new((pos - Vec3::ONE).into(), (pos + Vec3::ONE).into()),
+        }
+    }
+
+    #[test]
+    fn test_find_coincident_elements() {
+        let probes = vec![
+            probe(1, "a", Vec3::new(100.0, 0.0, 0.0)),
+            probe(2, "a", Vec3::new(100.2, 0.0, 0.0)),
+            probe(3, "b", Vec3::new(100.0, 0.0, 0.0)),
+            probe(4, "a", Vec3::new(500.0, 0.0, 0.0)),
+        ];
+        let groups = find_duplicates(&probes, &DuplicateTolerance::default());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].refnos, vec![probes[0].refno, probes[1].refno]);
+        assert_eq!(groups[0].keeper(), probes[0].refno);
+    }
+
+    #[test]
+    fn test_cell_boundary_and_rotation() {
+        // 跨越网格边界仍应被识别
+        let a = probe(1, "a", Vec3::new(0.99, 0.0, 0.0));
+        let b = probe(2, "a", Vec3::new(1.01, 0.0, 0.0));
+        let mut c = probe(3, "a", Vec3::new(1.0, 0.0, 0.0));
+        c.world_trans.rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        let groups = find_duplicates(&[a, b, c], &DuplicateTolerance::default());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].refnos.len(), 2);
+    }
+}
@@ -0,0 +1,192 @@
+//! 重力流管线的标高剖面
+//!
+//! 排水专业需要沿管线的标高剖面。本模块从分支中心线（元件 arrive/leave 点连线）
+//! 计算里程-标高序列，并按可配置的最小坡度检查反坡/坡度不足的管段。
+//! 坡度以分支 head→tail 方向为流向，下降为正。
+
+use crate::RefnoEnum;
+use crate::parsed_data::CateAxisParam;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// 中心线上的点
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CenterlinePoint {
+    /// 该点所属元件
+    pub refno: RefnoEnum,
+    pub pos: Vec3,
+}
+
+/// 剖面点
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ProfilePoint {
+    pub refno: RefnoEnum,
+    /// 沿中心线的累计长度（mm）
+    pub chainage: f32,
+    /// 标高（mm），即世界坐标 Z
+    pub elevation: f32,
+}
+
+/// 坡度不满足要求的管段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlopeViolation {
+    pub from: ProfilePoint,
+    pub to: ProfilePoint,
+    /// 实际坡度（下降为正），例如 0.01 表示 1%
+    pub slope: f32,
+    pub min_slope: f32,
+}
+
+/// 分支标高剖面数据集
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElevationProfile {
+    pub branch: RefnoEnum,
+    pub points: Vec<ProfilePoint>,
+    pub violations: Vec<SlopeViolation>,
+}
+
+impl ElevationProfile {
+    /// 导出为 CSV 文本（refno, chainage, elevation）
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("refno,chainage,elevation\n");
+        for p in &self.points {
+            out.push_str(&format!("{},{:.1},{:.1}\n", p.refno, p.chainage, p.elevation));
+        }
+        out
+    }
+}
+
+/// 由按顺序排列的元件端口生成中心线
+///
+/// 每个元件依次贡献 arrive、leave 两点，相邻元件之间的连线即隐含直段。
+pub fn centerline_from_ports(ports: &[(RefnoEnum, [CateAxisParam; 2])]) -> Vec<CenterlinePoint> {
+    let mut pts: Vec<CenterlinePoint> = Vec::with_capacity(ports.len() * 2);
+    for (refno, [arrive, leave]) in ports {
+        for p in [arrive.pt.0, leave.pt.0] {
+            // 合并重合点（如 arrive 与 leave 相同的元件）
+            if pts.last().map(|l| l.pos.distance(p) < 1.0e-3).unwrap_or(false) {
+                continue;
+            }
+            pts.push(CenterlinePoint { refno: *refno, pos: p });
+        }
+    }
+    pts
+}
+
+/// 计算中心线的里程-标高序列
+pub fn profile_from_centerline(centerline: &[CenterlinePoint]) -> Vec<ProfilePoint> {
+    let mut chainage = 0.0;
+    let mut prev: Option<Vec3> = None;
+    centerline
+        .iter()
+        .map(|c| {
+            if let Some(p) = prev {
+                chainage += p.distance(c.pos);
+            }
+            prev = Some(c.pos);
+            ProfilePoint {
+                refno: c.refno,
+                chainage,
+                elevation: c.pos.z,
+            }
+        })
+        .collect()
+}
+
+/// 检查坡度
+///
+/// 坡度 = 标高下降量 / 水平投影长度。水平投影接近 0 的立管段只在上升时报告。
+pub fn check_slopes(centerline: &[CenterlinePoint], min_slope: f32) -> Vec<SlopeViolation> {
+    let profile = profile_from_centerline(centerline);
+    let mut violations = vec![];
+    for (i, w) in centerline.windows(2).enumerate() {
+        let d = w[1].pos - w[0].pos;
+        let drop = -d.z;
+        let horizontal = d.truncate().length();
+        let slope = if horizontal < 1.0e-3 {
+            if drop < -1.0e-3 { f32::NEG_INFINITY } else { continue }
+        } else {
+            drop / horizontal
+        };
+        if slope < min_slope {
+            violations.push(SlopeViolation {
+                from: profile[i],
+                to: profile[i + 1],
+                slope,
+                min_slope,
+            });
+        }
+    }
+    violations
+}
+
+/// 查询分支中心线（世界坐标）
+pub async fn query_branch_centerline(bran: RefnoEnum) -> anyhow::Result<Vec<CenterlinePoint>> {
+    let children = crate::rs_surreal::get_children_refnos(bran).await?;
+    let port_map = crate::rs_surreal::point::query_arrive_leave_points_of_branch(bran).await?;
+    let ports = children
+        .into_iter()
+        .filter_map(|c| port_map.get(&c).map(|p| (c, p.value().clone())))
+        .collect::<Vec<_>>();
+    Ok(centerline_from_ports(&ports))
+}
+
+/// 分支的里程-标高序列
+pub async fn elevation_profile(bran: RefnoEnum) -> anyhow::Result<Vec<(f32, f32)>> {
+    let centerline = query_branch_centerline(bran).await?;
+    Ok(profile_from_centerline(&centerline)
+        .into_iter()
+        .map(|p| (p.chainage, p.elevation))
+        .collect())
+}
+
+/// 分支的剖面数据集及坡度违规报告
+pub async fn elevation_profile_report(
+    bran: RefnoEnum,
+    min_slope: f32,
+) -> anyhow::Result<ElevationProfile> {
+    let centerline = query_branch_centerline(bran).await?;
+    Ok(ElevationProfile {
+        branch: bran,
+        points: profile_from_centerline(&centerline),
+        violations: check_slopes(&centerline, min_slope),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn pt(n: u32, x: f32, z: f32) -> CenterlinePoint {
+        CenterlinePoint {
+            refno: RefU64::from_two_nums(1, n).into(),
+            pos: Vec3::new(x, 0.0, z),
+        }
+    }
+
+    #[test]
+    fn test_profile_and_slope_violations() {
+        let line = vec![
+            pt(1, 0.0, 1000.0),
+            // 1% 下坡
+            pt(2, 1000.0, 990.0),
+            // 反坡
+            pt(3, 2000.0, 995.0),
+            // 立管下行
+            pt(4, 2000.0, 500.0),
+            // 0.2% 坡度不足
+            pt(5, 3000.0, 498.0),
+        ];
+        let profile = profile_from_centerline(&line);
+        assert_eq!(profile.len(), 5);
+        assert!((profile[1].chainage - 1000.05).abs() < 0.01);
+        assert_eq!(profile[4].elevation, 498.0);
+
+        let v = check_slopes(&line, 0.005);
+        assert_eq!(v.len(), 2);
+        assert!(v[0].slope < 0.0);
+        assert_eq!(v[0].from.refno, line[1].refno);
+        assert!((v[1].slope - 0.002).abs() < 1e-5);
+    }
+}
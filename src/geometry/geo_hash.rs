@@ -0,0 +1,354 @@
+//! 几何哈希 V2
+//!
+//! 旧的几何哈希来自 `gen_bytes_hash`（bincode 布局）或 `DefaultHasher`，结构体字段增删、
+//! bincode / std 版本变化都会改变结果，使已存的 inst_geo 全部失效。
+//! V2 使用显式的规范字节编码，只依赖本文件记录的规则：
+//!
+//! - 字节流以 `b"GH"`、版本号 [`GeoHashV2::VERSION`] 与几何类型标签开头
+//! - 字段按 [`PdmsGeoParam::geo_hash_v2`] 中每个分支注释的顺序写入
+//! - f32 / f64 量化为 [`QUANTUM`] 的整数倍后按 i64 小端写入，`-0.0` 与 `0.0` 相同，NaN 统一编码
+//! - 整数小端，bool 一个字节，字符串与序列先写 u32 长度
+//! - 无法逐字段列出的复杂参数（放样体）按规范 JSON 编码：对象键排序、数字量化
+//! - 哈希函数为 FNV-1a 64 位
+//!
+//! 单位几何（长方体、圆柱、球）沿用保留哈希 1 / 2 / 3，不参与编码。
+
+use crate::parsed_data::geo_params_data::PdmsGeoParam;
+use crate::prim_geo::basic::{BOX_GEO_HASH, CYLINDER_GEO_HASH, SPHERE_GEO_HASH};
+use crate::prim_geo::wire::CurveType;
+use crate::types::InstGeoKey;
+use glam::{DVec3, Vec3};
+use std::fmt;
+
+/// 浮点量化步长（mm）
+pub const QUANTUM: f64 = 1e-4;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 版本化的几何哈希
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GeoHashV2(pub u64);
+
+impl GeoHashV2 {
+    /// 编码版本，写入字节流开头，同时记录在迁移后 inst_geo 的 `hash_ver` 字段
+    pub const VERSION: u8 = 2;
+
+    #[inline]
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// 是否为单位几何的保留哈希
+    #[inline]
+    pub fn is_reserved(self) -> bool {
+        matches!(self.0, BOX_GEO_HASH | CYLINDER_GEO_HASH | SPHERE_GEO_HASH)
+    }
+
+    #[inline]
+    pub fn key(self) -> InstGeoKey {
+        InstGeoKey::new(self.0.to_string())
+    }
+}
+
+impl fmt::Display for GeoHashV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// 规范字节编码器
+#[derive(Debug, Clone)]
+pub struct GeoHashEncoder {
+    buf: Vec<u8>,
+}
+
+impl GeoHashEncoder {
+    /// 写入魔数、版本与几何类型标签
+    pub fn new(tag: &str) -> Self {
+        let mut enc = Self {
+            buf: Vec::with_capacity(64),
+        };
+        enc.buf.extend_from_slice(b"GH");
+        enc.buf.push(GeoHashV2::VERSION);
+        enc.str(tag);
+        enc
+    }
+
+    pub fn u32(&mut self, v: u32) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn bool(&mut self, v: bool) -> &mut Self {
+        self.buf.push(v as u8);
+        self
+    }
+
+    pub fn str(&mut self, v: &str) -> &mut Self {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v.as_bytes());
+        self
+    }
+
+    pub fn f64(&mut self, v: f64) -> &mut Self {
+        self.buf.extend_from_slice(&quantize(v).to_le_bytes());
+        self
+    }
+
+    #[inline]
+    pub fn f32(&mut self, v: f32) -> &mut Self {
+        self.f64(v as f64)
+    }
+
+    pub fn vec3(&mut self, v: Vec3) -> &mut Self {
+        self.f32(v.x).f32(v.y).f32(v.z)
+    }
+
+    pub fn dvec3(&mut self, v: DVec3) -> &mut Self {
+        self.f64(v.x).f64(v.y).f64(v.z)
+    }
+
+    /// 序列：先写长度，再逐项写入
+    pub fn seq<T>(&mut self, items: &[T], mut f: impl FnMut(&mut Self, &T)) -> &mut Self {
+        self.u32(items.len() as u32);
+        for item in items {
+            f(self, item);
+        }
+        self
+    }
+
+    /// 规范 JSON：类型字节 + 内容，对象按键排序，数字量化
+    pub fn json(&mut self, v: &serde_json::Value) -> &mut Self {
+        use serde_json::Value;
+        match v {
+            Value::Null => self.buf.push(0),
+            Value::Bool(b) => {
+                self.buf.push(1);
+                self.bool(*b);
+            }
+            Value::Number(n) => {
+                self.buf.push(2);
+                self.f64(n.as_f64().unwrap_or_default());
+            }
+            Value::String(s) => {
+                self.buf.push(3);
+                self.str(s);
+            }
+            Value::Array(a) => {
+                self.buf.push(4);
+                self.seq(a, |e, v| {
+                    e.json(v);
+                });
+            }
+            Value::Object(o) => {
+                self.buf.push(5);
+                let mut keys = o.keys().collect::<Vec<_>>();
+                keys.sort();
+                self.u32(keys.len() as u32);
+                for k in keys {
+                    self.str(k).json(&o[k]);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn finish(&self) -> GeoHashV2 {
+        GeoHashV2(fnv1a(&self.buf))
+    }
+}
+
+/// 量化为 [`QUANTUM`] 的整数倍；NaN 为 `i64::MIN`，无穷大饱和
+fn quantize(v: f64) -> i64 {
+    if v.is_nan() {
+        return i64::MIN;
+    }
+    // `as` 对超范围值饱和，-0.0 得到 0
+    (v / QUANTUM).round() as i64
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(FNV_OFFSET, |h, &b| (h ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn loops(enc: &mut GeoHashEncoder, verts: &[Vec<Vec3>]) {
+    enc.seq(verts, |e, l| {
+        e.seq(l, |e, v| {
+            e.vec3(*v);
+        });
+    });
+}
+
+impl PdmsGeoParam {
+    /// 计算 V2 几何哈希，`Unknown` / `CompoundShape` 返回 None
+    pub fn geo_hash_v2(&self) -> Option<GeoHashV2> {
+        let enc = match self {
+            PdmsGeoParam::Unknown | PdmsGeoParam::CompoundShape => return None,
+            PdmsGeoParam::PrimBox(_) => return Some(GeoHashV2(BOX_GEO_HASH)),
+            PdmsGeoParam::PrimSphere(_) => return Some(GeoHashV2(SPHERE_GEO_HASH)),
+            PdmsGeoParam::PrimLCylinder(_) => return Some(GeoHashV2(CYLINDER_GEO_HASH)),
+            PdmsGeoParam::PrimSCylinder(c) if !c.is_sscl() => {
+                return Some(GeoHashV2(CYLINDER_GEO_HASH));
+            }
+            // paxi_pt, paxi_dir, phei, pdia, btm_shear_angles, top_shear_angles, negative, center_in_mid
+            PdmsGeoParam::PrimSCylinder(c) => {
+                let mut e = GeoHashEncoder::new("SSCL");
+                e.vec3(c.paxi_pt).vec3(c.paxi_dir).f32(c.phei).f32(c.pdia);
+                e.seq(&c.btm_shear_angles, |e, v| {
+                    e.f32(*v);
+                });
+                e.seq(&c.top_shear_angles, |e, v| {
+                    e.f32(*v);
+                });
+                e.bool(c.negative).bool(c.center_in_mid);
+                e
+            }
+            // paax_pt, paax_dir, pbax_pt, pbax_dir, ptdi, pbdi, ptdm, pbdm, poff, btm_on_top
+            PdmsGeoParam::PrimLSnout(s) => {
+                let mut e = GeoHashEncoder::new("LSNO");
+                e.vec3(s.paax_pt)
+                    .vec3(s.paax_dir)
+                    .vec3(s.pbax_pt)
+                    .vec3(s.pbax_dir);
+                e.f32(s.ptdi)
+                    .f32(s.pbdi)
+                    .f32(s.ptdm)
+                    .f32(s.pbdm)
+                    .f32(s.poff);
+                e.bool(s.btm_on_top);
+                e
+            }
+            // paax_pt, paax_dir, pdis, pheig, pdia, prad
+            PdmsGeoParam::PrimDish(d) => {
+                let mut e = GeoHashEncoder::new("DISH");
+                e.vec3(d.paax_pt).vec3(d.paax_dir);
+                e.f32(d.pdis).f32(d.pheig).f32(d.pdia).f32(d.prad);
+                e
+            }
+            // rins, rout, angle
+            PdmsGeoParam::PrimCTorus(t) => {
+                let mut e = GeoHashEncoder::new("CTOR");
+                e.f32(t.rins).f32(t.rout).f32(t.angle);
+                e
+            }
+            // rins, rout, height, angle
+            PdmsGeoParam::PrimRTorus(t) => {
+                let mut e = GeoHashEncoder::new("RTOR");
+                e.f32(t.rins).f32(t.rout).f32(t.height).f32(t.angle);
+                e
+            }
+            // paax_pt, paax_dir, pbax_pt, pbax_dir, pcax_pt, pcax_dir,
+            // pbtp, pctp, pbbt, pcbt, ptdi, pbdi, pbof, pcof
+            PdmsGeoParam::PrimPyramid(p) => {
+                let mut e = GeoHashEncoder::new("PYRA");
+                e.vec3(p.paax_pt)
+                    .vec3(p.paax_dir)
+                    .vec3(p.pbax_pt)
+                    .vec3(p.pbax_dir);
+                e.vec3(p.pcax_pt).vec3(p.pcax_dir);
+                e.f32(p.pbtp).f32(p.pctp).f32(p.pbbt).f32(p.pcbt);
+                e.f32(p.ptdi).f32(p.pbdi).f32(p.pbof).f32(p.pcof);
+                e
+            }
+            // 同 Pyramid
+            PdmsGeoParam::PrimLPyramid(p) => {
+                let mut e = GeoHashEncoder::new("LPYR");
+                e.vec3(p.paax_pt)
+                    .vec3(p.paax_dir)
+                    .vec3(p.pbax_pt)
+                    .vec3(p.pbax_dir);
+                e.vec3(p.pcax_pt).vec3(p.pcax_dir);
+                e.f32(p.pbtp).f32(p.pctp).f32(p.pbbt).f32(p.pcbt);
+                e.f32(p.ptdi).f32(p.pbdi).f32(p.pbof).f32(p.pcof);
+                e
+            }
+            // verts, angle
+            PdmsGeoParam::PrimRevolution(r) => {
+                let mut e = GeoHashEncoder::new("REVO");
+                loops(&mut e, &r.verts);
+                e.f32(r.angle);
+                e
+            }
+            // verts, height, cur_type（Fill 为 0；Spline 为 1 + 厚度）
+            PdmsGeoParam::PrimExtrusion(x) => {
+                let mut e = GeoHashEncoder::new("EXTR");
+                loops(&mut e, &x.verts);
+                e.f32(x.height);
+                match x.cur_type {
+                    CurveType::Fill => e.u32(0),
+                    CurveType::Spline(thick) => e.u32(1).f32(thick),
+                };
+                e
+            }
+            // polygons[].loops, is_polyhe；缓存的 mesh 不参与
+            PdmsGeoParam::PrimPolyhedron(p) => {
+                let mut e = GeoHashEncoder::new("POHE");
+                e.seq(&p.polygons, |e, poly| loops(e, &poly.loops));
+                e.bool(p.is_polyhe);
+                e
+            }
+            // 规范 JSON
+            PdmsGeoParam::PrimLoft(s) => {
+                let mut e = GeoHashEncoder::new("LOFT");
+                e.json(&serde_json::to_value(s).ok()?);
+                e
+            }
+        };
+        Some(enc.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prim_geo::{CTorus, SBox};
+
+    #[test]
+    fn test_encoding_is_fixed() {
+        let mut e = GeoHashEncoder::new("T");
+        e.f32(1.0).bool(true);
+        assert_eq!(
+            e.bytes(),
+            [
+                b'G', b'H', 2, 1, 0, 0, 0, b'T', 0x10, 0x27, 0, 0, 0, 0, 0, 0, 1
+            ]
+        );
+        // FNV-1a 的已知值，防止哈希实现被替换
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(quantize(-0.0), quantize(0.0));
+        assert_eq!(quantize(1.000_01), quantize(1.0));
+        assert_ne!(quantize(1.0001), quantize(1.0));
+        assert_eq!(quantize(f64::NAN), i64::MIN);
+    }
+
+    #[test]
+    fn test_param_hash() {
+        let a = PdmsGeoParam::PrimCTorus(CTorus {
+            rins: 10.0,
+            rout: 20.0,
+            angle: 90.0,
+        });
+        let b = PdmsGeoParam::PrimCTorus(CTorus {
+            rins: 10.0,
+            rout: 20.0,
+            angle: 90.000_01,
+        });
+        assert_eq!(a.geo_hash_v2(), b.geo_hash_v2());
+        assert!(!a.geo_hash_v2().unwrap().is_reserved());
+
+        let boxed = PdmsGeoParam::PrimBox(SBox::default());
+        assert_eq!(boxed.geo_hash_v2(), Some(GeoHashV2(BOX_GEO_HASH)));
+        assert_eq!(PdmsGeoParam::Unknown.geo_hash_v2(), None);
+    }
+}
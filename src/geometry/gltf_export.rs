@@ -0,0 +1,621 @@
+//! 将实例化几何导出为 glTF 2.0 (glb)
+//!
+//! 同一个 `geo_hash` 的所有实例共用一个 mesh，实例的世界变换写入
+//! `EXT_mesh_gpu_instancing` 扩展的 TRANSLATION/ROTATION/SCALE 访问器；
+//! 只有一个实例的几何体直接写成带 TRS 的普通节点。
+//! 节点按加载优先级（见 [`crate::fast_model::export_model::priority`]）降序排列，
+//! 得分写入节点的 `extras.priority`，客户端可据此先请求重要的几何。
+//! 设置 [`GltfExportOptions::compression`] 时按 [`MeshCompressionProfile`] 优化/量化每个 mesh，
+//! 量化的反变换合并到节点（或实例）的 TRS 中。
+//! mesh 数据由调用方按 `geo_hash` 提供（例如 [`load_mesh_from_dir`]），
+//! 或通过 [`GltfInstancingExporter::write_glb_from_store`] 从 [`MeshBlobStore`] 读取。
+
+use super::{EleGeosInfo, EleInstGeo, EleInstGeosData, GeoBasicType, ShapeInstancesData};
+use crate::fast_model::export_model::export_glb::glb_bytes;
+use crate::fast_model::export_model::mesh_compress::{
+    CompressionStats, MeshCompressionProfile, QuantizedMesh, compress_mesh,
+};
+use crate::fast_model::export_model::priority::{PriorityConfig, aabb_priority};
+use crate::mesh_store::{LocalDirStore, MeshBlobStore, load_meshes};
+use crate::shape::pdms_shape::PlantMesh;
+use crate::utils::build_mesh_path;
+use anyhow::Result;
+use bevy_transform::components::Transform;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+const EXT_INSTANCING: &str = "EXT_mesh_gpu_instancing";
+const EXT_QUANTIZATION: &str = "KHR_mesh_quantization";
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const BYTE: u32 = 5120;
+const UNSIGNED_SHORT: u32 = 5123;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+/// 导出选项
+#[derive(Debug, Clone)]
+pub struct GltfExportOptions {
+    /// 是否把 PDMS 的 Z 轴向上转换为 glTF 的 Y 轴向上
+    pub z_up_to_y_up: bool,
+    /// 是否导出不可见的几何体
+    pub include_invisible: bool,
+    /// 是否导出负实体（默认只导出布尔运算的结果和正实体）
+    pub include_neg: bool,
+    /// 加载优先级参数
+    pub priority: PriorityConfig,
+    /// mesh 压缩配置，None 表示按原样写入 f32 数据
+    pub compression: Option<MeshCompressionProfile>,
+}
+
+impl Default for GltfExportOptions {
+    fn default() -> Self {
+        Self {
+            z_up_to_y_up: true,
+            include_invisible: false,
+            include_neg: false,
+            priority: PriorityConfig::default(),
+            compression: None,
+        }
+    }
+}
+
+/// 导出统计
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GltfExportStats {
+    /// 写入的 mesh 数（即不同的 geo_hash 数）
+    pub meshes: usize,
+    /// 写入的实例数
+    pub instances: usize,
+    /// 找不到 mesh 而跳过的 geo_hash
+    pub missing_meshes: Vec<u64>,
+    /// 启用压缩时每个 mesh（按 geo_hash 分块）的压缩统计
+    pub compression: Vec<CompressionStats>,
+}
+
+/// 从本地目录读取原始精度 mesh，文件布局与 [`LocalDirStore`] 一致（见 [`build_mesh_path`]）
+pub fn load_mesh_from_dir(dir: impl Into<PathBuf>) -> impl FnMut(u64) -> Option<PlantMesh> {
+    let store = LocalDirStore::new(dir);
+    move |geo_hash| {
+        PlantMesh::des_mesh_file(&store.path_of(&build_mesh_path(&geo_hash.to_string(), ""))).ok()
+    }
+}
+
+/// 按 geo_hash 收集实例并导出为 glb
+#[derive(Debug, Default)]
+pub struct GltfInstancingExporter {
+    pub options: GltfExportOptions,
+    groups: BTreeMap<u64, Vec<Transform>>,
+    /// geo_hash -> 使用它的元素的优先级之和
+    priorities: HashMap<u64, f32>,
+}
+
+impl GltfInstancingExporter {
+    pub fn new(options: GltfExportOptions) -> Self {
+        Self {
+            options,
+            groups: BTreeMap::new(),
+            priorities: HashMap::new(),
+        }
+    }
+
+    fn accept(&self, geo: &EleInstGeo) -> bool {
+        let is_neg = matches!(
+            geo.geo_type,
+            GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+        );
+        (geo.visible || self.options.include_invisible) && (!is_neg || self.options.include_neg)
+    }
+
+    /// 添加一个元素的几何，`world` 为元素的世界变换
+    ///
+    /// 与 [`EleGeosInfo::get_geo_world_transform`] 一致，隐含直段（tubi）的变换已经是世界坐标
+    pub fn add_geos(&mut self, geos: &EleInstGeosData, world: Transform) {
+        for geo in geos.insts.iter().filter(|g| self.accept(g)) {
+            let transform = if geo.is_tubi {
+                geo.transform
+            } else {
+                world * geo.transform
+            };
+            self.add_instance(geo.geo_hash, transform);
+        }
+    }
+
+    /// 直接添加一个实例
+    pub fn add_instance(&mut self, geo_hash: u64, transform: Transform) {
+        self.groups.entry(geo_hash).or_default().push(transform);
+    }
+
+    /// 添加 [`ShapeInstancesData`] 中所有元素（含隐含直段）的几何
+    pub fn add_shape_instances(&mut self, data: &ShapeInstancesData) {
+        let infos: Vec<&EleGeosInfo> = data
+            .inst_info_map
+            .values()
+            .chain(data.inst_tubi_map.values())
+            .filter(|info| info.visible || self.options.include_invisible)
+            .collect();
+        for info in infos {
+            let Some(geos) = data.inst_geos_map.get(&info.get_inst_key()) else {
+                continue;
+            };
+            self.add_geos(geos, info.get_ele_world_transform());
+            if let Some(aabb) = &info.aabb {
+                let score = aabb_priority(aabb, info.generic_type, &self.options.priority);
+                let hashes: Vec<u64> = geos
+                    .insts
+                    .iter()
+                    .filter(|g| self.accept(g))
+                    .map(|g| g.geo_hash)
+                    .collect();
+                for geo_hash in hashes {
+                    self.add_priority(geo_hash, score);
+                }
+            }
+        }
+    }
+
+    /// 累加 geo_hash 的加载优先级
+    pub fn add_priority(&mut self, geo_hash: u64, score: f32) {
+        *self.priorities.entry(geo_hash).or_default() += score;
+    }
+
+    /// geo_hash 按加载优先级降序排列，得分相同时按 geo_hash 升序
+    pub fn priority_order(&self) -> Vec<u64> {
+        let mut order: Vec<u64> = self.groups.keys().copied().collect();
+        order.sort_by(|a, b| self.priority(*b).total_cmp(&self.priority(*a)));
+        order
+    }
+
+    fn priority(&self, geo_hash: u64) -> f32 {
+        self.priorities.get(&geo_hash).copied().unwrap_or(0.0)
+    }
+
+    /// 实例总数
+    pub fn instance_count(&self) -> usize {
+        self.groups.values().map(|v| v.len()).sum()
+    }
+
+    /// 已添加的 geo_hash
+    pub fn geo_hashes(&self) -> impl Iterator<Item = u64> + '_ {
+        self.groups.keys().copied()
+    }
+
+    /// 生成 glb 字节
+    pub fn to_glb(
+        &self,
+        mut mesh_loader: impl FnMut(u64) -> Option<PlantMesh>,
+    ) -> Result<(Vec<u8>, GltfExportStats)> {
+        let mut stats = GltfExportStats::default();
+        let mut buffer = GlbBuffer::default();
+        let mut meshes = vec![];
+        let mut nodes = vec![json!({})];
+        let mut children = vec![];
+        let mut use_instancing = false;
+        let mut use_quantization = false;
+
+        for geo_hash in self.priority_order() {
+            let Some(mesh) = mesh_loader(geo_hash).filter(|m| !m.vertices.is_empty()) else {
+                stats.missing_meshes.push(geo_hash);
+                continue;
+            };
+            let mut transforms = self.groups[&geo_hash].clone();
+            let primitive = match &self.options.compression {
+                Some(profile) => {
+                    let (optimized, quantized, mut chunk_stats) =
+                        compress_mesh(&mesh, profile, &geo_hash.to_string());
+                    let start = buffer.data.len();
+                    let primitive = match &quantized {
+                        Some(q) => {
+                            use_quantization = true;
+                            for t in &mut transforms {
+                                *t = dequantized(*t, q);
+                            }
+                            buffer.push_quantized_primitive(&optimized, q, profile.quantize_normals)
+                        }
+                        None => buffer.push_primitive(&optimized),
+                    };
+                    chunk_stats.compressed_bytes = buffer.data.len() - start;
+                    stats.compression.push(chunk_stats);
+                    primitive
+                }
+                None => buffer.push_primitive(&mesh),
+            };
+            let mesh_index = meshes.len();
+            meshes.push(json!({
+                "name": geo_hash.to_string(),
+                "primitives": [primitive],
+            }));
+
+            let mut node = json!({
+                "name": geo_hash.to_string(),
+                "mesh": mesh_index,
+            });
+            if let Some(score) = self.priorities.get(&geo_hash) {
+                node["extras"] = json!({ "priority": score });
+            }
+            if let [t] = transforms.as_slice() {
+                node["translation"] = json!(t.translation.to_array());
+                node["rotation"] = json!(t.rotation.normalize().to_array());
+                node["scale"] = json!(t.scale.to_array());
+            } else {
+                use_instancing = true;
+                let translations: Vec<[f32; 3]> = transforms
+                    .iter()
+                    .map(|t| t.translation.to_array())
+                    .collect();
+                let rotations: Vec<[f32; 4]> = transforms
+                    .iter()
+                    .map(|t| t.rotation.normalize().to_array())
+                    .collect();
+                let scales: Vec<[f32; 3]> = transforms.iter().map(|t| t.scale.to_array()).collect();
+                node["extensions"] = json!({
+                    EXT_INSTANCING: {
+                        "attributes": {
+                            "TRANSLATION": buffer.push_floats(&translations, "VEC3", None, false),
+                            "ROTATION": buffer.push_floats(&rotations, "VEC4", None, false),
+                            "SCALE": buffer.push_floats(&scales, "VEC3", None, false),
+                        }
+                    }
+                });
+            }
+            children.push(nodes.len());
+            nodes.push(node);
+            stats.meshes += 1;
+            stats.instances += transforms.len();
+        }
+
+        // 根节点负责坐标系转换：Z 轴向上 -> Y 轴向上（绕 X 轴 -90°）
+        nodes[0] = json!({ "name": "root", "children": children });
+        if self.options.z_up_to_y_up {
+            let half = std::f32::consts::FRAC_1_SQRT_2;
+            nodes[0]["rotation"] = json!([-half, 0.0, 0.0, half]);
+        }
+
+        let mut gltf = json!({
+            "asset": {
+                "version": "2.0",
+                "generator": "AIOS GLB Exporter"
+            },
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": nodes,
+            "meshes": meshes,
+            "buffers": [{
+                "byteLength": buffer.data.len()
+            }],
+            "bufferViews": buffer.views,
+            "accessors": buffer.accessors
+        });
+        let extensions: Vec<&str> = [
+            (use_instancing, EXT_INSTANCING),
+            (use_quantization, EXT_QUANTIZATION),
+        ]
+        .into_iter()
+        .filter_map(|(used, ext)| used.then_some(ext))
+        .collect();
+        if !extensions.is_empty() {
+            gltf["extensionsUsed"] = json!(extensions);
+            gltf["extensionsRequired"] = json!(extensions);
+        }
+
+        Ok((glb_bytes(&gltf, &buffer.data)?, stats))
+    }
+
+    /// 导出到 glb 文件
+    pub fn write_glb(
+        &self,
+        path: &Path,
+        mesh_loader: impl FnMut(u64) -> Option<PlantMesh>,
+    ) -> Result<GltfExportStats> {
+        let (bytes, stats) = self.to_glb(mesh_loader)?;
+        std::fs::write(path, bytes)?;
+        if !stats.missing_meshes.is_empty() {
+            log::warn!(
+                "glb 导出 {}: {} 个几何体缺少 mesh",
+                path.display(),
+                stats.missing_meshes.len()
+            );
+        }
+        Ok(stats)
+    }
+
+    /// 从 mesh 存储读取原始精度 mesh 并导出到 glb 文件
+    pub async fn write_glb_from_store(
+        &self,
+        path: &Path,
+        store: &dyn MeshBlobStore,
+    ) -> Result<GltfExportStats> {
+        let mut meshes = load_meshes(store, self.geo_hashes(), "").await;
+        self.write_glb(path, |geo_hash| meshes.remove(&geo_hash))
+    }
+}
+
+/// 导出 [`ShapeInstancesData`] 到 glb 文件，mesh 从 `store` 读取（见 [`crate::mesh_store::mesh_store`]）
+pub async fn export_shape_instances_glb(
+    data: &ShapeInstancesData,
+    store: &dyn MeshBlobStore,
+    output_path: &Path,
+    options: GltfExportOptions,
+) -> Result<GltfExportStats> {
+    let mut exporter = GltfInstancingExporter::new(options);
+    exporter.add_shape_instances(data);
+    exporter.write_glb_from_store(output_path, store).await
+}
+
+/// glb 的 BIN 块及其 bufferView/accessor
+#[derive(Default)]
+struct GlbBuffer {
+    data: Vec<u8>,
+    views: Vec<serde_json::Value>,
+    accessors: Vec<serde_json::Value>,
+}
+
+impl GlbBuffer {
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        while self.data.len() % 4 != 0 {
+            self.data.push(0);
+        }
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.data.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    /// 写入浮点数组，返回 accessor 索引；`with_bounds` 时写入 min/max（POSITION 必需）
+    fn push_floats<const N: usize>(
+        &mut self,
+        values: &[[f32; N]],
+        ty: &str,
+        target: Option<u32>,
+        with_bounds: bool,
+    ) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|v| v.iter().flat_map(|f| f.to_le_bytes()))
+            .collect();
+        let view = self.push_view(&bytes, target);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len(),
+            "type": ty,
+        });
+        if with_bounds {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for v in values {
+                for i in 0..N {
+                    min[i] = min[i].min(v[i]);
+                    max[i] = max[i].max(v[i]);
+                }
+            }
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_primitive(&mut self, mesh: &PlantMesh) -> serde_json::Value {
+        let positions: Vec<[f32; 3]> = mesh.vertices.iter().map(|v| v.to_array()).collect();
+        let mut attributes = json!({
+            "POSITION": self.push_floats(&positions, "VEC3", Some(ARRAY_BUFFER), true)
+        });
+        if mesh.normals.len() == mesh.vertices.len() {
+            let normals: Vec<[f32; 3]> = mesh
+                .normals
+                .iter()
+                .map(|n| n.normalize_or_zero().to_array())
+                .collect();
+            attributes["NORMAL"] =
+                json!(self.push_floats(&normals, "VEC3", Some(ARRAY_BUFFER), false));
+        }
+        let mut primitive = json!({ "attributes": attributes, "mode": 4 });
+        if !mesh.indices.is_empty() {
+            primitive["indices"] = json!(self.push_indices(&mesh.indices, false));
+        }
+        primitive
+    }
+
+    /// 写入索引，`short` 时使用 u16
+    fn push_indices(&mut self, indices: &[u32], short: bool) -> usize {
+        let bytes: Vec<u8> = if short {
+            indices
+                .iter()
+                .flat_map(|&i| (i as u16).to_le_bytes())
+                .collect()
+        } else {
+            indices.iter().flat_map(|i| i.to_le_bytes()).collect()
+        };
+        let view = self.push_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": if short { UNSIGNED_SHORT } else { UNSIGNED_INT },
+            "count": indices.len(),
+            "type": "SCALAR"
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// 写入量化后的 mesh（`KHR_mesh_quantization`）：位置为 u16，
+    /// 法线在 `quantize_normals` 时为归一化 i8，否则保留 f32
+    fn push_quantized_primitive(
+        &mut self,
+        mesh: &PlantMesh,
+        quantized: &QuantizedMesh,
+        quantize_normals: bool,
+    ) -> serde_json::Value {
+        // u16x3 补齐为 8 字节步长
+        let bytes: Vec<u8> = quantized
+            .positions
+            .iter()
+            .flat_map(|p| [p[0], p[1], p[2], 0])
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        self.views[view]["byteStride"] = json!(8);
+        let (min, max) =
+            quantized
+                .positions
+                .iter()
+                .fold(([u16::MAX; 3], [0u16; 3]), |(min, max), p| {
+                    (
+                        [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+                        [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+                    )
+                });
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_SHORT,
+            "count": quantized.positions.len(),
+            "type": "VEC3",
+            "min": min,
+            "max": max,
+        }));
+        let mut attributes = json!({ "POSITION": self.accessors.len() - 1 });
+        if !mesh.normals.is_empty() && mesh.normals.len() == mesh.vertices.len() {
+            if quantize_normals {
+                // i8x3 补齐为 4 字节步长
+                let bytes: Vec<u8> = quantized
+                    .normals
+                    .iter()
+                    .flat_map(|n| [n[0] as u8, n[1] as u8, n[2] as u8, 0])
+                    .collect();
+                let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+                self.views[view]["byteStride"] = json!(4);
+                self.accessors.push(json!({
+                    "bufferView": view,
+                    "componentType": BYTE,
+                    "normalized": true,
+                    "count": quantized.normals.len(),
+                    "type": "VEC3"
+                }));
+                attributes["NORMAL"] = json!(self.accessors.len() - 1);
+            } else {
+                let normals: Vec<[f32; 3]> = mesh
+                    .normals
+                    .iter()
+                    .map(|n| n.normalize_or_zero().to_array())
+                    .collect();
+                attributes["NORMAL"] =
+                    json!(self.push_floats(&normals, "VEC3", Some(ARRAY_BUFFER), false));
+            }
+        }
+        let mut primitive = json!({ "attributes": attributes, "mode": 4 });
+        if !quantized.indices.is_empty() {
+            let short = quantized.positions.len() <= u16::MAX as usize;
+            primitive["indices"] = json!(self.push_indices(&quantized.indices, short));
+        }
+        primitive
+    }
+}
+
+/// 把量化的反变换（先缩放再平移到 `offset`）合并到实例变换中
+///
+/// `t * T(offset) * S(scale)` 仍可表示为 TRS：平移为 `t` 作用于 `offset` 的结果，缩放逐分量相乘
+fn dequantized(t: Transform, quantized: &QuantizedMesh) -> Transform {
+    Transform {
+        translation: t.transform_point(quantized.offset),
+        rotation: t.rotation,
+        scale: t.scale * quantized.scale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    fn triangle() -> PlantMesh {
+        PlantMesh {
+            indices: vec![0, 1, 2],
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            normals: vec![Vec3::Z; 3],
+            ..Default::default()
+        }
+    }
+
+    fn parse_json_chunk(glb: &[u8]) -> serde_json::Value {
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+            glb.len()
+        );
+        let json_len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        serde_json::from_slice(&glb[20..20 + json_len]).unwrap()
+    }
+
+    #[test]
+    fn test_instanced_glb() {
+        let mut exporter = GltfInstancingExporter::new(GltfExportOptions::default());
+        exporter.add_instance(1, Transform::from_xyz(0.0, 0.0, 0.0));
+        exporter.add_instance(1, Transform::from_xyz(1000.0, 0.0, 0.0));
+        exporter.add_instance(2, Transform::from_xyz(0.0, 500.0, 0.0));
+        exporter.add_instance(3, Transform::IDENTITY);
+
+        let (glb, stats) = exporter.to_glb(|hash| (hash != 3).then(triangle)).unwrap();
+        assert_eq!(stats.meshes, 2);
+        assert_eq!(stats.instances, 3);
+        assert_eq!(stats.missing_meshes, vec![3]);
+
+        let gltf = parse_json_chunk(&glb);
+        assert_eq!(gltf["extensionsRequired"][0], EXT_INSTANCING);
+        let nodes = gltf["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+        let instancing = &nodes[1]["extensions"][EXT_INSTANCING]["attributes"];
+        let translation = instancing["TRANSLATION"].as_u64().unwrap() as usize;
+        assert_eq!(gltf["accessors"][translation]["count"], 2);
+        // 单个实例直接写在节点上
+        assert_eq!(nodes[2]["translation"], json!([0.0, 500.0, 0.0]));
+    }
+
+    #[test]
+    fn test_quantized_glb() {
+        let options = GltfExportOptions {
+            compression: Some(MeshCompressionProfile {
+                quantize_normals: false,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut exporter = GltfInstancingExporter::new(options);
+        exporter.add_instance(1, Transform::from_xyz(100.0, 0.0, 0.0));
+        let (glb, stats) = exporter.to_glb(|_| Some(triangle())).unwrap();
+        assert_eq!(stats.compression.len(), 1);
+        assert!(stats.compression[0].compressed_bytes > 0);
+
+        let gltf = parse_json_chunk(&glb);
+        assert_eq!(gltf["extensionsRequired"][0], EXT_QUANTIZATION);
+        let attributes = &gltf["meshes"][0]["primitives"][0]["attributes"];
+        let position = attributes["POSITION"].as_u64().unwrap() as usize;
+        let normal = attributes["NORMAL"].as_u64().unwrap() as usize;
+        assert_eq!(gltf["accessors"][position]["componentType"], UNSIGNED_SHORT);
+        // 未量化法线时保留 f32 法线
+        assert_eq!(gltf["accessors"][normal]["componentType"], FLOAT);
+        // 反量化合并到节点变换：原点顶点仍落在 (100, 0, 0)
+        assert_eq!(gltf["nodes"][1]["translation"], json!([100.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_nodes_ordered_by_priority() {
+        let mut exporter = GltfInstancingExporter::new(GltfExportOptions::default());
+        exporter.add_instance(1, Transform::IDENTITY);
+        exporter.add_instance(2, Transform::IDENTITY);
+        exporter.add_priority(2, 0.5);
+        assert_eq!(exporter.priority_order(), vec![2, 1]);
+
+        let (glb, _) = exporter.to_glb(|_| Some(triangle())).unwrap();
+        let gltf = parse_json_chunk(&glb);
+        assert_eq!(gltf["nodes"][1]["name"], "2");
+        assert_eq!(gltf["nodes"][1]["extras"]["priority"], 0.5);
+        assert!(gltf["nodes"][2].get("extras").is_none());
+    }
+}
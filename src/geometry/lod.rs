@@ -0,0 +1,488 @@
+//! 网格 LOD 生成
+//!
+//! 对同一个 geo_hash 的 `PlantMesh` 做二次误差度量（QEM）边折叠简化，生成若干个
+//! 低精度版本，供 web 端按视距选择。简化前按位置焊接顶点（CSG 输出在硬边处会拆分顶点），
+//! 开放边界上的顶点保持不动，折叠导致三角面翻转时放弃该次折叠。
+//! 生成结果的文件布局与 [`crate::utils::lod_path_detector::build_mesh_path`] 一致。
+
+use super::EleInstGeosData;
+use crate::mesh_precision::LodLevel;
+use crate::mesh_store::{MeshBlobStore, save_mesh};
+use crate::rs_surreal::transaction::Transaction;
+use crate::shape::pdms_shape::PlantMesh;
+use glam::{DMat4, DVec3, DVec4};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// 单个 LOD 的生成目标
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LodTarget {
+    pub level: LodLevel,
+    /// 保留的三角面比例
+    pub ratio: f32,
+    /// 视距大于等于该值时使用此 LOD（mm）
+    pub min_distance: f32,
+}
+
+/// LOD 生成配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LodConfig {
+    /// 源 mesh 对应的等级
+    pub source_level: LodLevel,
+    /// 由精到粗排列的目标
+    pub targets: Vec<LodTarget>,
+    /// 允许的最大几何误差（mm），超过后停止折叠
+    pub max_error: f32,
+    /// 三角面数少于该值的 mesh 不生成 LOD
+    pub min_triangles: usize,
+    /// 顶点焊接容差（mm）
+    pub weld_tolerance: f32,
+}
+
+impl Default for LodConfig {
+    fn default() -> Self {
+        Self {
+            source_level: LodLevel::L2,
+            targets: vec![
+                LodTarget {
+                    level: LodLevel::L1,
+                    ratio: 0.5,
+                    min_distance: 20_000.0,
+                },
+                LodTarget {
+                    level: LodLevel::L0,
+                    ratio: 0.2,
+                    min_distance: 60_000.0,
+                },
+            ],
+            max_error: 50.0,
+            min_triangles: 64,
+            weld_tolerance: 0.01,
+        }
+    }
+}
+
+/// 生成的一个 LOD
+#[derive(Debug, Clone)]
+pub struct MeshLod {
+    pub level: LodLevel,
+    pub min_distance: f32,
+    pub mesh: PlantMesh,
+}
+
+/// 记录在 [`EleInstGeosData`] 上的 LOD 信息
+#[derive(
+    rkyv::Archive,
+    rkyv::Deserialize,
+    rkyv::Serialize,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+)]
+pub struct GeoLodInfo {
+    pub geo_hash: u64,
+    pub level: LodLevel,
+    pub triangles: u32,
+    /// 视距大于等于该值时使用此 LOD（mm）
+    pub min_distance: f32,
+}
+
+impl GeoLodInfo {
+    /// mesh 文件相对 meshes 目录的路径
+    pub fn mesh_file(&self, mesh_dir: &Path) -> PathBuf {
+        lod_mesh_file(mesh_dir, self.geo_hash, Some(self.level))
+    }
+}
+
+/// LOD 等级的目录/文件后缀，如 `L1`
+pub fn lod_suffix(level: LodLevel) -> &'static str {
+    match level {
+        LodLevel::L0 => "L0",
+        LodLevel::L1 => "L1",
+        LodLevel::L2 => "L2",
+        LodLevel::L3 => "L3",
+        LodLevel::L4 => "L4",
+    }
+}
+
+/// `{mesh_dir}/lod_{L}/{geo_hash}_{L}.mesh`，`level` 为空时为 `{mesh_dir}/{geo_hash}.mesh`
+pub fn lod_mesh_file(mesh_dir: &Path, geo_hash: u64, level: Option<LodLevel>) -> PathBuf {
+    match level.map(lod_suffix) {
+        Some(l) => mesh_dir
+            .join(format!("lod_{l}"))
+            .join(format!("{geo_hash}_{l}.mesh")),
+        None => mesh_dir.join(format!("{geo_hash}.mesh")),
+    }
+}
+
+/// 按配置生成 LOD，三角面数太少或简化后没有减少的目标会被跳过
+pub fn generate_lods(mesh: &PlantMesh, config: &LodConfig) -> Vec<MeshLod> {
+    let tri_count = mesh.indices.len() / 3;
+    if tri_count < config.min_triangles {
+        return vec![];
+    }
+    let welded = weld_vertices(mesh, config.weld_tolerance);
+    let mut lods = vec![];
+    let mut last_count = tri_count;
+    for target in &config.targets {
+        let target_tris = ((tri_count as f32) * target.ratio.clamp(0.0, 1.0)) as usize;
+        let decimated = decimate(&welded, target_tris.max(1), config.max_error);
+        let count = decimated.indices.len() / 3;
+        if count == 0 || count >= last_count {
+            continue;
+        }
+        last_count = count;
+        lods.push(MeshLod {
+            level: target.level,
+            min_distance: target.min_distance,
+            mesh: decimated,
+        });
+    }
+    lods
+}
+
+/// 生成 LOD、写入 mesh 存储并在 `geos` 上登记
+///
+/// mesh 通过 [`MeshBlobStore`] 写入，各等级的路径由 `tx` 记录到 inst_geo 上。
+pub async fn build_and_save_lods(
+    geos: &mut EleInstGeosData,
+    geo_hash: u64,
+    mesh: &PlantMesh,
+    store: &dyn MeshBlobStore,
+    config: &LodConfig,
+    tx: &mut Transaction,
+) -> anyhow::Result<Vec<GeoLodInfo>> {
+    let hash = geo_hash.to_string();
+    let mut infos = vec![GeoLodInfo {
+        geo_hash,
+        level: config.source_level,
+        triangles: (mesh.indices.len() / 3) as u32,
+        min_distance: 0.0,
+    }];
+    for lod in generate_lods(mesh, config) {
+        save_mesh(store, &hash, lod_suffix(lod.level), &lod.mesh).await?;
+        infos.push(GeoLodInfo {
+            geo_hash,
+            level: lod.level,
+            triangles: (lod.mesh.indices.len() / 3) as u32,
+            min_distance: lod.min_distance,
+        });
+    }
+    let levels: Vec<&str> = infos[1..].iter().map(|i| lod_suffix(i.level)).collect();
+    tx.save_mesh_paths(&hash, &levels)?;
+    geos.set_lods(geo_hash, infos.clone());
+    Ok(infos)
+}
+
+/// 按位置焊接顶点，丢弃原有法线
+pub fn weld_vertices(mesh: &PlantMesh, tolerance: f32) -> PlantMesh {
+    let tol = tolerance.max(f32::EPSILON);
+    let mut map: HashMap<[i64; 3], u32> = HashMap::new();
+    let mut vertices = vec![];
+    let remap: Vec<u32> = mesh
+        .vertices
+        .iter()
+        .map(|v| {
+            let key = [v.x, v.y, v.z].map(|c| (c / tol).round() as i64);
+            *map.entry(key).or_insert_with(|| {
+                vertices.push(*v);
+                (vertices.len() - 1) as u32
+            })
+        })
+        .collect();
+    let indices = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|t| [remap[t[0] as usize], remap[t[1] as usize], remap[t[2] as usize]])
+        .filter(|t| t[0] != t[1] && t[1] != t[2] && t[0] != t[2])
+        .flatten()
+        .collect();
+    PlantMesh {
+        indices,
+        vertices,
+        ..Default::default()
+    }
+}
+
+#[derive(PartialEq)]
+struct Collapse {
+    cost: f64,
+    a: u32,
+    b: u32,
+    /// 入堆时两端顶点的版本，用于丢弃过期条目
+    stamp: (u32, u32),
+    pos: DVec3,
+}
+
+impl Eq for Collapse {}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 代价小的优先
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn plane_quadric(a: DVec3, b: DVec3, c: DVec3) -> DMat4 {
+    let n = (b - a).cross(c - a);
+    let area = n.length();
+    if area <= f64::EPSILON {
+        return DMat4::ZERO;
+    }
+    let n = n / area;
+    let p = DVec4::new(n.x, n.y, n.z, -n.dot(a));
+    let m = DMat4::from_cols(p * p.x, p * p.y, p * p.z, p * p.w);
+    m * (area * 0.5)
+}
+
+#[inline]
+fn quadric_error(q: &DMat4, v: DVec3) -> f64 {
+    let h = v.extend(1.0);
+    h.dot(*q * h).max(0.0)
+}
+
+/// QEM 边折叠简化，面数降到 `target_tris` 或误差超过 `max_error` 时停止
+///
+/// 输入应已焊接顶点（见 [`weld_vertices`]），输出带面积加权的顶点法线
+pub fn decimate(mesh: &PlantMesh, target_tris: usize, max_error: f32) -> PlantMesh {
+    let mut pos: Vec<DVec3> = mesh.vertices.iter().map(|v| v.as_dvec3()).collect();
+    let mut faces: Vec<[u32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        .collect();
+    let n = pos.len();
+    let mut face_alive = vec![true; faces.len()];
+    let mut alive_faces = faces.len();
+    let mut vertex_faces: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut quadrics = vec![DMat4::ZERO; n];
+    let mut edge_use: HashMap<(u32, u32), u32> = HashMap::new();
+    for (fi, f) in faces.iter().enumerate() {
+        let q = plane_quadric(pos[f[0] as usize], pos[f[1] as usize], pos[f[2] as usize]);
+        for k in 0..3 {
+            let v = f[k] as usize;
+            vertex_faces[v].push(fi);
+            quadrics[v] += q;
+            let (a, b) = (f[k], f[(k + 1) % 3]);
+            *edge_use.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    // 开放边界上的顶点保持不动
+    let mut locked = vec![false; n];
+    for (&(a, b), &cnt) in &edge_use {
+        if cnt == 1 {
+            locked[a as usize] = true;
+            locked[b as usize] = true;
+        }
+    }
+    let max_cost = (max_error as f64).powi(2);
+    let mut removed = vec![false; n];
+    let mut stamp = vec![0u32; n];
+
+    let make_collapse = |a: u32, b: u32, pos: &[DVec3], quadrics: &[DMat4], stamp: &[u32]| {
+        let q = quadrics[a as usize] + quadrics[b as usize];
+        let (pa, pb) = (pos[a as usize], pos[b as usize]);
+        let (cost, p) = [pa, pb, (pa + pb) * 0.5]
+            .into_iter()
+            .map(|p| (quadric_error(&q, p), p))
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .unwrap();
+        Collapse {
+            cost,
+            a,
+            b,
+            stamp: (stamp[a as usize], stamp[b as usize]),
+            pos: p,
+        }
+    };
+
+    let mut heap = BinaryHeap::new();
+    for &(a, b) in edge_use.keys() {
+        if !locked[a as usize] && !locked[b as usize] {
+            heap.push(make_collapse(a, b, &pos, &quadrics, &stamp));
+        }
+    }
+
+    while alive_faces > target_tris {
+        let Some(c) = heap.pop() else { break };
+        let (a, b) = (c.a as usize, c.b as usize);
+        if removed[a] || removed[b] || c.stamp != (stamp[a], stamp[b]) {
+            continue;
+        }
+        if c.cost > max_cost {
+            break;
+        }
+        // 折叠后若有三角面翻转则放弃
+        let flips = [a, b].iter().any(|&v| {
+            vertex_faces[v].iter().any(|&fi| {
+                let f = faces[fi];
+                if !face_alive[fi] || (f.contains(&c.a) && f.contains(&c.b)) {
+                    return false;
+                }
+                let before = f.map(|i| pos[i as usize]);
+                let after = f.map(|i| if i as usize == v { c.pos } else { pos[i as usize] });
+                let n0 = (before[1] - before[0]).cross(before[2] - before[0]);
+                let n1 = (after[1] - after[0]).cross(after[2] - after[0]);
+                n0.dot(n1) <= 0.0
+            })
+        });
+        if flips {
+            continue;
+        }
+
+        // b 合并到 a
+        pos[a] = c.pos;
+        quadrics[a] = quadrics[a] + quadrics[b];
+        removed[b] = true;
+        stamp[a] += 1;
+        let b_faces = std::mem::take(&mut vertex_faces[b]);
+        for fi in b_faces {
+            if !face_alive[fi] {
+                continue;
+            }
+            if faces[fi].contains(&c.a) {
+                face_alive[fi] = false;
+                alive_faces -= 1;
+            } else {
+                for i in faces[fi].iter_mut() {
+                    if *i == c.b {
+                        *i = c.a;
+                    }
+                }
+                vertex_faces[a].push(fi);
+            }
+        }
+        vertex_faces[a].retain(|&fi| face_alive[fi]);
+
+        let neighbors: HashSet<u32> = vertex_faces[a]
+            .iter()
+            .flat_map(|&fi| faces[fi])
+            .filter(|&v| v != c.a)
+            .collect();
+        for v in neighbors {
+            if !locked[v as usize] {
+                heap.push(make_collapse(c.a, v, &pos, &quadrics, &stamp));
+            }
+        }
+    }
+
+    // 压缩顶点并重建索引
+    let mut remap = vec![u32::MAX; n];
+    let mut out = PlantMesh::default();
+    for (fi, f) in faces.iter().enumerate() {
+        if !face_alive[fi] {
+            continue;
+        }
+        for &v in f {
+            let v = v as usize;
+            if remap[v] == u32::MAX {
+                remap[v] = out.vertices.len() as u32;
+                out.vertices.push(pos[v].as_vec3());
+            }
+            out.indices.push(remap[v]);
+        }
+    }
+    out.normals = out.vertex_normals();
+    out.aabb = out.cal_aabb();
+    out
+}
+
+impl EleInstGeosData {
+    /// 登记某个 geo_hash 的 LOD，替换已有的记录
+    pub fn set_lods(&mut self, geo_hash: u64, mut lods: Vec<GeoLodInfo>) {
+        self.lods.retain(|l| l.geo_hash != geo_hash);
+        lods.sort_by(|a, b| a.min_distance.total_cmp(&b.min_distance));
+        self.lods.extend(lods);
+    }
+
+    /// 某个 geo_hash 的 LOD，按视距从近到远排列
+    pub fn lods_for(&self, geo_hash: u64) -> impl Iterator<Item = &GeoLodInfo> {
+        self.lods.iter().filter(move |l| l.geo_hash == geo_hash)
+    }
+
+    /// 按视距选择 LOD：取 `min_distance` 不超过视距的最粗一级，没有登记 LOD 时返回 None
+    pub fn select_lod(&self, geo_hash: u64, distance: f32) -> Option<&GeoLodInfo> {
+        self.lods_for(geo_hash)
+            .filter(|l| l.min_distance <= distance)
+            .last()
+            .or_else(|| self.lods_for(geo_hash).next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    /// n x n 网格的平面，三角面数 2 * (n-1)^2
+    fn grid(n: usize) -> PlantMesh {
+        let mut mesh = PlantMesh::default();
+        for j in 0..n {
+            for i in 0..n {
+                mesh.vertices.push(Vec3::new(i as f32 * 10.0, j as f32 * 10.0, 0.0));
+            }
+        }
+        for j in 0..n - 1 {
+            for i in 0..n - 1 {
+                let v = (j * n + i) as u32;
+                let n = n as u32;
+                mesh.indices
+                    .extend_from_slice(&[v, v + 1, v + n + 1, v, v + n + 1, v + n]);
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_decimate_plane() {
+        let mesh = grid(12);
+        let tris = mesh.indices.len() / 3;
+        let out = decimate(&mesh, tris / 4, 1.0);
+        let out_tris = out.indices.len() / 3;
+        assert!(out_tris < tris / 2, "{} -> {}", tris, out_tris);
+        // 平面简化后仍在平面上，边界不动
+        assert!(out.vertices.iter().all(|v| v.z.abs() < 1e-4));
+        assert_eq!(out.cal_aabb(), mesh.cal_aabb());
+        assert_eq!(out.normals.len(), out.vertices.len());
+    }
+
+    #[test]
+    fn test_generate_and_select_lods() {
+        let mesh = grid(12);
+        let lods = generate_lods(&mesh, &LodConfig::default());
+        assert_eq!(lods.len(), 2);
+        assert!(lods[1].mesh.indices.len() < lods[0].mesh.indices.len());
+
+        let mut geos = EleInstGeosData::default();
+        let mut infos: Vec<GeoLodInfo> = lods
+            .iter()
+            .map(|l| GeoLodInfo {
+                geo_hash: 7,
+                level: l.level,
+                triangles: (l.mesh.indices.len() / 3) as u32,
+                min_distance: l.min_distance,
+            })
+            .collect();
+        infos.push(GeoLodInfo {
+            geo_hash: 7,
+            level: LodLevel::L2,
+            triangles: (mesh.indices.len() / 3) as u32,
+            min_distance: 0.0,
+        });
+        geos.set_lods(7, infos);
+        assert_eq!(geos.select_lod(7, 100.0).unwrap().level, LodLevel::L2);
+        assert_eq!(geos.select_lod(7, 30_000.0).unwrap().level, LodLevel::L1);
+        assert_eq!(geos.select_lod(7, 1e9).unwrap().level, LodLevel::L0);
+        assert!(geos.select_lod(8, 0.0).is_none());
+    }
+}
@@ -0,0 +1,296 @@
+//! 按内容去重的 mesh
+//!
+//! 不同元件库条目经常生成逐字节相同的 mesh，却各自占用一个 geo_hash。
+//! CSG 生成 mesh 后把它登记到 [`MeshStore`]，按顶点 + 索引的指纹归并，
+//! 同一指纹中最小的 geo_hash 作为规范几何；之后调用
+//! [`ShapeInstancesData::dedup_meshes`] 改写实例数据：
+//!
+//! - 所有 `EleInstGeo::geo_hash` 与 LOD 改为规范几何
+//! - 改写后实例列表完全相同的元件库条目（`inst_geos_map` 中键不含 `_` 的项）合并为一条，
+//!   引用被合并条目的 inst_info / inst_tubi 改为引用保留的条目
+//!
+//! 保存与 XKT 导出都按 geo_hash 去重，因此改写后重复的 mesh 与 inst_geo 不再写出。
+//! 被替代的 geo_hash 可从 [`MeshStore::aliases`] 取得，用于清理多余的 mesh 文件。
+//!
+//! ```rust,ignore
+//! let mut store = MeshStore::new();
+//! for (geo_hash, mesh) in &generated {
+//!     store.insert(*geo_hash, mesh);
+//! }
+//! let report = shape_insts.dedup_meshes(&store);
+//! ```
+
+use super::geo_hash::{GeoHashEncoder, fnv1a};
+use super::{EleGeosInfo, EleInstGeo, ShapeInstancesData};
+use crate::shape::pdms_shape::PlantMesh;
+use std::collections::{BTreeMap, HashMap};
+
+/// mesh 指纹：顶点坐标（`-0.0` 视为 `0.0`）与三角形索引的 FNV-1a 哈希
+pub fn mesh_fingerprint(mesh: &PlantMesh) -> u64 {
+    let mut bytes = Vec::with_capacity(8 + mesh.vertices.len() * 12 + mesh.indices.len() * 4);
+    bytes.extend_from_slice(&(mesh.vertices.len() as u32).to_le_bytes());
+    for v in &mesh.vertices {
+        for c in v.to_array() {
+            bytes.extend_from_slice(&(c + 0.0).to_bits().to_le_bytes());
+        }
+    }
+    bytes.extend_from_slice(&(mesh.indices.len() as u32).to_le_bytes());
+    for i in &mesh.indices {
+        bytes.extend_from_slice(&i.to_le_bytes());
+    }
+    fnv1a(&bytes)
+}
+
+/// 按内容寻址的 mesh 登记表
+#[derive(Debug, Clone, Default)]
+pub struct MeshStore {
+    /// 指纹 -> 规范 geo_hash
+    canonical: HashMap<u64, u64>,
+    /// geo_hash -> 指纹
+    fingerprints: HashMap<u64, u64>,
+}
+
+impl MeshStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记 mesh，返回当前的规范 geo_hash
+    pub fn insert(&mut self, geo_hash: u64, mesh: &PlantMesh) -> u64 {
+        self.insert_fingerprint(geo_hash, mesh_fingerprint(mesh))
+    }
+
+    /// 登记已计算好的指纹
+    pub fn insert_fingerprint(&mut self, geo_hash: u64, fingerprint: u64) -> u64 {
+        self.fingerprints.insert(geo_hash, fingerprint);
+        let canonical = self.canonical.entry(fingerprint).or_insert(geo_hash);
+        // 取最小值，使结果与登记顺序无关
+        *canonical = (*canonical).min(geo_hash);
+        *canonical
+    }
+
+    /// geo_hash 对应的规范几何，未登记的返回自身
+    pub fn canonical(&self, geo_hash: u64) -> u64 {
+        self.fingerprints
+            .get(&geo_hash)
+            .and_then(|fp| self.canonical.get(fp))
+            .copied()
+            .unwrap_or(geo_hash)
+    }
+
+    /// 被替代的 (geo_hash, 规范 geo_hash)，按 geo_hash 排序
+    pub fn aliases(&self) -> Vec<(u64, u64)> {
+        let mut aliases = self
+            .fingerprints
+            .keys()
+            .map(|&h| (h, self.canonical(h)))
+            .filter(|(h, c)| h != c)
+            .collect::<Vec<_>>();
+        aliases.sort_unstable();
+        aliases
+    }
+
+    /// 登记的 geo_hash 数
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+
+    /// 不同 mesh 的数量
+    pub fn unique_count(&self) -> usize {
+        self.canonical.len()
+    }
+}
+
+/// 去重结果
+#[derive(Debug, Clone, Default)]
+pub struct MeshDedupReport {
+    /// 改为引用规范几何的实例数
+    pub rewritten_insts: usize,
+    /// 合并掉的 inst_geos 条目：(被合并的键, 保留的键)
+    pub collapsed: Vec<(String, String)>,
+    /// 改为引用保留条目的 inst_info / inst_tubi 数
+    pub redirected_infos: usize,
+}
+
+/// 实例列表的签名，只包含渲染相关的字段
+fn insts_signature(insts: &[EleInstGeo]) -> u64 {
+    let mut e = GeoHashEncoder::new("INSTS");
+    e.seq(insts, |e, g| {
+        e.str(&g.geo_hash.to_string());
+        e.vec3(g.transform.translation);
+        for c in g.transform.rotation.to_array() {
+            e.f32(c);
+        }
+        e.vec3(g.transform.scale);
+        e.str(&g.geo_type.to_string())
+            .bool(g.visible)
+            .bool(g.is_tubi)
+            .bool(g.unit_flag);
+        e.seq(&g.pts, |e, p| {
+            e.u32(*p as u32);
+        });
+        e.seq(&g.cata_neg_refnos, |e, r| {
+            e.str(&r.to_string());
+        });
+    });
+    e.finish().value()
+}
+
+fn redirect(info: &mut EleGeosInfo, collapsed: &HashMap<String, String>) -> bool {
+    let Some(kept) = info.cata_hash.as_ref().and_then(|h| collapsed.get(h)) else {
+        return false;
+    };
+    info.cata_hash = Some(kept.clone());
+    true
+}
+
+impl ShapeInstancesData {
+    /// 按 `store` 把实例改写为引用规范几何，并合并改写后相同的元件库条目
+    pub fn dedup_meshes(&mut self, store: &MeshStore) -> MeshDedupReport {
+        let mut report = MeshDedupReport::default();
+        if store.is_empty() {
+            return report;
+        }
+
+        for geos in self.inst_geos_map.values_mut() {
+            for inst in &mut geos.insts {
+                let canonical = store.canonical(inst.geo_hash);
+                if canonical != inst.geo_hash {
+                    inst.geo_hash = canonical;
+                    report.rewritten_insts += 1;
+                }
+            }
+            let mut lods = Vec::with_capacity(geos.lods.len());
+            for mut lod in std::mem::take(&mut geos.lods) {
+                lod.geo_hash = store.canonical(lod.geo_hash);
+                if !lods.contains(&lod) {
+                    lods.push(lod);
+                }
+            }
+            geos.lods = lods;
+        }
+
+        // 键含 `_` 的是 `{refno}_{sesno}` 形式的私有条目，EleGeosInfo 无法改指向它们
+        let mut groups: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+        for (key, geos) in &self.inst_geos_map {
+            if !key.contains('_') && !geos.insts.is_empty() {
+                groups
+                    .entry(insts_signature(&geos.insts))
+                    .or_default()
+                    .push(key.clone());
+            }
+        }
+        let mut collapsed = HashMap::new();
+        for mut keys in groups.into_values().filter(|k| k.len() > 1) {
+            keys.sort();
+            let kept = keys.remove(0);
+            for key in keys {
+                self.inst_geos_map.remove(&key);
+                report.collapsed.push((key.clone(), kept.clone()));
+                collapsed.insert(key, kept.clone());
+            }
+        }
+        if collapsed.is_empty() {
+            return report;
+        }
+        report.collapsed.sort();
+
+        for info in self
+            .inst_info_map
+            .values_mut()
+            .chain(self.inst_tubi_map.values_mut())
+        {
+            if redirect(info, &collapsed) {
+                report.redirected_infos += 1;
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::EleInstGeosData;
+    use crate::{RefU64, RefnoEnum};
+    use glam::Vec3;
+
+    fn mesh(offset: f32) -> PlantMesh {
+        PlantMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X * offset, Vec3::Y],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+
+    fn geos(key: &str, hash: u64) -> EleInstGeosData {
+        EleInstGeosData {
+            inst_key: key.to_string(),
+            insts: vec![EleInstGeo {
+                geo_hash: hash,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn info(n: u32, cata_hash: &str) -> EleGeosInfo {
+        EleGeosInfo {
+            refno: refno(n),
+            cata_hash: Some(cata_hash.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let mut store = MeshStore::new();
+        assert_eq!(store.insert(20, &mesh(1.0)), 20);
+        assert_eq!(store.insert(10, &mesh(1.0)), 10);
+        assert_eq!(store.insert(30, &mesh(2.0)), 30);
+        assert_eq!(store.canonical(20), 10);
+        assert_eq!(store.canonical(99), 99);
+        assert_eq!(store.aliases(), vec![(20, 10)]);
+        assert_eq!(store.unique_count(), 2);
+
+        let mut negative_zero = mesh(1.0);
+        negative_zero.vertices[0] = Vec3::new(-0.0, 0.0, 0.0);
+        assert_eq!(
+            mesh_fingerprint(&negative_zero),
+            mesh_fingerprint(&mesh(1.0))
+        );
+    }
+
+    #[test]
+    fn test_dedup_meshes() {
+        let mut store = MeshStore::new();
+        store.insert(10, &mesh(1.0));
+        store.insert(20, &mesh(1.0));
+
+        let mut data = ShapeInstancesData::default();
+        data.insert_geos_data("100".into(), geos("100", 10));
+        data.insert_geos_data("200".into(), geos("200", 20));
+        data.insert_geos_data("17496_3_1".into(), geos("17496_3_1", 20));
+        data.insert_info(refno(1), info(1, "100"));
+        data.insert_info(refno(2), info(2, "200"));
+
+        let report = data.dedup_meshes(&store);
+        assert_eq!(report.rewritten_insts, 2);
+        assert_eq!(
+            report.collapsed,
+            vec![("200".to_string(), "100".to_string())]
+        );
+        assert_eq!(report.redirected_infos, 1);
+        assert!(!data.inst_geos_map.contains_key("200"));
+        assert_eq!(data.inst_geos_map["17496_3_1"].insts[0].geo_hash, 10);
+        assert_eq!(data.get_info(&refno(2)).unwrap().get_inst_key(), "100");
+    }
+}
@@ -0,0 +1,196 @@
+//! mesh 增量传输
+//!
+//! 设计会话更新后，服务端用 [`PdmsInstanceMeshData::diff`] 对比新旧两份实例数据，
+//! 只把发生变化的 inst_info / inst_tubi / inst_geos / ngmr 条目打包成 [`MeshPatch`] 下发，
+//! 客户端在本地数据上调用 [`PdmsInstanceMeshData::apply_patch`] 即可得到新数据。
+//!
+//! 条目是否变化按序列化后的字节比较，因此 `#[serde(skip)]` 的字段（如 `ptset_map`、
+//! `neg_relate_map`）既不参与比较也不会传输。
+
+use super::{EleGeosInfo, EleInstGeosData, PdmsInstanceMeshData};
+use crate::RefnoEnum;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// 一张表的增量：新增/修改的条目和删除的键
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapPatch<K: Eq + Hash, V> {
+    pub upserts: HashMap<K, V>,
+    pub removed: Vec<K>,
+}
+
+impl<K: Eq + Hash, V> Default for MapPatch<K, V> {
+    fn default() -> Self {
+        Self {
+            upserts: HashMap::new(),
+            removed: vec![],
+        }
+    }
+}
+
+impl<K, V> MapPatch<K, V>
+where
+    K: Eq + Hash + Clone + Ord,
+    V: serde::Serialize + Clone,
+{
+    fn diff(old: &HashMap<K, V>, new: &HashMap<K, V>) -> Self {
+        let upserts = new
+            .iter()
+            .filter(|(k, v)| old.get(*k).is_none_or(|o| !same_content(o, *v)))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let mut removed: Vec<K> = old
+            .keys()
+            .filter(|k| !new.contains_key(*k))
+            .cloned()
+            .collect();
+        removed.sort();
+        Self { upserts, removed }
+    }
+
+    fn apply(self, target: &mut HashMap<K, V>) {
+        for k in &self.removed {
+            target.remove(k);
+        }
+        target.extend(self.upserts);
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.removed.is_empty()
+    }
+
+    /// 变化的条目数
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.upserts.len() + self.removed.len()
+    }
+}
+
+fn same_content<V: serde::Serialize>(a: &V, b: &V) -> bool {
+    match (bincode::serialize(a), bincode::serialize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// [`PdmsInstanceMeshData`] 的增量数据
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MeshPatch {
+    pub inst_info: MapPatch<RefnoEnum, EleGeosInfo>,
+    pub inst_tubi: MapPatch<RefnoEnum, EleGeosInfo>,
+    pub inst_geos: MapPatch<String, EleInstGeosData>,
+    pub ngmr_neg_relate: MapPatch<RefnoEnum, Vec<(RefnoEnum, RefnoEnum)>>,
+}
+
+impl MeshPatch {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inst_info.is_empty()
+            && self.inst_tubi.is_empty()
+            && self.inst_geos.is_empty()
+            && self.ngmr_neg_relate.is_empty()
+    }
+
+    /// 变化的条目总数
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inst_info.len()
+            + self.inst_tubi.len()
+            + self.inst_geos.len()
+            + self.ngmr_neg_relate.len()
+    }
+
+    /// patch 中新增/修改的几何 hash，客户端据此下载缺失的 mesh 文件
+    pub fn geo_hashes(&self) -> std::collections::BTreeSet<u64> {
+        self.inst_geos
+            .upserts
+            .values()
+            .flat_map(|g| g.insts.iter().map(|i| i.geo_hash))
+            .collect()
+    }
+}
+
+impl PdmsInstanceMeshData {
+    /// 计算从 `old` 到 `new` 的增量
+    pub fn diff(old: &Self, new: &Self) -> MeshPatch {
+        let (o, n) = (&old.shape_insts, &new.shape_insts);
+        MeshPatch {
+            inst_info: MapPatch::diff(&o.inst_info_map, &n.inst_info_map),
+            inst_tubi: MapPatch::diff(&o.inst_tubi_map, &n.inst_tubi_map),
+            inst_geos: MapPatch::diff(&o.inst_geos_map, &n.inst_geos_map),
+            ngmr_neg_relate: MapPatch::diff(&o.ngmr_neg_relate_map, &n.ngmr_neg_relate_map),
+        }
+    }
+
+    /// 把增量应用到当前数据上，重复应用同一个 patch 结果不变
+    pub fn apply_patch(&mut self, patch: MeshPatch) {
+        let insts = &mut self.shape_insts;
+        patch.inst_info.apply(&mut insts.inst_info_map);
+        patch.inst_tubi.apply(&mut insts.inst_tubi_map);
+        patch.inst_geos.apply(&mut insts.inst_geos_map);
+        patch.ngmr_neg_relate.apply(&mut insts.ngmr_neg_relate_map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::EleInstGeo;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn info(n: u32, visible: bool) -> EleGeosInfo {
+        EleGeosInfo {
+            refno: refno(n),
+            visible,
+            ..Default::default()
+        }
+    }
+
+    fn geos(key: &str, hash: u64) -> EleInstGeosData {
+        EleInstGeosData {
+            inst_key: key.to_string(),
+            insts: vec![EleInstGeo {
+                geo_hash: hash,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let mut old = PdmsInstanceMeshData::default();
+        old.shape_insts.insert_info(refno(1), info(1, true));
+        old.shape_insts.insert_info(refno(2), info(2, true));
+        old.shape_insts.insert_info(refno(3), info(3, true));
+        old.shape_insts.inst_geos_map.insert("a".into(), geos("a", 1));
+        old.shape_insts.inst_geos_map.insert("b".into(), geos("b", 2));
+
+        let mut new = PdmsInstanceMeshData::default();
+        new.shape_insts.insert_info(refno(1), info(1, true));
+        new.shape_insts.insert_info(refno(2), info(2, false));
+        new.shape_insts.insert_info(refno(4), info(4, true));
+        new.shape_insts.inst_geos_map.insert("a".into(), geos("a", 1));
+        new.shape_insts.inst_geos_map.insert("b".into(), geos("b", 3));
+
+        let patch = PdmsInstanceMeshData::diff(&old, &new);
+        assert_eq!(patch.inst_info.upserts.len(), 2);
+        assert!(!patch.inst_info.upserts.contains_key(&refno(1)));
+        assert_eq!(patch.inst_info.removed, vec![refno(3)]);
+        assert_eq!(patch.inst_geos.upserts.keys().collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(patch.geo_hashes().into_iter().collect::<Vec<_>>(), vec![3]);
+        assert_eq!(patch.len(), 4);
+
+        let bytes = bincode::serialize(&patch).unwrap();
+        let patch: MeshPatch = bincode::deserialize(&bytes).unwrap();
+        old.apply_patch(patch.clone());
+        old.apply_patch(patch);
+        assert!(PdmsInstanceMeshData::diff(&old, &new).is_empty());
+    }
+}
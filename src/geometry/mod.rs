@@ -1,14 +1,27 @@
+pub mod archive;
+#[cfg(feature = "gen_model")]
+pub mod boolean;
 pub mod csg;
+pub mod duplicate_detector;
+pub mod elevation_profile;
+pub mod geo_hash;
+pub mod gltf_export;
+pub mod lod;
+pub mod mesh_dedup;
+pub mod mesh_patch;
+pub mod stats;
 pub mod sweep_mesh;
+pub mod tubi_repair;
 
 use crate::parsed_data::CateAxisParam;
 use crate::parsed_data::geo_params_data::PdmsGeoParam;
-use crate::vec3_pool::{compress_ptset, CateAxisParamCompact};
+use crate::vec3_pool::{compress_ptset, ptset_sql_value, CateAxisParamCompact, PtsetEncoding};
 use crate::pdms_types::PdmsGenericType;
 use crate::prim_geo::basic::{BOXI_GEO_HASH, TUBI_GEO_HASH};
 use crate::prim_geo::{SBox, SCylinder};
 use crate::shape::pdms_shape::{PlantMesh, RsVec3};
 use crate::tool::hash_tool::hash_two_str;
+use crate::types::{InstGeoKey, InstInfoKey, TubiInfoKey};
 use crate::{RefU64, RefnoEnum, gen_bytes_hash};
 #[cfg(feature = "render")]
 use bevy_asset::RenderAssetUsages;
@@ -67,7 +80,17 @@ pub enum GeoBasicType {
 }
 
 /// 存储一个Element 包含的所有几何信息
-#[derive(Serialize, Deserialize, Debug, Clone, Default, Resource)]
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Default,
+    Resource,
+    rkyv::Archive,
+    rkyv::Deserialize,
+    rkyv::Serialize,
+)]
 #[serde_as]
 pub struct EleGeosInfo {
     pub refno: RefnoEnum,
@@ -83,20 +106,24 @@ pub struct EleGeosInfo {
     //记录对应的元件库参考号
     #[serde(default)]
     #[serde(skip)]
+    #[rkyv(with = rkyv::with::Skip)]
     pub cata_refno: Option<RefnoEnum>,
     //是否可见
     pub visible: bool,
     //所属一般类型，ROOM、STRU、PIPE等, 用枚举处理
     pub generic_type: PdmsGenericType,
+    #[rkyv(with = rkyv::with::Map<archive::AabbAsArray>)]
     pub aabb: Option<Aabb>,
 
     //相对世界坐标系下的变换矩阵 rot, translation, scale
+    #[rkyv(with = archive::TransformAsArray)]
     pub world_transform: Transform,
 
     #[serde(default)]
     pub flow_pt_indexs: Vec<i32>,
 
     #[serde(skip, default)]
+    #[rkyv(with = rkyv::with::Skip)]
     pub ptset_map: BTreeMap<i32, CateAxisParam>,
     pub has_cata_neg: bool,
     pub is_solid: bool,
@@ -123,9 +150,15 @@ impl EleGeosInfo {
         }
     }
 
+    /// `inst_info` 表的键
+    #[inline]
+    pub fn inst_info_key(&self) -> InstInfoKey {
+        InstInfoKey::new(self.id_str())
+    }
+
     ///生成surreal的json文件（原始格式，向后兼容）
     pub fn gen_sur_json(&self, vec3_map: &mut HashMap<u64, String>) -> String {
-        let id = self.id_str();
+        let id = self.inst_info_key();
         // 只序列化 ptset_map 的 values，不包含键
         let ptset_values: Vec<&CateAxisParam> = self.ptset_map.values().collect();
         let mut json = serde_json::to_string_pretty(&serde_json::json!({
@@ -137,7 +170,7 @@ impl EleGeosInfo {
 
         json.remove(json.len() - 1);
         json.push_str(",");
-        json.push_str(&format!(r#""id": inst_info:⟨{}⟩, "#, id));
+        json.push_str(&format!(r#""id": {}, "#, id));
         json.push_str("}");
         json
     }
@@ -154,7 +187,7 @@ impl EleGeosInfo {
     /// - 省略默认值字段（pwidth=0, pheight=0 等）
     /// - 使用短字段名（n, p, d, rd 等）
     pub fn gen_sur_json_compact(&self, include_refno: bool) -> String {
-        let id = self.id_str();
+        let id = self.inst_info_key();
         // 转换为压缩格式
         let ptset_values: Vec<CateAxisParam> = self.ptset_map.values().cloned().collect();
         let ptset_compact = compress_ptset(&ptset_values, include_refno);
@@ -171,13 +204,38 @@ impl EleGeosInfo {
         
         // 添加 tubi_info 关联（如果有）
         if let Some(ref tubi_id) = self.tubi_info_id {
-            json.push_str(&format!(r#","tubi_info":tubi_info:⟨{}⟩"#, tubi_id));
+            json.push_str(&format!(r#","tubi_info":{}"#, TubiInfoKey::new(tubi_id.as_str())));
         }
         
-        json.push_str(&format!(r#","id":inst_info:⟨{}⟩}}"#, id));
+        json.push_str(&format!(r#","id":{}}}"#, id));
         json
     }
 
+    /// 生成surreal的记录，ptset 按 `encoding` 存储
+    ///
+    /// 返回的是 SurrealQL 对象字面量（CBOR 格式的 ptset 是函数调用），不能当作 JSON 解析。
+    pub fn gen_sur_json_encoded(
+        &self,
+        include_refno: bool,
+        encoding: PtsetEncoding,
+    ) -> anyhow::Result<String> {
+        let ptset_values: Vec<CateAxisParam> = self.ptset_map.values().cloned().collect();
+        let mut json = serde_json::to_string(&serde_json::json!({
+            "visible": self.visible,
+            "generic_type": self.generic_type,
+        }))?;
+        json.pop();
+        json.push_str(&format!(
+            r#","ptset":{}"#,
+            ptset_sql_value(&ptset_values, include_refno, encoding)?
+        ));
+        if let Some(ref tubi_id) = self.tubi_info_id {
+            json.push_str(&format!(r#","tubi_info":{}"#, TubiInfoKey::new(tubi_id.as_str())));
+        }
+        json.push_str(&format!(r#","id":{}}}"#, self.inst_info_key()));
+        Ok(json)
+    }
+
     ///获取几何体数据的string key
     #[inline]
     pub fn get_inst_key(&self) -> String {
@@ -261,6 +319,7 @@ impl ShapeInstancesData {
                 }],
                 aabb: Some(unit_cyli_aabb),
                 type_name: "TUBI".to_string(),
+                lods: vec![],
             },
         );
         self.insert_geos_data(
@@ -283,6 +342,7 @@ impl ShapeInstancesData {
                 }],
                 aabb: Some(unit_box_aabb),
                 type_name: "BOXI".to_string(),
+                lods: vec![],
             },
         );
     }
@@ -450,35 +510,13 @@ impl ShapeInstancesData {
         self.inst_info_map.get(refno)
     }
 
-    //serialize_to_bytes
-    // pub fn serialize_to_bytes(&self) -> Vec<u8> {
-    //     let serialized = rkyv::to_bytes::<_, 512>(self).unwrap().to_vec();
-    //     serialized
-    // }
-
-    // pub fn serialize_to_specify_file(&self, file_path: &str) -> bool {
-    //     let mut file = File::create(file_path).unwrap();
-    //     let serialized = rkyv::to_bytes::<_, 512>(self).unwrap().to_vec();
-    //     file.write_all(serialized.as_slice()).unwrap();
-    //     true
-    // }
-
-    // pub fn deserialize_from_bin_file(file_path: &dyn AsRef<Path>) -> anyhow::Result<Self> {
-    //     let mut file = File::open(file_path)?;
-    //     let mut buf: Vec<u8> = Vec::new();
-    //     file.read_to_end(&mut buf).ok();
-    //     use rkyv::Deserialize;
-    //     use std::io::Read;
-    //     let archived = unsafe { rkyv::archived_root::<Self>(buf.as_slice()) };
-    //     let r: Self = archived.deserialize(&mut rkyv::Infallible)?;
-    //     Ok(r)
-    // }
+    // rkyv 序列化 / mmap 读取见 [`archive`]
 
     ///保存compound的edge关系到arango图数据库
     pub async fn save_compound_edges_to_arango() {}
 }
 
-//todo mesh 增量传输
+/// 下发给客户端的实例数据，增量传输见 [`mesh_patch`]
 #[derive(
     Serialize, Deserialize, Debug, Default, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize,
 )]
@@ -500,6 +538,7 @@ pub type GeoHash = u64;
 )]
 pub struct PlantGeoData {
     pub geo_hash: u64,
+    #[rkyv(with = rkyv::with::Map<archive::AabbAsArray>)]
     pub aabb: Option<Aabb>,
 }
 
@@ -535,30 +574,6 @@ impl PlantGeoData {
 
         Some((mesh, self.aabb))
     }
-
-    // pub fn serialize_to_specify_file(&self, file_path: &dyn AsRef<Path>) -> bool {
-    //     let mut file = File::create(file_path).unwrap();
-    //     let serialized = rkyv::to_bytes::<_, 1024>(self).unwrap().to_vec();
-    //     file.write_all(serialized.as_slice()).unwrap();
-    //     true
-    // }
-    //
-    // pub fn deserialize_from_bin_file(file_path: &dyn AsRef<Path>) -> anyhow::Result<Self> {
-    //     let mut file = File::open(file_path)?;
-    //     let mut buf: Vec<u8> = Vec::new();
-    //     file.read_to_end(&mut buf).ok();
-    //     use rkyv::Deserialize;
-    //     let archived = unsafe { rkyv::archived_root::<Self>(buf.as_slice()) };
-    //     let r: Self = archived.deserialize(&mut rkyv::Infallible)?;
-    //     Ok(r)
-    // }
-    //
-    // pub fn deserialize_from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
-    //     use rkyv::Deserialize;
-    //     let archived = unsafe { rkyv::archived_root::<Self>(bytes) };
-    //     let r: Self = archived.deserialize(&mut rkyv::Infallible)?;
-    //     Ok(r)
-    // }
 }
 
 #[serde_as]
@@ -578,8 +593,13 @@ pub struct EleInstGeosData {
     pub refno: RefnoEnum,
     pub insts: Vec<EleInstGeo>,
 
+    #[rkyv(with = rkyv::with::Map<archive::AabbAsArray>)]
     pub aabb: Option<Aabb>,
     pub type_name: String,
+
+    /// 各 geo_hash 已生成的 LOD（见 [`lod`] 模块）
+    #[serde(default)]
+    pub lods: Vec<lod::GeoLodInfo>,
 }
 
 impl EleInstGeosData {
@@ -647,9 +667,11 @@ pub struct EleInstGeo {
     #[serde(default)]
     pub geo_param: PdmsGeoParam,
     pub pts: Vec<i32>,
+    #[rkyv(with = rkyv::with::Map<archive::AabbAsArray>)]
     pub aabb: Option<Aabb>,
     //相对于自身的坐标系变换
     #[serde(default)]
+    #[rkyv(with = archive::TransformAsArray)]
     pub transform: Transform,
     pub visible: bool,
     pub is_tubi: bool,
@@ -666,6 +688,12 @@ pub struct EleInstGeo {
 }
 
 impl EleInstGeo {
+    /// 导出 OBJ/STL 等调试文件时使用的名称
+    #[inline]
+    pub fn mesh_name(&self) -> String {
+        format!("{}_{}", self.geo_type, self.geo_hash)
+    }
+
     #[inline]
     pub fn is_cata_neg(&self) -> bool {
         self.geo_type == GeoBasicType::CataNeg
@@ -690,8 +718,8 @@ impl EleInstGeo {
         let mut json_string = "".to_string();
         let param = self.geo_param.convert_to_unit_param();
         json_string.push_str(&format!(
-            "{{'id': inst_geo:⟨{}⟩, 'param': {}, 'unit_flag': {} }}",
-            self.geo_hash,
+            "{{'id': {}, 'param': {}, 'unit_flag': {} }}",
+            InstGeoKey::new(self.geo_hash.to_string()),
             /* gen_bytes_hash::<_, 64>(&self.aabb),*/
             serde_json::to_string(&param).unwrap(),
             self.unit_flag
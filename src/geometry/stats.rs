@@ -0,0 +1,363 @@
+//! 几何实例化统计
+//!
+//! 统计 [`ShapeInstancesData`] 中每个 geo_hash 的实例数、三角面数与内存占用，
+//! 列出渲染代价最高的元件库条目，并按 noun 汇总，用于评估 web 端流式加载的优化方向。
+//!
+//! 实例数据本身不含 mesh，三角面数通过 [`analyze_with`] 的回调提供（例如读取 mesh 文件），
+//! [`analyze`] 只能从已生成的 LOD 信息中估计。负实体不参与渲染，不计入统计。
+//!
+//! ```rust,ignore
+//! let report = stats::analyze_with(&shape_insts, 20, |geo_hash| {
+//!     load_mesh_sync(geo_hash).map(|m| MeshStats::of(&m))
+//! });
+//! std::fs::write("geo_stats.json", report.to_json()?)?;
+//! std::fs::write("geo_stats.csv", report.geo_csv())?;
+//! ```
+
+use super::{EleInstGeo, GeoBasicType, ShapeInstancesData};
+use crate::shape::pdms_shape::PlantMesh;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// web 端每个实例的传输大小：4x4 f32 矩阵 + geo_hash
+pub const INSTANCE_BYTES: u64 = 64 + 8;
+
+/// 单个 mesh 的规模
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MeshStats {
+    pub triangles: u64,
+    pub vertices: u64,
+    /// 顶点（位置 + 法线 + UV）与索引的字节数
+    pub bytes: u64,
+}
+
+impl MeshStats {
+    pub fn of(mesh: &PlantMesh) -> Self {
+        let vertices = mesh.vertices.len() as u64;
+        Self {
+            triangles: mesh.indices.len() as u64 / 3,
+            vertices,
+            bytes: vertices * 24 + mesh.uvs.len() as u64 * 8 + mesh.indices.len() as u64 * 4,
+        }
+    }
+}
+
+/// 单个 geo_hash 的统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeoHashStats {
+    pub geo_hash: u64,
+    /// 被实例化的次数
+    pub instances: u64,
+    /// mesh 规模，未知时为 None
+    pub mesh: Option<MeshStats>,
+    /// 全部实例的三角面数
+    pub instanced_triangles: u64,
+}
+
+/// 单个元件库条目（inst_geos）的统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogueStats {
+    pub inst_key: String,
+    pub noun: String,
+    /// 引用该条目的元素数
+    pub elements: u64,
+    /// 每个元素的几何体数
+    pub geos_per_element: u64,
+    /// 每个元素的三角面数
+    pub triangles_per_element: u64,
+    /// 全部元素的三角面数
+    pub total_triangles: u64,
+}
+
+/// 按 noun 汇总的统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NounStats {
+    pub noun: String,
+    pub elements: u64,
+    pub instances: u64,
+    pub triangles: u64,
+    /// 实例数据的传输字节数
+    pub instance_bytes: u64,
+}
+
+/// 统计报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeometryStatsReport {
+    /// 元素数（含 tubi）
+    pub elements: u64,
+    /// 几何实例总数
+    pub instances: u64,
+    /// 不同 geo_hash 数
+    pub unique_geos: u64,
+    /// mesh 规模未知的 geo_hash 数
+    pub unknown_meshes: u64,
+    /// 去重后的 mesh 三角面数
+    pub unique_triangles: u64,
+    /// 按实例展开后的三角面数
+    pub instanced_triangles: u64,
+    /// 去重后的 mesh 字节数
+    pub mesh_bytes: u64,
+    /// 实例数据的字节数
+    pub instance_bytes: u64,
+    /// 按实例展开后的三角面数从高到低
+    pub geo_hashes: Vec<GeoHashStats>,
+    /// 渲染代价最高的元件库条目
+    pub top_catalogues: Vec<CatalogueStats>,
+    /// 按三角面数从高到低
+    pub nouns: Vec<NounStats>,
+}
+
+fn is_rendered(geo: &EleInstGeo) -> bool {
+    !matches!(
+        geo.geo_type,
+        GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+    )
+}
+
+/// 只用 LOD 信息估计三角面数（取最精细的 LOD），统计 `top_n` 个元件库条目
+pub fn analyze(data: &ShapeInstancesData, top_n: usize) -> GeometryStatsReport {
+    let mut lod_triangles: HashMap<u64, u64> = HashMap::new();
+    for geos in data.inst_geos_map.values() {
+        for lod in &geos.lods {
+            let t = lod_triangles.entry(lod.geo_hash).or_default();
+            *t = (*t).max(lod.triangles as u64);
+        }
+    }
+    analyze_with(data, top_n, |geo_hash| {
+        lod_triangles.get(&geo_hash).map(|&triangles| MeshStats {
+            triangles,
+            ..Default::default()
+        })
+    })
+}
+
+/// `mesh_stats` 提供每个 geo_hash 的 mesh 规模，每个 geo_hash 只调用一次
+pub fn analyze_with(
+    data: &ShapeInstancesData,
+    top_n: usize,
+    mut mesh_stats: impl FnMut(u64) -> Option<MeshStats>,
+) -> GeometryStatsReport {
+    let mut report = GeometryStatsReport::default();
+
+    // 每个 inst_geos 条目被多少个元素引用
+    let mut refs: HashMap<&str, u64> = HashMap::new();
+    for info in data
+        .inst_info_map
+        .values()
+        .chain(data.inst_tubi_map.values())
+    {
+        report.elements += 1;
+        let key = info.get_inst_key();
+        if let Some((k, _)) = data.inst_geos_map.get_key_value(&key) {
+            *refs.entry(k.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut geos: HashMap<u64, GeoHashStats> = HashMap::new();
+    let mut nouns: HashMap<String, NounStats> = HashMap::new();
+    let mut catalogues = vec![];
+    for (key, geos_data) in &data.inst_geos_map {
+        let elements = refs.get(key.as_str()).copied().unwrap_or_default();
+        if elements == 0 {
+            continue;
+        }
+        let rendered = geos_data.insts.iter().filter(|g| is_rendered(g));
+        let mut per_element = 0;
+        let mut count = 0;
+        for geo in rendered {
+            count += 1;
+            let stats = geos.entry(geo.geo_hash).or_insert_with(|| GeoHashStats {
+                geo_hash: geo.geo_hash,
+                mesh: mesh_stats(geo.geo_hash),
+                ..Default::default()
+            });
+            stats.instances += elements;
+            per_element += stats.mesh.map_or(0, |m| m.triangles);
+        }
+
+        let noun = if geos_data.type_name.is_empty() {
+            "UNKNOWN".to_string()
+        } else {
+            geos_data.type_name.clone()
+        };
+        let n = nouns.entry(noun.clone()).or_insert_with(|| NounStats {
+            noun: noun.clone(),
+            ..Default::default()
+        });
+        n.elements += elements;
+        n.instances += count * elements;
+        n.triangles += per_element * elements;
+        n.instance_bytes += count * elements * INSTANCE_BYTES;
+
+        catalogues.push(CatalogueStats {
+            inst_key: key.clone(),
+            noun,
+            elements,
+            geos_per_element: count,
+            triangles_per_element: per_element,
+            total_triangles: per_element * elements,
+        });
+    }
+
+    for g in geos.values_mut() {
+        let mesh = g.mesh.unwrap_or_default();
+        g.instanced_triangles = mesh.triangles * g.instances;
+        report.instances += g.instances;
+        report.unique_triangles += mesh.triangles;
+        report.instanced_triangles += g.instanced_triangles;
+        report.mesh_bytes += mesh.bytes;
+        if g.mesh.is_none() {
+            report.unknown_meshes += 1;
+        }
+    }
+    report.unique_geos = geos.len() as u64;
+    report.instance_bytes = report.instances * INSTANCE_BYTES;
+
+    report.geo_hashes = geos.into_values().collect();
+    report.geo_hashes.sort_by(|a, b| {
+        b.instanced_triangles
+            .cmp(&a.instanced_triangles)
+            .then(b.instances.cmp(&a.instances))
+            .then(a.geo_hash.cmp(&b.geo_hash))
+    });
+    catalogues.sort_by(|a, b| {
+        b.total_triangles
+            .cmp(&a.total_triangles)
+            .then(b.elements.cmp(&a.elements))
+            .then(a.inst_key.cmp(&b.inst_key))
+    });
+    catalogues.truncate(top_n);
+    report.top_catalogues = catalogues;
+    report.nouns = nouns.into_values().collect();
+    report
+        .nouns
+        .sort_by(|a, b| b.triangles.cmp(&a.triangles).then(a.noun.cmp(&b.noun)));
+    report
+}
+
+impl GeometryStatsReport {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 每个 geo_hash 一行
+    pub fn geo_csv(&self) -> String {
+        let mut out =
+            String::from("geo_hash,instances,triangles,vertices,mesh_bytes,instanced_triangles\n");
+        for g in &self.geo_hashes {
+            let (triangles, vertices, bytes) = match g.mesh {
+                Some(m) => (
+                    m.triangles.to_string(),
+                    m.vertices.to_string(),
+                    m.bytes.to_string(),
+                ),
+                None => Default::default(),
+            };
+            out.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                g.geo_hash, g.instances, triangles, vertices, bytes, g.instanced_triangles
+            ));
+        }
+        out
+    }
+
+    /// 元件库条目，每个一行
+    pub fn catalogue_csv(&self) -> String {
+        let mut out = String::from(
+            "inst_key,noun,elements,geos_per_element,triangles_per_element,total_triangles\n",
+        );
+        for c in &self.top_catalogues {
+            out.push_str(&format!(
+                "\"{}\",{},{},{},{},{}\n",
+                c.inst_key.replace('"', "\"\""),
+                c.noun,
+                c.elements,
+                c.geos_per_element,
+                c.triangles_per_element,
+                c.total_triangles
+            ));
+        }
+        out
+    }
+
+    /// 按 noun 汇总，每个一行
+    pub fn noun_csv(&self) -> String {
+        let mut out = String::from("noun,elements,instances,triangles,instance_bytes\n");
+        for n in &self.nouns {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                n.noun, n.elements, n.instances, n.triangles, n.instance_bytes
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{EleGeosInfo, EleInstGeosData};
+    use crate::{RefU64, RefnoEnum};
+
+    fn geos(key: &str, noun: &str, hashes: &[u64]) -> EleInstGeosData {
+        EleInstGeosData {
+            inst_key: key.to_string(),
+            type_name: noun.to_string(),
+            insts: hashes
+                .iter()
+                .map(|&geo_hash| EleInstGeo {
+                    geo_hash,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn info(n: u32, cata_hash: &str) -> EleGeosInfo {
+        let refno: RefnoEnum = RefU64::from_two_nums(17496, n).into();
+        EleGeosInfo {
+            refno,
+            cata_hash: Some(cata_hash.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_analyze_with() {
+        let mut data = ShapeInstancesData::default();
+        data.insert_geos_data("100".into(), geos("100", "ELBO", &[10, 20]));
+        data.insert_geos_data("200".into(), geos("200", "VALV", &[20]));
+        let mut neg = geos("300", "VALV", &[30]);
+        neg.insts[0].geo_type = GeoBasicType::CataNeg;
+        data.insert_geos_data("300".into(), neg);
+        for n in 1..=3 {
+            data.insert_info(RefU64::from_two_nums(17496, n).into(), info(n, "100"));
+        }
+        data.insert_info(RefU64::from_two_nums(17496, 9).into(), info(9, "200"));
+        data.insert_info(RefU64::from_two_nums(17496, 10).into(), info(10, "300"));
+
+        let report = analyze_with(&data, 1, |h| {
+            (h != 20).then_some(MeshStats {
+                triangles: h,
+                vertices: h * 3,
+                bytes: h * 100,
+            })
+        });
+        assert_eq!(report.elements, 5);
+        assert_eq!(report.unique_geos, 2);
+        assert_eq!(report.unknown_meshes, 1);
+        assert_eq!(report.instances, 3 + 3 + 1);
+        assert_eq!(report.instanced_triangles, 30);
+        assert_eq!(report.geo_hashes[0].geo_hash, 10);
+        assert_eq!(report.geo_hashes[1].instances, 4);
+        assert_eq!(report.top_catalogues.len(), 1);
+        assert_eq!(report.top_catalogues[0].inst_key, "100");
+        assert_eq!(report.nouns[0].noun, "ELBO");
+        assert_eq!(report.nouns[0].instance_bytes, 6 * INSTANCE_BYTES);
+
+        assert_eq!(report.geo_csv().lines().count(), 3);
+        assert!(report.noun_csv().contains("VALV,2,1,0,"));
+        assert!(report.to_json().unwrap().contains("\"top_catalogues\""));
+    }
+}
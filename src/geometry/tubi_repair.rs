@@ -0,0 +1,281 @@
+//! 直管段（tubi）间隙/重叠分析与修复建议
+//!
+//! 相邻元件之间由 leave 点与下一元件的 arrive 点决定隐含直段。生成 tubi 时常暴露出：
+//! - 重叠：下一元件的 arrive 点落在当前元件 leave 点之前
+//! - 间隙：两点间距大于容差却不足以形成有效直段
+//! - 错位：两端口方向夹角或横向偏移超出容差
+//!
+//! 本模块对每个缺陷分类并给出沿轴向的位移建议，结果可导出为设计人员工作清单，
+//! 也可通过 [`TubiRepairWorklist::preview_offsets`] 提供给 what-if 预览叠加层使用。
+
+use crate::RefnoEnum;
+use crate::parsed_data::CateAxisParam;
+use crate::utils::refno_span::with_refno_span;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 缺陷判定容差
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TubiRepairTolerance {
+    /// 轴向距离容差（mm）
+    pub distance: f32,
+    /// 有效直段的最小长度（mm），小于该值的间隙视为缺陷
+    pub min_tube_length: f32,
+    /// 端口方向夹角容差（度）
+    pub angle_deg: f32,
+}
+
+impl Default for TubiRepairTolerance {
+    fn default() -> Self {
+        Self {
+            distance: 0.5,
+            min_tube_length: 10.0,
+            angle_deg: 0.5,
+        }
+    }
+}
+
+/// 缺陷类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, strum_macros::Display)]
+pub enum TubiDefectKind {
+    /// 间隙，值为轴向距离（mm）
+    Gap(f32),
+    /// 重叠，值为重叠长度（mm）
+    Overlap(f32),
+    /// 错位，分别为方向夹角（度）与横向偏移（mm）
+    Misalignment { angle_deg: f32, offset: f32 },
+}
+
+/// 单个缺陷及修复建议
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TubiDefect {
+    pub branch: RefnoEnum,
+    /// 上游元件（提供 leave 点）
+    pub prev: RefnoEnum,
+    /// 下游元件（提供 arrive 点），修复建议作用于该元件
+    pub next: RefnoEnum,
+    pub kind: TubiDefectKind,
+    /// 建议的世界坐标位移（mm）
+    pub suggested_offset: Vec3,
+}
+
+impl TubiDefect {
+    /// 可读的修复建议描述
+    pub fn suggestion(&self) -> String {
+        let len = self.suggested_offset.length();
+        match self.kind {
+            TubiDefectKind::Gap(_) | TubiDefectKind::Overlap(_) => format!(
+                "沿轴向移动 {} {:.1}mm (dx={:.1}, dy={:.1}, dz={:.1})",
+                self.next,
+                len,
+                self.suggested_offset.x,
+                self.suggested_offset.y,
+                self.suggested_offset.z
+            ),
+            TubiDefectKind::Misalignment { angle_deg, .. } => format!(
+                "校正 {} 方向 {:.2}°，并平移 {:.1}mm",
+                self.next, angle_deg, len
+            ),
+        }
+    }
+}
+
+/// 分析单个分支
+///
+/// `ports` 为按分支顺序排列的元件及其世界坐标下的 [arrive, leave] 端口。
+pub fn analyze_branch_ports(
+    branch: RefnoEnum,
+    ports: &[(RefnoEnum, [CateAxisParam; 2])],
+    tol: &TubiRepairTolerance,
+) -> Vec<TubiDefect> {
+    let mut defects = vec![];
+    for pair in ports.windows(2) {
+        let (prev, [_, leave]) = &pair[0];
+        let (next, [arrive, _]) = &pair[1];
+        let Some(axis) = leave.dir.as_ref().map(|d| d.0.normalize_or_zero()) else {
+            continue;
+        };
+        if axis == Vec3::ZERO {
+            continue;
+        }
+        let delta = arrive.pt.0 - leave.pt.0;
+        let along = delta.dot(axis);
+        let lateral = delta - axis * along;
+
+        // arrive 方向指向元件外侧，对齐时应与 leave 方向相反
+        let angle_deg = arrive
+            .dir
+            .as_ref()
+            .map(|d| {
+                let d = d.0.normalize_or_zero();
+                (-d).dot(axis).clamp(-1.0, 1.0).acos().to_degrees()
+            })
+            .unwrap_or(0.0);
+
+        if angle_deg > tol.angle_deg || lateral.length() > tol.distance {
+            defects.push(TubiDefect {
+                branch,
+                prev: *prev,
+                next: *next,
+                kind: TubiDefectKind::Misalignment {
+                    angle_deg,
+                    offset: lateral.length(),
+                },
+                suggested_offset: -lateral,
+            });
+        }
+        if along < -tol.distance {
+            defects.push(TubiDefect {
+                branch,
+                prev: *prev,
+                next: *next,
+                kind: TubiDefectKind::Overlap(-along),
+                suggested_offset: -axis * along,
+            });
+        } else if along > tol.distance && along < tol.min_tube_length {
+            defects.push(TubiDefect {
+                branch,
+                prev: *prev,
+                next: *next,
+                kind: TubiDefectKind::Gap(along),
+                suggested_offset: -axis * along,
+            });
+        }
+    }
+    defects
+}
+
+/// 查询分支的端口数据并分析
+pub async fn analyze_branch(
+    branch: RefnoEnum,
+    tol: &TubiRepairTolerance,
+) -> anyhow::Result<Vec<TubiDefect>> {
+    let children = crate::rs_surreal::get_children_refnos(branch).await?;
+    let port_map = crate::rs_surreal::point::query_arrive_leave_points_of_branch(branch).await?;
+    let ports = children
+        .into_iter()
+        .filter_map(|c| port_map.get(&c).map(|p| (c, p.value().clone())))
+        .collect::<Vec<_>>();
+    Ok(analyze_branch_ports(branch, &ports, tol))
+}
+
+/// 批量分析多个分支，生成工作清单
+pub async fn build_repair_worklist(
+    branches: &[RefnoEnum],
+    tol: &TubiRepairTolerance,
+) -> anyhow::Result<TubiRepairWorklist> {
+    let mut worklist = TubiRepairWorklist::default();
+    for &bran in branches {
+        worklist
+            .defects
+            .extend(with_refno_span(bran, analyze_branch(bran, tol)).await?);
+    }
+    Ok(worklist)
+}
+
+/// 修复工作清单
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TubiRepairWorklist {
+    pub defects: Vec<TubiDefect>,
+}
+
+impl TubiRepairWorklist {
+    /// 导出为 CSV 文本（分支, 上游, 下游, 类型, 数值, 建议）
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("branch,prev,next,kind,value,dx,dy,dz,suggestion\n");
+        for d in &self.defects {
+            let value = match d.kind {
+                TubiDefectKind::Gap(v) | TubiDefectKind::Overlap(v) => v,
+                TubiDefectKind::Misalignment { angle_deg, .. } => angle_deg,
+            };
+            let kind = match d.kind {
+                TubiDefectKind::Gap(_) => "Gap",
+                TubiDefectKind::Overlap(_) => "Overlap",
+                TubiDefectKind::Misalignment { .. } => "Misalignment",
+            };
+            out.push_str(&format!(
+                "{},{},{},{},{:.3},{:.3},{:.3},{:.3},\"{}\"\n",
+                d.branch,
+                d.prev,
+                d.next,
+                kind,
+                value,
+                d.suggested_offset.x,
+                d.suggested_offset.y,
+                d.suggested_offset.z,
+                d.suggestion().replace('"', "\"\"")
+            ));
+        }
+        out
+    }
+
+    /// 汇总每个元件的建议位移，供 what-if 叠加层预览
+    pub fn preview_offsets(&self) -> HashMap<RefnoEnum, Vec3> {
+        let mut map: HashMap<RefnoEnum, Vec3> = HashMap::new();
+        for d in &self.defects {
+            *map.entry(d.next).or_default() += d.suggested_offset;
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::shape::pdms_shape::RsVec3;
+
+    fn port(pt: Vec3, dir: Vec3) -> CateAxisParam {
+        CateAxisParam {
+            pt: RsVec3(pt),
+            dir: Some(RsVec3(dir)),
+            ..Default::default()
+        }
+    }
+
+    fn comp(n: u32, arrive: Vec3, leave: Vec3) -> (RefnoEnum, [CateAxisParam; 2]) {
+        (
+            RefU64::from_two_nums(1, n).into(),
+            [port(arrive, Vec3::NEG_X), port(leave, Vec3::X)],
+        )
+    }
+
+    #[test]
+    fn test_classify_gap_and_overlap() {
+        let bran: RefnoEnum = RefU64::from_two_nums(1, 100).into();
+        let ports = vec![
+            comp(1, Vec3::ZERO, Vec3::new(100.0, 0.0, 0.0)),
+            // 与上一个元件重叠 5mm
+            comp(2, Vec3::new(95.0, 0.0, 0.0), Vec3::new(200.0, 0.0, 0.0)),
+            // 2mm 的间隙，不足以形成直段
+            comp(3, Vec3::new(202.0, 0.0, 0.0), Vec3::new(300.0, 0.0, 0.0)),
+            // 500mm 的正常直段
+            comp(4, Vec3::new(800.0, 0.0, 0.0), Vec3::new(900.0, 0.0, 0.0)),
+        ];
+        let defects = analyze_branch_ports(bran, &ports, &TubiRepairTolerance::default());
+        assert_eq!(defects.len(), 2);
+        assert_eq!(defects[0].kind, TubiDefectKind::Overlap(5.0));
+        assert!((defects[0].suggested_offset - Vec3::new(5.0, 0.0, 0.0)).length() < 1e-4);
+        assert_eq!(defects[1].kind, TubiDefectKind::Gap(2.0));
+
+        let worklist = TubiRepairWorklist { defects };
+        assert_eq!(worklist.to_csv().lines().count(), 3);
+        assert_eq!(worklist.preview_offsets().len(), 2);
+    }
+
+    #[test]
+    fn test_classify_misalignment() {
+        let bran: RefnoEnum = RefU64::from_two_nums(1, 100).into();
+        let ports = vec![
+            comp(1, Vec3::ZERO, Vec3::new(100.0, 0.0, 0.0)),
+            comp(2, Vec3::new(300.0, 3.0, 0.0), Vec3::new(400.0, 3.0, 0.0)),
+        ];
+        let defects = analyze_branch_ports(bran, &ports, &TubiRepairTolerance::default());
+        assert_eq!(defects.len(), 1);
+        assert!(matches!(
+            defects[0].kind,
+            TubiDefectKind::Misalignment { offset, .. } if (offset - 3.0).abs() < 1e-4
+        ));
+    }
+}
@@ -0,0 +1,504 @@
+//! 数据库浮点数解码
+//!
+//! 不同版本的 PDMS/E3D 数据库对 8 字节双精度的存储方式不同：
+//! PDMS 的库按大端存储但高低两个 4 字节字互换，E3D 的库按自然字序存储，
+//! 个别平台导出的库还会是小端。按固定的字互换解码其他版本的库会得到看似正常的垃圾值。
+//!
+//! 这里根据库文件头确定字节序和字序（[`FloatLayout`]），解析代码可以直接持有 [`FloatDecoder`]
+//! 显式解码；为了让 [`super::parse_to_f64`] 等旧接口也使用该文件的布局，用 [`scope_db_file`] 或
+//! [`FloatDecoder::scope`] 包住解析该文件的 future，解码器保存在 tokio 的 task-local 中，
+//! 随任务在线程间迁移，不会串到同一线程上的其他任务。task-local 不会传到 `spawn_blocking`
+//! 的线程中，阻塞线程里的解析用 [`FloatDecoder::sync_scope`] 包住（见 [`db_file_decoder`]）。
+//!
+//! 文件头的识别依据 `data/` 下随仓库提供的 PDMS 库文件（desvir.dat、padvir.dat 等）：
+//! 前两个字按大端为 `[6, 2]`，浮点按大端高低字互换存储。目前没有 E3D 等其他版本的库文件样本，
+//! 这些库的文件头不会被识别，需要在配置中用 `layout`、`file_layouts` 或 `fallback` 指定布局。
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+
+/// 文件头第一个字，`data/` 下的各个 PDMS 库文件均为 6
+pub const DB_HEADER_MAGIC: u32 = 6;
+
+/// 文件头中格式版本字所在的字节偏移，紧跟在 [`DB_HEADER_MAGIC`] 之后
+pub const DB_HEADER_VERSION_OFFSET: usize = 4;
+
+/// 识别文件头需要读取的字节数
+pub const DB_HEADER_LEN: usize = DB_HEADER_VERSION_OFFSET + 4;
+
+/// 双精度浮点数的存储布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FloatLayout {
+    /// 大端，高低字互换（PDMS 11/12）
+    #[default]
+    BeWordSwapped,
+    /// 大端，自然字序（E3D）
+    Be,
+    /// 小端，自然字序
+    Le,
+    /// 小端，高低字互换
+    LeWordSwapped,
+}
+
+impl FloatLayout {
+    pub const ALL: [FloatLayout; 4] = [
+        FloatLayout::BeWordSwapped,
+        FloatLayout::Be,
+        FloatLayout::Le,
+        FloatLayout::LeWordSwapped,
+    ];
+
+    pub fn from_parts(byte_order: ByteOrder, word_swapped: bool) -> Self {
+        match (byte_order, word_swapped) {
+            (ByteOrder::Big, true) => FloatLayout::BeWordSwapped,
+            (ByteOrder::Big, false) => FloatLayout::Be,
+            (ByteOrder::Little, false) => FloatLayout::Le,
+            (ByteOrder::Little, true) => FloatLayout::LeWordSwapped,
+        }
+    }
+
+    #[inline]
+    pub fn decode(self, bytes: [u8; 8]) -> f64 {
+        let [a, b, c, d, e, f, g, h] = bytes;
+        match self {
+            FloatLayout::BeWordSwapped => f64::from_be_bytes([e, f, g, h, a, b, c, d]),
+            FloatLayout::Be => f64::from_be_bytes(bytes),
+            FloatLayout::Le => f64::from_le_bytes(bytes),
+            FloatLayout::LeWordSwapped => f64::from_le_bytes([e, f, g, h, a, b, c, d]),
+        }
+    }
+
+    #[inline]
+    pub fn encode(self, value: f64) -> [u8; 8] {
+        let swap = |[a, b, c, d, e, f, g, h]: [u8; 8]| [e, f, g, h, a, b, c, d];
+        match self {
+            FloatLayout::BeWordSwapped => swap(value.to_be_bytes()),
+            FloatLayout::Be => value.to_be_bytes(),
+            FloatLayout::Le => value.to_le_bytes(),
+            FloatLayout::LeWordSwapped => swap(value.to_le_bytes()),
+        }
+    }
+}
+
+impl std::str::FromStr for FloatLayout {
+    type Err = FloatDecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "be-word-swapped" | "pdms" => Ok(FloatLayout::BeWordSwapped),
+            "be" | "e3d" => Ok(FloatLayout::Be),
+            "le" => Ok(FloatLayout::Le),
+            "le-word-swapped" => Ok(FloatLayout::LeWordSwapped),
+            _ => Err(FloatDecodeError::UnknownLayoutName(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ByteOrder {
+    Big,
+    Little,
+}
+
+/// 浮点解码错误
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum FloatDecodeError {
+    #[error("数据库文件头长度不足: {len} 字节")]
+    HeaderTooShort { len: usize },
+    #[error("无法识别的数据库文件头: {raw:#010x}")]
+    UnknownHeader { raw: u32 },
+    #[error("无法识别的数据库格式版本字: {raw:#010x}")]
+    UnknownVersion { raw: u32 },
+    #[error("数据库格式版本 {version}（{byte_order:?}）的浮点布局不受支持")]
+    UnsupportedLayout { version: u32, byte_order: ByteOrder },
+    #[error("无效的浮点布局名称: {0}")]
+    UnknownLayoutName(String),
+    #[error("浮点数据长度不足 8 字节: {0}")]
+    InvalidLength(usize),
+    #[error("解码结果不是有限值: {hex}（布局 {layout:?}）")]
+    NonFinite { hex: String, layout: FloatLayout },
+}
+
+/// 已知的格式版本字及其字序：(版本字, 高低字是否互换, 说明)
+///
+/// 只收录有样本文件可以核对的版本
+const KNOWN_FORMATS: &[(u32, bool, &str)] = &[(2, true, "PDMS")];
+
+fn known_format(version: u32) -> Option<(bool, &'static str)> {
+    KNOWN_FORMATS
+        .iter()
+        .find(|(v, _, _)| *v == version)
+        .map(|(_, swapped, name)| (*swapped, *name))
+}
+
+/// 从文件头解析出的格式信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbFloatHeader {
+    pub version: u32,
+    pub byte_order: ByteOrder,
+    pub layout: FloatLayout,
+}
+
+impl DbFloatHeader {
+    /// 解析文件头
+    ///
+    /// 首字按大端读出为 [`DB_HEADER_MAGIC`] 即为大端库，按小端读出为该值则为小端库，
+    /// 否则返回 [`FloatDecodeError::UnknownHeader`]；版本字不在 [`KNOWN_FORMATS`] 中时返回
+    /// [`FloatDecodeError::UnknownVersion`]
+    pub fn parse(header: &[u8]) -> Result<Self, FloatDecodeError> {
+        let word = |offset: usize| -> Result<[u8; 4], FloatDecodeError> {
+            header
+                .get(offset..offset + 4)
+                .and_then(|w| w.try_into().ok())
+                .ok_or(FloatDecodeError::HeaderTooShort { len: header.len() })
+        };
+        let magic = word(0)?;
+        let byte_order = if u32::from_be_bytes(magic) == DB_HEADER_MAGIC {
+            ByteOrder::Big
+        } else if u32::from_le_bytes(magic) == DB_HEADER_MAGIC {
+            ByteOrder::Little
+        } else {
+            return Err(FloatDecodeError::UnknownHeader {
+                raw: u32::from_be_bytes(magic),
+            });
+        };
+        let version_word = word(DB_HEADER_VERSION_OFFSET)?;
+        let version = match byte_order {
+            ByteOrder::Big => u32::from_be_bytes(version_word),
+            ByteOrder::Little => u32::from_le_bytes(version_word),
+        };
+        let (word_swapped, name) =
+            known_format(version).ok_or(FloatDecodeError::UnknownVersion { raw: version })?;
+        let layout = FloatLayout::from_parts(byte_order, word_swapped);
+        // 目前没有见过小端且高低字互换的库
+        if layout == FloatLayout::LeWordSwapped {
+            return Err(FloatDecodeError::UnsupportedLayout {
+                version,
+                byte_order,
+            });
+        }
+        log::debug!(
+            "数据库格式 {} (版本 {}), 浮点布局 {:?}",
+            name,
+            version,
+            layout
+        );
+        Ok(Self {
+            version,
+            byte_order,
+            layout,
+        })
+    }
+}
+
+/// 浮点解码配置，挂在 [`crate::options::DbOption`] 上供同步流程使用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FloatDecoderConfig {
+    /// 强制所有文件使用该布局，不再读取文件头
+    pub layout: Option<FloatLayout>,
+    /// 按文件名指定布局，优先级高于文件头
+    pub file_layouts: HashMap<String, FloatLayout>,
+    /// 文件头无法识别时的回退布局；为空时直接报错
+    pub fallback: Option<FloatLayout>,
+}
+
+impl FloatDecoderConfig {
+    /// 为某个数据库文件构造解码器
+    pub fn decoder_for(
+        &self,
+        file_name: &str,
+        header: &[u8],
+    ) -> Result<FloatDecoder, FloatDecodeError> {
+        if let Some(layout) = self.layout {
+            return Ok(FloatDecoder::new(layout));
+        }
+        if let Some(layout) = self.file_layouts.get(file_name) {
+            return Ok(FloatDecoder::new(*layout));
+        }
+        match DbFloatHeader::parse(header) {
+            Ok(h) => Ok(FloatDecoder::new(h.layout)),
+            Err(e) => match self.fallback {
+                Some(layout) => {
+                    log::warn!("{}: {}，使用回退布局 {:?}", file_name, e, layout);
+                    Ok(FloatDecoder::new(layout))
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_DECODER: FloatDecoder;
+}
+
+/// 按布局解码数据库中的浮点数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FloatDecoder {
+    pub layout: FloatLayout,
+}
+
+impl FloatDecoder {
+    /// 旧版本固定使用的布局
+    pub const LEGACY: FloatDecoder = FloatDecoder {
+        layout: FloatLayout::BeWordSwapped,
+    };
+
+    pub const fn new(layout: FloatLayout) -> Self {
+        Self { layout }
+    }
+
+    pub fn from_header(header: &[u8]) -> Result<Self, FloatDecodeError> {
+        Ok(Self::new(DbFloatHeader::parse(header)?.layout))
+    }
+
+    /// 解码 8 字节双精度，长度不足或结果不是有限值时报错
+    pub fn decode_f64(&self, input: &[u8]) -> Result<f64, FloatDecodeError> {
+        let bytes: [u8; 8] = input
+            .get(..8)
+            .and_then(|b| b.try_into().ok())
+            .ok_or(FloatDecodeError::InvalidLength(input.len()))?;
+        let value = self.layout.decode(bytes);
+        if !value.is_finite() {
+            return Err(FloatDecodeError::NonFinite {
+                hex: hex_string(&bytes),
+                layout: self.layout,
+            });
+        }
+        Ok(value)
+    }
+
+    /// 解码失败时返回 0.0，与旧的 `parse_to_f64` 行为一致
+    #[inline]
+    pub fn decode_f64_lossy(&self, input: &[u8]) -> f64 {
+        self.decode_f64(input).unwrap_or_default()
+    }
+
+    pub fn decode_f64_arr(&self, input: &[u8], num: usize) -> Result<Vec<f64>, FloatDecodeError> {
+        (0..num)
+            .map(|i| self.decode_f64(input.get(i * 8..).unwrap_or_default()))
+            .collect()
+    }
+
+    /// 在 `fut` 执行期间启用该解码器
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT_DECODER.scope(self, fut).await
+    }
+
+    /// 在 `f` 执行期间启用该解码器，用于同步的解析代码
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CURRENT_DECODER.sync_scope(self, f)
+    }
+}
+
+/// 按 [`DbOption::float_decoder`](crate::options::DbOption::float_decoder) 为数据库文件选择解码器；
+/// 未加载配置时按文件头判断
+pub fn decoder_for_file(file_name: &str, header: &[u8]) -> Result<FloatDecoder, FloatDecodeError> {
+    match crate::config::try_get_db_option() {
+        Ok(option) => option.float_decoder_for(file_name, header),
+        Err(_) => FloatDecoderConfig::default().decoder_for(file_name, header),
+    }
+}
+
+/// 读取 `path` 的文件头，按 [`decoder_for_file`] 选择解码器
+///
+/// 用于在阻塞线程中解析文件的场景：
+/// `db_file_decoder(path)?.sync_scope(|| parse(path))`
+pub fn db_file_decoder(path: &Path) -> anyhow::Result<FloatDecoder> {
+    let mut header = Vec::with_capacity(DB_HEADER_LEN);
+    std::fs::File::open(path)?
+        .take(DB_HEADER_LEN as u64)
+        .read_to_end(&mut header)?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    decoder_for_file(&file_name, &header)
+        .with_context(|| format!("无法确定 {} 的浮点布局", path.display()))
+}
+
+/// 为数据库文件选择解码器（见 [`decoder_for_file`]），并在 `fut` 执行期间启用
+pub async fn scope_db_file<F: Future>(
+    file_name: &str,
+    header: &[u8],
+    fut: F,
+) -> Result<F::Output, FloatDecodeError> {
+    let decoder = decoder_for_file(file_name, header)?;
+    Ok(decoder.scope(fut).await)
+}
+
+/// 当前任务使用的解码器，未设置时为 [`FloatDecoder::LEGACY`]
+#[inline]
+pub fn current_float_decoder() -> FloatDecoder {
+    CURRENT_DECODER
+        .try_with(|d| *d)
+        .unwrap_or(FloatDecoder::LEGACY)
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct FloatFixtureValue {
+        offset: usize,
+        hex: String,
+        value: f64,
+    }
+
+    #[derive(Deserialize)]
+    struct FloatFixture {
+        source: String,
+        header: String,
+        layout: FloatLayout,
+        values: Vec<FloatFixtureValue>,
+    }
+
+    fn parse_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn header_words(magic: [u8; 4], version: [u8; 4]) -> Vec<u8> {
+        [magic, version].concat()
+    }
+
+    fn fixtures() -> Vec<FloatFixture> {
+        serde_json::from_str(include_str!(
+            "../test/test-cases/float_decode/fixtures.json"
+        ))
+        .unwrap()
+    }
+
+    /// 样例摘自 data/ 下的 PDMS 库文件：文件头的前 8 字节，以及文件中已知偏移处的浮点原始字节
+    #[test]
+    fn test_fixture_corpus() {
+        for fixture in fixtures() {
+            let decoder = FloatDecoder::from_header(&parse_hex(&fixture.header)).unwrap();
+            assert_eq!(decoder.layout, fixture.layout, "{}", fixture.source);
+            for v in &fixture.values {
+                let value = decoder.decode_f64(&parse_hex(&v.hex)).unwrap();
+                assert_eq!(value, v.value, "{} @ {:#x}", fixture.source, v.offset);
+            }
+        }
+    }
+
+    /// 样例与仓库中的库文件逐字节一致，文件按实际文件头选出的解码器解码
+    #[test]
+    fn test_fixtures_match_db_files() {
+        for fixture in fixtures() {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(&fixture.source);
+            let bytes = std::fs::read(&path).unwrap();
+            assert_eq!(bytes[..DB_HEADER_LEN], parse_hex(&fixture.header)[..]);
+            let decoder = db_file_decoder(&path).unwrap();
+            assert_eq!(decoder.layout, fixture.layout);
+            for v in &fixture.values {
+                let raw = &bytes[v.offset..v.offset + 8];
+                assert_eq!(
+                    raw,
+                    &parse_hex(&v.hex)[..],
+                    "{} @ {:#x}",
+                    fixture.source,
+                    v.offset
+                );
+                assert_eq!(decoder.decode_f64(raw).unwrap(), v.value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        for layout in FloatLayout::ALL {
+            for v in [0.0, 1.0, -2.5, 1234.5678, 1e-3] {
+                assert_eq!(layout.decode(layout.encode(v)), v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_header() {
+        let header = header_words(0xdeadbeef_u32.to_be_bytes(), 2_u32.to_be_bytes());
+        assert!(matches!(
+            DbFloatHeader::parse(&header),
+            Err(FloatDecodeError::UnknownHeader { raw: 0xdeadbeef })
+        ));
+        assert!(matches!(
+            DbFloatHeader::parse(&[0u8; 4]),
+            Err(FloatDecodeError::HeaderTooShort { len: 4 })
+        ));
+        // attlib.dat 等非库文件的首字不是 DB_HEADER_MAGIC
+        let attlib =
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/data/attlib.dat")).unwrap();
+        assert!(matches!(
+            DbFloatHeader::parse(&attlib[..DB_HEADER_LEN]),
+            Err(FloatDecodeError::UnknownHeader { .. })
+        ));
+        // 没有样本的版本字不做猜测
+        let header = header_words(DB_HEADER_MAGIC.to_be_bytes(), 3_u32.to_be_bytes());
+        assert!(matches!(
+            DbFloatHeader::parse(&header),
+            Err(FloatDecodeError::UnknownVersion { raw: 3 })
+        ));
+        // 小端的 PDMS 文件头意味着小端 + 字互换，不支持
+        let header = header_words(DB_HEADER_MAGIC.to_le_bytes(), 2_u32.to_le_bytes());
+        assert!(matches!(
+            DbFloatHeader::parse(&header),
+            Err(FloatDecodeError::UnsupportedLayout { version: 2, .. })
+        ));
+
+        let config = FloatDecoderConfig {
+            fallback: Some(FloatLayout::Be),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.decoder_for("test", &[0u8; 4]).unwrap().layout,
+            FloatLayout::Be
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_task_decoder_scope() {
+        assert_eq!(current_float_decoder(), FloatDecoder::LEGACY);
+        let le = FloatDecoder::new(FloatLayout::Le).scope(async {
+            tokio::task::yield_now().await;
+            super::super::parse_to_f64(&2.5f64.to_le_bytes())
+        });
+        // 另一个任务不受影响
+        let legacy = tokio::spawn(async { current_float_decoder() });
+        assert_eq!(le.await, 2.5);
+        assert_eq!(legacy.await.unwrap(), FloatDecoder::LEGACY);
+        assert_eq!(current_float_decoder(), FloatDecoder::LEGACY);
+
+        let header = header_words(DB_HEADER_MAGIC.to_be_bytes(), 2_u32.to_be_bytes());
+        let layout = scope_db_file("desvir.dat", &header, async {
+            current_float_decoder().layout
+        })
+        .await
+        .unwrap();
+        assert_eq!(layout, FloatLayout::BeWordSwapped);
+
+        // task-local 不会传到阻塞线程，需要在线程内用 sync_scope 启用
+        let be = FloatDecoder::new(FloatLayout::Be);
+        let inherited = be
+            .scope(async { tokio::task::spawn_blocking(current_float_decoder).await })
+            .await
+            .unwrap();
+        assert_eq!(inherited, FloatDecoder::LEGACY);
+        let scoped = tokio::task::spawn_blocking(move || be.sync_scope(current_float_decoder))
+            .await
+            .unwrap();
+        assert_eq!(scoped, be);
+    }
+}
@@ -2,7 +2,9 @@ use crate::tool::db_tool::db1_dehash;
 use crate::tool::float_tool::{f32_round_3, f64_round_3};
 use smol_str::SmolStr;
 use std::borrow::Cow;
+pub mod float_decode;
 pub mod table;
+pub use float_decode::{FloatDecoder, FloatDecoderConfig, FloatLayout, current_float_decoder};
 pub use table::*;
 
 #[inline]
@@ -35,13 +37,10 @@ pub fn parse_to_f32(input: &[u8]) -> f32 {
     f32_round_3(f32::from_be_bytes(input.try_into().unwrap()))
 }
 
+/// 按当前线程的 [`FloatDecoder`] 解码，默认为 PDMS 的字互换布局
 #[inline]
 pub fn parse_to_f64(input: &[u8]) -> f64 {
-    return if let [a, b, c, d, e, f, g, h] = input[..8] {
-        f64_round_3(f64::from_be_bytes([e, f, g, h, a, b, c, d]))
-    } else {
-        0.0
-    };
+    f64_round_3(current_float_decoder().decode_f64_lossy(input))
 }
 
 #[inline]
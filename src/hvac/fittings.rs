@@ -0,0 +1,345 @@
+//! 风管管件（弯头、变径、三通）的展开长度与板材面积
+//!
+//! 直管段的面积由 surql 中的 `fn::fggd_*` 函数按 DESP 计算；
+//! 管件的形状不规则，这里直接按几何参数 `PdmsGeoParam` 求侧面积：
+//! - 矩形弯头 `RTorus`：两侧扇环 + 内外弧板
+//! - 圆形弯头 `CTorus`：按 Pappus 定理求环面侧面积
+//! - 圆变径 `LSnout`：圆台侧面积
+//! - 方变径 `Pyramid/LPyramid`：四个梯形侧板
+//! - 三通支管 `SBox/SCylinder/LCylinder`：直管侧面积
+//!
+//! 风管两端为开口，不计端面面积。长度单位 mm，面积单位 mm²。
+
+use crate::parsed_data::geo_params_data::PdmsGeoParam;
+use crate::rs_surreal::geometry_query::PlantTransform;
+use crate::types::RefnoEnum;
+use crate::{SUL_DB, SurrealQueryExt, get_inst_relate_keys};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 需要按几何计算展开面积的通风管件类型
+pub const HVAC_FITTING_NOUNS: [&str; 3] = ["BEND", "TRNS", "TEE"];
+
+/// 单个几何体的展开尺寸
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SheetSize {
+    /// 中心线展开长度 (mm)
+    pub length: f32,
+    /// 板材面积 (mm²)
+    pub area: f32,
+}
+
+impl SheetSize {
+    fn add(&mut self, other: SheetSize) {
+        self.length += other.length;
+        self.area += other.area;
+    }
+}
+
+/// 单个管件的展开结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FittingMaterial {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    /// 中心线展开长度 (mm)
+    pub length: f32,
+    /// 板材面积 (mm²)
+    pub area: f32,
+}
+
+impl FittingMaterial {
+    /// 面积换算为 m²
+    #[inline]
+    pub fn area_m2(&self) -> f32 {
+        self.area / 1_000_000.0
+    }
+}
+
+/// 按几何参数计算展开尺寸，`scale` 为几何到实例的缩放（单位化几何时携带真实尺寸）
+///
+/// 不支持的几何类型返回 None。
+pub fn sheet_size(param: &PdmsGeoParam, scale: Vec3) -> Option<SheetSize> {
+    match param {
+        PdmsGeoParam::PrimRTorus(t) => {
+            let rins = t.rins * scale.x;
+            let rout = t.rout * scale.x;
+            let height = t.height * scale.z;
+            let angle = t.angle.to_radians();
+            // 两侧扇环 + 内外弧板
+            let cheeks = angle * (rout * rout - rins * rins);
+            let wraps = angle * (rins + rout) * height;
+            Some(SheetSize {
+                length: angle * (rins + rout) / 2.0,
+                area: cheeks + wraps,
+            })
+        }
+        PdmsGeoParam::PrimCTorus(t) => {
+            let rins = t.rins * scale.x;
+            let rout = t.rout * scale.x;
+            let angle = t.angle.to_radians();
+            let length = angle * (rins + rout) / 2.0;
+            Some(SheetSize {
+                length,
+                area: PI * (rout - rins) * length,
+            })
+        }
+        PdmsGeoParam::PrimLSnout(s) => {
+            let height = (s.ptdi - s.pbdi).abs() * scale.z;
+            let rt = s.ptdm * scale.x / 2.0;
+            let rb = s.pbdm * scale.x / 2.0;
+            let off = s.poff * scale.x;
+            let slant = (height * height + (rt - rb).powi(2) + off * off).sqrt();
+            Some(SheetSize {
+                length: height,
+                area: PI * (rt + rb) * slant,
+            })
+        }
+        PdmsGeoParam::PrimPyramid(p) => Some(pyramid_sheet(
+            [p.pbtp, p.pctp, p.pbbt, p.pcbt],
+            (p.ptdi - p.pbdi).abs(),
+            [p.pbof, p.pcof],
+            scale,
+        )),
+        PdmsGeoParam::PrimLPyramid(p) => Some(pyramid_sheet(
+            [p.pbtp, p.pctp, p.pbbt, p.pcbt],
+            (p.ptdi - p.pbdi).abs(),
+            [p.pbof, p.pcof],
+            scale,
+        )),
+        PdmsGeoParam::PrimBox(b) => {
+            let size = b.size * scale;
+            Some(SheetSize {
+                length: size.z,
+                area: 2.0 * (size.x + size.y) * size.z,
+            })
+        }
+        PdmsGeoParam::PrimSCylinder(c) => {
+            let height = c.phei.abs() * scale.z;
+            Some(SheetSize {
+                length: height,
+                area: PI * c.pdia * scale.x * height,
+            })
+        }
+        PdmsGeoParam::PrimLCylinder(c) => {
+            let height = (c.ptdi - c.pbdi).abs() * scale.z;
+            Some(SheetSize {
+                length: height,
+                area: PI * c.pdia * scale.x * height,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 方变径：`dims` 为 [顶宽, 顶高, 底宽, 底高]，`offs` 为顶面在 B/C 方向的偏移
+fn pyramid_sheet(dims: [f32; 4], height: f32, offs: [f32; 2], scale: Vec3) -> SheetSize {
+    let [bt, ct, bb, cb] = [
+        dims[0] * scale.x,
+        dims[1] * scale.y,
+        dims[2] * scale.x,
+        dims[3] * scale.y,
+    ];
+    let height = height * scale.z;
+    let (bof, cof) = (offs[0] * scale.x, offs[1] * scale.y);
+    let trapezoid = |top: f32, bottom: f32, shift: f32| {
+        (top + bottom) / 2.0 * (height * height + shift * shift).sqrt()
+    };
+    // 垂直于 B 轴的两块侧板，斜高受 B 方向收缩与偏移影响
+    let db = (bb - bt) / 2.0;
+    let dc = (cb - ct) / 2.0;
+    let area = trapezoid(ct, cb, db - bof)
+        + trapezoid(ct, cb, db + bof)
+        + trapezoid(bt, bb, dc - cof)
+        + trapezoid(bt, bb, dc + cof);
+    SheetSize {
+        length: height,
+        area,
+    }
+}
+
+/// 将多个几何体的展开尺寸累加为一个管件的结果
+pub fn fitting_sheet<'a>(
+    geos: impl IntoIterator<Item = (&'a PdmsGeoParam, Vec3)>,
+) -> Option<SheetSize> {
+    let mut total: Option<SheetSize> = None;
+    for (param, scale) in geos {
+        if let Some(size) = sheet_size(param, scale) {
+            total.get_or_insert_default().add(size);
+        }
+    }
+    total
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, SurrealValue)]
+struct FittingGeo {
+    param: PdmsGeoParam,
+    trans: PlantTransform,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, SurrealValue)]
+struct FittingGeoRow {
+    refno: RefnoEnum,
+    noun: String,
+    geos: Vec<FittingGeo>,
+}
+
+/// 查询管件的几何参数并计算展开长度、面积
+///
+/// 只统计正实体（Pos/DesiPos/CatePos），没有几何参数的管件不返回。
+pub async fn fitting_materials(refnos: &[RefnoEnum]) -> anyhow::Result<Vec<FittingMaterial>> {
+    if refnos.is_empty() {
+        return Ok(vec![]);
+    }
+    let inst_keys = get_inst_relate_keys(refnos);
+    let sql = format!(
+        r#"select in as refno, in.noun as noun,
+        (select out.param as param, trans.d as trans from out->geo_relate
+            where out.param != none and trans.d != none and geo_type in ['Pos', 'DesiPos', 'CatePos'])
+        as geos from {inst_keys}"#
+    );
+    let rows: Vec<FittingGeoRow> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let size = fitting_sheet(row.geos.iter().map(|g| (&g.param, g.trans.scale)))?;
+            Some(FittingMaterial {
+                refno: row.refno,
+                noun: row.noun,
+                length: size.length,
+                area: size.area,
+            })
+        })
+        .collect())
+}
+
+/// 同 [`fitting_materials`]，但查询失败时不返回错误
+///
+/// 整批查询失败时逐个管件重试，仍然失败的管件记录警告后跳过，用于不应因个别管件中断的材料表统计。
+pub async fn fitting_materials_or_skip(refnos: &[RefnoEnum]) -> Vec<FittingMaterial> {
+    let e = match fitting_materials(refnos).await {
+        Ok(fittings) => return fittings,
+        Err(e) => e,
+    };
+    tracing::warn!("批量计算 {} 个管件展开尺寸失败，改为逐个计算: {:#}", refnos.len(), e);
+    let mut fittings = Vec::with_capacity(refnos.len());
+    for refno in refnos {
+        match fitting_materials(std::slice::from_ref(refno)).await {
+            Ok(mut r) => fittings.append(&mut r),
+            Err(e) => tracing::warn!("管件 {} 展开尺寸计算失败，已跳过: {:#}", refno, e),
+        }
+    }
+    fittings
+}
+
+/// 把管件展开结果合并进通风材料表的行（按 "参考号" 匹配）
+///
+/// 写入 "展开长度"(mm) 与 "展开面积"(m²)，未匹配到的管件追加为新行。
+pub fn merge_fitting_materials(
+    rows: &mut Vec<HashMap<String, String>>,
+    fittings: &[FittingMaterial],
+) {
+    let index = rows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| row.get("参考号").map(|id| (id.clone(), i)))
+        .collect::<HashMap<_, _>>();
+    for fitting in fittings {
+        let id = fitting.refno.refno().to_slash_string();
+        let row = match index.get(&id) {
+            Some(&i) => &mut rows[i],
+            None => {
+                rows.push(HashMap::from([
+                    ("参考号".to_string(), id),
+                    ("version_tag".to_string(), String::new()),
+                ]));
+                rows.last_mut().unwrap()
+            }
+        };
+        row.insert("展开长度".to_string(), format!("{:.2}", fitting.length));
+        row.insert("展开面积".to_string(), format!("{:.2}", fitting.area_m2()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prim_geo::{CTorus, LSnout, RTorus};
+
+    #[test]
+    fn test_round_bend_area() {
+        // 直径 200，中心线半径 300 的 90° 弯头
+        let bend = PdmsGeoParam::PrimCTorus(CTorus {
+            rins: 200.0,
+            rout: 400.0,
+            angle: 90.0,
+        });
+        let size = sheet_size(&bend, Vec3::ONE).unwrap();
+        let length = PI / 2.0 * 300.0;
+        assert!((size.length - length).abs() < 1e-2);
+        assert!((size.area - PI * 200.0 * length).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_unit_rect_bend_matches_scaled() {
+        let full = PdmsGeoParam::PrimRTorus(RTorus {
+            rins: 200.0,
+            rout: 500.0,
+            height: 400.0,
+            angle: 90.0,
+        });
+        let unit = PdmsGeoParam::PrimRTorus(RTorus {
+            rins: 0.4,
+            rout: 1.0,
+            height: 1.0,
+            angle: 90.0,
+        });
+        let a = sheet_size(&full, Vec3::ONE).unwrap();
+        let b = sheet_size(&unit, Vec3::new(500.0, 500.0, 400.0)).unwrap();
+        assert!((a.area - b.area).abs() < 1.0);
+        assert!((a.length - b.length).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_concentric_snout_is_cylinder_when_equal() {
+        let snout = PdmsGeoParam::PrimLSnout(LSnout {
+            ptdi: 300.0,
+            pbdi: 0.0,
+            ptdm: 200.0,
+            pbdm: 200.0,
+            ..Default::default()
+        });
+        let size = sheet_size(&snout, Vec3::ONE).unwrap();
+        assert!((size.area - PI * 200.0 * 300.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_merge_rows() {
+        let refno: RefnoEnum = crate::RefU64::from_two_nums(24381, 57305).into();
+        let mut rows = vec![HashMap::from([(
+            "参考号".to_string(),
+            "24381/57305".to_string(),
+        )])];
+        let fittings = [
+            FittingMaterial {
+                refno,
+                noun: "BEND".into(),
+                length: 471.24,
+                area: 2_000_000.0,
+            },
+            FittingMaterial {
+                refno: crate::RefU64::from_two_nums(24381, 1).into(),
+                noun: "TEE".into(),
+                length: 100.0,
+                area: 500_000.0,
+            },
+        ];
+        merge_fitting_materials(&mut rows, &fittings);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["展开面积"], "2.00");
+        assert_eq!(rows[1]["参考号"], "24381/1");
+    }
+}
@@ -0,0 +1,8 @@
+//! 通风专业功能
+
+pub mod fittings;
+
+pub use fittings::{
+    FittingMaterial, HVAC_FITTING_NOUNS, SheetSize, fitting_materials, fitting_materials_or_skip,
+    merge_fitting_materials, sheet_size,
+};
@@ -0,0 +1,88 @@
+//! PDMS 类型到 IFC 实体的映射
+
+/// 导出使用的 IFC4 实体
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IfcEntityKind {
+    Site,
+    Building,
+    ElementAssembly,
+    FlowSegment,
+    FlowFitting,
+    FlowController,
+    FlowTerminal,
+    Beam,
+    Column,
+    Member,
+    Plate,
+    Slab,
+    Wall,
+    BuildingElementProxy,
+}
+
+impl IfcEntityKind {
+    /// 按 PDMS 类型映射，未列出的类型导出为 `IfcBuildingElementProxy`
+    pub fn from_noun(noun: &str) -> Self {
+        match noun.to_ascii_uppercase().as_str() {
+            "SITE" => Self::Site,
+            "ZONE" => Self::Building,
+            "EQUI" | "SUBE" | "PIPE" | "BRAN" | "STRU" | "FRMW" | "SBFR" | "HVAC" | "REST"
+            | "HANG" => Self::ElementAssembly,
+            "TUBI" | "STRT" => Self::FlowSegment,
+            "ELBO" | "BEND" | "TEE" | "CROS" | "REDU" | "FLAN" | "FBLI" | "GASK" | "CAP"
+            | "COUP" | "UNIO" | "OLET" | "NOZZ" | "WELD" => Self::FlowFitting,
+            "VALV" | "VTWA" | "VFWA" | "INST" | "PCOM" | "FILT" | "TRAP" => Self::FlowController,
+            "VENT" | "DRAI" => Self::FlowTerminal,
+            "SCTN" | "GENSEC" => Self::Member,
+            "COLU" => Self::Column,
+            "BEAM" => Self::Beam,
+            "PANE" | "PLAT" => Self::Plate,
+            "FLOOR" | "CFLOOR" => Self::Slab,
+            "WALL" | "STWALL" | "CWALL" | "GWALL" => Self::Wall,
+            _ => Self::BuildingElementProxy,
+        }
+    }
+
+    /// SPF 中的实体名
+    pub fn entity_name(&self) -> &'static str {
+        match self {
+            Self::Site => "IFCSITE",
+            Self::Building => "IFCBUILDING",
+            Self::ElementAssembly => "IFCELEMENTASSEMBLY",
+            Self::FlowSegment => "IFCFLOWSEGMENT",
+            Self::FlowFitting => "IFCFLOWFITTING",
+            Self::FlowController => "IFCFLOWCONTROLLER",
+            Self::FlowTerminal => "IFCFLOWTERMINAL",
+            Self::Beam => "IFCBEAM",
+            Self::Column => "IFCCOLUMN",
+            Self::Member => "IFCMEMBER",
+            Self::Plate => "IFCPLATE",
+            Self::Slab => "IFCSLAB",
+            Self::Wall => "IFCWALL",
+            Self::BuildingElementProxy => "IFCBUILDINGELEMENTPROXY",
+        }
+    }
+
+    /// 空间结构元素（IfcSite / IfcBuilding），其余为构件
+    pub fn is_spatial(&self) -> bool {
+        matches!(self, Self::Site | Self::Building)
+    }
+
+    /// 在 `GlobalId, OwnerHistory, Name, Description, ObjectType, ObjectPlacement, Representation`
+    /// 之后还需要补齐的属性
+    pub(crate) fn trailing_attributes(&self) -> &'static str {
+        match self {
+            // LongName, CompositionType, RefLatitude, RefLongitude, RefElevation, LandTitleNumber, SiteAddress
+            Self::Site => "$,.ELEMENT.,$,$,$,$,$",
+            // LongName, CompositionType, ElevationOfRefHeight, ElevationOfTerrain, BuildingAddress
+            Self::Building => "$,.ELEMENT.,$,$,$",
+            // Tag, AssemblyPlace, PredefinedType
+            Self::ElementAssembly => "$,$,.NOTDEFINED.",
+            // Tag
+            Self::FlowSegment | Self::FlowFitting | Self::FlowController | Self::FlowTerminal => {
+                "$"
+            }
+            // Tag, PredefinedType
+            _ => "$,.NOTDEFINED.",
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! IFC 导出
+//!
+//! [`mapping`] 定义 PDMS 类型到 IFC4 实体的映射，[`writer`] 把元素层级、
+//! 三角化几何和属性集写成 IFC4 SPF 文件，供 BIM 软件读取。
+
+pub mod mapping;
+pub mod writer;
+
+pub use mapping::IfcEntityKind;
+pub use writer::{IfcExportOptions, IfcExportStats, build_ifc, export_ifc, query_ifc_attributes};
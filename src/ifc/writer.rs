@@ -0,0 +1,669 @@
+//! IFC4 SPF（STEP 物理文件）写出
+//!
+//! 结构：`IfcProject` 聚合 `IfcSite`，SITE 聚合 ZONE（`IfcBuilding`），
+//! ZONE 下的构件通过 `IfcRelContainedInSpatialStructure` 归属，构件之间用 `IfcRelAggregates` 表达层级。
+//! 不在任何 SITE / ZONE 之下的元素归入自动生成的默认场地和建筑。
+//!
+//! 几何按 `geo_hash` 写成 `IfcRepresentationMap`（`IfcTriangulatedFaceSet`），
+//! 实例通过 `IfcMappedItem` 引用，世界变换写入非均匀变换算子，所有构件使用同一个世界坐标放置。
+//! 长度单位为毫米。
+
+use super::mapping::IfcEntityKind;
+use crate::color_scheme::ColorSchemeManager;
+use crate::geometry::{EleGeosInfo, EleInstGeo, GeoBasicType, ShapeInstancesData};
+use crate::shape::pdms_shape::PlantMesh;
+use crate::types::named_attmap::NamedAttrMap;
+use crate::types::named_attvalue::NamedAttrValue;
+use crate::{RefnoEnum, get_named_attmap};
+use anyhow::Context;
+use bevy_transform::components::Transform;
+use glam::Vec3;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+const GUID_CHARS: &[u8; 64] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_$";
+
+/// 导出选项
+#[derive(Debug, Clone)]
+pub struct IfcExportOptions {
+    pub project_name: String,
+    pub author: String,
+    pub organization: String,
+    /// 元素属性，用于层级、名称和属性集（可用 [`query_ifc_attributes`] 查询）
+    pub attributes: HashMap<RefnoEnum, NamedAttrMap>,
+    /// 属性集名称
+    pub property_set_name: String,
+    /// 是否导出不可见的几何体
+    pub include_invisible: bool,
+    /// 是否导出负实体
+    pub include_neg: bool,
+}
+
+impl Default for IfcExportOptions {
+    fn default() -> Self {
+        Self {
+            project_name: "AIOS".to_string(),
+            author: String::new(),
+            organization: String::new(),
+            attributes: HashMap::new(),
+            property_set_name: "Pset_PDMS".to_string(),
+            include_invisible: false,
+            include_neg: false,
+        }
+    }
+}
+
+/// 导出统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IfcExportStats {
+    /// IfcSite / IfcBuilding 数（含自动生成的默认场地和建筑）
+    pub spatial: usize,
+    pub elements: usize,
+    /// 写入的几何数（即不同的 geo_hash 数）
+    pub representation_maps: usize,
+    pub mapped_items: usize,
+    pub property_sets: usize,
+    /// 找不到 mesh 而跳过的 geo_hash
+    pub missing_meshes: Vec<u64>,
+}
+
+/// 查询元素及其所有祖先的属性，用作 [`IfcExportOptions::attributes`]
+pub async fn query_ifc_attributes(
+    refnos: &[RefnoEnum],
+) -> anyhow::Result<HashMap<RefnoEnum, NamedAttrMap>> {
+    let mut all: HashSet<RefnoEnum> = refnos.iter().copied().collect();
+    for &refno in refnos {
+        all.extend(crate::query_ancestor_refnos(refno).await?);
+    }
+    let mut result = HashMap::new();
+    for refno in all {
+        result.insert(refno, get_named_attmap(refno).await?);
+    }
+    Ok(result)
+}
+
+/// 导出 IFC 文件，mesh 由 `mesh_loader` 按 `geo_hash` 提供
+pub fn export_ifc(
+    data: &ShapeInstancesData,
+    path: impl AsRef<Path>,
+    mesh_loader: impl FnMut(u64) -> Option<PlantMesh>,
+    options: &IfcExportOptions,
+) -> anyhow::Result<IfcExportStats> {
+    let path = path.as_ref();
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (content, stats) = build_ifc(data, &file_name, mesh_loader, options);
+    std::fs::write(path, content)
+        .with_context(|| format!("写入 IFC 文件失败: {}", path.display()))?;
+    Ok(stats)
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    owner: RefnoEnum,
+    name: String,
+    noun: String,
+    kind: IfcEntityKind,
+}
+
+/// 生成 IFC 文件内容
+pub fn build_ifc(
+    data: &ShapeInstancesData,
+    file_name: &str,
+    mut mesh_loader: impl FnMut(u64) -> Option<PlantMesh>,
+    options: &IfcExportOptions,
+) -> (String, IfcExportStats) {
+    let mut stats = IfcExportStats::default();
+    let accept = |geo: &EleInstGeo| {
+        let is_neg = matches!(
+            geo.geo_type,
+            GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+        );
+        (geo.visible || options.include_invisible) && (!is_neg || options.include_neg)
+    };
+
+    // 每个元素的几何
+    let mut geos: BTreeMap<RefnoEnum, (&EleGeosInfo, Vec<&EleInstGeo>, &str)> = BTreeMap::new();
+    for info in data
+        .inst_info_map
+        .values()
+        .chain(data.inst_tubi_map.values())
+        .filter(|i| i.visible || options.include_invisible)
+    {
+        let Some(geos_data) = data.get_inst_geos_data(info) else {
+            continue;
+        };
+        let entry = geos
+            .entry(info.refno)
+            .or_insert_with(|| (info, vec![], geos_data.type_name.as_str()));
+        entry.1.extend(geos_data.insts.iter().filter(|g| accept(g)));
+    }
+
+    // 补齐层级：元素 -> 祖先
+    let node_of = |refno: RefnoEnum| -> Node {
+        let (owner, name, noun) = match (options.attributes.get(&refno), geos.get(&refno)) {
+            (Some(att), _) => (att.get_owner(), att.get_name_or_default(), att.get_type()),
+            (None, Some((info, _, noun))) => (info.owner_refno, String::new(), noun.to_string()),
+            (None, None) => (
+                RefnoEnum::default(),
+                String::new(),
+                geos.values()
+                    .find(|(i, ..)| i.owner_refno == refno)
+                    .map(|(i, ..)| i.owner_type.clone())
+                    .unwrap_or_default(),
+            ),
+        };
+        Node {
+            owner,
+            name,
+            kind: IfcEntityKind::from_noun(&noun),
+            noun,
+        }
+    };
+    let mut nodes: BTreeMap<RefnoEnum, Node> = BTreeMap::new();
+    let mut pending: Vec<RefnoEnum> = geos.keys().copied().collect();
+    while let Some(refno) = pending.pop() {
+        if !refno.is_valid() || nodes.contains_key(&refno) {
+            continue;
+        }
+        let node = node_of(refno);
+        if node.owner != refno {
+            pending.push(node.owner);
+        }
+        nodes.insert(refno, node);
+    }
+
+    let mut spf = Spf::default();
+    let ctx = spf.header_entities(options);
+
+    // 几何和构件
+    let colors = ColorSchemeManager::default();
+    let mut meshes: HashMap<u64, Option<u32>> = HashMap::new();
+    let mut styles: HashMap<[u8; 4], u32> = HashMap::new();
+    let mut ids: BTreeMap<RefnoEnum, u32> = BTreeMap::new();
+    for (refno, node) in &nodes {
+        let representation = geos.get(refno).and_then(|(info, list, _)| {
+            let color = colors.get_color_for_type(info.generic_type);
+            let mut items = vec![];
+            for geo in list {
+                let map = *meshes.entry(geo.geo_hash).or_insert_with(|| {
+                    let mesh = mesh_loader(geo.geo_hash).filter(|m| m.indices.len() >= 3)?;
+                    Some(spf.representation_map(&ctx, &mesh))
+                });
+                let Some(map) = map else {
+                    continue;
+                };
+                let item = spf.mapped_item(map, &info.get_geo_world_transform(geo));
+                if let Some(color) = color {
+                    let style = *styles
+                        .entry(color)
+                        .or_insert_with(|| spf.surface_style(color));
+                    spf.add(format!("IFCSTYLEDITEM(#{item},(#{style}),$)"));
+                }
+                items.push(item);
+            }
+            stats.mapped_items += items.len();
+            (!items.is_empty()).then(|| {
+                let shape = spf.add(format!(
+                    "IFCSHAPEREPRESENTATION(#{},'Body','MappedRepresentation',({}))",
+                    ctx.body,
+                    refs(&items)
+                ));
+                spf.add(format!("IFCPRODUCTDEFINITIONSHAPE($,$,(#{shape}))"))
+            })
+        });
+
+        let name = if node.name.is_empty() {
+            format!("{} ={}", node.noun, refno.to_pdms_str())
+        } else {
+            node.name.clone()
+        };
+        let id = spf.add(format!(
+            "{}('{}',$,{},$,{},#{},{},{})",
+            node.kind.entity_name(),
+            ifc_guid(refno.refno().0, 1),
+            ifc_str(&name),
+            ifc_str(&node.noun),
+            ctx.placement,
+            representation.map_or("$".to_string(), |r| format!("#{r}")),
+            node.kind.trailing_attributes(),
+        ));
+        ids.insert(*refno, id);
+        if node.kind.is_spatial() {
+            stats.spatial += 1;
+        } else {
+            stats.elements += 1;
+        }
+
+        if let Some(att) = options.attributes.get(refno)
+            && let Some(pset) = spf.property_set(att, &options.property_set_name, refno)
+        {
+            spf.add(format!(
+                "IFCRELDEFINESBYPROPERTIES('{}',$,$,$,(#{id}),#{pset})",
+                ifc_guid(refno.refno().0, 3)
+            ));
+            stats.property_sets += 1;
+        }
+    }
+    stats.representation_maps = meshes.values().filter(|m| m.is_some()).count();
+    stats.missing_meshes = meshes
+        .iter()
+        .filter(|(_, m)| m.is_none())
+        .map(|(h, _)| *h)
+        .collect();
+    stats.missing_meshes.sort_unstable();
+
+    // 层级关系
+    let mut children: BTreeMap<RefnoEnum, Vec<RefnoEnum>> = BTreeMap::new();
+    let mut roots = vec![];
+    for (refno, node) in &nodes {
+        if nodes.contains_key(&node.owner) && node.owner != *refno {
+            children.entry(node.owner).or_default().push(*refno);
+        } else {
+            roots.push(*refno);
+        }
+    }
+    let mut default_site = None;
+    let mut default_building = None;
+    let mut project_sites = vec![];
+    let mut site_buildings = vec![];
+    let mut building_elements = vec![];
+    for refno in roots {
+        match nodes[&refno].kind {
+            IfcEntityKind::Site => project_sites.push(ids[&refno]),
+            IfcEntityKind::Building => site_buildings.push(ids[&refno]),
+            _ => building_elements.push(ids[&refno]),
+        }
+    }
+    if !site_buildings.is_empty() || !building_elements.is_empty() {
+        let site = spf.add(format!(
+            "IFCSITE('{}',$,'Default Site',$,$,#{},$,{})",
+            ifc_guid(0, 6),
+            ctx.placement,
+            IfcEntityKind::Site.trailing_attributes()
+        ));
+        project_sites.push(site);
+        default_site = Some(site);
+        stats.spatial += 1;
+    }
+    if !building_elements.is_empty() {
+        let building = spf.add(format!(
+            "IFCBUILDING('{}',$,'Default Building',$,$,#{},$,{})",
+            ifc_guid(0, 7),
+            ctx.placement,
+            IfcEntityKind::Building.trailing_attributes()
+        ));
+        site_buildings.push(building);
+        default_building = Some(building);
+        stats.spatial += 1;
+    }
+    spf.aggregate(ctx.project, &project_sites);
+    if let Some(site) = default_site {
+        spf.aggregate(site, &site_buildings);
+    }
+    if let Some(building) = default_building {
+        spf.contain(building, &building_elements);
+    }
+    for (parent, list) in &children {
+        let parent_id = ids[parent];
+        let (spatial, elements): (Vec<_>, Vec<_>) =
+            list.iter().partition(|c| nodes[*c].kind.is_spatial());
+        let spatial: Vec<u32> = spatial.iter().map(|c| ids[c]).collect();
+        let elements: Vec<u32> = elements.iter().map(|c| ids[c]).collect();
+        if nodes[parent].kind.is_spatial() {
+            spf.aggregate(parent_id, &spatial);
+            spf.contain(parent_id, &elements);
+        } else {
+            spf.aggregate(parent_id, &[spatial, elements].concat());
+        }
+    }
+
+    (spf.finish(file_name, options), stats)
+}
+
+/// 项目、表示上下文等公共实体
+struct SpfContext {
+    project: u32,
+    /// 'Body' 子上下文
+    body: u32,
+    /// 世界坐标系原点
+    origin: u32,
+    /// 所有元素共用的放置
+    placement: u32,
+}
+
+#[derive(Default)]
+struct Spf {
+    lines: Vec<String>,
+}
+
+impl Spf {
+    fn add(&mut self, entity: impl Into<String>) -> u32 {
+        self.lines.push(entity.into());
+        self.lines.len() as u32
+    }
+
+    fn header_entities(&mut self, options: &IfcExportOptions) -> SpfContext {
+        let point = self.add("IFCCARTESIANPOINT((0.,0.,0.))");
+        let origin = self.add(format!("IFCAXIS2PLACEMENT3D(#{point},$,$)"));
+        let model = self.add(format!(
+            "IFCGEOMETRICREPRESENTATIONCONTEXT($,'Model',3,1.E-05,#{origin},$)"
+        ));
+        let body = self.add(format!(
+            "IFCGEOMETRICREPRESENTATIONSUBCONTEXT('Body','Model',*,*,*,*,#{model},$,.MODEL_VIEW.,$)"
+        ));
+        let units = [
+            self.add("IFCSIUNIT(*,.LENGTHUNIT.,.MILLI.,.METRE.)"),
+            self.add("IFCSIUNIT(*,.AREAUNIT.,$,.SQUARE_METRE.)"),
+            self.add("IFCSIUNIT(*,.VOLUMEUNIT.,$,.CUBIC_METRE.)"),
+            self.add("IFCSIUNIT(*,.PLANEANGLEUNIT.,$,.RADIAN.)"),
+        ];
+        let units = self.add(format!("IFCUNITASSIGNMENT(({}))", refs(&units)));
+        let project = self.add(format!(
+            "IFCPROJECT('{}',$,{},$,$,$,$,(#{model}),#{units})",
+            ifc_guid(0, 5),
+            ifc_str(&options.project_name)
+        ));
+        let placement = self.add(format!("IFCLOCALPLACEMENT($,#{origin})"));
+        SpfContext {
+            project,
+            body,
+            origin,
+            placement,
+        }
+    }
+
+    fn representation_map(&mut self, ctx: &SpfContext, mesh: &PlantMesh) -> u32 {
+        let mut coords = String::new();
+        for (i, v) in mesh.vertices.iter().enumerate() {
+            if i > 0 {
+                coords.push(',');
+            }
+            let _ = write!(coords, "({},{},{})", real(v.x), real(v.y), real(v.z));
+        }
+        let points = self.add(format!("IFCCARTESIANPOINTLIST3D(({coords}))"));
+        let mut indices = String::new();
+        for (i, t) in mesh.indices.chunks_exact(3).enumerate() {
+            if i > 0 {
+                indices.push(',');
+            }
+            let _ = write!(indices, "({},{},{})", t[0] + 1, t[1] + 1, t[2] + 1);
+        }
+        let face_set = self.add(format!(
+            "IFCTRIANGULATEDFACESET(#{points},$,$,({indices}),$)"
+        ));
+        let shape = self.add(format!(
+            "IFCSHAPEREPRESENTATION(#{},'Body','Tessellation',(#{face_set}))",
+            ctx.body
+        ));
+        self.add(format!("IFCREPRESENTATIONMAP(#{},#{shape})", ctx.origin))
+    }
+
+    fn mapped_item(&mut self, map: u32, transform: &Transform) -> u32 {
+        let rotation = transform.rotation.normalize();
+        let [x, y, z] = [Vec3::X, Vec3::Y, Vec3::Z].map(|axis| {
+            let d = rotation * axis;
+            self.add(format!(
+                "IFCDIRECTION(({},{},{}))",
+                real(d.x),
+                real(d.y),
+                real(d.z)
+            ))
+        });
+        let t = transform.translation;
+        let origin = self.add(format!(
+            "IFCCARTESIANPOINT(({},{},{}))",
+            real(t.x),
+            real(t.y),
+            real(t.z)
+        ));
+        let s = transform.scale;
+        let operator = self.add(format!(
+            "IFCCARTESIANTRANSFORMATIONOPERATOR3DNONUNIFORM(#{x},#{y},#{origin},{},#{z},{},{})",
+            real(s.x),
+            real(s.y),
+            real(s.z)
+        ));
+        self.add(format!("IFCMAPPEDITEM(#{map},#{operator})"))
+    }
+
+    fn surface_style(&mut self, [r, g, b, a]: [u8; 4]) -> u32 {
+        let colour = self.add(format!(
+            "IFCCOLOURRGB($,{},{},{})",
+            real(r as f32 / 255.0),
+            real(g as f32 / 255.0),
+            real(b as f32 / 255.0)
+        ));
+        let rendering = self.add(format!(
+            "IFCSURFACESTYLESHADING(#{colour},{})",
+            real(1.0 - a as f32 / 255.0)
+        ));
+        self.add(format!("IFCSURFACESTYLE($,.BOTH.,(#{rendering}))"))
+    }
+
+    /// 属性集，无可导出的属性时返回 None
+    fn property_set(&mut self, att: &NamedAttrMap, name: &str, refno: &RefnoEnum) -> Option<u32> {
+        let mut props = vec![];
+        for (key, value) in &att.map {
+            let value = match value {
+                NamedAttrValue::IntegerType(v) => format!("IFCINTEGER({v})"),
+                NamedAttrValue::LongType(v) => format!("IFCINTEGER({v})"),
+                NamedAttrValue::F32Type(v) => format!("IFCREAL({})", real(*v)),
+                NamedAttrValue::BoolType(v) => {
+                    format!("IFCBOOLEAN(.{}.)", if *v { "T" } else { "F" })
+                }
+                NamedAttrValue::RefU64Type(v) => format!("IFCLABEL({})", ifc_str(&v.to_pdms_str())),
+                NamedAttrValue::RefnoEnumType(v) => {
+                    format!("IFCLABEL({})", ifc_str(&v.to_pdms_str()))
+                }
+                NamedAttrValue::InvalidType | NamedAttrValue::RefU64Array(_) => continue,
+                other => format!("IFCLABEL({})", ifc_str(&other.get_val_as_string())),
+            };
+            props.push(self.add(format!(
+                "IFCPROPERTYSINGLEVALUE({},$,{value},$)",
+                ifc_str(key)
+            )));
+        }
+        (!props.is_empty()).then(|| {
+            self.add(format!(
+                "IFCPROPERTYSET('{}',$,{},$,({}))",
+                ifc_guid(refno.refno().0, 2),
+                ifc_str(name),
+                refs(&props)
+            ))
+        })
+    }
+
+    fn aggregate(&mut self, parent: u32, children: &[u32]) {
+        if !children.is_empty() {
+            let guid = ifc_guid(parent as u64, 4);
+            self.add(format!(
+                "IFCRELAGGREGATES('{guid}',$,$,$,#{parent},({}))",
+                refs(children)
+            ));
+        }
+    }
+
+    fn contain(&mut self, structure: u32, elements: &[u32]) {
+        if !elements.is_empty() {
+            let guid = ifc_guid(structure as u64, 8);
+            self.add(format!(
+                "IFCRELCONTAINEDINSPATIALSTRUCTURE('{guid}',$,$,$,({}),#{structure})",
+                refs(elements)
+            ));
+        }
+    }
+
+    fn finish(self, file_name: &str, options: &IfcExportOptions) -> String {
+        let mut out = String::from("ISO-10303-21;\nHEADER;\n");
+        out.push_str("FILE_DESCRIPTION(('ViewDefinition [ReferenceView_V1.2]'),'2;1');\n");
+        let _ = writeln!(
+            out,
+            "FILE_NAME({},'{}',({}),({}),'AIOS','AIOS IFC Exporter','');",
+            ifc_str(file_name),
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+            ifc_str(&options.author),
+            ifc_str(&options.organization)
+        );
+        out.push_str("FILE_SCHEMA(('IFC4'));\nENDSEC;\nDATA;\n");
+        for (i, line) in self.lines.iter().enumerate() {
+            let _ = writeln!(out, "#{}={line};", i + 1);
+        }
+        out.push_str("ENDSEC;\nEND-ISO-10303-21;\n");
+        out
+    }
+}
+
+fn refs(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|id| format!("#{id}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// SPF 的实数必须带小数点
+fn real(v: f32) -> String {
+    let s = v.to_string();
+    if s.contains(['.', 'e', 'E']) || !v.is_finite() {
+        s
+    } else {
+        format!("{s}.")
+    }
+}
+
+/// SPF 字符串：单引号和反斜杠转义，非 ASCII 字符编码为 `\X2\....\X0\`
+pub fn ifc_str(s: &str) -> String {
+    let mut out = String::from("'");
+    let mut wide = false;
+    for c in s.chars() {
+        if c.is_ascii() && !c.is_ascii_control() {
+            if wide {
+                out.push_str("\\X0\\");
+                wide = false;
+            }
+            match c {
+                '\'' => out.push_str("''"),
+                '\\' => out.push_str("\\\\"),
+                _ => out.push(c),
+            }
+        } else {
+            if !wide {
+                out.push_str("\\X2\\");
+                wide = true;
+            }
+            let mut buf = [0u16; 2];
+            for unit in c.encode_utf16(&mut buf) {
+                let _ = write!(out, "{unit:04X}");
+            }
+        }
+    }
+    if wide {
+        out.push_str("\\X0\\");
+    }
+    out.push('\'');
+    out
+}
+
+/// 由两个整数确定性地生成 22 位 IfcGloballyUniqueId，`tag` 区分同一元素的不同实体
+pub fn ifc_guid(id: u64, tag: u64) -> String {
+    let v = ((tag as u128) << 64) | id as u128;
+    let mut out = String::with_capacity(22);
+    out.push(GUID_CHARS[(v >> 126) as usize] as char);
+    for i in (0..21).rev() {
+        out.push(GUID_CHARS[((v >> (i * 6)) & 0x3f) as usize] as char);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::EleInstGeosData;
+    use crate::geometry::csg::unit_box_mesh;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    #[test]
+    fn test_ifc_str() {
+        assert_eq!(ifc_str("A'B\\C"), "'A''B\\\\C'");
+        assert_eq!(ifc_str("泵-1"), "'\\X2\\6CF5\\X0\\-1'");
+        assert_eq!(ifc_guid(1, 1).len(), 22);
+        assert_ne!(ifc_guid(1, 1), ifc_guid(1, 2));
+    }
+
+    #[test]
+    fn test_build_ifc() {
+        let mut data = ShapeInstancesData::default();
+        for (n, x) in [(3, 0.0), (4, 1000.0)] {
+            let info = EleGeosInfo {
+                refno: refno(n),
+                owner_refno: refno(2),
+                owner_type: "EQUI".to_string(),
+                visible: true,
+                ..Default::default()
+            };
+            data.insert_geos_data(
+                info.get_inst_key(),
+                EleInstGeosData {
+                    refno: refno(n),
+                    type_name: "BOX".to_string(),
+                    insts: vec![EleInstGeo {
+                        geo_hash: 1,
+                        transform: Transform::from_xyz(x, 0.0, 0.0),
+                        visible: true,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            );
+            data.insert_info(refno(n), info);
+        }
+        let att = |n: u32, noun: &str, owner: Option<u32>| {
+            let mut att = NamedAttrMap::default();
+            att.insert(
+                "TYPE".to_string(),
+                NamedAttrValue::StringType(noun.to_string()),
+            );
+            att.insert("REFNO".to_string(), NamedAttrValue::RefnoEnumType(refno(n)));
+            if let Some(owner) = owner {
+                att.insert(
+                    "OWNER".to_string(),
+                    NamedAttrValue::RefnoEnumType(refno(owner)),
+                );
+            }
+            att
+        };
+        let mut options = IfcExportOptions::default();
+        let mut site = att(1, "SITE", None);
+        site.insert(
+            "NAME".to_string(),
+            NamedAttrValue::StringType("/SITE-1".to_string()),
+        );
+        options.attributes.insert(refno(1), site);
+        options.attributes.insert(refno(2), att(2, "EQUI", Some(1)));
+
+        let (content, stats) = build_ifc(&data, "test.ifc", |_| Some(unit_box_mesh()), &options);
+        assert_eq!(stats.spatial, 1);
+        assert_eq!(stats.elements, 3);
+        assert_eq!(stats.representation_maps, 1);
+        assert_eq!(stats.mapped_items, 2);
+        assert!(content.contains("IFCSITE('") && content.contains("'/SITE-1'"));
+        assert!(content.contains("IFCELEMENTASSEMBLY("));
+        assert_eq!(content.matches("IFCTRIANGULATEDFACESET(").count(), 1);
+        // SITE 包含 EQUI，EQUI 聚合两个 BOX
+        assert_eq!(
+            content
+                .matches("IFCRELCONTAINEDINSPATIALSTRUCTURE(")
+                .count(),
+            1
+        );
+        assert_eq!(content.matches("IFCRELAGGREGATES(").count(), 2);
+        assert!(content.contains("IFCPROPERTYSET("));
+    }
+}
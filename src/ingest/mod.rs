@@ -0,0 +1,354 @@
+//! 原始 PDMS db 文件的并行导入
+//!
+//! [`IngestPipeline`] 同时解析多个 db 文件，解析出的元素按批经有界通道流入
+//! [`BulkWriter`]，写入 pe 表与各 noun 属性表：
+//!
+//! - 解析在阻塞线程池中进行，同时解析的文件数受 `parse_concurrency`（CPU 预算）限制
+//! - 解析前按文件头与 `DbOption::float_decoder` 为每个文件选择浮点解码器，并在解析线程中启用，
+//!   解析器内的 `parse_to_f64` 等按该文件的布局解码
+//! - 所有文件共享 `io_concurrency` 个写入许可（IO 预算），通道满时解析线程等待（背压）
+//! - 每个文件完成后回调一次 [`FileReport`]，单个文件失败不影响其他文件
+//!
+//! 二进制格式的解析由调用方通过 [`DbFileParser`] 提供：
+//!
+//! ```rust,ignore
+//! let files = ingest::collect_db_files("/data/projects", ["AvevaMarineSample"])?;
+//! let report = IngestPipeline::new(MyParser)
+//!     .on_file_done(|f| println!("{}: {} 个元素", f.path.display(), f.elements))
+//!     .run(&SUL_DB, files)
+//!     .await;
+//! println!("{}", report.error_summary());
+//! ```
+//!
+//! [`BulkWriter`]: crate::rs_surreal::bulk::BulkWriter
+
+use crate::file_helper::collect_db_dirs;
+use crate::helper::float_decode::db_file_decoder;
+use crate::pe::SPdmsElement;
+use crate::rs_surreal::bulk::BulkWriter;
+use crate::types::NamedAttrMap;
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+use tokio::sync::{Semaphore, mpsc};
+
+/// 解析出的单个元素
+#[derive(Debug, Clone)]
+pub struct ParsedElement {
+    pub pe: SPdmsElement,
+    pub attmap: NamedAttrMap,
+}
+
+/// db 文件解析器
+pub trait DbFileParser: Send + Sync + 'static {
+    /// 解析单个文件，通过 `emit` 分批送出元素；`emit` 返回错误时应停止解析
+    ///
+    /// 在阻塞线程中调用，可直接进行文件 IO 与 CPU 密集计算。调用期间已启用该文件的浮点解码器
+    /// （[`current_float_decoder`](crate::helper::current_float_decoder)）。
+    fn parse_file(
+        &self,
+        path: &Path,
+        emit: &mut dyn FnMut(Vec<ParsedElement>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()>;
+}
+
+/// 导入选项
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    /// 同时解析的文件数
+    pub parse_concurrency: usize,
+    /// 同时进行的写入数（所有文件共享）
+    pub io_concurrency: usize,
+    /// 每个文件在途的元素批次数
+    pub channel_capacity: usize,
+    /// 单次写入的记录数
+    pub chunk_size: usize,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            parse_concurrency: num_cpus::get().max(1),
+            io_concurrency: 4,
+            channel_capacity: 4,
+            chunk_size: crate::consts::MAX_INSERT_LENGTH,
+        }
+    }
+}
+
+/// 单个文件的导入结果
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// 解析出的元素数
+    pub elements: usize,
+    /// 写入成功的元素数
+    pub written: usize,
+    pub elapsed: Duration,
+    /// 解析与写入错误
+    pub errors: Vec<String>,
+}
+
+impl FileReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// 导入汇总
+#[derive(Debug, Clone, Default)]
+pub struct IngestReport {
+    pub files: Vec<FileReport>,
+    pub elapsed: Duration,
+}
+
+impl IngestReport {
+    pub fn total_elements(&self) -> usize {
+        self.files.iter().map(|f| f.elements).sum()
+    }
+
+    pub fn total_written(&self) -> usize {
+        self.files.iter().map(|f| f.written).sum()
+    }
+
+    pub fn failed_files(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|f| !f.is_ok())
+    }
+
+    /// 错误汇总，每个失败文件一行
+    pub fn error_summary(&self) -> String {
+        let mut s = format!(
+            "导入 {} 个文件，解析 {} 个元素，写入 {} 个，失败文件 {} 个，耗时 {:.1}s",
+            self.files.len(),
+            self.total_elements(),
+            self.total_written(),
+            self.failed_files().count(),
+            self.elapsed.as_secs_f64()
+        );
+        for f in self.failed_files() {
+            s.push_str(&format!(
+                "\n  {}: {}",
+                f.path.display(),
+                f.errors.join("; ")
+            ));
+        }
+        s
+    }
+}
+
+type FileDoneFn = Arc<dyn Fn(&FileReport) + Send + Sync>;
+
+/// 并行导入管线
+pub struct IngestPipeline<P: DbFileParser> {
+    parser: Arc<P>,
+    options: IngestOptions,
+    on_file_done: Option<FileDoneFn>,
+}
+
+impl<P: DbFileParser> IngestPipeline<P> {
+    pub fn new(parser: P) -> Self {
+        Self {
+            parser: Arc::new(parser),
+            options: IngestOptions::default(),
+            on_file_done: None,
+        }
+    }
+
+    pub fn options(mut self, options: IngestOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 每个文件完成（含失败）后的回调
+    pub fn on_file_done(mut self, f: impl Fn(&FileReport) + Send + Sync + 'static) -> Self {
+        self.on_file_done = Some(Arc::new(f));
+        self
+    }
+
+    /// 导入全部文件，单个文件的错误记录在报告中
+    pub async fn run(&self, db: &Surreal<Any>, files: Vec<PathBuf>) -> IngestReport {
+        let start = Instant::now();
+        let io_permits = Semaphore::new(self.options.io_concurrency.max(1));
+        let files = futures::stream::iter(files)
+            .map(|path| self.ingest_file(db, path, &io_permits))
+            .buffer_unordered(self.options.parse_concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+        IngestReport {
+            files,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    async fn ingest_file(&self, db: &Surreal<Any>, path: PathBuf, io: &Semaphore) -> FileReport {
+        let start = Instant::now();
+        let mut report = FileReport {
+            path: path.clone(),
+            elements: 0,
+            written: 0,
+            elapsed: Duration::ZERO,
+            errors: vec![],
+        };
+
+        let (tx, mut rx) =
+            mpsc::channel::<Vec<ParsedElement>>(self.options.channel_capacity.max(1));
+        let parser = self.parser.clone();
+        let parse_path = path.clone();
+        let parse = tokio::task::spawn_blocking(move || {
+            // task-local 的解码器不会传到阻塞线程，在线程内启用
+            let decoder = db_file_decoder(&parse_path)?;
+            decoder.sync_scope(|| {
+                parser.parse_file(&parse_path, &mut |batch| {
+                    tx.blocking_send(batch)
+                        .map_err(|_| anyhow::anyhow!("写入端已关闭"))
+                })
+            })
+        });
+
+        while let Some(batch) = rx.recv().await {
+            report.elements += batch.len();
+            let _permit = io.acquire().await;
+            match self.write_batch(db, &batch).await {
+                Ok(()) => report.written += batch.len(),
+                Err(e) => report.errors.push(format!("{:#}", e)),
+            }
+        }
+
+        match parse.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => report.errors.push(format!("解析失败: {:#}", e)),
+            Err(e) => report.errors.push(format!("解析线程异常: {}", e)),
+        }
+        report.elapsed = start.elapsed();
+        if report.is_ok() {
+            log::info!(
+                "导入 {}: {} 个元素，耗时 {:.1}s",
+                path.display(),
+                report.elements,
+                report.elapsed.as_secs_f64()
+            );
+        } else {
+            log::warn!("导入 {} 出错: {}", path.display(), report.errors.join("; "));
+        }
+        if let Some(f) = &self.on_file_done {
+            f(&report);
+        }
+        report
+    }
+
+    /// 写入一批元素：pe 表以及按 noun 分组的属性表
+    async fn write_batch(&self, db: &Surreal<Any>, batch: &[ParsedElement]) -> anyhow::Result<()> {
+        let pes = batch
+            .iter()
+            .map(|e| e.pe.gen_sur_json(Some(e.pe.refno.to_pe_key())))
+            .collect::<Vec<_>>();
+        self.writer("pe").write_json(db, &pes).await?;
+
+        let mut by_noun: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for e in batch {
+            if let Some(json) = e.attmap.gen_sur_json() {
+                by_noun.entry(e.attmap.get_type()).or_default().push(json);
+            }
+        }
+        for (noun, attmaps) in by_noun {
+            self.writer(&noun).write_json(db, &attmaps).await?;
+        }
+        Ok(())
+    }
+
+    fn writer(&self, table: &str) -> BulkWriter {
+        BulkWriter::new(table)
+            .chunk_size(self.options.chunk_size)
+            .concurrency(1)
+    }
+}
+
+/// 收集项目 db 目录（`*000`）下的全部 db 文件，按路径排序
+pub fn collect_db_files<'a, T: AsRef<Path>>(
+    dir: T,
+    projects: impl IntoIterator<Item = &'a str>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for db_dir in collect_db_dirs(dir, projects)? {
+        for entry in std::fs::read_dir(db_dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_summary() {
+        let ok = FileReport {
+            path: PathBuf::from("sam000/sam1001_0001"),
+            elements: 10,
+            written: 10,
+            elapsed: Duration::ZERO,
+            errors: vec![],
+        };
+        let failed = FileReport {
+            path: PathBuf::from("sam000/sam1002_0001"),
+            elements: 5,
+            written: 0,
+            errors: vec!["解析失败: 文件头无效".to_string()],
+            ..ok.clone()
+        };
+        let report = IngestReport {
+            files: vec![ok, failed],
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(report.total_elements(), 15);
+        assert_eq!(report.total_written(), 10);
+        assert_eq!(report.failed_files().count(), 1);
+        let summary = report.error_summary();
+        assert!(summary.contains("失败文件 1 个"));
+        assert!(summary.contains("sam1002_0001: 解析失败: 文件头无效"));
+    }
+
+    struct LayoutProbe(Arc<std::sync::Mutex<Vec<crate::helper::FloatLayout>>>);
+
+    impl DbFileParser for LayoutProbe {
+        fn parse_file(
+            &self,
+            _path: &Path,
+            _emit: &mut dyn FnMut(Vec<ParsedElement>) -> anyhow::Result<()>,
+        ) -> anyhow::Result<()> {
+            let layout = crate::helper::current_float_decoder().layout;
+            self.0.lock().unwrap().push(layout);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_with_file_decoder() {
+        let seen = Arc::new(std::sync::Mutex::new(vec![]));
+        let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+        let report = IngestPipeline::new(LayoutProbe(seen.clone()))
+            .run(
+                &Surreal::<Any>::init(),
+                vec![data.join("desvir.dat"), data.join("attlib.dat")],
+            )
+            .await;
+        // 解析线程中使用按文件头选出的布局
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [crate::helper::FloatLayout::BeWordSwapped]
+        );
+        // attlib.dat 不是库文件，文件头无法识别，不解析并记录错误
+        let failed: Vec<_> = report.failed_files().collect();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].path.ends_with("attlib.dat"));
+    }
+}
@@ -5,7 +5,7 @@
 #![allow(warnings)]
 
 use crate::error::HandleError;
-use config::{Config, File};
+use ::config::{Config, File};
 use dashmap::DashMap;
 #[allow(unused_mut)]
 use std::collections::BTreeMap;
@@ -22,17 +22,22 @@ pub use debug_macros::*;
 
 pub mod accel_tree;
 pub mod aios_db_mgr;
+pub mod analysis;
+pub mod aql;
 pub mod attlib_parser;
 pub mod axis_param;
+pub mod catalog;
 
 pub mod basic;
 
 // pub mod parse; // 模块已移动或删除
 
 pub mod bevy_types;
-// pub mod cache; // 模块已删除
+pub mod cache;
+pub mod config;
 pub mod consts;
 pub mod csg;
+pub mod electrical;
 pub mod error;
 pub mod geom_types;
 pub mod table_const;
@@ -40,6 +45,8 @@ pub mod table_const;
 pub mod geometry;
 
 pub mod helper;
+pub mod hvac;
+pub mod ifc;
 #[cfg(feature = "live")]
 pub mod live;
 pub mod parsed_data;
@@ -53,22 +60,39 @@ pub mod tool;
 pub mod vec3_pool;
 // 全自动出图所需的结构体
 pub mod bin_data;
+pub mod blocking;
 pub mod create_attas_structs;
 pub mod data_center;
+pub mod derived_attr;
+pub mod diagnose;
+pub mod facade;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "grpc")]
+pub mod service;
 pub mod datacenter_options;
 pub mod dblist_parser;
+pub mod mesh_store;
 pub mod metadata;
 pub mod metadata_manager;
+pub mod metrics;
 pub mod negative_mesh_type;
+pub mod noun;
 pub mod options;
 pub mod pdms_pluggin;
 pub mod pdms_user;
 pub mod penetration;
 pub mod plat_user;
+pub mod rvm;
 pub mod rvm_types;
 pub mod ssc_setting;
 pub mod three_dimensional_review;
 pub mod vague_search;
+pub mod search;
+pub mod telemetry;
+pub mod xkt;
 pub mod version_control;
 pub mod virtual_hole;
 
@@ -98,13 +122,21 @@ pub mod schema;
 pub mod sync;
 pub mod types;
 
+#[cfg(feature = "typed-attrs")]
+pub mod typed_attrs;
+
 pub mod material;
 pub mod math;
 pub mod mesh_precision;
+pub mod piping;
+pub mod plot;
 pub mod room;
 
 pub mod file_helper;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ingest;
+
 pub mod petgraph;
 
 pub mod db;
@@ -145,28 +177,30 @@ use crate::options::{DbOption, SecondUnitDbOption};
 use once_cell_serde::sync::OnceCell;
 use surrealdb::opt::auth::Root;
 
-/// 获取配置文件名，支持环境变量
-fn get_config_file_name() -> String {
-    std::env::var("DB_OPTION_FILE").unwrap_or_else(|_| "DbOption".to_string())
+/// 主连接就绪后按 `surreal_pool_size` 建立 `SUL_DB` 连接池，失败时仅使用主连接
+async fn init_sul_db_pool(db_option: &DbOption) {
+    SUL_DB.set_options(PoolOptions {
+        size: db_option.surreal_pool_size.unwrap_or(1),
+        ..SUL_DB.options()
+    });
+    let config = rs_surreal::ConnectionConfig::new(
+        db_option.get_version_db_conn_str(),
+        &db_option.surreal_ns,
+        &db_option.project_name,
+        &db_option.v_user,
+        &db_option.v_password,
+    );
+    if let Err(e) = SUL_DB.init_pool(config).await {
+        log::warn!("连接池初始化失败，仅使用主连接: {}", e);
+    }
 }
 
 ///获得db option
+///
+/// 首次调用时按缺省路径读取配置文件，见 [`config::try_get_db_option`]
 #[inline]
-pub fn get_db_option() -> &'static DbOption {
-    static INSTANCE: OnceCell<DbOption> = OnceCell::new();
-    INSTANCE.get_or_init(|| {
-        use config::{Config, ConfigError, Environment, File};
-
-        let config_file_name = get_config_file_name();
-
-        let s = Config::builder()
-            .add_source(File::with_name(&config_file_name))
-            .build()
-            .unwrap();
-        let option = s.try_deserialize::<DbOption>().unwrap();
-        crate::mesh_precision::set_active_precision(option.mesh_precision.clone());
-        option
-    })
+pub fn get_db_option() -> Result<&'static DbOption, config::ConfigError> {
+    config::try_get_db_option()
 }
 
 ///获取默认的数据库属性元数据信息
@@ -200,16 +234,10 @@ pub fn get_uda_info() -> &'static (DashMap<u32, String>, DashMap<String, u32>) {
     INSTANCE.get_or_init(|| {
         let mut ukey_udna_map = DashMap::new();
         let mut udna_ukey_map = DashMap::new();
-        use config::{Config, ConfigError, Environment, File};
-        let config_file_name = get_config_file_name();
-        let Ok(s) = Config::builder()
-            .add_source(File::with_name(&config_file_name))
-            .build()
-        else {
+        let Ok(db_option) = config::try_get_db_option() else {
             return (DashMap::new(), DashMap::new());
         };
-        let db_option: DbOption = s.try_deserialize().unwrap();
-        for project in db_option.included_projects {
+        for project in &db_option.included_projects {
             let path = format!("{}_uda.bin", project);
             if let Ok(mut file) = std::fs::File::open(path) {
                 let mut data = Vec::new();
@@ -226,16 +254,20 @@ pub fn get_uda_info() -> &'static (DashMap<u32, String>, DashMap<String, u32>) {
 }
 
 pub async fn init_test_surreal() -> Result<DbOption, HandleError> {
-    let config_file_name = get_config_file_name();
-    let s = Config::builder()
-        .add_source(File::with_name(&config_file_name))
-        .build()
+    let db_option = get_db_option()
         .map_err(|e| HandleError::SurrealError {
             msg: format!("Failed to load DbOption config: {}", e),
-        })?;
-    let db_option: DbOption = s.try_deserialize().map_err(|e| HandleError::SurrealError {
-        msg: format!("Failed to deserialize DbOption: {}", e),
-    })?;
+        })?
+        .clone();
+    init_test_surreal_with(db_option).await
+}
+
+/// 按传入的配置初始化测试连接，不读取配置文件
+///
+/// 全局配置尚未设置时，`db_option` 同时成为 [`get_db_option`] 返回的全局配置。
+pub async fn init_test_surreal_with(db_option: DbOption) -> Result<DbOption, HandleError> {
+    // 全局配置已存在时保留原配置
+    let _ = config::set_db_option(db_option.clone());
 
     // 创建配置
     let config = surrealdb::opt::Config::default().ast_payload(); // 启用AST格式
@@ -266,6 +298,8 @@ pub async fn init_test_surreal() -> Result<DbOption, HandleError> {
         .use_db(&db_option.project_name)
         .await;
 
+    init_sul_db_pool(&db_option).await;
+
     // Define common functions (使用 None 从配置文件自动读取路径)
     define_common_functions(None)
         .await
@@ -284,15 +318,24 @@ pub async fn init_test_surreal() -> Result<DbOption, HandleError> {
 }
 
 pub async fn init_surreal() -> anyhow::Result<()> {
-    let config_file_name = get_config_file_name();
+    let config_path = config::resolve_config_path(None);
     println!("🔧 正在初始化数据库连接...");
-    println!("📄 使用配置文件: {}.toml", config_file_name);
+    println!(
+        "📄 使用配置文件: {}",
+        config::find_config_file(&config_path)
+            .unwrap_or(config_path)
+            .display()
+    );
+
+    init_surreal_with(get_db_option()?).await
+}
 
-    let s = Config::builder()
-        .add_source(File::with_name(&config_file_name))
-        .build()
-        .unwrap();
-    let db_option: DbOption = s.try_deserialize()?;
+/// 按传入的配置初始化数据库连接，不读取配置文件
+///
+/// 全局配置尚未设置时，`db_option` 同时成为 [`get_db_option`] 返回的全局配置。
+pub async fn init_surreal_with(db_option: &DbOption) -> anyhow::Result<()> {
+    // 全局配置已存在时保留原配置
+    let _ = config::set_db_option(db_option.clone());
 
     // 打印服务器连接信息
     let connection_str = db_option.get_version_db_conn_str();
@@ -331,6 +374,9 @@ pub async fn init_surreal() -> anyhow::Result<()> {
 
     println!("✅ 数据库连接成功！");
 
+    init_sul_db_pool(&db_option).await;
+    rs_surreal::project_registry::init_projects(&db_option).await;
+
     // Define common functions (使用 None 从配置文件自动读取路径)
     define_common_functions(None)
         .await
@@ -345,11 +391,17 @@ pub async fn init_surreal() -> anyhow::Result<()> {
 }
 
 /// 连接二号机组
+///
+/// 同时以 [`SECOND_UNIT`](rs_surreal::project_registry::SECOND_UNIT) 登记到
+/// [`PROJECT_REGISTRY`]；更多项目请在 DbOption 的 `[[projects]]` 中配置。
 pub async fn init_second_unit_surreal() -> anyhow::Result<()> {
     let s = Config::builder()
         .add_source(File::with_name("SecondUnitDbOption"))
         .build()?;
     let db_option: SecondUnitDbOption = s.try_deserialize()?;
+    PROJECT_REGISTRY
+        .register_option(&db_option.clone().into())
+        .await?;
     let config = surrealdb::opt::Config::default().ast_payload(); // 启用AST格式
     SECOND_SUL_DB
         .connect((db_option.get_version_db_conn_str(), config))
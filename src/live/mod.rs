@@ -1,3 +1,11 @@
+pub mod subscribe;
+pub mod supervisor;
+
+pub use subscribe::{
+    ChangeKind, GeomChange, GeomFilter, LiveStream, PeChange, PeFilter, subscribe_geom,
+    subscribe_pe, subscribe_pe_since,
+};
+
 use crate::RefU64;
 use serde::{Deserialize, Serialize};
 
@@ -29,5 +37,5 @@ pub const PE_LIVE_SQL: &str = r#"
         array::len(select value id from <-pe_owner) as children_count,
         status_code
     from pe
-    where !type::is::array(record::id(id))
+    where !type::is_array(record::id(id))
 "#;
@@ -0,0 +1,438 @@
+//! 类型化的变更订阅
+//!
+//! 在 [`LiveQueryManager`] 之上封装 `PE_LIVE_SQL` / `GEOM_LIVE_SQL`，以 [`Stream`] 输出类型化的变更：
+//! - 断线重连与补齐回放由监管任务完成
+//! - 回放与实时推送重叠的记录按内容去重
+//! - 过滤条件在本地应用
+//!
+//! ```rust,ignore
+//! use aios_core::live::{PeFilter, subscribe_pe};
+//! use futures::StreamExt;
+//!
+//! let mut changes = subscribe_pe(PeFilter::default().nouns(["EQUI", "PIPE"]));
+//! while let Some(change) = changes.next().await {
+//!     println!("{:?} {} {}", change.kind, change.refno, change.noun);
+//! }
+//! ```
+
+use super::supervisor::{LiveEvent, LiveQueryManager, LiveSubscription};
+use crate::RefnoEnum;
+use futures::Stream;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+use tokio::sync::mpsc;
+
+/// 去重窗口：每个订阅最多记住的元素数
+const DEDUP_CAPACITY: usize = 100_000;
+
+static NEXT_STREAM_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+    /// 重连补齐回放的记录，可能是新增或修改（回放的删除为 `Deleted`）
+    Replayed,
+}
+
+impl ChangeKind {
+    fn from_action(action: &str) -> Self {
+        match action {
+            "CREATE" => ChangeKind::Created,
+            "DELETE" => ChangeKind::Deleted,
+            _ => ChangeKind::Updated,
+        }
+    }
+
+    fn from_replay(action: &str) -> Self {
+        match action {
+            "DELETE" => ChangeKind::Deleted,
+            _ => ChangeKind::Replayed,
+        }
+    }
+}
+
+/// 与 `PE_LIVE_SQL` 字段一致的行
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+struct PeRow {
+    refno: RefnoEnum,
+    noun: String,
+    name: Option<String>,
+    owner: Option<RefnoEnum>,
+    #[serde(default)]
+    order: i64,
+    #[serde(default)]
+    op: i32,
+    #[serde(default)]
+    children_count: i64,
+    status_code: Option<String>,
+}
+
+/// pe 变更
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PeChange {
+    pub kind: ChangeKind,
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub name: String,
+    pub owner: RefnoEnum,
+    /// 在 owner 子节点中的序号
+    pub order: i64,
+    pub children_count: i64,
+    pub status_code: Option<String>,
+}
+
+impl PeChange {
+    fn from_event(event: LiveEvent<PeRow>) -> Self {
+        let (kind, row) = match event {
+            LiveEvent::Live { action, data } => (ChangeKind::from_action(&action), data),
+            LiveEvent::Replayed { action, data } => (ChangeKind::from_replay(&action), data),
+        };
+        // 软删除（op == 2）推送为 UPDATE，统一视为删除
+        let kind = if row.op == 2 {
+            ChangeKind::Deleted
+        } else {
+            kind
+        };
+        Self {
+            kind,
+            refno: row.refno,
+            noun: row.noun,
+            name: row.name.unwrap_or_default(),
+            owner: row.owner.unwrap_or_default(),
+            order: row.order,
+            children_count: row.children_count,
+            status_code: row.status_code,
+        }
+    }
+
+    #[inline]
+    pub fn is_deleted(&self) -> bool {
+        self.kind == ChangeKind::Deleted
+    }
+
+    /// 内容指纹，不含变更类型（回放与实时推送的同一状态指纹相同）
+    fn fingerprint(&self) -> u64 {
+        let mut h = DefaultHasher::new();
+        self.is_deleted().hash(&mut h);
+        self.noun.hash(&mut h);
+        self.name.hash(&mut h);
+        self.owner.hash(&mut h);
+        self.order.hash(&mut h);
+        self.children_count.hash(&mut h);
+        self.status_code.hash(&mut h);
+        h.finish()
+    }
+}
+
+/// pe 订阅过滤条件，各条件之间为“与”，条件为空表示不限
+#[derive(Debug, Clone, Default)]
+pub struct PeFilter {
+    pub nouns: HashSet<String>,
+    /// 参考号高位（ref0）
+    pub ref0s: HashSet<u32>,
+    pub owners: HashSet<RefnoEnum>,
+    /// 是否输出删除事件，默认输出
+    pub skip_deleted: bool,
+}
+
+impl PeFilter {
+    pub fn nouns<I, S>(mut self, nouns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.nouns.extend(nouns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn ref0s(mut self, ref0s: impl IntoIterator<Item = u32>) -> Self {
+        self.ref0s.extend(ref0s);
+        self
+    }
+
+    pub fn owners(mut self, owners: impl IntoIterator<Item = RefnoEnum>) -> Self {
+        self.owners.extend(owners);
+        self
+    }
+
+    pub fn skip_deleted(mut self, skip: bool) -> Self {
+        self.skip_deleted = skip;
+        self
+    }
+
+    pub fn matches(&self, change: &PeChange) -> bool {
+        (self.nouns.is_empty() || self.nouns.contains(&change.noun))
+            && (self.ref0s.is_empty() || self.ref0s.contains(&change.refno.refno().get_0()))
+            && (self.owners.is_empty() || self.owners.contains(&change.owner))
+            && !(self.skip_deleted && change.is_deleted())
+    }
+}
+
+/// 与 `GEOM_LIVE_SQL` 字段一致的行
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+struct GeomRow {
+    id: RefnoEnum,
+    tubi_owner: Option<RefnoEnum>,
+}
+
+/// 几何实例（inst_relate）变更
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GeomChange {
+    pub kind: ChangeKind,
+    pub refno: RefnoEnum,
+    /// 所属 BRAN / HANG，非管道元件为 None
+    pub tubi_owner: Option<RefnoEnum>,
+}
+
+impl GeomChange {
+    fn from_event(event: LiveEvent<GeomRow>) -> Self {
+        let (kind, row) = match event {
+            LiveEvent::Live { action, data } => (ChangeKind::from_action(&action), data),
+            LiveEvent::Replayed { action, data } => (ChangeKind::from_replay(&action), data),
+        };
+        Self {
+            kind,
+            refno: row.id,
+            tubi_owner: row.tubi_owner,
+        }
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut h = DefaultHasher::new();
+        (self.kind == ChangeKind::Deleted).hash(&mut h);
+        self.tubi_owner.hash(&mut h);
+        h.finish()
+    }
+}
+
+/// 几何订阅过滤条件
+#[derive(Debug, Clone, Default)]
+pub struct GeomFilter {
+    /// 参考号高位（ref0）
+    pub ref0s: HashSet<u32>,
+    /// 只输出管道元件（tubi_owner 非空）
+    pub tubi_only: bool,
+}
+
+impl GeomFilter {
+    pub fn ref0s(mut self, ref0s: impl IntoIterator<Item = u32>) -> Self {
+        self.ref0s.extend(ref0s);
+        self
+    }
+
+    pub fn tubi_only(mut self, tubi_only: bool) -> Self {
+        self.tubi_only = tubi_only;
+        self
+    }
+
+    pub fn matches(&self, change: &GeomChange) -> bool {
+        (self.ref0s.is_empty() || self.ref0s.contains(&change.refno.refno().get_0()))
+            && !(self.tubi_only && change.tubi_owner.is_none())
+    }
+}
+
+/// 按元素记录最近一次输出的内容指纹，容量满时淘汰最早的元素
+struct Deduper {
+    seen: HashMap<RefnoEnum, u64>,
+    order: VecDeque<RefnoEnum>,
+    capacity: usize,
+}
+
+impl Deduper {
+    fn new(capacity: usize) -> Self {
+        Self {
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 内容与上次输出相同时返回 false
+    fn check(&mut self, refno: RefnoEnum, fingerprint: u64) -> bool {
+        match self.seen.insert(refno, fingerprint) {
+            Some(prev) => prev != fingerprint,
+            None => {
+                self.order.push_back(refno);
+                if self.order.len() > self.capacity
+                    && let Some(old) = self.order.pop_front()
+                {
+                    self.seen.remove(&old);
+                }
+                true
+            }
+        }
+    }
+}
+
+/// 变更流，drop 时停止订阅
+pub struct LiveStream<T> {
+    rx: mpsc::UnboundedReceiver<T>,
+    manager: Option<LiveQueryManager>,
+}
+
+impl<T> LiveStream<T> {
+    /// 订阅名，可用于查询 [`crate::runtime::health`]
+    pub fn names(&self) -> Vec<String> {
+        self.manager.as_ref().map(|m| m.names()).unwrap_or_default()
+    }
+
+    /// 停止订阅并等待监管任务退出
+    pub async fn close(mut self) {
+        if let Some(manager) = self.manager.take() {
+            manager.shutdown().await;
+        }
+    }
+}
+
+impl<T> Stream for LiveStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+fn spawn_stream<R, T>(
+    mut sub: LiveSubscription,
+    convert: impl Fn(LiveEvent<R>) -> T + Send + Sync + 'static,
+    accept: impl Fn(&T) -> bool + Send + Sync + 'static,
+    key: impl Fn(&T) -> (RefnoEnum, u64) + Send + Sync + 'static,
+) -> LiveStream<T>
+where
+    R: SurrealValue + Send + 'static,
+    T: Send + 'static,
+{
+    sub.name = format!(
+        "{}#{}",
+        sub.name,
+        NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    let (tx, rx) = mpsc::unbounded_channel();
+    let dedup = Mutex::new(Deduper::new(DEDUP_CAPACITY));
+    let mut manager = LiveQueryManager::default();
+    manager.subscribe::<R>(
+        sub,
+        Arc::new(move |event| {
+            let change = convert(event);
+            if !accept(&change) {
+                return;
+            }
+            let (refno, fingerprint) = key(&change);
+            if dedup.lock().check(refno, fingerprint) {
+                // 接收端已关闭时忽略，监管任务随 manager drop 退出
+                let _ = tx.send(change);
+            }
+        }),
+    );
+    LiveStream {
+        rx,
+        manager: Some(manager),
+    }
+}
+
+/// 订阅 pe 变更
+pub fn subscribe_pe(filter: PeFilter) -> LiveStream<PeChange> {
+    subscribe_pe_since(filter, None)
+}
+
+/// 订阅 pe 变更，并先回放 `since` 水位（sesno）之后的变更
+///
+/// 用于进程重启后续接：传入上次处理到的水位（见订阅健康信息中的 `watermark`）
+pub fn subscribe_pe_since(filter: PeFilter, since: Option<i64>) -> LiveStream<PeChange> {
+    spawn_stream(
+        LiveSubscription {
+            name: "pe_stream".to_string(),
+            initial_watermark: since,
+            ..LiveSubscription::pe()
+        },
+        PeChange::from_event,
+        move |c| filter.matches(c),
+        |c| (c.refno, c.fingerprint()),
+    )
+}
+
+/// 订阅几何实例变更
+pub fn subscribe_geom(filter: GeomFilter) -> LiveStream<GeomChange> {
+    spawn_stream(
+        LiveSubscription {
+            name: "geom_stream".to_string(),
+            ..LiveSubscription::geom()
+        },
+        GeomChange::from_event,
+        move |c| filter.matches(c),
+        |c| (c.refno, c.fingerprint()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn change(kind: ChangeKind, name: &str) -> PeChange {
+        PeChange {
+            kind,
+            refno: RefnoEnum::Refno(RefU64::from_two_nums(17496, 1)),
+            noun: "EQUI".to_string(),
+            name: name.to_string(),
+            owner: RefnoEnum::default(),
+            order: 0,
+            children_count: 0,
+            status_code: None,
+        }
+    }
+
+    #[test]
+    fn test_dedup_replay_overlap() {
+        let mut dedup = Deduper::new(8);
+        let live = change(ChangeKind::Updated, "/E1");
+        let replayed = change(ChangeKind::Replayed, "/E1");
+        assert!(dedup.check(live.refno, live.fingerprint()));
+        // 回放出与已推送内容相同的记录
+        assert!(!dedup.check(replayed.refno, replayed.fingerprint()));
+
+        let renamed = change(ChangeKind::Updated, "/E2");
+        assert!(dedup.check(renamed.refno, renamed.fingerprint()));
+        let deleted = change(ChangeKind::Deleted, "/E2");
+        assert!(dedup.check(deleted.refno, deleted.fingerprint()));
+    }
+
+    #[test]
+    fn test_replayed_delete_kind() {
+        let geom = GeomChange::from_event(LiveEvent::Replayed {
+            action: "DELETE".to_string(),
+            data: GeomRow {
+                id: RefnoEnum::Refno(RefU64::from_two_nums(17496, 1)),
+                tubi_owner: None,
+            },
+        });
+        assert_eq!(geom.kind, ChangeKind::Deleted);
+        assert_eq!(ChangeKind::from_replay("UPDATE"), ChangeKind::Replayed);
+    }
+
+    #[test]
+    fn test_pe_filter() {
+        let c = change(ChangeKind::Deleted, "/E1");
+        assert!(PeFilter::default().matches(&c));
+        assert!(
+            PeFilter::default()
+                .nouns(["EQUI"])
+                .ref0s([17496])
+                .matches(&c)
+        );
+        assert!(!PeFilter::default().nouns(["PIPE"]).matches(&c));
+        assert!(!PeFilter::default().skip_deleted(true).matches(&c));
+    }
+}
@@ -0,0 +1,353 @@
+//! 受监管的实时订阅
+//!
+//! websocket 断开后 live query 会静默失效。[`LiveQueryManager`] 为每个订阅启动一个监管任务：
+//! 1. 建立订阅前记录当前会话水位（pe 的最大 sesno）
+//! 2. 用补齐查询回放上次水位之后的变更（含删除），弥补断线期间遗漏的数据
+//! 3. 持续读取订阅流，流结束或出错时按退避间隔重连；连接已失效时先重建 `SUL_DB` 的主连接
+//!
+//! 订阅状态写入 [`crate::runtime::health`]。
+
+use crate::live::{GEOM_LIVE_SQL, PE_LIVE_SQL};
+use crate::runtime::health::{SubscriptionState, remove_subscription_health, update_subscription_health};
+use crate::{SUL_DB, SurrealQueryExt};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use surrealdb::types::SurrealValue;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// `pe` 的补齐查询，与 [`PE_LIVE_SQL`] 字段一致
+pub const PE_CATCH_UP_SQL: &str = r#"
+    select refno,
+        noun,
+        if op == 2 { noun } else { fn::default_name(id) } as name,
+        owner,
+        if (->pe_owner.id)[0] == None { 0 } else { record::id(->pe_owner.id[0])[1] } as order,
+        op?:0 as op,
+        children_updated,
+        array::len(select value id from <-pe_owner) as children_count,
+        status_code
+    from pe
+    where !type::is_array(record::id(id)) and sesno > $watermark and op != 2 and !deleted
+"#;
+
+/// `pe` 的删除补齐查询（`op == 2` 或 `deleted`），字段与 [`PE_CATCH_UP_SQL`] 一致
+pub const PE_CATCH_UP_DELETED_SQL: &str = r#"
+    select refno,
+        noun,
+        noun as name,
+        owner,
+        0 as order,
+        op?:0 as op,
+        children_updated,
+        0 as children_count,
+        status_code
+    from pe
+    where !type::is_array(record::id(id)) and sesno > $watermark and (op == 2 or deleted)
+"#;
+
+/// `inst_relate` 的补齐查询，与 [`GEOM_LIVE_SQL`] 字段一致
+pub const GEOM_CATCH_UP_SQL: &str = r#"
+    select id,
+        (
+            if in.owner.noun in ['BRAN', 'HANG'] { in.owner }
+            else { none }
+        ) as tubi_owner
+    from inst_relate
+    where solid and aabb != none and in.sesno > $watermark
+"#;
+
+/// 几何的删除补齐查询：已删除元素的 inst_relate 随之删除，只能从 pe 找回
+pub const GEOM_CATCH_UP_DELETED_SQL: &str = r#"
+    select refno as id, none as tubi_owner
+    from pe
+    where !type::is_array(record::id(id)) and sesno > $watermark and (op == 2 or deleted)
+"#;
+
+/// 当前会话水位，走 `sesno_index` 取最大值，避免全表聚合
+const WATERMARK_SQL: &str = "SELECT VALUE sesno FROM pe WHERE sesno != NONE ORDER BY sesno DESC LIMIT 1";
+
+/// 订阅事件
+#[derive(Debug, Clone)]
+pub enum LiveEvent<T> {
+    /// 实时推送，`action` 为 CREATE/UPDATE/DELETE
+    Live { action: String, data: T },
+    /// 重连后补齐回放的记录，`action` 为 UPDATE 或 DELETE
+    Replayed { action: String, data: T },
+}
+
+/// 事件处理函数
+pub type LiveHandler<T> = Arc<dyn Fn(LiveEvent<T>) + Send + Sync>;
+
+/// 订阅定义
+#[derive(Debug, Clone)]
+pub struct LiveSubscription {
+    /// 订阅名，也是健康信息的键
+    pub name: String,
+    pub live_sql: String,
+    /// 补齐查询，使用 `$watermark` 绑定上次水位；为空时不回放
+    pub catch_up_sql: Option<String>,
+    /// 删除补齐查询，结果以 DELETE 回放；为空时不回放删除
+    pub catch_up_deleted_sql: Option<String>,
+    /// 首次订阅时的起始水位，设置后首次建立订阅即回放此后的变更
+    pub initial_watermark: Option<i64>,
+}
+
+impl LiveSubscription {
+    pub fn pe() -> Self {
+        Self {
+            name: "pe".to_string(),
+            live_sql: PE_LIVE_SQL.to_string(),
+            catch_up_sql: Some(PE_CATCH_UP_SQL.to_string()),
+            catch_up_deleted_sql: Some(PE_CATCH_UP_DELETED_SQL.to_string()),
+            initial_watermark: None,
+        }
+    }
+
+    pub fn geom() -> Self {
+        Self {
+            name: "inst_relate".to_string(),
+            live_sql: GEOM_LIVE_SQL.to_string(),
+            catch_up_sql: Some(GEOM_CATCH_UP_SQL.to_string()),
+            catch_up_deleted_sql: Some(GEOM_CATCH_UP_DELETED_SQL.to_string()),
+            initial_watermark: None,
+        }
+    }
+}
+
+/// 重连参数
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// 连续失败次数上限，`None` 表示无限重试
+    pub max_retries: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_retries: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次（从 1 开始）重连前的等待时间，指数退避
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1).min(16));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+async fn query_watermark() -> anyhow::Result<i64> {
+    let w: Vec<i64> = SUL_DB.query_take(WATERMARK_SQL, 0).await?;
+    Ok(w.first().copied().unwrap_or_default())
+}
+
+/// 订阅中断后检查连接，失效时重建 `SUL_DB` 的主连接（live query 跑在主连接上）
+async fn ensure_connected() -> anyhow::Result<()> {
+    if SUL_DB.primary().health().await.is_ok() {
+        return Ok(());
+    }
+    SUL_DB.reconnect_primary().await
+}
+
+/// 执行补齐查询并以 `action` 回放，返回回放条数
+async fn replay<T>(
+    sub: &LiveSubscription,
+    handler: &LiveHandler<T>,
+    sql: &str,
+    action: &str,
+    watermark: i64,
+) -> anyhow::Result<usize>
+where
+    T: SurrealValue + Send + 'static,
+{
+    let rows: Vec<T> = SUL_DB
+        .primary()
+        .query(sql)
+        .bind(("watermark", watermark))
+        .await?
+        .take(0)?;
+    let count = rows.len();
+    for row in rows {
+        crate::metrics::observe_live_event(&sub.name, "replayed");
+        handler(LiveEvent::Replayed {
+            action: action.to_string(),
+            data: row,
+        });
+    }
+    Ok(count)
+}
+
+/// 运行一次订阅，直到收到停止信号（返回 Ok）或流中断（返回 Err）
+async fn run_once<T>(
+    sub: &LiveSubscription,
+    handler: &LiveHandler<T>,
+    watermark: &mut Option<i64>,
+    established: &mut bool,
+    stop: &mut watch::Receiver<bool>,
+) -> anyhow::Result<()>
+where
+    T: SurrealValue + Send + 'static,
+{
+    update_subscription_health(&sub.name, |h| h.state = SubscriptionState::Connecting);
+    let current = query_watermark().await?;
+    let mut response = SUL_DB.primary().query(&sub.live_sql).await?;
+    let mut stream = response.stream::<surrealdb::Notification<T>>(0)?;
+
+    // 订阅建立后再回放，避免回放与订阅之间的变更丢失（可能重复，由处理方幂等处理）
+    let mut replayed = 0;
+    if let Some(last) = *watermark
+        && current > last
+    {
+        if let Some(sql) = &sub.catch_up_sql {
+            replayed += replay(sub, handler, sql, "UPDATE", last).await?;
+        }
+        if let Some(sql) = &sub.catch_up_deleted_sql {
+            replayed += replay(sub, handler, sql, "DELETE", last).await?;
+        }
+    }
+    *watermark = Some(current);
+    *established = true;
+    update_subscription_health(&sub.name, |h| {
+        h.state = SubscriptionState::Active;
+        h.watermark = current;
+        h.replayed = replayed;
+        h.last_error = None;
+    });
+
+    loop {
+        tokio::select! {
+            _ = stop.changed() => return Ok(()),
+            item = stream.next() => match item {
+                Some(Ok(n)) => {
+                    update_subscription_health(&sub.name, |h| h.last_event = Some(SystemTime::now()));
+                    crate::metrics::observe_live_event(&sub.name, "live");
+                    handler(LiveEvent::Live {
+                        action: format!("{:?}", n.action).to_uppercase(),
+                        data: n.data,
+                    });
+                }
+                Some(Err(e)) => return Err(e.into()),
+                None => return Err(anyhow::anyhow!("订阅流已结束")),
+            }
+        }
+    }
+}
+
+/// 监管任务：断线后按策略重连并补齐
+async fn supervise<T>(
+    sub: LiveSubscription,
+    handler: LiveHandler<T>,
+    policy: ReconnectPolicy,
+    mut stop: watch::Receiver<bool>,
+) where
+    T: SurrealValue + Send + 'static,
+{
+    let mut watermark: Option<i64> = sub.initial_watermark;
+    let mut failures = 0u32;
+    loop {
+        let mut established = false;
+        match run_once(&sub, &handler, &mut watermark, &mut established, &mut stop).await {
+            Ok(_) => break,
+            Err(e) => {
+                // 订阅曾恢复正常时重新计数
+                if established {
+                    failures = 0;
+                }
+                failures += 1;
+                log::warn!("实时订阅 {} 中断: {}", sub.name, e);
+                if let Err(e) = ensure_connected().await {
+                    log::warn!("实时订阅 {} 重建连接失败: {}", sub.name, e);
+                }
+                update_subscription_health(&sub.name, |h| {
+                    h.state = SubscriptionState::Reconnecting;
+                    h.reconnects += 1;
+                    h.last_error = Some(e.to_string());
+                });
+                if policy.max_retries.is_some_and(|m| failures > m) {
+                    break;
+                }
+            }
+        }
+        tokio::select! {
+            _ = stop.changed() => break,
+            _ = tokio::time::sleep(policy.backoff(failures)) => {}
+        }
+    }
+    update_subscription_health(&sub.name, |h| h.state = SubscriptionState::Stopped);
+}
+
+/// 实时订阅管理器
+pub struct LiveQueryManager {
+    policy: ReconnectPolicy,
+    stop_tx: watch::Sender<bool>,
+    tasks: Vec<(String, JoinHandle<()>)>,
+}
+
+impl Default for LiveQueryManager {
+    fn default() -> Self {
+        Self::new(ReconnectPolicy::default())
+    }
+}
+
+impl LiveQueryManager {
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        let (stop_tx, _) = watch::channel(false);
+        Self {
+            policy,
+            stop_tx,
+            tasks: vec![],
+        }
+    }
+
+    /// 启动受监管的订阅
+    pub fn subscribe<T>(&mut self, sub: LiveSubscription, handler: LiveHandler<T>)
+    where
+        T: SurrealValue + Send + 'static,
+    {
+        let name = sub.name.clone();
+        update_subscription_health(&name, |h| h.state = SubscriptionState::Connecting);
+        let handle = tokio::spawn(supervise(
+            sub,
+            handler,
+            self.policy.clone(),
+            self.stop_tx.subscribe(),
+        ));
+        self.tasks.push((name, handle));
+    }
+
+    /// 已启动的订阅名
+    pub fn names(&self) -> Vec<String> {
+        self.tasks.iter().map(|(n, _)| n.clone()).collect()
+    }
+
+    /// 停止全部订阅并等待监管任务退出
+    pub async fn shutdown(mut self) {
+        let _ = self.stop_tx.send(true);
+        for (name, handle) in self.tasks.drain(..) {
+            let _ = handle.await;
+            remove_subscription_health(&name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(10), Duration::from_secs(30));
+    }
+}
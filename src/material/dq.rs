@@ -1,6 +1,8 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::save_material_value;
 #[cfg(feature = "sql")]
 use super::query::save_material_value_test;
@@ -154,34 +156,17 @@ pub async fn save_dq_material(refno: RefU64) -> Vec<JoinHandle<()>> {
             }
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &DQ_TABLE_NAME, &FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &DQ_TABLE_NAME,
-                                    &BRAN_DATA_FIELDS,
-                                    &DQ_CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_dq_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -194,6 +179,37 @@ pub async fn save_dq_material(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 电气专业托盘及接地同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_dq_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, DQ_TABLE_NAME, &FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(
+            pool,
+            DQ_TABLE_NAME,
+            &BRAN_DATA_FIELDS,
+            &DQ_CHINESE_FIELDS,
+            rows,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// 查询电气专业托盘及接地数据，并补充材料表中的标准号、单位和物项编号
+pub async fn get_dq_material_rows(
+    db: Surreal<Any>,
+    refnos: Vec<RefU64>,
+) -> anyhow::Result<Vec<HashMap<String, Value>>> {
+    let mut rows = get_dq_bran_list(db, refnos).await?;
+    let material_data = read_dq_material_excel().unwrap_or_default();
+    get_dq_value_from_material(&material_data, &mut rows);
+    Ok(rows)
+}
+
 /// 读取电气专业材料表
 fn read_dq_material_excel() -> anyhow::Result<HashMap<String, Vec<DqMaterial>>> {
     let mut map = HashMap::new();
@@ -0,0 +1,117 @@
+use super::{MaterialExporter, MaterialSheet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// CSV 导出，每个表单一个文件
+///
+/// 只有一个表单时直接写到 `path`；多个表单时写到 `{path 的文件名}_{表单名}.csv`。
+#[derive(Debug, Clone)]
+pub struct CsvExporter {
+    pub delimiter: char,
+    /// 写入 UTF-8 BOM，避免 Excel 打开中文乱码
+    pub bom: bool,
+}
+
+impl Default for CsvExporter {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            bom: true,
+        }
+    }
+}
+
+impl CsvExporter {
+    fn escape(&self, field: &str) -> String {
+        if field.contains(self.delimiter)
+            || field.contains('"')
+            || field.contains('\n')
+            || field.contains('\r')
+        {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn write_line(&self, out: &mut impl Write, fields: &[String]) -> std::io::Result<()> {
+        let sep = self.delimiter.to_string();
+        let line = fields
+            .iter()
+            .map(|f| self.escape(f))
+            .collect::<Vec<_>>()
+            .join(&sep);
+        out.write_all(line.as_bytes())?;
+        out.write_all(b"\r\n")
+    }
+
+    /// 写出单个表单
+    pub fn write_sheet(&self, sheet: &MaterialSheet, out: &mut impl Write) -> std::io::Result<()> {
+        if self.bom {
+            out.write_all(b"\xEF\xBB\xBF")?;
+        }
+        self.write_line(out, &sheet.headers)?;
+        for row in &sheet.rows {
+            self.write_line(out, row)?;
+        }
+        Ok(())
+    }
+}
+
+impl MaterialExporter for CsvExporter {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, sheets: &[MaterialSheet], path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = Vec::with_capacity(sheets.len());
+        for sheet in sheets {
+            let file = if sheets.len() == 1 {
+                path.to_path_buf()
+            } else {
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                path.with_file_name(format!("{}_{}.{}", stem, sheet.name, self.extension()))
+            };
+            let mut out = std::io::BufWriter::new(std::fs::File::create(&file)?);
+            self.write_sheet(sheet, &mut out)?;
+            out.flush()?;
+            files.push(file);
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::export::{ExportOptions, MaterialTable};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_csv_sheet() {
+        let records: Vec<HashMap<String, serde_json::Value>> = vec![
+            serde_json::from_value(json!({"id": "17496_1", "code": "A,1", "count": 2})).unwrap(),
+            serde_json::from_value(json!({"id": "17496_2", "code": "B\"2"})).unwrap(),
+        ];
+        let options = ExportOptions::default()
+            .with_columns(MaterialTable::GyList, ["code", "id", "count"])
+            .with_header("count", "数量（个）");
+        let sheet = MaterialSheet::from_records(MaterialTable::GyList, &records, &options);
+        assert_eq!(sheet.headers, vec!["编码", "参考号", "数量（个）"]);
+
+        let exporter = CsvExporter {
+            bom: false,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        exporter.write_sheet(&sheet, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "编码,参考号,数量（个）\r\n\"A,1\",17496_1,2\r\n\"B\"\"2\",17496_2,\r\n"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! 材料表单导出
+//!
+//! 材料表单保存在 SurrealDB 的 `material_*` 表中，这里将其导出为下游常用的文件格式：
+//! - [`CsvExporter`]：每个表单一个 CSV 文件（UTF-8 BOM，Excel 可直接打开）
+//! - `XlsxExporter`（`xlsx` 特性）：每个表单一个工作表
+//!
+//! 列顺序和表头文字通过 [`ExportOptions`] 配置。
+
+mod csv;
+#[cfg(feature = "xlsx")]
+mod xlsx;
+
+pub use csv::CsvExporter;
+#[cfg(feature = "xlsx")]
+pub use xlsx::XlsxExporter;
+
+use super::MatMajorType;
+use crate::SUL_DB;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use strum_macros::{AsRefStr, Display, EnumIter, EnumString};
+
+/// 材料表单对应的 SurrealDB 表
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display, AsRefStr, EnumIter)]
+pub enum MaterialTable {
+    #[strum(serialize = "material_gy_list")]
+    GyList,
+    #[strum(serialize = "material_gy_list_tubi")]
+    GyListTubi,
+    #[strum(serialize = "material_gy_equi")]
+    GyEqui,
+    #[strum(serialize = "material_gy_valv")]
+    GyValv,
+    #[strum(serialize = "material_inst_list")]
+    InstList,
+    #[strum(serialize = "material_inst_pipe")]
+    InstPipe,
+    #[strum(serialize = "material_inst_equi")]
+    InstEqui,
+    #[strum(serialize = "material_hvac_pipe")]
+    HvacPipe,
+    #[strum(serialize = "material_elec_list")]
+    ElecList,
+    #[strum(serialize = "material_tx_list")]
+    TxList,
+    #[strum(serialize = "material_gps_list")]
+    GpsList,
+    #[strum(serialize = "material_gps_list_tubi")]
+    GpsListTubi,
+    #[strum(serialize = "material_sb_list")]
+    SbList,
+    #[strum(serialize = "material_nt_valv")]
+    NtValv,
+}
+
+impl MaterialTable {
+    /// 表单名，用作工作表名/文件名
+    pub fn title(&self) -> &'static str {
+        match self {
+            MaterialTable::GyList => "工艺布置专业_大宗材料",
+            MaterialTable::GyListTubi => "工艺布置专业_管道",
+            MaterialTable::GyEqui => "工艺布置专业_设备清单",
+            MaterialTable::GyValv => "工艺布置专业_阀门清单",
+            MaterialTable::InstList => "仪控专业_大宗材料",
+            MaterialTable::InstPipe => "仪控专业_仪表管道",
+            MaterialTable::InstEqui => "仪控专业_设备清单",
+            MaterialTable::HvacPipe => "通风专业_风管管段",
+            MaterialTable::ElecList => "电气专业_托盘及接地",
+            MaterialTable::TxList => "通信专业_通信系统",
+            MaterialTable::GpsList => "给排水专业_大宗材料",
+            MaterialTable::GpsListTubi => "给排水专业_管道",
+            MaterialTable::SbList => "设备专业_大宗材料",
+            MaterialTable::NtValv => "暖通专业_阀门清单",
+        }
+    }
+
+    /// 专业内的清单名（不带专业前缀），如 `大宗材料`
+    pub fn list_name(&self) -> &'static str {
+        match self {
+            MaterialTable::GyList
+            | MaterialTable::InstList
+            | MaterialTable::GpsList
+            | MaterialTable::SbList => "大宗材料",
+            MaterialTable::GyListTubi | MaterialTable::GpsListTubi => "管道",
+            MaterialTable::GyEqui | MaterialTable::InstEqui => "设备清单",
+            MaterialTable::GyValv | MaterialTable::NtValv => "阀门清单",
+            MaterialTable::InstPipe => "仪表管道",
+            MaterialTable::HvacPipe => "风管管段",
+            MaterialTable::ElecList => "托盘及接地",
+            MaterialTable::TxList => "通信系统",
+        }
+    }
+}
+
+impl MatMajorType {
+    /// 专业对应的材料表
+    pub fn tables(&self) -> &'static [MaterialTable] {
+        match self {
+            MatMajorType::GyDz => &[MaterialTable::GyList, MaterialTable::GyListTubi],
+            MatMajorType::GyEquip => &[MaterialTable::GyEqui],
+            MatMajorType::Dq => &[MaterialTable::ElecList],
+            MatMajorType::Yk => &[
+                MaterialTable::InstList,
+                MaterialTable::InstPipe,
+                MaterialTable::InstEqui,
+            ],
+        }
+    }
+}
+
+/// 表头语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderLocale {
+    /// 使用数据库字段名
+    Raw,
+    /// 使用中文表头，未收录的字段保留字段名
+    #[default]
+    Zh,
+}
+
+/// 内置的中文表头（字段名 -> 表头），各专业写入 MySQL 的材料表也从这里取表头
+const ZH_HEADERS: &[(&str, &str)] = &[
+    ("id", "参考号"),
+    ("code", "编码"),
+    ("noun", "类型"),
+    ("count", "数量"),
+    ("length", "数量"),
+    ("name", "名称"),
+    ("room_code", "所在房间号"),
+    ("nozz_name", "管口号"),
+    ("nozz_pos", "管口坐标"),
+    ("nozz_cref", "相连管道编号"),
+    ("valv_name", "阀门位号"),
+    ("valv_belong", "阀门归属"),
+    ("bran_name", "阀门归属"),
+    ("valv_length", "阀门长度"),
+    ("valv_weight", "阀门重量"),
+    ("valv_size", "阀门尺寸"),
+    ("valv_use", "阀门功能"),
+    ("valv_x", "阀门重心X"),
+    ("valv_y", "阀门重心Y"),
+    ("valv_z", "阀门重心Z"),
+    ("valv_supp", "是否阀门支架"),
+    ("act_type", "执行机构类型"),
+    ("act_fail_pos", "故障位置"),
+    ("act_power", "执行机构动力"),
+    ("material", "材料"),
+    ("unit", "单位"),
+    ("weight", "单重（kg）"),
+    ("total_weight", "总重（kg）"),
+];
+
+/// 字段在指定表单中的中文表头，表单专用的表头优先于 [`ZH_HEADERS`]
+pub fn zh_header(table: MaterialTable, field: &str) -> Option<&'static str> {
+    let special = match (table, field) {
+        (MaterialTable::GyEqui, "name") => Some("设备位号"),
+        (MaterialTable::NtValv, "name") => Some("阀门位号"),
+        (MaterialTable::NtValv, "material") => Some("阀门材质"),
+        _ => None,
+    };
+    special.or_else(|| {
+        ZH_HEADERS
+            .iter()
+            .find(|(f, _)| *f == field)
+            .map(|(_, h)| *h)
+    })
+}
+
+/// 按 [`zh_header`] 构建字段到中文表头的映射，未收录的字段不出现在映射中
+pub(crate) fn zh_header_map(
+    table: MaterialTable,
+    fields: &[&'static str],
+) -> HashMap<&'static str, &'static str> {
+    fields
+        .iter()
+        .filter_map(|f| zh_header(table, f).map(|h| (*f, h)))
+        .collect()
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub locale: HeaderLocale,
+    /// 各表的列顺序；未配置的表按字段名排序，并把 `id` 放在首列
+    pub columns: HashMap<MaterialTable, Vec<String>>,
+    /// 自定义表头，优先于内置表头
+    pub headers: HashMap<String, String>,
+}
+
+impl ExportOptions {
+    pub fn with_locale(mut self, locale: HeaderLocale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn with_columns<S: Into<String>>(
+        mut self,
+        table: MaterialTable,
+        columns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.columns
+            .insert(table, columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_header(mut self, field: impl Into<String>, header: impl Into<String>) -> Self {
+        self.headers.insert(field.into(), header.into());
+        self
+    }
+
+    /// 字段在表单中对应的表头
+    pub fn header(&self, table: MaterialTable, field: &str) -> String {
+        if let Some(h) = self.headers.get(field) {
+            return h.clone();
+        }
+        match self.locale {
+            HeaderLocale::Zh => zh_header(table, field).map(str::to_string),
+            HeaderLocale::Raw => None,
+        }
+        .unwrap_or_else(|| field.to_string())
+    }
+}
+
+/// 一个导出的表单
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterialSheet {
+    pub name: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(arr) => arr.iter().map(cell_text).collect::<Vec<_>>().join(","),
+        v => v.to_string(),
+    }
+}
+
+impl MaterialSheet {
+    /// 按选项的列顺序和表头构建表单
+    pub fn from_records(
+        table: MaterialTable,
+        records: &[HashMap<String, Value>],
+        options: &ExportOptions,
+    ) -> Self {
+        let columns = match options.columns.get(&table) {
+            Some(columns) => columns.clone(),
+            None => {
+                let mut columns = records
+                    .iter()
+                    .flat_map(|r| r.keys().cloned())
+                    .collect::<std::collections::BTreeSet<_>>()
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                if let Some(pos) = columns.iter().position(|c| c == "id") {
+                    let id = columns.remove(pos);
+                    columns.insert(0, id);
+                }
+                columns
+            }
+        };
+        let rows = records
+            .iter()
+            .map(|r| {
+                columns
+                    .iter()
+                    .map(|c| r.get(c).map(cell_text).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        Self {
+            name: table.title().to_string(),
+            headers: columns.iter().map(|c| options.header(table, c)).collect(),
+            rows,
+        }
+    }
+}
+
+/// 材料表单导出器
+pub trait MaterialExporter {
+    /// 输出文件扩展名
+    fn extension(&self) -> &'static str;
+
+    /// 导出表单，返回写出的文件
+    ///
+    /// `path` 为输出文件路径，多表单格式以外的实现可据此派生每个表单的文件名。
+    fn export(&self, sheets: &[MaterialSheet], path: &Path) -> anyhow::Result<Vec<PathBuf>>;
+}
+
+/// 读取材料表的全部记录
+pub async fn query_material_records(
+    table: MaterialTable,
+) -> anyhow::Result<Vec<HashMap<String, Value>>> {
+    let sql = format!("select *, record::id(id) as id from {}", table.as_ref());
+    let mut response = SUL_DB.query(&sql).await?;
+    Ok(response.take::<Vec<HashMap<String, Value>>>(0)?)
+}
+
+/// 导出指定的材料表
+pub async fn export_material_tables(
+    tables: &[MaterialTable],
+    exporter: &dyn MaterialExporter,
+    options: &ExportOptions,
+    path: impl AsRef<Path>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut sheets = Vec::with_capacity(tables.len());
+    for &table in tables {
+        let records = query_material_records(table).await?;
+        sheets.push(MaterialSheet::from_records(table, &records, options));
+    }
+    exporter.export(&sheets, path.as_ref())
+}
+
+/// 导出某个专业的全部材料表
+pub async fn export_major(
+    major: MatMajorType,
+    exporter: &dyn MaterialExporter,
+    options: &ExportOptions,
+    path: impl AsRef<Path>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    export_material_tables(major.tables(), exporter, options, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zh_header_per_table() {
+        assert_eq!(zh_header(MaterialTable::GyList, "name"), Some("名称"));
+        assert_eq!(zh_header(MaterialTable::GyEqui, "name"), Some("设备位号"));
+        assert_eq!(zh_header(MaterialTable::NtValv, "name"), Some("阀门位号"));
+        assert_eq!(zh_header(MaterialTable::NtValv, "material"), Some("阀门材质"));
+        assert_eq!(zh_header(MaterialTable::GyList, "material"), Some("材料"));
+        assert_eq!(zh_header(MaterialTable::GyList, "unknown"), None);
+
+        let map = zh_header_map(MaterialTable::NtValv, &["id", "bran_name", "unknown"]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["bran_name"], "阀门归属");
+    }
+}
@@ -0,0 +1,49 @@
+use super::{MaterialExporter, MaterialSheet};
+use rust_xlsxwriter::{Format, Workbook};
+use std::path::{Path, PathBuf};
+
+/// Excel 工作表名最长 31 个字符
+const MAX_SHEET_NAME: usize = 31;
+
+/// XLSX 导出，所有表单写到同一个工作簿，每个表单一个工作表
+#[derive(Debug, Clone, Default)]
+pub struct XlsxExporter;
+
+impl MaterialExporter for XlsxExporter {
+    fn extension(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn export(&self, sheets: &[MaterialSheet], path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut workbook = Workbook::new();
+        let header_format = Format::new().set_bold();
+        for sheet in sheets {
+            let worksheet = workbook.add_worksheet();
+            let name = sheet
+                .name
+                .chars()
+                .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+                .take(MAX_SHEET_NAME)
+                .collect::<String>();
+            worksheet.set_name(&name)?;
+            for (col, header) in sheet.headers.iter().enumerate() {
+                worksheet.write_string_with_format(0, col as u16, header, &header_format)?;
+            }
+            for (row, values) in sheet.rows.iter().enumerate() {
+                for (col, value) in values.iter().enumerate() {
+                    let (row, col) = (row as u32 + 1, col as u16);
+                    // 数值列按数字写入，便于在 Excel 中求和；保留编码类的前导零
+                    let leading_zero = value.len() > 1 && value.starts_with('0') && !value.starts_with("0.");
+                    match value.parse::<f64>() {
+                        Ok(n) if n.is_finite() && !leading_zero => {
+                            worksheet.write_number(row, col, n)?
+                        }
+                        _ => worksheet.write_string(row, col, value)?,
+                    };
+                }
+            }
+        }
+        workbook.save(path)?;
+        Ok(vec![path.to_path_buf()])
+    }
+}
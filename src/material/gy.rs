@@ -1,10 +1,13 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::{save_material_data_to_mysql, save_two_material_data_to_mysql};
 #[cfg(feature = "sql")]
 use crate::db_pool;
 use crate::material::define_core_material_surreal_funtions;
+use crate::material::export::{MaterialTable, zh_header_map};
 #[cfg(feature = "sql")]
 use crate::material::query::save_material_value_test;
 use crate::{
@@ -19,6 +22,7 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use surrealdb::Surreal;
 use surrealdb::engine::any::Any;
+use surrealdb::types::SurrealValue;
 use tokio::task::{self, JoinHandle};
 
 const DZ_COLUMNS: [&str; 17] = [
@@ -42,39 +46,32 @@ const DZ_COLUMNS: [&str; 17] = [
 ];
 
 lazy_static! {
-    static ref DZ_CHINESE_FIELDS: HashMap<&'static str, &'static str> = {
-        let mut map = HashMap::new();
-        map.entry("id").or_insert("参考号");
-        map.entry("code").or_insert("编码");
-        map.entry("noun").or_insert("类型");
-        map.entry("count").or_insert("数量");
-        map.entry("length").or_insert("数量");
-        map
-    };
-    static ref EQUI_CHINESE_FIELDS: HashMap<&'static str, &'static str> = {
-        let mut map = HashMap::new();
-        map.entry("id").or_insert("参考号");
-        map.entry("name").or_insert("设备位号");
-        map.entry("room_code").or_insert("所在房间号");
-        map.entry("nozz_name").or_insert("管口号");
-        map.entry("nozz_pos").or_insert("管口坐标");
-        map.entry("nozz_cref").or_insert("相连管道编号");
-        map
-    };
-    static ref VALV_CHINESE_FIELDS: HashMap<&'static str, &'static str> = {
-        let mut map = HashMap::new();
-        map.entry("id").or_insert("参考号");
-        map.entry("valv_name").or_insert("阀门位号");
-        map.entry("room_code").or_insert("所在房间号");
-        map.entry("valv_belong").or_insert("阀门归属");
-        map.entry("valv_length").or_insert("阀门长度");
-        map.entry("valv_weight").or_insert("阀门重量");
-        map.entry("valv_x").or_insert("阀门重心X");
-        map.entry("valv_y").or_insert("阀门重心Y");
-        map.entry("valv_z").or_insert("阀门重心Z");
-        map.entry("valv_supp").or_insert("是否阀门支架");
-        map
-    };
+    static ref DZ_CHINESE_FIELDS: HashMap<&'static str, &'static str> = zh_header_map(
+        MaterialTable::GyList,
+        &["id", "code", "noun", "count", "length"],
+    );
+    static ref EQUI_CHINESE_FIELDS: HashMap<&'static str, &'static str> = zh_header_map(
+        MaterialTable::GyEqui,
+        &["id", "name", "room_code", "nozz_name", "nozz_pos", "nozz_cref"],
+    );
+    static ref VALV_CHINESE_FIELDS: HashMap<&'static str, &'static str> = zh_header_map(
+        MaterialTable::GyValv,
+        &[
+            "id",
+            "valv_name",
+            "room_code",
+            "valv_belong",
+            "valv_length",
+            "valv_weight",
+            "valv_x",
+            "valv_y",
+            "valv_z",
+            "valv_supp",
+            "act_type",
+            "act_fail_pos",
+            "act_power",
+        ],
+    );
 }
 
 const TABLE: &'static str = "工艺布置专业_大宗材料";
@@ -98,7 +95,7 @@ const EQ_DATA_FIELDS: [&'static str; 6] = [
     "nozz_cref",
 ];
 
-const VALVE_FIELDS: [&'static str; 10] = [
+const VALVE_FIELDS: [&'static str; 13] = [
     "参考号",
     "阀门位号",
     "所在房间号",
@@ -109,9 +106,12 @@ const VALVE_FIELDS: [&'static str; 10] = [
     "阀门重心Y",
     "阀门重心Z",
     "是否阀门支架",
+    "执行机构类型",
+    "故障位置",
+    "执行机构动力",
 ];
 
-const VALVE_DATA_FIELDS: [&'static str; 10] = [
+const VALVE_DATA_FIELDS: [&'static str; 13] = [
     "id",
     "valv_name",
     "room_code",
@@ -122,6 +122,9 @@ const VALVE_DATA_FIELDS: [&'static str; 10] = [
     "valv_y",
     "valv_z",
     "valv_supp",
+    "act_type",
+    "act_fail_pos",
+    "act_power",
 ];
 
 const VALVE_TABLE: &'static str = "工艺布置专业_阀门清单";
@@ -148,39 +151,17 @@ pub async fn save_gy_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return vec![];
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &TABLE, &DZ_COLUMNS).await {
-                        Ok(_) => {
-                            // 保存到数据库
-                            if !r.is_empty() {
-                                let data_field_1 = vec!["id", "code", "noun"];
-                                let data_field_2 = vec!["id", "code", "noun", "length"];
-                                match save_two_material_data_to_mysql(
-                                    &TABLE,
-                                    &DZ_CHINESE_FIELDS,
-                                    &data_field_1,
-                                    r,
-                                    &data_field_2,
-                                    tubi_r,
-                                    &pool,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_gy_dzcl_mysql(&pool, r, tubi_r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -194,6 +175,31 @@ pub async fn save_gy_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 工艺布置专业大宗材料（含管道）同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_gy_dzcl_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+    tubi_rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, TABLE, &DZ_COLUMNS).await?;
+    if !rows.is_empty() {
+        let data_field_1 = vec!["id", "code", "noun"];
+        let data_field_2 = vec!["id", "code", "noun", "length"];
+        save_two_material_data_to_mysql(
+            TABLE,
+            &DZ_CHINESE_FIELDS,
+            &data_field_1,
+            rows,
+            &data_field_2,
+            tubi_rows,
+            pool,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 /// 工艺专业 设备清单
 pub async fn save_gy_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
     let mut handles = vec![];
@@ -212,34 +218,17 @@ pub async fn save_gy_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return vec![];
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &EQ_TABLE, &EQ_FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &EQ_TABLE,
-                                    &EQ_DATA_FIELDS,
-                                    &EQUI_CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_gy_equi_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -252,6 +241,26 @@ pub async fn save_gy_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 工艺布置专业设备清单同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_gy_equi_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, EQ_TABLE, &EQ_FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(
+            pool,
+            EQ_TABLE,
+            &EQ_DATA_FIELDS,
+            &EQUI_CHINESE_FIELDS,
+            rows,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 /// 工艺专业 阀门清单
 pub async fn save_gy_material_valv(refno: RefU64) -> Vec<JoinHandle<()>> {
     let db = SUL_DB.clone();
@@ -270,34 +279,17 @@ pub async fn save_gy_material_valv(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return vec![];
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &VALVE_TABLE, &VALVE_FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &VALVE_TABLE,
-                                    &VALVE_DATA_FIELDS,
-                                    &VALV_CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_gy_valv_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -310,6 +302,26 @@ pub async fn save_gy_material_valv(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 工艺布置专业阀门清单同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_gy_valv_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, VALVE_TABLE, &VALVE_FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(
+            pool,
+            VALVE_TABLE,
+            &VALVE_DATA_FIELDS,
+            &VALV_CHINESE_FIELDS,
+            rows,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MaterialGyDataBend {
     pub id: RefU64,
@@ -576,6 +588,64 @@ pub async fn get_gy_valv_list(
     Ok(data)
 }
 
+/// 阀门执行机构信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize, SurrealValue)]
+pub struct ValveActuator {
+    /// 执行机构类型（电动/气动/液动/电磁，手动阀为空）
+    pub act_type: String,
+    /// 故障位置（FO/FC/FL）
+    pub act_fail_pos: String,
+    /// 执行机构动力
+    pub act_power: String,
+}
+
+impl ValveActuator {
+    /// 是否带执行机构
+    #[inline]
+    pub fn is_actuated(&self) -> bool {
+        !self.act_type.is_empty()
+    }
+}
+
+/// 阀门信号清单的一行
+#[derive(Debug, Clone, Default, Serialize, Deserialize, SurrealValue)]
+pub struct ValveSignature {
+    pub id: String,
+    pub valv_name: String,
+    pub room_code: String,
+    pub valv_belong: String,
+    pub actuator: ValveActuator,
+}
+
+/// 查询阀门信号清单
+///
+/// `only_actuated` 为 true 时只返回带执行机构的阀门
+pub async fn query_valve_signatures(
+    refnos: &[RefU64],
+    only_actuated: bool,
+) -> anyhow::Result<Vec<ValveSignature>> {
+    let mut data = Vec::new();
+    for &refno in refnos {
+        let refnos = query_filter_deep_children(refno.into(), &["VALV"]).await?;
+        if refnos.is_empty() {
+            continue;
+        }
+        let refnos_str = refnos
+            .into_iter()
+            .map(|refno| refno.to_pe_key())
+            .collect::<Vec<String>>()
+            .join(",");
+        let sql = format!(r#"return fn::valve_signature([{}])"#, refnos_str);
+        let mut response = SUL_DB.query(&sql).await?;
+        let mut result: Vec<ValveSignature> = response.take(0)?;
+        if only_actuated {
+            result.retain(|v| v.actuator.is_actuated());
+        }
+        data.append(&mut result);
+    }
+    Ok(data)
+}
+
 /// 查询工艺设备清单数据
 pub async fn get_gy_equi_list(
     db: Surreal<Any>,
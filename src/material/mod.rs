@@ -1,5 +1,7 @@
 use crate::aios_db_mgr::aios_mgr::AiosDBMgr;
 use crate::material::dq::save_dq_material;
+use crate::material::export::MaterialTable;
+use crate::material::runner::{MaterialRunOptions, MaterialRunSummary, run_material_tasks};
 use crate::material::gps::save_gps_material_dzcl;
 use crate::material::gy::{save_gy_material_dzcl, save_gy_material_equi, save_gy_material_valv};
 use crate::material::nt::save_nt_material_dzcl;
@@ -8,19 +10,22 @@ use crate::material::tf::save_tf_material_hvac;
 use crate::material::tx::save_tx_material_equi;
 use crate::material::yk::{save_yk_material_dzcl, save_yk_material_equi, save_yk_material_pipe};
 use crate::pdms_user::RefnoMajor;
-use crate::ssc_setting::{gen_pdms_major_table, query_all_site_with_major, set_pdms_major_code};
+use crate::ssc_setting::set_pdms_major_code;
 use crate::{RefU64, SUL_DB, SurrealQueryExt, query_filter_ancestors};
 use std::collections::HashMap;
 use std::io::Read;
 use strum::IntoEnumIterator;
+use tokio::task::JoinHandle;
 use surrealdb::Surreal;
 use surrealdb::engine::any::Any;
 
 pub mod dq;
+pub mod export;
 pub mod gps;
 pub mod gy;
 pub mod nt;
 pub(crate) mod query;
+pub mod runner;
 pub mod sb;
 pub mod tf;
 pub mod tx;
@@ -90,96 +95,233 @@ mod tests {
         assert_eq!("仪控专业".parse::<MatMajorType>(), Ok(MatMajorType::Yk));
         assert!("Invalid".parse::<MatMajorType>().is_err());
     }
+
+    #[test]
+    fn test_valve_actuator_called_once_per_row() {
+        let files = [
+            include_str!("../rs_surreal/material_list/gy/gy_valve.surql"),
+            include_str!("../rs_surreal/material_list/nt/nt_valve.surql"),
+        ];
+        let mut checked = 0;
+        for file in files {
+            // 每个函数定义中执行机构只能计算一次，列通过子查询的 actuator 字段展开
+            for body in file.to_lowercase().split("define function").skip(1) {
+                let calls = body.matches("fn::valve_actuator(").count();
+                if calls > 0 {
+                    assert_eq!(calls, 1, "fn::valve_actuator called {calls} times in: {body}");
+                    checked += 1;
+                }
+            }
+        }
+        assert_eq!(checked, 3);
+    }
 }
 
 /// 保存所有的材料表单数据
-pub async fn save_all_material_data() -> anyhow::Result<()> {
-    // 生成专业代码
-    // set_pdms_major_code(&aios_mgr).await?;
-    gen_pdms_major_table().await?;
-    // 提前跑已经创建surreal的方法
-    if let Err(e) = define_material_surreal_funtions(SUL_DB.clone()).await {
-        dbg!(e.to_string());
-        return Ok(());
-    }
+///
+/// 以默认并发处理全部带专业的 site，返回每个 site 的执行结果；
+/// 需要筛选专业/site 或调整并发时使用 [`runner::run_material_tasks`]。
+pub async fn save_all_material_data() -> anyhow::Result<MaterialRunSummary> {
+    let summary = run_material_tasks(&MaterialRunOptions::default()).await?;
+    tracing::info!(
+        "材料表单生成完成: 成功 {}，失败 {}",
+        summary.succeeded, summary.failed
+    );
+    Ok(summary)
+}
+
+/// 保存单个 site 下指定专业的材料表单数据
+///
+/// `major` 为专业代码（T/I/V/E/W/EQUI/N），未知专业返回空列表。
+pub async fn save_major_material(major: &str, refno: RefU64) -> Vec<JoinHandle<()>> {
     let mut handles = Vec::new();
-    // 查找所有带专业的site
-    let sites = query_all_site_with_major().await?;
-    // 处理所有专业表单的数据
-    for site in sites {
-        dbg!(&site.id);
-        let refno = site.id;
-        if site.major != "V".to_string() {
-            continue;
-        };
-        match site.major.as_str() {
-            // 工艺
-            "T" => {
-                // 大宗材料
-                println!("工艺布置专业-大宗材料");
-                handles.append(&mut save_gy_material_dzcl(refno).await);
-                // 设备清单
-                println!("工艺布置专业-设备清单");
-                handles.append(&mut save_gy_material_equi(refno).await);
-                // 阀门清单
-                println!("工艺布置专业-阀门清单");
-                handles.append(&mut save_gy_material_valv(refno).await);
-            }
-            // 仪控
-            "I" => {
-                // 大宗材料
-                println!("仪控专业-大宗材料");
-                handles.append(&mut save_yk_material_dzcl(refno).await);
-                // 仪表管道
-                println!("仪控专业-仪表管道");
-                handles.append(&mut save_yk_material_pipe(refno).await);
-                // 设备清单
-                println!("仪控专业-设备清单");
-                handles.append(&mut save_yk_material_equi(refno).await);
-            }
-            // 通风
-            "V" => {
-                // 风管管段
-                println!("通风专业-风管管段");
-                handles.append(&mut save_tf_material_hvac(refno).await);
-            }
-            // 电气
-            "E" => {
-                // 托盘及接地
-                println!("电气专业-托盘及接地");
-                handles.append(&mut save_dq_material(refno).await);
-                // 通信
-                // 通信系统
-                println!("通信专业-通信系统");
-                handles.append(&mut save_tx_material_equi(refno).await);
-            }
-            // 给排水
-            "W" => {
-                // 大宗材料
-                println!("给排水专业-大宗材料");
-                handles.append(&mut save_gps_material_dzcl(refno).await);
-            }
-            // 设备
-            "EQUI" => {
-                // 大宗材料
-                println!("设备专业-大宗材料");
-                handles.append(&mut save_sb_material_dzcl(refno).await);
-            }
-            // 暖通
-            "N" => {
-                // 阀门清单
-                println!("暖通专业-阀门清单");
-                handles.append(&mut save_nt_material_dzcl(refno).await);
-            }
-            _ => {}
+    match major {
+        // 工艺
+        "T" => {
+            // 大宗材料
+            tracing::info!("工艺布置专业-大宗材料");
+            handles.append(&mut save_gy_material_dzcl(refno).await);
+            // 设备清单
+            tracing::info!("工艺布置专业-设备清单");
+            handles.append(&mut save_gy_material_equi(refno).await);
+            // 阀门清单
+            tracing::info!("工艺布置专业-阀门清单");
+            handles.append(&mut save_gy_material_valv(refno).await);
+        }
+        // 仪控
+        "I" => {
+            // 大宗材料
+            tracing::info!("仪控专业-大宗材料");
+            handles.append(&mut save_yk_material_dzcl(refno).await);
+            // 仪表管道
+            tracing::info!("仪控专业-仪表管道");
+            handles.append(&mut save_yk_material_pipe(refno).await);
+            // 设备清单
+            tracing::info!("仪控专业-设备清单");
+            handles.append(&mut save_yk_material_equi(refno).await);
         }
+        // 通风
+        "V" => {
+            // 风管管段
+            tracing::info!("通风专业-风管管段");
+            handles.append(&mut save_tf_material_hvac(refno).await);
+        }
+        // 电气
+        "E" => {
+            // 托盘及接地
+            tracing::info!("电气专业-托盘及接地");
+            handles.append(&mut save_dq_material(refno).await);
+            // 通信
+            // 通信系统
+            tracing::info!("通信专业-通信系统");
+            handles.append(&mut save_tx_material_equi(refno).await);
+        }
+        // 给排水
+        "W" => {
+            // 大宗材料
+            tracing::info!("给排水专业-大宗材料");
+            handles.append(&mut save_gps_material_dzcl(refno).await);
+        }
+        // 设备
+        "EQUI" => {
+            // 大宗材料
+            tracing::info!("设备专业-大宗材料");
+            handles.append(&mut save_sb_material_dzcl(refno).await);
+        }
+        // 暖通
+        "N" => {
+            // 阀门清单
+            tracing::info!("暖通专业-阀门清单");
+            handles.append(&mut save_nt_material_dzcl(refno).await);
+        }
+        _ => {}
+    }
+    handles
+}
+
+fn to_json_rows<T: serde::Serialize>(rows: Vec<T>) -> Vec<serde_json::Value> {
+    rows.into_iter()
+        .filter_map(|r| serde_json::to_value(r).ok())
+        .collect()
+}
+
+/// 查询单个 site 下指定专业的材料表单数据（不保存）
+///
+/// 返回 (材料表, 行数据) 列表，表单划分与 [`save_major_material`] 一致；未知专业返回空列表。
+pub async fn get_major_material_lists(
+    major: &str,
+    refno: RefU64,
+) -> anyhow::Result<Vec<(MaterialTable, Vec<serde_json::Value>)>> {
+    let db = SUL_DB.clone();
+    let refnos = vec![refno];
+    let mut lists = Vec::new();
+    match major {
+        "T" => {
+            let (dzcl, tubi) = gy::get_gy_dzcl(db.clone(), refnos.clone()).await?;
+            lists.push((MaterialTable::GyList, to_json_rows(dzcl)));
+            lists.push((MaterialTable::GyListTubi, to_json_rows(tubi)));
+            let equi = gy::get_gy_equi_list(db.clone(), refnos.clone()).await?;
+            lists.push((MaterialTable::GyEqui, to_json_rows(equi)));
+            let valv = gy::get_gy_valv_list(db, refnos).await?;
+            lists.push((MaterialTable::GyValv, to_json_rows(valv)));
+        }
+        "I" => {
+            let dzcl = yk::get_yk_dzcl_list(db.clone(), refnos.clone()).await?;
+            lists.push((MaterialTable::InstList, to_json_rows(dzcl)));
+            let pipe = yk::get_yk_inst_pipe(db.clone(), refnos.clone()).await?;
+            lists.push((MaterialTable::InstPipe, to_json_rows(pipe)));
+            let equi = yk::get_yk_equi_list_material(db, refnos).await?;
+            lists.push((MaterialTable::InstEqui, to_json_rows(equi)));
+        }
+        "V" => {
+            let hvac = tf::get_tf_hvac_material(&db, refnos).await?;
+            lists.push((MaterialTable::HvacPipe, to_json_rows(hvac)));
+        }
+        "E" => {
+            let bran = dq::get_dq_material_rows(db.clone(), refnos.clone()).await?;
+            lists.push((MaterialTable::ElecList, to_json_rows(bran)));
+            let txsb = tx::get_tx_txsb_list_material(db, refnos).await?;
+            lists.push((MaterialTable::TxList, to_json_rows(txsb)));
+        }
+        "W" => {
+            let (dzcl, tubi) = gps::get_gps_dzcl_material(db, refnos).await?;
+            lists.push((MaterialTable::GpsList, to_json_rows(dzcl)));
+            lists.push((MaterialTable::GpsListTubi, to_json_rows(tubi)));
+        }
+        "EQUI" => {
+            let dzcl = sb::get_sb_dzcl_list_material(db, refnos).await?;
+            lists.push((MaterialTable::SbList, to_json_rows(dzcl)));
+        }
+        "N" => {
+            let valv = nt::get_nt_valv_list_material(db, refnos).await?;
+            lists.push((MaterialTable::NtValv, to_json_rows(valv)));
+        }
+        _ => {}
+    }
+    Ok(lists)
+}
+
+/// 将 [`get_major_material_lists`] 查询到的材料表单同步到项目 MySQL 库
+///
+/// 表结构与各专业 `save_*` 任务写入的 MySQL 表一致。
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_material_lists_to_mysql(
+    lists: Vec<(MaterialTable, Vec<serde_json::Value>)>,
+) -> anyhow::Result<()> {
+    let pool = crate::db_pool::get_project_pool(crate::get_db_option()?).await?;
+    let mut lists: HashMap<MaterialTable, Vec<serde_json::Value>> = lists.into_iter().collect();
+    if lists.contains_key(&MaterialTable::GyList) {
+        gy::sync_gy_dzcl_mysql(
+            &pool,
+            take_rows(&mut lists, MaterialTable::GyList)?,
+            take_rows(&mut lists, MaterialTable::GyListTubi)?,
+        )
+        .await?;
+        gy::sync_gy_equi_mysql(&pool, take_rows(&mut lists, MaterialTable::GyEqui)?).await?;
+        gy::sync_gy_valv_mysql(&pool, take_rows(&mut lists, MaterialTable::GyValv)?).await?;
+    }
+    if lists.contains_key(&MaterialTable::InstList) {
+        yk::sync_yk_dzcl_mysql(&pool, take_rows(&mut lists, MaterialTable::InstList)?).await?;
+        yk::sync_yk_pipe_mysql(&pool, take_rows(&mut lists, MaterialTable::InstPipe)?).await?;
+        yk::sync_yk_equi_mysql(&pool, take_rows(&mut lists, MaterialTable::InstEqui)?).await?;
+    }
+    if lists.contains_key(&MaterialTable::HvacPipe) {
+        tf::sync_tf_hvac_mysql(&pool, take_rows(&mut lists, MaterialTable::HvacPipe)?).await?;
+    }
+    if lists.contains_key(&MaterialTable::ElecList) {
+        dq::sync_dq_mysql(&pool, take_rows(&mut lists, MaterialTable::ElecList)?).await?;
+        tx::sync_tx_mysql(&pool, take_rows(&mut lists, MaterialTable::TxList)?).await?;
+    }
+    if lists.contains_key(&MaterialTable::GpsList) {
+        gps::sync_gps_dzcl_mysql(
+            &pool,
+            take_rows(&mut lists, MaterialTable::GpsList)?,
+            take_rows(&mut lists, MaterialTable::GpsListTubi)?,
+        )
+        .await?;
+    }
+    if lists.contains_key(&MaterialTable::SbList) {
+        sb::sync_sb_mysql(&pool, take_rows(&mut lists, MaterialTable::SbList)?).await?;
+    }
+    if lists.contains_key(&MaterialTable::NtValv) {
+        nt::sync_nt_mysql(&pool, take_rows(&mut lists, MaterialTable::NtValv)?).await?;
     }
-    // 等待保存线程完成
-    println!("查询完毕，等待数据库保存完成");
-    futures::prelude::future::join_all(handles).await;
     Ok(())
 }
 
+#[cfg(feature = "sql")]
+fn take_rows<T: serde::de::DeserializeOwned>(
+    lists: &mut HashMap<MaterialTable, Vec<serde_json::Value>>,
+    table: MaterialTable,
+) -> anyhow::Result<Vec<T>> {
+    Ok(lists
+        .remove(&table)
+        .unwrap_or_default()
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
 /// 提前运行定义好的方法
 pub async fn define_surreal_functions(db: Surreal<Any>) -> anyhow::Result<()> {
     // db.query(include_str!("../rs_surreal/tools/bolt.surql")).await?;
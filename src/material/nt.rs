@@ -1,11 +1,14 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::save_material_value;
 #[cfg(feature = "sql")]
 use super::query::save_material_value_test;
 
 use crate::SUL_DB;
+use crate::material::export::{MaterialTable, zh_header_map};
 #[cfg(feature = "sql")]
 use crate::db_pool;
 use crate::init_test_surreal;
@@ -22,20 +25,11 @@ use surrealdb::engine::any::Any;
 use tokio::task::{self, JoinHandle};
 
 lazy_static::lazy_static! {
-    static ref CHINESE_FIELDS: HashMap<&'static str, &'static str> = {
-        let mut map = HashMap::new();
-        map.insert("id", "参考号");
-        map.insert("name", "阀门位号");
-        map.insert("room_code", "所在房间号");
-        map.insert("bran_name", "阀门归属");
-        map.insert("valv_size", "阀门尺寸");
-        map.insert("material", "阀门材质");
-        map.insert("valv_use", "阀门功能");
-        map
-    };
+    static ref CHINESE_FIELDS: HashMap<&'static str, &'static str> =
+        zh_header_map(MaterialTable::NtValv, &DATA_FIELDS);
 }
 
-const FIELDS: [&str; 7] = [
+const FIELDS: [&str; 10] = [
     "参考号",
     "阀门位号",
     "所在房间号",
@@ -43,11 +37,14 @@ const FIELDS: [&str; 7] = [
     "阀门尺寸",
     "阀门材质",
     "阀门功能",
+    "执行机构类型",
+    "故障位置",
+    "执行机构动力",
 ];
 
 const TABLE: &'static str = "暖通专业_阀门清单";
 
-const DATA_FIELDS: [&str; 7] = [
+const DATA_FIELDS: [&str; 10] = [
     "id",
     "name",
     "room_code",
@@ -55,6 +52,9 @@ const DATA_FIELDS: [&str; 7] = [
     "valv_size",
     "material",
     "valv_use",
+    "act_type",
+    "act_fail_pos",
+    "act_power",
 ];
 
 /// 暖通专业 大宗材料
@@ -78,34 +78,17 @@ pub async fn save_nt_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &TABLE, &FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &TABLE,
-                                    &DATA_FIELDS,
-                                    &CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_nt_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -118,6 +101,19 @@ pub async fn save_nt_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 暖通专业阀门清单同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_nt_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, TABLE, &FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(pool, TABLE, &DATA_FIELDS, &CHINESE_FIELDS, rows).await?;
+    }
+    Ok(())
+}
+
 /// 暖通 阀门清单
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MaterialNtValvData {
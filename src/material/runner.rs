@@ -0,0 +1,161 @@
+//! 材料表单批量生成
+//!
+//! 按 (site, 专业) 拆分为独立任务，以有限并发执行：
+//! 1. 准备：生成专业代码表、定义材料 surreal 函数（失败则整体失败）
+//! 2. 逐个 site 查询材料数据并写入对应的 `material_*` 表，单个任务失败不影响其他任务
+//! 3. 汇总每个任务的结果，写入 `material_run` 表
+//!
+//! 启用 `sql` 特性时，同一份数据还会同步到项目 MySQL 库（见 [`super::sync_material_lists_to_mysql`]）。
+
+use super::get_major_material_lists;
+use crate::ssc_setting::{gen_pdms_major_table, query_all_site_with_major};
+use crate::{RefU64, SUL_DB, insert_into_table_with_chunks};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// 运行结果表
+pub const MATERIAL_RUN_TABLE: &str = "material_run";
+
+/// 运行选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterialRunOptions {
+    /// 同时处理的 site 数
+    pub concurrency: usize,
+    /// 只处理这些专业代码（T/I/V/E/W/EQUI/N），为空表示全部
+    pub majors: Vec<String>,
+    /// 只处理这些 site，为空表示全部
+    pub sites: Vec<RefU64>,
+    /// 是否将汇总写入 [`MATERIAL_RUN_TABLE`]
+    pub save_summary: bool,
+}
+
+impl Default for MaterialRunOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            majors: vec![],
+            sites: vec![],
+            save_summary: true,
+        }
+    }
+}
+
+/// 单个 (site, 专业) 任务的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteMaterialResult {
+    pub site: RefU64,
+    pub major: String,
+    pub ok: bool,
+    /// 写入的行数
+    pub rows: usize,
+    pub elapsed_ms: f64,
+    pub error: Option<String>,
+}
+
+/// 一次运行的汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaterialRunSummary {
+    pub started_at: String,
+    pub finished_at: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<SiteMaterialResult>,
+}
+
+impl MaterialRunSummary {
+    pub fn failures(&self) -> impl Iterator<Item = &SiteMaterialResult> {
+        self.results.iter().filter(|r| !r.ok)
+    }
+}
+
+/// 查询并保存单个 site 的材料表单，返回写入的行数
+pub async fn save_site_material(major: &str, site: RefU64) -> anyhow::Result<usize> {
+    let lists = get_major_material_lists(major, site).await?;
+    let mut rows = 0;
+    for (table, data) in &lists {
+        if data.is_empty() {
+            continue;
+        }
+        rows += data.len();
+        insert_into_table_with_chunks(&SUL_DB, table.as_ref(), data.clone()).await?;
+    }
+    #[cfg(feature = "sql")]
+    super::sync_material_lists_to_mysql(lists).await?;
+    Ok(rows)
+}
+
+async fn run_site_task(site: RefU64, major: String) -> SiteMaterialResult {
+    let start = Instant::now();
+    let result = save_site_material(&major, site).await;
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+    match result {
+        Ok(rows) => SiteMaterialResult {
+            site,
+            major,
+            ok: true,
+            rows,
+            elapsed_ms,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!("site {} 专业 {} 材料表单生成失败: {}", site, major, e);
+            SiteMaterialResult {
+                site,
+                major,
+                ok: false,
+                rows: 0,
+                elapsed_ms,
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+/// 按选项批量生成材料表单
+pub async fn run_material_tasks(
+    options: &MaterialRunOptions,
+) -> anyhow::Result<MaterialRunSummary> {
+    let started_at = chrono::Local::now().to_rfc3339();
+
+    gen_pdms_major_table().await?;
+    super::define_material_surreal_funtions(SUL_DB.clone()).await?;
+
+    let tasks = query_all_site_with_major()
+        .await?
+        .into_iter()
+        .filter(|s| options.majors.is_empty() || options.majors.contains(&s.major))
+        .filter(|s| options.sites.is_empty() || options.sites.contains(&s.id))
+        .map(|s| run_site_task(s.id, s.major))
+        .collect::<Vec<_>>();
+    let total = tasks.len();
+    tracing::info!("材料表单任务数: {}，并发数: {}", total, options.concurrency);
+
+    let mut results = Vec::with_capacity(total);
+    let mut stream = futures::stream::iter(tasks).buffer_unordered(options.concurrency.max(1));
+    while let Some(result) = stream.next().await {
+        results.push(result);
+        if results.len() % 10 == 0 {
+            tracing::info!("材料表单进度: {}/{}", results.len(), total);
+        }
+    }
+    results.sort_by(|a, b| (a.site, &a.major).cmp(&(b.site, &b.major)));
+
+    let succeeded = results.iter().filter(|r| r.ok).count();
+    let summary = MaterialRunSummary {
+        started_at,
+        finished_at: chrono::Local::now().to_rfc3339(),
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    };
+    if options.save_summary {
+        let sql = format!(
+            "CREATE {} CONTENT {}",
+            MATERIAL_RUN_TABLE,
+            serde_json::to_string(&summary)?
+        );
+        SUL_DB.query(sql).await?;
+    }
+    Ok(summary)
+}
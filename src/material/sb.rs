@@ -1,6 +1,8 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::save_material_value;
 #[cfg(feature = "sql")]
 use super::query::save_material_value_test;
@@ -60,34 +62,17 @@ pub async fn save_sb_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, TABLE, &FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &TABLE,
-                                    &DATA_FIELDS,
-                                    &CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_sb_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -100,6 +85,19 @@ pub async fn save_sb_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 设备专业大宗材料同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_sb_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, TABLE, &FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(pool, TABLE, &DATA_FIELDS, &CHINESE_FIELDS, rows).await?;
+    }
+    Ok(())
+}
+
 /// 设备专业 通信系统
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MaterialTxTxsbData {
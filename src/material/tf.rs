@@ -1,10 +1,13 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::save_material_value;
 use crate::SUL_DB;
 #[cfg(feature = "sql")]
 use crate::db_pool;
+use crate::hvac::{HVAC_FITTING_NOUNS, fitting_materials_or_skip, merge_fitting_materials};
 use crate::init_test_surreal;
 use crate::utils::take_vec;
 use crate::{
@@ -41,52 +44,17 @@ pub async fn save_tf_material_hvac(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    let table_name = "通风专业_风管管段清单".to_string();
-                    let filed = vec![
-                        "参考号",
-                        "管段编号",
-                        "材质",
-                        "压力等级",
-                        "风管宽度",
-                        "风管高度",
-                        "风管壁厚",
-                        "风管面积",
-                        "风管重量",
-                        "加强筋型材",
-                        "加强筋长度",
-                        "加强筋重量",
-                        "法兰规格",
-                        "法兰长度",
-                        "法兰重量",
-                        "垫圈类型",
-                        "垫圈长度",
-                        "螺栓数量",
-                        "其它材料类型",
-                        "其它材料数量",
-                        "螺杆",
-                        "螺母数量",
-                        "所在房间号",
-                    ];
-                    match create_table_sql(&pool, &table_name, &filed).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value(&pool, &table_name, &filed, r).await {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_tf_hvac_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -99,6 +67,47 @@ pub async fn save_tf_material_hvac(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 通风专业风管管段同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_tf_hvac_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, String>>,
+) -> anyhow::Result<()> {
+    let table_name = "通风专业_风管管段清单";
+    let filed = [
+        "参考号",
+        "管段编号",
+        "材质",
+        "压力等级",
+        "风管宽度",
+        "风管高度",
+        "风管壁厚",
+        "风管面积",
+        "风管重量",
+        "加强筋型材",
+        "加强筋长度",
+        "加强筋重量",
+        "法兰规格",
+        "法兰长度",
+        "法兰重量",
+        "垫圈类型",
+        "垫圈长度",
+        "螺栓数量",
+        "其它材料类型",
+        "其它材料数量",
+        "螺杆",
+        "螺母数量",
+        "所在房间号",
+        "展开长度",
+        "展开面积",
+    ];
+    create_table_sql(pool, table_name, &filed).await?;
+    if !rows.is_empty() {
+        save_material_value(pool, table_name, &filed, rows).await?;
+    }
+    Ok(())
+}
+
 /// 通风 风管管段
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
@@ -570,6 +579,15 @@ pub async fn get_tf_hvac_material(
             dbg!("STIF");
             let mut result = get_tf_hvac_stif_data(db, caps).await?;
             data.append(&mut result);
+            // 弯头、变径、三通 按几何补充展开长度和面积
+            let fittings = refnos
+                .iter()
+                .filter(|x| HVAC_FITTING_NOUNS.contains(&x.noun.as_str()))
+                .map(|x| x.refno.into())
+                .collect::<Vec<_>>();
+            // 管件展开尺寸只是补充信息，计算失败的管件跳过，不影响材料表
+            let fittings = fitting_materials_or_skip(&fittings).await;
+            merge_fitting_materials(&mut data, &fittings);
         }
     }
     Ok(data)
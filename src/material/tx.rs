@@ -1,6 +1,8 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::save_material_value;
 #[cfg(feature = "sql")]
 use super::query::save_material_value_test;
@@ -84,34 +86,17 @@ pub async fn save_tx_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &TABLE, &FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &TABLE,
-                                    &DATA_FIELDS,
-                                    &CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_tx_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -124,6 +109,19 @@ pub async fn save_tx_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 通信专业通信系统同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_tx_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, TABLE, &FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(pool, TABLE, &DATA_FIELDS, &CHINESE_FIELDS, rows).await?;
+    }
+    Ok(())
+}
+
 /// 通信专业 通信设备
 pub async fn get_tx_txsb_list_material(
     db: Surreal<Any>,
@@ -1,6 +1,8 @@
 #[cfg(feature = "sql")]
 use super::query::create_table_sql;
 #[cfg(feature = "sql")]
+use sqlx::{MySql, Pool};
+#[cfg(feature = "sql")]
 use super::query::save_material_value;
 #[cfg(feature = "sql")]
 use super::query::save_material_value_test;
@@ -113,34 +115,17 @@ pub async fn save_yk_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &DZCL_TABLE, &FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &DZCL_TABLE,
-                                    &YK_DZCL_DATA_FIELDS,
-                                    &YK_DZCL_CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_yk_dzcl_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -153,6 +138,26 @@ pub async fn save_yk_material_dzcl(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 仪控专业大宗材料同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_yk_dzcl_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, DZCL_TABLE, &FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(
+            pool,
+            DZCL_TABLE,
+            &YK_DZCL_DATA_FIELDS,
+            &YK_DZCL_CHINESE_FIELDS,
+            rows,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 /// 仪控专业 仪表管道
 pub async fn save_yk_material_pipe(refno: RefU64) -> Vec<JoinHandle<()>> {
     let db = SUL_DB.clone();
@@ -174,34 +179,17 @@ pub async fn save_yk_material_pipe(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &PIPE_TABLE, &PIPE_FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &PIPE_TABLE,
-                                    &PIPE_DATA_FIELDS,
-                                    &YK_INST_CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_yk_pipe_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -214,6 +202,26 @@ pub async fn save_yk_material_pipe(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 仪控专业仪表管道同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_yk_pipe_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, PIPE_TABLE, &PIPE_FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(
+            pool,
+            PIPE_TABLE,
+            &PIPE_DATA_FIELDS,
+            &YK_INST_CHINESE_FIELDS,
+            rows,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 /// 仪控专业 设备清单
 pub async fn save_yk_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
     let db = SUL_DB.clone();
@@ -235,34 +243,17 @@ pub async fn save_yk_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
             handles.push(task);
             #[cfg(feature = "sql")]
             {
-                let db_option = get_db_option();
+                let Ok(db_option) = get_db_option() else {
+                    dbg!("无法读取数据库配置");
+                    return handles;
+                };
                 let Ok(pool) = db_pool::get_project_pool(&db_option).await else {
                     dbg!("无法连接到数据库");
                     return handles;
                 };
                 let task = task::spawn(async move {
-                    match create_table_sql(&pool, &EQUI_TABLE, &EQUI_FIELDS).await {
-                        Ok(_) => {
-                            if !r.is_empty() {
-                                match save_material_value_test(
-                                    &pool,
-                                    &EQUI_TABLE,
-                                    &EQUI_DATA_FIELDS,
-                                    &YK_EQUI_CHINESE_FIELDS,
-                                    r,
-                                )
-                                .await
-                                {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        dbg!(&e.to_string());
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            dbg!(&e.to_string());
-                        }
+                    if let Err(e) = sync_yk_equi_mysql(&pool, r).await {
+                        dbg!(&e.to_string());
                     }
                 });
                 handles.push(task);
@@ -275,6 +266,26 @@ pub async fn save_yk_material_equi(refno: RefU64) -> Vec<JoinHandle<()>> {
     handles
 }
 
+/// 仪控专业设备清单同步到 MySQL
+#[cfg(feature = "sql")]
+pub(crate) async fn sync_yk_equi_mysql(
+    pool: &Pool<MySql>,
+    rows: Vec<HashMap<String, serde_json::Value>>,
+) -> anyhow::Result<()> {
+    create_table_sql(pool, EQUI_TABLE, &EQUI_FIELDS).await?;
+    if !rows.is_empty() {
+        save_material_value_test(
+            pool,
+            EQUI_TABLE,
+            &EQUI_DATA_FIELDS,
+            &YK_EQUI_CHINESE_FIELDS,
+            rows,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 /// 查询仪控 大宗材料
 pub async fn get_yk_dzcl_list(
     db: Surreal<Any>,
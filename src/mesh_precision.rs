@@ -4,7 +4,21 @@ use std::collections::HashMap;
 use std::sync::RwLock;
 
 /// 预设的 LOD 等级
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Ord, PartialOrd)]
+#[derive(
+    rkyv::Archive,
+    rkyv::Deserialize,
+    rkyv::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    Ord,
+    PartialOrd,
+)]
 pub enum LodLevel {
     #[serde(rename = "L0")]
     L0,
@@ -0,0 +1,259 @@
+//! mesh 文件存储
+//!
+//! mesh 二进制不写入 SurrealDB，inst_geo 只记录 geo_hash 与各 LOD 的相对路径
+//! （[`build_mesh_path`]，由 `Transaction::save_mesh_paths` 写入），文件本身通过 [`MeshBlobStore`] 读写：
+//!
+//! - [`LocalDirStore`]：本地目录，默认为 `DbOption::get_meshes_path()`
+//! - `S3Store`（`s3` 特性）：S3 兼容的对象存储
+//! - [`CachedMeshStore`]：远端存储 + 本地目录缓存，读取时先查缓存，未命中再从远端拉取并落盘
+//!
+//! 配置见 DbOption 的 `[mesh_store]`：
+//!
+//! ```toml
+//! [mesh_store]
+//! url = "s3://plant-meshes/project_a"   # 缺省时使用 meshes_path 本地目录
+//! cache_dir = "cache/meshes"
+//! ```
+
+mod local;
+#[cfg(feature = "s3")]
+mod s3;
+
+pub use local::LocalDirStore;
+#[cfg(feature = "s3")]
+pub use s3::S3Store;
+
+use crate::shape::pdms_shape::PlantMesh;
+use crate::utils::build_mesh_path;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+/// mesh 二进制的存取接口，键为 [`build_mesh_path`] 生成的相对路径
+#[async_trait]
+pub trait MeshBlobStore: Send + Sync {
+    /// 读取，不存在时返回 `None`
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()>;
+
+    async fn exists(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.get(key).await?.is_some())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// 用于日志的描述
+    fn describe(&self) -> String;
+}
+
+/// mesh 存储配置，对应 DbOption 中的 `[mesh_store]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MeshStoreConfig {
+    /// 存储位置：`s3://bucket/prefix`，或本地目录；缺省时使用 `meshes_path`
+    #[serde(default)]
+    pub url: Option<String>,
+    /// 远端存储的本地缓存目录，缺省时不缓存
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+}
+
+impl MeshStoreConfig {
+    /// 按配置打开存储，`default_dir` 为未配置 `url` 时使用的本地目录
+    pub fn open(
+        &self,
+        default_dir: impl Into<std::path::PathBuf>,
+    ) -> anyhow::Result<Arc<dyn MeshBlobStore>> {
+        let Some(url) = self.url.as_deref() else {
+            return Ok(Arc::new(LocalDirStore::new(default_dir)));
+        };
+        if let Some(_rest) = url.strip_prefix("s3://") {
+            #[cfg(feature = "s3")]
+            {
+                let remote: Arc<dyn MeshBlobStore> = Arc::new(S3Store::from_url(url)?);
+                return Ok(match &self.cache_dir {
+                    Some(dir) => Arc::new(CachedMeshStore::new(remote, LocalDirStore::new(dir))),
+                    None => remote,
+                });
+            }
+            #[cfg(not(feature = "s3"))]
+            anyhow::bail!("mesh_store 配置为 {url}，需要开启 s3 特性");
+        }
+        Ok(Arc::new(LocalDirStore::new(url)))
+    }
+}
+
+/// 远端存储加本地目录缓存
+pub struct CachedMeshStore {
+    remote: Arc<dyn MeshBlobStore>,
+    cache: LocalDirStore,
+}
+
+impl CachedMeshStore {
+    pub fn new(remote: Arc<dyn MeshBlobStore>, cache: LocalDirStore) -> Self {
+        Self { remote, cache }
+    }
+}
+
+#[async_trait]
+impl MeshBlobStore for CachedMeshStore {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        if let Some(bytes) = self.cache.get(key).await? {
+            return Ok(Some(bytes));
+        }
+        let Some(bytes) = self.remote.get(key).await? else {
+            return Ok(None);
+        };
+        // 缓存写入失败不影响读取
+        if let Err(e) = self.cache.put(key, bytes.clone()).await {
+            log::warn!("mesh 缓存写入失败 {}: {}", key, e);
+        }
+        Ok(Some(bytes))
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        self.remote.put(key, bytes.clone()).await?;
+        self.cache.put(key, bytes).await
+    }
+
+    async fn exists(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.cache.exists(key).await? || self.remote.exists(key).await?)
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        self.cache.delete(key).await?;
+        self.remote.delete(key).await
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{}（缓存 {}）",
+            self.remote.describe(),
+            self.cache.describe()
+        )
+    }
+}
+
+static MESH_STORE: OnceLock<Arc<dyn MeshBlobStore>> = OnceLock::new();
+
+/// 按 DbOption 的 `[mesh_store]` 打开的全局存储，配置无效时退回 `meshes_path` 本地目录
+pub fn mesh_store() -> Arc<dyn MeshBlobStore> {
+    MESH_STORE
+        .get_or_init(|| {
+            let default_option;
+            let option = match crate::get_db_option() {
+                Ok(option) => option,
+                Err(e) => {
+                    log::warn!("无法读取配置，mesh_store 使用缺省配置: {}", e);
+                    default_option = crate::options::DbOption::default();
+                    &default_option
+                }
+            };
+            let meshes_path = option.get_meshes_path();
+            option
+                .mesh_store
+                .open(meshes_path.clone())
+                .unwrap_or_else(|e| {
+                    log::warn!(
+                        "mesh_store 配置无效，使用本地目录 {:?}: {:#}",
+                        meshes_path,
+                        e
+                    );
+                    Arc::new(LocalDirStore::new(meshes_path))
+                })
+        })
+        .clone()
+}
+
+/// 读取 mesh，`lod_level` 为空时读取原始精度
+pub async fn load_mesh(
+    store: &dyn MeshBlobStore,
+    geo_hash: &str,
+    lod_level: &str,
+) -> anyhow::Result<Option<PlantMesh>> {
+    let key = build_mesh_path(geo_hash, lod_level);
+    store
+        .get(&key)
+        .await?
+        .map(|bytes| PlantMesh::des_from_bytes(&bytes))
+        .transpose()
+}
+
+/// 带缓存读取 mesh，同一个 geo_hash 只访问一次存储，不存在或读取失败时返回 `None`
+pub async fn load_mesh_cached<'a>(
+    store: &dyn MeshBlobStore,
+    cache: &'a mut HashMap<String, Option<PlantMesh>>,
+    geo_hash: &str,
+    lod_level: &str,
+) -> Option<&'a PlantMesh> {
+    if !cache.contains_key(geo_hash) {
+        let mesh = load_mesh(store, geo_hash, lod_level)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("mesh {} 读取失败: {}", geo_hash, e);
+                None
+            });
+        cache.insert(geo_hash.to_string(), mesh);
+    }
+    cache[geo_hash].as_ref()
+}
+
+/// 批量读取 mesh，不存在或读取失败的 geo_hash 不出现在结果中
+pub async fn load_meshes(
+    store: &dyn MeshBlobStore,
+    geo_hashes: impl IntoIterator<Item = u64>,
+    lod_level: &str,
+) -> HashMap<u64, PlantMesh> {
+    let mut meshes = HashMap::new();
+    for geo_hash in geo_hashes {
+        match load_mesh(store, &geo_hash.to_string(), lod_level).await {
+            Ok(Some(mesh)) => {
+                meshes.insert(geo_hash, mesh);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("mesh {} 读取失败: {}", geo_hash, e),
+        }
+    }
+    meshes
+}
+
+/// 写入 mesh，返回存储键
+pub async fn save_mesh(
+    store: &dyn MeshBlobStore,
+    geo_hash: &str,
+    lod_level: &str,
+    mesh: &PlantMesh,
+) -> anyhow::Result<String> {
+    let key = build_mesh_path(geo_hash, lod_level);
+    store.put(&key, bincode::serialize(mesh)?).await?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    #[tokio::test]
+    async fn test_cached_store() {
+        let remote_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let remote: Arc<dyn MeshBlobStore> = Arc::new(LocalDirStore::new(remote_dir.path()));
+        let mesh = PlantMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        let key = save_mesh(remote.as_ref(), "123", "L1", &mesh)
+            .await
+            .unwrap();
+        assert_eq!(key, "meshes/lod_L1/123_L1.mesh");
+
+        let store = CachedMeshStore::new(remote, LocalDirStore::new(cache_dir.path()));
+        let loaded = load_mesh(&store, "123", "L1").await.unwrap().unwrap();
+        assert_eq!(loaded.indices, mesh.indices);
+        assert!(cache_dir.path().join(&key).exists());
+        assert!(load_mesh(&store, "456", "").await.unwrap().is_none());
+    }
+}
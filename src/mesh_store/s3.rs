@@ -0,0 +1,78 @@
+use super::MeshBlobStore;
+use async_trait::async_trait;
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+
+/// S3 兼容的对象存储
+///
+/// 凭据、区域与 endpoint 从 `AWS_*` 环境变量读取（如 `AWS_ACCESS_KEY_ID`、`AWS_ENDPOINT`）。
+pub struct S3Store {
+    inner: Box<dyn ObjectStore>,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Store {
+    /// `s3://bucket/prefix`
+    pub fn from_url(url: &str) -> anyhow::Result<Self> {
+        let rest = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| anyhow::anyhow!("不是 s3 地址: {url}"))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        anyhow::ensure!(!bucket.is_empty(), "s3 地址缺少 bucket: {url}");
+        let inner = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()?;
+        Ok(Self {
+            inner: Box::new(inner),
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+
+    fn path_of(&self, key: &str) -> Path {
+        if self.prefix.is_empty() {
+            Path::from(key)
+        } else {
+            Path::from(format!("{}/{}", self.prefix, key))
+        }
+    }
+}
+
+#[async_trait]
+impl MeshBlobStore for S3Store {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.inner.get(&self.path_of(key)).await {
+            Ok(r) => Ok(Some(r.bytes().await?.to_vec())),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        self.inner
+            .put(&self.path_of(key), PutPayload::from(bytes))
+            .await?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> anyhow::Result<bool> {
+        match self.inner.head(&self.path_of(key)).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self.inner.delete(&self.path_of(key)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.prefix)
+    }
+}
@@ -0,0 +1,615 @@
+//! 分支端部连接报告
+//!
+//! 以分支头/尾（HREF/TREF）到被连接元素的连接关系为边，列出每个分支端部连接的设备管口或其他管道，
+//! 包括连接类型、法兰压力等级、朝向和标高，并检查分支端部参数（HCON/TCON、HBOR/TBOR、
+//! HPOS/TPOS、HDIR/TDIR）与被连接点（管口 P1 或元件最近的 P 点）是否一致。
+//!
+//! 报告写入 [`BRANCH_CONNECTION_TABLE`]，不一致项同时写入检查结果表（检查类型为
+//! [`BRANCH_CONNECTION_CHECK`]），并可导出为 CSV。
+
+use crate::material::export::{CsvExporter, MaterialExporter, MaterialSheet};
+use crate::metadata::inspection::{InspectionIssue, InspectionSeverity, save_inspection_issues};
+use crate::tool::math_tool::{dvec3_to_xyz_str, to_pdms_dvec_str};
+use crate::{NamedAttrMap, RefnoEnum, SUL_DB, SurrealQueryExt, join_pe_keys};
+use bevy_transform::prelude::Transform;
+use glam::{DMat4, DVec3};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 报告表名
+pub const BRANCH_CONNECTION_TABLE: &str = "branch_connection";
+
+/// 检查类型名
+pub const BRANCH_CONNECTION_CHECK: &str = "branch_connection";
+
+/// 分支端部
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, SurrealValue)]
+pub enum BranchEnd {
+    Head,
+    Tail,
+}
+
+impl BranchEnd {
+    /// 端部属性前缀（H/T）
+    fn prefix(&self) -> &'static str {
+        match self {
+            BranchEnd::Head => "H",
+            BranchEnd::Tail => "T",
+        }
+    }
+
+    fn att(&self, suffix: &str) -> String {
+        format!("{}{}", self.prefix(), suffix)
+    }
+}
+
+/// 被连接元素的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, SurrealValue)]
+pub enum ConnectionTargetKind {
+    /// 设备管口
+    Nozzle,
+    /// 另一分支的端部
+    Branch,
+    /// 其他管道元件（如三通）
+    Component,
+    /// 未连接
+    Open,
+}
+
+/// 不一致类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConnectionMismatch {
+    /// 连接类型不同
+    ConnType,
+    /// 公称通径不同
+    Bore,
+    /// 法兰压力等级不同
+    Rating,
+    /// 端点位置不重合
+    Position,
+    /// 端部方向不共线
+    Direction,
+}
+
+impl ConnectionMismatch {
+    pub const ALL: [ConnectionMismatch; 5] = [
+        ConnectionMismatch::ConnType,
+        ConnectionMismatch::Bore,
+        ConnectionMismatch::Rating,
+        ConnectionMismatch::Position,
+        ConnectionMismatch::Direction,
+    ];
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.code() == code)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConnectionMismatch::ConnType => "conn_type",
+            ConnectionMismatch::Bore => "bore",
+            ConnectionMismatch::Rating => "rating",
+            ConnectionMismatch::Position => "position",
+            ConnectionMismatch::Direction => "direction",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            ConnectionMismatch::ConnType => "分支端部连接类型与被连接点不一致",
+            ConnectionMismatch::Bore => "分支端部通径与被连接点不一致",
+            ConnectionMismatch::Rating => "分支端部法兰等级与被连接点不一致",
+            ConnectionMismatch::Position => "分支端部位置与被连接点不重合",
+            ConnectionMismatch::Direction => "分支端部方向与被连接点不共线",
+        }
+    }
+}
+
+/// 检查参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchConnectionConfig {
+    /// 位置容差（mm）
+    pub position_tol: f64,
+    /// 通径容差（mm）
+    pub bore_tol: f32,
+    /// 方向夹角容差（度）
+    pub angle_tol_deg: f64,
+}
+
+impl Default for BranchConnectionConfig {
+    fn default() -> Self {
+        Self {
+            position_tol: 1.0,
+            bore_tol: 0.5,
+            angle_tol_deg: 1.0,
+        }
+    }
+}
+
+/// 一个连接端点（世界坐标）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionPoint {
+    pub conn_type: String,
+    pub bore: f32,
+    pub pos: DVec3,
+    pub dir: Option<DVec3>,
+}
+
+/// 从连接类型中提取法兰压力等级
+///
+/// 取连接类型中的数字部分，保留 `PN`/`CL` 前缀，如 `FB150` → `150`，`PN16` → `PN16`。
+/// 非法兰连接（不以 `F` 开头且没有等级前缀）返回空串。
+pub fn flange_rating(conn_type: &str) -> String {
+    let conn = conn_type.trim().to_uppercase();
+    let digits = conn
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    if digits.is_empty() {
+        return String::new();
+    }
+    for prefix in ["PN", "CL"] {
+        if conn.starts_with(prefix) {
+            return format!("{}{}", prefix, digits);
+        }
+    }
+    if conn.starts_with('F') {
+        digits
+    } else {
+        String::new()
+    }
+}
+
+/// 比较分支端部与被连接点
+pub fn check_connection(
+    end: &ConnectionPoint,
+    target: &ConnectionPoint,
+    config: &BranchConnectionConfig,
+) -> Vec<ConnectionMismatch> {
+    let mut mismatches = vec![];
+    let (a, b) = (end.conn_type.trim(), target.conn_type.trim());
+    if !a.is_empty() && !b.is_empty() && !a.eq_ignore_ascii_case(b) {
+        mismatches.push(ConnectionMismatch::ConnType);
+    }
+    if end.bore > 0.0 && target.bore > 0.0 && (end.bore - target.bore).abs() > config.bore_tol {
+        mismatches.push(ConnectionMismatch::Bore);
+    }
+    let (ra, rb) = (flange_rating(a), flange_rating(b));
+    if !ra.is_empty() && !rb.is_empty() && ra != rb {
+        mismatches.push(ConnectionMismatch::Rating);
+    }
+    if end.pos.distance(target.pos) > config.position_tol {
+        mismatches.push(ConnectionMismatch::Position);
+    }
+    if let (Some(d1), Some(d2)) = (end.dir, target.dir) {
+        let cos = d1.normalize_or_zero().dot(d2.normalize_or_zero()).abs();
+        if cos < config.angle_tol_deg.to_radians().cos() {
+            mismatches.push(ConnectionMismatch::Direction);
+        }
+    }
+    mismatches
+}
+
+/// 报告中的一行：一个分支端部的连接
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct BranchConnectionRow {
+    pub bran: RefnoEnum,
+    pub bran_name: String,
+    pub end: BranchEnd,
+    pub target: Option<RefnoEnum>,
+    pub target_name: String,
+    pub target_noun: String,
+    pub target_kind: ConnectionTargetKind,
+    pub conn_type: String,
+    pub target_conn_type: String,
+    pub bore: f32,
+    pub target_bore: f32,
+    /// 法兰压力等级
+    pub rating: String,
+    /// 端部方向，如 `E`、`N 45 U`
+    pub orientation: String,
+    /// 端点世界坐标
+    pub position: String,
+    /// 端点标高（mm）
+    pub elevation: f64,
+    /// 不一致项代码
+    pub mismatches: Vec<String>,
+}
+
+impl BranchConnectionRow {
+    fn to_surreal_json(&self) -> String {
+        let target = self
+            .target
+            .map(|r| r.to_pe_key())
+            .unwrap_or_else(|| "NONE".to_string());
+        let s = |v: &str| serde_json::to_string(v).unwrap_or_default();
+        format!(
+            "{{bran: {}, bran_name: {}, end: {}, target: {}, target_name: {}, target_noun: {}, target_kind: {}, conn_type: {}, target_conn_type: {}, bore: {}, target_bore: {}, rating: {}, orientation: {}, position: {}, elevation: {}, mismatches: {}, time: time::now()}}",
+            self.bran.to_pe_key(),
+            s(&self.bran_name),
+            serde_json::to_string(&self.end).unwrap_or_default(),
+            target,
+            s(&self.target_name),
+            s(&self.target_noun),
+            serde_json::to_string(&self.target_kind).unwrap_or_default(),
+            s(&self.conn_type),
+            s(&self.target_conn_type),
+            self.bore,
+            self.target_bore,
+            s(&self.rating),
+            s(&self.orientation),
+            s(&self.position),
+            self.elevation,
+            serde_json::to_string(&self.mismatches).unwrap_or_default(),
+        )
+    }
+
+    const HEADERS: [&'static str; 15] = [
+        "分支",
+        "端部",
+        "连接对象",
+        "连接对象类型",
+        "连接类别",
+        "连接类型",
+        "对象连接类型",
+        "通径",
+        "对象通径",
+        "法兰等级",
+        "朝向",
+        "位置",
+        "标高",
+        "不一致项",
+        "参考号",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.bran_name.clone(),
+            format!("{:?}", self.end),
+            self.target_name.clone(),
+            self.target_noun.clone(),
+            format!("{:?}", self.target_kind),
+            self.conn_type.clone(),
+            self.target_conn_type.clone(),
+            self.bore.to_string(),
+            self.target_bore.to_string(),
+            self.rating.clone(),
+            self.orientation.clone(),
+            self.position.clone(),
+            format!("{:.1}", self.elevation),
+            self.mismatches.join(","),
+            self.bran.to_string(),
+        ]
+    }
+}
+
+/// 分支连接报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BranchConnectionReport {
+    /// 参与检查的分支
+    pub branches: Vec<RefnoEnum>,
+    pub rows: Vec<BranchConnectionRow>,
+}
+
+impl BranchConnectionReport {
+    /// 存在不一致的行
+    pub fn mismatched(&self) -> impl Iterator<Item = &BranchConnectionRow> {
+        self.rows.iter().filter(|r| !r.mismatches.is_empty())
+    }
+
+    /// 转换为可导出的表单
+    pub fn to_sheet(&self) -> MaterialSheet {
+        MaterialSheet {
+            name: "分支连接报告".to_string(),
+            headers: BranchConnectionRow::HEADERS
+                .iter()
+                .map(|h| h.to_string())
+                .collect(),
+            rows: self.rows.iter().map(|r| r.cells()).collect(),
+        }
+    }
+
+    /// 导出 CSV
+    pub fn export_csv(&self, path: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
+        CsvExporter::default().export(&[self.to_sheet()], path.as_ref())
+    }
+
+    /// 不一致项转换为检查结果
+    pub fn issues(&self) -> Vec<InspectionIssue> {
+        self.mismatched()
+            .flat_map(|row| {
+                row.mismatches.iter().map(move |code| {
+                    let message = ConnectionMismatch::from_code(code)
+                        .map(|m| m.message())
+                        .unwrap_or_default();
+                    InspectionIssue {
+                        check: BRANCH_CONNECTION_CHECK.to_string(),
+                        refno: row.bran,
+                        related: row.target,
+                        severity: InspectionSeverity::Error,
+                        code: code.clone(),
+                        message: format!("{}（{:?}）", message, row.end),
+                        value: None,
+                        limit: None,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// 保存报告和不一致项，覆盖这些分支的旧结果
+    pub async fn save(&self) -> anyhow::Result<()> {
+        if !self.branches.is_empty() {
+            let sql = format!(
+                "DELETE {BRANCH_CONNECTION_TABLE} WHERE bran IN [{}];",
+                join_pe_keys(self.branches.iter())
+            );
+            SUL_DB.query_response(&sql).await?;
+        }
+        for chunk in self.rows.chunks(300) {
+            let sql = chunk
+                .iter()
+                .map(|r| {
+                    format!(
+                        "CREATE {BRANCH_CONNECTION_TABLE} CONTENT {};",
+                        r.to_surreal_json()
+                    )
+                })
+                .collect::<String>();
+            SUL_DB.query_response(&sql).await?;
+        }
+        save_inspection_issues(BRANCH_CONNECTION_CHECK, &self.branches, &self.issues()).await
+    }
+}
+
+async fn world_mat(refno: RefnoEnum) -> anyhow::Result<DMat4> {
+    Ok(crate::transform::get_world_mat4(refno, false)
+        .await?
+        .unwrap_or(DMat4::IDENTITY))
+}
+
+/// 分支端部的连接点（世界坐标）
+fn branch_end_point(att: &NamedAttrMap, end: BranchEnd, world: &DMat4) -> ConnectionPoint {
+    let pos = att.get_dvec3(&end.att("POS")).unwrap_or_default();
+    ConnectionPoint {
+        conn_type: att.get_as_string(&end.att("CON")).unwrap_or_default(),
+        bore: att.get_f32(&end.att("BOR")).unwrap_or_default(),
+        pos: world.transform_point3(pos),
+        dir: att
+            .get_dvec3(&end.att("DIR"))
+            .map(|d| world.transform_vector3(d).normalize_or_zero()),
+    }
+}
+
+/// 被连接元素上离 `near` 最近的连接点
+async fn target_point(
+    target: RefnoEnum,
+    noun: &str,
+    near: DVec3,
+) -> anyhow::Result<Option<ConnectionPoint>> {
+    if noun == "BRAN" {
+        let att = crate::get_named_attmap(target).await?;
+        let world = world_mat(target).await?;
+        let point = [BranchEnd::Head, BranchEnd::Tail]
+            .into_iter()
+            .map(|end| branch_end_point(&att, end, &world))
+            .min_by(|a, b| a.pos.distance(near).total_cmp(&b.pos.distance(near)));
+        return Ok(point);
+    }
+    let Some(point_map) = crate::rs_surreal::query_point_map(target).await? else {
+        return Ok(None);
+    };
+    let trans = Transform::from_matrix(world_mat(target).await?.as_mat4());
+    // 管口只看 P1
+    let params = point_map
+        .ptset_map
+        .values()
+        .filter(|p| noun != "NOZZ" || p.number == 1)
+        .map(|p| p.transformed(&trans))
+        .collect::<Vec<_>>();
+    Ok(params
+        .into_iter()
+        .map(|p| ConnectionPoint {
+            conn_type: p.pconnect.clone(),
+            bore: p.pbore,
+            pos: p.pt.0.as_dvec3(),
+            dir: p.dir.map(|d| d.0.as_dvec3()),
+        })
+        .min_by(|a, b| a.pos.distance(near).total_cmp(&b.pos.distance(near))))
+}
+
+async fn branch_rows(
+    bran: RefnoEnum,
+    config: &BranchConnectionConfig,
+) -> anyhow::Result<Vec<BranchConnectionRow>> {
+    let att = crate::get_named_attmap(bran).await?;
+    let world = world_mat(bran).await?;
+    let bran_name = crate::get_default_full_name(bran).await.unwrap_or_default();
+    let mut rows = Vec::with_capacity(2);
+    for end in [BranchEnd::Head, BranchEnd::Tail] {
+        let point = branch_end_point(&att, end, &world);
+        let target = att
+            .get_foreign_refno(&end.att("REF"))
+            .filter(|r| !r.is_unset());
+        let target_pe = match target {
+            Some(t) => crate::get_pe(t).await?,
+            None => None,
+        };
+        let (target_name, target_noun) = match &target_pe {
+            Some(pe) => (
+                crate::get_default_full_name(pe.refno).await.unwrap_or_default(),
+                pe.noun.clone(),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let target_kind = match target_noun.as_str() {
+            "" => ConnectionTargetKind::Open,
+            "NOZZ" => ConnectionTargetKind::Nozzle,
+            "BRAN" => ConnectionTargetKind::Branch,
+            _ => ConnectionTargetKind::Component,
+        };
+        let target_point = match target_pe.as_ref() {
+            Some(pe) => target_point(pe.refno, &pe.noun, point.pos).await?,
+            None => None,
+        };
+        let mismatches = target_point
+            .as_ref()
+            .map(|t| check_connection(&point, t, config))
+            .unwrap_or_default();
+        let target_point = target_point.unwrap_or_default();
+        let rating = flange_rating(&point.conn_type);
+        rows.push(BranchConnectionRow {
+            bran,
+            bran_name: bran_name.clone(),
+            end,
+            target,
+            target_name,
+            target_noun,
+            target_kind,
+            conn_type: point.conn_type.clone(),
+            target_conn_type: target_point.conn_type,
+            bore: point.bore,
+            target_bore: target_point.bore,
+            rating,
+            orientation: point
+                .dir
+                .map(|d| to_pdms_dvec_str(&d, false))
+                .unwrap_or_default(),
+            position: dvec3_to_xyz_str(point.pos),
+            elevation: point.pos.z,
+            mismatches: mismatches.iter().map(|m| m.code().to_string()).collect(),
+        });
+    }
+    Ok(rows)
+}
+
+/// 生成 `roots` 下所有分支的连接报告
+pub async fn build_branch_connection_report(
+    roots: &[RefnoEnum],
+    config: &BranchConnectionConfig,
+) -> anyhow::Result<BranchConnectionReport> {
+    let mut report = BranchConnectionReport::default();
+    for &root in roots {
+        for bran in crate::query_filter_deep_children(root, &["BRAN"]).await? {
+            if report.branches.contains(&bran) {
+                continue;
+            }
+            match branch_rows(bran, config).await {
+                Ok(mut rows) => report.rows.append(&mut rows),
+                Err(e) => log::warn!("分支 {} 连接报告生成失败: {}", bran, e),
+            }
+            report.branches.push(bran);
+        }
+    }
+    Ok(report)
+}
+
+/// 生成并保存连接报告
+pub async fn run_branch_connection_report(
+    roots: &[RefnoEnum],
+    config: &BranchConnectionConfig,
+) -> anyhow::Result<BranchConnectionReport> {
+    let report = build_branch_connection_report(roots, config).await?;
+    report.save().await?;
+    Ok(report)
+}
+
+/// 查询已保存的连接报告
+pub async fn query_branch_connections(
+    only_mismatched: bool,
+) -> anyhow::Result<Vec<BranchConnectionRow>> {
+    let filter = if only_mismatched {
+        " WHERE array::len(mismatches) > 0"
+    } else {
+        ""
+    };
+    let sql = format!(
+        "SELECT bran, bran_name, end, target, target_name, target_noun, target_kind, conn_type, target_conn_type, bore, target_bore, rating, orientation, position, elevation, mismatches FROM {BRANCH_CONNECTION_TABLE}{filter}"
+    );
+    SUL_DB.query_take(&sql, 0).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use surrealdb::Surreal;
+    use surrealdb::engine::any::Any;
+
+    #[test]
+    fn test_flange_rating() {
+        assert_eq!(flange_rating("FB150"), "150");
+        assert_eq!(flange_rating("pn16"), "PN16");
+        assert_eq!(flange_rating("BWD"), "");
+        assert_eq!(flange_rating("FBD"), "");
+    }
+
+    #[test]
+    fn test_check_connection() {
+        let config = BranchConnectionConfig::default();
+        let nozzle = ConnectionPoint {
+            conn_type: "FB150".to_string(),
+            bore: 100.0,
+            pos: DVec3::new(1000.0, 0.0, 2500.0),
+            dir: Some(DVec3::X),
+        };
+        let mut head = nozzle.clone();
+        head.dir = Some(-DVec3::X);
+        assert!(check_connection(&head, &nozzle, &config).is_empty());
+
+        head.conn_type = "FB300".to_string();
+        head.bore = 80.0;
+        head.pos.z += 5.0;
+        head.dir = Some(DVec3::Z);
+        assert_eq!(
+            check_connection(&head, &nozzle, &config),
+            vec![
+                ConnectionMismatch::ConnType,
+                ConnectionMismatch::Bore,
+                ConnectionMismatch::Rating,
+                ConnectionMismatch::Position,
+                ConnectionMismatch::Direction,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_row_surreal_json_roundtrip() {
+        let db = Surreal::<Any>::init();
+        db.connect("mem://").await.unwrap();
+        db.use_ns("test").use_db("test").await.unwrap();
+
+        let row = BranchConnectionRow {
+            bran: RefnoEnum::from_str("17496_100").unwrap(),
+            bran_name: "/BRAN-1".to_string(),
+            end: BranchEnd::Tail,
+            target: Some(RefnoEnum::from_str("17496_200").unwrap()),
+            target_name: "/E-1/N1".to_string(),
+            target_noun: "NOZZ".to_string(),
+            target_kind: ConnectionTargetKind::Nozzle,
+            conn_type: "FB150".to_string(),
+            target_conn_type: "FB300".to_string(),
+            bore: 100.0,
+            target_bore: 100.0,
+            rating: "150".to_string(),
+            orientation: "E".to_string(),
+            position: "E 1000mm N 0mm U 2500mm".to_string(),
+            elevation: 2500.0,
+            mismatches: vec!["rating".to_string()],
+        };
+        let sql = format!(
+            "CREATE {BRANCH_CONNECTION_TABLE} CONTENT {};",
+            row.to_surreal_json()
+        );
+        db.query(sql).await.unwrap().check().unwrap();
+
+        let sql = format!(
+            "SELECT bran, bran_name, end, target, target_name, target_noun, target_kind, conn_type, target_conn_type, bore, target_bore, rating, orientation, position, elevation, mismatches FROM {BRANCH_CONNECTION_TABLE}"
+        );
+        let rows: Vec<BranchConnectionRow> = db.query(sql).await.unwrap().take(0).unwrap();
+        assert_eq!(rows, vec![row]);
+    }
+}
@@ -0,0 +1,302 @@
+//! 支吊架锚固与土建预埋板检查
+//!
+//! 土建接口要求支吊架的锚固件（建模为 FITT 等结构附件）落在宿主墙板的预埋板范围内，
+//! 并与墙板边缘保持足够距离（钢筋密集区）。检查逻辑：
+//! 1. 按空间关系为每个锚固件寻找宿主墙板（包围盒在容差内包含锚固件中心）
+//! 2. 锚固件包围盒在墙板平面内须被某块预埋板包含（留有 `plate_margin` 余量）
+//! 3. 锚固件中心到墙板边缘（面内两个方向）的距离不小于 `min_edge_distance`
+//!
+//! 结果写入检查结果表，检查类型为 [`EMBED_PLATE_CHECK`]。
+
+use crate::metadata::inspection::{InspectionIssue, InspectionSeverity, save_inspection_issues};
+use crate::RefnoEnum;
+use glam::Vec3;
+use parry3d::bounding_volume::Aabb;
+use serde::{Deserialize, Serialize};
+
+/// 检查类型名
+pub const EMBED_PLATE_CHECK: &str = "embed_plate";
+
+/// 检查参数（mm）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedPlateCheckConfig {
+    /// 锚固件中心到墙板边缘的最小距离
+    pub min_edge_distance: f32,
+    /// 锚固件边缘到预埋板边缘的最小余量
+    pub plate_margin: f32,
+    /// 判断锚固件属于墙板时的包围盒外扩容差
+    pub host_tolerance: f32,
+    /// 锚固件类型
+    pub anchor_nouns: Vec<String>,
+    /// 预埋板类型（墙板下的结构附件）
+    pub plate_nouns: Vec<String>,
+}
+
+impl Default for EmbedPlateCheckConfig {
+    fn default() -> Self {
+        Self {
+            min_edge_distance: 150.0,
+            plate_margin: 0.0,
+            host_tolerance: 50.0,
+            anchor_nouns: vec!["FITT".to_string()],
+            plate_nouns: vec!["FITT".to_string()],
+        }
+    }
+}
+
+/// 宿主墙板及其预埋板
+#[derive(Debug, Clone)]
+pub struct HostPanel {
+    pub refno: RefnoEnum,
+    pub aabb: Aabb,
+    pub plates: Vec<(RefnoEnum, Aabb)>,
+}
+
+impl HostPanel {
+    /// 墙板厚度方向：包围盒最薄的轴
+    #[inline]
+    fn normal_axis(&self) -> usize {
+        self.aabb.extents().imin()
+    }
+
+    /// 面内到边缘的最小距离
+    fn edge_distance(&self, p: Vec3) -> f32 {
+        let n = self.normal_axis();
+        (0..3)
+            .filter(|&i| i != n)
+            .map(|i| (p[i] - self.aabb.mins[i]).min(self.aabb.maxs[i] - p[i]))
+            .fold(f32::MAX, f32::min)
+    }
+
+    /// 面内包含判断：锚固件在面内两个方向都位于预埋板内（至少留 `margin`）
+    fn plate_contains(&self, plate: &Aabb, anchor: &Aabb, margin: f32) -> bool {
+        let n = self.normal_axis();
+        (0..3).filter(|&i| i != n).all(|i| {
+            anchor.mins[i] >= plate.mins[i] + margin && anchor.maxs[i] <= plate.maxs[i] - margin
+        })
+    }
+}
+
+/// 参与检查的锚固件
+#[derive(Debug, Clone)]
+pub struct AnchorProbe {
+    /// 锚固件
+    pub refno: RefnoEnum,
+    /// 所属支吊架
+    pub support: RefnoEnum,
+    pub aabb: Aabb,
+}
+
+#[inline]
+fn aabb_center(aabb: &Aabb) -> Vec3 {
+    Vec3::from(aabb.center().coords)
+}
+
+/// 为锚固件寻找宿主墙板：包围盒（外扩容差后）包含锚固件中心，多块时取最薄方向距离最近者
+pub fn find_host_panel<'a>(
+    anchor: &AnchorProbe,
+    panels: &'a [HostPanel],
+    tolerance: f32,
+) -> Option<&'a HostPanel> {
+    let c = aabb_center(&anchor.aabb);
+    panels
+        .iter()
+        .filter(|p| (0..3).all(|i| c[i] >= p.aabb.mins[i] - tolerance && c[i] <= p.aabb.maxs[i] + tolerance))
+        .min_by(|a, b| {
+            let da = (c - aabb_center(&a.aabb))[a.normal_axis()].abs();
+            let db = (c - aabb_center(&b.aabb))[b.normal_axis()].abs();
+            da.total_cmp(&db)
+        })
+}
+
+/// 检查单个锚固件
+pub fn check_anchor(
+    anchor: &AnchorProbe,
+    panels: &[HostPanel],
+    config: &EmbedPlateCheckConfig,
+) -> Vec<InspectionIssue> {
+    let issue = |related: Option<RefnoEnum>, severity, code: &str, message: String, value, limit| {
+        InspectionIssue {
+            check: EMBED_PLATE_CHECK.to_string(),
+            refno: anchor.refno,
+            related,
+            severity,
+            code: code.to_string(),
+            message,
+            value,
+            limit,
+        }
+    };
+    let Some(host) = find_host_panel(anchor, panels, config.host_tolerance) else {
+        return vec![issue(
+            None,
+            InspectionSeverity::Warning,
+            "NO_HOST_PANEL",
+            format!("支吊架 {} 的锚固件未找到宿主墙板", anchor.support),
+            None,
+            None,
+        )];
+    };
+
+    let mut issues = vec![];
+    if !host
+        .plates
+        .iter()
+        .any(|(_, plate)| host.plate_contains(plate, &anchor.aabb, config.plate_margin))
+    {
+        issues.push(issue(
+            Some(host.refno),
+            InspectionSeverity::Error,
+            "OUTSIDE_EMBED_PLATE",
+            format!(
+                "支吊架 {} 的锚固件超出墙板 {} 的预埋板范围",
+                anchor.support, host.refno
+            ),
+            None,
+            Some(config.plate_margin),
+        ));
+    }
+    let edge = host.edge_distance(aabb_center(&anchor.aabb));
+    if edge < config.min_edge_distance {
+        issues.push(issue(
+            Some(host.refno),
+            InspectionSeverity::Error,
+            "TOO_CLOSE_TO_EDGE",
+            format!(
+                "支吊架 {} 的锚固件距墙板 {} 边缘 {:.1}mm，小于 {:.1}mm",
+                anchor.support, host.refno, edge, config.min_edge_distance
+            ),
+            Some(edge),
+            Some(config.min_edge_distance),
+        ));
+    }
+    issues
+}
+
+/// 批量检查
+pub fn check_anchors(
+    anchors: &[AnchorProbe],
+    panels: &[HostPanel],
+    config: &EmbedPlateCheckConfig,
+) -> Vec<InspectionIssue> {
+    anchors
+        .iter()
+        .flat_map(|a| check_anchor(a, panels, config))
+        .collect()
+}
+
+async fn query_world_aabbs(refnos: &[RefnoEnum]) -> anyhow::Result<Vec<(RefnoEnum, Aabb)>> {
+    let insts = crate::rs_surreal::inst::query_insts(refnos, false).await?;
+    Ok(insts
+        .into_iter()
+        .filter_map(|i| i.world_aabb.map(|a| (i.refno, a.0)))
+        .collect())
+}
+
+/// 从数据库加载墙板及其下的预埋板
+pub async fn load_host_panels(
+    panels: &[RefnoEnum],
+    config: &EmbedPlateCheckConfig,
+) -> anyhow::Result<Vec<HostPanel>> {
+    let plate_nouns = config.plate_nouns.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let mut result = vec![];
+    for (refno, aabb) in query_world_aabbs(panels).await? {
+        let plate_refnos = crate::rs_surreal::query_filter_deep_children(refno, &plate_nouns).await?;
+        let plates = query_world_aabbs(&plate_refnos).await?;
+        result.push(HostPanel {
+            refno,
+            aabb,
+            plates,
+        });
+    }
+    Ok(result)
+}
+
+/// 从数据库加载支吊架下的锚固件
+pub async fn load_anchors(
+    supports: &[RefnoEnum],
+    config: &EmbedPlateCheckConfig,
+) -> anyhow::Result<Vec<AnchorProbe>> {
+    let anchor_nouns = config.anchor_nouns.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let mut result = vec![];
+    for &support in supports {
+        let refnos = crate::rs_surreal::query_filter_deep_children(support, &anchor_nouns).await?;
+        result.extend(
+            query_world_aabbs(&refnos)
+                .await?
+                .into_iter()
+                .map(|(refno, aabb)| AnchorProbe {
+                    refno,
+                    support,
+                    aabb,
+                }),
+        );
+    }
+    Ok(result)
+}
+
+/// 执行预埋板检查并写入检查结果表
+pub async fn run_embed_plate_check(
+    supports: &[RefnoEnum],
+    panels: &[RefnoEnum],
+    config: &EmbedPlateCheckConfig,
+) -> anyhow::Result<Vec<InspectionIssue>> {
+    let host_panels = load_host_panels(panels, config).await?;
+    let anchors = load_anchors(supports, config).await?;
+    let issues = check_anchors(&anchors, &host_panels, config);
+    let scope = anchors.iter().map(|a| a.refno).collect::<Vec<_>>();
+    save_inspection_issues(EMBED_PLATE_CHECK, &scope, &issues).await?;
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use nalgebra::Point3;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(1, n).into()
+    }
+
+    fn aabb(mins: [f32; 3], maxs: [f32; 3]) -> Aabb {
+        Aabb::new(Point3::from(mins), Point3::from(maxs))
+    }
+
+    fn anchor(n: u32, x: f32, z: f32) -> AnchorProbe {
+        AnchorProbe {
+            refno: r(n),
+            support: r(100 + n),
+            aabb: aabb([x - 50.0, -10.0, z - 50.0], [x + 50.0, 0.0, z + 50.0]),
+        }
+    }
+
+    #[test]
+    fn test_embed_plate_check() {
+        // 墙板位于 XZ 平面，厚度沿 Y
+        let panels = vec![HostPanel {
+            refno: r(1),
+            aabb: aabb([0.0, 0.0, 0.0], [3000.0, 300.0, 3000.0]),
+            plates: vec![
+                (r(2), aabb([400.0, -20.0, 400.0], [800.0, 0.0, 800.0])),
+                (r(3), aabb([0.0, -20.0, 2000.0], [400.0, 0.0, 2400.0])),
+            ],
+        }];
+        let config = EmbedPlateCheckConfig::default();
+
+        assert!(check_anchor(&anchor(1, 600.0, 600.0), &panels, &config).is_empty());
+
+        let outside = check_anchor(&anchor(2, 1500.0, 1500.0), &panels, &config);
+        assert_eq!(outside.len(), 1);
+        assert_eq!(outside[0].code, "OUTSIDE_EMBED_PLATE");
+        assert_eq!(outside[0].related, Some(r(1)));
+
+        let edge = check_anchor(&anchor(3, 100.0, 2200.0), &panels, &config);
+        assert_eq!(edge.len(), 1);
+        assert_eq!(edge[0].code, "TOO_CLOSE_TO_EDGE");
+        assert_eq!(edge[0].value, Some(100.0));
+
+        let orphan = check_anchor(&anchor(4, 6000.0, 600.0), &panels, &config);
+        assert_eq!(orphan[0].code, "NO_HOST_PANEL");
+    }
+}
@@ -0,0 +1,95 @@
+//! 检查结果表
+//!
+//! 各类设计检查（如预埋板/锚固检查）的结果统一写入 `inspection_issue` 表，
+//! 以 `check` 字段区分检查类型。重新执行某项检查时，先清除该检查下相同元素的旧结果。
+
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, join_pe_keys};
+use serde::{Deserialize, Serialize};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 检查结果表名
+pub const INSPECTION_TABLE: &str = "inspection_issue";
+
+/// 问题严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, SurrealValue)]
+pub enum InspectionSeverity {
+    Warning,
+    Error,
+}
+
+/// 一条检查结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct InspectionIssue {
+    /// 检查类型，如 `embed_plate`
+    pub check: String,
+    /// 问题元素
+    pub refno: RefnoEnum,
+    /// 关联元素（如宿主墙板），可为空
+    pub related: Option<RefnoEnum>,
+    pub severity: InspectionSeverity,
+    /// 问题分类代码
+    pub code: String,
+    pub message: String,
+    /// 实测值（如距离，mm）
+    pub value: Option<f32>,
+    /// 限值
+    pub limit: Option<f32>,
+}
+
+impl InspectionIssue {
+    fn to_surreal_json(&self) -> String {
+        let related = self
+            .related
+            .map(|r| r.to_pe_key())
+            .unwrap_or_else(|| "NONE".to_string());
+        let num = |v: Option<f32>| v.map(|v| v.to_string()).unwrap_or_else(|| "NONE".to_string());
+        format!(
+            "{{check: {}, refno: {}, related: {}, severity: {}, code: {}, message: {}, value: {}, limit: {}, time: time::now()}}",
+            serde_json::to_string(&self.check).unwrap_or_default(),
+            self.refno.to_pe_key(),
+            related,
+            serde_json::to_string(&self.severity).unwrap_or_default(),
+            serde_json::to_string(&self.code).unwrap_or_default(),
+            serde_json::to_string(&self.message).unwrap_or_default(),
+            num(self.value),
+            num(self.limit),
+        )
+    }
+}
+
+/// 保存检查结果
+///
+/// 先删除 `check` 下 `scope` 中元素的旧结果，再写入新结果，
+/// 因此没有问题的元素也应放入 `scope` 以清除历史记录。
+pub async fn save_inspection_issues(
+    check: &str,
+    scope: &[RefnoEnum],
+    issues: &[InspectionIssue],
+) -> anyhow::Result<()> {
+    if !scope.is_empty() {
+        let sql = format!(
+            "DELETE {INSPECTION_TABLE} WHERE check = {} AND refno IN [{}];",
+            serde_json::to_string(check)?,
+            join_pe_keys(scope.iter())
+        );
+        SUL_DB.query_response(&sql).await?;
+    }
+    for chunk in issues.chunks(300) {
+        let sql = chunk
+            .iter()
+            .map(|i| format!("CREATE {INSPECTION_TABLE} CONTENT {};", i.to_surreal_json()))
+            .collect::<String>();
+        SUL_DB.query_response(&sql).await?;
+    }
+    Ok(())
+}
+
+/// 查询某项检查的结果
+pub async fn query_inspection_issues(check: &str) -> anyhow::Result<Vec<InspectionIssue>> {
+    let sql = format!(
+        "SELECT check, refno, related, severity, code, message, value, limit FROM {INSPECTION_TABLE} WHERE check = {}",
+        serde_json::to_string(check)?
+    );
+    SUL_DB.query_take(&sql, 0).await
+}
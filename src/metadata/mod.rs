@@ -1,3 +1,6 @@
+pub mod branch_connection;
+pub mod embed_plate_check;
+pub mod inspection;
 pub mod spatial_computation;
 
 #[cfg(test)]
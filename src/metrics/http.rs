@@ -0,0 +1,75 @@
+//! 简易 `/metrics` HTTP 导出（`metrics-http` 特性）
+//!
+//! 只处理 `GET /metrics`，其余请求返回 404，供 Prometheus 拉取；
+//! 已有 Web 服务的进程直接把 [`super::gather`] 挂到路由上即可。
+
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 在 `addr` 上监听，直到进程退出
+pub async fn serve(addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("指标导出监听 http://{}/metrics", addr);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream).await {
+                log::debug!("指标请求处理失败: {}", e);
+            }
+        });
+    }
+}
+
+/// 后台启动导出任务
+pub fn spawn(addr: SocketAddr) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = serve(addr).await {
+            log::error!("指标导出服务退出: {}", e);
+        }
+    })
+}
+
+async fn handle(mut stream: TcpStream) -> anyhow::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let response = match request_path(&request) {
+        Some("/metrics") => {
+            let body = super::gather();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// 取 GET 请求的路径（去掉查询串）
+fn request_path(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+    parts.next()?.split('?').next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(request_path("GET /metrics HTTP/1.1\r\n"), Some("/metrics"));
+        assert_eq!(
+            request_path("GET /metrics?x=1 HTTP/1.1\r\n"),
+            Some("/metrics")
+        );
+        assert_eq!(request_path("POST /metrics HTTP/1.1\r\n"), None);
+    }
+}
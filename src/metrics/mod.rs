@@ -0,0 +1,462 @@
+//! 运行时指标（Prometheus 文本格式）
+//!
+//! 查询、缓存、实时订阅、批量写入和同步各子系统在关键路径上埋点，
+//! 通过 [`gather`] 输出 Prometheus 文本，可挂到已有的 Web 服务上，
+//! 或启用 `metrics-http` 特性后用 [`http::serve`] 单独暴露 `/metrics`。
+//!
+//! 指标一览：
+//! - `aios_query_duration_seconds{location}`：查询耗时，按调用位置（文件:行）区分
+//! - `aios_query_errors_total{location}`：查询失败次数
+//! - `aios_cache_hits_total` / `aios_cache_misses_total` / `aios_cache_hit_rate{cache}`：属性缓存命中情况
+//! - `aios_live_events_total{subscription,kind}`：实时订阅收到的事件数（live / replayed）
+//! - `aios_live_lag_seconds{subscription}`：距离最近一次收到事件的时长
+//! - `aios_bulk_records_total{table}` / `aios_bulk_duration_seconds{table}` / `aios_bulk_throughput{table}`：批量写入
+//! - `aios_sync_records_total{status}` / `aios_sync_duration_seconds`：数据同步
+
+#[cfg(feature = "metrics-http")]
+pub mod http;
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// 默认耗时桶（秒）
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// 单调递增计数器
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 可任意设置的浮点值
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicU64);
+
+impl Gauge {
+    pub fn set(&self, v: f64) {
+        self.0.store(v.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// 固定桶直方图
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// 各桶（非累计）计数，最后一个为 +Inf
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    /// 总和，单位微秒，避免浮点原子累加
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, secs: f64) {
+        let idx = self
+            .bounds
+            .iter()
+            .position(|b| secs <= *b)
+            .unwrap_or(self.bounds.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add((secs.max(0.0) * 1e6) as u64, Ordering::Relaxed);
+    }
+
+    pub fn observe_duration(&self, d: Duration) {
+        self.observe(d.as_secs_f64());
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6
+    }
+
+    /// 累计桶计数，与 `bounds` 一一对应，最后一个为 +Inf
+    pub fn cumulative(&self) -> Vec<u64> {
+        let mut acc = 0;
+        self.buckets
+            .iter()
+            .map(|b| {
+                acc += b.load(Ordering::Relaxed);
+                acc
+            })
+            .collect()
+    }
+}
+
+/// 同名指标按标签值区分
+#[derive(Debug)]
+pub struct Family<M> {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    metrics: DashMap<Vec<String>, M>,
+}
+
+impl<M> Family<M> {
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            metrics: DashMap::new(),
+        }
+    }
+
+    /// 按标签值取指标，不存在时用 `init` 创建
+    fn get_or(
+        &self,
+        values: &[&str],
+        init: impl FnOnce() -> M,
+    ) -> dashmap::mapref::one::Ref<'_, Vec<String>, M> {
+        debug_assert_eq!(
+            values.len(),
+            self.labels.len(),
+            "{} 标签数量不符",
+            self.name
+        );
+        let key: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        if let Some(m) = self.metrics.get(&key) {
+            return m;
+        }
+        self.metrics.entry(key).or_insert_with(init).downgrade()
+    }
+
+    /// 按标签排序后的快照，输出稳定
+    fn sorted<T>(&self, f: impl Fn(&M) -> T) -> Vec<(Vec<String>, T)> {
+        let mut items: Vec<_> = self
+            .metrics
+            .iter()
+            .map(|e| (e.key().clone(), f(e.value())))
+            .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items
+    }
+
+    fn label_str(&self, values: &[String], extra: Option<(&str, &str)>) -> String {
+        let pairs = self
+            .labels
+            .iter()
+            .zip(values)
+            .map(|(k, v)| (*k, v.as_str()))
+            .chain(extra);
+        let body = pairs
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        if body.is_empty() {
+            body
+        } else {
+            format!("{{{}}}", body)
+        }
+    }
+
+    fn write_header(&self, out: &mut String, kind: &str) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, kind);
+    }
+}
+
+impl Family<Counter> {
+    pub fn with(&self, values: &[&str]) -> dashmap::mapref::one::Ref<'_, Vec<String>, Counter> {
+        self.get_or(values, Counter::default)
+    }
+
+    fn encode(&self, out: &mut String) {
+        self.write_header(out, "counter");
+        for (labels, v) in self.sorted(Counter::get) {
+            let _ = writeln!(out, "{}{} {}", self.name, self.label_str(&labels, None), v);
+        }
+    }
+}
+
+impl Family<Gauge> {
+    pub fn with(&self, values: &[&str]) -> dashmap::mapref::one::Ref<'_, Vec<String>, Gauge> {
+        self.get_or(values, Gauge::default)
+    }
+
+    fn encode(&self, out: &mut String) {
+        self.write_header(out, "gauge");
+        for (labels, v) in self.sorted(Gauge::get) {
+            let _ = writeln!(out, "{}{} {}", self.name, self.label_str(&labels, None), v);
+        }
+    }
+}
+
+impl Family<Histogram> {
+    pub fn with(&self, values: &[&str]) -> dashmap::mapref::one::Ref<'_, Vec<String>, Histogram> {
+        self.get_or(values, || Histogram::new(DEFAULT_BUCKETS))
+    }
+
+    fn encode(&self, out: &mut String) {
+        self.write_header(out, "histogram");
+        for (labels, (bounds, cumulative, sum, count)) in
+            self.sorted(|h| (h.bounds, h.cumulative(), h.sum(), h.count()))
+        {
+            let les = bounds
+                .iter()
+                .map(|b| b.to_string())
+                .chain(std::iter::once("+Inf".to_string()));
+            for (le, c) in les.zip(cumulative) {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    self.name,
+                    self.label_str(&labels, Some(("le", &le))),
+                    c
+                );
+            }
+            let label = self.label_str(&labels, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, label, sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, label, count);
+        }
+    }
+}
+
+fn escape_label(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 全局指标注册表
+pub struct Registry {
+    pub query_duration: Family<Histogram>,
+    pub query_errors: Family<Counter>,
+    pub cache_hits: Family<Gauge>,
+    pub cache_misses: Family<Gauge>,
+    pub cache_hit_rate: Family<Gauge>,
+    pub live_events: Family<Counter>,
+    pub live_lag: Family<Gauge>,
+    pub bulk_records: Family<Counter>,
+    pub bulk_duration: Family<Histogram>,
+    pub bulk_throughput: Family<Gauge>,
+    pub sync_records: Family<Counter>,
+    pub sync_duration: Family<Histogram>,
+}
+
+impl Registry {
+    fn new() -> Self {
+        Self {
+            query_duration: Family::new(
+                "aios_query_duration_seconds",
+                "SurrealDB query latency by call site",
+                &["location"],
+            ),
+            query_errors: Family::new(
+                "aios_query_errors_total",
+                "Failed SurrealDB queries by call site",
+                &["location"],
+            ),
+            // 缓存命中数由缓存自身计数，采集时同步，所以用 gauge 表示
+            cache_hits: Family::new("aios_cache_hits_total", "Attribute cache hits", &["cache"]),
+            cache_misses: Family::new(
+                "aios_cache_misses_total",
+                "Attribute cache misses",
+                &["cache"],
+            ),
+            cache_hit_rate: Family::new(
+                "aios_cache_hit_rate",
+                "Attribute cache hit rate",
+                &["cache"],
+            ),
+            live_events: Family::new(
+                "aios_live_events_total",
+                "Live query events received",
+                &["subscription", "kind"],
+            ),
+            live_lag: Family::new(
+                "aios_live_lag_seconds",
+                "Seconds since the last live query event",
+                &["subscription"],
+            ),
+            bulk_records: Family::new(
+                "aios_bulk_records_total",
+                "Records written by bulk insert",
+                &["table"],
+            ),
+            bulk_duration: Family::new(
+                "aios_bulk_duration_seconds",
+                "Bulk insert duration",
+                &["table"],
+            ),
+            bulk_throughput: Family::new(
+                "aios_bulk_throughput",
+                "Records per second of the last bulk insert",
+                &["table"],
+            ),
+            sync_records: Family::new(
+                "aios_sync_records_total",
+                "Records processed by sync tasks",
+                &["status"],
+            ),
+            sync_duration: Family::new("aios_sync_duration_seconds", "Sync batch duration", &[]),
+        }
+    }
+
+    /// 采集时才能得到的指标（缓存、订阅延迟）
+    fn refresh(&self) {
+        for (name, stats) in crate::cache::ATTR_CACHES.stats() {
+            self.cache_hits.with(&[name]).set(stats.hits as f64);
+            self.cache_misses.with(&[name]).set(stats.misses as f64);
+            self.cache_hit_rate.with(&[name]).set(stats.hit_rate());
+        }
+        let now = SystemTime::now();
+        for health in crate::runtime::health::subscription_health() {
+            if let Some(last) = health.last_event {
+                let lag = now.duration_since(last).unwrap_or_default();
+                self.live_lag.with(&[&health.name]).set(lag.as_secs_f64());
+            }
+        }
+    }
+
+    fn encode(&self) -> String {
+        let mut out = String::new();
+        self.query_duration.encode(&mut out);
+        self.query_errors.encode(&mut out);
+        self.cache_hits.encode(&mut out);
+        self.cache_misses.encode(&mut out);
+        self.cache_hit_rate.encode(&mut out);
+        self.live_events.encode(&mut out);
+        self.live_lag.encode(&mut out);
+        self.bulk_records.encode(&mut out);
+        self.bulk_duration.encode(&mut out);
+        self.bulk_throughput.encode(&mut out);
+        self.sync_records.encode(&mut out);
+        self.sync_duration.encode(&mut out);
+        out
+    }
+}
+
+pub static METRICS: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// 采集全部指标，返回 Prometheus 文本格式
+pub fn gather() -> String {
+    METRICS.refresh();
+    METRICS.encode()
+}
+
+/// 记录一次查询
+pub fn observe_query(location: &std::panic::Location<'_>, elapsed: Duration, ok: bool) {
+    let location = format!("{}:{}", location.file(), location.line());
+    METRICS
+        .query_duration
+        .with(&[&location])
+        .observe_duration(elapsed);
+    if !ok {
+        METRICS.query_errors.with(&[&location]).inc();
+    }
+}
+
+/// 记录一次实时订阅事件，`kind` 为 `live` 或 `replayed`
+pub fn observe_live_event(subscription: &str, kind: &str) {
+    METRICS.live_events.with(&[subscription, kind]).inc();
+}
+
+/// 记录一次批量写入
+pub fn observe_bulk(table: &str, written: usize, elapsed: Duration) {
+    METRICS.bulk_records.with(&[table]).add(written as u64);
+    METRICS
+        .bulk_duration
+        .with(&[table])
+        .observe_duration(elapsed);
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        METRICS
+            .bulk_throughput
+            .with(&[table])
+            .set(written as f64 / secs);
+    }
+}
+
+/// 记录一批同步结果
+pub fn observe_sync(stats: &crate::sync::SyncStatistics) {
+    let sync = &METRICS.sync_records;
+    sync.with(&["success"]).add(stats.successful_records as u64);
+    sync.with(&["failed"]).add(stats.failed_records as u64);
+    sync.with(&["skipped"]).add(stats.skipped_records as u64);
+    METRICS
+        .sync_duration
+        .with(&[])
+        .observe_duration(stats.total_duration);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_cumulative() {
+        let h = Histogram::new(&[0.1, 1.0]);
+        h.observe(0.05);
+        h.observe(0.5);
+        h.observe(5.0);
+        assert_eq!(h.cumulative(), vec![1, 2, 3]);
+        assert_eq!(h.count(), 3);
+        assert!((h.sum() - 5.55).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_family_encode() {
+        let family: Family<Histogram> = Family::new("t_seconds", "test", &["location"]);
+        family.with(&["a.rs:1"]).observe(0.002);
+        let mut out = String::new();
+        family.encode(&mut out);
+        assert!(out.contains("# TYPE t_seconds histogram"));
+        assert!(out.contains("t_seconds_bucket{location=\"a.rs:1\",le=\"0.001\"} 0"));
+        assert!(out.contains("t_seconds_bucket{location=\"a.rs:1\",le=\"0.005\"} 1"));
+        assert!(out.contains("t_seconds_bucket{location=\"a.rs:1\",le=\"+Inf\"} 1"));
+        assert!(out.contains("t_seconds_count{location=\"a.rs:1\"} 1"));
+
+        let counters: Family<Counter> = Family::new("t_total", "test", &["kind"]);
+        counters.with(&["x\"y"]).add(2);
+        let mut out = String::new();
+        counters.encode(&mut out);
+        assert!(out.contains("t_total{kind=\"x\\\"y\"} 2"));
+    }
+
+    #[test]
+    fn test_gather_contains_bulk() {
+        observe_bulk("metrics_test", 100, Duration::from_millis(50));
+        let text = gather();
+        assert!(text.contains("aios_bulk_records_total{table=\"metrics_test\"} 100"));
+        assert!(text.contains("# TYPE aios_query_duration_seconds histogram"));
+    }
+}
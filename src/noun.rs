@@ -0,0 +1,438 @@
+//! 名词 / 属性名的 base27 哈希
+//!
+//! PDMS 用 base27 把不超过 6 个字符的名称编码为 u32：空格为 0，`A`..`Z` 为 1..26，
+//! 低位在前，再加上偏移 [`BASE27_OFFSET`]。大于 [`UDA_OFFSET`] 的哈希为用户定义属性（UDA），
+//! 低 24 位按 base64（字符减 32）存放最多 4 个字符，高位为 UDA 序号。
+//!
+//! 本模块统一了原先分散在 `attlib_parser` 与 `tool::db_tool` 中的实现，两处仍以原名称重新导出：
+//!
+//! - [`hash`] / [`hash_const`]：宽松编码，与旧的 `db1_hash` 结果一致，只额外接受小写与空格
+//! - [`encode`]：严格编码，拒绝非法字符、超长与末尾空格，支持 UDNA 名称
+//! - [`decode`]：反查名称，UDA 优先取注册表中的完整名称
+//! - [`validate_roundtrip`]：编码后再解码，确认名称可以无损往返
+//!
+//! UDNA（用户定义名称）以 `/` 开头（旧写法 `:` 同样接受），长度不限。
+//! 不超过 4 个字符的 UDNA 可直接编码；更长的需先通过 [`register_udna`] 登记其哈希。
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::num::NonZeroUsize;
+
+/// base27 偏移
+pub const BASE27_OFFSET: u32 = 0x81BF1;
+/// 最小的有效 base27 哈希（单字符 `A`）
+pub const BASE27_MIN: u32 = BASE27_OFFSET + 1;
+/// UDA 偏移，同时也是最大的 base27 哈希
+pub const UDA_OFFSET: u32 = 0x171FAD39;
+/// base27 名称的最大长度
+pub const MAX_BASE27_LEN: usize = 6;
+/// UDA 哈希低 24 位可容纳的字符数
+pub const MAX_UDA_PACKED_LEN: usize = 4;
+/// UDNA 名称前缀
+pub const UDNA_PREFIX: char = '/';
+/// 旧的 UDA 名称前缀
+pub const UDA_PREFIX: char = ':';
+
+lazy_static! {
+    /// UDA 哈希 -> 名称（不含前缀）
+    pub static ref GLOBAL_UDA_NAME_MAP: DashMap<u32, String> = DashMap::new();
+    /// UDA 名称（不含前缀，大写）-> 哈希
+    pub static ref GLOBAL_UDA_UKEY_MAP: DashMap<String, u32> = DashMap::new();
+}
+
+/// 哈希反查缓存
+///
+/// 使用 LRU 缓存最近查询的结果，避免重复计算
+/// 缓存大小设置为 1000，适合大多数使用场景
+static DEHASH_CACHE: Lazy<Mutex<LruCache<u32, String>>> =
+    Lazy::new(|| Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap())));
+
+/// 名称编码 / 解码错误
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NounError {
+    #[error("名称为空")]
+    Empty,
+    #[error("名称 {name:?} 含非法字符 {ch:?}")]
+    InvalidChar { name: String, ch: char },
+    #[error("名称 {name:?} 超过 {max} 个字符")]
+    TooLong { name: String, max: usize },
+    #[error("名称 {0:?} 以空格结尾，无法往返")]
+    TrailingSpace(String),
+    #[error("UDNA {0:?} 超过 4 个字符且未登记")]
+    UnregisteredUdna(String),
+    #[error("哈希 {0:#x} 不在有效范围内")]
+    OutOfRange(u32),
+    #[error("名称 {name:?} 编码为 {hash:#x} 后解码为 {decoded:?}")]
+    RoundTrip {
+        name: String,
+        hash: u32,
+        decoded: String,
+    },
+}
+
+const fn digit(b: u8) -> i32 {
+    match b {
+        b' ' => 0,
+        b'a'..=b'z' => (b - b'a' + 1) as i32,
+        _ => b as i32 - 64,
+    }
+}
+
+/// 宽松编码（编译期可用），空字符串返回 0，不校验字符与长度
+pub const fn hash_const(name: &str) -> u32 {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() {
+        return 0;
+    }
+    let mut val = 0u32;
+    let mut i = bytes.len();
+    while i > 0 {
+        i -= 1;
+        val = val.wrapping_mul(27).wrapping_add_signed(digit(bytes[i]));
+    }
+    val.wrapping_add(BASE27_OFFSET)
+}
+
+/// 宽松编码，见 [`hash_const`]
+#[inline]
+pub fn hash(name: &str) -> u32 {
+    hash_const(name)
+}
+
+#[inline]
+pub fn hash_i32(name: &str) -> i32 {
+    hash_const(name) as _
+}
+
+/// 批量宽松编码
+pub fn hash_batch<I, S>(names: I) -> Vec<u32>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    names.into_iter().map(|n| hash(n.as_ref())).collect()
+}
+
+fn check_base27(name: &str) -> Result<(), NounError> {
+    if name.is_empty() {
+        return Err(NounError::Empty);
+    }
+    if let Some(ch) = name.chars().find(|c| *c != ' ' && !c.is_ascii_uppercase()) {
+        return Err(NounError::InvalidChar {
+            name: name.to_string(),
+            ch,
+        });
+    }
+    if name.len() > MAX_BASE27_LEN {
+        return Err(NounError::TooLong {
+            name: name.to_string(),
+            max: MAX_BASE27_LEN,
+        });
+    }
+    if name.ends_with(' ') {
+        return Err(NounError::TrailingSpace(name.to_string()));
+    }
+    Ok(())
+}
+
+/// UDNA 的注册表键：去掉前缀并转为大写
+fn udna_key(name: &str) -> String {
+    name.strip_prefix([UDNA_PREFIX, UDA_PREFIX])
+        .unwrap_or(name)
+        .to_ascii_uppercase()
+}
+
+fn encode_udna(name: &str) -> Result<u32, NounError> {
+    let key = udna_key(name);
+    if key.is_empty() {
+        return Err(NounError::Empty);
+    }
+    if let Some(hash) = GLOBAL_UDA_UKEY_MAP.get(&key) {
+        return Ok(*hash);
+    }
+    if key.len() > MAX_UDA_PACKED_LEN {
+        return Err(NounError::UnregisteredUdna(name.to_string()));
+    }
+    if let Some(ch) = key.chars().find(|c| !(' '..='_').contains(c)) {
+        return Err(NounError::InvalidChar {
+            name: name.to_string(),
+            ch,
+        });
+    }
+    if key.ends_with(' ') {
+        return Err(NounError::TrailingSpace(name.to_string()));
+    }
+    let packed = key
+        .bytes()
+        .rev()
+        .fold(0u32, |k, b| k * 64 + (b - b' ') as u32);
+    Ok(UDA_OFFSET + packed)
+}
+
+/// 严格编码
+///
+/// 普通名称只允许 `A`..`Z` 与中间的空格（小写自动转大写），最多 6 个字符；
+/// 以 `/` 或 `:` 开头的为 UDNA。
+pub fn encode(name: &str) -> Result<u32, NounError> {
+    if name.starts_with([UDNA_PREFIX, UDA_PREFIX]) {
+        return encode_udna(name);
+    }
+    let upper = name.to_ascii_uppercase();
+    check_base27(&upper)?;
+    Ok(hash_const(&upper))
+}
+
+/// 批量严格编码，结果与输入一一对应
+pub fn encode_batch<I, S>(names: I) -> Vec<Result<u32, NounError>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    names.into_iter().map(|n| encode(n.as_ref())).collect()
+}
+
+fn decode_base27(hash: u32) -> String {
+    let mut result = String::new();
+    if hash < BASE27_MIN || hash > UDA_OFFSET {
+        return result;
+    }
+    let mut k = hash - BASE27_OFFSET;
+    while k > 0 {
+        match k % 27 {
+            0 => result.push(' '),
+            c => result.push((c as u8 + 64) as char),
+        }
+        k /= 27;
+    }
+    result
+}
+
+fn decode_uda(hash: u32) -> String {
+    if let Some(name) = GLOBAL_UDA_NAME_MAP.get(&hash) {
+        return format!("{UDNA_PREFIX}{}", name.as_str());
+    }
+    let mut result = String::from(UDA_PREFIX);
+    let mut k = (hash - UDA_OFFSET) % 0x1000000;
+    while k > 0 {
+        result.push((k % 64 + 32) as u8 as char);
+        k /= 64;
+    }
+    result
+}
+
+/// 哈希反查名称，无效哈希返回空字符串
+///
+/// 已登记的 UDA 返回 `/完整名称`，否则返回低 24 位中的 `:缩写`。
+pub fn decode(hash: u32) -> String {
+    if hash > UDA_OFFSET {
+        decode_uda(hash)
+    } else {
+        decode_base27(hash)
+    }
+}
+
+/// 带 LRU 缓存的 [`decode`]
+pub fn decode_cached(hash: u32) -> String {
+    if let Some(cached) = DEHASH_CACHE.lock().get(&hash) {
+        return cached.clone();
+    }
+    let result = decode(hash);
+    DEHASH_CACHE.lock().put(hash, result.clone());
+    result
+}
+
+/// 哈希反查名称，无效哈希返回错误
+pub fn try_decode(hash: u32) -> Result<String, NounError> {
+    let name = decode(hash);
+    // UDA 低 24 位为 0 时只剩前缀
+    if name.len() <= 1 && (name.is_empty() || hash > UDA_OFFSET) {
+        return Err(NounError::OutOfRange(hash));
+    }
+    Ok(name)
+}
+
+/// 批量反查，结果与输入一一对应
+pub fn decode_batch(hashes: &[u32]) -> Vec<String> {
+    hashes.iter().map(|&h| decode(h)).collect()
+}
+
+/// 编码后再解码，确认名称可无损往返，成功返回哈希
+pub fn validate_roundtrip(name: &str) -> Result<u32, NounError> {
+    let hash = encode(name)?;
+    let decoded = decode(hash);
+    let same = if name.starts_with([UDNA_PREFIX, UDA_PREFIX]) {
+        decoded.starts_with([UDNA_PREFIX, UDA_PREFIX]) && udna_key(&decoded) == udna_key(name)
+    } else {
+        decoded == name.to_ascii_uppercase()
+    };
+    if same {
+        Ok(hash)
+    } else {
+        Err(NounError::RoundTrip {
+            name: name.to_string(),
+            hash,
+            decoded,
+        })
+    }
+}
+
+/// 批量校验，返回未通过的 (下标, 错误)
+pub fn validate_batch<I, S>(names: I) -> Vec<(usize, NounError)>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    names
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, n)| validate_roundtrip(n.as_ref()).err().map(|e| (i, e)))
+        .collect()
+}
+
+/// 登记 UDNA 名称与哈希，之后 [`encode`] / [`decode`] 可处理任意长度的该名称
+pub fn register_udna(name: &str, hash: u32) -> Result<(), NounError> {
+    if hash <= UDA_OFFSET {
+        return Err(NounError::OutOfRange(hash));
+    }
+    let key = udna_key(name);
+    if key.is_empty() {
+        return Err(NounError::Empty);
+    }
+    GLOBAL_UDA_UKEY_MAP.insert(key.clone(), hash);
+    GLOBAL_UDA_NAME_MAP.insert(hash, key);
+    DEHASH_CACHE.lock().pop(&hash);
+    Ok(())
+}
+
+#[inline]
+pub fn is_uda(hash: i32) -> bool {
+    hash > UDA_OFFSET as i32
+}
+
+#[inline]
+pub fn is_uda_name(name: &str) -> bool {
+    name.starts_with([UDA_PREFIX, UDNA_PREFIX])
+}
+
+#[inline]
+pub fn get_uda_index(hash: u32) -> Option<u32> {
+    if hash > UDA_OFFSET {
+        Some((hash - UDA_OFFSET) >> 24)
+    } else {
+        None
+    }
+}
+
+// 旧名称，供 `attlib_parser` 与 `tool::db_tool` 重新导出
+pub use self::decode_cached as db1_dehash;
+pub use self::hash as db1_hash;
+pub use self::hash_const as db1_hash_const;
+pub use self::hash_i32 as db1_hash_i32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_known_hashes() {
+        assert_eq!(hash("ELBO"), 0xCA439);
+        assert_eq!(hash("NAME"), 639374);
+        assert_eq!(encode("elbo"), Ok(0xCA439));
+        assert_eq!(decode(0xCA439), "ELBO");
+        assert_eq!(hash(""), 0);
+        assert_eq!(decode(0), "");
+    }
+
+    #[test]
+    fn test_encode_errors() {
+        assert_eq!(encode(""), Err(NounError::Empty));
+        assert!(matches!(
+            encode("PIPE1"),
+            Err(NounError::InvalidChar { ch: '1', .. })
+        ));
+        assert!(matches!(encode("ABCDEFG"), Err(NounError::TooLong { .. })));
+        assert!(matches!(encode("AB "), Err(NounError::TrailingSpace(_))));
+        assert!(matches!(
+            encode("/LONGNAME"),
+            Err(NounError::UnregisteredUdna(_))
+        ));
+        assert!(try_decode(0).is_err());
+    }
+
+    #[test]
+    fn test_udna() {
+        let short = encode(":AB1").unwrap();
+        assert!(is_uda(short as i32));
+        assert_eq!(decode(short), ":AB1");
+        assert_eq!(encode("/AB1"), Ok(short));
+
+        let hash = UDA_OFFSET + 0x3000000 + 7;
+        register_udna("/PIPESPEC", hash).unwrap();
+        assert_eq!(encode("/pipespec"), Ok(hash));
+        assert_eq!(decode_cached(hash), "/PIPESPEC");
+        assert_eq!(validate_roundtrip("/PIPESPEC"), Ok(hash));
+        assert_eq!(get_uda_index(hash), Some(3));
+    }
+
+    #[test]
+    fn test_batch() {
+        let names = ["PIPE", "BRAN", "bad!"];
+        let hashes = encode_batch(names);
+        assert!(hashes[2].is_err());
+        let ok: Vec<u32> = hashes.iter().take(2).map(|r| r.clone().unwrap()).collect();
+        assert_eq!(decode_batch(&ok), ["PIPE", "BRAN"]);
+        assert_eq!(hash_batch(["PIPE"]), [hash("PIPE")]);
+        let failed = validate_batch(names);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, 2);
+    }
+
+    /// 旧 `db1_hash` 的实现，用于确认兼容
+    fn legacy_hash(s: &str) -> u32 {
+        let mut val = 0i64;
+        for &b in s.as_bytes().iter().rev() {
+            val = val.overflowing_mul(27).0 + (b as i64 - 64);
+        }
+        val.saturating_add_unsigned(0x81BF1) as u32
+    }
+
+    proptest! {
+        #[test]
+        fn prop_name_roundtrip(name in "[A-Z]([A-Z ]{0,4}[A-Z])?") {
+            let hash = encode(&name).unwrap();
+            prop_assert!((BASE27_MIN..=UDA_OFFSET).contains(&hash));
+            prop_assert_eq!(decode(hash), name.clone());
+            prop_assert_eq!(validate_roundtrip(&name), Ok(hash));
+        }
+
+        #[test]
+        fn prop_hash_roundtrip(hash in BASE27_MIN..=UDA_OFFSET) {
+            prop_assert_eq!(encode(&decode(hash)), Ok(hash));
+        }
+
+        #[test]
+        fn prop_legacy_compatible(name in "[A-Z@]{1,12}") {
+            prop_assert_eq!(hash(&name), legacy_hash(&name));
+            prop_assert_eq!(hash_const(&name), hash(&name));
+        }
+
+        #[test]
+        fn prop_uda_roundtrip(name in ":[ -_]{0,3}[!-_]") {
+            let hash = encode(&name).unwrap();
+            prop_assert!(hash > UDA_OFFSET);
+            prop_assert_eq!(decode(hash), name);
+        }
+
+        #[test]
+        fn prop_batch_matches_single(names in prop::collection::vec("[A-Z]{1,6}", 0..32)) {
+            let hashes = encode_batch(&names);
+            prop_assert!(hashes.iter().zip(&names).all(|(h, n)| *h == encode(n)));
+            let hashes: Vec<u32> = hashes.into_iter().map(Result::unwrap).collect();
+            prop_assert_eq!(decode_batch(&hashes), names);
+        }
+    }
+}
@@ -377,40 +377,14 @@ impl Default for NounAttributeStore {
     }
 }
 
-/// PDMS 名称哈希编码
+/// PDMS 名称哈希编码，见 [`crate::noun::hash`]
 pub fn db1_hash(name: &str) -> u32 {
-    let mut val: u32 = 0;
-    for c in name.to_uppercase().chars().rev() {
-        if c.is_ascii_uppercase() {
-            val = val.wrapping_mul(27).wrapping_add((c as u32) - 64);
-        }
-    }
-    val.wrapping_add(0x81BF1)
+    crate::noun::hash(name)
 }
 
-/// PDMS 哈希解码为名称
+/// PDMS 哈希解码为名称，无效哈希返回 None
 pub fn db1_dehash(hash: u32) -> Option<String> {
-    const BASE27_MIN: u32 = 0x81BF2;
-    const BASE27_MAX: u32 = 0x171FAD39;
-
-    if hash < BASE27_MIN || hash > BASE27_MAX {
-        return None;
-    }
-
-    let mut k = hash - 0x81BF1;
-    let mut result = String::new();
-
-    while k > 0 {
-        let c = ((k % 27) + 64) as u8 as char;
-        result.push(c);
-        k /= 27;
-    }
-
-    if result.is_empty() {
-        None
-    } else {
-        Some(result)
-    }
+    crate::noun::try_decode(hash).ok()
 }
 
 // ============================================================================
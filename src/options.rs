@@ -1,6 +1,9 @@
 use std::path::{Path, PathBuf};
 
+use crate::cache::disk::DiskCacheConfig;
+use crate::helper::float_decode::{FloatDecodeError, FloatDecoder, FloatDecoderConfig};
 use crate::mesh_precision::MeshPrecisionSettings;
+use crate::mesh_store::MeshStoreConfig;
 use crate::{RefU64, RefnoEnum};
 use clap::Parser;
 use serde::{Deserialize, Serialize};
@@ -66,6 +69,14 @@ pub struct DbOption {
     /// 版本库的端口
     #[clap(long)]
     pub v_port: u16,
+    /// 版本库连接池大小（含主连接），缺省为 1
+    #[clap(long)]
+    #[serde(default)]
+    pub surreal_pool_size: Option<usize>,
+    /// Kuzu 图数据库目录，配置后 `QueryRouter` 将深层层级查询路由到 Kuzu（`kuzu` 特性）
+    #[clap(skip)]
+    #[serde(default)]
+    pub kuzu_path: Option<String>,
     // #[clap(long)]
     // pub kv_ip: String,
     // #[clap(long)]
@@ -289,6 +300,26 @@ pub struct DbOption {
     #[clap(long)]
     #[serde(default = "default_mem_kv_password")]
     pub mem_kv_password: String,
+
+    /// 数据库浮点解码配置，导入时按文件构造解码器
+    #[clap(skip)]
+    #[serde(default)]
+    pub float_decoder: FloatDecoderConfig,
+
+    /// 属性与世界变换的磁盘缓存
+    #[clap(skip)]
+    #[serde(default)]
+    pub disk_cache: DiskCacheConfig,
+
+    /// mesh 文件存储位置（本地目录或 S3）
+    #[clap(skip)]
+    #[serde(default)]
+    pub mesh_store: MeshStoreConfig,
+
+    /// 其它项目（机组）连接，启动时登记到 `PROJECT_REGISTRY`
+    #[clap(skip)]
+    #[serde(default)]
+    pub projects: Vec<ProjectDbOption>,
 }
 
 impl DbOption {
@@ -313,6 +344,15 @@ impl DbOption {
         self.build_cate_relate.unwrap_or(false)
     }
 
+    /// 为某个数据库文件构造浮点解码器（读取文件头中的格式版本）
+    pub fn float_decoder_for(
+        &self,
+        file_name: &str,
+        header: &[u8],
+    ) -> Result<FloatDecoder, FloatDecodeError> {
+        self.float_decoder.decoder_for(file_name, header)
+    }
+
     #[inline]
     pub fn is_replace_mesh(&self) -> bool {
         self.replace_mesh.unwrap_or(false)
@@ -545,6 +585,49 @@ impl SecondUnitDbOption {
     }
 }
 
+/// 额外项目的连接配置，对应 DbOption.toml 中的 `[[projects]]`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProjectDbOption {
+    /// 注册名，`with_project` 按此名称查找
+    pub name: String,
+    pub project_name: String,
+    #[serde(default)]
+    pub project_code: String,
+    pub surreal_ns: String,
+    pub v_ip: String,
+    pub v_port: u16,
+    pub v_user: String,
+    pub v_password: String,
+    /// 连接池大小，缺省为 1
+    #[serde(default)]
+    pub surreal_pool_size: Option<usize>,
+}
+
+impl ProjectDbOption {
+    #[inline]
+    pub fn get_version_db_conn_str(&self) -> String {
+        let ip = self.v_ip.as_str();
+        let port = self.v_port;
+        format!("ws://{ip}:{port}")
+    }
+}
+
+impl From<SecondUnitDbOption> for ProjectDbOption {
+    fn from(o: SecondUnitDbOption) -> Self {
+        Self {
+            name: crate::rs_surreal::project_registry::SECOND_UNIT.to_string(),
+            project_name: o.project_name,
+            project_code: o.project_code,
+            surreal_ns: o.surreal_ns,
+            v_ip: o.v_ip,
+            v_port: o.v_port,
+            v_user: o.v_user,
+            v_password: o.v_password,
+            surreal_pool_size: None,
+        }
+    }
+}
+
 // ============================================================================
 // 内存KV数据库配置默认值函数
 // ============================================================================
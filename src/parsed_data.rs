@@ -220,7 +220,15 @@ impl TubiPointData {
 impl TubiInfoData {
     /// 生成组合键 ID
     pub fn make_id(cata_hash: &str, arrive_num: i32, leave_num: i32) -> String {
-        format!("{}_{}_{}",cata_hash, arrive_num, leave_num)
+        TubiInfoKey::from_parts(cata_hash, arrive_num, leave_num)
+            .key()
+            .to_string()
+    }
+
+    /// `tubi_info` 表的键
+    #[inline]
+    pub fn key(&self) -> TubiInfoKey {
+        TubiInfoKey::new(self.id.as_str())
     }
 
     /// 从 CateAxisParam 对创建
@@ -235,8 +243,8 @@ impl TubiInfoData {
     /// 生成 SurrealDB INSERT JSON
     pub fn to_surreal_json(&self) -> String {
         format!(
-            r#"{{ id: tubi_info:⟨{}⟩, arrive: {}, leave: {} }}"#,
-            self.id,
+            r#"{{ id: {}, arrive: {}, leave: {} }}"#,
+            self.key(),
             serde_json::to_string(&self.arrive).unwrap_or_default(),
             serde_json::to_string(&self.leave).unwrap_or_default()
         )
@@ -0,0 +1,564 @@
+//! 贯穿孔洞（虚拟孔洞）自动识别
+//!
+//! 把管道 / 风管路由与墙板、楼板求交，得到需要预留的孔洞 [`VirtualHole`]：
+//! - 墙板以世界包围盒表示，厚度方向取包围盒最薄的轴，孔洞位于墙板中面
+//! - 管道路由取各元件的进出口连线以及元件之间的直管，截面为圆（外径）
+//! - 其它路由（风管、桥架等）取各元件的世界包围盒，沿最长轴走向，截面为矩形
+//!
+//! 圆管按管道等级的套管规则 [`SleeveSpec`] 选套管，孔洞尺寸 = 套管外径 + 两侧间隙；
+//! 斜穿时孔洞沿倾斜方向加长（截面椭圆加上穿过墙厚的扫掠量），倾角超过
+//! [`PenetrationRules::round_tilt_tolerance`] 时改为矩形孔。
+
+use crate::rs_surreal::pipeline::{BranchAttributes, PipelineQueryService};
+use crate::rs_surreal::{query_filter_deep_children, query_insts};
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, get_named_attmap};
+use glam::{Vec2, Vec3};
+use parry3d::bounding_volume::Aabb;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 路由截面
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RouteSection {
+    /// 圆截面，外径（mm）
+    Round { od: f32 },
+    /// 与坐标轴对齐的矩形截面，以元件包围盒尺寸表示
+    Box { size: Vec3 },
+}
+
+/// 一段路由中心线
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteSegment {
+    /// 所属路由（BRAN 或风管等顶层元素）
+    pub route: RefnoEnum,
+    /// 穿墙的元件，元件之间的直管取所属分支
+    pub element: RefnoEnum,
+    pub start: Vec3,
+    pub end: Vec3,
+    pub section: RouteSection,
+    /// 管道等级，用于选择套管规则
+    pub spec: Option<String>,
+}
+
+/// 墙板 / 楼板
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructurePanel {
+    pub refno: RefnoEnum,
+    /// 构件类型
+    pub generic: String,
+    pub aabb: Aabb,
+}
+
+impl StructurePanel {
+    /// 厚度方向：包围盒最薄的轴
+    #[inline]
+    fn normal_axis(&self) -> usize {
+        self.aabb.extents().imin()
+    }
+
+    /// 面内两个轴，按轴序
+    #[inline]
+    fn plane_axes(&self) -> (usize, usize) {
+        match self.normal_axis() {
+            0 => (1, 2),
+            1 => (0, 2),
+            _ => (0, 1),
+        }
+    }
+}
+
+/// 孔洞形状
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HoleShape {
+    #[default]
+    Round,
+    Rect,
+}
+
+/// 识别出的虚拟孔洞
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VirtualHole {
+    pub route: RefnoEnum,
+    pub element: RefnoEnum,
+    pub structure: RefnoEnum,
+    /// 墙板的构件类型
+    pub structure_generic: String,
+    /// 孔洞中心（墙板中面上）
+    pub position: Vec3,
+    /// 墙板法向
+    pub normal: Vec3,
+    /// 面内第一个轴，对应 `size.x`
+    pub u_axis: Vec3,
+    /// 面内第二个轴，对应 `size.y`
+    pub v_axis: Vec3,
+    pub shape: HoleShape,
+    /// 孔洞尺寸（mm），圆孔两者相等，均为直径
+    pub size: Vec2,
+    /// 孔深，即墙厚
+    pub depth: f32,
+    /// 套管外径，只有圆管路由有
+    pub sleeve_od: Option<f32>,
+    pub spec: Option<String>,
+    /// 路由与墙板法向的夹角（度）
+    pub tilt_angle: f32,
+}
+
+/// 套管选型表的一行：外径不大于 `max_od` 的管子用外径为 `sleeve_od` 的套管
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SleeveSize {
+    pub max_od: f32,
+    pub sleeve_od: f32,
+}
+
+/// 一个管道等级的套管规则（mm）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SleeveSpec {
+    /// 套管选型表
+    pub sizes: Vec<SleeveSize>,
+    /// 超出选型表时套管外径 = 管外径 + 2 × sleeve_gap
+    pub sleeve_gap: f32,
+    /// 套管外壁到孔壁的间隙
+    pub hole_gap: f32,
+    /// 孔洞尺寸向上取整的模数
+    pub round_to: f32,
+}
+
+impl Default for SleeveSpec {
+    fn default() -> Self {
+        Self {
+            sizes: vec![],
+            sleeve_gap: 25.0,
+            hole_gap: 25.0,
+            round_to: 50.0,
+        }
+    }
+}
+
+impl SleeveSpec {
+    /// 管外径对应的套管外径
+    pub fn sleeve_for(&self, od: f32) -> f32 {
+        self.sizes
+            .iter()
+            .filter(|s| s.max_od >= od)
+            .min_by(|a, b| a.max_od.total_cmp(&b.max_od))
+            .map_or(od + 2.0 * self.sleeve_gap, |s| s.sleeve_od)
+    }
+
+    fn round_up(&self, value: f32) -> f32 {
+        if self.round_to > 0.0 {
+            (value / self.round_to - 1e-4).ceil() * self.round_to
+        } else {
+            value
+        }
+    }
+}
+
+/// 孔洞识别规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PenetrationRules {
+    /// 按管道等级的套管规则
+    pub specs: BTreeMap<String, SleeveSpec>,
+    /// 没有配置等级或不属于任何等级时使用
+    pub default_spec: SleeveSpec,
+    /// 矩形路由（风管等）到孔壁的间隙
+    pub rect_gap: f32,
+    /// 倾角（度）不超过该值的圆管开圆孔，否则开矩形孔
+    pub round_tilt_tolerance: f32,
+    /// 倾角（度）超过该值视为沿墙敷设而非穿墙
+    pub max_tilt: f32,
+}
+
+impl Default for PenetrationRules {
+    fn default() -> Self {
+        Self {
+            specs: BTreeMap::new(),
+            default_spec: SleeveSpec::default(),
+            rect_gap: 50.0,
+            round_tilt_tolerance: 5.0,
+            max_tilt: 80.0,
+        }
+    }
+}
+
+impl PenetrationRules {
+    pub fn spec(&self, spec: Option<&str>) -> &SleeveSpec {
+        spec.and_then(|s| self.specs.get(s))
+            .unwrap_or(&self.default_spec)
+    }
+}
+
+/// 路由段与墙板中面的交点，不穿过中面或落在墙板外时返回 None
+fn crossing(seg: &RouteSegment, panel: &StructurePanel) -> Option<Vec3> {
+    let n = panel.normal_axis();
+    let mid = panel.aabb.center()[n];
+    let (d0, d1) = (seg.start[n] - mid, seg.end[n] - mid);
+    if d0 * d1 > 0.0 || d0 == d1 {
+        return None;
+    }
+    let point = seg.start.lerp(seg.end, d0 / (d0 - d1));
+    let (a, b) = panel.plane_axes();
+    let inside = [a, b]
+        .iter()
+        .all(|&k| point[k] >= panel.aabb.mins[k] && point[k] <= panel.aabb.maxs[k]);
+    inside.then_some(point)
+}
+
+/// 计算一段路由穿过一块墙板的孔洞
+pub fn hole_for(
+    seg: &RouteSegment,
+    panel: &StructurePanel,
+    rules: &PenetrationRules,
+) -> Option<VirtualHole> {
+    let dir = (seg.end - seg.start).try_normalize()?;
+    let position = crossing(seg, panel)?;
+    let n = panel.normal_axis();
+    let (a, b) = panel.plane_axes();
+    let cos = dir[n].abs();
+    let tilt_angle = cos.clamp(0.0, 1.0).acos().to_degrees();
+    if tilt_angle > rules.max_tilt {
+        return None;
+    }
+    let depth = panel.aabb.extents()[n];
+    // 沿墙厚扫掠带来的加长量
+    let sweep = |k: usize| depth * dir[k].abs() / cos;
+
+    let spec = rules.spec(seg.spec.as_deref());
+    let (shape, size, sleeve_od) = match seg.section {
+        RouteSection::Round { od } => {
+            let sleeve = spec.sleeve_for(od);
+            let d = sleeve + 2.0 * spec.hole_gap;
+            // 斜截圆柱得到的椭圆在面内轴 k 上的宽度，l 为另一个面内轴
+            let extent = |k: usize, l: usize| d * (1.0 - dir[l] * dir[l]).max(0.0).sqrt() / cos;
+            let size = Vec2::new(extent(a, b) + sweep(a), extent(b, a) + sweep(b));
+            if tilt_angle <= rules.round_tilt_tolerance {
+                let d = spec.round_up(size.max_element());
+                (HoleShape::Round, Vec2::splat(d), Some(sleeve))
+            } else {
+                let size = Vec2::new(spec.round_up(size.x), spec.round_up(size.y));
+                (HoleShape::Rect, size, Some(sleeve))
+            }
+        }
+        RouteSection::Box { size } => {
+            let size = Vec2::new(
+                size[a] + sweep(a) + 2.0 * rules.rect_gap,
+                size[b] + sweep(b) + 2.0 * rules.rect_gap,
+            );
+            let size = Vec2::new(spec.round_up(size.x), spec.round_up(size.y));
+            (HoleShape::Rect, size, None)
+        }
+    };
+
+    Some(VirtualHole {
+        route: seg.route,
+        element: seg.element,
+        structure: panel.refno,
+        structure_generic: panel.generic.clone(),
+        position,
+        normal: axis_vec(n),
+        u_axis: axis_vec(a),
+        v_axis: axis_vec(b),
+        shape,
+        size,
+        depth,
+        sleeve_od,
+        spec: seg.spec.clone(),
+        tilt_angle,
+    })
+}
+
+fn axis_vec(k: usize) -> Vec3 {
+    let mut v = Vec3::ZERO;
+    v[k] = 1.0;
+    v
+}
+
+/// 在给定的路由段和墙板之间识别孔洞
+///
+/// 同一路由在同一墙板上相距小于墙厚的多个交点（例如元件端点正好落在墙内，
+/// 元件和直管各算一次）合并为一个，取较大的孔洞。
+pub fn detect_in(
+    routes: &[RouteSegment],
+    panels: &[StructurePanel],
+    rules: &PenetrationRules,
+) -> Vec<VirtualHole> {
+    let mut holes: Vec<VirtualHole> = vec![];
+    for seg in routes {
+        for panel in panels {
+            let Some(hole) = hole_for(seg, panel, rules) else {
+                continue;
+            };
+            let duplicate = holes.iter_mut().find(|h| {
+                h.route == hole.route
+                    && h.structure == hole.structure
+                    && h.position.distance(hole.position) < hole.depth.max(1.0)
+            });
+            match duplicate {
+                Some(h) => {
+                    if hole.size.x * hole.size.y > h.size.x * h.size.y {
+                        *h = hole;
+                    }
+                }
+                None => holes.push(hole),
+            }
+        }
+    }
+    holes
+}
+
+/// 加载一个分支的路由段：分支头 → 各元件（进口 → 出口）→ 分支尾
+pub async fn load_branch_route(bran: RefnoEnum) -> anyhow::Result<Vec<RouteSegment>> {
+    let att = get_named_attmap(bran).await?;
+    let spec = BranchAttributes::fetch(bran).await?.pipe_spec;
+    let records = PipelineQueryService::fetch_branch_segments(bran).await?;
+
+    let mut segments = vec![];
+    let mut push = |element: RefnoEnum, start: Vec3, end: Vec3, od: Option<f32>| {
+        if let Some(od) = od.filter(|d| *d > 0.0)
+            && start.distance_squared(end) > f32::EPSILON
+        {
+            segments.push(RouteSegment {
+                route: bran,
+                element,
+                start,
+                end,
+                section: RouteSection::Round { od },
+                spec: spec.clone(),
+            });
+        }
+    };
+    let mut last = att.get_vec3("HPOS");
+    let mut last_od = records.first().and_then(|r| r.outside_diameter.or(r.bore));
+    for record in &records {
+        let Some(span) = record.main_span() else {
+            continue;
+        };
+        let od = record.outside_diameter.or(record.bore).or(last_od);
+        // 元件之间的直管
+        if let Some(prev) = last {
+            push(bran, prev, span.start.world_pos, last_od.or(od));
+        }
+        push(record.refno, span.start.world_pos, span.end.world_pos, od);
+        last = Some(span.end.world_pos);
+        last_od = od;
+    }
+    if let (Some(prev), Some(tail)) = (last, att.get_vec3("TPOS")) {
+        push(bran, prev, tail, last_od);
+    }
+    Ok(segments)
+}
+
+/// 加载非管道路由：取自身及子孙元素的世界包围盒，沿最长轴走向
+async fn load_box_route(route: RefnoEnum) -> anyhow::Result<Vec<RouteSegment>> {
+    let mut refnos = query_filter_deep_children(route, &[]).await?;
+    refnos.push(route);
+    let insts = query_insts(&refnos, false).await?;
+    Ok(insts
+        .into_iter()
+        .filter_map(|inst| {
+            let aabb = inst.world_aabb?.0;
+            let axis = aabb.extents().imax();
+            let size = Vec3::from(aabb.extents());
+            let center = Vec3::from(aabb.center().coords);
+            let half = axis_vec(axis) * size[axis] / 2.0;
+            Some(RouteSegment {
+                route,
+                element: inst.refno,
+                start: center - half,
+                end: center + half,
+                section: RouteSection::Box { size },
+                spec: None,
+            })
+        })
+        .collect())
+}
+
+/// 按类型加载路由：PIPE 取全部分支，BRAN 取分支，其它按包围盒
+pub async fn load_route(refno: RefnoEnum) -> anyhow::Result<Vec<RouteSegment>> {
+    let att = get_named_attmap(refno).await?;
+    match att.get_type_str() {
+        "BRAN" => load_branch_route(refno).await,
+        "PIPE" => {
+            let sql = format!(
+                "select value in from {}<-pe_owner where in != none and !in.deleted and in.noun = 'BRAN'",
+                refno.to_pe_key()
+            );
+            let brans: Vec<RefnoEnum> = SUL_DB.query_take(&sql, 0).await?;
+            let mut segments = vec![];
+            for bran in brans {
+                segments.extend(load_branch_route(bran).await?);
+            }
+            Ok(segments)
+        }
+        _ => load_box_route(refno).await,
+    }
+}
+
+/// 加载墙板 / 楼板的世界包围盒
+pub async fn load_structure_panels(refnos: &[RefnoEnum]) -> anyhow::Result<Vec<StructurePanel>> {
+    let insts = query_insts(refnos, false).await?;
+    Ok(insts
+        .into_iter()
+        .filter_map(|inst| {
+            Some(StructurePanel {
+                refno: inst.refno,
+                generic: inst.generic,
+                aabb: inst.world_aabb?.0,
+            })
+        })
+        .collect())
+}
+
+/// 识别路由穿过墙板 / 楼板需要预留的孔洞
+///
+/// `route_refnos` 可以是 PIPE、BRAN 或风管等元素，`structure_refnos` 为墙板、楼板。
+pub async fn detect(
+    route_refnos: &[RefnoEnum],
+    structure_refnos: &[RefnoEnum],
+    rules: &PenetrationRules,
+) -> anyhow::Result<Vec<VirtualHole>> {
+    let panels = load_structure_panels(structure_refnos).await?;
+    let mut routes = vec![];
+    for &refno in route_refnos {
+        routes.extend(load_route(refno).await?);
+    }
+    Ok(detect_in(&routes, &panels, rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use nalgebra::Point3;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(1, n).into()
+    }
+
+    /// x=0 处 200mm 厚、竖直的墙
+    fn wall() -> StructurePanel {
+        StructurePanel {
+            refno: r(100),
+            generic: "WALL".into(),
+            aabb: Aabb::new(
+                Point3::new(-100.0, -5000.0, 0.0),
+                Point3::new(100.0, 5000.0, 3000.0),
+            ),
+        }
+    }
+
+    fn pipe(start: Vec3, end: Vec3, od: f32, spec: Option<&str>) -> RouteSegment {
+        RouteSegment {
+            route: r(1),
+            element: r(2),
+            start,
+            end,
+            section: RouteSection::Round { od },
+            spec: spec.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_round_hole_with_sleeve_spec() {
+        let mut rules = PenetrationRules::default();
+        rules.specs.insert(
+            "A1".into(),
+            SleeveSpec {
+                sizes: vec![
+                    SleeveSize {
+                        max_od: 114.3,
+                        sleeve_od: 168.3,
+                    },
+                    SleeveSize {
+                        max_od: 60.3,
+                        sleeve_od: 114.3,
+                    },
+                ],
+                ..Default::default()
+            },
+        );
+        let seg = pipe(
+            Vec3::new(-1000.0, 0.0, 1500.0),
+            Vec3::new(1000.0, 0.0, 1500.0),
+            88.9,
+            Some("A1"),
+        );
+        let holes = detect_in(&[seg.clone()], &[wall()], &rules);
+        assert_eq!(holes.len(), 1);
+        let hole = &holes[0];
+        assert_eq!(hole.shape, HoleShape::Round);
+        assert_eq!(hole.sleeve_od, Some(168.3));
+        // 168.3 + 2 × 25 = 218.3，取整到 250
+        assert_eq!(hole.size, Vec2::splat(250.0));
+        assert_eq!(hole.depth, 200.0);
+        assert!(hole.position.abs_diff_eq(Vec3::new(0.0, 0.0, 1500.0), 1e-3));
+        assert_eq!(hole.normal, Vec3::X);
+
+        // 未配置的等级按默认规则：88.9 + 50 + 50 = 188.9 -> 200
+        let mut other = seg.clone();
+        other.spec = Some("B2".into());
+        let hole = &detect_in(&[other], &[wall()], &rules)[0];
+        assert_eq!(hole.size, Vec2::splat(200.0));
+
+        // 端点落在墙中面上时两段只算一个孔
+        let a = pipe(
+            Vec3::new(-1000.0, 0.0, 1500.0),
+            Vec3::new(0.0, 0.0, 1500.0),
+            88.9,
+            None,
+        );
+        let b = pipe(
+            Vec3::new(0.0, 0.0, 1500.0),
+            Vec3::new(1000.0, 0.0, 1500.0),
+            88.9,
+            None,
+        );
+        assert_eq!(detect_in(&[a, b], &[wall()], &rules).len(), 1);
+
+        // 没有穿过墙中面、沿墙敷设都不算
+        let short = pipe(
+            Vec3::new(-1000.0, 0.0, 1500.0),
+            Vec3::new(-50.0, 0.0, 1500.0),
+            88.9,
+            None,
+        );
+        let along = pipe(
+            Vec3::new(-10.0, -1000.0, 1500.0),
+            Vec3::new(10.0, 1000.0, 1500.0),
+            88.9,
+            None,
+        );
+        assert!(detect_in(&[short, along], &[wall()], &rules).is_empty());
+    }
+
+    #[test]
+    fn test_oblique_and_rect_holes() {
+        let rules = PenetrationRules::default();
+        // 在 XY 面内倾斜 45° 穿墙：沿 Y 方向加长
+        let seg = pipe(
+            Vec3::new(-1000.0, -1000.0, 1500.0),
+            Vec3::new(1000.0, 1000.0, 1500.0),
+            100.0,
+            None,
+        );
+        let hole = &detect_in(&[seg], &[wall()], &rules)[0];
+        assert_eq!(hole.shape, HoleShape::Rect);
+        assert!((hole.tilt_angle - 45.0).abs() < 1e-3);
+        // d = 100 + 50 + 50 = 200；Y 向 200·√2 + 200 ≈ 482.8 -> 500，Z 向 200
+        assert_eq!(hole.size, Vec2::new(500.0, 200.0));
+
+        let duct = RouteSegment {
+            route: r(3),
+            element: r(4),
+            start: Vec3::new(-500.0, 0.0, 2000.0),
+            end: Vec3::new(500.0, 0.0, 2000.0),
+            section: RouteSection::Box {
+                size: Vec3::new(1000.0, 630.0, 400.0),
+            },
+            spec: None,
+        };
+        let hole = &detect_in(&[duct], &[wall()], &rules)[0];
+        assert_eq!(hole.shape, HoleShape::Rect);
+        assert_eq!(hole.sleeve_od, None);
+        assert_eq!(hole.size, Vec2::new(750.0, 500.0));
+    }
+}
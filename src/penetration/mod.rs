@@ -1,3 +1,9 @@
+pub mod detection;
+
+pub use detection::{
+    HoleShape, PenetrationRules, SleeveSize, SleeveSpec, VirtualHole, detect, detect_in,
+};
+
 use crate::types::*;
 use bevy_ecs::resource::Resource;
 use bevy_math::prelude::Vec3;
@@ -0,0 +1,266 @@
+//! 图导出
+//!
+//! 将 [`PetRefnoGraph`] 与 [`NOUN_GRAPH`] 导出为 Graphviz DOT 或 GraphML 文本，
+//! 节点标签中的 noun 由 `db1_dehash` 还原，便于在 Graphviz / Gephi 中查看装置拓扑。
+
+use super::PetRefnoGraph;
+use crate::RefU64;
+use crate::noun_graph::NOUN_GRAPH;
+use crate::tool::db_tool::db1_dehash;
+use petgraph::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+/// 导出过滤条件
+///
+/// 指定 `root` 时从其出发沿出边做广度优先遍历，只导出 `max_depth` 层以内的节点；
+/// 不满足 noun 条件的节点仍会被遍历，但不出现在结果中，经过它的边也一并略去
+#[derive(Default)]
+pub struct ExportFilter {
+    pub root: Option<RefU64>,
+    pub max_depth: Option<usize>,
+    noun_predicate: Option<Box<dyn Fn(&str) -> bool>>,
+}
+
+impl ExportFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn root(mut self, root: RefU64) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// 按 noun 名称过滤节点
+    pub fn nouns(mut self, predicate: impl Fn(&str) -> bool + 'static) -> Self {
+        self.noun_predicate = Some(Box::new(predicate));
+        self
+    }
+
+    fn accepts_noun(&self, noun: &str) -> bool {
+        self.noun_predicate.as_ref().is_none_or(|p| p(noun))
+    }
+}
+
+/// 导出用的节点与边，`id` 为输出中的节点标识
+struct ExportGraph {
+    nodes: Vec<(String, String)>,
+    edges: Vec<(String, String)>,
+}
+
+impl ExportGraph {
+    fn to_dot(&self, name: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph {} {{", name);
+        for (id, label) in &self.nodes {
+            let _ = writeln!(out, "    \"{}\" [label=\"{}\"];", id, escape_dot(label));
+        }
+        for (from, to) in &self.edges {
+            let _ = writeln!(out, "    \"{}\" -> \"{}\";", from, to);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_graphml(&self, name: &str) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        out.push_str(
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        );
+        let _ = writeln!(out, "  <graph id=\"{}\" edgedefault=\"directed\">", name);
+        for (id, label) in &self.nodes {
+            let _ = writeln!(
+                out,
+                "    <node id=\"{}\"><data key=\"label\">{}</data></node>",
+                id,
+                escape_xml(label)
+            );
+        }
+        for (i, (from, to)) in self.edges.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"/>",
+                i, from, to
+            );
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// 按过滤条件选出节点，返回保留节点的下标
+fn select_nodes<N, E>(
+    graph: &DiGraph<N, E>,
+    start: Option<NodeIndex>,
+    max_depth: Option<usize>,
+    accepts: impl Fn(NodeIndex) -> bool,
+) -> Vec<NodeIndex> {
+    let Some(start) = start else {
+        return graph.node_indices().filter(|&i| accepts(i)).collect();
+    };
+    let mut depths = HashMap::from([(start, 0usize)]);
+    let mut order = vec![start];
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        let depth = depths[&node];
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        for next in graph.neighbors(node) {
+            if !depths.contains_key(&next) {
+                depths.insert(next, depth + 1);
+                order.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    order.into_iter().filter(|&i| accepts(i)).collect()
+}
+
+/// 保留两端都被选中的边
+fn select_edges<N, E>(
+    graph: &DiGraph<N, E>,
+    nodes: &[NodeIndex],
+    id_of: impl Fn(NodeIndex) -> String,
+) -> Vec<(String, String)> {
+    let selected: HashSet<_> = nodes.iter().copied().collect();
+    graph
+        .edge_references()
+        .filter(|e| selected.contains(&e.source()) && selected.contains(&e.target()))
+        .map(|e| (id_of(e.source()), id_of(e.target())))
+        .collect()
+}
+
+impl PetRefnoGraph {
+    fn export_graph(&self, filter: &ExportFilter) -> ExportGraph {
+        let start = filter.root.map(|r| self.node_indices.get(&r.0).copied());
+        // 根节点不在图中时导出为空
+        if start == Some(None) {
+            return ExportGraph {
+                nodes: vec![],
+                edges: vec![],
+            };
+        }
+        let id_of = |i: NodeIndex| RefU64(self.graph[i].id).to_string();
+        let nodes = select_nodes(&self.graph, start.flatten(), filter.max_depth, |i| {
+            filter.accepts_noun(&db1_dehash(self.graph[i].noun_hash))
+        });
+        let edges = select_edges(&self.graph, &nodes, id_of);
+        ExportGraph {
+            nodes: nodes
+                .iter()
+                .map(|&i| {
+                    let node = &self.graph[i];
+                    (
+                        id_of(i),
+                        format!("{} {}", db1_dehash(node.noun_hash), RefU64(node.id)),
+                    )
+                })
+                .collect(),
+            edges,
+        }
+    }
+
+    /// 导出为 Graphviz DOT
+    pub fn to_dot(&self) -> String {
+        self.to_dot_filtered(&ExportFilter::default())
+    }
+
+    /// 导出为 GraphML，可直接导入 Gephi
+    pub fn to_graphml(&self) -> String {
+        self.to_graphml_filtered(&ExportFilter::default())
+    }
+
+    pub fn to_dot_filtered(&self, filter: &ExportFilter) -> String {
+        self.export_graph(filter).to_dot("refno")
+    }
+
+    pub fn to_graphml_filtered(&self, filter: &ExportFilter) -> String {
+        self.export_graph(filter).to_graphml("refno")
+    }
+}
+
+/// noun 层级图（[`NOUN_GRAPH`]）导出，节点标识与标签均为 noun 名称，忽略 `root`
+fn noun_export_graph(filter: &ExportFilter) -> ExportGraph {
+    let graph = &*NOUN_GRAPH;
+    let id_of = |i: NodeIndex| db1_dehash(graph[i]);
+    let nodes = select_nodes(graph, None, None, |i| filter.accepts_noun(&id_of(i)));
+    let edges = select_edges(graph, &nodes, id_of);
+    ExportGraph {
+        nodes: nodes.iter().map(|&i| (id_of(i), id_of(i))).collect(),
+        edges,
+    }
+}
+
+/// 将 noun 层级图导出为 Graphviz DOT
+pub fn noun_graph_to_dot(filter: &ExportFilter) -> String {
+    noun_export_graph(filter).to_dot("noun")
+}
+
+/// 将 noun 层级图导出为 GraphML
+pub fn noun_graph_to_graphml(filter: &ExportFilter) -> String {
+    noun_export_graph(filter).to_graphml("noun")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petgraph::PetRefnoNode;
+    use crate::tool::db_tool::db1_hash;
+
+    fn sample() -> PetRefnoGraph {
+        let mut g = PetRefnoGraph::default();
+        for (n, noun) in [(1, "PIPE"), (2, "BRAN"), (3, "ELBO"), (4, "TEE")] {
+            let id = RefU64::from_two_nums(1, n).0;
+            let idx = g.graph.add_node(PetRefnoNode {
+                id,
+                noun_hash: db1_hash(noun),
+            });
+            g.node_indices.insert(id, idx);
+        }
+        let idx = |n| g.node_indices[&RefU64::from_two_nums(1, n).0];
+        let edges = [(idx(1), idx(2)), (idx(2), idx(3)), (idx(3), idx(4))];
+        for (a, b) in edges {
+            g.graph.add_edge(a, b, ());
+        }
+        g
+    }
+
+    #[test]
+    fn test_export_filtered() {
+        let g = sample();
+        let dot = g.to_dot();
+        assert!(dot.contains("\"1_2\" [label=\"BRAN 1_2\"];"));
+        assert!(dot.contains("\"1_1\" -> \"1_2\";"));
+
+        let filter = ExportFilter::new()
+            .root(RefU64::from_two_nums(1, 2))
+            .max_depth(1)
+            .nouns(|n| n != "PIPE");
+        let graphml = g.to_graphml_filtered(&filter);
+        assert!(graphml.contains("<node id=\"1_3\"><data key=\"label\">ELBO 1_3</data></node>"));
+        assert!(graphml.contains("source=\"1_2\" target=\"1_3\""));
+        assert!(!graphml.contains("1_1"));
+        assert!(!graphml.contains("1_4"));
+    }
+}
@@ -5,6 +5,11 @@ use petgraph::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque, hash_map::RandomState};
 
+pub mod export;
+pub mod path;
+pub use export::{ExportFilter, noun_graph_to_dot, noun_graph_to_graphml};
+pub use path::{WeightedPath, hop_weight};
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct PetRefnoNode {
     pub id: u64,
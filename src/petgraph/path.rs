@@ -0,0 +1,260 @@
+//! 带权最短路径与环检测
+//!
+//! [`PetRefnoGraph::find_path`] 枚举全部简单路径，在大图上不可用。这里提供：
+//! - [`PetRefnoGraph::shortest_path`]：Dijkstra 最短路径，边权由调用方给出（如 TUBI 长度）
+//! - [`PetRefnoGraph::k_shortest_paths`]：Yen 算法求前 k 条无环最短路径
+//! - [`PetRefnoGraph::find_cycles`]：按强连通分量找出环
+//!
+//! 边权函数的参数为边的起点与终点，返回值须为非负数。
+
+use super::PetRefnoGraph;
+use crate::RefU64;
+use petgraph::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// 一条路径及其总权重
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedPath {
+    pub cost: f64,
+    pub refnos: Vec<RefU64>,
+}
+
+/// 按跳数计权
+pub fn hop_weight(_: RefU64, _: RefU64) -> f64 {
+    1.0
+}
+
+#[derive(PartialEq)]
+struct HeapItem(f64, NodeIndex);
+
+impl Eq for HeapItem {}
+
+impl Ord for HeapItem {
+    // 反向比较，使 BinaryHeap 成为小顶堆
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0)
+    }
+}
+
+impl PartialOrd for HeapItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PetRefnoGraph {
+    fn refno_of(&self, i: NodeIndex) -> RefU64 {
+        RefU64(self.graph[i].id)
+    }
+
+    /// 排除指定节点与边后的 Dijkstra，返回总权重与节点序列
+    fn dijkstra(
+        &self,
+        start: NodeIndex,
+        end: NodeIndex,
+        weight: &impl Fn(RefU64, RefU64) -> f64,
+        banned_nodes: &HashSet<NodeIndex>,
+        banned_edges: &HashSet<(NodeIndex, NodeIndex)>,
+    ) -> Option<(f64, Vec<NodeIndex>)> {
+        let mut dist: HashMap<NodeIndex, f64> = HashMap::from([(start, 0.0)]);
+        let mut prev: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut heap = BinaryHeap::from([HeapItem(0.0, start)]);
+        while let Some(HeapItem(cost, node)) = heap.pop() {
+            if node == end {
+                let mut path = vec![end];
+                while let Some(&p) = prev.get(path.last().unwrap()) {
+                    path.push(p);
+                }
+                path.reverse();
+                return Some((cost, path));
+            }
+            if dist.get(&node).is_some_and(|&d| cost > d) {
+                continue;
+            }
+            for next in self.graph.neighbors(node) {
+                if banned_nodes.contains(&next) || banned_edges.contains(&(node, next)) {
+                    continue;
+                }
+                let next_cost = cost + weight(self.refno_of(node), self.refno_of(next));
+                if dist.get(&next).is_none_or(|&d| next_cost < d) {
+                    dist.insert(next, next_cost);
+                    prev.insert(next, node);
+                    heap.push(HeapItem(next_cost, next));
+                }
+            }
+        }
+        None
+    }
+
+    fn path_cost(&self, path: &[NodeIndex], weight: &impl Fn(RefU64, RefU64) -> f64) -> f64 {
+        path.windows(2)
+            .map(|w| weight(self.refno_of(w[0]), self.refno_of(w[1])))
+            .sum()
+    }
+
+    fn to_weighted_path(&self, cost: f64, path: &[NodeIndex]) -> WeightedPath {
+        WeightedPath {
+            cost,
+            refnos: path.iter().map(|&i| self.refno_of(i)).collect(),
+        }
+    }
+
+    /// `start` 到 `end` 的最短路径，不可达时返回 None
+    pub fn shortest_path(
+        &self,
+        start: RefU64,
+        end: RefU64,
+        weight: impl Fn(RefU64, RefU64) -> f64,
+    ) -> Option<WeightedPath> {
+        let start = *self.node_indices.get(&start.0)?;
+        let end = *self.node_indices.get(&end.0)?;
+        let (cost, path) = self.dijkstra(start, end, &weight, &HashSet::new(), &HashSet::new())?;
+        Some(self.to_weighted_path(cost, &path))
+    }
+
+    /// 按权重从小到大返回至多 `k` 条无环路径（Yen 算法）
+    pub fn k_shortest_paths(
+        &self,
+        start: RefU64,
+        end: RefU64,
+        k: usize,
+        weight: impl Fn(RefU64, RefU64) -> f64,
+    ) -> Vec<WeightedPath> {
+        let (Some(&start), Some(&end)) = (
+            self.node_indices.get(&start.0),
+            self.node_indices.get(&end.0),
+        ) else {
+            return vec![];
+        };
+        if k == 0 {
+            return vec![];
+        }
+        let Some(first) = self.dijkstra(start, end, &weight, &HashSet::new(), &HashSet::new())
+        else {
+            return vec![];
+        };
+        let mut found: Vec<(f64, Vec<NodeIndex>)> = vec![first];
+        let mut candidates: Vec<(f64, Vec<NodeIndex>)> = vec![];
+
+        while found.len() < k {
+            let last = found.last().unwrap().1.clone();
+            for i in 0..last.len() - 1 {
+                let spur = last[i];
+                let root = &last[..=i];
+                let banned_edges: HashSet<_> = found
+                    .iter()
+                    .filter(|(_, p)| p.len() > i + 1 && &p[..=i] == root)
+                    .map(|(_, p)| (p[i], p[i + 1]))
+                    .collect();
+                let banned_nodes: HashSet<_> = root[..i].iter().copied().collect();
+                let Some((_, spur_path)) =
+                    self.dijkstra(spur, end, &weight, &banned_nodes, &banned_edges)
+                else {
+                    continue;
+                };
+                let mut path = root[..i].to_vec();
+                path.extend(spur_path);
+                if !candidates.iter().any(|(_, p)| *p == path)
+                    && !found.iter().any(|(_, p)| *p == path)
+                {
+                    candidates.push((self.path_cost(&path, &weight), path));
+                }
+            }
+            let Some(best) = candidates
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.0.total_cmp(&b.1.0))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            found.push(candidates.swap_remove(best));
+        }
+        found
+            .iter()
+            .map(|(cost, path)| self.to_weighted_path(*cost, path))
+            .collect()
+    }
+
+    /// 图中是否存在有向环
+    pub fn has_cycle(&self) -> bool {
+        petgraph::algo::is_cyclic_directed(&self.graph)
+    }
+
+    /// 找出所有处于环上的节点组：每组为一个包含环的强连通分量（含自环）
+    pub fn find_cycles(&self) -> Vec<Vec<RefU64>> {
+        petgraph::algo::tarjan_scc(&self.graph)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || self.graph.contains_edge(scc[0], scc[0]))
+            .map(|scc| scc.into_iter().map(|i| self.refno_of(i)).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petgraph::PetRefnoNode;
+
+    fn r(n: u32) -> RefU64 {
+        RefU64::from_two_nums(1, n)
+    }
+
+    fn graph(edges: &[(u32, u32)]) -> PetRefnoGraph {
+        let mut g = PetRefnoGraph::default();
+        for &(a, b) in edges {
+            let mut idx = |n: u32| {
+                *g.node_indices.entry(r(n).0).or_insert_with(|| {
+                    g.graph.add_node(PetRefnoNode {
+                        id: r(n).0,
+                        noun_hash: 0,
+                    })
+                })
+            };
+            let (a, b) = (idx(a), idx(b));
+            g.graph.add_edge(a, b, ());
+        }
+        g
+    }
+
+    #[test]
+    fn test_shortest_and_k_paths() {
+        // 1 -> 2 -> 4，1 -> 3 -> 4，1 -> 4
+        let g = graph(&[(1, 2), (2, 4), (1, 3), (3, 4), (1, 4)]);
+        let weight = |a: RefU64, b: RefU64| match (a.get_1(), b.get_1()) {
+            (1, 4) => 10.0,
+            (1, 3) => 2.0,
+            _ => 1.0,
+        };
+        let best = g.shortest_path(r(1), r(4), weight).unwrap();
+        assert_eq!(best.refnos, [r(1), r(2), r(4)]);
+        assert_eq!(best.cost, 2.0);
+
+        let hops = g.shortest_path(r(1), r(4), hop_weight).unwrap();
+        assert_eq!(hops.refnos, [r(1), r(4)]);
+
+        let paths = g.k_shortest_paths(r(1), r(4), 5, weight);
+        let costs: Vec<f64> = paths.iter().map(|p| p.cost).collect();
+        assert_eq!(costs, [2.0, 3.0, 10.0]);
+        assert!(g.shortest_path(r(4), r(1), weight).is_none());
+    }
+
+    #[test]
+    fn test_find_cycles() {
+        let g = graph(&[(1, 2), (2, 3), (3, 1), (3, 4), (5, 5)]);
+        assert!(g.has_cycle());
+        let mut cycles: Vec<Vec<u32>> = g
+            .find_cycles()
+            .into_iter()
+            .map(|c| {
+                let mut c: Vec<u32> = c.iter().map(|r| r.get_1()).collect();
+                c.sort();
+                c
+            })
+            .collect();
+        cycles.sort();
+        assert_eq!(cycles, [vec![1, 2, 3], vec![5]]);
+        assert!(!graph(&[(1, 2), (2, 3)]).has_cycle());
+    }
+}
@@ -0,0 +1,518 @@
+//! 螺栓、螺母、垫圈计算
+//!
+//! 沿 BRAN 成员顺序找出法兰连接（法兰-法兰、法兰-阀门等，中间可夹一片垫片），
+//! 两侧元件都要有螺栓集（元件 `BLRF` → BTSE，其下 BLTP 按 p 点给出螺栓直径、
+//! 法兰螺栓厚度和数量）。螺栓长度为两侧螺栓厚度、垫片厚度与螺母、垫圈、
+//! 伸出余量之和，再取螺栓表（BLIS 下的 SBOL）中不小于该值的标准长度。
+//!
+//! 结果可写入 [`BOLT_MATERIAL_TABLE`]，字段与 `material_gy_list` 一致
+//! （`id` / `code` / `noun` / `count`），另带螺栓规格。分支端部与设备管口的
+//! 连接不在此计算。
+
+use crate::parsed_data::CateAxisParam;
+use crate::rs_surreal::point::query_arrive_leave_points_of_branch;
+use crate::{
+    RefnoEnum, SUL_DB, SurrealQueryExt, eval_str_to_f32, get_cat_attmap,
+    get_children_named_attmaps, get_named_attmap, get_or_create_cata_context,
+    insert_into_table_with_chunks,
+};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 螺栓材料表
+pub const BOLT_MATERIAL_TABLE: &str = "material_gy_bolt";
+
+/// 两个端点视为重合的距离（mm）
+const JOINT_TOLERANCE: f32 = 0.5;
+
+/// 螺栓计算规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoltingRule {
+    /// 每根螺栓的螺母数
+    pub nuts_per_bolt: u32,
+    /// 每根螺栓的垫圈数
+    pub washers_per_bolt: u32,
+    /// 螺母高度与螺栓直径之比
+    pub nut_height_factor: f32,
+    /// 垫圈厚度（mm）
+    pub washer_thickness: f32,
+    /// 螺母外每端的伸出余量（mm）
+    pub projection: f32,
+    /// 没有标准长度表时螺栓长度向上取整的模数（mm）
+    pub round_to: f32,
+    /// 标准螺栓长度，键为螺栓直径（mm，取整）
+    pub standard_lengths: BTreeMap<u32, Vec<f32>>,
+}
+
+impl Default for BoltingRule {
+    fn default() -> Self {
+        Self {
+            nuts_per_bolt: 2,
+            washers_per_bolt: 2,
+            nut_height_factor: 1.0,
+            washer_thickness: 3.0,
+            projection: 5.0,
+            round_to: 5.0,
+            standard_lengths: BTreeMap::new(),
+        }
+    }
+}
+
+impl BoltingRule {
+    /// 由夹紧厚度求螺栓长度
+    pub fn bolt_length(&self, diameter: f32, grip: f32) -> f32 {
+        let required = grip
+            + self.nuts_per_bolt as f32 * self.nut_height_factor * diameter
+            + self.washers_per_bolt as f32 * self.washer_thickness
+            + 2.0 * self.projection;
+        let standard = self
+            .standard_lengths
+            .get(&(diameter.round() as u32))
+            .and_then(|lens| {
+                lens.iter()
+                    .copied()
+                    .filter(|l| *l >= required - f32::EPSILON)
+                    .min_by(f32::total_cmp)
+            });
+        standard.unwrap_or_else(|| {
+            if self.round_to > 0.0 {
+                (required / self.round_to).ceil() * self.round_to
+            } else {
+                required
+            }
+        })
+    }
+}
+
+/// 某个 p 点的螺栓数据
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoltPoint {
+    /// p 点编号，0 表示对所有 p 点适用
+    pub number: i32,
+    pub bolt_type: String,
+    /// 螺栓直径（mm）
+    pub diameter: f32,
+    /// 法兰螺栓厚度（mm）
+    pub thickness: f32,
+    pub count: u32,
+}
+
+/// 元件的螺栓集
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoltSet {
+    pub refno: RefnoEnum,
+    /// 螺栓集级的默认值
+    pub default: BoltPoint,
+    pub points: Vec<BoltPoint>,
+}
+
+impl BoltSet {
+    /// p 点的螺栓数据，没有单独定义时用螺栓集默认值
+    pub fn at(&self, number: i32) -> BoltPoint {
+        let mut point = self
+            .points
+            .iter()
+            .find(|p| p.number == number)
+            .cloned()
+            .unwrap_or_else(|| BoltPoint {
+                number,
+                ..self.default.clone()
+            });
+        if point.bolt_type.is_empty() {
+            point.bolt_type = self.default.bolt_type.clone();
+        }
+        if point.diameter <= 0.0 {
+            point.diameter = self.default.diameter;
+        }
+        if point.count == 0 {
+            point.count = self.default.count;
+        }
+        point
+    }
+}
+
+/// 分支成员及其进出口
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointMember {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    /// 进口位置和 p 点编号
+    pub arrive: Option<(Vec3, i32)>,
+    /// 出口位置和 p 点编号
+    pub leave: Option<(Vec3, i32)>,
+}
+
+/// 一个法兰连接
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlangeJoint {
+    pub left: RefnoEnum,
+    /// 左侧元件连接处的 p 点
+    pub left_point: i32,
+    pub right: RefnoEnum,
+    pub right_point: i32,
+    pub gasket: Option<RefnoEnum>,
+    /// 垫片厚度（mm）
+    pub gasket_thickness: f32,
+    pub position: Vec3,
+}
+
+/// 连接的螺栓计算结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JointBolting {
+    pub joint: FlangeJoint,
+    pub bolt_type: String,
+    pub diameter: f32,
+    pub length: f32,
+    pub bolt_count: u32,
+    pub nut_count: u32,
+    pub washer_count: u32,
+}
+
+/// 材料表行，字段与 `material_gy_list` 相同
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct BoltMaterialRow {
+    /// 左侧元件参考号（`24383/67446` 形式）
+    pub id: String,
+    pub code: String,
+    pub noun: String,
+    pub count: u32,
+    pub diameter: f32,
+    /// 螺母、垫圈为 None
+    pub length: Option<f32>,
+    pub bran: String,
+}
+
+fn coincide(a: Vec3, b: Vec3) -> bool {
+    a.distance(b) <= JOINT_TOLERANCE
+}
+
+/// 按成员顺序找出法兰连接，`bolted` 判断元件是否有螺栓集
+pub fn find_flange_joints(
+    members: &[JointMember],
+    bolted: impl Fn(&RefnoEnum) -> bool,
+) -> Vec<FlangeJoint> {
+    let mut joints = vec![];
+    for (i, m) in members.iter().enumerate() {
+        if m.noun == "GASK" || !bolted(&m.refno) {
+            continue;
+        }
+        let Some((leave, left_point)) = m.leave else {
+            continue;
+        };
+        let Some(next) = members.get(i + 1) else {
+            continue;
+        };
+        // 中间的垫片：进口与左侧出口重合，出口与右侧进口重合
+        let (gasket, gasket_thickness, right, face) = if next.noun == "GASK" {
+            let (Some((ga, _)), Some((gl, _)), Some(right)) =
+                (next.arrive, next.leave, members.get(i + 2))
+            else {
+                continue;
+            };
+            if !coincide(leave, ga) {
+                continue;
+            }
+            (Some(next.refno), ga.distance(gl), right, gl)
+        } else {
+            (None, 0.0, next, leave)
+        };
+        if !bolted(&right.refno) {
+            continue;
+        }
+        let Some((arrive, right_point)) = right.arrive else {
+            continue;
+        };
+        if !coincide(face, arrive) {
+            continue;
+        }
+        joints.push(FlangeJoint {
+            left: m.refno,
+            left_point,
+            right: right.refno,
+            right_point,
+            gasket,
+            gasket_thickness,
+            position: leave,
+        });
+    }
+    joints
+}
+
+/// 按两侧螺栓集计算连接的螺栓
+///
+/// 直径和数量取两侧中较大者，螺栓类型取左侧。
+pub fn size_joint(
+    joint: FlangeJoint,
+    left: &BoltSet,
+    right: &BoltSet,
+    rule: &BoltingRule,
+) -> JointBolting {
+    let l = left.at(joint.left_point);
+    let r = right.at(joint.right_point);
+    let diameter = l.diameter.max(r.diameter);
+    let bolt_count = l.count.max(r.count);
+    let grip = l.thickness + r.thickness + joint.gasket_thickness;
+    let bolt_type = if l.bolt_type.is_empty() {
+        r.bolt_type
+    } else {
+        l.bolt_type
+    };
+    JointBolting {
+        length: rule.bolt_length(diameter, grip),
+        bolt_type,
+        diameter,
+        bolt_count,
+        nut_count: bolt_count * rule.nuts_per_bolt,
+        washer_count: bolt_count * rule.washers_per_bolt,
+        joint,
+    }
+}
+
+impl JointBolting {
+    /// 转为材料表行：螺栓、螺母、垫圈各一行，数量为 0 的不输出
+    pub fn material_rows(&self, bran: RefnoEnum) -> Vec<BoltMaterialRow> {
+        let id = self.joint.left.refno().to_slash_string();
+        let bran = bran.refno().to_slash_string();
+        let d = self.diameter.round();
+        let bolt_noun = if self.bolt_type.is_empty() {
+            "BOLT".to_string()
+        } else {
+            self.bolt_type.to_uppercase()
+        };
+        [
+            (
+                bolt_noun.clone(),
+                format!("{bolt_noun} M{d}x{}", self.length.round()),
+                self.bolt_count,
+                Some(self.length),
+            ),
+            ("NUT".to_string(), format!("NUT M{d}"), self.nut_count, None),
+            (
+                "WASH".to_string(),
+                format!("WASH M{d}"),
+                self.washer_count,
+                None,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, _, count, _)| *count > 0)
+        .map(|(noun, code, count, length)| BoltMaterialRow {
+            id: id.clone(),
+            code,
+            noun,
+            count,
+            diameter: self.diameter,
+            length,
+            bran: bran.clone(),
+        })
+        .collect()
+    }
+}
+
+/// 读取元件的螺栓集，数值属性按元件库表达式求值
+pub async fn load_bolt_set(refno: RefnoEnum) -> anyhow::Result<Option<BoltSet>> {
+    let cat_att = get_cat_attmap(refno).await?;
+    let Some(btse) = cat_att.get_foreign_refno("BLRF").filter(|r| r.is_valid()) else {
+        return Ok(None);
+    };
+    let context = get_or_create_cata_context(refno, false).await?;
+    let eval = |att: &crate::NamedAttrMap, key: &str| {
+        att.get_as_string(key)
+            .and_then(|expr| eval_str_to_f32(expr, &context, "DIST").ok())
+            .unwrap_or_default()
+    };
+    let point = |att: &crate::NamedAttrMap| BoltPoint {
+        number: att.get_i32("NUMB").unwrap_or_default(),
+        bolt_type: att.get_as_string("BTYP").unwrap_or_default(),
+        diameter: eval(att, "BDIA"),
+        thickness: eval(att, "BTHK"),
+        count: eval(att, "NOFF").max(0.0).round() as u32,
+    };
+    let btse_att = get_named_attmap(btse).await?;
+    let default = point(&btse_att);
+    let points = get_children_named_attmaps(btse)
+        .await?
+        .iter()
+        .filter(|a| a.get_type_str() == "BLTP")
+        .map(point)
+        .collect();
+    Ok(Some(BoltSet {
+        refno: btse,
+        default,
+        points,
+    }))
+}
+
+/// 读取螺栓表（BLIS）中的标准螺栓长度
+pub async fn load_standard_bolt_lengths(
+    blis: RefnoEnum,
+) -> anyhow::Result<BTreeMap<u32, Vec<f32>>> {
+    let mut lengths: BTreeMap<u32, Vec<f32>> = BTreeMap::new();
+    for att in get_children_named_attmaps(blis).await? {
+        if att.get_type_str() != "SBOL" {
+            continue;
+        }
+        let (Some(d), Some(len)) = (att.get_f32("BDIA"), att.get_f32("LENG")) else {
+            continue;
+        };
+        lengths.entry(d.round() as u32).or_default().push(len);
+    }
+    for lens in lengths.values_mut() {
+        lens.sort_by(f32::total_cmp);
+        lens.dedup();
+    }
+    Ok(lengths)
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct MemberRow {
+    refno: RefnoEnum,
+    noun: String,
+}
+
+/// 计算分支上所有法兰连接的螺栓
+pub async fn branch_bolting(
+    bran: RefnoEnum,
+    rule: &BoltingRule,
+) -> anyhow::Result<Vec<JointBolting>> {
+    let sql = format!(
+        "select in as refno, in.noun as noun from {}<-pe_owner where in != none and !in.deleted",
+        bran.to_pe_key()
+    );
+    let rows: Vec<MemberRow> = SUL_DB.query_take(&sql, 0).await?;
+    let points = query_arrive_leave_points_of_branch(bran).await?;
+    let port = |p: &CateAxisParam| (p.pt.0, p.number);
+    let members: Vec<JointMember> = rows
+        .into_iter()
+        .map(|row| {
+            let axes = points.get(&row.refno);
+            JointMember {
+                arrive: axes.as_ref().map(|a| port(&a.value()[0])),
+                leave: axes.as_ref().map(|a| port(&a.value()[1])),
+                refno: row.refno,
+                noun: row.noun,
+            }
+        })
+        .collect();
+
+    let mut bolt_sets: HashMap<RefnoEnum, BoltSet> = HashMap::new();
+    for m in members.iter().filter(|m| m.noun != "GASK") {
+        if let Some(set) = load_bolt_set(m.refno).await? {
+            bolt_sets.insert(m.refno, set);
+        }
+    }
+    Ok(find_flange_joints(&members, |r| bolt_sets.contains_key(r))
+        .into_iter()
+        .map(|joint| {
+            let left = &bolt_sets[&joint.left];
+            let right = &bolt_sets[&joint.right];
+            size_joint(joint, left, right, rule)
+        })
+        .collect())
+}
+
+/// 计算分支螺栓并写入 [`BOLT_MATERIAL_TABLE`]，返回写入的材料行
+pub async fn save_branch_bolting(
+    bran: RefnoEnum,
+    rule: &BoltingRule,
+) -> anyhow::Result<Vec<BoltMaterialRow>> {
+    let rows: Vec<BoltMaterialRow> = branch_bolting(bran, rule)
+        .await?
+        .iter()
+        .flat_map(|b| b.material_rows(bran))
+        .collect();
+    SUL_DB
+        .query_response(&format!(
+            "DELETE {BOLT_MATERIAL_TABLE} WHERE bran = {}",
+            serde_json::to_string(&bran.refno().to_slash_string())?
+        ))
+        .await?;
+    if !rows.is_empty() {
+        insert_into_table_with_chunks(&SUL_DB, BOLT_MATERIAL_TABLE, rows.clone()).await?;
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(1, n).into()
+    }
+
+    fn member(n: u32, noun: &str, arrive: f32, leave: f32) -> JointMember {
+        JointMember {
+            refno: r(n),
+            noun: noun.into(),
+            arrive: Some((Vec3::new(arrive, 0.0, 0.0), 1)),
+            leave: Some((Vec3::new(leave, 0.0, 0.0), 2)),
+        }
+    }
+
+    #[test]
+    fn test_find_flange_joints() {
+        // 法兰 - 垫片 - 阀门 - 垫片 - 法兰，其后隔一段直管再接法兰
+        let members = vec![
+            member(1, "FLAN", 0.0, 100.0),
+            member(2, "GASK", 100.0, 103.0),
+            member(3, "VALV", 103.0, 403.0),
+            member(4, "GASK", 403.0, 406.0),
+            member(5, "FLAN", 406.0, 506.0),
+            member(6, "FLAN", 1500.0, 1600.0),
+            member(7, "ELBO", 1600.0, 1700.0),
+        ];
+        let joints = find_flange_joints(&members, |x| *x != r(7));
+        assert_eq!(joints.len(), 2);
+        assert_eq!((joints[0].left, joints[0].right), (r(1), r(3)));
+        assert_eq!(joints[0].gasket, Some(r(2)));
+        assert!((joints[0].gasket_thickness - 3.0).abs() < 1e-4);
+        assert_eq!((joints[1].left, joints[1].right), (r(3), r(5)));
+        assert_eq!((joints[1].left_point, joints[1].right_point), (2, 1));
+    }
+
+    #[test]
+    fn test_size_joint() {
+        let set = |thickness: f32| BoltSet {
+            refno: r(100),
+            default: BoltPoint {
+                number: 0,
+                bolt_type: "STUD".into(),
+                diameter: 16.0,
+                thickness,
+                count: 8,
+            },
+            points: vec![],
+        };
+        let joint = FlangeJoint {
+            left: r(1),
+            left_point: 2,
+            right: r(3),
+            right_point: 1,
+            gasket: Some(r(2)),
+            gasket_thickness: 3.0,
+            position: Vec3::ZERO,
+        };
+        let mut rule = BoltingRule::default();
+        // 20 + 22 + 3 + 2×16 + 2×3 + 2×5 = 93，取整到 95
+        let b = size_joint(joint.clone(), &set(20.0), &set(22.0), &rule);
+        assert_eq!(b.length, 95.0);
+        assert_eq!((b.bolt_count, b.nut_count, b.washer_count), (8, 16, 16));
+
+        rule.standard_lengths.insert(16, vec![90.0, 100.0, 110.0]);
+        let b = size_joint(joint, &set(20.0), &set(22.0), &rule);
+        assert_eq!(b.length, 100.0);
+
+        let rows = b.material_rows(r(9));
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].code, "STUD M16x100");
+        assert_eq!(rows[0].id, "1/1");
+        assert_eq!(rows[1].noun, "NUT");
+        assert_eq!(rows[1].count, 16);
+    }
+}
@@ -0,0 +1,391 @@
+//! 轴测图数据提取
+//!
+//! 为 PIPE / BRAN 提取自动出轴测图所需的数据 [`IsoSheetData`]：
+//! - 中心线折线：分支头 → 各元件进出口（弯头取两端方向线的交点）→ 分支尾
+//! - 元件符号：按元件类型（noun）与元件库类型映射为 [`IsoSymbol`]
+//! - 流向：从分支头指向分支尾，每段直管一个箭头
+//! - 尺寸：中心线上每一段的长度与走向（E/W/N/S/U/D）
+//!
+//! 只负责数据，绘制由出图 / SVG 层完成。元件数据来自
+//! [`PipelineQueryService::fetch_branch_segments`]，坐标均为世界坐标。
+
+use crate::rs_surreal::pipeline::{BranchAttributes, PipelineQueryService, PipelineSegmentRecord};
+use crate::{NamedAttrMap, RefnoEnum, SUL_DB, SurrealQueryExt, get_named_attmap};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// 长度小于该值（mm）的中心线段不标注、不画箭头
+const MIN_SEGMENT_LENGTH: f32 = 1.0;
+
+/// 判断走向是否与坐标轴对齐的余弦阈值
+const AXIS_ALIGNED_COS: f32 = 0.999;
+
+/// 元件符号
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IsoSymbol {
+    Elbow,
+    Bend,
+    Tee,
+    Olet,
+    Reducer,
+    Flange,
+    Gasket,
+    Valve,
+    Instrument,
+    Cap,
+    Coupling,
+    Support,
+    Weld,
+    /// 未识别的类型，保留原始 noun
+    Other(String),
+}
+
+impl IsoSymbol {
+    pub fn from_noun(noun: &str) -> Self {
+        match noun {
+            "ELBO" => Self::Elbow,
+            "BEND" => Self::Bend,
+            "TEE" | "CROS" => Self::Tee,
+            "OLET" => Self::Olet,
+            "REDU" => Self::Reducer,
+            "FLAN" | "FBLI" => Self::Flange,
+            "GASK" => Self::Gasket,
+            "VALV" | "VTWA" | "VFWA" => Self::Valve,
+            "INST" | "PCOM" => Self::Instrument,
+            "CAP" | "CLOS" => Self::Cap,
+            "COUP" | "UNIO" => Self::Coupling,
+            "ATTA" => Self::Support,
+            "WELD" => Self::Weld,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// 中心线在该元件处转向
+    fn turns(&self) -> bool {
+        matches!(self, Self::Elbow | Self::Bend)
+    }
+}
+
+/// 分支端部
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IsoEnd {
+    pub position: Vec3,
+    pub direction: Option<Vec3>,
+    /// 连接类型（HCON/TCON）
+    pub connect: String,
+    /// 连接的元素（HREF/TREF）及其名称
+    pub connected_to: Option<RefnoEnum>,
+    pub connected_name: Option<String>,
+}
+
+/// 元件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsoComponent {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub name: Option<String>,
+    pub symbol: IsoSymbol,
+    /// 元件库类型名
+    pub catalog_type: Option<String>,
+    /// 元件等级（SPRE）
+    pub spec: Option<String>,
+    pub bore: Option<f32>,
+    pub arrive: Option<Vec3>,
+    pub arrive_dir: Option<Vec3>,
+    pub leave: Option<Vec3>,
+    pub leave_dir: Option<Vec3>,
+    /// 支管口（三通等）
+    pub outlets: Vec<Vec3>,
+}
+
+impl IsoComponent {
+    pub fn from_segment(seg: &PipelineSegmentRecord) -> Self {
+        let noun = seg.noun_raw.clone().unwrap_or_default();
+        Self {
+            refno: seg.refno,
+            symbol: IsoSymbol::from_noun(&noun),
+            noun,
+            name: seg.name.clone(),
+            catalog_type: seg.type_name.clone(),
+            spec: seg.spec.clone(),
+            bore: seg.bore,
+            arrive: seg.arrive.map(|p| p.world_pos),
+            arrive_dir: seg.arrive.and_then(|p| p.world_dir),
+            leave: seg.leave.map(|p| p.world_pos),
+            leave_dir: seg.leave.and_then(|p| p.world_dir),
+            outlets: seg.extra_ports.iter().map(|p| p.world_pos).collect(),
+        }
+    }
+
+    /// 弯头的转角点：进出口方向线最近点的中点
+    fn corner(&self) -> Option<Vec3> {
+        let (p1, d1, p2, d2) = (self.arrive?, self.arrive_dir?, self.leave?, self.leave_dir?);
+        let (d1, d2) = (d1.normalize_or_zero(), d2.normalize_or_zero());
+        let w = p1 - p2;
+        let b = d1.dot(d2);
+        let denom = 1.0 - b * b;
+        if denom.abs() < 1e-6 {
+            return None;
+        }
+        let (d, e) = (d1.dot(w), d2.dot(w));
+        let s = (b * e - d) / denom;
+        let t = (e - b * d) / denom;
+        Some(((p1 + d1 * s) + (p2 + d2 * t)) * 0.5)
+    }
+}
+
+/// 流向箭头
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsoFlowArrow {
+    pub position: Vec3,
+    pub direction: Vec3,
+}
+
+/// 中心线段的尺寸
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IsoDimension {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub length: f32,
+    /// 走向，如 `E`、`U`，不与坐标轴对齐时为 `SKEW`
+    pub direction: String,
+}
+
+/// 单个分支
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IsoBranch {
+    pub refno: RefnoEnum,
+    pub name: String,
+    pub head: Option<IsoEnd>,
+    pub tail: Option<IsoEnd>,
+    pub centerline: Vec<Vec3>,
+    pub components: Vec<IsoComponent>,
+    pub flow: Vec<IsoFlowArrow>,
+    pub dimensions: Vec<IsoDimension>,
+}
+
+/// 一张轴测图的数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IsoSheetData {
+    /// PIPE，或单独提取分支时为 BRAN
+    pub refno: RefnoEnum,
+    pub name: String,
+    /// 标题栏属性，取自首个分支
+    pub attributes: BranchAttributes,
+    pub branches: Vec<IsoBranch>,
+}
+
+/// 向量的走向（X 为东、Y 为北、Z 为上）
+pub fn axis_direction(v: Vec3) -> String {
+    let Some(n) = v.try_normalize() else {
+        return String::new();
+    };
+    let axes = [
+        (Vec3::X, "E"),
+        (Vec3::NEG_X, "W"),
+        (Vec3::Y, "N"),
+        (Vec3::NEG_Y, "S"),
+        (Vec3::Z, "U"),
+        (Vec3::NEG_Z, "D"),
+    ];
+    axes.iter()
+        .find(|(axis, _)| n.dot(*axis) >= AXIS_ALIGNED_COS)
+        .map(|(_, label)| label.to_string())
+        .unwrap_or_else(|| "SKEW".to_string())
+}
+
+fn push_point(line: &mut Vec<Vec3>, p: Vec3) {
+    if line
+        .last()
+        .is_none_or(|last| last.distance(p) >= MIN_SEGMENT_LENGTH)
+    {
+        line.push(p);
+    }
+}
+
+impl IsoBranch {
+    /// 由端部与有序元件生成中心线、流向和尺寸
+    pub fn build(
+        refno: RefnoEnum,
+        name: String,
+        head: Option<IsoEnd>,
+        tail: Option<IsoEnd>,
+        components: Vec<IsoComponent>,
+    ) -> Self {
+        let mut centerline = vec![];
+        if let Some(head) = &head {
+            push_point(&mut centerline, head.position);
+        }
+        for c in &components {
+            let corner = c.symbol.turns().then(|| c.corner()).flatten();
+            for p in [c.arrive, corner, c.leave].into_iter().flatten() {
+                push_point(&mut centerline, p);
+            }
+        }
+        if let Some(tail) = &tail {
+            push_point(&mut centerline, tail.position);
+        }
+
+        let mut flow = vec![];
+        let mut dimensions = vec![];
+        for w in centerline.windows(2) {
+            let (from, to) = (w[0], w[1]);
+            let length = from.distance(to);
+            if length < MIN_SEGMENT_LENGTH {
+                continue;
+            }
+            flow.push(IsoFlowArrow {
+                position: (from + to) * 0.5,
+                direction: (to - from) / length,
+            });
+            dimensions.push(IsoDimension {
+                from,
+                to,
+                length,
+                direction: axis_direction(to - from),
+            });
+        }
+
+        Self {
+            refno,
+            name,
+            head,
+            tail,
+            centerline,
+            components,
+            flow,
+            dimensions,
+        }
+    }
+}
+
+async fn branch_end(att: &NamedAttrMap, prefix: &str) -> anyhow::Result<Option<IsoEnd>> {
+    let Some(position) = att.get_vec3(&format!("{prefix}POS")) else {
+        return Ok(None);
+    };
+    let connected_to = att
+        .get_foreign_refno(&format!("{prefix}REF"))
+        .filter(|r| !r.is_unset());
+    let connected_name = match connected_to {
+        Some(r) => Some(get_named_attmap(r).await?.get_name_or_default()),
+        None => None,
+    };
+    Ok(Some(IsoEnd {
+        position,
+        direction: att.get_vec3(&format!("{prefix}DIR")),
+        connect: att.get_string_or_default(&format!("{prefix}CON")),
+        connected_to,
+        connected_name,
+    }))
+}
+
+/// 提取单个分支
+pub async fn extract_iso_branch(bran: RefnoEnum) -> anyhow::Result<IsoBranch> {
+    let att = get_named_attmap(bran).await?;
+    let segments = PipelineQueryService::fetch_branch_segments(bran).await?;
+    let components = segments.iter().map(IsoComponent::from_segment).collect();
+    Ok(IsoBranch::build(
+        bran,
+        att.get_name_or_default(),
+        branch_end(&att, "H").await?,
+        branch_end(&att, "T").await?,
+        components,
+    ))
+}
+
+/// 提取 PIPE（全部分支）或单个 BRAN 的轴测图数据
+pub async fn extract_iso_sheet(refno: RefnoEnum) -> anyhow::Result<IsoSheetData> {
+    let att = get_named_attmap(refno).await?;
+    let brans = match att.get_type_str() {
+        "BRAN" => vec![refno],
+        "PIPE" => {
+            let sql = format!(
+                "select value in from {}<-pe_owner where in != none and !in.deleted and in.noun = 'BRAN'",
+                refno.to_pe_key()
+            );
+            let brans: Vec<RefnoEnum> = SUL_DB.query_take(&sql, 0).await?;
+            brans
+        }
+        other => anyhow::bail!("{} 的类型 {} 不是 PIPE 或 BRAN", refno, other),
+    };
+
+    let mut branches = Vec::with_capacity(brans.len());
+    for bran in &brans {
+        branches.push(extract_iso_branch(*bran).await?);
+    }
+    let attributes = match brans.first() {
+        Some(&first) => BranchAttributes::fetch(first).await?,
+        None => BranchAttributes::default(),
+    };
+    Ok(IsoSheetData {
+        refno,
+        name: att.get_name_or_default(),
+        attributes,
+        branches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn component(
+        noun: &str,
+        arrive: Vec3,
+        arrive_dir: Vec3,
+        leave: Vec3,
+        leave_dir: Vec3,
+    ) -> IsoComponent {
+        IsoComponent {
+            refno: RefnoEnum::Refno(RefU64::from_two_nums(1, 1)),
+            noun: noun.into(),
+            name: None,
+            symbol: IsoSymbol::from_noun(noun),
+            catalog_type: None,
+            spec: None,
+            bore: None,
+            arrive: Some(arrive),
+            arrive_dir: Some(arrive_dir),
+            leave: Some(leave),
+            leave_dir: Some(leave_dir),
+            outlets: vec![],
+        }
+    }
+
+    #[test]
+    fn test_build_branch() {
+        let end = |x: f32, y: f32| IsoEnd {
+            position: Vec3::new(x, y, 0.0),
+            ..Default::default()
+        };
+        // 沿 X 向东，经 90° 弯头转向北
+        let elbow = component(
+            "ELBO",
+            Vec3::new(900.0, 0.0, 0.0),
+            Vec3::NEG_X,
+            Vec3::new(1000.0, 100.0, 0.0),
+            Vec3::Y,
+        );
+        let branch = IsoBranch::build(
+            RefnoEnum::default(),
+            "/B1".into(),
+            Some(end(0.0, 0.0)),
+            Some(end(1000.0, 1000.0)),
+            vec![elbow],
+        );
+        assert_eq!(branch.centerline.len(), 5);
+        assert!(branch.centerline[2].distance(Vec3::new(1000.0, 0.0, 0.0)) < 1e-3);
+
+        let dirs: Vec<&str> = branch
+            .dimensions
+            .iter()
+            .map(|d| d.direction.as_str())
+            .collect();
+        assert_eq!(dirs, ["E", "E", "N", "N"]);
+        assert_eq!(branch.dimensions[0].length, 900.0);
+        assert_eq!(branch.flow.len(), 4);
+        assert_eq!(branch.flow[3].direction, Vec3::Y);
+        assert_eq!(axis_direction(Vec3::new(1.0, 1.0, 0.0)), "SKEW");
+    }
+}
@@ -0,0 +1,15 @@
+//! 管道专业功能
+
+pub mod bolting;
+pub mod iso;
+pub mod weld_numbering;
+
+pub use bolting::{
+    BOLT_MATERIAL_TABLE, BoltMaterialRow, BoltingRule, JointBolting, branch_bolting,
+    save_branch_bolting,
+};
+pub use iso::{IsoBranch, IsoSheetData, extract_iso_branch, extract_iso_sheet};
+pub use weld_numbering::{
+    WELD_RULE_TABLE, WELD_TABLE, WeldJoint, WeldNumberingRule, WeldRecord, load_weld_rule,
+    number_branch_welds, save_weld_rule,
+};
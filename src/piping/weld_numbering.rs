@@ -0,0 +1,532 @@
+//! 焊缝编号
+//!
+//! 沿 BRAN 成员顺序找出所有连接点（元件-元件、元件-直管、分支端部），按连接类型
+//! （端点的 pconnect，如 BWD / SWD）判断是否为焊缝，再按项目规则分配编号并写入 [`WELD_TABLE`]。
+//!
+//! 编号是稳定的：每个连接点以“元件 + 端口”作为键，重新编号时已有焊缝保留原编号，
+//! 新增焊缝从当前最大序号之后继续，已不存在的焊缝记录会被删除。
+//!
+//! 分支内显式建模的 WELD 元件本身即为一道焊缝，按其 SHOP 属性区分车间焊与现场焊，
+//! 与其相邻的连接点不再重复计数。
+
+use crate::rs_surreal::pipeline::WeldType;
+use crate::rs_surreal::point::query_arrive_leave_points_of_branch;
+use crate::rs_surreal::transaction::Transaction;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, get_named_attmap};
+use anyhow::Context;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 焊缝表
+pub const WELD_TABLE: &str = "weld";
+
+/// 项目编号规则表，键为项目名
+pub const WELD_RULE_TABLE: &str = "weld_rule";
+
+/// 两个端点视为重合的距离（mm）
+const JOINT_TOLERANCE: f32 = 0.5;
+
+/// 编号规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+#[serde(default)]
+pub struct WeldNumberingRule {
+    /// 编号模板，可用占位符 `{pipe}`、`{bran}`、`{kind}`、`{seq}`
+    pub template: String,
+    /// 序号补零宽度
+    pub seq_width: usize,
+    /// 起始序号
+    pub start: u32,
+    /// 车间焊标记，替换 `{kind}`
+    pub shop_mark: String,
+    /// 现场焊标记，替换 `{kind}`
+    pub field_mark: String,
+    /// 车间焊与现场焊分别编号
+    pub separate_field_sequence: bool,
+    /// 分支端部（连接管口或其他分支）的焊缝视为现场焊
+    pub field_at_branch_ends: bool,
+    /// 连接类型前缀与焊缝类型，不匹配的连接（法兰、螺纹等）不计为焊缝
+    pub weld_connections: Vec<(String, WeldType)>,
+}
+
+impl Default for WeldNumberingRule {
+    fn default() -> Self {
+        Self {
+            template: "{pipe}-{kind}{seq}".to_string(),
+            seq_width: 3,
+            start: 1,
+            shop_mark: "S".to_string(),
+            field_mark: "F".to_string(),
+            separate_field_sequence: false,
+            field_at_branch_ends: true,
+            weld_connections: vec![
+                ("BW".to_string(), WeldType::Butt),
+                ("SW".to_string(), WeldType::Socket),
+                ("FW".to_string(), WeldType::Fillet),
+            ],
+        }
+    }
+}
+
+impl WeldNumberingRule {
+    /// 连接类型对应的焊缝类型
+    pub fn weld_type(&self, connect: &str) -> Option<WeldType> {
+        let connect = connect.trim().to_uppercase();
+        self.weld_connections
+            .iter()
+            .find(|(prefix, _)| connect.starts_with(prefix.as_str()))
+            .map(|(_, t)| *t)
+    }
+
+    /// 按模板生成编号
+    pub fn format(&self, pipe: &str, bran: &str, shop: bool, seq: u32) -> String {
+        let kind = if shop {
+            &self.shop_mark
+        } else {
+            &self.field_mark
+        };
+        self.template
+            .replace("{pipe}", pipe.trim_start_matches('/'))
+            .replace("{bran}", bran.trim_start_matches('/'))
+            .replace("{kind}", kind)
+            .replace("{seq}", &format!("{:0width$}", seq, width = self.seq_width))
+    }
+}
+
+/// 元件端口
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Port {
+    pub pos: Vec3,
+    pub connect: String,
+}
+
+/// 分支成员及其进出口
+#[derive(Debug, Clone)]
+pub(crate) struct MemberPorts {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub shop: Option<bool>,
+    pub arrive: Option<Port>,
+    pub leave: Option<Port>,
+}
+
+/// 一个焊接连接点
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeldJoint {
+    /// 分支内唯一的键，用于保持编号稳定
+    pub key: String,
+    pub weld_type: WeldType,
+    pub shop: bool,
+    pub position: Vec3,
+    pub connect: String,
+    /// 连接的元件，直管一侧为 None
+    pub members: (Option<RefnoEnum>, Option<RefnoEnum>),
+}
+
+/// 已编号的焊缝
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeldRecord {
+    pub joint: WeldJoint,
+    pub seq: u32,
+    pub number: String,
+    /// 本次新分配的编号
+    pub is_new: bool,
+}
+
+fn coincide(a: &Port, b: &Port) -> bool {
+    a.pos.distance(b.pos) <= JOINT_TOLERANCE
+}
+
+fn is_weld_member(m: &MemberPorts) -> bool {
+    m.noun == "WELD"
+}
+
+/// 按成员顺序找出焊接连接点
+///
+/// `head` / `tail` 为分支端部的位置与连接类型（HPOS/HCON、TPOS/TCON）
+pub(crate) fn find_joints(
+    bran: RefnoEnum,
+    head: Option<Port>,
+    tail: Option<Port>,
+    members: &[MemberPorts],
+    rule: &WeldNumberingRule,
+) -> Vec<WeldJoint> {
+    let mut joints = vec![];
+    // `fallback` 为连接类型不是焊接时使用的焊缝类型，None 表示不计为焊缝
+    let mut push = |key: String,
+                    port: &Port,
+                    shop: bool,
+                    pair: (Option<RefnoEnum>, Option<RefnoEnum>),
+                    fallback: Option<WeldType>| {
+        if let Some(weld_type) = rule.weld_type(&port.connect).or(fallback) {
+            joints.push(WeldJoint {
+                key,
+                weld_type,
+                shop,
+                position: port.pos,
+                connect: port.connect.clone(),
+                members: pair,
+            });
+        }
+    };
+    let end_shop = !rule.field_at_branch_ends;
+
+    // 分支头部：端部连接本身，以及首个元件前的直管
+    if let Some(head) = &head {
+        push(
+            format!("{}:H", bran),
+            head,
+            end_shop,
+            (None, members.first().map(|m| m.refno)),
+            None,
+        );
+        if let Some(first) = members.first()
+            && !is_weld_member(first)
+            && let Some(arrive) = &first.arrive
+            && !coincide(head, arrive)
+        {
+            push(
+                format!("{}:A", first.refno),
+                arrive,
+                true,
+                (None, Some(first.refno)),
+                None,
+            );
+        }
+    }
+
+    for (i, m) in members.iter().enumerate() {
+        if is_weld_member(m) {
+            let Some(port) = m.arrive.as_ref().or(m.leave.as_ref()) else {
+                continue;
+            };
+            // 显式焊缝以 Butt 兜底，连接类型未定义时同样计数
+            let neighbours = (
+                i.checked_sub(1).map(|p| members[p].refno),
+                members.get(i + 1).map(|n| n.refno),
+            );
+            push(
+                format!("{}:W", m.refno),
+                port,
+                m.shop.unwrap_or(true),
+                neighbours,
+                Some(WeldType::Butt),
+            );
+            continue;
+        }
+        let Some(next) = members.get(i + 1) else {
+            continue;
+        };
+        if is_weld_member(next) {
+            continue;
+        }
+        match (&m.leave, &next.arrive) {
+            (Some(leave), Some(arrive)) if coincide(leave, arrive) => {
+                push(
+                    format!("{}:L", m.refno),
+                    leave,
+                    true,
+                    (Some(m.refno), Some(next.refno)),
+                    None,
+                );
+            }
+            (leave, arrive) => {
+                if let Some(leave) = leave {
+                    push(
+                        format!("{}:L", m.refno),
+                        leave,
+                        true,
+                        (Some(m.refno), None),
+                        None,
+                    );
+                }
+                if let Some(arrive) = arrive {
+                    push(
+                        format!("{}:A", next.refno),
+                        arrive,
+                        true,
+                        (None, Some(next.refno)),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    // 分支尾部：末个元件后的直管，以及端部连接本身
+    if let Some(tail) = &tail {
+        if let Some(last) = members.last()
+            && !is_weld_member(last)
+            && let Some(leave) = &last.leave
+            && !coincide(leave, tail)
+        {
+            push(
+                format!("{}:L", last.refno),
+                leave,
+                true,
+                (Some(last.refno), None),
+                None,
+            );
+        }
+        push(
+            format!("{}:T", bran),
+            tail,
+            end_shop,
+            (members.last().map(|m| m.refno), None),
+            None,
+        );
+    }
+    joints
+}
+
+/// 已保存的焊缝编号
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+pub(crate) struct ExistingWeld {
+    pub key: String,
+    pub seq: u32,
+    pub shop: bool,
+}
+
+/// 分配编号：已有焊缝沿用原序号，新焊缝按顺序接在各序列的最大序号之后
+pub(crate) fn assign_numbers(
+    joints: Vec<WeldJoint>,
+    existing: &[ExistingWeld],
+    rule: &WeldNumberingRule,
+    pipe: &str,
+    bran: &str,
+) -> Vec<WeldRecord> {
+    let sequence_of = |shop: bool| rule.separate_field_sequence && !shop;
+    let existing: HashMap<&str, &ExistingWeld> =
+        existing.iter().map(|e| (e.key.as_str(), e)).collect();
+    let mut next: HashMap<bool, u32> = HashMap::new();
+    for e in existing.values() {
+        let n = next.entry(sequence_of(e.shop)).or_insert(rule.start);
+        *n = (*n).max(e.seq + 1);
+    }
+
+    joints
+        .into_iter()
+        .map(|joint| {
+            // 车间/现场属性变化且分别编号时，需要在新序列中重新分配
+            let kept = existing
+                .get(joint.key.as_str())
+                .filter(|e| sequence_of(e.shop) == sequence_of(joint.shop));
+            let (seq, is_new) = match kept {
+                Some(e) => (e.seq, false),
+                None => {
+                    let n = next.entry(sequence_of(joint.shop)).or_insert(rule.start);
+                    let seq = *n;
+                    *n += 1;
+                    (seq, true)
+                }
+            };
+            WeldRecord {
+                number: rule.format(pipe, bran, joint.shop, seq),
+                joint,
+                seq,
+                is_new,
+            }
+        })
+        .collect()
+}
+
+/// 读取项目的编号规则，未配置时返回默认规则
+pub async fn load_weld_rule(project: &str) -> anyhow::Result<WeldNumberingRule> {
+    let sql = format!(
+        "select * omit id from only {}:⟨{}⟩",
+        WELD_RULE_TABLE, project
+    );
+    let rule: Option<WeldNumberingRule> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(rule.unwrap_or_default())
+}
+
+/// 保存项目的编号规则
+pub async fn save_weld_rule(project: &str, rule: &WeldNumberingRule) -> anyhow::Result<()> {
+    let mut tx = Transaction::new();
+    tx.upsert_content(&format!("{}:⟨{}⟩", WELD_RULE_TABLE, project), rule)?;
+    tx.commit().await
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct MemberRow {
+    refno: RefnoEnum,
+    noun: String,
+    shop: Option<bool>,
+}
+
+/// 为分支编号焊缝并写入 [`WELD_TABLE`]，返回按分支顺序排列的焊缝
+pub async fn number_branch_welds(
+    bran: RefnoEnum,
+    rule: &WeldNumberingRule,
+) -> anyhow::Result<Vec<WeldRecord>> {
+    let bran_att = get_named_attmap(bran).await?;
+    let pipe_att = get_named_attmap(bran_att.get_owner()).await?;
+    let bran_key = bran.to_pe_key();
+
+    let sql = format!(
+        "select in as refno, in.noun as noun, in.refno.SHOP as shop from {}<-pe_owner where in != none and !in.deleted",
+        bran_key
+    );
+    let rows: Vec<MemberRow> = SUL_DB.query_take(&sql, 0).await?;
+    let points = query_arrive_leave_points_of_branch(bran).await?;
+    let members: Vec<MemberPorts> = rows
+        .into_iter()
+        .map(|row| {
+            let axes = points.get(&row.refno);
+            let port = |i: usize| {
+                axes.as_ref().map(|a| Port {
+                    pos: a.value()[i].pt.0,
+                    connect: a.value()[i].pconnect.clone(),
+                })
+            };
+            MemberPorts {
+                arrive: port(0),
+                leave: port(1),
+                refno: row.refno,
+                noun: row.noun,
+                shop: row.shop,
+            }
+        })
+        .collect();
+    let end = |pos: &str, conn: &str| {
+        bran_att.get_vec3(pos).map(|pos| Port {
+            pos,
+            connect: bran_att.get_string_or_default(conn),
+        })
+    };
+    let joints = find_joints(
+        bran,
+        end("HPOS", "HCON"),
+        end("TPOS", "TCON"),
+        &members,
+        rule,
+    );
+
+    let sql = format!(
+        "select key, seq, shop from {} where bran = {}",
+        WELD_TABLE, bran_key
+    );
+    let existing: Vec<ExistingWeld> = SUL_DB.query_take(&sql, 0).await?;
+    let records = assign_numbers(
+        joints,
+        &existing,
+        rule,
+        &pipe_att.get_name_or_default(),
+        &bran_att.get_name_or_default(),
+    );
+
+    let mut tx = Transaction::new();
+    let keys = records
+        .iter()
+        .map(|r| serde_json::to_string(&r.joint.key))
+        .collect::<Result<Vec<_>, _>>()?;
+    tx.push(format!(
+        "DELETE {} WHERE bran = {} AND key NOT IN [{}]",
+        WELD_TABLE,
+        bran_key,
+        keys.join(",")
+    ));
+    let opt_key = |r: Option<RefnoEnum>| r.map(|r| r.to_pe_key()).unwrap_or("NONE".into());
+    for (r, key) in records.iter().zip(&keys) {
+        let p = r.joint.position;
+        tx.push(format!(
+            "UPSERT {table}:[{bran_key}, {key}] CONTENT {{ bran: {bran_key}, key: {key}, seq: {}, number: {}, weld_type: {}, shop: {}, connect: {}, position: [{}, {}, {}], prev: {}, next: {} }}",
+            r.seq,
+            serde_json::to_string(&r.number)?,
+            serde_json::to_string(&r.joint.weld_type)?,
+            r.joint.shop,
+            serde_json::to_string(&r.joint.connect)?,
+            p.x,
+            p.y,
+            p.z,
+            opt_key(r.joint.members.0),
+            opt_key(r.joint.members.1),
+            table = WELD_TABLE,
+        ));
+    }
+    tx.commit()
+        .await
+        .with_context(|| format!("保存 {} 的焊缝编号失败", bran))?;
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefnoEnum::Refno(RefU64::from_two_nums(1, n))
+    }
+
+    fn port(x: f32, connect: &str) -> Option<Port> {
+        Some(Port {
+            pos: Vec3::new(x, 0.0, 0.0),
+            connect: connect.into(),
+        })
+    }
+
+    fn member(n: u32, noun: &str, arrive: Option<Port>, leave: Option<Port>) -> MemberPorts {
+        MemberPorts {
+            refno: r(n),
+            noun: noun.into(),
+            shop: None,
+            arrive,
+            leave,
+        }
+    }
+
+    #[test]
+    fn test_find_joints_and_numbering() {
+        let rule = WeldNumberingRule::default();
+        // 管口(BWD) -直管- 弯头 - 法兰(法兰面不焊) ... 三通 -直管- 管口(FBD)
+        let members = vec![
+            member(1, "ELBO", port(100.0, "BWD"), port(200.0, "BWD")),
+            member(2, "FLAN", port(200.0, "BWD"), port(250.0, "FBD")),
+            member(3, "TEE", port(400.0, "BWD"), port(500.0, "BWD")),
+        ];
+        let joints = find_joints(r(10), port(0.0, "BWD"), port(900.0, "FBD"), &members, &rule);
+        let keys: Vec<&str> = joints.iter().map(|j| j.key.as_str()).collect();
+        assert_eq!(keys, ["1_10:H", "1_1:A", "1_1:L", "1_3:A", "1_3:L"]);
+        assert!(!joints[0].shop);
+        assert_eq!(joints[2].members, (Some(r(1)), Some(r(2))));
+
+        // 已有焊缝保持编号，新焊缝接在最大序号之后
+        let existing = vec![ExistingWeld {
+            key: "1_1:L".into(),
+            seq: 7,
+            shop: true,
+        }];
+        let records = assign_numbers(joints, &existing, &rule, "/P1", "/P1/B1");
+        let numbers: Vec<&str> = records.iter().map(|r| r.number.as_str()).collect();
+        assert_eq!(
+            numbers,
+            ["P1-F008", "P1-S009", "P1-S007", "P1-S010", "P1-S011"]
+        );
+        assert!(!records[2].is_new);
+    }
+
+    #[test]
+    fn test_explicit_weld_member() {
+        let rule = WeldNumberingRule {
+            separate_field_sequence: true,
+            ..Default::default()
+        };
+        let mut weld = member(2, "WELD", port(200.0, ""), port(200.0, ""));
+        weld.shop = Some(false);
+        let members = vec![
+            member(1, "ELBO", port(100.0, "BWD"), port(200.0, "BWD")),
+            weld,
+            member(3, "ELBO", port(200.0, "BWD"), port(300.0, "BWD")),
+        ];
+        let joints = find_joints(r(10), None, None, &members, &rule);
+        let keys: Vec<&str> = joints.iter().map(|j| j.key.as_str()).collect();
+        assert_eq!(keys, ["1_2:W"]);
+        assert_eq!(joints[0].members, (Some(r(1)), Some(r(3))));
+
+        let records = assign_numbers(joints, &[], &rule, "P1", "B1");
+        assert_eq!(records[0].number, "P1-F001");
+    }
+}
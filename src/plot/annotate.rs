@@ -0,0 +1,495 @@
+//! 图纸标注：尺寸、标高和名称标签
+//!
+//! 标注点以世界坐标给出，按 [`View`] 投影后生成 [`Drawing`] 中的线和文字，
+//! 与投影几何共用同一个图纸模型，可直接 [`Drawing::merge`] 后输出 SVG / DXF。
+//! 文字放置时避开已放置的文字框：依次尝试锚点周围八个方向、逐圈外扩的候选位置，
+//! 取第一个不重叠的，并用引线连回锚点。
+
+use super::model::{Drawing, Entity};
+use super::projection::View;
+use crate::RefnoEnum;
+use crate::geometry::EleGeosInfo;
+use glam::{Vec2, Vec3};
+
+/// 尺寸图层
+pub const DIM_LAYER: &str = "DIM";
+/// 标高图层
+pub const ELEVATION_LAYER: &str = "ELEV";
+/// 名称标签图层
+pub const LABEL_LAYER: &str = "LABEL";
+
+/// 标注样式，长度均为模型 mm
+#[derive(Debug, Clone)]
+pub struct AnnotationStyle {
+    /// 文字高度
+    pub text_height: f32,
+    /// 尺寸线离标注点的默认距离
+    pub dim_offset: f32,
+    /// 尺寸界线超出尺寸线的长度
+    pub extension: f32,
+    /// 尺寸端部斜线的长度
+    pub tick_size: f32,
+    /// 标签离锚点的间距，也是候选位置逐圈外扩的步长
+    pub label_gap: f32,
+    /// 标签候选位置的最大圈数
+    pub label_rings: usize,
+    /// 标高符号三角形的边长
+    pub marker_size: f32,
+    /// 小于该长度的尺寸不标
+    pub min_dimension: f32,
+}
+
+impl Default for AnnotationStyle {
+    fn default() -> Self {
+        Self {
+            text_height: 100.0,
+            dim_offset: 500.0,
+            extension: 50.0,
+            tick_size: 50.0,
+            label_gap: 150.0,
+            label_rings: 4,
+            marker_size: 150.0,
+            min_dimension: 1.0,
+        }
+    }
+}
+
+impl AnnotationStyle {
+    /// 按出图比例给出样式，`text_height_mm` 为图纸上的字高
+    pub fn for_scale(scale: f32, text_height_mm: f32) -> Self {
+        let d = Self::default();
+        let k = text_height_mm / scale.max(f32::EPSILON) / d.text_height;
+        Self {
+            text_height: d.text_height * k,
+            dim_offset: d.dim_offset * k,
+            extension: d.extension * k,
+            tick_size: d.tick_size * k,
+            label_gap: d.label_gap * k,
+            label_rings: d.label_rings,
+            marker_size: d.marker_size * k,
+            min_dimension: d.min_dimension,
+        }
+    }
+}
+
+/// 尺寸方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DimAxis {
+    /// 沿图纸水平方向
+    Horizontal,
+    /// 沿图纸竖直方向
+    Vertical,
+    /// 沿两点连线
+    Aligned,
+}
+
+/// 需要标注的元素
+#[derive(Debug, Clone, Default)]
+pub struct AnnotatedElement {
+    pub refno: RefnoEnum,
+    pub name: String,
+    pub noun: String,
+    /// 标签锚点（世界坐标）
+    pub anchor: Vec3,
+    /// 关键点（世界坐标），如出入口点、管口端面
+    pub key_points: Vec<Vec3>,
+}
+
+impl AnnotatedElement {
+    /// 从几何信息取关键点：有流向点（arrive/leave）时取流向点，否则取全部 ptset 点；
+    /// 锚点取关键点中心，没有关键点时取元素原点
+    pub fn from_geos_info(info: &EleGeosInfo, name: &str, noun: &str) -> Self {
+        let world = info.get_ele_world_transform();
+        let axes: Vec<_> = if info.flow_pt_indexs.is_empty() {
+            info.ptset_map.values().collect()
+        } else {
+            info.flow_pt_indexs
+                .iter()
+                .filter_map(|n| info.ptset_map.get(n))
+                .collect()
+        };
+        let key_points: Vec<Vec3> = axes
+            .into_iter()
+            .map(|axis| world.transform_point(axis.pt.0))
+            .collect();
+        let anchor = if key_points.is_empty() {
+            world.translation
+        } else {
+            key_points.iter().sum::<Vec3>() / key_points.len() as f32
+        };
+        Self {
+            refno: info.refno,
+            name: name.to_string(),
+            noun: noun.to_string(),
+            anchor,
+            key_points,
+        }
+    }
+}
+
+/// 文字框 (min, max)
+type TextBox = (Vec2, Vec2);
+
+fn overlaps(a: &TextBox, b: &TextBox) -> bool {
+    a.0.x < b.1.x && b.0.x < a.1.x && a.0.y < b.1.y && b.0.y < a.1.y
+}
+
+/// 标注生成器
+#[derive(Debug, Clone)]
+pub struct Annotator {
+    view: View,
+    style: AnnotationStyle,
+    occupied: Vec<TextBox>,
+    drawing: Drawing,
+}
+
+impl Annotator {
+    pub fn new(view: View, style: AnnotationStyle) -> Self {
+        Self {
+            view,
+            style,
+            occupied: vec![],
+            drawing: Drawing::new(),
+        }
+    }
+
+    /// 登记不允许文字压住的区域（图纸坐标）
+    pub fn avoid(&mut self, min: Vec2, max: Vec2) {
+        self.occupied.push((min.min(max), min.max(max)));
+    }
+
+    /// 把已有图纸中的文字登记为避让区域
+    pub fn avoid_texts(&mut self, drawing: &Drawing) {
+        for item in &drawing.items {
+            if let Entity::Text {
+                position,
+                text,
+                height,
+                ..
+            } = &item.entity
+            {
+                let size = self.text_size(text, *height);
+                self.avoid(*position, *position + size);
+            }
+        }
+    }
+
+    fn text_size(&self, text: &str, height: f32) -> Vec2 {
+        // 与 Drawing::bounds 的估算一致
+        Vec2::new(text.chars().count() as f32 * height * 0.6, height)
+    }
+
+    fn is_free(&self, bx: &TextBox) -> bool {
+        !self.occupied.iter().any(|o| overlaps(o, bx))
+    }
+
+    fn push_text(&mut self, layer: &str, refno: Option<RefnoEnum>, position: Vec2, text: &str) {
+        let size = self.text_size(text, self.style.text_height);
+        self.occupied.push((position, position + size));
+        self.drawing.push(
+            layer,
+            refno,
+            Entity::Text {
+                position,
+                text: text.to_string(),
+                height: self.style.text_height,
+                rotation: 0.0,
+            },
+        );
+    }
+
+    /// 在 `anchor` 周围找一个不重叠的文字位置，返回文字左下角
+    ///
+    /// 全部候选都被占用时取第一圈的第一个候选。
+    fn place_text(&self, anchor: Vec2, text: &str) -> Vec2 {
+        const DIRS: [(f32, f32); 8] = [
+            (1.0, 1.0),
+            (0.0, 1.0),
+            (-1.0, 1.0),
+            (-1.0, 0.0),
+            (-1.0, -1.0),
+            (0.0, -1.0),
+            (1.0, -1.0),
+            (1.0, 0.0),
+        ];
+        let size = self.text_size(text, self.style.text_height);
+        let candidate = |ring: usize, (dx, dy): (f32, f32)| -> Vec2 {
+            let dist = self.style.label_gap * ring as f32;
+            // 文字框贴在锚点的对应一侧
+            let x = match dx {
+                d if d > 0.0 => anchor.x + dist,
+                d if d < 0.0 => anchor.x - dist - size.x,
+                _ => anchor.x - size.x / 2.0,
+            };
+            let y = match dy {
+                d if d > 0.0 => anchor.y + dist,
+                d if d < 0.0 => anchor.y - dist - size.y,
+                _ => anchor.y - size.y / 2.0,
+            };
+            Vec2::new(x, y)
+        };
+        (1..=self.style.label_rings.max(1))
+            .flat_map(|ring| DIRS.iter().map(move |d| (ring, *d)))
+            .map(|(ring, d)| candidate(ring, d))
+            .find(|pos| self.is_free(&(*pos, *pos + size)))
+            .unwrap_or_else(|| candidate(1, DIRS[0]))
+    }
+
+    /// 名称标签，带引线指向锚点
+    pub fn label(&mut self, refno: Option<RefnoEnum>, text: &str, anchor: Vec3) {
+        if text.is_empty() {
+            return;
+        }
+        let anchor = self.view.project(anchor);
+        let pos = self.place_text(anchor, text);
+        let size = self.text_size(text, self.style.text_height);
+        // 引线连到文字框上离锚点最近的点
+        let end = anchor.clamp(pos, pos + size);
+        if end.distance(anchor) > f32::EPSILON {
+            self.drawing
+                .push(LABEL_LAYER, refno, Entity::Line { a: anchor, b: end });
+        }
+        self.push_text(LABEL_LAYER, refno, pos, text);
+    }
+
+    /// 标高符号：倒三角指向标注点，旁边写标高（m，三位小数）
+    pub fn elevation_marker(&mut self, refno: Option<RefnoEnum>, point: Vec3) {
+        let p = self.view.project(point);
+        let s = self.style.marker_size;
+        let h = s * 0.866;
+        self.drawing.push(
+            ELEVATION_LAYER,
+            refno,
+            Entity::Polyline {
+                points: vec![p, p + Vec2::new(-s / 2.0, h), p + Vec2::new(s / 2.0, h)],
+                closed: true,
+            },
+        );
+        let text = format!("EL{:+.3}", point.z / 1000.0);
+        let line_end = p + Vec2::new(s * 2.0, h);
+        self.drawing.push(
+            ELEVATION_LAYER,
+            refno,
+            Entity::Line {
+                a: p + Vec2::new(-s / 2.0, h),
+                b: line_end,
+            },
+        );
+        let pos = self.place_text(p + Vec2::new(0.0, h), &text);
+        self.push_text(ELEVATION_LAYER, refno, pos, &text);
+    }
+
+    /// 两点间的线性尺寸，`offset` 为尺寸线相对标注点的偏移（正值在上方 / 右侧 / 左法向）
+    ///
+    /// 尺寸小于 [`AnnotationStyle::min_dimension`] 时不标，返回 false。
+    pub fn linear_dimension(&mut self, a: Vec3, b: Vec3, axis: DimAxis, offset: f32) -> bool {
+        let (pa, pb) = (self.view.project(a), self.view.project(b));
+        let dir = match axis {
+            DimAxis::Horizontal => Vec2::X,
+            DimAxis::Vertical => Vec2::Y,
+            DimAxis::Aligned => match (pb - pa).try_normalize() {
+                Some(d) => d,
+                None => return false,
+            },
+        };
+        let normal = dir.perp();
+        let value = (pb - pa).dot(dir).abs();
+        if value < self.style.min_dimension {
+            return false;
+        }
+        // 尺寸线位置：两点中离尺寸线一侧更远的点再偏移 offset
+        let base = if offset >= 0.0 {
+            pa.dot(normal).max(pb.dot(normal))
+        } else {
+            pa.dot(normal).min(pb.dot(normal))
+        } + offset;
+        let foot = |p: Vec2| p + normal * (base - p.dot(normal));
+        let (da, db) = (foot(pa), foot(pb));
+        let side = if offset >= 0.0 { 1.0 } else { -1.0 };
+
+        for (p, d) in [(pa, da), (pb, db)] {
+            self.drawing.push(
+                DIM_LAYER,
+                None,
+                Entity::Line {
+                    a: p,
+                    b: d + normal * side * self.style.extension,
+                },
+            );
+            let tick = (dir + normal).normalize() * self.style.tick_size / 2.0;
+            self.drawing.push(
+                DIM_LAYER,
+                None,
+                Entity::Line {
+                    a: d - tick,
+                    b: d + tick,
+                },
+            );
+        }
+        self.drawing
+            .push(DIM_LAYER, None, Entity::Line { a: da, b: db });
+
+        let text = format!("{value:.0}");
+        let size = self.text_size(&text, self.style.text_height);
+        let rotation = dir.to_angle();
+        // 文字沿尺寸线居中，写在尺寸线外侧
+        let mid = (da + db) / 2.0;
+        let mut position = mid - dir * size.x / 2.0 + normal * self.style.text_height * 0.25;
+        if side < 0.0 {
+            position -= normal * (size.y + self.style.text_height * 0.5);
+        }
+        // 只有水平文字参与避让，重叠时沿尺寸线外侧推开
+        if rotation.abs() < 1e-3 {
+            let mut tries = 0;
+            while !self.is_free(&(position, position + size)) && tries < 4 {
+                position += normal * side * (size.y + self.style.text_height * 0.25);
+                tries += 1;
+            }
+            self.push_text(DIM_LAYER, None, position, &text);
+        } else {
+            self.drawing.push(
+                DIM_LAYER,
+                None,
+                Entity::Text {
+                    position,
+                    text,
+                    height: self.style.text_height,
+                    rotation,
+                },
+            );
+        }
+        true
+    }
+
+    /// 连续尺寸：按 `axis` 方向排序后逐段标注，重合的点只标一次
+    pub fn dimension_chain(&mut self, points: &[Vec3], axis: DimAxis, offset: f32) -> usize {
+        let dir = match axis {
+            DimAxis::Vertical => Vec2::Y,
+            _ => Vec2::X,
+        };
+        let mut sorted: Vec<(f32, Vec3)> = points
+            .iter()
+            .map(|p| (self.view.project(*p).dot(dir), *p))
+            .collect();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        sorted.dedup_by(|b, a| (b.0 - a.0).abs() < self.style.min_dimension);
+        let axis = if axis == DimAxis::Aligned {
+            DimAxis::Horizontal
+        } else {
+            axis
+        };
+        sorted
+            .windows(2)
+            .filter(|w| self.linear_dimension(w[0].1, w[1].1, axis, offset))
+            .count()
+    }
+
+    /// 对一组元素标注：名称标签、关键点的水平和竖直连续尺寸，
+    /// 立面 / 剖面视图中再给每个元素的首个关键点加标高
+    pub fn annotate_elements(&mut self, elements: &[AnnotatedElement]) {
+        let key_points: Vec<Vec3> = elements
+            .iter()
+            .flat_map(|e| e.key_points.iter().copied())
+            .collect();
+        if key_points.len() >= 2 {
+            let offset = self.style.dim_offset;
+            self.dimension_chain(&key_points, DimAxis::Horizontal, offset);
+            self.dimension_chain(&key_points, DimAxis::Vertical, offset);
+        }
+        let plan = self.view.dir.z.abs() > 0.999;
+        for element in elements {
+            if !plan && let Some(p) = element.key_points.first() {
+                self.elevation_marker(Some(element.refno), *p);
+            }
+        }
+        for element in elements {
+            self.label(Some(element.refno), &element.name, element.anchor);
+        }
+    }
+
+    /// 生成的标注
+    pub fn finish(self) -> Drawing {
+        self.drawing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(drawing: &Drawing) -> Vec<(Vec2, String)> {
+        drawing
+            .items
+            .iter()
+            .filter_map(|i| match &i.entity {
+                Entity::Text { position, text, .. } => Some((*position, text.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_linear_dimension() {
+        let mut annotator = Annotator::new(View::plan(), AnnotationStyle::default());
+        let points = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1200.0, 300.0, 0.0),
+            Vec3::new(1200.0, 0.0, 0.0),
+            Vec3::new(3000.0, 0.0, 0.0),
+        ];
+        assert_eq!(
+            annotator.dimension_chain(&points, DimAxis::Horizontal, 500.0),
+            2
+        );
+        let drawing = annotator.finish();
+        let values: Vec<String> = texts(&drawing).into_iter().map(|t| t.1).collect();
+        assert_eq!(values, vec!["1200", "1800"]);
+        // 尺寸线在最高点上方 500
+        assert!(drawing.items.iter().any(|i| matches!(
+            i.entity,
+            Entity::Line { a, b } if a.y == 800.0 && b.y == 800.0 && a.x == 0.0 && b.x == 1200.0
+        )));
+    }
+
+    #[test]
+    fn test_label_avoidance() {
+        let mut annotator = Annotator::new(View::plan(), AnnotationStyle::default());
+        annotator.label(None, "P-101", Vec3::ZERO);
+        annotator.label(None, "P-102", Vec3::ZERO);
+        annotator.label(None, "P-103", Vec3::new(10.0, 0.0, 0.0));
+        let drawing = annotator.finish();
+        let style = AnnotationStyle::default();
+        let boxes: Vec<TextBox> = texts(&drawing)
+            .into_iter()
+            .map(|(p, t)| {
+                (
+                    p,
+                    p + Vec2::new(t.len() as f32 * style.text_height * 0.6, style.text_height),
+                )
+            })
+            .collect();
+        assert_eq!(boxes.len(), 3);
+        for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                assert!(!overlaps(&boxes[i], &boxes[j]));
+            }
+        }
+        // 每个标签都有引线
+        let leaders = drawing
+            .items
+            .iter()
+            .filter(|i| matches!(i.entity, Entity::Line { .. }))
+            .count();
+        assert_eq!(leaders, 3);
+    }
+
+    #[test]
+    fn test_elevation_marker() {
+        let view = View::elevation(Vec3::Y).unwrap();
+        let mut annotator = Annotator::new(view, AnnotationStyle::default());
+        annotator.elevation_marker(None, Vec3::new(0.0, 0.0, 3500.0));
+        let drawing = annotator.finish();
+        assert_eq!(texts(&drawing)[0].1, "EL+3.500");
+        assert_eq!(drawing.layer_names(), vec![ELEVATION_LAYER]);
+    }
+}
@@ -0,0 +1,343 @@
+//! 图纸的 DXF 输出
+//!
+//! 输出 AutoCAD 2000（AC1015）格式的 ASCII DXF，图元写成 LINE / ARC / CIRCLE /
+//! LWPOLYLINE / TEXT。图层默认按专业归并（管道、设备、结构……），也可以保留
+//! 按元素类型分层。遮挡用的填充多边形只在 SVG 中有意义，DXF 中不输出。
+
+use super::model::{Drawing, Entity, LayerStyle};
+use anyhow::Result;
+use glam::Vec2;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// DXF 图层划分方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DxfLayerMode {
+    /// 按专业分层，见 [`discipline_layer`]
+    #[default]
+    Discipline,
+    /// 沿用图纸的图层（元素类型）
+    Noun,
+}
+
+/// DXF 输出选项
+#[derive(Debug, Clone)]
+pub struct DxfOptions {
+    pub layer_mode: DxfLayerMode,
+    /// 坐标缩放，默认 1 即按模型 mm 输出
+    pub scale: f32,
+}
+
+impl Default for DxfOptions {
+    fn default() -> Self {
+        Self {
+            layer_mode: DxfLayerMode::Discipline,
+            scale: 1.0,
+        }
+    }
+}
+
+/// 元素类型所属专业的图层名，无法归类的放在 `OTHER`
+pub fn discipline_layer(noun: &str) -> &'static str {
+    match noun {
+        "PIPE" | "BRAN" | "TUBI" | "ELBO" | "TEE" | "REDU" | "FLAN" | "GASK" | "VALV" | "INST"
+        | "OLET" | "CAP" | "COUP" | "BEND" | "WELD" | "PCOM" | "FTUB" | "ATTA" => "PIPING",
+        "EQUI" | "SUBE" | "NOZZ" | "TMPL" => "EQUIPMENT",
+        "STRU" | "FRMW" | "SBFR" | "SCTN" | "GENSEC" | "PANE" | "FLOOR" | "FITT" => "STRUCTURE",
+        "WALL" | "STWALL" | "GWALL" | "SLAB" | "CWALL" => "CIVIL",
+        "HVAC" | "DUCT" => "HVAC",
+        "CWAY" | "CTRAY" | "CABLE" => "ELECTRICAL",
+        "HANG" | "SUPP" | "PCLA" | "PFIT" => "SUPPORT",
+        "DIM" | "ELEV" | "LABEL" | "TEXT" => "ANNOTATION",
+        _ => "OTHER",
+    }
+}
+
+/// 与 RGB 最接近的 AutoCAD 索引色（只在 1~7 号基本色中选）
+fn aci_color(rgb: [u8; 3]) -> i32 {
+    const BASIC: [(i32, [u8; 3]); 7] = [
+        (1, [255, 0, 0]),
+        (2, [255, 255, 0]),
+        (3, [0, 255, 0]),
+        (4, [0, 255, 255]),
+        (5, [0, 0, 255]),
+        (6, [255, 0, 255]),
+        (7, [255, 255, 255]),
+    ];
+    // 黑色在 AutoCAD 中用 7 号（随背景黑白切换）表示
+    if rgb.iter().all(|&c| c < 48) {
+        return 7;
+    }
+    let dist = |c: [u8; 3]| -> i32 {
+        (0..3)
+            .map(|i| (c[i] as i32 - rgb[i] as i32).pow(2))
+            .sum::<i32>()
+    };
+    BASIC
+        .iter()
+        .min_by_key(|(_, c)| dist(*c))
+        .map_or(7, |(aci, _)| *aci)
+}
+
+fn true_color(rgb: [u8; 3]) -> i32 {
+    ((rgb[0] as i32) << 16) | ((rgb[1] as i32) << 8) | rgb[2] as i32
+}
+
+/// 按组码逐行写 DXF，并分配实体句柄
+struct DxfWriter {
+    out: String,
+    next_handle: u32,
+}
+
+impl DxfWriter {
+    fn new() -> Self {
+        Self {
+            out: String::new(),
+            next_handle: 0x100,
+        }
+    }
+
+    fn pair(&mut self, code: i32, value: impl std::fmt::Display) {
+        let _ = write!(self.out, "{code}\n{value}\n");
+    }
+
+    fn float(&mut self, code: i32, value: f32) {
+        let _ = write!(self.out, "{code}\n{value:.6}\n");
+    }
+
+    fn point(&mut self, code: i32, p: Vec2) {
+        self.float(code, p.x);
+        self.float(code + 10, p.y);
+    }
+
+    fn handle(&mut self) {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.pair(5, format!("{handle:X}"));
+    }
+
+    /// 实体公共部分：类型、句柄、图层
+    fn entity_header(&mut self, kind: &str, layer: &str) {
+        self.pair(0, kind);
+        self.handle();
+        self.pair(100, "AcDbEntity");
+        self.pair(8, layer);
+    }
+
+    fn header(&mut self, min: Vec2, max: Vec2) {
+        self.pair(0, "SECTION");
+        self.pair(2, "HEADER");
+        self.pair(9, "$ACADVER");
+        self.pair(1, "AC1015");
+        self.pair(9, "$INSUNITS");
+        self.pair(70, 4);
+        self.pair(9, "$EXTMIN");
+        self.point(10, min);
+        self.float(30, 0.0);
+        self.pair(9, "$EXTMAX");
+        self.point(10, max);
+        self.float(30, 0.0);
+        self.pair(0, "ENDSEC");
+    }
+
+    fn layer_table(&mut self, layers: &BTreeMap<String, LayerStyle>) {
+        self.pair(0, "SECTION");
+        self.pair(2, "TABLES");
+        self.pair(0, "TABLE");
+        self.pair(2, "LAYER");
+        self.handle();
+        self.pair(100, "AcDbSymbolTable");
+        self.pair(70, layers.len());
+        for (name, style) in layers {
+            self.pair(0, "LAYER");
+            self.handle();
+            self.pair(100, "AcDbSymbolTableRecord");
+            self.pair(100, "AcDbLayerTableRecord");
+            self.pair(2, name);
+            self.pair(70, 0);
+            self.pair(62, aci_color(style.stroke));
+            self.pair(420, true_color(style.stroke));
+            self.pair(6, "CONTINUOUS");
+            // 线宽以 0.01mm 为单位
+            self.pair(370, (style.stroke_width * 100.0).round() as i32);
+        }
+        self.pair(0, "ENDTAB");
+        self.pair(0, "ENDSEC");
+    }
+
+    fn entity(&mut self, layer: &str, entity: &Entity, scale: f32) {
+        match entity {
+            Entity::Line { a, b } => {
+                self.entity_header("LINE", layer);
+                self.pair(100, "AcDbLine");
+                self.point(10, *a * scale);
+                self.float(30, 0.0);
+                self.point(11, *b * scale);
+                self.float(31, 0.0);
+            }
+            Entity::Polyline { points, closed } => self.lwpolyline(layer, points, *closed, scale),
+            // 遮挡填充不输出
+            Entity::Polygon { .. } => {}
+            Entity::Circle { center, radius } => {
+                self.entity_header("CIRCLE", layer);
+                self.pair(100, "AcDbCircle");
+                self.point(10, *center * scale);
+                self.float(30, 0.0);
+                self.float(40, radius * scale);
+            }
+            Entity::Arc {
+                center,
+                radius,
+                start_angle,
+                end_angle,
+            } => {
+                self.entity_header("ARC", layer);
+                self.pair(100, "AcDbCircle");
+                self.point(10, *center * scale);
+                self.float(30, 0.0);
+                self.float(40, radius * scale);
+                self.pair(100, "AcDbArc");
+                self.float(50, start_angle.to_degrees());
+                self.float(51, end_angle.to_degrees());
+            }
+            Entity::Text {
+                position,
+                text,
+                height,
+                rotation,
+            } => {
+                self.entity_header("TEXT", layer);
+                self.pair(100, "AcDbText");
+                self.point(10, *position * scale);
+                self.float(30, 0.0);
+                self.float(40, height * scale);
+                // 组码 1 不能跨行
+                self.pair(1, text.replace(['\r', '\n'], " "));
+                self.float(50, rotation.to_degrees());
+                self.pair(100, "AcDbText");
+            }
+        }
+    }
+
+    fn lwpolyline(&mut self, layer: &str, points: &[Vec2], closed: bool, scale: f32) {
+        if points.len() < 2 {
+            return;
+        }
+        self.entity_header("LWPOLYLINE", layer);
+        self.pair(100, "AcDbPolyline");
+        self.pair(90, points.len());
+        self.pair(70, closed as i32);
+        for p in points {
+            self.point(10, *p * scale);
+        }
+    }
+}
+
+/// 把图纸输出为 DXF 字符串
+pub fn to_dxf(drawing: &Drawing, options: &DxfOptions) -> String {
+    let layer_of = |layer: &str| -> String {
+        match options.layer_mode {
+            DxfLayerMode::Discipline => discipline_layer(layer).to_string(),
+            DxfLayerMode::Noun => layer.to_string(),
+        }
+    };
+    // 专业图层取第一个归入它的图纸图层的样式
+    let mut layers: BTreeMap<String, LayerStyle> = BTreeMap::new();
+    for name in drawing.layer_names() {
+        layers
+            .entry(layer_of(name))
+            .or_insert_with(|| drawing.layer_style(name));
+    }
+
+    let (min, max) = drawing.bounds().unwrap_or((Vec2::ZERO, Vec2::ZERO));
+    let mut w = DxfWriter::new();
+    w.header(min * options.scale, max * options.scale);
+    w.layer_table(&layers);
+    w.pair(0, "SECTION");
+    w.pair(2, "ENTITIES");
+    for item in &drawing.items {
+        w.entity(&layer_of(&item.layer), &item.entity, options.scale);
+    }
+    w.pair(0, "ENDSEC");
+    w.pair(0, "EOF");
+    w.out
+}
+
+/// 输出 DXF 文件
+pub fn write_dxf(drawing: &Drawing, options: &DxfOptions, path: impl AsRef<Path>) -> Result<()> {
+    std::fs::write(path, to_dxf(drawing, options))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drawing() -> Drawing {
+        let mut drawing = Drawing::new();
+        drawing.push(
+            "ELBO",
+            None,
+            Entity::Arc {
+                center: Vec2::ZERO,
+                radius: 10.0,
+                start_angle: 0.0,
+                end_angle: std::f32::consts::FRAC_PI_2,
+            },
+        );
+        drawing.push(
+            "TUBI",
+            None,
+            Entity::Polyline {
+                points: vec![Vec2::ZERO, Vec2::new(100.0, 0.0), Vec2::new(100.0, 50.0)],
+                closed: false,
+            },
+        );
+        drawing.push(
+            "EQUI",
+            None,
+            Entity::Polygon {
+                points: vec![Vec2::ZERO, Vec2::X, Vec2::Y],
+            },
+        );
+        drawing.push(
+            "EQUI",
+            None,
+            Entity::Text {
+                position: Vec2::ZERO,
+                text: "E-101".into(),
+                height: 5.0,
+                rotation: 0.0,
+            },
+        );
+        drawing
+    }
+
+    #[test]
+    fn test_to_dxf() {
+        let dxf = to_dxf(&drawing(), &DxfOptions::default());
+        assert!(dxf.starts_with("0\nSECTION\n2\nHEADER\n"));
+        assert!(dxf.ends_with("0\nEOF\n"));
+        // 管件和直管归入同一个专业图层
+        assert_eq!(dxf.matches("0\nLAYER\n").count(), 2);
+        assert!(dxf.contains("2\nPIPING\n"));
+        assert!(dxf.contains("2\nEQUIPMENT\n"));
+        assert_eq!(dxf.matches("0\nARC\n").count(), 1);
+        assert!(dxf.contains("51\n90.000000\n"));
+        assert!(dxf.contains("0\nLWPOLYLINE\n"));
+        assert!(dxf.contains("90\n3\n"));
+        assert!(dxf.contains("1\nE-101\n"));
+        // 遮挡填充不输出
+        assert!(!dxf.contains("SOLID"));
+
+        let dxf = to_dxf(
+            &drawing(),
+            &DxfOptions {
+                layer_mode: DxfLayerMode::Noun,
+                scale: 1.0,
+            },
+        );
+        assert_eq!(dxf.matches("0\nLAYER\n").count(), 3);
+    }
+}
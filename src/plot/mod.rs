@@ -0,0 +1,16 @@
+//! 出图：三维几何投影为二维图纸并输出
+//!
+//! [`PlotScene`] 按 [`View`]（平面、立面、剖面）正交投影得到 [`Drawing`]，
+//! 再由 [`svg`] 或 [`dxf`] 输出；尺寸、标高和标签由 [`annotate`] 生成到同一模型中。图层以元素类型命名，样式见 [`LayerStyle::for_noun`]。
+
+pub mod annotate;
+pub mod dxf;
+pub mod model;
+pub mod projection;
+pub mod svg;
+
+pub use annotate::{AnnotatedElement, AnnotationStyle, Annotator, DimAxis};
+pub use dxf::{DxfLayerMode, DxfOptions, discipline_layer, to_dxf, write_dxf};
+pub use model::{Drawing, DrawingItem, Entity, LayerStyle};
+pub use projection::{PlotScene, SceneItem, View};
+pub use svg::{SvgOptions, to_svg, write_svg};
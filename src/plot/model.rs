@@ -0,0 +1,185 @@
+//! 二维图纸模型
+//!
+//! 投影、标注产生的都是 [`Drawing`]，再由 SVG / DXF 等写出器输出。
+//! 坐标为图纸平面上的模型单位（mm），Y 轴向上；图元按绘制顺序排列，
+//! 后绘制的覆盖先绘制的（画家算法依赖这一顺序）。
+
+use crate::RefnoEnum;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 图元
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Entity {
+    Line {
+        a: Vec2,
+        b: Vec2,
+    },
+    Polyline {
+        points: Vec<Vec2>,
+        closed: bool,
+    },
+    /// 填充多边形，用于遮挡
+    Polygon {
+        points: Vec<Vec2>,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// 逆时针圆弧，角度为弧度
+    Arc {
+        center: Vec2,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    },
+    Text {
+        position: Vec2,
+        text: String,
+        height: f32,
+        /// 旋转角，弧度
+        rotation: f32,
+    },
+}
+
+impl Entity {
+    /// 参与范围计算的点
+    fn extent_points(&self) -> Vec<Vec2> {
+        match self {
+            Entity::Line { a, b } => vec![*a, *b],
+            Entity::Polyline { points, .. } | Entity::Polygon { points } => points.clone(),
+            Entity::Circle { center, radius } | Entity::Arc { center, radius, .. } => {
+                vec![
+                    *center - Vec2::splat(*radius),
+                    *center + Vec2::splat(*radius),
+                ]
+            }
+            Entity::Text {
+                position,
+                text,
+                height,
+                ..
+            } => {
+                let width = text.chars().count() as f32 * height * 0.6;
+                vec![*position, *position + Vec2::new(width, *height)]
+            }
+        }
+    }
+}
+
+/// 图层样式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerStyle {
+    /// 线条颜色 RGB
+    pub stroke: [u8; 3],
+    /// 填充颜色，None 表示不填充
+    pub fill: Option<[u8; 3]>,
+    /// 线宽（mm）
+    pub stroke_width: f32,
+}
+
+impl Default for LayerStyle {
+    fn default() -> Self {
+        Self {
+            stroke: [0, 0, 0],
+            fill: Some([255, 255, 255]),
+            stroke_width: 0.25,
+        }
+    }
+}
+
+impl LayerStyle {
+    /// 按元素类型给出的默认样式，图层名即元素类型
+    pub fn for_noun(noun: &str) -> Self {
+        let (stroke, fill, stroke_width) = match noun {
+            "PIPE" | "BRAN" | "TUBI" | "ELBO" | "TEE" | "REDU" | "FLAN" | "GASK" | "VALV"
+            | "INST" | "OLET" | "CAP" | "COUP" | "BEND" => ([0, 64, 192], [220, 232, 255], 0.35),
+            "EQUI" | "SUBE" | "NOZZ" => ([0, 128, 0], [225, 245, 225], 0.35),
+            "STRU" | "FRMW" | "SBFR" | "SCTN" | "GENSEC" | "PANE" | "FLOOR" => {
+                ([96, 96, 96], [235, 235, 235], 0.25)
+            }
+            "WALL" | "STWALL" | "GWALL" => ([64, 64, 64], [210, 210, 210], 0.5),
+            "HANG" | "ATTA" => ([160, 80, 0], [250, 235, 215], 0.25),
+            _ => return Self::default(),
+        };
+        Self {
+            stroke,
+            fill: Some(fill),
+            stroke_width,
+        }
+    }
+}
+
+/// 带图层和所属元素的图元
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawingItem {
+    pub layer: String,
+    pub refno: Option<RefnoEnum>,
+    pub entity: Entity,
+}
+
+/// 图纸
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Drawing {
+    /// 图层样式，未登记的图层按 [`LayerStyle::for_noun`] 取默认样式
+    pub layers: BTreeMap<String, LayerStyle>,
+    /// 按绘制顺序排列的图元
+    pub items: Vec<DrawingItem>,
+}
+
+impl Drawing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_layer_style(&mut self, layer: &str, style: LayerStyle) {
+        self.layers.insert(layer.to_string(), style);
+    }
+
+    pub fn layer_style(&self, layer: &str) -> LayerStyle {
+        self.layers
+            .get(layer)
+            .cloned()
+            .unwrap_or_else(|| LayerStyle::for_noun(layer))
+    }
+
+    pub fn push(&mut self, layer: &str, refno: Option<RefnoEnum>, entity: Entity) {
+        self.items.push(DrawingItem {
+            layer: layer.to_string(),
+            refno,
+            entity,
+        });
+    }
+
+    /// 追加另一张图纸的图元（绘制在其上方），图层样式以已有的为准
+    pub fn merge(&mut self, other: Drawing) {
+        for (layer, style) in other.layers {
+            self.layers.entry(layer).or_insert(style);
+        }
+        self.items.extend(other.items);
+    }
+
+    /// 图元范围 (min, max)，空图纸返回 None
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        self.items
+            .iter()
+            .flat_map(|item| item.entity.extent_points())
+            .fold(None, |acc, p| match acc {
+                None => Some((p, p)),
+                Some((min, max)) => Some((min.min(p), max.max(p))),
+            })
+    }
+
+    /// 出现过的图层名，按首次出现的顺序
+    pub fn layer_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = vec![];
+        for item in &self.items {
+            if !names.contains(&item.layer.as_str()) {
+                names.push(&item.layer);
+            }
+        }
+        names
+    }
+}
@@ -0,0 +1,400 @@
+//! 三维几何到图纸平面的正交投影
+//!
+//! 视图由一个正交基 (u, v, d) 描述：u 为图纸向右、v 为图纸向上、d 为视线方向。
+//! 消隐采用画家算法：元素按平均深度从远到近绘制，每个元素先画朝向视点的
+//! 三角面作为遮挡填充，再画轮廓线和特征棱线，近处元素自然覆盖远处的线条。
+
+use super::model::{Drawing, Entity};
+use crate::RefnoEnum;
+use crate::geometry::{EleInstGeo, GeoBasicType, ShapeInstancesData};
+use crate::shape::pdms_shape::PlantMesh;
+use glam::{Mat4, Vec2, Vec3};
+use std::collections::HashMap;
+
+/// 特征棱线的默认二面角阈值（度）
+pub const DEFAULT_FEATURE_ANGLE: f32 = 30.0;
+
+/// 视图
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    /// 视图原点，投影到图纸坐标 (0, 0)
+    pub origin: Vec3,
+    /// 图纸向右方向
+    pub right: Vec3,
+    /// 图纸向上方向
+    pub up: Vec3,
+    /// 视线方向（从视点指向场景）
+    pub dir: Vec3,
+    /// 深度范围 [near, far]，沿视线方向从原点量起，None 表示不限
+    pub depth_range: Option<(f32, f32)>,
+    /// 图纸平面上的裁剪框 (min, max)，None 表示不限
+    pub clip: Option<(Vec2, Vec2)>,
+}
+
+impl View {
+    /// 平面图：从上向下看，东向右、北向上
+    pub fn plan() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            right: Vec3::X,
+            up: Vec3::Y,
+            dir: Vec3::NEG_Z,
+            depth_range: None,
+            clip: None,
+        }
+    }
+
+    /// 立面图：沿水平方向 `dir` 看，Z 向上
+    ///
+    /// `dir` 为竖直方向或零向量时返回 None。
+    pub fn elevation(dir: Vec3) -> Option<Self> {
+        let dir = Vec3::new(dir.x, dir.y, 0.0).try_normalize()?;
+        Some(Self {
+            origin: Vec3::ZERO,
+            right: dir.cross(Vec3::Z),
+            up: Vec3::Z,
+            dir,
+            depth_range: None,
+            clip: None,
+        })
+    }
+
+    /// 剖面图：剖切面过 `point`，沿 `dir` 看向剖切面后方 `depth` 范围内的几何
+    ///
+    /// 竖直视线时图纸向上为 Y，否则为 Z。
+    pub fn section(point: Vec3, dir: Vec3, depth: f32) -> Option<Self> {
+        let dir = dir.try_normalize()?;
+        let reference = if dir.z.abs() > 0.999 {
+            Vec3::Y
+        } else {
+            Vec3::Z
+        };
+        let right = dir.cross(reference).try_normalize()?;
+        let up = right.cross(dir);
+        Some(Self {
+            origin: point,
+            right,
+            up,
+            dir,
+            depth_range: Some((0.0, depth.max(0.0))),
+            clip: None,
+        })
+    }
+
+    /// 设置视图原点
+    pub fn with_origin(mut self, origin: Vec3) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// 设置深度范围
+    pub fn with_depth_range(mut self, near: f32, far: f32) -> Self {
+        self.depth_range = Some((near.min(far), near.max(far)));
+        self
+    }
+
+    /// 设置图纸平面上的裁剪框
+    pub fn with_clip(mut self, min: Vec2, max: Vec2) -> Self {
+        self.clip = Some((min.min(max), min.max(max)));
+        self
+    }
+
+    /// 投影到图纸坐标
+    #[inline]
+    pub fn project(&self, p: Vec3) -> Vec2 {
+        let rel = p - self.origin;
+        Vec2::new(rel.dot(self.right), rel.dot(self.up))
+    }
+
+    /// 沿视线方向的深度，越大越远
+    #[inline]
+    pub fn depth(&self, p: Vec3) -> f32 {
+        (p - self.origin).dot(self.dir)
+    }
+
+    /// 三角形是否落在视图的剖切盒内（与盒相交即算）
+    fn accepts(&self, tri: &[Vec3; 3]) -> bool {
+        if let Some((near, far)) = self.depth_range {
+            let depths = tri.map(|p| self.depth(p));
+            if depths.iter().all(|&d| d < near) || depths.iter().all(|&d| d > far) {
+                return false;
+            }
+        }
+        if let Some((min, max)) = self.clip {
+            let pts = tri.map(|p| self.project(p));
+            if pts.iter().all(|p| p.x < min.x)
+                || pts.iter().all(|p| p.x > max.x)
+                || pts.iter().all(|p| p.y < min.y)
+                || pts.iter().all(|p| p.y > max.y)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 场景中的一个元素
+#[derive(Debug, Clone, Default)]
+pub struct SceneItem {
+    pub refno: RefnoEnum,
+    /// 元素类型，同时作为图层名
+    pub noun: String,
+    /// 世界坐标下的三角形
+    pub triangles: Vec<[Vec3; 3]>,
+}
+
+/// 待投影的三维场景
+#[derive(Debug, Clone, Default)]
+pub struct PlotScene {
+    pub items: Vec<SceneItem>,
+}
+
+impl PlotScene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个变换到世界坐标的 mesh，同一参考号的多个 mesh 合并为一个元素
+    pub fn add_mesh(&mut self, refno: RefnoEnum, noun: &str, mesh: &PlantMesh, transform: Mat4) {
+        let vertices: Vec<Vec3> = mesh
+            .vertices
+            .iter()
+            .map(|v| transform.transform_point3(*v))
+            .collect();
+        let triangles = mesh.indices.chunks_exact(3).filter_map(|tri| {
+            let a = *vertices.get(tri[0] as usize)?;
+            let b = *vertices.get(tri[1] as usize)?;
+            let c = *vertices.get(tri[2] as usize)?;
+            Some([a, b, c])
+        });
+        match self.items.iter_mut().find(|item| item.refno == refno) {
+            Some(item) => item.triangles.extend(triangles),
+            None => self.items.push(SceneItem {
+                refno,
+                noun: noun.to_string(),
+                triangles: triangles.collect(),
+            }),
+        }
+    }
+
+    /// 从 [`ShapeInstancesData`] 收集选中元素的几何
+    ///
+    /// `nouns` 给出选中元素及其类型，不在其中的元素跳过；`load_mesh` 按 geo_hash
+    /// 提供 mesh（例如 [`load_mesh_from_dir`](crate::geometry::gltf_export::load_mesh_from_dir)），
+    /// 每个 geo_hash 只加载一次。不可见的元素和实例、负实体不参与出图。
+    pub fn from_shape_instances(
+        data: &ShapeInstancesData,
+        nouns: &HashMap<RefnoEnum, String>,
+        mut load_mesh: impl FnMut(u64) -> Option<PlantMesh>,
+    ) -> Self {
+        let mut scene = Self::new();
+        let mut meshes: HashMap<u64, Option<PlantMesh>> = HashMap::new();
+        let infos = data
+            .inst_info_map
+            .values()
+            .chain(data.inst_tubi_map.values())
+            .filter(|info| info.visible);
+        for info in infos {
+            let Some(noun) = nouns.get(&info.refno) else {
+                continue;
+            };
+            let Some(geos) = data.inst_geos_map.get(&info.get_inst_key()) else {
+                continue;
+            };
+            for geo in geos.insts.iter().filter(|g| plottable(g)) {
+                let Some(mesh) = meshes
+                    .entry(geo.geo_hash)
+                    .or_insert_with(|| load_mesh(geo.geo_hash))
+                else {
+                    continue;
+                };
+                let trans = info.get_geo_world_transform(geo).to_matrix();
+                scene.add_mesh(info.refno, noun, mesh, trans);
+            }
+        }
+        scene
+    }
+
+    /// 按视图投影为图纸，每个元素的图元放在以其类型命名的图层上
+    pub fn project(&self, view: &View) -> Drawing {
+        self.project_with(view, DEFAULT_FEATURE_ANGLE)
+    }
+
+    /// 同 [`Self::project`]，`feature_angle` 为特征棱线的二面角阈值（度）
+    pub fn project_with(&self, view: &View, feature_angle: f32) -> Drawing {
+        let cos_feature = feature_angle.to_radians().cos();
+        let mut visible: Vec<(f32, &SceneItem, Vec<[Vec3; 3]>)> = self
+            .items
+            .iter()
+            .filter_map(|item| {
+                let tris: Vec<[Vec3; 3]> = item
+                    .triangles
+                    .iter()
+                    .filter(|tri| view.accepts(tri))
+                    .copied()
+                    .collect();
+                if tris.is_empty() {
+                    return None;
+                }
+                let depth = tris
+                    .iter()
+                    .map(|tri| tri.iter().map(|p| view.depth(*p)).sum::<f32>())
+                    .sum::<f32>()
+                    / (tris.len() * 3) as f32;
+                Some((depth, item, tris))
+            })
+            .collect();
+        // 从远到近
+        visible.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut drawing = Drawing::new();
+        for (_, item, tris) in visible {
+            let refno = Some(item.refno);
+            for tri in tris.iter().filter(|tri| faces_viewer(tri, view)) {
+                drawing.push(
+                    &item.noun,
+                    refno,
+                    Entity::Polygon {
+                        points: tri.iter().map(|p| view.project(*p)).collect(),
+                    },
+                );
+            }
+            for (a, b) in feature_edges(&tris, view, cos_feature) {
+                let (a, b) = (view.project(a), view.project(b));
+                if a.distance_squared(b) > f32::EPSILON {
+                    drawing.push(&item.noun, refno, Entity::Line { a, b });
+                }
+            }
+        }
+        drawing
+    }
+}
+
+fn plottable(geo: &EleInstGeo) -> bool {
+    geo.visible
+        && !matches!(
+            geo.geo_type,
+            GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+        )
+}
+
+fn tri_normal(tri: &[Vec3; 3]) -> Vec3 {
+    (tri[1] - tri[0]).cross(tri[2] - tri[0]).normalize_or_zero()
+}
+
+fn faces_viewer(tri: &[Vec3; 3], view: &View) -> bool {
+    tri_normal(tri).dot(view.dir) < -1e-4
+}
+
+/// 顶点焊接用的量化键（0.01 mm）
+fn vertex_key(p: Vec3) -> [i64; 3] {
+    (p * 100.0).round().as_i64vec3().to_array()
+}
+
+/// 需要画出的棱线：边界边、轮廓边（一侧朝向视点另一侧背离）和二面角超过阈值的边，
+/// 且至少有一侧朝向视点
+fn feature_edges(tris: &[[Vec3; 3]], view: &View, cos_feature: f32) -> Vec<(Vec3, Vec3)> {
+    let mut edges: HashMap<([i64; 3], [i64; 3]), (Vec3, Vec3, Vec<usize>)> = HashMap::new();
+    for (i, tri) in tris.iter().enumerate() {
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            let (ka, kb) = (vertex_key(a), vertex_key(b));
+            if ka == kb {
+                continue;
+            }
+            let key = if ka < kb { (ka, kb) } else { (kb, ka) };
+            edges.entry(key).or_insert((a, b, vec![])).2.push(i);
+        }
+    }
+    let normals: Vec<Vec3> = tris.iter().map(tri_normal).collect();
+    let front: Vec<bool> = tris.iter().map(|tri| faces_viewer(tri, view)).collect();
+    let mut result: Vec<(([i64; 3], [i64; 3]), (Vec3, Vec3))> = edges
+        .into_iter()
+        .filter(|(_, (_, _, faces))| {
+            if !faces.iter().any(|&f| front[f]) {
+                return false;
+            }
+            match faces.as_slice() {
+                [f0, f1] => {
+                    front[*f0] != front[*f1] || normals[*f0].dot(normals[*f1]) < cos_feature
+                }
+                _ => true,
+            }
+        })
+        .map(|(key, (a, b, _))| (key, (a, b)))
+        .collect();
+    // 输出顺序稳定
+    result.sort_by(|x, y| x.0.cmp(&y.0));
+    result.into_iter().map(|(_, edge)| edge).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::csg::unit_box_mesh;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn scene() -> PlotScene {
+        let mut scene = PlotScene::new();
+        let mesh = unit_box_mesh();
+        // 下方的大板和上方的小盒子
+        scene.add_mesh(
+            refno(1),
+            "PANE",
+            &mesh,
+            Mat4::from_scale(Vec3::new(10.0, 10.0, 1.0)),
+        );
+        scene.add_mesh(
+            refno(2),
+            "EQUI",
+            &mesh,
+            Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0)),
+        );
+        scene
+    }
+
+    #[test]
+    fn test_plan_projection() {
+        let drawing = scene().project(&View::plan());
+        // 远处的板先画，近处的盒子后画
+        let first = drawing.items.first().unwrap();
+        let last = drawing.items.last().unwrap();
+        assert_eq!(first.refno, Some(refno(1)));
+        assert_eq!(last.refno, Some(refno(2)));
+        assert_eq!(drawing.layer_names(), vec!["PANE", "EQUI"]);
+
+        // 盒子俯视只看到顶面：两个三角形、四条轮廓边，顶面对角线不画
+        let count = |n: u32, line: bool| {
+            drawing
+                .items
+                .iter()
+                .filter(|i| i.refno == Some(refno(n)))
+                .filter(|i| matches!(i.entity, Entity::Line { .. }) == line)
+                .count()
+        };
+        assert_eq!(count(2, false), 2);
+        assert_eq!(count(2, true), 4);
+
+        let (min, max) = drawing.bounds().unwrap();
+        assert!(min.abs_diff_eq(Vec2::splat(-5.0), 1e-4));
+        assert!(max.abs_diff_eq(Vec2::splat(5.0), 1e-4));
+    }
+
+    #[test]
+    fn test_section_depth() {
+        // 从 z=6 向下剖切 2mm 深，只剩上方的盒子
+        let view = View::section(Vec3::new(0.0, 0.0, 6.0), Vec3::NEG_Z, 2.0).unwrap();
+        let drawing = scene().project(&view);
+        assert!(drawing.items.iter().all(|i| i.refno == Some(refno(2))));
+        assert!(!drawing.items.is_empty());
+
+        let elev = View::elevation(Vec3::Y).unwrap();
+        assert!(elev.right.abs_diff_eq(Vec3::X, 1e-6));
+        assert!(View::elevation(Vec3::Z).is_none());
+    }
+}
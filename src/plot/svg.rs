@@ -0,0 +1,299 @@
+//! 图纸的 SVG 输出
+//!
+//! 每个图层生成一条 CSS 样式，图元按绘制顺序输出，连续属于同一元素的图元
+//! 放在一个 `<g>` 中并带上 `data-refno`，便于前端按元素拾取和高亮。
+
+use super::model::{Drawing, Entity, LayerStyle};
+use crate::RefnoEnum;
+use anyhow::Result;
+use glam::Vec2;
+use std::f32::consts::PI;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// SVG 输出选项
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// 出图比例，图纸 mm = 模型 mm × scale（1:50 即 0.02）
+    pub scale: f32,
+    /// 图纸四周留白（图纸 mm）
+    pub margin: f32,
+    /// 背景色，None 表示透明
+    pub background: Option<[u8; 3]>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            margin: 10.0,
+            background: Some([255, 255, 255]),
+        }
+    }
+}
+
+/// 图纸坐标（Y 向上）到 SVG 坐标（Y 向下）的映射
+struct Mapper {
+    min: Vec2,
+    max_y: f32,
+    scale: f32,
+    margin: f32,
+}
+
+impl Mapper {
+    fn map(&self, p: Vec2) -> Vec2 {
+        Vec2::new(
+            (p.x - self.min.x) * self.scale + self.margin,
+            (self.max_y - p.y) * self.scale + self.margin,
+        )
+    }
+}
+
+/// 把图层名转成可用作 CSS 类名的字符串
+fn layer_class(layer: &str) -> String {
+    let name: String = layer
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("L-{name}")
+}
+
+fn rgb(c: [u8; 3]) -> String {
+    format!("rgb({},{},{})", c[0], c[1], c[2])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn points_attr(points: &[Vec2], m: &Mapper) -> String {
+    points
+        .iter()
+        .map(|p| {
+            let p = m.map(*p);
+            format!("{:.3},{:.3}", p.x, p.y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 线宽是图纸尺寸，不随出图比例缩放
+fn style_rules(class: &str, style: &LayerStyle) -> String {
+    let fill = style.fill.map_or("none".to_string(), rgb);
+    format!(
+        ".{class} {{ stroke: {}; stroke-width: {:.3}; fill: none; }}\n\
+         .{class} polygon {{ fill: {fill}; stroke: none; }}\n\
+         .{class} polygon.outline {{ fill: none; stroke: {}; }}\n\
+         .{class} text {{ fill: {}; stroke: none; }}\n",
+        rgb(style.stroke),
+        style.stroke_width,
+        rgb(style.stroke),
+        rgb(style.stroke),
+    )
+}
+
+fn write_entity(out: &mut String, entity: &Entity, m: &Mapper) {
+    match entity {
+        Entity::Line { a, b } => {
+            let (a, b) = (m.map(*a), m.map(*b));
+            let _ = writeln!(
+                out,
+                r#"<line x1="{:.3}" y1="{:.3}" x2="{:.3}" y2="{:.3}"/>"#,
+                a.x, a.y, b.x, b.y
+            );
+        }
+        Entity::Polyline { points, closed } => {
+            // 闭合折线只画轮廓，不参与遮挡填充
+            let tag = if *closed {
+                r#"polygon class="outline""#
+            } else {
+                "polyline"
+            };
+            let _ = writeln!(out, r#"<{tag} points="{}"/>"#, points_attr(points, m));
+        }
+        Entity::Polygon { points } => {
+            let _ = writeln!(out, r#"<polygon points="{}"/>"#, points_attr(points, m));
+        }
+        Entity::Circle { center, radius } => {
+            let c = m.map(*center);
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.3}" cy="{:.3}" r="{:.3}"/>"#,
+                c.x,
+                c.y,
+                radius * m.scale
+            );
+        }
+        Entity::Arc {
+            center,
+            radius,
+            start_angle,
+            end_angle,
+        } => {
+            let mut sweep = end_angle - start_angle;
+            while sweep <= 0.0 {
+                sweep += 2.0 * PI;
+            }
+            let start = m.map(*center + Vec2::from_angle(*start_angle) * *radius);
+            let end = m.map(*center + Vec2::from_angle(*end_angle) * *radius);
+            let r = radius * m.scale;
+            let large = (sweep > PI) as u8;
+            // 图纸逆时针在 SVG（Y 向下）中为顺时针，sweep-flag 取 0
+            let _ = writeln!(
+                out,
+                r#"<path d="M {:.3} {:.3} A {r:.3} {r:.3} 0 {large} 0 {:.3} {:.3}"/>"#,
+                start.x, start.y, end.x, end.y
+            );
+        }
+        Entity::Text {
+            position,
+            text,
+            height,
+            rotation,
+        } => {
+            let p = m.map(*position);
+            let _ = writeln!(
+                out,
+                r#"<text transform="translate({:.3},{:.3}) rotate({:.3})" font-size="{:.3}">{}</text>"#,
+                p.x,
+                p.y,
+                -rotation.to_degrees(),
+                height * m.scale,
+                escape(text)
+            );
+        }
+    }
+}
+
+/// 把图纸输出为 SVG 字符串，宽高单位为 mm
+pub fn to_svg(drawing: &Drawing, options: &SvgOptions) -> String {
+    let (min, max) = drawing.bounds().unwrap_or((Vec2::ZERO, Vec2::ZERO));
+    let scale = if options.scale > 0.0 {
+        options.scale
+    } else {
+        1.0
+    };
+    let m = Mapper {
+        min,
+        max_y: max.y,
+        scale,
+        margin: options.margin,
+    };
+    let width = (max.x - min.x) * scale + options.margin * 2.0;
+    let height = (max.y - min.y) * scale + options.margin * 2.0;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.3}mm" height="{height:.3}mm" viewBox="0 0 {width:.3} {height:.3}">"#
+    );
+    out.push_str("<style>\n");
+    for layer in drawing.layer_names() {
+        out.push_str(&style_rules(
+            &layer_class(layer),
+            &drawing.layer_style(layer),
+        ));
+    }
+    out.push_str("</style>\n");
+    if let Some(bg) = options.background {
+        let _ = writeln!(
+            out,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            rgb(bg)
+        );
+    }
+
+    let mut current: Option<(&str, Option<RefnoEnum>)> = None;
+    for item in &drawing.items {
+        let key = (item.layer.as_str(), item.refno);
+        if current != Some(key) {
+            if current.is_some() {
+                out.push_str("</g>\n");
+            }
+            let refno_attr = item
+                .refno
+                .map(|r| format!(r#" data-refno="{r}""#))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                r#"<g class="{}"{refno_attr}>"#,
+                layer_class(&item.layer)
+            );
+            current = Some(key);
+        }
+        write_entity(&mut out, &item.entity, &m);
+    }
+    if current.is_some() {
+        out.push_str("</g>\n");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// 输出 SVG 文件
+pub fn write_svg(drawing: &Drawing, options: &SvgOptions, path: impl AsRef<Path>) -> Result<()> {
+    std::fs::write(path, to_svg(drawing, options))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    #[test]
+    fn test_to_svg() {
+        let refno: RefnoEnum = RefU64::from_two_nums(17496, 1).into();
+        let mut drawing = Drawing::new();
+        drawing.push(
+            "EQUI",
+            Some(refno),
+            Entity::Polygon {
+                points: vec![Vec2::ZERO, Vec2::new(100.0, 0.0), Vec2::new(100.0, 50.0)],
+            },
+        );
+        drawing.push(
+            "EQUI",
+            Some(refno),
+            Entity::Line {
+                a: Vec2::ZERO,
+                b: Vec2::new(100.0, 50.0),
+            },
+        );
+        drawing.push(
+            "TEXT",
+            None,
+            Entity::Text {
+                position: Vec2::ZERO,
+                text: "A<B".into(),
+                height: 5.0,
+                rotation: 0.0,
+            },
+        );
+        let svg = to_svg(
+            &drawing,
+            &SvgOptions {
+                scale: 0.5,
+                margin: 0.0,
+                background: None,
+            },
+        );
+        assert!(svg.contains(r#"width="50.000mm""#));
+        assert!(svg.contains(".L-EQUI {"));
+        assert!(svg.contains(r#"<g class="L-EQUI" data-refno="17496_1">"#));
+        // Y 轴翻转：图纸 (0,0) 在 SVG 左下角
+        assert!(svg.contains(r#"<line x1="0.000" y1="25.000" x2="50.000" y2="0.000"/>"#));
+        assert!(svg.contains("A&lt;B"));
+        assert_eq!(svg.matches("<g ").count(), 2);
+        assert_eq!(svg.matches("</g>").count(), 2);
+    }
+}
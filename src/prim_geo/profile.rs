@@ -18,6 +18,7 @@ use crate::tool::math_tool::{
     dquat_to_pdms_ori_xyz_str, quat_to_pdms_ori_str, to_pdms_ori_str, to_pdms_vec_str,
 };
 use crate::transform::{calculate_plax_transform, get_local_transform};
+use crate::utils::refno_span::{record_span_noun, with_refno_span};
 use crate::{RefU64, get_world_transform};
 use anyhow::anyhow;
 use bevy_transform::prelude::Transform;
@@ -244,6 +245,14 @@ pub async fn create_profile_geos(
     refno: RefnoEnum,
     geom_info: &CateGeomsInfo,
     csg_shapes_map: &CateCsgShapeMap,
+) -> anyhow::Result<bool> {
+    with_refno_span(refno, create_profile_geos_impl(refno, geom_info, csg_shapes_map)).await
+}
+
+async fn create_profile_geos_impl(
+    refno: RefnoEnum,
+    geom_info: &CateGeomsInfo,
+    csg_shapes_map: &CateCsgShapeMap,
 ) -> anyhow::Result<bool> {
     let geos = &geom_info.geometries;
     if geos.len() == 0 {
@@ -251,6 +260,7 @@ pub async fn create_profile_geos(
     }
     let att = crate::get_named_attmap(refno).await?;
     let type_name = att.get_type_str();
+    record_span_noun(type_name);
     let mut plax = Vec3::Y;
     let mut extrude_dir = DVec3::Z;
 
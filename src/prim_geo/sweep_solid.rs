@@ -52,6 +52,7 @@ pub struct SweepSolid {
     pub path: SweepPath3D,
     pub lmirror: bool,
     pub spine_segments: Vec<Spine3D>, // 存储原始 Spine3D 段信息（用于变换）
+    #[rkyv(with = rkyv::with::Map<crate::geometry::archive::TransformAsArray>)]
     pub segment_transforms: Vec<Transform>, // 存储每段起点 POINSP 的 local transform
 }
 
@@ -0,0 +1,139 @@
+//! Python 绑定（`python` 特性）
+//!
+//! 基于 PyO3，通过 [`crate::blocking`] 的全局同步客户端调用异步接口。
+//! 每次调用都会在等待 tokio 运行时期间释放 GIL，不会阻塞其他 Python 线程。
+//!
+//! 参考号以字符串传入/返回（如 `"17496/201377"`），结构化结果转换为 dict/list。
+//! 构建：`maturin build --release`（见 `pyproject.toml`），类型存根见 `aios_core.pyi`。
+//!
+//! 不提供 XKT 导出：几何后端由下游模型生成程序注册，Python 扩展中没有可用的后端。
+
+use crate::RefnoEnum;
+use crate::blocking::{BlockingClient, global_client};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::Serialize;
+use std::str::FromStr;
+
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+fn parse_refno(s: &str) -> PyResult<RefnoEnum> {
+    RefnoEnum::from_str(s).map_err(|_| PyValueError::new_err(format!("无效的参考号: {}", s)))
+}
+
+fn parse_refnos(refnos: &[String]) -> PyResult<Vec<RefnoEnum>> {
+    refnos.iter().map(|s| parse_refno(s)).collect()
+}
+
+fn to_py<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    pythonize::pythonize(py, value).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+/// 释放 GIL 后在全局客户端上执行
+fn call<T, F>(py: Python<'_>, f: F) -> PyResult<T>
+where
+    T: Send,
+    F: FnOnce(&'static BlockingClient) -> anyhow::Result<T> + Send,
+{
+    py.allow_threads(|| global_client().and_then(f))
+        .map_err(to_py_err)
+}
+
+/// 初始化数据库连接（按 `DB_OPTION_FILE` 配置），重复调用无副作用
+#[pyfunction]
+fn init(py: Python<'_>) -> PyResult<()> {
+    call(py, |_| Ok(()))
+}
+
+/// 查询元素基本信息，不存在时返回 None
+#[pyfunction]
+fn get_pe<'py>(py: Python<'py>, refno: &str) -> PyResult<Bound<'py, PyAny>> {
+    let refno = parse_refno(refno)?;
+    let pe = call(py, |c| c.get_pe(refno))?;
+    to_py(py, &pe)
+}
+
+/// 查询元素属性，返回 dict
+#[pyfunction]
+fn get_attmap<'py>(py: Python<'py>, refno: &str) -> PyResult<Bound<'py, PyAny>> {
+    let refno = parse_refno(refno)?;
+    let attmap = call(py, |c| c.get_named_attmap(refno))?;
+    to_py(py, &attmap)
+}
+
+/// 查询直接子元素
+#[pyfunction]
+fn get_children(py: Python<'_>, refno: &str) -> PyResult<Vec<String>> {
+    let refno = parse_refno(refno)?;
+    let children = call(py, |c| c.get_children_refnos(refno))?;
+    Ok(children.iter().map(|r| r.to_string()).collect())
+}
+
+/// 查询全部子孙元素
+#[pyfunction]
+fn get_descendants(py: Python<'_>, refno: &str) -> PyResult<Vec<String>> {
+    let refno = parse_refno(refno)?;
+    let refnos = call(py, |c| c.query_deep_children_refnos(refno))?;
+    Ok(refnos.iter().map(|r| r.to_string()).collect())
+}
+
+/// 查询世界变换，返回列主序 4x4 矩阵（16 个元素）
+#[pyfunction]
+fn get_world_transform(py: Python<'_>, refno: &str) -> PyResult<Option<Vec<f32>>> {
+    let refno = parse_refno(refno)?;
+    let transform = call(py, |c| c.get_world_transform(refno))?;
+    Ok(transform.map(|t| t.to_matrix().to_cols_array().to_vec()))
+}
+
+/// 查询几何实例
+#[pyfunction]
+#[pyo3(signature = (refnos, enable_holes = false))]
+fn query_insts<'py>(
+    py: Python<'py>,
+    refnos: Vec<String>,
+    enable_holes: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let refnos = parse_refnos(&refnos)?;
+    let insts = call(py, |c| c.query_insts(&refnos, enable_holes))?;
+    to_py(py, &insts)
+}
+
+/// 查询指定专业、指定 site 的材料表单，返回 {表单名: [行]}
+#[pyfunction]
+fn get_material_lists<'py>(
+    py: Python<'py>,
+    major: &str,
+    site: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let site = parse_refno(site)?;
+    let lists = call(py, |c| c.get_material_lists(major, site))?;
+    let dict = PyDict::new(py);
+    for (name, rows) in lists {
+        dict.set_item(name, to_py(py, &rows)?)?;
+    }
+    Ok(dict)
+}
+
+/// 检查数据库连接与数据完整性
+#[pyfunction]
+fn run_doctor<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let report = call(py, |c| c.run_doctor())?;
+    to_py(py, &report)
+}
+
+#[pymodule]
+fn aios_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(init, m)?)?;
+    m.add_function(wrap_pyfunction!(get_pe, m)?)?;
+    m.add_function(wrap_pyfunction!(get_attmap, m)?)?;
+    m.add_function(wrap_pyfunction!(get_children, m)?)?;
+    m.add_function(wrap_pyfunction!(get_descendants, m)?)?;
+    m.add_function(wrap_pyfunction!(get_world_transform, m)?)?;
+    m.add_function(wrap_pyfunction!(query_insts, m)?)?;
+    m.add_function(wrap_pyfunction!(get_material_lists, m)?)?;
+    m.add_function(wrap_pyfunction!(run_doctor, m)?)?;
+    Ok(())
+}
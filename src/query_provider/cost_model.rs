@@ -0,0 +1,173 @@
+//! 查询代价模型
+//!
+//! 按 (引擎, 查询类别) 记录延迟直方图，为 `QueryRouter` 的自动路由提供代价估算
+
+use super::router::QueryEngine;
+use crate::sync::{LatencyHistogram, LatencySummary};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// 查询类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryClass {
+    /// 深层层级遍历（子孙、祖先、路径、深度）
+    DeepHierarchy,
+    /// 属性 / PE 查找
+    Attribute,
+    /// 直接子节点、按类型查询等一般查询
+    General,
+}
+
+impl QueryClass {
+    /// 该类别下各引擎的优先顺序
+    ///
+    /// 深层遍历优先交给 TreeIndex / Kuzu，属性查找只走 SurrealDB
+    pub fn preferred_engines(&self) -> &'static [QueryEngine] {
+        match self {
+            QueryClass::DeepHierarchy => &[
+                QueryEngine::TreeIndex,
+                QueryEngine::Kuzu,
+                QueryEngine::SurrealDB,
+            ],
+            QueryClass::Attribute => &[QueryEngine::SurrealDB],
+            QueryClass::General => &[
+                QueryEngine::SurrealDB,
+                QueryEngine::Kuzu,
+                QueryEngine::TreeIndex,
+            ],
+        }
+    }
+}
+
+/// 代价模型
+pub struct CostModel {
+    stats: RwLock<HashMap<(QueryEngine, QueryClass), LatencyHistogram>>,
+    /// 参与比较前每个引擎至少需要的样本数
+    min_samples: u64,
+    /// 样本数超过该值时衰减一次
+    window: u64,
+    /// 每单位错误率附加的代价（毫秒）
+    error_penalty_ms: f64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self::new(8, 1024, 1000.0)
+    }
+}
+
+impl CostModel {
+    /// 创建代价模型
+    pub fn new(min_samples: u64, window: u64, error_penalty_ms: f64) -> Self {
+        Self {
+            stats: RwLock::new(HashMap::new()),
+            min_samples: min_samples.max(1),
+            window: window.max(min_samples * 2),
+            error_penalty_ms,
+        }
+    }
+
+    /// 记录一次查询
+    pub fn record(&self, engine: QueryEngine, class: QueryClass, elapsed: Duration, ok: bool) {
+        if let Ok(mut stats) = self.stats.write() {
+            let hist = stats.entry((engine, class)).or_default();
+            hist.record(elapsed, ok);
+            if hist.count() >= self.window {
+                hist.decay();
+            }
+        }
+    }
+
+    /// 估算代价（毫秒）：中位数延迟加错误惩罚；样本不足时返回 None
+    pub fn estimate(&self, engine: QueryEngine, class: QueryClass) -> Option<f64> {
+        let stats = self.stats.read().ok()?;
+        let hist = stats.get(&(engine, class))?;
+        if hist.count() < self.min_samples {
+            return None;
+        }
+        Some(hist.percentile_ms(0.5)? + hist.error_rate() * self.error_penalty_ms)
+    }
+
+    /// 在候选引擎中选择代价最低者
+    ///
+    /// 候选按优先顺序排列：样本不足的引擎先被选中以积累样本，
+    /// 其余按估算代价比较，代价相同时取靠前的引擎
+    pub fn choose(&self, class: QueryClass, candidates: &[QueryEngine]) -> Option<QueryEngine> {
+        let mut best: Option<(QueryEngine, f64)> = None;
+        for &engine in candidates {
+            let Some(cost) = self.estimate(engine, class) else {
+                return Some(engine);
+            };
+            if best.is_none_or(|(_, c)| cost < c) {
+                best = Some((engine, cost));
+            }
+        }
+        best.map(|(engine, _)| engine)
+    }
+
+    /// 各 (引擎, 类别) 的延迟摘要
+    pub fn snapshot(&self) -> Vec<(QueryEngine, QueryClass, LatencySummary)> {
+        let Ok(stats) = self.stats.read() else {
+            return Vec::new();
+        };
+        stats
+            .iter()
+            .map(|(&(engine, class), hist)| (engine, class, hist.summary()))
+            .collect()
+    }
+
+    /// 清空统计
+    pub fn reset(&self) {
+        if let Ok(mut stats) = self.stats.write() {
+            stats.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_explores_then_picks_cheapest() {
+        let model = CostModel::new(2, 64, 1000.0);
+        let candidates = [QueryEngine::Kuzu, QueryEngine::SurrealDB];
+        let class = QueryClass::DeepHierarchy;
+
+        assert_eq!(model.choose(class, &candidates), Some(QueryEngine::Kuzu));
+
+        for _ in 0..2 {
+            model.record(QueryEngine::Kuzu, class, Duration::from_millis(40), true);
+        }
+        // SurrealDB 样本不足，先积累样本
+        assert_eq!(
+            model.choose(class, &candidates),
+            Some(QueryEngine::SurrealDB)
+        );
+
+        for _ in 0..2 {
+            model.record(
+                QueryEngine::SurrealDB,
+                class,
+                Duration::from_millis(5),
+                true,
+            );
+        }
+        assert_eq!(
+            model.choose(class, &candidates),
+            Some(QueryEngine::SurrealDB)
+        );
+
+        // 出错会抬高代价
+        for _ in 0..4 {
+            model.record(
+                QueryEngine::SurrealDB,
+                class,
+                Duration::from_millis(5),
+                false,
+            );
+        }
+        assert_eq!(model.choose(class, &candidates), Some(QueryEngine::Kuzu));
+    }
+}
@@ -0,0 +1,805 @@
+//! 基于 HTTP 的查询提供者
+//!
+//! 通过 SurrealDB 的 `/sql` HTTP 接口（或转发该接口的代理）执行查询，不依赖原生的
+//! WebSocket 连接和全局 `SUL_DB`，因此可以在 wasm32（浏览器）中使用。
+//!
+//! 传输层抽象为 [`SqlTransport`]，`http-query` 特性提供基于 reqwest 的 [`ReqwestTransport`]
+//! （wasm32 下走浏览器 fetch）；也可以接入宿主自己的 HTTP 客户端。
+//!
+//! ```rust,ignore
+//! use aios_core::query_provider::{HttpProviderConfig, HttpQueryProvider, QueryProvider};
+//!
+//! let provider = HttpQueryProvider::new(
+//!     HttpProviderConfig::new("http://127.0.0.1:8020", "1516", "AvevaMarineSample")
+//!         .basic_auth("root", "root"),
+//! );
+//! let attmap = provider.get_attmap(refno).await?;
+//! ```
+
+use super::error::{QueryError, QueryResult};
+use super::traits::*;
+use crate::rs_surreal::{PlantTransform, convert_to_sql_str_array};
+use crate::types::{NamedAttrMap as NamedAttMap, NamedAttrValue, SPdmsElement as PE};
+use crate::{RefU64, RefnoEnum};
+use async_trait::async_trait;
+use log::debug;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// HTTP 查询配置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpProviderConfig {
+    /// 服务地址，如 `http://127.0.0.1:8020`
+    pub endpoint: String,
+    /// SQL 接口路径，直连 SurrealDB 时为 `/sql`，经代理时按代理的路由填写
+    pub sql_path: String,
+    pub namespace: String,
+    pub database: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Bearer token，优先于用户名密码
+    pub token: Option<String>,
+}
+
+impl HttpProviderConfig {
+    pub fn new(
+        endpoint: impl Into<String>,
+        namespace: impl Into<String>,
+        database: impl Into<String>,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            sql_path: "/sql".to_string(),
+            namespace: namespace.into(),
+            database: database.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn basic_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn sql_path(mut self, path: impl Into<String>) -> Self {
+        self.sql_path = path.into();
+        self
+    }
+
+    /// SQL 接口完整地址
+    pub fn sql_url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.endpoint.trim_end_matches('/'),
+            self.sql_path.trim_start_matches('/')
+        )
+    }
+}
+
+/// SurQL 传输层
+#[async_trait]
+pub trait SqlTransport: Send + Sync {
+    /// 执行 SurQL，按语句顺序返回各语句的结果
+    async fn execute(&self, sql: &str) -> QueryResult<Vec<JsonValue>>;
+}
+
+/// 解析 `/sql` 接口的响应：`[{"status": "OK", "result": ...}, ...]`
+///
+/// 任一语句失败时返回该语句的错误信息。
+pub fn parse_sql_response(body: JsonValue) -> QueryResult<Vec<JsonValue>> {
+    let JsonValue::Array(statements) = body else {
+        return Err(QueryError::ParseError(format!("响应不是数组: {body}")));
+    };
+    statements
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut stmt)| {
+            let status = stmt.get("status").and_then(|s| s.as_str()).unwrap_or("OK");
+            let result = stmt
+                .get_mut("result")
+                .map(JsonValue::take)
+                .unwrap_or(JsonValue::Null);
+            if status == "OK" {
+                Ok(result)
+            } else {
+                Err(QueryError::ExecutionError(format!(
+                    "第 {} 条语句执行失败: {}",
+                    i + 1,
+                    result
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| result.to_string())
+                )))
+            }
+        })
+        .collect()
+}
+
+/// 基于 reqwest 的传输层
+#[cfg(feature = "http-query")]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    config: HttpProviderConfig,
+}
+
+#[cfg(feature = "http-query")]
+impl ReqwestTransport {
+    pub fn new(config: HttpProviderConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    async fn post(&self, sql: String) -> QueryResult<Vec<JsonValue>> {
+        let config = &self.config;
+        let mut request = self
+            .client
+            .post(config.sql_url())
+            .header("Accept", "application/json")
+            .header("surreal-ns", &config.namespace)
+            .header("surreal-db", &config.database)
+            .body(sql);
+        if let Some(token) = &config.token {
+            request = request.bearer_auth(token);
+        } else if let Some(username) = &config.username {
+            request = request.basic_auth(username, config.password.as_ref());
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| QueryError::ConnectionError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| QueryError::ConnectionError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(QueryError::ExecutionError(format!("HTTP {status}: {text}")));
+        }
+        let body =
+            serde_json::from_str(&text).map_err(|e| QueryError::ParseError(e.to_string()))?;
+        parse_sql_response(body)
+    }
+}
+
+#[cfg(feature = "http-query")]
+#[async_trait]
+impl SqlTransport for ReqwestTransport {
+    async fn execute(&self, sql: &str) -> QueryResult<Vec<JsonValue>> {
+        let fut = self.post(sql.to_string());
+        // 浏览器中 fetch 的 future 不是 Send，wasm32 为单线程，包装后满足 trait 约束
+        #[cfg(target_arch = "wasm32")]
+        let fut = send_wrapper::SendWrapper::new(fut);
+        fut.await
+    }
+}
+
+/// PE 记录，字段缺失时取默认值
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PeRow {
+    refno: RefnoEnum,
+    owner: RefnoEnum,
+    name: String,
+    noun: String,
+    dbnum: i32,
+    sesno: i32,
+    status_code: String,
+    cata_hash: String,
+    lock: bool,
+    deleted: bool,
+    typex: Option<i32>,
+}
+
+impl From<PeRow> for PE {
+    fn from(row: PeRow) -> Self {
+        PE {
+            refno: row.refno,
+            owner: row.owner,
+            name: row.name,
+            noun: row.noun,
+            dbnum: row.dbnum,
+            sesno: row.sesno,
+            status_code: row.status_code,
+            cata_hash: row.cata_hash,
+            lock: row.lock,
+            deleted: row.deleted,
+            typex: row.typex,
+            ..Default::default()
+        }
+    }
+}
+
+/// 查询 PE 时去掉 JSON 中无法还原的记录链接字段
+const PE_FIELDS: &str = "* omit id, children, inst_relate_id, tubi_id, world_trans";
+
+/// 参考号形式的字符串（`pe:17496_172806`）
+fn json_refno(s: &str) -> Option<RefU64> {
+    s.strip_prefix("pe:").and_then(|_| RefU64::from_str(s).ok())
+}
+
+/// 按 JSON 值的形态推断属性类型
+///
+/// HTTP 接口返回的 JSON 不带属性类型信息，记录链接也只是 `pe:` 开头的字符串。
+fn json_to_attr_value(value: JsonValue) -> Option<NamedAttrValue> {
+    Some(match value {
+        JsonValue::Bool(b) => NamedAttrValue::BoolType(b),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => match i32::try_from(i) {
+                Ok(i) => NamedAttrValue::IntegerType(i),
+                Err(_) => NamedAttrValue::LongType(i),
+            },
+            None => NamedAttrValue::F32Type(n.as_f64().unwrap_or_default() as f32),
+        },
+        JsonValue::String(s) => match json_refno(&s) {
+            Some(refno) => NamedAttrValue::RefU64Type(refno),
+            None => NamedAttrValue::StringType(s),
+        },
+        JsonValue::Array(items) => match items.first() {
+            None => NamedAttrValue::StringArrayType(Vec::new()),
+            Some(JsonValue::Number(_)) => NamedAttrValue::F32VecType(
+                items
+                    .iter()
+                    .map(|v| v.as_f64().unwrap_or_default() as f32)
+                    .collect(),
+            ),
+            Some(JsonValue::Bool(_)) => NamedAttrValue::BoolArrayType(
+                items
+                    .iter()
+                    .map(|v| v.as_bool().unwrap_or_default())
+                    .collect(),
+            ),
+            Some(JsonValue::String(s)) if json_refno(s).is_some() => NamedAttrValue::RefU64Array(
+                items
+                    .iter()
+                    .filter_map(|v| v.as_str().and_then(json_refno))
+                    .map(RefnoEnum::from)
+                    .collect(),
+            ),
+            Some(JsonValue::String(_)) => NamedAttrValue::StringArrayType(
+                items
+                    .iter()
+                    .map(|v| v.as_str().unwrap_or_default().to_string())
+                    .collect(),
+            ),
+            Some(_) => return None,
+        },
+        JsonValue::Null | JsonValue::Object(_) => return None,
+    })
+}
+
+/// JSON 属性对象转为 NamedAttMap，无法识别的字段跳过
+pub fn json_to_attmap(value: JsonValue) -> Option<NamedAttMap> {
+    let JsonValue::Object(obj) = value else {
+        return None;
+    };
+    let mut attmap = NamedAttMap::default();
+    for (k, v) in obj {
+        if k == "id" {
+            continue;
+        }
+        if let Some(v) = json_to_attr_value(v) {
+            attmap.map.insert(k, v);
+        }
+    }
+    Some(attmap)
+}
+
+fn pe_keys(refnos: &[RefnoEnum]) -> String {
+    refnos
+        .iter()
+        .map(|r| r.to_pe_key())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 基于 HTTP 的查询提供者
+pub struct HttpQueryProvider {
+    name: String,
+    transport: Arc<dyn SqlTransport>,
+}
+
+impl HttpQueryProvider {
+    #[cfg(feature = "http-query")]
+    pub fn new(config: HttpProviderConfig) -> Self {
+        Self::with_transport("SurrealDB-HTTP", Arc::new(ReqwestTransport::new(config)))
+    }
+
+    /// 使用自定义传输层
+    pub fn with_transport(name: impl Into<String>, transport: Arc<dyn SqlTransport>) -> Self {
+        Self {
+            name: name.into(),
+            transport,
+        }
+    }
+
+    /// 执行 SurQL 并把第 `index` 条语句的结果反序列化为 `T`
+    pub async fn query_json<T: DeserializeOwned>(&self, sql: &str, index: usize) -> QueryResult<T> {
+        let mut results = self.transport.execute(sql).await?;
+        if index >= results.len() {
+            return Err(QueryError::ParseError(format!(
+                "结果只有 {} 条，无法取第 {} 条: {sql}",
+                results.len(),
+                index
+            )));
+        }
+        serde_json::from_value(results.swap_remove(index))
+            .map_err(|e| QueryError::ParseError(format!("{e}: {sql}")))
+    }
+
+    /// 世界坐标变换（取 pe 上缓存的 `world_trans`）
+    pub async fn get_world_transform(
+        &self,
+        refno: RefnoEnum,
+    ) -> QueryResult<Option<PlantTransform>> {
+        debug!("[{}] get_world_transform: {:?}", self.name, refno);
+        let sql = format!(
+            "select value world_trans from only {} limit 1;",
+            refno.to_pe_key()
+        );
+        self.query_json(&sql, 0).await
+    }
+
+    async fn query_pes(&self, sql: &str) -> QueryResult<Vec<PE>> {
+        let rows: Vec<PeRow> = self.query_json(sql, 0).await?;
+        Ok(rows.into_iter().map(PE::from).collect())
+    }
+
+    async fn query_attmaps(&self, sql: &str) -> QueryResult<Vec<NamedAttMap>> {
+        let rows: Vec<JsonValue> = self.query_json(sql, 0).await?;
+        Ok(rows.into_iter().filter_map(json_to_attmap).collect())
+    }
+
+    /// 与 `collect_descendant_filter_ids` 相同的子孙查询
+    async fn descendants(
+        &self,
+        refnos: &[RefnoEnum],
+        nouns: &[&str],
+        max_depth: Option<usize>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        if refnos.is_empty() {
+            return Ok(Vec::new());
+        }
+        let range = max_depth.map_or("..".to_string(), |d| format!("1..{d}"));
+        let sql = format!(
+            r#"
+            let $ids = array::distinct(array::filter(array::flatten(array::map([{}], |$refno|
+                fn::collect_descendant_ids_by_types($refno, [{}], none, "{}")
+            )), |$v| $v != none));
+            SELECT VALUE id FROM $ids;
+            "#,
+            pe_keys(refnos),
+            convert_to_sql_str_array(nouns),
+            range
+        );
+        self.query_json(&sql, 1).await
+    }
+}
+
+// ============================================================================
+// HierarchyQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl HierarchyQuery for HttpQueryProvider {
+    async fn get_children(&self, refno: RefnoEnum) -> QueryResult<Vec<RefnoEnum>> {
+        debug!("[{}] get_children: {:?}", self.name, refno);
+        let sql = format!(
+            r#"select value in from {}<-pe_owner where in.id!=none and record::exists(in.id) and !in.deleted"#,
+            refno.to_pe_key()
+        );
+        self.query_json(&sql, 0).await
+    }
+
+    async fn get_descendants(
+        &self,
+        refno: RefnoEnum,
+        max_depth: Option<usize>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] get_descendants: {:?}, depth: {:?}",
+            self.name, refno, max_depth
+        );
+        self.descendants(&[refno], &[], max_depth).await
+    }
+
+    async fn get_ancestors(&self, refno: RefnoEnum) -> QueryResult<Vec<RefnoEnum>> {
+        debug!("[{}] get_ancestors: {:?}", self.name, refno);
+        let sql = format!("return fn::ancestor({}).refno;", refno.to_pe_key());
+        self.query_json(&sql, 0).await
+    }
+
+    async fn get_ancestors_of_type(
+        &self,
+        refno: RefnoEnum,
+        nouns: &[&str],
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] get_ancestors_of_type: {:?}, nouns: {:?}",
+            self.name, refno, nouns
+        );
+        let nouns_str = convert_to_sql_str_array(nouns);
+        let sql = format!(
+            "select value refno from fn::ancestor({}) where refno.TYPE in [{nouns_str}] or refno.TYPEX in [{nouns_str}]",
+            refno.to_pe_key()
+        );
+        self.query_json(&sql, 0).await
+    }
+
+    async fn get_descendants_filtered(
+        &self,
+        refno: RefnoEnum,
+        nouns: &[&str],
+        max_depth: Option<usize>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] get_descendants_filtered: {:?}, nouns: {:?}, depth: {:?}",
+            self.name, refno, nouns, max_depth
+        );
+        self.descendants(&[refno], nouns, max_depth).await
+    }
+
+    async fn get_children_pes(&self, refno: RefnoEnum) -> QueryResult<Vec<PE>> {
+        debug!("[{}] get_children_pes: {:?}", self.name, refno);
+        let sql = format!(
+            "select {PE_FIELDS} from (select value in from {}<-pe_owner where in.id!=none and !in.deleted)",
+            refno.to_pe_key()
+        );
+        self.query_pes(&sql).await
+    }
+}
+
+// ============================================================================
+// TypeQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl TypeQuery for HttpQueryProvider {
+    async fn query_by_type(
+        &self,
+        nouns: &[&str],
+        dbnum: i32,
+        has_children: Option<bool>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] query_by_type: nouns={:?}, dbnum={}, has_children={:?}",
+            self.name, nouns, dbnum, has_children
+        );
+        let children_filter = match has_children {
+            Some(true) => " and array::len(REFNO.children ?? []) > 0",
+            Some(false) => " and array::len(REFNO.children ?? []) = 0",
+            None => "",
+        };
+        let mut refnos = Vec::new();
+        for noun in nouns {
+            let sql = format!(
+                "select value REFNO from {noun} where REFNO.dbnum = {dbnum}{children_filter}"
+            );
+            refnos.extend(self.query_json::<Vec<RefnoEnum>>(&sql, 0).await?);
+        }
+        Ok(refnos)
+    }
+
+    async fn query_by_type_name_contains(
+        &self,
+        nouns: &[&str],
+        dbnum: i32,
+        keyword: &str,
+        case_sensitive: bool,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] query_by_type_name_contains: nouns={:?}, dbnum={}, keyword={}, case_sensitive={}",
+            self.name, nouns, dbnum, keyword, case_sensitive
+        );
+        if keyword.trim().is_empty() {
+            return self.query_by_type(nouns, dbnum, None).await;
+        }
+        let keyword = keyword.replace('\\', "\\\\").replace('\'', "\\'");
+        let name_filter = if case_sensitive {
+            format!("string::contains(NAME, '{keyword}')")
+        } else {
+            format!(
+                "string::contains(string::lowercase(NAME), '{}')",
+                keyword.to_lowercase()
+            )
+        };
+        let mut refnos = Vec::new();
+        for noun in nouns {
+            let sql = format!(
+                "select value REFNO from {noun} where REFNO.dbnum = {dbnum} and NAME != NONE and {name_filter}"
+            );
+            refnos.extend(self.query_json::<Vec<RefnoEnum>>(&sql, 0).await?);
+        }
+        Ok(refnos)
+    }
+
+    async fn query_by_type_multi_db(
+        &self,
+        nouns: &[&str],
+        dbnums: &[i32],
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] query_by_type_multi_db: nouns={:?}, dbnums={:?}",
+            self.name, nouns, dbnums
+        );
+        let dbnums_str = dbnums
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut refnos = Vec::new();
+        for noun in nouns {
+            let sql = format!("select value id from {noun} where REFNO.dbnum in [{dbnums_str}]");
+            refnos.extend(self.query_json::<Vec<RefnoEnum>>(&sql, 0).await?);
+        }
+        Ok(refnos)
+    }
+
+    async fn get_world(&self, dbnum: i32) -> QueryResult<Option<RefnoEnum>> {
+        debug!("[{}] get_world: dbnum={}", self.name, dbnum);
+        let worlds = self.query_by_type(&["WORLD"], dbnum, None).await?;
+        Ok(worlds.first().copied())
+    }
+
+    async fn get_sites(&self, dbnum: i32) -> QueryResult<Vec<RefnoEnum>> {
+        debug!("[{}] get_sites: dbnum={}", self.name, dbnum);
+        self.query_by_type(&["SITE"], dbnum, None).await
+    }
+
+    async fn count_by_type(&self, noun: &str, dbnum: i32) -> QueryResult<usize> {
+        debug!(
+            "[{}] count_by_type: noun={}, dbnum={}",
+            self.name, noun, dbnum
+        );
+        let sql = format!(
+            "select value count() from only {noun} where REFNO.dbnum = {dbnum} group all limit 1"
+        );
+        let count: Option<usize> = self.query_json(&sql, 0).await?;
+        Ok(count.unwrap_or_default())
+    }
+}
+
+// ============================================================================
+// BatchQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl BatchQuery for HttpQueryProvider {
+    async fn get_pes_batch(&self, refnos: &[RefnoEnum]) -> QueryResult<Vec<PE>> {
+        debug!("[{}] get_pes_batch: {} items", self.name, refnos.len());
+        if refnos.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!("select {PE_FIELDS} from [{}]", pe_keys(refnos));
+        self.query_pes(&sql).await
+    }
+
+    async fn get_attmaps_batch(&self, refnos: &[RefnoEnum]) -> QueryResult<Vec<NamedAttMap>> {
+        debug!("[{}] get_attmaps_batch: {} items", self.name, refnos.len());
+        if refnos.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!("select value refno.* from [{}]", pe_keys(refnos));
+        self.query_attmaps(&sql).await
+    }
+
+    async fn get_full_names_batch(
+        &self,
+        refnos: &[RefnoEnum],
+    ) -> QueryResult<Vec<(RefnoEnum, String)>> {
+        debug!(
+            "[{}] get_full_names_batch: {} items",
+            self.name,
+            refnos.len()
+        );
+        if refnos.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "select value [id, fn::default_full_name(id)] from [{}]",
+            pe_keys(refnos)
+        );
+        self.query_json(&sql, 0).await
+    }
+}
+
+// ============================================================================
+// GraphQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl GraphQuery for HttpQueryProvider {
+    async fn query_multi_descendants(
+        &self,
+        refnos: &[RefnoEnum],
+        nouns: &[&str],
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!(
+            "[{}] query_multi_descendants: {} refnos, {:?} nouns",
+            self.name,
+            refnos.len(),
+            nouns
+        );
+        self.descendants(refnos, nouns, None).await
+    }
+
+    async fn find_shortest_path(
+        &self,
+        from: RefnoEnum,
+        to: RefnoEnum,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        debug!("[{}] find_shortest_path: {:?} -> {:?}", self.name, from, to);
+        // 只处理祖先/子孙关系：祖先列表从直接父节点到根节点
+        let ancestors = self.get_ancestors(from).await?;
+        if let Some(pos) = ancestors.iter().position(|r| *r == to) {
+            let mut path = vec![from];
+            path.extend_from_slice(&ancestors[..=pos]);
+            return Ok(path);
+        }
+        let to_ancestors = self.get_ancestors(to).await?;
+        if let Some(pos) = to_ancestors.iter().position(|r| *r == from) {
+            let mut path = vec![to];
+            path.extend_from_slice(&to_ancestors[..=pos]);
+            path.reverse();
+            return Ok(path);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn get_node_depth(&self, refno: RefnoEnum) -> QueryResult<usize> {
+        debug!("[{}] get_node_depth: {:?}", self.name, refno);
+        Ok(self.get_ancestors(refno).await?.len())
+    }
+}
+
+// ============================================================================
+// QueryProvider 实现
+// ============================================================================
+
+#[async_trait]
+impl QueryProvider for HttpQueryProvider {
+    async fn get_pe(&self, refno: RefnoEnum) -> QueryResult<Option<PE>> {
+        debug!("[{}] get_pe: {:?}", self.name, refno);
+        let sql = format!(
+            "select {PE_FIELDS} from only {} limit 1;",
+            refno.to_pe_key()
+        );
+        let row: Option<PeRow> = self.query_json(&sql, 0).await?;
+        Ok(row.map(PE::from))
+    }
+
+    async fn get_attmap(&self, refno: RefnoEnum) -> QueryResult<Option<NamedAttMap>> {
+        debug!("[{}] get_attmap: {:?}", self.name, refno);
+        let sql = format!(r#"(select * from {}.refno)[0];"#, refno.to_pe_key());
+        let value: JsonValue = self.query_json(&sql, 0).await?;
+        Ok(json_to_attmap(value))
+    }
+
+    async fn exists(&self, refno: RefnoEnum) -> QueryResult<bool> {
+        debug!("[{}] exists: {:?}", self.name, refno);
+        let sql = format!("return record::exists({});", refno.to_pe_key());
+        self.query_json(&sql, 0).await
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn health_check(&self) -> QueryResult<bool> {
+        debug!("[{}] health_check", self.name);
+        match self.query_json::<i64>("return 1;", 0).await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                log::warn!("[{}] health check failed: {}", self.name, e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 记录 SQL 并返回固定结果
+    struct MockTransport {
+        result: JsonValue,
+        sqls: parking_lot::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl SqlTransport for MockTransport {
+        async fn execute(&self, sql: &str) -> QueryResult<Vec<JsonValue>> {
+            self.sqls.lock().push(sql.to_string());
+            parse_sql_response(self.result.clone())
+        }
+    }
+
+    fn provider(result: JsonValue) -> (HttpQueryProvider, Arc<MockTransport>) {
+        let transport = Arc::new(MockTransport {
+            result,
+            sqls: Default::default(),
+        });
+        (
+            HttpQueryProvider::with_transport("mock", transport.clone()),
+            transport,
+        )
+    }
+
+    #[test]
+    fn test_parse_sql_response() {
+        let ok = parse_sql_response(json!([{"status": "OK", "result": [1, 2], "time": "1ms"}]));
+        assert_eq!(ok.unwrap(), vec![json!([1, 2])]);
+        let err = parse_sql_response(json!([
+            {"status": "OK", "result": null},
+            {"status": "ERR", "result": "Parse error"}
+        ]));
+        assert!(
+            err.unwrap_err()
+                .to_string()
+                .contains("第 2 条语句执行失败: Parse error")
+        );
+    }
+
+    #[test]
+    fn test_json_to_attmap() {
+        let attmap = json_to_attmap(json!({
+            "id": "ELBO:17496_172806",
+            "REFNO": "pe:17496_172806",
+            "TYPE": "ELBO",
+            "ANGL": 90.0,
+            "PGNO": 3,
+            "POS": [1.0, 2.0, 3.0],
+            "SHOP": true,
+            "MEMB": ["pe:17496_1", "pe:17496_2"],
+            "DESC": null,
+        }))
+        .unwrap();
+        assert!(!attmap.map.contains_key("id") && !attmap.map.contains_key("DESC"));
+        assert_eq!(
+            attmap.map["TYPE"],
+            NamedAttrValue::StringType("ELBO".into())
+        );
+        assert_eq!(attmap.map["ANGL"], NamedAttrValue::F32Type(90.0));
+        assert_eq!(attmap.map["PGNO"], NamedAttrValue::IntegerType(3));
+        assert_eq!(
+            attmap.map["POS"],
+            NamedAttrValue::F32VecType(vec![1.0, 2.0, 3.0])
+        );
+        assert_eq!(
+            attmap.map["REFNO"],
+            NamedAttrValue::RefU64Type(RefU64::from_str("17496_172806").unwrap())
+        );
+        assert!(matches!(&attmap.map["MEMB"], NamedAttrValue::RefU64Array(v) if v.len() == 2));
+    }
+
+    #[tokio::test]
+    async fn test_provider_queries() {
+        let (p, transport) =
+            provider(json!([{"status": "OK", "result": ["pe:17496_1", "pe:17496_2"]}]));
+        let refno = RefnoEnum::from("17496_100");
+        let children = p.get_children(refno).await.unwrap();
+        assert_eq!(children.len(), 2);
+        assert!(transport.sqls.lock()[0].contains("<-pe_owner"));
+
+        let (p, _) = provider(json!([{"status": "OK", "result": {
+            "refno": "pe:17496_100", "owner": "pe:17496_1", "name": "/PIPE-1", "noun": "PIPE", "dbnum": 1112
+        }}]));
+        let pe = p.get_pe(refno).await.unwrap().unwrap();
+        assert_eq!(
+            (pe.name.as_str(), pe.noun.as_str(), pe.dbnum),
+            ("/PIPE-1", "PIPE", 1112)
+        );
+    }
+}
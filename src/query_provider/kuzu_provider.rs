@@ -0,0 +1,595 @@
+//! Kuzu 图数据库查询提供者
+//!
+//! Kuzu 只保存 PE 层级（`PE` 节点表 + `OWNS` 关系表），用于深层遍历；
+//! 属性、全名等查询返回错误，由 `QueryRouter` 回退到 SurrealDB。
+//!
+//! 层级数据通过 [`KuzuQueryProvider::sync_dbnum`] 从 SurrealDB 导入，
+//! 或由调用方通过 [`KuzuQueryProvider::load_pes`] 写入。
+//! 配置了 `DbOption::kuzu_path` 时，`QueryRouter::new` 会自动打开并注册为 [`QueryEngine::Kuzu`]。
+
+use super::error::{QueryError, QueryResult};
+use super::router::{QueryEngine, QueryRouter};
+use super::traits::*;
+use crate::types::{NamedAttrMap as NamedAttMap, SPdmsElement as PE};
+use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt};
+use async_trait::async_trait;
+use kuzu::{Connection, Database, SystemConfig, Value};
+use log::debug;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 变长路径的最大跳数
+const MAX_DEPTH: usize = 30;
+
+/// 每条 UNWIND 语句写入的行数
+const LOAD_CHUNK: usize = 1000;
+
+const SCHEMA: &[&str] = &[
+    "CREATE NODE TABLE IF NOT EXISTS PE(refno INT64, owner INT64, name STRING, noun STRING, \
+     dbnum INT32, sesno INT32, deleted BOOLEAN, PRIMARY KEY(refno))",
+    "CREATE REL TABLE IF NOT EXISTS OWNS(FROM PE TO PE)",
+];
+
+/// Kuzu 查询提供者
+pub struct KuzuQueryProvider {
+    db: Arc<Database>,
+    name: String,
+}
+
+fn kuzu_err(e: impl std::fmt::Display) -> QueryError {
+    QueryError::ExecutionError(format!("Kuzu: {e}"))
+}
+
+fn unsupported<T>(what: &str) -> QueryResult<T> {
+    Err(QueryError::ExecutionError(format!("Kuzu 不保存{what}")))
+}
+
+/// Kuzu 中以 INT64 保存的参考号
+#[inline]
+fn key(refno: RefnoEnum) -> i64 {
+    refno.refno().0 as i64
+}
+
+fn keys(refnos: &[RefnoEnum]) -> String {
+    refnos
+        .iter()
+        .map(|r| key(*r).to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Cypher 字符串字面量
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// 类型名列表，只允许字母、数字和下划线
+fn nouns_list(nouns: &[&str]) -> QueryResult<String> {
+    nouns
+        .iter()
+        .map(|n| {
+            if !n.is_empty() && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                Ok(quote(n))
+            } else {
+                Err(QueryError::InvalidParameter(format!("无效的类型名: {n}")))
+            }
+        })
+        .collect::<QueryResult<Vec<_>>>()
+        .map(|v| v.join(","))
+}
+
+fn depth_range(max_depth: Option<usize>) -> String {
+    format!("1..{}", max_depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH))
+}
+
+fn value_i64(value: &Value) -> QueryResult<i64> {
+    match value {
+        Value::Int64(v) => Ok(*v),
+        Value::Int32(v) => Ok(*v as i64),
+        v => Err(QueryError::ParseError(format!("期望整数，实际为 {v}"))),
+    }
+}
+
+fn value_refno(value: &Value) -> QueryResult<RefnoEnum> {
+    Ok(RefU64(value_i64(value)? as u64).into())
+}
+
+fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null(_) => String::new(),
+        v => v.to_string(),
+    }
+}
+
+fn value_bool(value: &Value) -> bool {
+    matches!(value, Value::Bool(true))
+}
+
+const PE_RETURN: &str = "p.refno, p.owner, p.name, p.noun, p.dbnum, p.sesno, p.deleted";
+
+fn row_to_pe(row: &[Value]) -> QueryResult<PE> {
+    let [refno, owner, name, noun, dbnum, sesno, deleted] = row else {
+        return Err(QueryError::ParseError(format!(
+            "PE 行的列数为 {}",
+            row.len()
+        )));
+    };
+    Ok(PE {
+        refno: value_refno(refno)?,
+        owner: value_refno(owner)?,
+        name: value_string(name),
+        noun: value_string(noun),
+        dbnum: value_i64(dbnum)? as i32,
+        sesno: value_i64(sesno)? as i32,
+        deleted: value_bool(deleted),
+        ..Default::default()
+    })
+}
+
+impl KuzuQueryProvider {
+    /// 打开（不存在时创建）Kuzu 数据库并建表
+    pub fn open(path: impl AsRef<Path>) -> QueryResult<Self> {
+        let db = Database::new(path.as_ref(), SystemConfig::default())
+            .map_err(|e| QueryError::ConnectionError(format!("Kuzu: {e}")))?;
+        let conn = Connection::new(&db).map_err(kuzu_err)?;
+        for stmt in SCHEMA {
+            conn.query(stmt).map_err(kuzu_err)?;
+        }
+        Ok(Self {
+            db: Arc::new(db),
+            name: "Kuzu".to_string(),
+        })
+    }
+
+    /// 执行 Cypher，返回全部行
+    async fn rows(&self, cypher: String) -> QueryResult<Vec<Vec<Value>>> {
+        debug!("[{}] {}", self.name, cypher);
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = Connection::new(&db).map_err(kuzu_err)?;
+            let result = conn.query(&cypher).map_err(kuzu_err)?;
+            Ok(result.collect())
+        })
+        .await
+        .map_err(kuzu_err)?
+    }
+
+    /// 只取第一列的参考号
+    async fn refnos(&self, cypher: String) -> QueryResult<Vec<RefnoEnum>> {
+        self.rows(cypher)
+            .await?
+            .iter()
+            .filter_map(|row| row.first())
+            .map(value_refno)
+            .collect()
+    }
+
+    async fn pes(&self, cypher: String) -> QueryResult<Vec<PE>> {
+        self.rows(cypher)
+            .await?
+            .iter()
+            .map(|row| row_to_pe(row))
+            .collect()
+    }
+
+    /// 写入（或更新）PE 节点及其 OWNS 关系
+    ///
+    /// 同一批中的 owner 不必先于子节点出现，关系在节点全部写入后建立
+    pub async fn load_pes(&self, pes: &[PE]) -> QueryResult<()> {
+        for chunk in pes.chunks(LOAD_CHUNK) {
+            let rows = chunk
+                .iter()
+                .map(|pe| {
+                    format!(
+                        "{{refno: {}, owner: {}, name: {}, noun: {}, dbnum: {}, sesno: {}, deleted: {}}}",
+                        key(pe.refno),
+                        key(pe.owner),
+                        quote(&pe.name),
+                        quote(&pe.noun),
+                        pe.dbnum,
+                        pe.sesno,
+                        pe.deleted
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            self.rows(format!(
+                "UNWIND [{rows}] AS r MERGE (p:PE {{refno: r.refno}}) \
+                 SET p.owner = r.owner, p.name = r.name, p.noun = r.noun, \
+                 p.dbnum = r.dbnum, p.sesno = r.sesno, p.deleted = r.deleted"
+            ))
+            .await?;
+        }
+        for chunk in pes.chunks(LOAD_CHUNK) {
+            let pairs = chunk
+                .iter()
+                .filter(|pe| pe.owner.refno().0 != 0)
+                .map(|pe| format!("[{}, {}]", key(pe.owner), key(pe.refno)))
+                .collect::<Vec<_>>()
+                .join(",");
+            if pairs.is_empty() {
+                continue;
+            }
+            self.rows(format!(
+                "UNWIND [{pairs}] AS e MATCH (o:PE {{refno: e[1]}}), (c:PE {{refno: e[2]}}) \
+                 MERGE (o)-[:OWNS]->(c)"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// 从 SurrealDB 导入一个数据库编号下的全部 PE，返回导入数
+    pub async fn sync_dbnum(&self, dbnum: i32) -> QueryResult<usize> {
+        let sql = format!("select * from pe where dbnum = {dbnum} and !deleted");
+        let pes: Vec<PE> = SUL_DB.query_take(&sql, 0).await?;
+        self.load_pes(&pes).await?;
+        debug!(
+            "[{}] 导入 dbnum={} 的 {} 个 PE",
+            self.name,
+            dbnum,
+            pes.len()
+        );
+        Ok(pes.len())
+    }
+}
+
+impl QueryRouter {
+    /// 打开 `path` 处的 Kuzu 数据库并注册为 [`QueryEngine::Kuzu`]
+    pub fn register_kuzu(&self, path: impl AsRef<Path>) -> QueryResult<Arc<KuzuQueryProvider>> {
+        let provider = Arc::new(KuzuQueryProvider::open(path)?);
+        self.register_provider(QueryEngine::Kuzu, provider.clone())?;
+        Ok(provider)
+    }
+}
+
+// ============================================================================
+// HierarchyQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl HierarchyQuery for KuzuQueryProvider {
+    async fn get_children(&self, refno: RefnoEnum) -> QueryResult<Vec<RefnoEnum>> {
+        self.refnos(format!(
+            "MATCH (:PE {{refno: {}}})-[:OWNS]->(c:PE) WHERE NOT c.deleted RETURN c.refno",
+            key(refno)
+        ))
+        .await
+    }
+
+    async fn get_descendants(
+        &self,
+        refno: RefnoEnum,
+        max_depth: Option<usize>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        self.get_descendants_filtered(refno, &[], max_depth).await
+    }
+
+    async fn get_ancestors(&self, refno: RefnoEnum) -> QueryResult<Vec<RefnoEnum>> {
+        self.get_ancestors_of_type(refno, &[]).await
+    }
+
+    async fn get_ancestors_of_type(
+        &self,
+        refno: RefnoEnum,
+        nouns: &[&str],
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        let filter = if nouns.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE a.noun IN [{}]", nouns_list(nouns)?)
+        };
+        // 从直接父节点到根节点
+        self.refnos(format!(
+            "MATCH (:PE {{refno: {}}})<-[e:OWNS*{}]-(a:PE){filter} RETURN a.refno ORDER BY length(e)",
+            key(refno),
+            depth_range(None)
+        ))
+        .await
+    }
+
+    async fn get_descendants_filtered(
+        &self,
+        refno: RefnoEnum,
+        nouns: &[&str],
+        max_depth: Option<usize>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        self.query_descendants(&[refno], nouns, max_depth).await
+    }
+
+    async fn get_children_pes(&self, refno: RefnoEnum) -> QueryResult<Vec<PE>> {
+        self.pes(format!(
+            "MATCH (:PE {{refno: {}}})-[:OWNS]->(p:PE) WHERE NOT p.deleted RETURN {PE_RETURN}",
+            key(refno)
+        ))
+        .await
+    }
+}
+
+impl KuzuQueryProvider {
+    async fn query_descendants(
+        &self,
+        refnos: &[RefnoEnum],
+        nouns: &[&str],
+        max_depth: Option<usize>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        if refnos.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut filter = "NOT d.deleted".to_string();
+        if !nouns.is_empty() {
+            filter.push_str(&format!(" AND d.noun IN [{}]", nouns_list(nouns)?));
+        }
+        self.refnos(format!(
+            "MATCH (r:PE)-[:OWNS*{}]->(d:PE) WHERE r.refno IN [{}] AND {filter} RETURN DISTINCT d.refno",
+            depth_range(max_depth),
+            keys(refnos)
+        ))
+        .await
+    }
+}
+
+// ============================================================================
+// TypeQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl TypeQuery for KuzuQueryProvider {
+    async fn query_by_type(
+        &self,
+        nouns: &[&str],
+        dbnum: i32,
+        has_children: Option<bool>,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        let children = match has_children {
+            Some(true) => " AND EXISTS { MATCH (p)-[:OWNS]->(:PE) }",
+            Some(false) => " AND NOT EXISTS { MATCH (p)-[:OWNS]->(:PE) }",
+            None => "",
+        };
+        self.refnos(format!(
+            "MATCH (p:PE) WHERE p.dbnum = {dbnum} AND p.noun IN [{}] AND NOT p.deleted{children} RETURN p.refno",
+            nouns_list(nouns)?
+        ))
+        .await
+    }
+
+    async fn query_by_type_name_contains(
+        &self,
+        nouns: &[&str],
+        dbnum: i32,
+        keyword: &str,
+        case_sensitive: bool,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        let cond = if case_sensitive {
+            format!("contains(p.name, {})", quote(keyword))
+        } else {
+            format!(
+                "contains(lower(p.name), {})",
+                quote(&keyword.to_lowercase())
+            )
+        };
+        self.refnos(format!(
+            "MATCH (p:PE) WHERE p.dbnum = {dbnum} AND p.noun IN [{}] AND NOT p.deleted AND {cond} RETURN p.refno",
+            nouns_list(nouns)?
+        ))
+        .await
+    }
+
+    async fn query_by_type_multi_db(
+        &self,
+        nouns: &[&str],
+        dbnums: &[i32],
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        if dbnums.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dbnums = dbnums
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.refnos(format!(
+            "MATCH (p:PE) WHERE p.dbnum IN [{dbnums}] AND p.noun IN [{}] AND NOT p.deleted RETURN p.refno",
+            nouns_list(nouns)?
+        ))
+        .await
+    }
+
+    async fn get_world(&self, dbnum: i32) -> QueryResult<Option<RefnoEnum>> {
+        Ok(self
+            .query_by_type(&["WORL"], dbnum, None)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn get_sites(&self, dbnum: i32) -> QueryResult<Vec<RefnoEnum>> {
+        self.query_by_type(&["SITE"], dbnum, None).await
+    }
+
+    async fn count_by_type(&self, noun: &str, dbnum: i32) -> QueryResult<usize> {
+        let rows = self
+            .rows(format!(
+                "MATCH (p:PE) WHERE p.dbnum = {dbnum} AND p.noun IN [{}] AND NOT p.deleted RETURN count(*)",
+                nouns_list(&[noun])?
+            ))
+            .await?;
+        match rows.first().and_then(|r| r.first()) {
+            Some(v) => Ok(value_i64(v)? as usize),
+            None => Ok(0),
+        }
+    }
+}
+
+// ============================================================================
+// BatchQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl BatchQuery for KuzuQueryProvider {
+    async fn get_pes_batch(&self, refnos: &[RefnoEnum]) -> QueryResult<Vec<PE>> {
+        if refnos.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pes = self
+            .pes(format!(
+                "MATCH (p:PE) WHERE p.refno IN [{}] RETURN {PE_RETURN}",
+                keys(refnos)
+            ))
+            .await?;
+        // 保持输入顺序
+        Ok(refnos
+            .iter()
+            .filter_map(|r| pes.iter().find(|pe| pe.refno.refno() == r.refno()).cloned())
+            .collect())
+    }
+
+    async fn get_attmaps_batch(&self, _refnos: &[RefnoEnum]) -> QueryResult<Vec<NamedAttMap>> {
+        unsupported("属性")
+    }
+
+    async fn get_full_names_batch(
+        &self,
+        _refnos: &[RefnoEnum],
+    ) -> QueryResult<Vec<(RefnoEnum, String)>> {
+        unsupported("全名")
+    }
+}
+
+// ============================================================================
+// GraphQuery 实现
+// ============================================================================
+
+#[async_trait]
+impl GraphQuery for KuzuQueryProvider {
+    async fn query_multi_descendants(
+        &self,
+        refnos: &[RefnoEnum],
+        nouns: &[&str],
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        self.query_descendants(refnos, nouns, None).await
+    }
+
+    async fn find_shortest_path(
+        &self,
+        from: RefnoEnum,
+        to: RefnoEnum,
+    ) -> QueryResult<Vec<RefnoEnum>> {
+        if from.refno() == to.refno() {
+            return Ok(vec![from]);
+        }
+        let rows = self
+            .rows(format!(
+                "MATCH (a:PE {{refno: {}}})-[e:OWNS* SHORTEST {}]-(b:PE {{refno: {}}}) \
+                 RETURN properties(nodes(e), 'refno')",
+                key(from),
+                depth_range(Some(MAX_DEPTH * 2)),
+                key(to)
+            ))
+            .await?;
+        let Some(Value::List(_, middle)) = rows.first().and_then(|r| r.first()) else {
+            return Ok(Vec::new());
+        };
+        // nodes(e) 只包含中间节点
+        let mut path = vec![from];
+        for v in middle {
+            path.push(value_refno(v)?);
+        }
+        path.push(to);
+        Ok(path)
+    }
+
+    async fn get_node_depth(&self, refno: RefnoEnum) -> QueryResult<usize> {
+        Ok(self.get_ancestors(refno).await?.len())
+    }
+}
+
+// ============================================================================
+// QueryProvider 实现
+// ============================================================================
+
+#[async_trait]
+impl QueryProvider for KuzuQueryProvider {
+    async fn get_pe(&self, refno: RefnoEnum) -> QueryResult<Option<PE>> {
+        Ok(self.get_pes_batch(&[refno]).await?.into_iter().next())
+    }
+
+    async fn get_attmap(&self, _refno: RefnoEnum) -> QueryResult<Option<NamedAttMap>> {
+        unsupported("属性")
+    }
+
+    async fn exists(&self, refno: RefnoEnum) -> QueryResult<bool> {
+        Ok(self.get_pe(refno).await?.is_some())
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn health_check(&self) -> QueryResult<bool> {
+        Ok(self.rows("RETURN 1".to_string()).await.is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pe(n: u32, owner: u32, noun: &str) -> PE {
+        PE {
+            refno: RefU64::from_two_nums(17496, n).into(),
+            owner: if owner == 0 {
+                RefnoEnum::default()
+            } else {
+                RefU64::from_two_nums(17496, owner).into()
+            },
+            name: format!("/N{n}"),
+            noun: noun.to_string(),
+            dbnum: 1112,
+            ..Default::default()
+        }
+    }
+
+    fn r(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    #[tokio::test]
+    async fn test_hierarchy_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = KuzuQueryProvider::open(dir.path().join("graph")).unwrap();
+        provider
+            .load_pes(&[
+                pe(4, 3, "BRAN"),
+                pe(1, 0, "SITE"),
+                pe(2, 1, "ZONE"),
+                pe(3, 2, "PIPE"),
+                pe(5, 2, "EQUI"),
+            ])
+            .await
+            .unwrap();
+
+        let mut desc = provider.get_descendants(r(1), None).await.unwrap();
+        desc.sort_by_key(|r| r.refno().0);
+        assert_eq!(desc, vec![r(2), r(3), r(4), r(5)]);
+        assert_eq!(
+            provider
+                .get_descendants_filtered(r(1), &["BRAN"], None)
+                .await
+                .unwrap(),
+            vec![r(4)]
+        );
+        assert_eq!(
+            provider.get_ancestors(r(4)).await.unwrap(),
+            vec![r(3), r(2), r(1)]
+        );
+        assert_eq!(provider.get_node_depth(r(4)).await.unwrap(), 3);
+        assert_eq!(
+            provider.find_shortest_path(r(4), r(5)).await.unwrap(),
+            vec![r(4), r(3), r(2), r(5)]
+        );
+        assert_eq!(provider.count_by_type("PIPE", 1112).await.unwrap(), 1);
+        assert!(provider.get_attmap(r(1)).await.is_err());
+    }
+}
@@ -8,6 +8,7 @@
 //! - **类型安全**: 使用 Rust 类型系统确保查询正确性
 //! - **异步优先**: 全面支持 async/await
 //! - **可扩展**: 易于添加新的数据库实现
+//! - **浏览器可用**: [`HttpQueryProvider`] 走 SurrealDB 的 HTTP 接口，不依赖原生连接
 //!
 //! # 使用示例
 //!
@@ -26,12 +27,22 @@
 //! }
 //! ```
 
+pub mod cost_model;
 pub mod error;
+pub mod http_provider;
+#[cfg(feature = "kuzu")]
+pub mod kuzu_provider;
 pub mod router;
 pub mod surreal_provider;
 pub mod traits;
 
+pub use cost_model::{CostModel, QueryClass};
 pub use error::{QueryError, QueryResult};
+pub use http_provider::{HttpProviderConfig, HttpQueryProvider, SqlTransport};
+#[cfg(feature = "http-query")]
+pub use http_provider::ReqwestTransport;
+#[cfg(feature = "kuzu")]
+pub use kuzu_provider::KuzuQueryProvider;
 pub use router::{QueryEngine, QueryRouter, QueryStrategy};
 pub use surreal_provider::SurrealQueryProvider;
 pub use traits::{BatchQuery, GraphQuery, HierarchyQuery, QueryProvider, TypeQuery};
@@ -3,20 +3,27 @@
 //! 提供智能的查询路由和自动选择功能
 
 use super::SurrealQueryProvider;
+use super::cost_model::{CostModel, QueryClass};
 use super::error::{QueryError, QueryResult};
 use super::traits::*;
 use crate::RefnoEnum;
+use crate::sync::LatencySummary;
 use crate::types::{NamedAttrMap as NamedAttMap, SPdmsElement as PE};
 use async_trait::async_trait;
 use log::{debug, info, warn};
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 /// 查询引擎类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QueryEngine {
     /// 使用 SurrealDB
     SurrealDB,
-    /// 自动选择（当前仅有 SurrealDB，实现等同于 SurrealDB）
+    /// 使用 Kuzu 图数据库（`kuzu` 特性，配置 `kuzu_path` 或调用 `QueryRouter::register_kuzu` 注册）
+    Kuzu,
+    /// 使用内存层级索引（需通过 `QueryRouter::register_provider` 注册）
+    TreeIndex,
+    /// 按代价模型自动选择
     Auto,
 }
 
@@ -86,10 +93,17 @@ impl QueryStrategy {
 
 /// 查询路由器
 ///
-/// 根据策略自动选择合适的查询提供者
+/// 根据策略自动选择合适的查询提供者。`Auto` 模式下按查询类别和
+/// 各引擎的历史延迟（见 [`CostModel`]）选择，可通过 `set_override` 手动指定
 pub struct QueryRouter {
     /// SurrealDB 查询提供者
     surreal_provider: Arc<SurrealQueryProvider>,
+    /// 额外注册的查询提供者（Kuzu / TreeIndex）
+    providers: RwLock<HashMap<QueryEngine, Arc<dyn QueryProvider>>>,
+    /// 按查询类别的手动路由
+    overrides: RwLock<HashMap<QueryClass, QueryEngine>>,
+    /// 代价模型
+    cost_model: Arc<CostModel>,
     /// 查询策略
     strategy: Arc<RwLock<QueryStrategy>>,
 }
@@ -99,10 +113,24 @@ impl QueryRouter {
     pub fn new(strategy: QueryStrategy) -> QueryResult<Self> {
         let surreal_provider = Arc::new(SurrealQueryProvider::new()?);
 
-        Ok(Self {
+        let router = Self {
             surreal_provider,
+            providers: RwLock::new(HashMap::new()),
+            overrides: RwLock::new(HashMap::new()),
+            cost_model: Arc::new(CostModel::default()),
             strategy: Arc::new(RwLock::new(strategy)),
-        })
+        };
+
+        // 配置了 kuzu_path 时自动注册 Kuzu，打开失败只影响路由，不影响 SurrealDB 查询
+        #[cfg(feature = "kuzu")]
+        if let Ok(option) = crate::config::try_get_db_option()
+            && let Some(path) = &option.kuzu_path
+            && let Err(e) = router.register_kuzu(path)
+        {
+            warn!("打开 Kuzu 数据库 {} 失败: {}", path, e);
+        }
+
+        Ok(router)
     }
 
     /// 创建使用自动选择策略的路由器
@@ -127,24 +155,133 @@ impl QueryRouter {
         self.strategy.read().unwrap().clone()
     }
 
+    /// 注册额外的查询提供者
+    ///
+    /// SurrealDB 提供者由路由器内置，`Auto` 不是具体引擎，二者均不可注册
+    pub fn register_provider(
+        &self,
+        engine: QueryEngine,
+        provider: Arc<dyn QueryProvider>,
+    ) -> QueryResult<()> {
+        if matches!(engine, QueryEngine::SurrealDB | QueryEngine::Auto) {
+            return Err(QueryError::InvalidParameter(format!(
+                "不能注册 {:?} 查询提供者",
+                engine
+            )));
+        }
+        info!(
+            "注册查询提供者: {:?} -> {}",
+            engine,
+            provider.provider_name()
+        );
+        if let Ok(mut providers) = self.providers.write() {
+            providers.insert(engine, provider);
+        }
+        Ok(())
+    }
+
+    /// 移除已注册的查询提供者
+    pub fn unregister_provider(&self, engine: QueryEngine) -> Option<Arc<dyn QueryProvider>> {
+        self.providers.write().ok()?.remove(&engine)
+    }
+
+    /// 当前可用的引擎（始终包含 SurrealDB）
+    pub fn registered_engines(&self) -> Vec<QueryEngine> {
+        let mut engines = vec![QueryEngine::SurrealDB];
+        if let Ok(providers) = self.providers.read() {
+            engines.extend(providers.keys().copied());
+        }
+        engines
+    }
+
+    /// 手动指定某类查询使用的引擎，优先于代价模型
+    pub fn set_override(&self, class: QueryClass, engine: QueryEngine) {
+        if let Ok(mut overrides) = self.overrides.write() {
+            if engine == QueryEngine::Auto {
+                overrides.remove(&class);
+            } else {
+                overrides.insert(class, engine);
+            }
+        }
+    }
+
+    /// 取消某类查询的手动路由
+    pub fn clear_override(&self, class: QueryClass) {
+        if let Ok(mut overrides) = self.overrides.write() {
+            overrides.remove(&class);
+        }
+    }
+
+    /// 取消所有手动路由
+    pub fn clear_overrides(&self) {
+        if let Ok(mut overrides) = self.overrides.write() {
+            overrides.clear();
+        }
+    }
+
+    /// 代价模型
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    /// 各 (引擎, 类别) 的延迟摘要
+    pub fn latency_report(&self) -> Vec<(QueryEngine, QueryClass, LatencySummary)> {
+        self.cost_model.snapshot()
+    }
+
+    /// 按引擎查找提供者
+    fn provider_for(&self, engine: QueryEngine) -> Option<Arc<dyn QueryProvider>> {
+        match engine {
+            QueryEngine::SurrealDB => Some(self.surreal_provider.clone()),
+            QueryEngine::Auto => None,
+            _ => self.providers.read().ok()?.get(&engine).cloned(),
+        }
+    }
+
     /// 选择查询提供者
-    fn select_provider(&self) -> (QueryEngine, Arc<dyn QueryProvider>) {
+    fn select_provider(&self, class: QueryClass) -> (QueryEngine, Arc<dyn QueryProvider>) {
         let strategy = self.get_strategy();
 
-        match strategy.engine {
-            QueryEngine::SurrealDB => {
-                // debug!("选择 SurrealDB 查询提供者");
-                (QueryEngine::SurrealDB, self.surreal_provider.clone())
-            }
-            QueryEngine::Auto => {
-                // debug!("自动选择模式，使用 SurrealDB 查询提供者");
-                (QueryEngine::SurrealDB, self.surreal_provider.clone())
+        let requested = match strategy.engine {
+            QueryEngine::Auto => self
+                .overrides
+                .read()
+                .ok()
+                .and_then(|o| o.get(&class).copied()),
+            engine => Some(engine),
+        };
+        if let Some(engine) = requested {
+            if let Some(provider) = self.provider_for(engine) {
+                return (engine, provider);
             }
+            warn!("{:?} 查询提供者未注册，使用 SurrealDB", engine);
+            return (QueryEngine::SurrealDB, self.surreal_provider.clone());
+        }
+
+        let candidates: Vec<QueryEngine> = class
+            .preferred_engines()
+            .iter()
+            .copied()
+            .filter(|e| self.provider_for(*e).is_some())
+            .collect();
+        let engine = self
+            .cost_model
+            .choose(class, &candidates)
+            .unwrap_or(QueryEngine::SurrealDB);
+        debug!("自动选择 {:?} 执行 {:?} 查询", engine, class);
+        match self.provider_for(engine) {
+            Some(provider) => (engine, provider),
+            None => (QueryEngine::SurrealDB, self.surreal_provider.clone()),
         }
     }
 
     /// 执行查询（带回退机制）
-    async fn execute_with_fallback<F, T>(&self, query_name: &str, f: F) -> QueryResult<T>
+    async fn execute_with_fallback<F, T>(
+        &self,
+        query_name: &str,
+        class: QueryClass,
+        f: F,
+    ) -> QueryResult<T>
     where
         F: Fn(
                 Arc<dyn QueryProvider>,
@@ -154,24 +291,24 @@ impl QueryRouter {
         T: Send,
     {
         let strategy = self.get_strategy();
-        let (engine, provider) = self.select_provider();
+        let (engine, provider) = self.select_provider(class);
 
         let start_time = std::time::Instant::now();
 
         // 执行查询
         let result = f(provider.clone()).await;
+        let elapsed = start_time.elapsed();
+        self.cost_model
+            .record(engine, class, elapsed, result.is_ok());
 
         // 性能日志
-        if strategy.enable_performance_log {
-            let elapsed = start_time.elapsed();
-            if elapsed.as_millis() > 100 {
-                info!(
-                    "[{}] {} 查询耗时: {:?}",
-                    provider.provider_name(),
-                    query_name,
-                    elapsed
-                );
-            }
+        if strategy.enable_performance_log && elapsed.as_millis() > 100 {
+            info!(
+                "[{}] {} 查询耗时: {:?}",
+                provider.provider_name(),
+                query_name,
+                elapsed
+            );
         }
 
         // 处理结果和回退
@@ -189,7 +326,15 @@ impl QueryRouter {
                 if strategy.enable_fallback && engine != QueryEngine::SurrealDB {
                     info!("回退到 SurrealDB 执行查询: {}", query_name);
                     let fallback_provider: Arc<dyn QueryProvider> = self.surreal_provider.clone();
-                    f(fallback_provider).await
+                    let start_time = std::time::Instant::now();
+                    let result = f(fallback_provider).await;
+                    self.cost_model.record(
+                        QueryEngine::SurrealDB,
+                        class,
+                        start_time.elapsed(),
+                        result.is_ok(),
+                    );
+                    result
                 } else {
                     Err(e)
                 }
@@ -205,7 +350,7 @@ impl QueryRouter {
 #[async_trait]
 impl HierarchyQuery for QueryRouter {
     async fn get_children(&self, refno: RefnoEnum) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("get_children", |provider| {
+        self.execute_with_fallback("get_children", QueryClass::General, |provider| {
             Box::pin(async move { provider.get_children(refno).await })
         })
         .await
@@ -216,14 +361,14 @@ impl HierarchyQuery for QueryRouter {
         refno: RefnoEnum,
         max_depth: Option<usize>,
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("get_descendants", |provider| {
+        self.execute_with_fallback("get_descendants", QueryClass::DeepHierarchy, |provider| {
             Box::pin(async move { provider.get_descendants(refno, max_depth).await })
         })
         .await
     }
 
     async fn get_ancestors(&self, refno: RefnoEnum) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("get_ancestors", |provider| {
+        self.execute_with_fallback("get_ancestors", QueryClass::DeepHierarchy, |provider| {
             Box::pin(async move { provider.get_ancestors(refno).await })
         })
         .await
@@ -234,13 +379,17 @@ impl HierarchyQuery for QueryRouter {
         refno: RefnoEnum,
         nouns: &[&str],
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("get_ancestors_of_type", |provider| {
-            let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
-            Box::pin(async move {
-                let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
-                provider.get_ancestors_of_type(refno, &noun_refs).await
-            })
-        })
+        self.execute_with_fallback(
+            "get_ancestors_of_type",
+            QueryClass::DeepHierarchy,
+            |provider| {
+                let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
+                Box::pin(async move {
+                    let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
+                    provider.get_ancestors_of_type(refno, &noun_refs).await
+                })
+            },
+        )
         .await
     }
 
@@ -250,20 +399,24 @@ impl HierarchyQuery for QueryRouter {
         nouns: &[&str],
         max_depth: Option<usize>,
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("get_descendants_filtered", |provider| {
-            let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
-            Box::pin(async move {
-                let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
-                provider
-                    .get_descendants_filtered(refno, &noun_refs, max_depth)
-                    .await
-            })
-        })
+        self.execute_with_fallback(
+            "get_descendants_filtered",
+            QueryClass::DeepHierarchy,
+            |provider| {
+                let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
+                Box::pin(async move {
+                    let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
+                    provider
+                        .get_descendants_filtered(refno, &noun_refs, max_depth)
+                        .await
+                })
+            },
+        )
         .await
     }
 
     async fn get_children_pes(&self, refno: RefnoEnum) -> QueryResult<Vec<PE>> {
-        self.execute_with_fallback("get_children_pes", |provider| {
+        self.execute_with_fallback("get_children_pes", QueryClass::Attribute, |provider| {
             Box::pin(async move { provider.get_children_pes(refno).await })
         })
         .await
@@ -282,7 +435,7 @@ impl TypeQuery for QueryRouter {
         dbnum: i32,
         has_children: Option<bool>,
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("query_by_type", |provider| {
+        self.execute_with_fallback("query_by_type", QueryClass::General, |provider| {
             let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
             Box::pin(async move {
                 let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
@@ -301,16 +454,20 @@ impl TypeQuery for QueryRouter {
         keyword: &str,
         case_sensitive: bool,
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("query_by_type_name_contains", |provider| {
-            let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
-            let keyword = keyword.to_string();
-            Box::pin(async move {
-                let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
-                provider
-                    .query_by_type_name_contains(&noun_refs, dbnum, &keyword, case_sensitive)
-                    .await
-            })
-        })
+        self.execute_with_fallback(
+            "query_by_type_name_contains",
+            QueryClass::General,
+            |provider| {
+                let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
+                let keyword = keyword.to_string();
+                Box::pin(async move {
+                    let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
+                    provider
+                        .query_by_type_name_contains(&noun_refs, dbnum, &keyword, case_sensitive)
+                        .await
+                })
+            },
+        )
         .await
     }
 
@@ -319,7 +476,7 @@ impl TypeQuery for QueryRouter {
         nouns: &[&str],
         dbnums: &[i32],
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("query_by_type_multi_db", |provider| {
+        self.execute_with_fallback("query_by_type_multi_db", QueryClass::General, |provider| {
             let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
             let dbnums = dbnums.to_vec();
             Box::pin(async move {
@@ -331,21 +488,21 @@ impl TypeQuery for QueryRouter {
     }
 
     async fn get_world(&self, dbnum: i32) -> QueryResult<Option<RefnoEnum>> {
-        self.execute_with_fallback("get_world", |provider| {
+        self.execute_with_fallback("get_world", QueryClass::General, |provider| {
             Box::pin(async move { provider.get_world(dbnum).await })
         })
         .await
     }
 
     async fn get_sites(&self, dbnum: i32) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("get_sites", |provider| {
+        self.execute_with_fallback("get_sites", QueryClass::General, |provider| {
             Box::pin(async move { provider.get_sites(dbnum).await })
         })
         .await
     }
 
     async fn count_by_type(&self, noun: &str, dbnum: i32) -> QueryResult<usize> {
-        self.execute_with_fallback("count_by_type", |provider| {
+        self.execute_with_fallback("count_by_type", QueryClass::General, |provider| {
             let noun = noun.to_string();
             Box::pin(async move { provider.count_by_type(&noun, dbnum).await })
         })
@@ -360,7 +517,7 @@ impl TypeQuery for QueryRouter {
 #[async_trait]
 impl BatchQuery for QueryRouter {
     async fn get_pes_batch(&self, refnos: &[RefnoEnum]) -> QueryResult<Vec<PE>> {
-        self.execute_with_fallback("get_pes_batch", |provider| {
+        self.execute_with_fallback("get_pes_batch", QueryClass::Attribute, |provider| {
             let refnos = refnos.to_vec();
             Box::pin(async move { provider.get_pes_batch(&refnos).await })
         })
@@ -368,7 +525,7 @@ impl BatchQuery for QueryRouter {
     }
 
     async fn get_attmaps_batch(&self, refnos: &[RefnoEnum]) -> QueryResult<Vec<NamedAttMap>> {
-        self.execute_with_fallback("get_attmaps_batch", |provider| {
+        self.execute_with_fallback("get_attmaps_batch", QueryClass::Attribute, |provider| {
             let refnos = refnos.to_vec();
             Box::pin(async move { provider.get_attmaps_batch(&refnos).await })
         })
@@ -379,7 +536,7 @@ impl BatchQuery for QueryRouter {
         &self,
         refnos: &[RefnoEnum],
     ) -> QueryResult<Vec<(RefnoEnum, String)>> {
-        self.execute_with_fallback("get_full_names_batch", |provider| {
+        self.execute_with_fallback("get_full_names_batch", QueryClass::Attribute, |provider| {
             let refnos = refnos.to_vec();
             Box::pin(async move { provider.get_full_names_batch(&refnos).await })
         })
@@ -398,14 +555,18 @@ impl GraphQuery for QueryRouter {
         refnos: &[RefnoEnum],
         nouns: &[&str],
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("query_multi_descendants", |provider| {
-            let refnos = refnos.to_vec();
-            let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
-            Box::pin(async move {
-                let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
-                provider.query_multi_descendants(&refnos, &noun_refs).await
-            })
-        })
+        self.execute_with_fallback(
+            "query_multi_descendants",
+            QueryClass::DeepHierarchy,
+            |provider| {
+                let refnos = refnos.to_vec();
+                let nouns: Vec<String> = nouns.iter().map(|s| s.to_string()).collect();
+                Box::pin(async move {
+                    let noun_refs: Vec<&str> = nouns.iter().map(|s| s.as_str()).collect();
+                    provider.query_multi_descendants(&refnos, &noun_refs).await
+                })
+            },
+        )
         .await
     }
 
@@ -414,14 +575,16 @@ impl GraphQuery for QueryRouter {
         from: RefnoEnum,
         to: RefnoEnum,
     ) -> QueryResult<Vec<RefnoEnum>> {
-        self.execute_with_fallback("find_shortest_path", |provider| {
-            Box::pin(async move { provider.find_shortest_path(from, to).await })
-        })
+        self.execute_with_fallback(
+            "find_shortest_path",
+            QueryClass::DeepHierarchy,
+            |provider| Box::pin(async move { provider.find_shortest_path(from, to).await }),
+        )
         .await
     }
 
     async fn get_node_depth(&self, refno: RefnoEnum) -> QueryResult<usize> {
-        self.execute_with_fallback("get_node_depth", |provider| {
+        self.execute_with_fallback("get_node_depth", QueryClass::DeepHierarchy, |provider| {
             Box::pin(async move { provider.get_node_depth(refno).await })
         })
         .await
@@ -435,21 +598,21 @@ impl GraphQuery for QueryRouter {
 #[async_trait]
 impl QueryProvider for QueryRouter {
     async fn get_pe(&self, refno: RefnoEnum) -> QueryResult<Option<PE>> {
-        self.execute_with_fallback("get_pe", |provider| {
+        self.execute_with_fallback("get_pe", QueryClass::Attribute, |provider| {
             Box::pin(async move { provider.get_pe(refno).await })
         })
         .await
     }
 
     async fn get_attmap(&self, refno: RefnoEnum) -> QueryResult<Option<NamedAttMap>> {
-        self.execute_with_fallback("get_attmap", |provider| {
+        self.execute_with_fallback("get_attmap", QueryClass::Attribute, |provider| {
             Box::pin(async move { provider.get_attmap(refno).await })
         })
         .await
     }
 
     async fn exists(&self, refno: RefnoEnum) -> QueryResult<bool> {
-        self.execute_with_fallback("exists", |provider| {
+        self.execute_with_fallback("exists", QueryClass::Attribute, |provider| {
             Box::pin(async move { provider.exists(refno).await })
         })
         .await
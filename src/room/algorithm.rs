@@ -149,7 +149,7 @@ pub async fn query_all_room_infos(room_keywords: &[String]) -> anyhow::Result<Ve
 /// # 典型用途
 /// - 结构/建筑专业房间总览、结构化数据导出
 pub async fn query_room_codes_of_arch() -> anyhow::Result<HashMap<String, BTreeSet<RoomInfo>>> {
-    let room_keywords = crate::get_db_option().get_room_key_word();
+    let room_keywords = crate::get_db_option()?.get_room_key_word();
     let results = query_all_room_infos(&room_keywords).await?;
     let mut map = HashMap::new();
     for r in results {
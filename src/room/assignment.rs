@@ -0,0 +1,390 @@
+//! 元素到房间的归属
+//!
+//! 房间由一组房间面板（`room_panel_relate`，面板 → 房间号）的封闭体表示。
+//! 判断元素属于哪个房间时，在元素世界包围盒内均匀取样（每轴 n 个格心），
+//! 逐点判断所在房间，按落入各房间的样点比例给出体积占比；跨多个房间的元素
+//! 取占比最大的房间作为主房间。
+//!
+//! 结果缓存在 [`ROOM_ASSIGN_TABLE`] 表中，[`get_room_code`] 首次调用时一次性
+//! 读入整张表，之后都在内存中查，不再逐个参考号查询数据库。
+
+use crate::accel_tree::acceleration_tree::{AccelerationTree, RStarBoundingBox};
+use crate::rs_surreal::transaction::Transaction;
+use crate::shape::pdms_shape::PlantMesh;
+use crate::mesh_store::{load_mesh_cached, mesh_store};
+use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt, query_insts};
+use dashmap::DashMap;
+use glam::Vec3;
+use parry3d::bounding_volume::Aabb;
+use parry3d::math::Isometry;
+use parry3d::query::PointQuery;
+use parry3d::shape::{TriMesh, TriMeshFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 房间归属缓存表
+pub const ROOM_ASSIGN_TABLE: &str = "room_assign";
+
+/// 每轴默认取样数
+pub const DEFAULT_SAMPLES_PER_AXIS: usize = 4;
+
+/// 每个事务写入的归属记录数
+const SAVE_CHUNK: usize = 500;
+
+/// 元素在某个房间内的体积占比
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct RoomShare {
+    pub room_code: String,
+    pub fraction: f32,
+}
+
+/// 元素的房间归属
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct RoomAssignment {
+    pub refno: RefnoEnum,
+    /// 主房间（占比最大），不在任何房间内时为 None
+    pub room_code: Option<String>,
+    /// 按占比从大到小排列
+    pub shares: Vec<RoomShare>,
+    /// 不在任何房间内的占比
+    pub outside: f32,
+}
+
+/// 一个房间面板的封闭体
+pub struct RoomSolid {
+    pub panel: RefnoEnum,
+    pub room_code: String,
+    pub aabb: Aabb,
+    /// 世界坐标下带朝向的三角网格
+    pub meshes: Vec<TriMesh>,
+}
+
+/// 点所在房间的判断器，面板包围盒放在 R 树中做初筛
+#[derive(Default)]
+pub struct RoomClassifier {
+    tree: AccelerationTree,
+    solids: HashMap<RefU64, RoomSolid>,
+}
+
+impl RoomClassifier {
+    pub fn new(solids: Vec<RoomSolid>) -> Self {
+        let bboxes = solids
+            .iter()
+            .map(|s| RStarBoundingBox::from_aabb(s.aabb, s.panel))
+            .collect();
+        Self {
+            tree: AccelerationTree::load(bboxes),
+            solids: solids.into_iter().map(|s| (s.panel.refno(), s)).collect(),
+        }
+    }
+
+    /// 房间面板数
+    pub fn len(&self) -> usize {
+        self.solids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.solids.is_empty()
+    }
+
+    /// 点所在的房间号，多个面板同时包含时取参考号最小的，结果稳定
+    pub fn room_at(&self, point: Vec3) -> Option<&str> {
+        let probe = Aabb::new(point.into(), point.into());
+        let pt = parry3d::math::Point::new(point.x, point.y, point.z);
+        self.tree
+            .locate_intersecting_bounds(&probe)
+            .filter_map(|bb| self.solids.get(&bb.refno))
+            .filter(|s| {
+                s.meshes
+                    .iter()
+                    .any(|m| m.contains_point(&Isometry::identity(), &pt))
+            })
+            .min_by_key(|s| s.panel.refno())
+            .map(|s| s.room_code.as_str())
+    }
+
+    /// 按包围盒取样计算元素的房间归属
+    pub fn classify(
+        &self,
+        refno: RefnoEnum,
+        aabb: &Aabb,
+        samples_per_axis: usize,
+    ) -> RoomAssignment {
+        classify_samples(refno, &sample_points(aabb, samples_per_axis), |p| {
+            self.room_at(p)
+        })
+    }
+}
+
+/// 包围盒内均匀分布的样点（格心），厚度接近零的轴只取中心一层
+pub fn sample_points(aabb: &Aabb, samples_per_axis: usize) -> Vec<Vec3> {
+    let n = samples_per_axis.max(1);
+    let mins = Vec3::from(aabb.mins.coords);
+    let size = Vec3::from(aabb.extents());
+    let counts = size.to_array().map(|s| if s > 1e-3 { n } else { 1 });
+    let mut points = Vec::with_capacity(counts.iter().product());
+    for i in 0..counts[0] {
+        for j in 0..counts[1] {
+            for k in 0..counts[2] {
+                let t = Vec3::new(
+                    (i as f32 + 0.5) / counts[0] as f32,
+                    (j as f32 + 0.5) / counts[1] as f32,
+                    (k as f32 + 0.5) / counts[2] as f32,
+                );
+                points.push(mins + size * t);
+            }
+        }
+    }
+    points
+}
+
+/// 按样点所在房间统计占比
+pub fn classify_samples<'a>(
+    refno: RefnoEnum,
+    points: &[Vec3],
+    mut room_at: impl FnMut(Vec3) -> Option<&'a str>,
+) -> RoomAssignment {
+    if points.is_empty() {
+        return RoomAssignment {
+            refno,
+            outside: 1.0,
+            ..Default::default()
+        };
+    }
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut outside = 0;
+    for p in points {
+        match room_at(*p) {
+            Some(code) => *counts.entry(code).or_default() += 1,
+            None => outside += 1,
+        }
+    }
+    let total = points.len() as f32;
+    let mut shares: Vec<RoomShare> = counts
+        .into_iter()
+        .map(|(code, count)| RoomShare {
+            room_code: code.to_string(),
+            fraction: count as f32 / total,
+        })
+        .collect();
+    // 占比相同时按房间号排序（BTreeMap 已有序，稳定排序保持该顺序）
+    shares.sort_by(|a, b| b.fraction.total_cmp(&a.fraction));
+    RoomAssignment {
+        refno,
+        room_code: shares.first().map(|s| s.room_code.clone()),
+        shares,
+        outside: outside as f32 / total,
+    }
+}
+
+/// 从 `room_panel_relate` 加载全部房间面板及其几何
+pub async fn load_room_classifier() -> anyhow::Result<RoomClassifier> {
+    let sql = "select value [out, room_num] from room_panel_relate where out != none and room_num != none and room_num != ''";
+    let rows: Vec<(RefnoEnum, String)> = SUL_DB.query_take(sql, 0).await?;
+    let room_codes: HashMap<RefnoEnum, String> = rows.into_iter().collect();
+    let panels: Vec<RefnoEnum> = room_codes.keys().copied().collect();
+
+    let insts = query_insts(&panels, true).await?;
+    let store = mesh_store();
+    let mut mesh_cache: HashMap<String, Option<PlantMesh>> = HashMap::new();
+    let mut solids = vec![];
+    for geom_inst in insts {
+        let (Some(room_code), Some(aabb)) =
+            (room_codes.get(&geom_inst.refno), geom_inst.world_aabb)
+        else {
+            continue;
+        };
+        let mut meshes = vec![];
+        for inst in &geom_inst.insts {
+            let Some(mesh) =
+                load_mesh_cached(store.as_ref(), &mut mesh_cache, &inst.geo_hash, "L0").await
+            else {
+                continue;
+            };
+            if let Some(tri_mesh) = mesh.get_tri_mesh_with_flag(
+                (geom_inst.world_trans * &inst.transform).to_matrix(),
+                TriMeshFlags::ORIENTED,
+            ) {
+                meshes.push(tri_mesh);
+            }
+        }
+        if meshes.is_empty() {
+            continue;
+        }
+        solids.push(RoomSolid {
+            panel: geom_inst.refno,
+            room_code: room_code.clone(),
+            aabb: aabb.0,
+            meshes,
+        });
+    }
+    Ok(RoomClassifier::new(solids))
+}
+
+/// 保存房间归属到缓存表
+pub async fn save_room_assignments(assignments: &[RoomAssignment]) -> anyhow::Result<()> {
+    for chunk in assignments.chunks(SAVE_CHUNK) {
+        let mut tx = Transaction::new();
+        for a in chunk {
+            let pe_key = a.refno.to_pe_key();
+            let room_code = match &a.room_code {
+                Some(code) => serde_json::to_string(code)?,
+                None => "NONE".to_string(),
+            };
+            tx.push(format!(
+                "UPSERT {ROOM_ASSIGN_TABLE}:[{pe_key}] CONTENT {{ refno: {pe_key}, room_code: {room_code}, shares: {}, outside: {} }}",
+                serde_json::to_string(&a.shares)?,
+                a.outside,
+            ));
+        }
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// 用已加载的判断器计算并缓存一批元素的房间归属，没有包围盒的元素跳过
+pub async fn assign_rooms_with(
+    classifier: &RoomClassifier,
+    refnos: &[RefnoEnum],
+    samples_per_axis: usize,
+) -> anyhow::Result<Vec<RoomAssignment>> {
+    let insts = query_insts(refnos, false).await?;
+    let assignments: Vec<RoomAssignment> = insts
+        .into_iter()
+        .filter_map(|inst| {
+            let aabb = inst.world_aabb?.0;
+            Some(classifier.classify(inst.refno, &aabb, samples_per_axis))
+        })
+        .collect();
+    save_room_assignments(&assignments).await?;
+    let cache = room_code_cache();
+    for a in &assignments {
+        cache.insert(a.refno, a.room_code.clone());
+    }
+    Ok(assignments)
+}
+
+/// 计算并缓存一批元素的房间归属
+pub async fn assign_rooms(refnos: &[RefnoEnum]) -> anyhow::Result<Vec<RoomAssignment>> {
+    let classifier = load_room_classifier().await?;
+    assign_rooms_with(&classifier, refnos, DEFAULT_SAMPLES_PER_AXIS).await
+}
+
+static ROOM_CODE_CACHE: OnceLock<DashMap<RefnoEnum, Option<String>>> = OnceLock::new();
+static ROOM_CODE_LOADED: AtomicBool = AtomicBool::new(false);
+
+fn room_code_cache() -> &'static DashMap<RefnoEnum, Option<String>> {
+    ROOM_CODE_CACHE.get_or_init(DashMap::new)
+}
+
+/// 把缓存表整表读入内存
+pub async fn load_room_code_cache() -> anyhow::Result<usize> {
+    let sql = format!("select value [refno, room_code] from {ROOM_ASSIGN_TABLE}");
+    let rows: Vec<(RefnoEnum, Option<String>)> = SUL_DB.query_take(&sql, 0).await?;
+    let cache = room_code_cache();
+    let count = rows.len();
+    for (refno, room_code) in rows {
+        cache.insert(refno, room_code);
+    }
+    ROOM_CODE_LOADED.store(true, Ordering::Release);
+    Ok(count)
+}
+
+/// 清空内存中的房间号缓存，下次查询时重新读表
+pub fn invalidate_room_code_cache() {
+    room_code_cache().clear();
+    ROOM_CODE_LOADED.store(false, Ordering::Release);
+}
+
+/// 元素的主房间号，未计算过归属或不在任何房间内时返回 None
+pub async fn get_room_code(refno: RefnoEnum) -> anyhow::Result<Option<String>> {
+    if !ROOM_CODE_LOADED.load(Ordering::Acquire) {
+        load_room_code_cache().await?;
+    }
+    Ok(room_code_cache().get(&refno).and_then(|v| v.clone()))
+}
+
+/// 批量查询主房间号，只返回有房间号的元素
+pub async fn get_room_codes(refnos: &[RefnoEnum]) -> anyhow::Result<HashMap<RefnoEnum, String>> {
+    if !ROOM_CODE_LOADED.load(Ordering::Acquire) {
+        load_room_code_cache().await?;
+    }
+    let cache = room_code_cache();
+    Ok(refnos
+        .iter()
+        .filter_map(|r| Some((*r, cache.get(r)?.clone()?)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::csg::unit_box_mesh;
+    use glam::Mat4;
+    use nalgebra::Point3;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(1, n).into()
+    }
+
+    /// x ∈ [x0, x0 + 10]、y/z ∈ [0, 10] 的房间
+    fn room(n: u32, code: &str, x0: f32) -> RoomSolid {
+        let trans = Mat4::from_translation(Vec3::new(x0 + 5.0, 5.0, 5.0))
+            * Mat4::from_scale(Vec3::splat(10.0));
+        RoomSolid {
+            panel: r(n),
+            room_code: code.into(),
+            aabb: Aabb::new(
+                Point3::new(x0, 0.0, 0.0),
+                Point3::new(x0 + 10.0, 10.0, 10.0),
+            ),
+            meshes: vec![
+                unit_box_mesh()
+                    .get_tri_mesh_with_flag(trans, TriMeshFlags::ORIENTED)
+                    .unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_classify_spanning_rooms() {
+        let classifier = RoomClassifier::new(vec![room(1, "R-101", 0.0), room(2, "R-102", 10.0)]);
+        assert_eq!(classifier.room_at(Vec3::new(2.0, 5.0, 5.0)), Some("R-101"));
+        assert_eq!(classifier.room_at(Vec3::new(12.0, 5.0, 5.0)), Some("R-102"));
+        assert_eq!(classifier.room_at(Vec3::new(25.0, 5.0, 5.0)), None);
+
+        // 左 1/4 在 R-101，其余在 R-102
+        let aabb = Aabb::new(Point3::new(7.5, 2.0, 2.0), Point3::new(17.5, 4.0, 4.0));
+        let a = classifier.classify(r(10), &aabb, 4);
+        assert_eq!(a.room_code.as_deref(), Some("R-102"));
+        assert_eq!(a.shares.len(), 2);
+        assert_eq!(a.shares[0].fraction, 0.75);
+        assert_eq!(a.shares[1].room_code, "R-101");
+        assert_eq!(a.outside, 0.0);
+
+        // 一半在房间外
+        let aabb = Aabb::new(Point3::new(15.0, 2.0, 2.0), Point3::new(25.0, 4.0, 4.0));
+        let a = classifier.classify(r(11), &aabb, 4);
+        assert_eq!(a.room_code.as_deref(), Some("R-102"));
+        assert_eq!(a.outside, 0.5);
+
+        // 扁平元素只在厚度方向取一层
+        let flat = Aabb::new(Point3::new(1.0, 1.0, 5.0), Point3::new(3.0, 3.0, 5.0));
+        assert_eq!(sample_points(&flat, 4).len(), 16);
+    }
+
+    #[test]
+    fn test_classify_samples_tie() {
+        let points = [Vec3::ZERO, Vec3::X];
+        let a = classify_samples(r(1), &points, |p| Some(if p.x > 0.5 { "A" } else { "B" }));
+        // 占比相同时取房间号小的
+        assert_eq!(a.room_code.as_deref(), Some("A"));
+        let empty = classify_samples(r(1), &[], |_| None);
+        assert_eq!(empty.room_code, None);
+        assert_eq!(empty.outside, 1.0);
+    }
+}
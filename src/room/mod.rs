@@ -2,6 +2,9 @@ pub mod data;
 
 pub mod algorithm;
 
+// 元素房间归属
+pub mod assignment;
+
 pub mod query;
 
 // 改进版本的房间查询模块
@@ -12,6 +15,8 @@ pub mod query_v2;
 #[cfg(all(not(target_arch = "wasm32"), feature = "sqlite"))]
 pub use query_v2::{query_elements_in_room_by_spatial_index, query_room_panels_by_keywords};
 
+pub use assignment::{RoomAssignment, assign_rooms, get_room_code, get_room_codes};
+
 // 房间系统监控模块
 pub mod monitoring;
 
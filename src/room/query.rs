@@ -1,4 +1,3 @@
-use crate::shape::pdms_shape::PlantMesh;
 use crate::{RefU64, RefnoEnum, SUL_DB, query_insts};
 use glam::Vec3;
 use nalgebra::Point3;
@@ -56,13 +55,14 @@ pub async fn query_room_panel_by_point(point: Vec3) -> anyhow::Result<Option<Ref
             continue;
         };
         for inst in &geom_inst.insts {
-            // 使用配置路径和 L0 最低精度 LOD
-            use crate::utils::lod_path_detector::build_mesh_path;
-            let mesh_path = crate::get_db_option()
-                .get_meshes_path()
-                .join(build_mesh_path(&inst.geo_hash, "L0"));
-
-            let Ok(mesh) = PlantMesh::des_mesh_file(&mesh_path) else {
+            // 通过 mesh_store 读取 L0 最低精度 LOD
+            let Ok(Some(mesh)) = crate::mesh_store::load_mesh(
+                crate::mesh_store::mesh_store().as_ref(),
+                &inst.geo_hash,
+                "L0",
+            )
+            .await
+            else {
                 continue;
             };
             let Some(mut tri_mesh) = mesh.get_tri_mesh_with_flag(
@@ -187,13 +187,11 @@ async fn load_geometry_cached(
         return Ok(cached_mesh.clone());
     }
 
-    // 加载几何文件
-    let file_path = format!("assets/meshes/{}.mesh", geo_hash);
-    let mesh = tokio::task::spawn_blocking(move || {
-        crate::shape::pdms_shape::PlantMesh::des_mesh_file(&file_path)
-    })
-    .await
-    .context("几何文件加载任务失败")??;
+    // 通过 mesh_store 加载原始精度几何
+    let store = crate::mesh_store::mesh_store();
+    let mesh = crate::mesh_store::load_mesh(store.as_ref(), geo_hash, "")
+        .await?
+        .with_context(|| format!("几何文件不存在: {}", geo_hash))?;
 
     let mesh_arc = Arc::new(mesh);
 
@@ -650,7 +648,7 @@ mod tests {
         use crate::{get_db_option, init_surreal};
 
         init_surreal().await.expect("初始化数据库失败");
-        let db_option = get_db_option();
+        let db_option = get_db_option().unwrap();
         let keywords = db_option.get_room_key_word();
 
         let result = query_room_panels_by_keywords(&keywords).await;
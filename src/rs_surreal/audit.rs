@@ -0,0 +1,223 @@
+//! 属性级变更审计
+//!
+//! 会话号只说明数据在哪次保存中变化，审计表 `attr_audit` 另外记录
+//! “谁在何时把哪个元素的哪个属性从什么改成了什么”。写入路径（[`OperationJournal`] 的属性设置与撤销/重做、
+//! [`move_element`]、[`insert_child_at`]、[`clone_subtree`]、[`rollback`]、`Nullify` 模式的
+//! [`delete_subtree`]）通过 [`attr_change_statements`] 把审计记录放进同一事务，与数据修改同时提交。
+//! 新建的元素记为属性从未设置变为新值。
+//!
+//! 操作人取自 [`with_user`] 设置的任务级用户，未设置时取系统用户名：
+//!
+//! ```rust,ignore
+//! audit::with_user("zhangsan", async {
+//!     journal.set_attrs(refno, attrs).await
+//! })
+//! .await?;
+//! let history = audit::history(refno, Some("DESC")).await?;
+//! ```
+//!
+//! [`OperationJournal`]: super::operation::OperationJournal
+//! [`move_element`]: super::hierarchy::move_element
+//! [`insert_child_at`]: super::hierarchy::insert_child_at
+//! [`clone_subtree`]: super::operation::clone_subtree
+//! [`rollback`]: super::version::rollback
+//! [`delete_subtree`]: super::operation::delete_subtree
+
+use crate::types::NamedAttrValue;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::future::Future;
+use std::ops::Range;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::{Datetime, SurrealValue};
+
+/// 审计表
+pub const AUDIT_TABLE: &str = "attr_audit";
+
+tokio::task_local! {
+    static AUDIT_USER: String;
+}
+
+/// 在当前任务内以 `user` 身份执行写操作
+pub async fn with_user<F: Future>(user: impl Into<String>, fut: F) -> F::Output {
+    AUDIT_USER.scope(user.into(), fut).await
+}
+
+/// 未通过 [`with_user`] 指定用户时，以 `user` 身份执行
+pub async fn with_default_user<F: Future>(user: &str, fut: F) -> F::Output {
+    match AUDIT_USER.try_with(|_| ()) {
+        Ok(()) => fut.await,
+        Err(_) => with_user(user, fut).await,
+    }
+}
+
+/// 当前操作人：[`with_user`] 设置的用户，否则为系统用户名
+pub fn current_user() -> String {
+    AUDIT_USER
+        .try_with(|u| u.clone())
+        .ok()
+        .or_else(|| std::env::var("USERNAME").ok())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 一条属性变更
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub refno: RefnoEnum,
+    pub attr: String,
+    /// 修改前的值，None 表示原先未设置
+    pub old: Option<NamedAttrValue>,
+    /// 修改后的值，None 表示被清空
+    pub new: Option<NamedAttrValue>,
+    pub user: String,
+    pub time: Datetime,
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct AuditRow {
+    refno: RefnoEnum,
+    attr: String,
+    old: Option<String>,
+    new: Option<String>,
+    user: String,
+    time: Datetime,
+}
+
+impl From<AuditRow> for AuditRecord {
+    fn from(row: AuditRow) -> Self {
+        Self {
+            refno: row.refno,
+            attr: row.attr,
+            old: decode_value(row.old),
+            new: decode_value(row.new),
+            user: row.user,
+            time: row.time,
+        }
+    }
+}
+
+/// SurrealQL 字符串字面量
+pub(crate) fn sql_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+fn decode_value(json: Option<String>) -> Option<NamedAttrValue> {
+    json.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+fn encode_value(value: Option<&NamedAttrValue>) -> String {
+    value
+        .and_then(|v| serde_json::to_string(v).ok())
+        .map_or("NONE".to_string(), |json| sql_string(&json))
+}
+
+/// 生成审计记录语句，值未变化的属性不记录
+///
+/// 由写入方追加到自己的事务中，操作人为 [`current_user`]
+pub fn attr_change_statements<'a>(
+    refno: RefnoEnum,
+    changes: impl IntoIterator<
+        Item = (
+            &'a str,
+            Option<&'a NamedAttrValue>,
+            Option<&'a NamedAttrValue>,
+        ),
+    >,
+) -> Vec<String> {
+    let user = sql_string(&current_user());
+    changes
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(attr, old, new)| {
+            format!(
+                "CREATE {AUDIT_TABLE} SET refno = {}, attr = {}, old = {}, new = {}, user = {}, time = time::now()",
+                refno.to_pe_key(),
+                sql_string(attr),
+                encode_value(old),
+                encode_value(new),
+                user
+            )
+        })
+        .collect()
+}
+
+/// 元素的属性变更历史，按时间倒序；`attr` 为 None 时返回全部属性
+pub async fn history(refno: RefnoEnum, attr: Option<&str>) -> anyhow::Result<Vec<AuditRecord>> {
+    let attr_filter = attr
+        .map(|a| format!(" AND attr = {}", sql_string(a)))
+        .unwrap_or_default();
+    let sql = format!(
+        "SELECT refno, attr, old, new, user, time FROM {AUDIT_TABLE} WHERE refno = {}{} ORDER BY time DESC",
+        refno.to_pe_key(),
+        attr_filter
+    );
+    let rows: Vec<AuditRow> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// 用户在时间范围 `[start, end)` 内的全部属性变更，按时间正序
+pub async fn changes_by_user(
+    user: &str,
+    range: Range<DateTime<Utc>>,
+) -> anyhow::Result<Vec<AuditRecord>> {
+    let sql = format!(
+        "SELECT refno, attr, old, new, user, time FROM {AUDIT_TABLE} WHERE user = {} AND time >= <datetime> {} AND time < <datetime> {} ORDER BY time",
+        sql_string(user),
+        sql_string(&range.start.to_rfc3339()),
+        sql_string(&range.end.to_rfc3339())
+    );
+    let rows: Vec<AuditRow> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// 审计表索引，按元素与按用户查询
+pub async fn define_audit_index() -> anyhow::Result<()> {
+    let sql = format!(
+        r#"
+        DEFINE INDEX IF NOT EXISTS idx_{AUDIT_TABLE}_refno ON TABLE {AUDIT_TABLE} COLUMNS refno, attr;
+        DEFINE INDEX IF NOT EXISTS idx_{AUDIT_TABLE}_user ON TABLE {AUDIT_TABLE} COLUMNS user, time;
+        "#
+    );
+    SUL_DB.query_response(&sql).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    #[tokio::test]
+    async fn test_attr_change_statements() {
+        let refno = RefnoEnum::Refno(RefU64::from_two_nums(1, 2));
+        let old = NamedAttrValue::StringType("A".into());
+        let new = NamedAttrValue::StringType("B".into());
+        let sqls = with_user("tester", async {
+            attr_change_statements(
+                refno,
+                [
+                    ("DESC", Some(&old), Some(&new)),
+                    ("FUNC", Some(&old), Some(&old)),
+                    ("PURP", None, Some(&new)),
+                ],
+            )
+        })
+        .await;
+        assert_eq!(sqls.len(), 2);
+        assert!(sqls[0].starts_with("CREATE attr_audit SET refno = pe:1_2, attr = \"DESC\""));
+        assert!(sqls[0].contains("user = \"tester\""));
+        assert!(sqls[1].contains("old = NONE"));
+    }
+
+    #[test]
+    fn test_value_roundtrip() {
+        let value = NamedAttrValue::F32Type(1.5);
+        let encoded = encode_value(Some(&value));
+        let json: String = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decode_value(Some(json)), Some(value));
+        assert_eq!(encode_value(None), "NONE");
+        assert_eq!(decode_value(None), None);
+    }
+}
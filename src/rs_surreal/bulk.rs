@@ -0,0 +1,319 @@
+//! 批量写入
+//!
+//! [`BulkWriter`] 将大批记录按块写入同一张表：
+//! - 按 `chunk_size` 分块，块的 SQL 在发送前才生成，内存占用与并发数成正比
+//! - 最多 `concurrency` 个块同时在途（背压）
+//! - 连接断开、超时、事务冲突等暂时性错误按退避重试
+//! - 每完成一块回调一次进度
+//!
+//! ```rust,ignore
+//! use aios_core::rs_surreal::bulk::BulkWriter;
+//!
+//! let stats = BulkWriter::new("material_inst_list")
+//!     .chunk_size(2000)
+//!     .concurrency(4)
+//!     .on_progress(|p| println!("{}/{}", p.written, p.total))
+//!     .write(&SUL_DB, &rows)
+//!     .await?;
+//! ```
+
+use super::pool::is_connection_error;
+use crate::consts::MAX_INSERT_LENGTH;
+use futures::StreamExt;
+use log::warn;
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+
+/// 写入进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkProgress {
+    /// 已写入记录数
+    pub written: usize,
+    /// 总记录数
+    pub total: usize,
+    /// 已完成块数（含失败块）
+    pub chunks_done: usize,
+    /// 总块数
+    pub chunks_total: usize,
+}
+
+/// 写入统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkStats {
+    /// 成功写入的记录数
+    pub written: usize,
+    /// 总块数
+    pub chunks: usize,
+    /// 重试次数
+    pub retries: usize,
+    /// 重试后仍失败的块数
+    pub failed_chunks: usize,
+}
+
+type ProgressFn = Arc<dyn Fn(&BulkProgress) + Send + Sync>;
+
+/// 批量写入器
+#[derive(Clone)]
+pub struct BulkWriter {
+    table: String,
+    chunk_size: usize,
+    concurrency: usize,
+    max_retries: usize,
+    retry_backoff: Duration,
+    ignore_existing: bool,
+    progress: Option<ProgressFn>,
+}
+
+impl BulkWriter {
+    /// 创建写入 `table` 的写入器，默认 `INSERT IGNORE`、块大小 `MAX_INSERT_LENGTH`、并发 4
+    pub fn new(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            chunk_size: MAX_INSERT_LENGTH,
+            concurrency: 4,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(200),
+            ignore_existing: true,
+            progress: None,
+        }
+    }
+
+    /// 每块记录数
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// 同时在途的块数
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 暂时性错误的最大重试次数
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 首次重试的等待时间，之后每次翻倍
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// 为 true 时使用 `INSERT IGNORE`（默认），否则使用 `INSERT`
+    pub fn ignore_existing(mut self, ignore: bool) -> Self {
+        self.ignore_existing = ignore;
+        self
+    }
+
+    /// 每完成一块的进度回调
+    pub fn on_progress(mut self, f: impl Fn(&BulkProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(f));
+        self
+    }
+
+    /// 写入可序列化的记录，记录需自带 `id` 或由表定义生成
+    pub async fn write<T: Serialize + Sync>(
+        &self,
+        db: &Surreal<Any>,
+        records: &[T],
+    ) -> anyhow::Result<BulkStats> {
+        self.run(db, records.len(), |range| {
+            Ok(serde_json::to_string(&records[range])?)
+        })
+        .await
+    }
+
+    /// 写入已生成的 SurrealQL 对象字面量（如 `gen_sur_json` 的输出）
+    pub async fn write_json(
+        &self,
+        db: &Surreal<Any>,
+        objects: &[String],
+    ) -> anyhow::Result<BulkStats> {
+        self.run(db, objects.len(), |range| {
+            Ok(format!("[{}]", objects[range].join(",")))
+        })
+        .await
+    }
+
+    async fn run<F>(&self, db: &Surreal<Any>, total: usize, encode: F) -> anyhow::Result<BulkStats>
+    where
+        F: Fn(std::ops::Range<usize>) -> anyhow::Result<String> + Sync,
+    {
+        if total == 0 {
+            return Ok(BulkStats::default());
+        }
+        let start = std::time::Instant::now();
+        let chunks_total = total.div_ceil(self.chunk_size);
+        let written = AtomicUsize::new(0);
+        let chunks_done = AtomicUsize::new(0);
+        let retries = AtomicUsize::new(0);
+        let verb = if self.ignore_existing {
+            "INSERT IGNORE INTO"
+        } else {
+            "INSERT INTO"
+        };
+
+        let results: Vec<anyhow::Result<()>> = futures::stream::iter(0..chunks_total)
+            .map(|i| {
+                let range = i * self.chunk_size..((i + 1) * self.chunk_size).min(total);
+                let encode = &encode;
+                let written = &written;
+                let chunks_done = &chunks_done;
+                let retries = &retries;
+                async move {
+                    let len = range.len();
+                    let result = match encode(range) {
+                        Ok(values) => {
+                            let sql = format!("{} {} {}", verb, self.table, values);
+                            self.execute_with_retry(db, &sql, retries).await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if result.is_ok() {
+                        written.fetch_add(len, Ordering::Relaxed);
+                    }
+                    let done = chunks_done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Some(progress) = &self.progress {
+                        progress(&BulkProgress {
+                            written: written.load(Ordering::Relaxed),
+                            total,
+                            chunks_done: done,
+                            chunks_total,
+                        });
+                    }
+                    result
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut stats = BulkStats {
+            written: written.into_inner(),
+            chunks: chunks_total,
+            retries: retries.into_inner(),
+            failed_chunks: 0,
+        };
+        let mut first_err = None;
+        for r in results {
+            if let Err(e) = r {
+                stats.failed_chunks += 1;
+                first_err.get_or_insert(e);
+            }
+        }
+        crate::metrics::observe_bulk(&self.table, stats.written, start.elapsed());
+        match first_err {
+            None => Ok(stats),
+            Some(e) => Err(e.context(format!(
+                "批量写入 {} 失败: {}/{} 块失败，已写入 {}/{} 条",
+                self.table, stats.failed_chunks, stats.chunks, stats.written, total
+            ))),
+        }
+    }
+
+    /// 执行单块写入，暂时性错误按指数退避重试
+    async fn execute_with_retry(
+        &self,
+        db: &Surreal<Any>,
+        sql: &str,
+        retries: &AtomicUsize,
+    ) -> anyhow::Result<()> {
+        let mut delay = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            let err = match execute_chunk(db, sql).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if attempt >= self.max_retries || !is_transient_error(&err) {
+                return Err(err);
+            }
+            attempt += 1;
+            retries.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "批量写入 {} 暂时性错误（第 {} 次重试）: {}",
+                self.table, attempt, err
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+}
+
+async fn execute_chunk(db: &Surreal<Any>, sql: &str) -> anyhow::Result<()> {
+    let mut response = db.query(sql).await?;
+    let errors = response.take_errors();
+    if let Some((_, e)) = errors.into_iter().min_by_key(|(i, _)| *i) {
+        anyhow::bail!("{}", e);
+    }
+    Ok(())
+}
+
+/// 是否为可重试的暂时性错误
+fn is_transient_error(err: &anyhow::Error) -> bool {
+    if is_connection_error(err) {
+        return true;
+    }
+    let msg = format!("{err:#}").to_ascii_lowercase();
+    ["timed out", "timeout", "conflict", "can be retried", "busy"]
+        .iter()
+        .any(|k| msg.contains(k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_error() {
+        assert!(is_transient_error(&anyhow::anyhow!(
+            "Failed to commit transaction due to a read or write conflict. This transaction can be retried"
+        )));
+        assert!(is_transient_error(&anyhow::anyhow!("WebSocket closed")));
+        assert!(!is_transient_error(&anyhow::anyhow!(
+            "Found NONE for field `name`"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_writer_memory() {
+        let db = Surreal::<Any>::init();
+        db.connect("mem://").await.unwrap();
+        db.use_ns("test").use_db("test").await.unwrap();
+
+        let rows: Vec<_> = (0..25)
+            .map(|i| serde_json::json!({ "id": i, "v": i * 2 }))
+            .collect();
+        let progress = Arc::new(AtomicUsize::new(0));
+        let seen = progress.clone();
+        let stats = BulkWriter::new("bulk_t")
+            .chunk_size(10)
+            .concurrency(2)
+            .on_progress(move |p| {
+                seen.fetch_max(p.chunks_done, Ordering::Relaxed);
+            })
+            .write(&db, &rows)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.chunks, 3);
+        assert_eq!(stats.written, 25);
+        assert_eq!(stats.failed_chunks, 0);
+        assert_eq!(progress.load(Ordering::Relaxed), 3);
+
+        let ids: Vec<crate::SurlValue> = db
+            .query("SELECT VALUE id FROM bulk_t")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(ids.len(), 25);
+    }
+}
@@ -0,0 +1,212 @@
+//! inst_geo 几何哈希迁移到 [`GeoHashV2`]
+//!
+//! 对 `hash_ver` 不是 V2 的 inst_geo，用其 `param` 计算 V2 哈希：
+//!
+//! - 哈希不变（含单位几何的保留哈希）：只标记 `hash_ver`
+//! - 哈希改变：复制为新记录，把 geo_relate 的 `out` 与 tubi_relate 的 `geo` 指向新记录，
+//!   删除旧记录，并在 `geo_hash_map:⟨旧⟩` 中记录映射，供 mesh 文件等外部数据重命名
+//! - `param` 缺失或无法解析：跳过，记录在报告中
+//!
+//! geo_relate 的 `in` / `out` 不可修改，因此按 [`GeoRelate`](super::GeoRelate) 的字段重建边。
+
+use crate::geometry::geo_hash::GeoHashV2;
+use crate::parsed_data::geo_params_data::PdmsGeoParam;
+use crate::rs_surreal::transaction::with_transaction;
+use crate::types::InstGeoKey;
+use crate::{SUL_DB, SurrealQueryExt};
+use serde::{Deserialize, Serialize};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 旧哈希 -> 新哈希映射表
+pub const GEO_HASH_MAP_TABLE: &str = "geo_hash_map";
+
+/// 迁移选项
+#[derive(Debug, Clone)]
+pub struct GeoHashMigrationOptions {
+    /// 只计算映射，不修改数据库
+    pub dry_run: bool,
+    /// 每个事务处理的 inst_geo 数
+    pub chunk_size: usize,
+}
+
+impl Default for GeoHashMigrationOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            chunk_size: 200,
+        }
+    }
+}
+
+/// 一条哈希映射
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct GeoHashMapping {
+    pub old: String,
+    pub new: String,
+    pub version: u8,
+}
+
+/// 迁移结果
+#[derive(Debug, Clone, Default)]
+pub struct GeoHashMigrationReport {
+    /// 待迁移的 inst_geo 数
+    pub scanned: usize,
+    /// 哈希不变，只标记版本
+    pub unchanged: usize,
+    /// 哈希改变的映射
+    pub remapped: Vec<GeoHashMapping>,
+    /// 跳过的 (inst_geo 键, 原因)
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct GeoParamRow {
+    key: String,
+    param: Option<serde_json::Value>,
+}
+
+fn pending_keys_sql() -> String {
+    format!(
+        "SELECT VALUE <string> record::id(id) FROM inst_geo WHERE hash_ver != {}",
+        GeoHashV2::VERSION
+    )
+}
+
+/// 重建指向 `old` 的 geo_relate 并改写 tubi_relate 引用
+fn repoint_sql(old: &InstGeoKey, new: &InstGeoKey) -> String {
+    format!(
+        "FOR $r IN (SELECT * FROM geo_relate WHERE out = {old}) {{ \
+            DELETE $r.id; \
+            CREATE $r.id SET in = $r.in, out = {new}, trans = $r.trans, visible = $r.visible, \
+            meshed = $r.meshed, geo_type = $r.geo_type, geom_refno = $r.geom_refno; \
+         }}; \
+         UPDATE tubi_relate SET geo = {new} WHERE geo = {old}"
+    )
+}
+
+/// 用 `param` 计算 V2 哈希
+fn new_hash(row: &GeoParamRow) -> Result<GeoHashV2, String> {
+    let param = row.param.clone().ok_or("缺少 param")?;
+    let param: PdmsGeoParam =
+        serde_json::from_value(param).map_err(|e| format!("param 无法解析: {}", e))?;
+    param
+        .geo_hash_v2()
+        .ok_or_else(|| "param 不支持 V2 哈希".to_string())
+}
+
+/// 迁移全部未迁移的 inst_geo
+pub async fn migrate_geo_hashes(
+    options: &GeoHashMigrationOptions,
+) -> anyhow::Result<GeoHashMigrationReport> {
+    let keys: Vec<String> = SUL_DB.query_take(&pending_keys_sql(), 0).await?;
+    let mut report = GeoHashMigrationReport {
+        scanned: keys.len(),
+        ..Default::default()
+    };
+
+    for chunk in keys.chunks(options.chunk_size.max(1)) {
+        let targets = chunk
+            .iter()
+            .map(|k| InstGeoKey::new(k.as_str()).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!("SELECT <string> record::id(id) AS key, param FROM [{targets}]");
+        let rows: Vec<GeoParamRow> = SUL_DB.query_take(&sql, 0).await?;
+
+        let mut unchanged = vec![];
+        let mut remapped = vec![];
+        for row in rows {
+            match new_hash(&row) {
+                Ok(hash) if hash.to_string() == row.key => unchanged.push(InstGeoKey::new(row.key)),
+                Ok(hash) => remapped.push(GeoHashMapping {
+                    old: row.key,
+                    new: hash.to_string(),
+                    version: GeoHashV2::VERSION,
+                }),
+                Err(reason) => report.skipped.push((row.key, reason)),
+            }
+        }
+
+        if !options.dry_run && !(unchanged.is_empty() && remapped.is_empty()) {
+            with_transaction(|tx| {
+                for key in &unchanged {
+                    tx.push(format!("UPDATE {key} SET hash_ver = {}", GeoHashV2::VERSION));
+                }
+                for m in &remapped {
+                    let old = InstGeoKey::new(m.old.as_str());
+                    let new = InstGeoKey::new(m.new.as_str());
+                    tx.push(format!(
+                        "IF !record::exists({new}) {{ CREATE {new} CONTENT (SELECT * OMIT id FROM ONLY {old}) }}"
+                    ));
+                    tx.push(format!("UPDATE {new} SET hash_ver = {}", GeoHashV2::VERSION));
+                    tx.push(repoint_sql(&old, &new));
+                    tx.delete(&old.to_string());
+                    tx.upsert_content(
+                        &format!("{GEO_HASH_MAP_TABLE}:⟨{}⟩", m.old.replace('⟩', "\\⟩")),
+                        m,
+                    )?;
+                }
+                Ok(())
+            })
+            .await?;
+        }
+        report.unchanged += unchanged.len();
+        report.remapped.extend(remapped);
+    }
+
+    log::info!(
+        "inst_geo 哈希迁移{}: 共 {} 个, 不变 {} 个, 改变 {} 个, 跳过 {} 个",
+        if options.dry_run {
+            "（试运行）"
+        } else {
+            ""
+        },
+        report.scanned,
+        report.unchanged,
+        report.remapped.len(),
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// 查询旧哈希迁移后的新哈希
+pub async fn lookup_migrated(old: &str) -> anyhow::Result<Option<String>> {
+    let sql = format!(
+        "SELECT VALUE new FROM {GEO_HASH_MAP_TABLE}:⟨{}⟩",
+        old.replace('⟩', "\\⟩")
+    );
+    let rows: Vec<String> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(rows.into_iter().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repoint_sql() {
+        let sql = repoint_sql(&InstGeoKey::new("123"), &InstGeoKey::new("456"));
+        assert!(sql.contains("WHERE out = inst_geo:⟨123⟩"));
+        assert!(sql.contains("out = inst_geo:⟨456⟩"));
+        assert!(
+            sql.contains("UPDATE tubi_relate SET geo = inst_geo:⟨456⟩ WHERE geo = inst_geo:⟨123⟩")
+        );
+    }
+
+    #[test]
+    fn test_new_hash() {
+        let row = GeoParamRow {
+            key: "1".into(),
+            param: Some(
+                serde_json::json!({"PrimBox": {"center": [0.0, 0.0, 0.0], "size": [1.0, 1.0, 1.0]}}),
+            ),
+        };
+        assert_eq!(new_hash(&row), Ok(GeoHashV2(1)));
+        let row = GeoParamRow {
+            key: "9".into(),
+            param: None,
+        };
+        assert!(new_hash(&row).is_err());
+    }
+}
@@ -3,7 +3,7 @@ use crate::pdms_pluggin::heat_dissipation::InstPointMap;
 use crate::pe::SPdmsElement;
 use crate::shape::pdms_shape::RsVec3;
 use crate::utils::{take_option, take_vec};
-use crate::vec3_pool::parse_ptset_auto;
+use crate::vec3_pool::{parse_ptset_auto, ptset_select_expr};
 use crate::{NamedAttrMap, RefnoEnum};
 use crate::{SUL_DB, SurlValue, SurrealQueryExt};
 use surrealdb::types as surrealdb_types;
@@ -216,7 +216,8 @@ pub async fn query_refnos_has_pos_neg_map(
 pub async fn query_bran_children_point_map(refno: RefnoEnum) -> anyhow::Result<Vec<InstPointMap>> {
     // ptset 现在是数组，需要转换为 BTreeMap<String, CateAxisParam>
     let sql = format!(
-        "select in.id as refno, out.ptset as ptset_array, in.noun as att_type from pe:{}<-pe_owner->inst_relate;",
+        "select in.id as refno, {} as ptset_array, in.noun as att_type from pe:{}<-pe_owner->inst_relate;",
+        ptset_select_expr("out.ptset"),
         refno.to_string()
     );
     let mut response = SUL_DB.query_response(&sql).await?;
@@ -309,8 +310,8 @@ pub async fn query_refnos_point_map(
 ///通过geo hash 查询参考号
 pub async fn query_refnos_by_geo_hash(id: &str) -> anyhow::Result<Vec<RefnoEnum>> {
     let sql = format!(
-        "array::distinct(array::flatten(select value in<-inst_relate.in from inst_geo:⟨{}⟩<-geo_relate));",
-        id
+        "array::distinct(array::flatten(select value in<-inst_relate.in from {}<-geo_relate));",
+        InstGeoKey::new(id)
     );
     let mut response = SUL_DB.query_response(&sql).await?;
     let result = take_vec::<RefnoEnum>(&mut response, 0)?;
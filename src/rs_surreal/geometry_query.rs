@@ -3,7 +3,9 @@
 /// 本模块提供了用于从 SurrealDB 批量查询几何参数和 AABB 数据的结构体和辅助方法
 use crate::error::init_save_database_error;
 use crate::parsed_data::geo_params_data::PdmsGeoParam;
-use crate::types::{PlantAabb, RefnoEnum, Thing};
+use crate::rs_surreal::bulk::BulkWriter;
+use crate::rs_surreal::transaction::Transaction;
+use crate::types::{InstGeoKey, PlantAabb, RefnoEnum, Thing};
 use crate::utils::RecordIdExt;
 use crate::{SUL_DB, SurrealQueryExt, gen_bytes_hash, get_inst_relate_keys};
 use anyhow::anyhow;
@@ -12,6 +14,7 @@ use dashmap::DashMap;
 use parry3d::bounding_volume::{Aabb, BoundingVolume};
 use parry3d::math::Isometry;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::ops::{Deref, DerefMut, Mul};
 use surrealdb::types::{self as surrealdb_types, RecordId, RecordIdKey};
 use surrealdb::types::{Kind, SurrealValue, Value};
@@ -216,7 +219,7 @@ pub async fn query_geo_params(inst_geo_ids: &str) -> anyhow::Result<Vec<QueryGeo
     // 将逗号分隔的数字 ID 转换为 Thing ID 格式：inst_geo:⟨id⟩
     let thing_ids = inst_geo_ids
         .split(',')
-        .map(|id| format!("inst_geo:⟨{}⟩", id.trim()))
+        .map(|id| InstGeoKey::new(id.trim()).to_string())
         .collect::<Vec<_>>()
         .join(", ");
 
@@ -272,29 +275,28 @@ pub async fn query_aabb_params(
 ///
 /// * `aabb_map` - AABB 哈希到 AABB 对象的映射
 pub async fn save_aabb_to_surreal(aabb_map: &DashMap<String, Aabb>) {
-    if !aabb_map.is_empty() {
-        let keys = aabb_map
-            .iter()
-            .map(|kv| kv.key().clone())
-            .collect::<Vec<_>>();
-        for chunk in keys.chunks(300) {
-            let mut sql = String::new();
-            for k in chunk {
-                let v = aabb_map.get(k).unwrap();
-                let json = format!(
-                    "{{'id':aabb:⟨{}⟩, 'd':{}}}",
-                    k,
-                    serde_json::to_string(v.value()).unwrap()
-                );
-                sql.push_str(&format!("INSERT IGNORE INTO aabb {};", json));
-            }
-            match SUL_DB.query_response(&sql).await {
-                Ok(_) => {}
-                Err(_) => {
-                    init_save_database_error(&sql, &std::panic::Location::caller().to_string());
-                }
-            }
-        }
+    if aabb_map.is_empty() {
+        return;
+    }
+    let objects = aabb_map
+        .iter()
+        .map(|kv| {
+            format!(
+                "{{'id':aabb:⟨{}⟩, 'd':{}}}",
+                kv.key(),
+                serde_json::to_string(kv.value()).unwrap()
+            )
+        })
+        .collect::<Vec<_>>();
+    if let Err(e) = BulkWriter::new("aabb")
+        .chunk_size(300)
+        .write_json(&SUL_DB, &objects)
+        .await
+    {
+        init_save_database_error(
+            &format!("{e:#}"),
+            &std::panic::Location::caller().to_string(),
+        );
     }
 }
 
@@ -306,22 +308,22 @@ pub async fn save_aabb_to_surreal(aabb_map: &DashMap<String, Aabb>) {
 ///
 /// * `vec3_map` - Vec3 ID 到 JSON 字符串的映射
 pub async fn save_pts_to_surreal(vec3_map: &DashMap<u64, String>) {
-    if !vec3_map.is_empty() {
-        let keys = vec3_map.iter().map(|kv| *kv.key()).collect::<Vec<_>>();
-        for chunk in keys.chunks(100) {
-            let mut sql = String::new();
-            for &k in chunk {
-                let v = vec3_map.get(&k).unwrap();
-                let json = format!("{{'id':vec3:⟨{}⟩, 'd':{}}}", k, v.value());
-                sql.push_str(&format!("INSERT IGNORE INTO vec3 {};", json));
-            }
-            match SUL_DB.query_response(&sql).await {
-                Ok(_) => {}
-                Err(_e) => {
-                    init_save_database_error(&sql, &std::panic::Location::caller().to_string());
-                }
-            };
-        }
+    if vec3_map.is_empty() {
+        return;
+    }
+    let objects = vec3_map
+        .iter()
+        .map(|kv| format!("{{'id':vec3:⟨{}⟩, 'd':{}}}", kv.key(), kv.value()))
+        .collect::<Vec<_>>();
+    if let Err(e) = BulkWriter::new("vec3")
+        .chunk_size(100)
+        .write_json(&SUL_DB, &objects)
+        .await
+    {
+        init_save_database_error(
+            &format!("{e:#}"),
+            &std::panic::Location::caller().to_string(),
+        );
     }
 }
 
@@ -345,8 +347,7 @@ pub async fn save_pts_to_surreal(vec3_map: &DashMap<u64, String>) {
 /// 该方法会：
 /// 1. 查询 inst_relate 的世界变换和关联几何的 AABB
 /// 2. 计算每个实例的全局 AABB（通过变换合并所有几何 AABB）
-/// 3. 批量更新到 SurrealDB
-/// 4. 保存 AABB 数据到 aabb 表中去重存储
+/// 3. 每批的 AABB 去重记录与 inst_relate 更新在同一事务中写入 SurrealDB
 ///
 /// # SQL 说明
 ///
@@ -359,7 +360,7 @@ pub async fn update_inst_relate_aabbs_by_refnos(
 ) -> anyhow::Result<()> {
     const CHUNK: usize = 100;
 
-    let aabb_map = DashMap::new();
+    let mut saved_aabbs = HashSet::new();
     for chunk in refnos.chunks(CHUNK) {
         if chunk.is_empty() {
             continue;
@@ -369,7 +370,8 @@ pub async fn update_inst_relate_aabbs_by_refnos(
         // 查询 AABB 参数
         let result = query_aabb_params(&inst_keys, replace_exist).await?;
 
-        let mut update_sql = String::new();
+        // 每批的 aabb 记录与 inst_relate 更新在同一事务中提交，避免引用不存在的 aabb
+        let mut tx = Transaction::new();
         for r in result {
             // 计算合并后的 AABB
             let mut aabb = Aabb::new_invalid();
@@ -391,21 +393,14 @@ pub async fn update_inst_relate_aabbs_by_refnos(
             }
 
             let aabb_hash = gen_bytes_hash(&aabb).to_string();
-            aabb_map.entry(aabb_hash.clone()).or_insert(aabb);
-
-            let inst_key = r.refno().to_inst_relate_key();
-            // 生成更新 SQL
-            let sql = format!("update {} set aabb = aabb:⟨{}⟩;", inst_key, aabb_hash,);
-            update_sql.push_str(&sql);
+            if saved_aabbs.insert(aabb_hash.clone()) {
+                tx.save_aabb(&aabb_hash, &aabb)?;
+            }
+            tx.set_inst_relate_aabb(r.refno(), &aabb_hash);
         }
 
-        if !update_sql.is_empty() {
-            SUL_DB.query_response(&update_sql).await?;
-        }
+        tx.commit().await?;
     }
 
-    // 批量保存 AABB 到 aabb 表
-    save_aabb_to_surreal(&aabb_map).await;
-
     Ok(())
 }
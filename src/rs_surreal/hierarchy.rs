@@ -0,0 +1,309 @@
+//! 层级写入
+//!
+//! 子节点的顺序由 `pe_owner` 边的 id 决定：`pe_owner:[父节点, 序号]`，`<-pe_owner` 按 id 返回，
+//! `fn::prev` / `fn::next` 等函数直接用序号做下标。插入或移动节点时必须重写父节点下全部边，
+//! 保证序号从 0 连续，这里在同一事务中完成。子节点列表在事务外读取，事务开头会校验
+//! 列表未被并发修改（[`guard_children`]），校验失败时整个事务回滚并重新读取、重试。
+//!
+//! 位置 `index` 按可见（未软删除）子节点计算，已删除的子节点保持原有相对位置。
+
+use super::transaction::Transaction;
+use crate::derived_attr::DERIVED_ATTRS;
+use crate::pe::SPdmsElement;
+use crate::types::NamedAttrValue;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, clear_all_caches, query_ancestor_refnos};
+use serde::Deserialize;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+pub(crate) struct ChildRow {
+    pub refno: RefnoEnum,
+    pub deleted: Option<bool>,
+}
+
+/// 父节点下按序号排列的全部子节点（含已软删除的）
+pub(crate) async fn query_child_rows(parent: RefnoEnum) -> anyhow::Result<Vec<ChildRow>> {
+    let sql = format!(
+        "select in as refno, in.deleted as deleted from {}<-pe_owner where in != none",
+        parent.to_pe_key()
+    );
+    SUL_DB.query_take(&sql, 0).await
+}
+
+/// 可见位置 `index` 在全部子节点中对应的下标；超出范围时追加到末尾
+fn insert_position(children: &[ChildRow], index: usize) -> usize {
+    children
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.deleted.unwrap_or(false))
+        .nth(index)
+        .map(|(i, _)| i)
+        .unwrap_or(children.len())
+}
+
+/// 在全部子节点中插入 `refno`，返回其可见位置
+pub(crate) fn place_child(children: &mut Vec<ChildRow>, refno: RefnoEnum, index: usize) -> usize {
+    let pos = insert_position(children, index);
+    children.insert(
+        pos,
+        ChildRow {
+            refno,
+            deleted: None,
+        },
+    );
+    children[..pos]
+        .iter()
+        .filter(|c| !c.deleted.unwrap_or(false))
+        .count()
+}
+
+/// 并发修改导致校验失败时的错误标记
+const CONCURRENT_MODIFIED: &str = "子节点已被并发修改";
+
+/// 校验失败时的最大尝试次数
+pub(crate) const MAX_ATTEMPTS: usize = 3;
+
+/// 在事务中校验父节点的子节点列表仍是 `children`，否则抛错回滚
+pub(crate) fn guard_children(tx: &mut Transaction, parent: RefnoEnum, children: &[ChildRow]) {
+    let keys = children
+        .iter()
+        .map(|c| c.refno.to_pe_key())
+        .collect::<Vec<_>>()
+        .join(",");
+    tx.push(format!(
+        "IF (select value in from {parent_key}<-pe_owner where in != none) != [{keys}] {{ THROW \"{parent} {CONCURRENT_MODIFIED}\" }}",
+        parent_key = parent.to_pe_key(),
+    ));
+}
+
+pub(crate) fn is_concurrent_modified(e: &anyhow::Error) -> bool {
+    format!("{e:#}").contains(CONCURRENT_MODIFIED)
+}
+
+/// 按给定顺序重写父节点的全部 `pe_owner` 边，以及父节点内联的 children 数组（若有）
+pub(crate) fn rewrite_children(tx: &mut Transaction, parent: RefnoEnum, children: &[ChildRow]) {
+    let parent_key = parent.to_pe_key();
+    tx.push(format!("DELETE {}<-pe_owner", parent_key));
+    if children.is_empty() {
+        tx.push(format!(
+            "UPDATE {} SET children = [] WHERE children != NONE",
+            parent_key
+        ));
+        return;
+    }
+    let edges = children
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            format!(
+                "{{ id: pe_owner:[{parent_key}, {i}], in: {}, out: {parent_key}, order_num: {i} }}",
+                c.refno.to_pe_key()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    tx.push(format!("INSERT RELATION INTO pe_owner [{}]", edges));
+    let keys = children
+        .iter()
+        .map(|c| c.refno.to_pe_key())
+        .collect::<Vec<_>>()
+        .join(",");
+    tx.push(format!(
+        "UPDATE {} SET children = [{}] WHERE children != NONE",
+        parent_key, keys
+    ));
+}
+
+/// 在 `parent` 的第 `index` 个可见子节点之前插入新元素，返回实际位置
+///
+/// 只写入 pe 记录和层级关系，属性表由调用方另行保存。`index` 超出范围时追加到末尾
+pub async fn insert_child_at(
+    parent: RefnoEnum,
+    new_pe: &SPdmsElement,
+    index: usize,
+) -> anyhow::Result<usize> {
+    let mut attempt = 1;
+    loop {
+        match try_insert_child_at(parent, new_pe, index).await {
+            Err(e) if attempt < MAX_ATTEMPTS && is_concurrent_modified(&e) => {
+                log::warn!("{}，第 {} 次重试", e, attempt);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn try_insert_child_at(
+    parent: RefnoEnum,
+    new_pe: &SPdmsElement,
+    index: usize,
+) -> anyhow::Result<usize> {
+    let mut children = query_child_rows(parent).await?;
+    anyhow::ensure!(
+        children
+            .iter()
+            .all(|c| c.refno.refno() != new_pe.refno.refno()),
+        "{} 已是 {} 的子节点",
+        new_pe.refno,
+        parent
+    );
+    let mut tx = Transaction::new();
+    guard_children(&mut tx, parent, &children);
+    let position = place_child(&mut children, new_pe.refno, index);
+
+    let mut pe = new_pe.clone();
+    pe.owner = parent;
+    tx.save_pe(&pe);
+    rewrite_children(&mut tx, parent, &children);
+    tx.extend(super::audit::attr_change_statements(
+        pe.refno,
+        [("OWNER", None, Some(&NamedAttrValue::RefnoEnumType(parent)))],
+    ));
+    tx.commit().await?;
+
+    clear_all_caches(parent).await;
+    Ok(position)
+}
+
+/// 将 `refno` 移动到 `new_parent` 的第 `index` 个可见子节点之前，返回实际位置
+///
+/// 同时更新 pe 与属性表中的 owner；新旧父节点的序号在同一事务中重写，
+/// 提交后清除元素及其子孙的世界变换缓存
+pub async fn move_element(
+    refno: RefnoEnum,
+    new_parent: RefnoEnum,
+    index: usize,
+) -> anyhow::Result<usize> {
+    let mut attempt = 1;
+    loop {
+        match try_move_element(refno, new_parent, index).await {
+            Err(e) if attempt < MAX_ATTEMPTS && is_concurrent_modified(&e) => {
+                log::warn!("{}，第 {} 次重试", e, attempt);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn try_move_element(
+    refno: RefnoEnum,
+    new_parent: RefnoEnum,
+    index: usize,
+) -> anyhow::Result<usize> {
+    anyhow::ensure!(refno != new_parent, "不能将 {} 移动到自身之下", refno);
+    let ancestors = query_ancestor_refnos(new_parent).await?;
+    anyhow::ensure!(
+        !ancestors.iter().any(|a| a.refno() == refno.refno()),
+        "不能将 {} 移动到其子孙 {} 之下",
+        refno,
+        new_parent
+    );
+    let old_parent: Option<RefnoEnum> = SUL_DB
+        .query_take(
+            &format!("select value owner from only {}", refno.to_pe_key()),
+            0,
+        )
+        .await?;
+    let old_parent = old_parent.ok_or_else(|| anyhow::anyhow!("{} 不存在", refno))?;
+
+    let mut tx = Transaction::new();
+    tx.push(format!(
+        "IF (select value owner from only {}) != {} {{ THROW \"{} {CONCURRENT_MODIFIED}\" }}",
+        refno.to_pe_key(),
+        old_parent.to_pe_key(),
+        old_parent
+    ));
+    let mut new_children = query_child_rows(new_parent).await?;
+    guard_children(&mut tx, new_parent, &new_children);
+    new_children.retain(|c| c.refno.refno() != refno.refno());
+    if old_parent.refno() != new_parent.refno() {
+        let mut old_children = query_child_rows(old_parent).await?;
+        guard_children(&mut tx, old_parent, &old_children);
+        old_children.retain(|c| c.refno.refno() != refno.refno());
+        rewrite_children(&mut tx, old_parent, &old_children);
+    }
+    let position = place_child(&mut new_children, refno, index);
+    rewrite_children(&mut tx, new_parent, &new_children);
+
+    let pe_key = refno.to_pe_key();
+    let new_parent_key = new_parent.to_pe_key();
+    tx.push(format!("UPDATE {} SET owner = {}", pe_key, new_parent_key));
+    tx.push(format!(
+        "UPDATE (select value refno from only {} limit 1) SET OWNER = {}",
+        pe_key, new_parent_key
+    ));
+    tx.extend(super::audit::attr_change_statements(
+        refno,
+        [(
+            "OWNER",
+            Some(&NamedAttrValue::RefnoEnumType(old_parent)),
+            Some(&NamedAttrValue::RefnoEnumType(new_parent)),
+        )],
+    ));
+    tx.commit().await?;
+
+    for r in [refno, old_parent, new_parent] {
+        clear_all_caches(r).await;
+    }
+    DERIVED_ATTRS.invalidate_attrs(refno, &["OWNER"]);
+    // 换了父节点，元素及其子孙缓存的世界变换都已失效
+    crate::transform::invalidate_world_trans_cache_recursive(refno).await?;
+    Ok(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn rows(spec: &[(u32, bool)]) -> Vec<ChildRow> {
+        spec.iter()
+            .map(|&(n, deleted)| ChildRow {
+                refno: RefnoEnum::Refno(RefU64::from_two_nums(1, n)),
+                deleted: Some(deleted),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_place_child_skips_deleted() {
+        // 可见顺序为 1, 3, 4；2 已删除
+        let mut children = rows(&[(1, false), (2, true), (3, false), (4, false)]);
+        let new = RefnoEnum::Refno(RefU64::from_two_nums(1, 9));
+
+        assert_eq!(place_child(&mut children, new, 1), 1);
+        let order: Vec<u32> = children.iter().map(|c| c.refno.refno().get_1()).collect();
+        assert_eq!(order, [1, 2, 9, 3, 4]);
+
+        let mut children = rows(&[(1, false), (2, true)]);
+        assert_eq!(place_child(&mut children, new, 10), 1);
+        assert_eq!(children.last().unwrap().refno, new);
+    }
+
+    #[test]
+    fn test_rewrite_children_sql() {
+        let parent = RefnoEnum::Refno(RefU64::from_two_nums(1, 100));
+        let mut tx = Transaction::new();
+        rewrite_children(&mut tx, parent, &rows(&[(1, false), (2, false)]));
+        let sql = tx.to_sql();
+        assert!(sql.contains("DELETE pe:1_100<-pe_owner;"));
+        assert!(sql.contains("id: pe_owner:[pe:1_100, 1], in: pe:1_2"));
+    }
+
+    #[test]
+    fn test_guard_children_sql() {
+        let parent = RefnoEnum::Refno(RefU64::from_two_nums(1, 100));
+        let mut tx = Transaction::new();
+        guard_children(&mut tx, parent, &rows(&[(1, false), (2, true)]));
+        let sql = tx.to_sql();
+        assert!(sql.contains(
+            "IF (select value in from pe:1_100<-pe_owner where in != none) != [pe:1_1,pe:1_2]"
+        ));
+        assert!(is_concurrent_modified(&anyhow::anyhow!(
+            "执行事务失败: An error occurred: 1_100 {CONCURRENT_MODIFIED}"
+        )));
+    }
+}
@@ -322,12 +322,14 @@ pub async fn query_insts_with_negative(
 ///
 /// ## has_neg 字段：
 /// 表示该实例是否有成功的布尔运算结果（bool_status='Success' && booled_id != none）
+#[tracing::instrument(level = "debug", skip(refnos), fields(count = tracing::field::Empty))]
 pub async fn query_insts_with_batch(
     refnos: impl IntoIterator<Item = &RefnoEnum>,
     enable_holes: bool,
     batch_size: Option<usize>,
 ) -> anyhow::Result<Vec<GeomInstQuery>> {
     let refnos = refnos.into_iter().cloned().collect::<Vec<_>>();
+    tracing::Span::current().record("count", refnos.len());
     if refnos.is_empty() {
         return Ok(Vec::new());
     }
@@ -503,36 +505,40 @@ pub async fn delete_inst_relate_cascade(
     chunk_size: usize,
 ) -> anyhow::Result<()> {
     for chunk in refnos.chunks(chunk_size) {
-        let mut delete_sql_vec = vec![];
-
-        let mut inst_ids = vec![];
+        super::transaction::with_transaction(|tx| {
+            for &refno in chunk {
+                tx.delete_inst_relate_cascade(refno);
+            }
+            Ok(())
+        })
+        .await
+        .context("delete model insts info failed")?;
         for &refno in chunk {
-            inst_ids.push(refno.to_inst_relate_key());
-            let delete_sql = format!(
-                r#"
-                    delete array::flatten(select value [out, id, in] from {}->inst_info->geo_relate);
-                "#,
-                refno.to_inst_relate_key()
-            );
-            delete_sql_vec.push(delete_sql);
-        }
-
-        if !delete_sql_vec.is_empty() {
-            let mut sql = "BEGIN TRANSACTION;\n".to_string();
-            sql.push_str(&delete_sql_vec.join(""));
-            sql.push_str(&format!("delete {};", inst_ids.join(",")));
-            sql.push_str("\nCOMMIT TRANSACTION;");
-            // println!("Delete Sql is {}", &sql);
-            SUL_DB
-                .query(sql)
-                .await
-                .expect("delete model insts info failed");
+            crate::derived_attr::DERIVED_ATTRS.invalidate_geometry(refno);
         }
     }
 
     Ok(())
 }
 
+/// 批量写入 inst_info 记录，ptset 按 `encoding` 存储
+///
+/// 与 [`Transaction::save_inst_info_encoded`](super::transaction::Transaction::save_inst_info_encoded)
+/// 生成相同的记录，但经 [`BulkWriter`](super::bulk::BulkWriter) 分块并发写入，适合模型生成的大批量保存。
+pub async fn save_inst_infos(
+    infos: &[crate::geometry::EleGeosInfo],
+    encoding: crate::vec3_pool::PtsetEncoding,
+) -> anyhow::Result<super::bulk::BulkStats> {
+    let records = infos
+        .iter()
+        .map(|info| info.gen_sur_json_encoded(false, encoding))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    super::bulk::BulkWriter::new("inst_info")
+        .write_json(&SUL_DB, &records)
+        .await
+        .context("save inst_info failed")
+}
+
 /// 删除所有模型生成相关的数据
 ///
 /// 删除 inst_relate、inst_geo、inst_info、geo_relate 四个表中的所有数据
@@ -557,8 +563,9 @@ pub async fn delete_all_model_data() -> anyhow::Result<()> {
 
     sql.push_str("COMMIT TRANSACTION;");
 
-    println!("Delete Sql is: \n {}", &sql);
+    crate::compat_debug!("Delete Sql is: \n {}", &sql);
 
     SUL_DB.query(sql).await.unwrap();
+    crate::derived_attr::DERIVED_ATTRS.invalidate_all_geometry();
     Ok(())
 }
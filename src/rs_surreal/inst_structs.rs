@@ -4,6 +4,7 @@
 //! 以及相应的 to_surql 方法用于生成 SurrealDB 插入语句
 
 use crate::RefnoEnum;
+use crate::types::InstGeoKey;
 use crate::shape::pdms_shape::RsVec3;
 use bevy_transform::components::Transform;
 use chrono::NaiveDateTime;
@@ -299,6 +300,12 @@ impl InstGeo {
         }
     }
 
+    /// `inst_geo` 表的键
+    #[inline]
+    pub fn key(&self) -> InstGeoKey {
+        InstGeoKey::new(self.id.as_str())
+    }
+
     /// 设置变换矩阵
     pub fn with_trans(mut self, trans: Transform) -> Self {
         self.trans = Some(TransformData { d: trans });
@@ -338,7 +345,7 @@ impl InstGeo {
         };
 
         format!(
-            r#"CREATE inst_geo:{} SET
+            r#"CREATE {} SET
                 param = {},
                 meshed = {},
                 visible = {},
@@ -347,7 +354,7 @@ impl InstGeo {
                 created_at = {},
                 updated_at = {},
                 unit_flag = {};"#,
-            self.id,
+            self.key(),
             self.param.to_string(),
             self.meshed,
             self.visible,
@@ -373,7 +380,7 @@ impl InstGeo {
         });
 
         // 添加 ID
-        json["id"] = serde_json::json!(format!("inst_geo:{}", self.id));
+        json["id"] = serde_json::json!(self.key().to_string());
 
         serde_json::to_string(&json).unwrap_or_default()
     }
@@ -549,7 +556,7 @@ impl TubiRelate {
         };
 
         let geo_str = match &self.geo {
-            Some(geo) => format!("inst_geo:{}", geo),
+            Some(geo) => InstGeoKey::new(geo.as_str()).to_string(),
             None => "NONE".to_string(),
         };
 
@@ -1141,7 +1148,7 @@ mod tests {
         let inst_geo = InstGeo::new("geo_123".to_string(), param, true, true, "Pos".to_string(), true); // 单位 mesh
 
         let sql = inst_geo.to_surql();
-        assert!(sql.contains("CREATE inst_geo:geo_123"));
+        assert!(sql.contains("CREATE inst_geo:⟨geo_123⟩"));
         assert!(sql.contains("meshed = true"));
         assert!(sql.contains("visible = true"));
         assert!(sql.contains("geo_type = 'Pos'"));
@@ -1215,7 +1222,7 @@ mod tests {
         assert!(sql.contains("CREATE tubi_relate:tubi_123"));
         assert!(sql.contains("in = pe:"));
         assert!(sql.contains("out = pe:"));
-        assert!(sql.contains("geo = inst_geo:geo_hash"));
+        assert!(sql.contains("geo = inst_geo:⟨geo_hash⟩"));
         // 验证UPDATE语句将tubi_id添加到pe记录
         assert!(sql.contains("UPDATE pe:"));
         assert!(sql.contains("tubi_id = array::push(tubi_id?:[], tubi_relate:tubi_123)"));
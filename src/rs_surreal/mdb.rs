@@ -624,7 +624,7 @@ pub async fn query_type_refnos(
     db_type: DBType,
     name_filter: Option<&NameFilter>,
 ) -> anyhow::Result<Vec<RefnoEnum>> {
-    let mdb_name = &get_db_option().mdb_name;
+    let mdb_name = &get_db_option()?.mdb_name;
     query_type_refnos_in_mdb(nouns, mdb_name, db_type, name_filter).await
 }
 
@@ -678,7 +678,10 @@ pub async fn query_all_mdb_names() -> anyhow::Result<Vec<String>> {
 #[cached(result = true)]
 pub async fn query_mdb_db_nums(mdb: Option<String>, module: DBType) -> anyhow::Result<Vec<u32>> {
     let db_type: u8 = module.into();
-    let mdb = mdb.unwrap_or_else(|| crate::get_db_option().mdb_name.clone());
+    let mdb = match mdb {
+        Some(mdb) => mdb,
+        None => crate::get_db_option()?.mdb_name.clone(),
+    };
     let processed_mdb = crate::helper::to_e3d_name(&mdb).into_owned();
     let sql = format!(
         " select value (select value DBNO from CURD.refno where STYP={db_type}) from only MDB where NAME='{processed_mdb}' limit 1"
@@ -857,7 +860,7 @@ mod tests {
     async fn test_get_world_refno() {
         init_test_surreal().await;
 
-        let mdb = get_db_option().mdb_name.clone();
+        let mdb = get_db_option().unwrap().mdb_name.clone();
         println!("🧪 测试 get_world_refno, MDB: {}", mdb);
 
         let result = get_world_refno(mdb.clone()).await;
@@ -874,7 +877,7 @@ mod tests {
 
         println!("🧪 测试 query_mdb_db_nums");
 
-        let mdb = get_db_option().mdb_name.clone();
+        let mdb = get_db_option().unwrap().mdb_name.clone();
         let result = query_mdb_db_nums(Some(mdb), DBType::DESI).await;
         assert!(result.is_ok(), "查询数据库编号应该成功");
 
@@ -890,7 +893,7 @@ mod tests {
     async fn test_get_site_pes_by_dbnum() {
         init_test_surreal().await;
 
-        let db_nums = query_mdb_db_nums(Some(get_db_option().mdb_name.clone()), DBType::DESI)
+        let db_nums = query_mdb_db_nums(Some(get_db_option().unwrap().mdb_name.clone()), DBType::DESI)
             .await
             .unwrap();
         if db_nums.is_empty() {
@@ -921,7 +924,7 @@ mod tests {
     async fn test_query_type_refnos_by_dbnum() {
         init_test_surreal().await;
 
-        let db_nums = query_mdb_db_nums(Some(get_db_option().mdb_name.clone()), DBType::DESI)
+        let db_nums = query_mdb_db_nums(Some(get_db_option().unwrap().mdb_name.clone()), DBType::DESI)
             .await
             .unwrap();
         if db_nums.is_empty() {
@@ -951,7 +954,7 @@ mod tests {
     async fn test_query_type_refnos_by_dbnum_with_children() {
         init_test_surreal().await;
 
-        let db_nums = query_mdb_db_nums(Some(get_db_option().mdb_name.clone()), DBType::DESI)
+        let db_nums = query_mdb_db_nums(Some(get_db_option().unwrap().mdb_name.clone()), DBType::DESI)
             .await
             .unwrap();
         if db_nums.is_empty() {
@@ -977,7 +980,7 @@ mod tests {
     async fn test_query_type_refnos_by_dbnum_with_name_filter() {
         init_test_surreal().await;
 
-        let db_nums = query_mdb_db_nums(Some(get_db_option().mdb_name.clone()), DBType::DESI)
+        let db_nums = query_mdb_db_nums(Some(get_db_option().unwrap().mdb_name.clone()), DBType::DESI)
             .await
             .unwrap();
         if db_nums.is_empty() {
@@ -1063,7 +1066,7 @@ mod tests {
     async fn test_get_mdb_world_site_pes() {
         init_test_surreal().await;
 
-        let mdb = get_db_option().mdb_name.clone();
+        let mdb = get_db_option().unwrap().mdb_name.clone();
         println!("🧪 测试 get_mdb_world_site_pes, MDB: {}", mdb);
 
         let result = get_mdb_world_site_pes(mdb.clone(), DBType::DESI).await;
@@ -1082,7 +1085,7 @@ mod tests {
     async fn test_get_mdb_world_site_ele_nodes() {
         init_test_surreal().await;
 
-        let mdb = get_db_option().mdb_name.clone();
+        let mdb = get_db_option().unwrap().mdb_name.clone();
         println!("🧪 测试 get_mdb_world_site_ele_nodes, MDB: {}", mdb);
 
         let result = get_mdb_world_site_ele_nodes(mdb.clone(), DBType::DESI).await;
@@ -1105,7 +1108,7 @@ mod tests {
     async fn test_query_type_refnos_by_dbnums() {
         init_test_surreal().await;
 
-        let db_nums = query_mdb_db_nums(Some(get_db_option().mdb_name.clone()), DBType::DESI)
+        let db_nums = query_mdb_db_nums(Some(get_db_option().unwrap().mdb_name.clone()), DBType::DESI)
             .await
             .unwrap();
         if db_nums.is_empty() {
@@ -1131,7 +1134,7 @@ mod tests {
     async fn test_query_use_cate_refnos_by_dbnum() {
         init_test_surreal().await;
 
-        let db_nums = query_mdb_db_nums(Some(get_db_option().mdb_name.clone()), DBType::DESI)
+        let db_nums = query_mdb_db_nums(Some(get_db_option().unwrap().mdb_name.clone()), DBType::DESI)
             .await
             .unwrap();
         if db_nums.is_empty() {
@@ -1,18 +1,26 @@
 pub mod adapter;
 pub mod attr_cache;
+pub mod bulk;
 pub mod boolean_query;
 pub mod boolean_query_optimized;
 pub mod datacenter_query;
+pub mod geo_hash_migration;
 pub mod geom;
 pub mod geometry_query;
 pub mod graph;
+pub mod hierarchy;
 pub mod index;
 pub mod mdb;
+#[cfg(feature = "ptset-cbor")]
+pub mod ptset_migration;
 pub mod query;
 pub mod query_ext;
 pub mod query_methods;
 pub mod query_structs;
 pub mod connection_manager;
+pub mod pool;
+pub mod project_registry;
+pub mod transaction;
 
 pub mod cate;
 pub mod resolve;
@@ -34,6 +42,7 @@ pub mod version;
 pub mod e3d_db;
 pub mod topology;
 
+pub mod audit;
 pub mod operation;
 pub mod pipeline;
 
@@ -49,6 +58,9 @@ pub mod annotation_query;
 // Tag name mapping 表相关查询
 pub mod tag_name_mapping;
 
+// PE/属性分层读取缓存
+pub mod tiered_cache;
+
 pub use attr_cache::*;
 pub use boolean_query::*;
 pub use cate::*;
@@ -77,6 +89,8 @@ pub use uda::*;
 
 pub use adapter::create_surreal_adapter;
 pub use connection_manager::{CONNECTION_MANAGER, ConnectionConfig, SurrealConnectionManager};
+pub use pool::{PoolOptions, PoolStatus, PoolStrategy, SurrealPool};
+pub use project_registry::{PROJECT_REGISTRY, ProjectRegistry, with_project};
 
 use once_cell::sync::Lazy;
 use surrealdb::Surreal;
@@ -85,7 +99,8 @@ use surrealdb::opt::auth::Root;
 
 // pub type SurlValue = surrealdb::Value;
 pub type SurlValue = surrealdb::types::Value;
-pub static SUL_DB: Lazy<Surreal<Any>> = Lazy::new(Surreal::init);
+/// 主库连接池，`Deref` 到主连接；`query_take` / `query_response` 走连接池
+pub static SUL_DB: Lazy<SurrealPool> = Lazy::new(SurrealPool::new);
 pub static SECOND_SUL_DB: Lazy<Surreal<Any>> = Lazy::new(Surreal::init);
 pub static KV_DB: Lazy<Surreal<Any>> = Lazy::new(Surreal::init);
 
@@ -117,7 +132,15 @@ pub async fn connect_surdb(
     let config = ConnectionConfig::new(conn_str, ns, db, username, password);
 
     // 使用连接管理器执行智能连接
-    CONNECTION_MANAGER.connect_or_reconnect(&SUL_DB, config).await
+    CONNECTION_MANAGER
+        .connect_or_reconnect(&SUL_DB, config.clone())
+        .await?;
+
+    // 主连接就绪后按当前参数建立连接池
+    if let Err(e) = SUL_DB.init_pool(config).await {
+        log::warn!("连接池初始化失败，仅使用主连接: {}", e);
+    }
+    Ok(())
 }
 
 pub async fn connect_kvdb(
@@ -0,0 +1,334 @@
+//! 子树删除
+//!
+//! [`delete_subtree`] 软删除一个元素及其全部子孙，删除前检查子树外对子树内元素的引用：
+//! - 连接属性（HREF、TREF、CREF）
+//! - NGMR / 负实体关系（`ngmr_relate`、`neg_relate`）
+//! - 贯穿件记录（[`PenetrationData`]，由调用方提供）
+//!
+//! 按 [`DeleteMode`] 处理这些引用，并先在 [`DeleteReport`] 中给出受影响的元素，
+//! 可用 [`plan_delete_subtree`] 只生成报告而不写入。
+
+use super::subtree::collect_subtree;
+use crate::derived_attr::DERIVED_ATTRS;
+use crate::penetration::PenetrationData;
+use crate::rs_surreal::audit;
+use crate::rs_surreal::transaction::Transaction;
+use crate::types::NamedAttrValue;
+use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt, clear_all_caches, join_pe_keys};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 检查的连接属性
+const REF_ATTRS: [&str; 3] = ["HREF", "TREF", "CREF"];
+
+/// 检查的几何关系表
+const RELATION_TABLES: [&str; 2] = ["ngmr_relate", "neg_relate"];
+
+/// 级联删除的最大扩展轮数，防止引用链异常时无限扩展
+const MAX_CASCADE_ROUNDS: usize = 16;
+
+/// 存在外部引用时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeleteMode {
+    /// 存在外部引用时不删除
+    #[default]
+    Block,
+    /// 清空外部元素的引用属性、删除关系后再删除
+    Nullify,
+    /// 连同引用方（及其子孙）一起删除
+    Cascade,
+}
+
+/// 引用类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InboundKind {
+    /// 连接属性，如 CREF
+    Attr(String),
+    /// `ngmr_relate` / `neg_relate` 关系，值为表名
+    Relation(String),
+    /// 贯穿件
+    Penetration,
+}
+
+/// 子树外元素对子树内元素的引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundRef {
+    /// 引用方
+    pub from: RefnoEnum,
+    /// 被引用的子树内元素
+    pub to: RefnoEnum,
+    pub kind: InboundKind,
+}
+
+/// 删除报告
+#[derive(Debug, Clone, Default)]
+pub struct DeleteReport {
+    pub root: RefnoEnum,
+    pub mode: DeleteMode,
+    /// 将被（或已被）删除的全部元素，含级联的元素
+    pub deleted: Vec<RefnoEnum>,
+    /// 因级联而额外删除的引用方
+    pub cascaded: Vec<RefnoEnum>,
+    /// 外部引用，`Cascade` 模式下包含已并入删除集合的引用方
+    pub references: Vec<InboundRef>,
+    /// `Block` 模式下存在外部引用，未执行删除
+    pub blocked: bool,
+    /// 是否已写入数据库
+    pub committed: bool,
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct RefAttrRow {
+    id: RefnoEnum,
+    href: Option<RefnoEnum>,
+    tref: Option<RefnoEnum>,
+    cref: Option<RefnoEnum>,
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct RelationRow {
+    pe: Option<RefnoEnum>,
+    out: Option<RefnoEnum>,
+}
+
+/// 查询指向 `targets` 的连接属性
+async fn query_attr_refs(targets: &[RefnoEnum]) -> anyhow::Result<Vec<InboundRef>> {
+    let keys = join_pe_keys(targets.iter());
+    let sql = format!(
+        r#"SELECT id, refno.HREF as href, refno.TREF as tref, refno.CREF as cref FROM pe
+           WHERE !deleted AND (refno.HREF IN [{0}] OR refno.TREF IN [{0}] OR refno.CREF IN [{0}])"#,
+        keys
+    );
+    let rows: Vec<RefAttrRow> = SUL_DB.query_take(&sql, 0).await?;
+    Ok(rows
+        .into_iter()
+        .flat_map(|row| {
+            let from = row.id;
+            REF_ATTRS
+                .into_iter()
+                .zip([row.href, row.tref, row.cref])
+                .filter_map(move |(attr, to)| {
+                    to.map(|to| InboundRef {
+                        from,
+                        to,
+                        kind: InboundKind::Attr(attr.to_string()),
+                    })
+                })
+        })
+        .collect())
+}
+
+/// 查询与 `targets` 相关的几何关系，`from` 为关系另一端的元素
+async fn query_relation_refs(targets: &[RefnoEnum]) -> anyhow::Result<Vec<InboundRef>> {
+    let keys = join_pe_keys(targets.iter());
+    let mut refs = vec![];
+    for table in RELATION_TABLES {
+        let sql = format!("SELECT pe, out FROM {table} WHERE pe IN [{keys}] OR out IN [{keys}]");
+        let rows: Vec<RelationRow> = SUL_DB.query_take(&sql, 0).await?;
+        for row in rows {
+            let (Some(pe), Some(out)) = (row.pe, row.out) else {
+                continue;
+            };
+            // 两个方向都记录，由 external_refs 过滤掉两端都在删除集合内的关系
+            for (from, to) in [(pe, out), (out, pe)] {
+                refs.push(InboundRef {
+                    from,
+                    to,
+                    kind: InboundKind::Relation(table.to_string()),
+                });
+            }
+        }
+    }
+    Ok(refs)
+}
+
+/// 贯穿件所属元素在删除集合内时，贯穿件本身即为外部引用
+fn penetration_refs(penetrations: &[PenetrationData]) -> Vec<InboundRef> {
+    penetrations
+        .iter()
+        .map(|p| InboundRef {
+            from: RefnoEnum::Refno(p.refno),
+            to: RefnoEnum::Refno(p.owner_refno),
+            kind: InboundKind::Penetration,
+        })
+        .collect()
+}
+
+/// 保留引用方在集合外、被引用方在集合内的引用，并去重
+fn external_refs(refs: Vec<InboundRef>, set: &BTreeSet<RefU64>) -> Vec<InboundRef> {
+    let mut out: Vec<InboundRef> = vec![];
+    for r in refs {
+        if set.contains(&r.from.refno()) || !set.contains(&r.to.refno()) {
+            continue;
+        }
+        if !out.contains(&r) {
+            out.push(r);
+        }
+    }
+    out
+}
+
+/// 生成删除报告，不写入数据库
+///
+/// `Cascade` 模式下会反复把引用方的子树并入删除集合，直到没有新的外部引用
+pub async fn plan_delete_subtree(
+    refno: RefnoEnum,
+    mode: DeleteMode,
+    penetrations: &[PenetrationData],
+) -> anyhow::Result<DeleteReport> {
+    let (mut deleted, _) = collect_subtree(refno).await?;
+    let mut set: BTreeSet<RefU64> = deleted.iter().map(|r| r.refno()).collect();
+    let mut cascaded = vec![];
+    let mut references = vec![];
+
+    for _ in 0..MAX_CASCADE_ROUNDS {
+        let mut refs = query_attr_refs(&deleted).await?;
+        refs.extend(query_relation_refs(&deleted).await?);
+        refs.extend(penetration_refs(penetrations));
+        let found = external_refs(refs, &set);
+        // 关系随删除一并清理，只有属性与贯穿件的引用方需要级联
+        let referrers: BTreeSet<RefU64> = found
+            .iter()
+            .filter(|r| !matches!(r.kind, InboundKind::Relation(_)))
+            .map(|r| r.from.refno())
+            .collect();
+        for r in found {
+            if !references.contains(&r) {
+                references.push(r);
+            }
+        }
+        if mode != DeleteMode::Cascade || referrers.is_empty() {
+            break;
+        }
+        for referrer in referrers {
+            let (nodes, _) = collect_subtree(RefnoEnum::Refno(referrer)).await?;
+            for node in nodes {
+                if set.insert(node.refno()) {
+                    deleted.push(node);
+                    cascaded.push(node);
+                }
+            }
+        }
+    }
+
+    Ok(DeleteReport {
+        root: refno,
+        mode,
+        blocked: mode == DeleteMode::Block && !references.is_empty(),
+        deleted,
+        cascaded,
+        references,
+        committed: false,
+    })
+}
+
+/// 删除 `refno` 及其子孙，见 [`delete_subtree_with`]
+pub async fn delete_subtree(refno: RefnoEnum, mode: DeleteMode) -> anyhow::Result<DeleteReport> {
+    delete_subtree_with(refno, mode, &[]).await
+}
+
+/// 删除 `refno` 及其子孙，同时检查给定的贯穿件记录
+///
+/// 元素做软删除并级联删除实例数据；`Block` 模式下存在外部引用时直接返回报告，不做任何写入。
+/// 贯穿件记录由调用方维护，`Nullify` 模式下仅在报告中列出
+pub async fn delete_subtree_with(
+    refno: RefnoEnum,
+    mode: DeleteMode,
+    penetrations: &[PenetrationData],
+) -> anyhow::Result<DeleteReport> {
+    let mut report = plan_delete_subtree(refno, mode, penetrations).await?;
+    if report.blocked {
+        return Ok(report);
+    }
+    let owner: Option<RefnoEnum> = SUL_DB
+        .query_take(
+            &format!("select value owner from only {}", refno.to_pe_key()),
+            0,
+        )
+        .await?;
+
+    let mut tx = Transaction::new();
+    if mode == DeleteMode::Nullify {
+        for r in &report.references {
+            if let InboundKind::Attr(attr) = &r.kind {
+                tx.push(format!(
+                    "UPDATE (select value refno from only {} limit 1) SET {} = NONE",
+                    r.from.to_pe_key(),
+                    attr
+                ));
+                tx.extend(audit::attr_change_statements(
+                    r.from,
+                    [(
+                        attr.as_str(),
+                        Some(&NamedAttrValue::RefnoEnumType(r.to)),
+                        None,
+                    )],
+                ));
+            }
+        }
+    }
+    let keys = join_pe_keys(report.deleted.iter());
+    for table in RELATION_TABLES {
+        tx.push(format!(
+            "DELETE {table} WHERE pe IN [{keys}] OR out IN [{keys}]"
+        ));
+    }
+    for &r in &report.deleted {
+        tx.delete_inst_relate_cascade(r);
+    }
+    tx.push(format!("UPDATE [{}] SET deleted = true", keys));
+    tx.commit()
+        .await
+        .with_context(|| format!("删除 {} 失败", refno))?;
+    report.committed = true;
+
+    for &r in report.deleted.iter().chain(owner.iter()) {
+        clear_all_caches(r).await;
+    }
+    for &r in &report.deleted {
+        DERIVED_ATTRS.invalidate_element(r);
+    }
+    for r in &report.references {
+        clear_all_caches(r.from).await;
+        if mode == DeleteMode::Nullify
+            && let InboundKind::Attr(attr) = &r.kind
+        {
+            DERIVED_ATTRS.invalidate_attrs(r.from, &[attr.as_str()]);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefnoEnum::Refno(RefU64::from_two_nums(1, n))
+    }
+
+    #[test]
+    fn test_external_refs() {
+        let set: BTreeSet<RefU64> = [1, 2].into_iter().map(|n| refno(n).refno()).collect();
+        let attr = |from, to| InboundRef {
+            from: refno(from),
+            to: refno(to),
+            kind: InboundKind::Attr("CREF".into()),
+        };
+        let refs = vec![attr(2, 1), attr(3, 2), attr(3, 2), attr(3, 4)];
+        assert_eq!(external_refs(refs, &set), vec![attr(3, 2)]);
+
+        let pene = PenetrationData {
+            refno: RefU64::from_two_nums(1, 5),
+            owner_refno: RefU64::from_two_nums(1, 1),
+            ..Default::default()
+        };
+        let refs = external_refs(penetration_refs(&[pene]), &set);
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].from, refno(5));
+        assert_eq!(refs[0].kind, InboundKind::Penetration);
+    }
+}
@@ -0,0 +1,610 @@
+//! 操作日志（撤销 / 重做）
+//!
+//! 通过 [`OperationJournal`] 执行的修改（设置属性、移动、复制、删除）会同时记录正向操作与逆操作，
+//! 按用户会话保存在 `op_journal` 表中，[`OperationJournal::undo`] / [`OperationJournal::redo`]
+//! 依次回放逆操作或正向操作。新的修改会丢弃尚未重做的记录。
+//!
+//! 复制与删除都以软删除标记（`deleted`）实现撤销：撤销复制即把新元素标记删除，
+//! 撤销删除即清除删除标记并恢复 `Nullify` 模式清空的连接属性。删除时级联清理的
+//! 实例数据与 `ngmr_relate` / `neg_relate` 关系不会恢复，需要重新生成模型。
+//!
+//! 属性变更同时写入审计表（见 [`audit`]），未指定操作人时以会话名作为操作人。
+//! 修改位置、方向等影响变换的属性后，清除元素及其子孙的世界变换缓存。
+//!
+//! ```rust,ignore
+//! let journal = journal_for(&user.id).await?;
+//! journal.set_attrs(refno, [("DESC".to_string(), NamedAttrValue::StringType("A".into()))]).await?;
+//! journal.move_element(refno, new_parent, 0).await?;
+//! journal.undo(2).await?;
+//! ```
+
+use super::delete::{DeleteMode, DeleteReport, InboundKind, delete_subtree};
+use super::subtree::{CloneOptions, CloneResult, clone_subtree};
+use crate::derived_attr::DERIVED_ATTRS;
+use crate::rs_surreal::audit::{self, sql_string, with_default_user};
+use crate::rs_surreal::hierarchy::{self, query_child_rows};
+use crate::rs_surreal::transaction::Transaction;
+use crate::transform::invalidate_world_trans_cache_recursive;
+use crate::types::NamedAttrValue;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, clear_all_caches, get_named_attmap, join_pe_keys};
+use anyhow::Context;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+use tokio::sync::Mutex;
+
+/// 日志表
+pub const JOURNAL_TABLE: &str = "op_journal";
+
+/// 每个会话保留的最大记录数，超出后丢弃最早的记录
+pub const MAX_JOURNAL_LEN: usize = 200;
+
+/// 影响元素局部变换的属性，修改后需要清除元素及其子孙的世界变换缓存
+const TRANSFORM_ATTRS: &[&str] = &[
+    "POS", "ORI", "POSI", "POSL", "ZDIS", "PKDI", "BANG", "DELP", "YDIR", "OPDI", "CUTP", "NPOS",
+    "JUSL",
+];
+
+/// 可回放的基本操作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JournalOp {
+    /// 设置属性，值为 None 表示清空
+    SetAttrs {
+        refno: RefnoEnum,
+        attrs: BTreeMap<String, Option<NamedAttrValue>>,
+    },
+    /// 移动到 `parent` 的第 `index` 个可见子节点之前
+    Move {
+        refno: RefnoEnum,
+        parent: RefnoEnum,
+        index: usize,
+    },
+    /// 设置软删除标记
+    SetDeleted {
+        refnos: Vec<RefnoEnum>,
+        deleted: bool,
+    },
+}
+
+/// 一次用户操作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: i64,
+    /// 操作描述，如 `move 17496/100`
+    pub label: String,
+    /// 重做时按顺序执行
+    pub forward: Vec<JournalOp>,
+    /// 撤销时按顺序执行
+    pub inverse: Vec<JournalOp>,
+    pub undone: bool,
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct JournalRow {
+    seq: i64,
+    label: String,
+    forward: String,
+    inverse: String,
+    undone: bool,
+}
+
+/// 写入数据库的记录，操作列表以 JSON 字符串保存
+#[derive(Serialize)]
+struct JournalRecord<'a> {
+    session: &'a str,
+    seq: i64,
+    label: &'a str,
+    forward: String,
+    inverse: String,
+    undone: bool,
+}
+
+impl TryFrom<JournalRow> for JournalEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(row: JournalRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            seq: row.seq,
+            label: row.label,
+            forward: serde_json::from_str(&row.forward)?,
+            inverse: serde_json::from_str(&row.inverse)?,
+            undone: row.undone,
+        })
+    }
+}
+
+/// 单个用户会话的操作日志
+#[derive(Debug)]
+pub struct OperationJournal {
+    session: String,
+    /// 按 seq 升序，已撤销的记录位于末尾
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl OperationJournal {
+    /// 从数据库加载会话的日志
+    pub async fn load(session: &str) -> anyhow::Result<Self> {
+        let sql = format!(
+            "SELECT seq, label, forward, inverse, undone FROM {JOURNAL_TABLE} WHERE session = {} ORDER BY seq",
+            sql_string(session)
+        );
+        let rows: Vec<JournalRow> = SUL_DB.query_take(&sql, 0).await?;
+        let entries = rows
+            .into_iter()
+            .map(JournalEntry::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .with_context(|| format!("解析会话 {} 的操作日志失败", session))?;
+        Ok(Self {
+            session: session.to_string(),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// 当前日志快照
+    pub async fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.lock().await.clone()
+    }
+
+    pub async fn can_undo(&self) -> bool {
+        self.entries.lock().await.iter().any(|e| !e.undone)
+    }
+
+    pub async fn can_redo(&self) -> bool {
+        self.entries.lock().await.iter().any(|e| e.undone)
+    }
+
+    /// 设置属性，同名属性在 pe 上的镜像字段（NAME）一并更新
+    pub async fn set_attrs(
+        &self,
+        refno: RefnoEnum,
+        attrs: impl IntoIterator<Item = (String, NamedAttrValue)>,
+    ) -> anyhow::Result<()> {
+        let attrs: BTreeMap<String, Option<NamedAttrValue>> =
+            attrs.into_iter().map(|(k, v)| (k, Some(v))).collect();
+        let mut entries = self.entries.lock().await;
+        let current = get_named_attmap(refno).await?;
+        let old = attrs
+            .keys()
+            .map(|k| (k.clone(), current.map.get(k).cloned()))
+            .collect();
+        let forward = vec![JournalOp::SetAttrs { refno, attrs }];
+        with_default_user(&self.session, apply_ops(&forward)).await?;
+        self.push(
+            &mut entries,
+            format!("set_attrs {}", refno),
+            forward,
+            vec![JournalOp::SetAttrs { refno, attrs: old }],
+        )
+        .await
+    }
+
+    /// 移动元素，见 [`hierarchy::move_element`]
+    pub async fn move_element(
+        &self,
+        refno: RefnoEnum,
+        new_parent: RefnoEnum,
+        index: usize,
+    ) -> anyhow::Result<usize> {
+        let mut entries = self.entries.lock().await;
+        let old_parent: Option<RefnoEnum> = SUL_DB
+            .query_take(
+                &format!("select value owner from only {}", refno.to_pe_key()),
+                0,
+            )
+            .await?;
+        let old_parent = old_parent.ok_or_else(|| anyhow::anyhow!("{} 不存在", refno))?;
+        let old_index = visible_index(old_parent, refno).await?;
+
+        let position = with_default_user(
+            &self.session,
+            hierarchy::move_element(refno, new_parent, index),
+        )
+        .await?;
+        self.push(
+            &mut entries,
+            format!("move {}", refno),
+            vec![JournalOp::Move {
+                refno,
+                parent: new_parent,
+                index: position,
+            }],
+            vec![JournalOp::Move {
+                refno,
+                parent: old_parent,
+                index: old_index,
+            }],
+        )
+        .await?;
+        Ok(position)
+    }
+
+    /// 复制子树，见 [`clone_subtree`]
+    pub async fn clone_subtree(
+        &self,
+        root: RefnoEnum,
+        new_parent: RefnoEnum,
+        options: CloneOptions,
+    ) -> anyhow::Result<CloneResult> {
+        let mut entries = self.entries.lock().await;
+        let result = clone_subtree(root, new_parent, options).await?;
+        let refnos: Vec<RefnoEnum> = result.mapping.values().map(|&r| r.into()).collect();
+        self.push(
+            &mut entries,
+            format!("clone {}", root),
+            vec![JournalOp::SetDeleted {
+                refnos: refnos.clone(),
+                deleted: false,
+            }],
+            vec![JournalOp::SetDeleted {
+                refnos,
+                deleted: true,
+            }],
+        )
+        .await?;
+        Ok(result)
+    }
+
+    /// 删除子树，见 [`delete_subtree`]；`Block` 模式被阻止时不记录
+    pub async fn delete_subtree(
+        &self,
+        refno: RefnoEnum,
+        mode: DeleteMode,
+    ) -> anyhow::Result<DeleteReport> {
+        let mut entries = self.entries.lock().await;
+        let report = with_default_user(&self.session, delete_subtree(refno, mode)).await?;
+        if !report.committed {
+            return Ok(report);
+        }
+        let (forward, inverse) = delete_ops(&report);
+        self.push(&mut entries, format!("delete {}", refno), forward, inverse)
+            .await?;
+        Ok(report)
+    }
+
+    /// 撤销最近的 `n` 次操作，返回实际撤销的次数
+    pub async fn undo(&self, n: usize) -> anyhow::Result<usize> {
+        let mut entries = self.entries.lock().await;
+        let mut count = 0;
+        while count < n {
+            let Some(entry) = entries.iter_mut().rev().find(|e| !e.undone) else {
+                break;
+            };
+            with_default_user(&self.session, apply_ops(&entry.inverse))
+                .await
+                .with_context(|| format!("撤销 {} 失败", entry.label))?;
+            entry.undone = true;
+            self.save_undone(entry.seq, true).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 重做最近撤销的 `n` 次操作，返回实际重做的次数
+    pub async fn redo(&self, n: usize) -> anyhow::Result<usize> {
+        let mut entries = self.entries.lock().await;
+        let mut count = 0;
+        while count < n {
+            let Some(entry) = entries.iter_mut().find(|e| e.undone) else {
+                break;
+            };
+            with_default_user(&self.session, apply_ops(&entry.forward))
+                .await
+                .with_context(|| format!("重做 {} 失败", entry.label))?;
+            entry.undone = false;
+            self.save_undone(entry.seq, false).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 清空会话日志
+    pub async fn clear(&self) -> anyhow::Result<()> {
+        let mut entries = self.entries.lock().await;
+        SUL_DB
+            .query_response(format!(
+                "DELETE {JOURNAL_TABLE} WHERE session = {}",
+                sql_string(&self.session)
+            ))
+            .await?;
+        entries.clear();
+        Ok(())
+    }
+
+    /// 记录一次已执行的操作：丢弃未重做的记录，超出上限时丢弃最早的记录
+    async fn push(
+        &self,
+        entries: &mut Vec<JournalEntry>,
+        label: String,
+        forward: Vec<JournalOp>,
+        inverse: Vec<JournalOp>,
+    ) -> anyhow::Result<()> {
+        let seq = entries.last().map_or(1, |e| e.seq + 1);
+        let entry = JournalEntry {
+            seq,
+            label,
+            forward,
+            inverse,
+            undone: false,
+        };
+        entries.retain(|e| !e.undone);
+        let dropped = entries.len().saturating_sub(MAX_JOURNAL_LEN - 1);
+        entries.drain(..dropped);
+        let min_seq = entries.first().map_or(seq, |e| e.seq);
+
+        let mut tx = Transaction::new();
+        tx.push(format!(
+            "DELETE {JOURNAL_TABLE} WHERE session = {} AND (undone = true OR seq < {})",
+            sql_string(&self.session),
+            min_seq
+        ));
+        tx.upsert_content(
+            &record_key(&self.session, seq),
+            &JournalRecord {
+                session: &self.session,
+                seq,
+                label: &entry.label,
+                forward: serde_json::to_string(&entry.forward)?,
+                inverse: serde_json::to_string(&entry.inverse)?,
+                undone: false,
+            },
+        )?;
+        tx.commit().await.context("保存操作日志失败")?;
+        entries.push(entry);
+        Ok(())
+    }
+
+    async fn save_undone(&self, seq: i64, undone: bool) -> anyhow::Result<()> {
+        SUL_DB
+            .query_response(format!(
+                "UPDATE {} SET undone = {}",
+                record_key(&self.session, seq),
+                undone
+            ))
+            .await?;
+        Ok(())
+    }
+}
+
+static JOURNALS: Lazy<DashMap<String, Arc<OperationJournal>>> = Lazy::new(DashMap::new);
+
+/// 取会话的操作日志，首次使用时从数据库加载
+pub async fn journal_for(session: &str) -> anyhow::Result<Arc<OperationJournal>> {
+    if let Some(journal) = JOURNALS.get(session) {
+        return Ok(journal.clone());
+    }
+    let journal = Arc::new(OperationJournal::load(session).await?);
+    Ok(JOURNALS
+        .entry(session.to_string())
+        .or_insert(journal)
+        .clone())
+}
+
+/// 释放会话的内存日志（数据库中的记录保留）
+pub fn close_journal(session: &str) {
+    JOURNALS.remove(session);
+}
+
+/// 日志记录 id：`op_journal:[session, seq]`
+fn record_key(session: &str, seq: i64) -> String {
+    format!("{JOURNAL_TABLE}:[{}, {}]", sql_string(session), seq)
+}
+
+/// `refno` 在父节点可见子节点中的位置
+async fn visible_index(parent: RefnoEnum, refno: RefnoEnum) -> anyhow::Result<usize> {
+    let children = query_child_rows(parent).await?;
+    Ok(children
+        .iter()
+        .filter(|c| c.deleted != Some(true))
+        .position(|c| c.refno.refno() == refno.refno())
+        .unwrap_or(usize::MAX))
+}
+
+/// 删除的正向操作与逆操作
+fn delete_ops(report: &DeleteReport) -> (Vec<JournalOp>, Vec<JournalOp>) {
+    let mut forward = vec![JournalOp::SetDeleted {
+        refnos: report.deleted.clone(),
+        deleted: true,
+    }];
+    let mut inverse = vec![JournalOp::SetDeleted {
+        refnos: report.deleted.clone(),
+        deleted: false,
+    }];
+    if report.mode == DeleteMode::Nullify {
+        for r in &report.references {
+            if let InboundKind::Attr(attr) = &r.kind {
+                forward.push(JournalOp::SetAttrs {
+                    refno: r.from,
+                    attrs: BTreeMap::from([(attr.clone(), None)]),
+                });
+                inverse.push(JournalOp::SetAttrs {
+                    refno: r.from,
+                    attrs: BTreeMap::from([(
+                        attr.clone(),
+                        Some(NamedAttrValue::RefnoEnumType(r.to)),
+                    )]),
+                });
+            }
+        }
+    }
+    (forward, inverse)
+}
+
+/// 依次执行操作
+async fn apply_ops(ops: &[JournalOp]) -> anyhow::Result<()> {
+    for op in ops {
+        match op {
+            JournalOp::SetAttrs { refno, attrs } => {
+                let current = get_named_attmap(*refno).await?;
+                let mut tx = Transaction::new();
+                tx.extend(set_attrs_sql(*refno, attrs));
+                tx.extend(audit::attr_change_statements(
+                    *refno,
+                    attrs
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), current.map.get(k), v.as_ref())),
+                ));
+                tx.commit().await?;
+                clear_all_caches(*refno).await;
+                let names: Vec<&str> = attrs.keys().map(String::as_str).collect();
+                DERIVED_ATTRS.invalidate_attrs(*refno, &names);
+                if names.iter().any(|n| TRANSFORM_ATTRS.contains(n)) {
+                    invalidate_world_trans_cache_recursive(*refno).await?;
+                }
+            }
+            JournalOp::Move {
+                refno,
+                parent,
+                index,
+            } => {
+                hierarchy::move_element(*refno, *parent, *index).await?;
+            }
+            JournalOp::SetDeleted { refnos, deleted } => {
+                if refnos.is_empty() {
+                    continue;
+                }
+                SUL_DB
+                    .query_response(format!(
+                        "UPDATE [{}] SET deleted = {}",
+                        join_pe_keys(refnos.iter()),
+                        deleted
+                    ))
+                    .await?;
+                for &r in refnos {
+                    clear_all_caches(r).await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn set_attrs_sql(
+    refno: RefnoEnum,
+    attrs: &BTreeMap<String, Option<NamedAttrValue>>,
+) -> Vec<String> {
+    let values = |v: &Option<NamedAttrValue>| v.as_ref().map_or("NONE".to_string(), attr_value_sql);
+    let sets = attrs
+        .iter()
+        .map(|(k, v)| format!("{} = {}", attr_ident(k), values(v)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sqls = vec![format!(
+        "UPDATE (select value refno from only {} limit 1) SET {}",
+        refno.to_pe_key(),
+        sets
+    )];
+    if let Some(name) = attrs.get("NAME") {
+        sqls.push(format!(
+            "UPDATE {} SET name = {}",
+            refno.to_pe_key(),
+            values(name)
+        ));
+    }
+    sqls
+}
+
+/// 属性名对应的 SurrealQL 标识符，转义反引号，避免属性名拼出额外的语句
+fn attr_ident(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+/// 属性值对应的 SurrealQL 字面量
+fn attr_value_sql(value: &NamedAttrValue) -> String {
+    match value {
+        NamedAttrValue::InvalidType => "NONE".to_string(),
+        NamedAttrValue::LongType(v) => v.to_string(),
+        NamedAttrValue::RefnoEnumType(r) => r.to_pe_key(),
+        NamedAttrValue::RefU64Type(r) => r.to_pe_key(),
+        NamedAttrValue::ElementType(_) => value
+            .refno_value()
+            .map_or("NONE".to_string(), |r| r.to_pe_key()),
+        NamedAttrValue::RefU64Array(refnos) => format!("[{}]", join_pe_keys(refnos.iter())),
+        other => Into::<serde_json::Value>::into(other.clone()).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::rs_surreal::operation::delete::InboundRef;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefnoEnum::Refno(RefU64::from_two_nums(1, n))
+    }
+
+    #[test]
+    fn test_set_attrs_sql() {
+        let attrs = BTreeMap::from([
+            (
+                "DESC".to_string(),
+                Some(NamedAttrValue::StringType("A \"B\"".into())),
+            ),
+            (
+                "CREF".to_string(),
+                Some(NamedAttrValue::RefnoEnumType(refno(2))),
+            ),
+            ("NAME".to_string(), None),
+        ]);
+        let sqls = set_attrs_sql(refno(1), &attrs);
+        assert_eq!(
+            sqls[0],
+            r#"UPDATE (select value refno from only pe:1_1 limit 1) SET `CREF` = pe:1_2, `DESC` = "A \"B\"", `NAME` = NONE"#
+        );
+        assert_eq!(sqls[1], "UPDATE pe:1_1 SET name = NONE");
+
+        let attrs = BTreeMap::from([("A` = 1; DELETE pe; --".to_string(), None)]);
+        assert_eq!(
+            set_attrs_sql(refno(1), &attrs)[0],
+            r"UPDATE (select value refno from only pe:1_1 limit 1) SET `A\` = 1; DELETE pe; --` = NONE"
+        );
+    }
+
+    #[test]
+    fn test_delete_ops_restore_nullified_refs() {
+        let report = DeleteReport {
+            root: refno(1),
+            mode: DeleteMode::Nullify,
+            deleted: vec![refno(1), refno(2)],
+            references: vec![InboundRef {
+                from: refno(9),
+                to: refno(2),
+                kind: InboundKind::Attr("CREF".into()),
+            }],
+            committed: true,
+            ..Default::default()
+        };
+        let (forward, inverse) = delete_ops(&report);
+        assert_eq!(forward.len(), 2);
+        assert_eq!(
+            inverse[1],
+            JournalOp::SetAttrs {
+                refno: refno(9),
+                attrs: BTreeMap::from([(
+                    "CREF".to_string(),
+                    Some(NamedAttrValue::RefnoEnumType(refno(2)))
+                )]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_entry_json_roundtrip() {
+        let op = JournalOp::Move {
+            refno: refno(1),
+            parent: refno(2),
+            index: 3,
+        };
+        let json = serde_json::to_string(&vec![op.clone()]).unwrap();
+        let ops: Vec<JournalOp> = serde_json::from_str(&json).unwrap();
+        assert_eq!(ops, vec![op]);
+    }
+}
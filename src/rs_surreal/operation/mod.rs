@@ -1,2 +1,12 @@
+pub mod delete;
 pub mod geometry_op;
+pub mod journal;
+pub mod subtree;
 pub mod zone_update;
+
+pub use delete::{
+    DeleteMode, DeleteReport, InboundKind, InboundRef, delete_subtree, delete_subtree_with,
+    plan_delete_subtree,
+};
+pub use journal::{JournalEntry, JournalOp, OperationJournal, close_journal, journal_for};
+pub use subtree::{CloneOptions, CloneResult, allocate_refnos, clone_subtree};
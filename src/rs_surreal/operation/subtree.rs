@@ -0,0 +1,288 @@
+//! 子树复制
+//!
+//! [`clone_subtree`] 深拷贝一个元素及其全部（未删除的）子孙：
+//! - 按 [`allocate_refnos`] 分配新的参考号
+//! - 复制 pe、属性表，名称加后缀
+//! - 子树内部的引用（CREF、OWNER 等）改指向新元素，指向子树外的引用（如 SPRE）保持不变
+//! - 复制 inst_relate，世界变换、包围盒与区域等依赖位置的字段清空，待几何流程重新计算
+//! - 所有写入在一个事务中完成，新元素的属性同时写入审计表；新父节点的子节点被并发修改时重试
+
+use crate::pe::SPdmsElement;
+use crate::rs_surreal::audit;
+use crate::rs_surreal::hierarchy::{
+    ChildRow, MAX_ATTEMPTS, guard_children, is_concurrent_modified, place_child, query_child_rows,
+    rewrite_children,
+};
+use crate::rs_surreal::transaction::Transaction;
+use crate::rs_surreal::version::load_versions;
+use crate::types::{NamedAttrMap, NamedAttrValue};
+use crate::{RefU64, RefnoAllocator, RefnoEnum, SUL_DB, SurrealQueryExt, clear_all_caches};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 复制选项
+#[derive(Debug, Clone)]
+pub struct CloneOptions {
+    /// 加在非空名称后的后缀，为空时不改名
+    pub name_suffix: String,
+    /// 新参考号的 ref0，默认与根节点相同
+    pub ref0: Option<u32>,
+    /// 在新父节点可见子节点中的位置，默认追加到末尾
+    pub index: Option<usize>,
+    /// 是否复制 inst_relate
+    pub copy_geometry: bool,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        Self {
+            name_suffix: "-COPY".to_string(),
+            ref0: None,
+            index: None,
+            copy_geometry: true,
+        }
+    }
+}
+
+/// 复制结果
+#[derive(Debug, Clone, Default)]
+pub struct CloneResult {
+    /// 新子树的根
+    pub root: RefnoEnum,
+    /// 原参考号 -> 新参考号
+    pub mapping: BTreeMap<RefU64, RefU64>,
+}
+
+/// 在 `ref0` 下分配 `count` 个连续的新参考号
+///
+/// 与 [`RefnoAllocator`] 共用序列，序列首次使用时以现有 pe 中最大的 ref1 初始化
+pub async fn allocate_refnos(ref0: u32, count: usize) -> anyhow::Result<Vec<RefU64>> {
+    if count == 0 {
+        return Ok(vec![]);
+    }
+    let block = RefnoAllocator::reserve(ref0, count as u32).await?;
+    Ok(block.iter().collect())
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct ChildrenRow {
+    parent: RefnoEnum,
+    children: Vec<RefnoEnum>,
+}
+
+/// 逐层收集未删除的子孙，返回按层序排列的节点以及每个节点的有序子节点
+pub(super) async fn collect_subtree(
+    root: RefnoEnum,
+) -> anyhow::Result<(Vec<RefnoEnum>, HashMap<RefU64, Vec<RefnoEnum>>)> {
+    let mut nodes = vec![root];
+    let mut children_of = HashMap::new();
+    let mut frontier = vec![root];
+    while !frontier.is_empty() {
+        let keys = frontier
+            .iter()
+            .map(|r| r.to_pe_key())
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            "select id as parent, <-pe_owner[where in != none and !in.deleted].in as children from [{}]",
+            keys
+        );
+        let rows: Vec<ChildrenRow> = SUL_DB.query_take(&sql, 0).await?;
+        frontier.clear();
+        for row in rows {
+            nodes.extend(row.children.iter().copied());
+            frontier.extend(row.children.iter().copied());
+            children_of.insert(row.parent.refno(), row.children);
+        }
+    }
+    Ok((nodes, children_of))
+}
+
+/// 将属性中指向子树内的引用改为新参考号
+fn remap_refs(attmap: &mut NamedAttrMap, mapping: &BTreeMap<RefU64, RefU64>) {
+    let map_one = |r: RefnoEnum| match mapping.get(&r.refno()) {
+        Some(new) => RefnoEnum::Refno(*new),
+        None => r,
+    };
+    for val in attmap.map.values_mut() {
+        match val {
+            NamedAttrValue::RefU64Type(r) => {
+                if let Some(new) = mapping.get(r) {
+                    *r = *new;
+                }
+            }
+            NamedAttrValue::RefnoEnumType(r) => *r = map_one(*r),
+            NamedAttrValue::RefU64Array(refs) => {
+                for r in refs.iter_mut() {
+                    *r = map_one(*r);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 非空名称加后缀
+fn suffixed_name(name: &str, suffix: &str) -> Option<String> {
+    (!name.is_empty() && !suffix.is_empty()).then(|| format!("{}{}", name, suffix))
+}
+
+/// 将 `root` 子树复制到 `new_parent` 之下
+pub async fn clone_subtree(
+    root: RefnoEnum,
+    new_parent: RefnoEnum,
+    options: CloneOptions,
+) -> anyhow::Result<CloneResult> {
+    let (nodes, children_of) = collect_subtree(root).await?;
+    let loaded = load_versions(&nodes).await?;
+    let ref0 = options.ref0.unwrap_or(root.refno().get_0());
+    let new_refnos = allocate_refnos(ref0, nodes.len()).await?;
+    let mapping: BTreeMap<RefU64, RefU64> = nodes
+        .iter()
+        .map(|r| r.refno())
+        .zip(new_refnos.iter().copied())
+        .collect();
+    let new_root = RefnoEnum::Refno(mapping[&root.refno()]);
+
+    let mut tx = Transaction::new();
+    for (old, (pe, attmap)) in nodes.iter().zip(loaded) {
+        let new = RefnoEnum::Refno(mapping[&old.refno()]);
+        let owner = if *old == root {
+            new_parent
+        } else {
+            RefnoEnum::Refno(
+                mapping
+                    .get(&pe.owner.refno())
+                    .copied()
+                    .unwrap_or(pe.owner.refno()),
+            )
+        };
+        let new_name = suffixed_name(&attmap.get_name_or_default(), &options.name_suffix);
+
+        let mut attmap = attmap;
+        remap_refs(&mut attmap, &mapping);
+        attmap.insert("REFNO".into(), NamedAttrValue::RefnoEnumType(new));
+        attmap.insert("OWNER".into(), NamedAttrValue::RefnoEnumType(owner));
+        if let Some(name) = &new_name {
+            attmap.insert("NAME".into(), NamedAttrValue::StringType(name.clone()));
+        }
+        let json = attmap
+            .gen_sur_json()
+            .with_context(|| format!("生成 {} 的属性数据失败", old))?;
+        tx.push(format!("INSERT IGNORE INTO {} {}", pe.noun, json));
+        // 新元素的全部属性记为从未设置到复制值
+        tx.extend(audit::attr_change_statements(
+            new,
+            attmap
+                .map
+                .iter()
+                .filter(|(k, _)| k.as_str() != "REFNO")
+                .map(|(k, v)| (k.as_str(), None, Some(v))),
+        ));
+
+        let pe = SPdmsElement {
+            refno: new,
+            owner,
+            name: new_name.unwrap_or(pe.name),
+            children: None,
+            inst_relate_id: None,
+            tubi_id: None,
+            world_trans: None,
+            ..pe
+        };
+        tx.save_pe(&pe);
+
+        if options.copy_geometry {
+            tx.push(format!(
+                "INSERT RELATION INTO inst_relate (SELECT *, {} AS id, {} AS in, NONE AS world_trans, NONE AS aabb, NONE AS zone_refno FROM {})",
+                new.to_inst_relate_key(),
+                new.to_pe_key(),
+                old.to_inst_relate_key()
+            ));
+        }
+    }
+
+    // 子树内部的层级关系保持原有顺序
+    for (parent, children) in &children_of {
+        let Some(new_parent_refno) = mapping.get(parent) else {
+            continue;
+        };
+        let rows: Vec<ChildRow> = children
+            .iter()
+            .filter_map(|c| mapping.get(&c.refno()))
+            .map(|r| ChildRow {
+                refno: RefnoEnum::Refno(*r),
+                deleted: None,
+            })
+            .collect();
+        if !rows.is_empty() {
+            rewrite_children(&mut tx, RefnoEnum::Refno(*new_parent_refno), &rows);
+        }
+    }
+
+    // 新父节点的子节点在事务外读取，事务中校验未被并发修改，否则重新读取并重试
+    let mut attempt = 1;
+    loop {
+        let mut attempt_tx = tx.clone();
+        let mut siblings = query_child_rows(new_parent).await?;
+        guard_children(&mut attempt_tx, new_parent, &siblings);
+        place_child(&mut siblings, new_root, options.index.unwrap_or(usize::MAX));
+        rewrite_children(&mut attempt_tx, new_parent, &siblings);
+        match attempt_tx
+            .commit()
+            .await
+            .with_context(|| format!("复制 {} 到 {} 失败", root, new_parent))
+        {
+            Err(e) if attempt < MAX_ATTEMPTS && is_concurrent_modified(&e) => {
+                log::warn!("{}，第 {} 次重试", e, attempt);
+                attempt += 1;
+            }
+            result => break result?,
+        }
+    }
+    clear_all_caches(new_parent).await;
+
+    Ok(CloneResult {
+        root: new_root,
+        mapping,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap_refs() {
+        let inner = RefU64::from_two_nums(1, 10);
+        let outer = RefU64::from_two_nums(2, 20);
+        let mapping = BTreeMap::from([(inner, RefU64::from_two_nums(1, 99))]);
+
+        let mut attmap = NamedAttrMap::default();
+        attmap.insert("CREF".into(), NamedAttrValue::RefU64Type(inner));
+        attmap.insert("SPRE".into(), NamedAttrValue::RefU64Type(outer));
+        attmap.insert(
+            "LIST".into(),
+            NamedAttrValue::RefU64Array(vec![RefnoEnum::Refno(inner), RefnoEnum::Refno(outer)]),
+        );
+        remap_refs(&mut attmap, &mapping);
+
+        assert_eq!(
+            attmap.map["CREF"],
+            NamedAttrValue::RefU64Type(RefU64::from_two_nums(1, 99))
+        );
+        assert_eq!(attmap.map["SPRE"], NamedAttrValue::RefU64Type(outer));
+        assert_eq!(
+            attmap.map["LIST"],
+            NamedAttrValue::RefU64Array(vec![
+                RefnoEnum::Refno(RefU64::from_two_nums(1, 99)),
+                RefnoEnum::Refno(outer)
+            ])
+        );
+        assert_eq!(suffixed_name("/E1", "-COPY").as_deref(), Some("/E1-COPY"));
+        assert_eq!(suffixed_name("", "-COPY"), None);
+    }
+}
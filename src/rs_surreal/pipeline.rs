@@ -4,6 +4,9 @@ use anyhow::Result;
 use bevy_transform::components::Transform;
 use futures::future::try_join_all;
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
 
 use crate::{
     NamedAttrMap, NamedAttrValue, RefU64, RefnoEnum, get_named_attmap, parsed_data::CateAxisParam,
@@ -468,7 +471,7 @@ fn attr_to_vec3(attrs: &NamedAttrMap, key: &str) -> Option<Vec3> {
 }
 
 /// 分支属性（用于图纸标题栏等）
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BranchAttributes {
     /// 系统
     pub duty: Option<String>,
@@ -519,7 +522,7 @@ impl BranchAttributes {
 }
 
 /// 焊缝类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, SurrealValue)]
 pub enum WeldType {
     /// 对接焊
     Butt,
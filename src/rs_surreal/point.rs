@@ -2,6 +2,7 @@ use crate::basic::aabb::ParryAabb;
 use crate::parsed_data::{CateAxisParam, TubiInfoData};
 use crate::pdms_types::PdmsGenericType;
 use crate::rs_surreal::geometry_query::PlantTransform;
+use crate::types::TubiInfoKey;
 use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt};
 use bevy_transform::components::Transform;
 use dashmap::DashMap;
@@ -199,7 +200,7 @@ pub async fn query_tubi_info_by_ids(
     for chunk in ids.chunks(BATCH_SIZE) {
         let id_list: String = chunk
             .iter()
-            .map(|id| format!("tubi_info:⟨{}⟩", id))
+            .map(|id| TubiInfoKey::new(id.as_str()).to_string())
             .join(",");
         
         let sql = format!(
@@ -0,0 +1,491 @@
+//! SurrealDB 连接池
+//!
+//! `SUL_DB` 的实际类型。对外仍通过 `Deref` 暴露主连接（`connect` / `use_ns` / `query` 等
+//! 原有调用不受影响），而 `SurrealQueryExt::query_take` / `query_response` 走连接池：
+//! - 按轮询或最少在途请求选择连接
+//! - 连接断开时带退避地重建该连接，第 0 个连接重建后同时替换 [`SurrealPool::primary`]；
+//!   `Deref` 始终指向最初的主连接（`connect` / `use_ns` 等初始化调用作用于它），
+//!   重连后需要直接使用主连接的调用方（如 live query）应通过 `primary()` 获取
+//! - 内嵌引擎（`mem://`、`rocksdb://` 等）只使用主连接，多个连接会各自打开一份数据
+//! - 只读语句在连接错误后自动重试
+//! - 在 `project_registry::with_project` 内时，`SUL_DB` 的查询转发到对应项目
+
+use super::connection_manager::ConnectionConfig;
+use super::query_ext::{SurrealQueryExt, query_response_with_location};
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use parking_lot::RwLock;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use surrealdb::IndexedResults as Response;
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+use surrealdb::opt::QueryResult as SurrealQueryResult;
+use surrealdb::opt::auth::Root;
+use surrealdb::types::SurrealValue;
+use tracing::Instrument;
+
+/// 连接选择策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoolStrategy {
+    /// 轮询
+    RoundRobin,
+    /// 选择在途请求最少的连接
+    #[default]
+    LeastBusy,
+}
+
+/// 连接池参数
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    /// 连接数（含主连接）
+    pub size: usize,
+    /// 连接选择策略
+    pub strategy: PoolStrategy,
+    /// 只读语句遇到连接错误时的最大重试次数
+    pub max_retries: usize,
+    /// 单次重连的最大尝试次数
+    pub max_reconnect_attempts: usize,
+    /// 初始退避时间
+    pub initial_backoff: Duration,
+    /// 最大退避时间
+    pub max_backoff: Duration,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            strategy: PoolStrategy::default(),
+            max_retries: 2,
+            max_reconnect_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 池中的单个连接
+struct PoolSlot {
+    db: Surreal<Any>,
+    in_flight: AtomicUsize,
+    healthy: AtomicBool,
+}
+
+impl PoolSlot {
+    fn new(db: Surreal<Any>) -> Arc<Self> {
+        Arc::new(Self {
+            db,
+            in_flight: AtomicUsize::new(0),
+            healthy: AtomicBool::new(true),
+        })
+    }
+}
+
+/// 在途请求计数守卫
+struct InFlight(Arc<PoolSlot>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 连接池状态摘要
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStatus {
+    /// 连接数
+    pub size: usize,
+    /// 健康连接数
+    pub healthy: usize,
+    /// 在途请求总数
+    pub in_flight: usize,
+}
+
+/// SurrealDB 连接池
+pub struct SurrealPool {
+    /// 最初的主连接，`Deref` 目标
+    initial: Surreal<Any>,
+    /// 当前主连接；未重连时与 `initial` 共享会话，第 0 个连接重建后替换
+    primary: RwLock<Arc<Surreal<Any>>>,
+    slots: RwLock<Vec<Arc<PoolSlot>>>,
+    config: RwLock<Option<ConnectionConfig>>,
+    options: RwLock<PoolOptions>,
+    next: AtomicUsize,
+    reconnect_lock: tokio::sync::Mutex<()>,
+}
+
+impl Deref for SurrealPool {
+    type Target = Surreal<Any>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.initial
+    }
+}
+
+impl Default for SurrealPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SurrealPool {
+    /// 创建连接池，未调用 `init_pool` 前所有查询都走主连接
+    pub fn new() -> Self {
+        let initial = Surreal::init();
+        Self {
+            primary: RwLock::new(Arc::new(initial.clone())),
+            initial,
+            slots: RwLock::new(Vec::new()),
+            config: RwLock::new(None),
+            options: RwLock::new(PoolOptions::default()),
+            next: AtomicUsize::new(0),
+            reconnect_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 当前主连接，第 0 个连接重建后返回新的连接
+    pub fn primary(&self) -> Arc<Surreal<Any>> {
+        self.primary.read().clone()
+    }
+
+    /// 替换主连接，旧连接在最后一个持有者释放后关闭
+    fn replace_primary(&self, db: Surreal<Any>) {
+        *self.primary.write() = Arc::new(db);
+    }
+
+    /// 设置连接池参数，连接数变化在下次 `init_pool` 时生效
+    pub fn set_options(&self, options: PoolOptions) {
+        *self.options.write() = options;
+    }
+
+    /// 当前连接池参数
+    pub fn options(&self) -> PoolOptions {
+        self.options.read().clone()
+    }
+
+    /// 设置连接选择策略
+    pub fn set_strategy(&self, strategy: PoolStrategy) {
+        self.options.write().strategy = strategy;
+    }
+
+    /// 在主连接建立后初始化连接池
+    ///
+    /// 第 0 个连接与主连接共享底层会话，其余连接按 `config` 新建；
+    /// 内嵌引擎强制只有 1 个连接
+    pub async fn init_pool(&self, config: ConnectionConfig) -> Result<()> {
+        let mut size = self.options.read().size.max(1);
+        if size > 1 && is_embedded_engine(&config.host) {
+            warn!("{} 为内嵌引擎，连接池大小固定为 1", config.host);
+            size = 1;
+        }
+        let mut slots = vec![PoolSlot::new(self.primary().as_ref().clone())];
+        for i in 1..size {
+            let db = open_connection(&config)
+                .await
+                .with_context(|| format!("创建第 {} 个连接池连接失败", i + 1))?;
+            slots.push(PoolSlot::new(db));
+        }
+        info!(
+            "SurrealDB 连接池就绪: {} 个连接 -> {}",
+            slots.len(),
+            config.host
+        );
+        *self.slots.write() = slots;
+        *self.config.write() = Some(config);
+        Ok(())
+    }
+
+    /// 连接池状态
+    pub fn status(&self) -> PoolStatus {
+        let slots = self.slots.read();
+        PoolStatus {
+            size: slots.len(),
+            healthy: slots
+                .iter()
+                .filter(|s| s.healthy.load(Ordering::Relaxed))
+                .count(),
+            in_flight: slots
+                .iter()
+                .map(|s| s.in_flight.load(Ordering::Relaxed))
+                .sum(),
+        }
+    }
+
+    /// 选择一个连接，连接池为空时返回 None（使用主连接）
+    fn acquire(&self) -> Option<(usize, InFlight)> {
+        let slots = self.slots.read();
+        if slots.is_empty() {
+            return None;
+        }
+        let healthy: Vec<usize> = (0..slots.len())
+            .filter(|&i| slots[i].healthy.load(Ordering::Relaxed))
+            .collect();
+        let candidates: Vec<usize> = if healthy.is_empty() {
+            (0..slots.len()).collect()
+        } else {
+            healthy
+        };
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let idx = match self.options.read().strategy {
+            PoolStrategy::RoundRobin => candidates[start % candidates.len()],
+            PoolStrategy::LeastBusy => (0..candidates.len())
+                .map(|k| candidates[(start + k) % candidates.len()])
+                .min_by_key(|&i| slots[i].in_flight.load(Ordering::Relaxed))
+                .unwrap(),
+        };
+        let slot = slots[idx].clone();
+        slot.in_flight.fetch_add(1, Ordering::Relaxed);
+        Some((idx, InFlight(slot)))
+    }
+
+    /// 重建指定连接，按指数退避重试
+    async fn reconnect(&self, idx: usize, broken: &Arc<PoolSlot>) -> Result<()> {
+        let _guard = self.reconnect_lock.lock().await;
+        // 其他任务已经完成重连
+        if self
+            .slots
+            .read()
+            .get(idx)
+            .is_some_and(|s| !Arc::ptr_eq(s, broken))
+        {
+            return Ok(());
+        }
+        let Some(config) = self.config.read().clone() else {
+            anyhow::bail!("连接池未初始化，无法重连");
+        };
+        let options = self.options();
+        let mut delay = options.initial_backoff;
+        let mut last_err = None;
+        for attempt in 1..=options.max_reconnect_attempts.max(1) {
+            match open_connection(&config).await {
+                Ok(db) => {
+                    // 第 0 个连接与主连接共享会话，一并替换
+                    if idx == 0 {
+                        self.replace_primary(db.clone());
+                    }
+                    if let Some(slot) = self.slots.write().get_mut(idx) {
+                        *slot = PoolSlot::new(db);
+                    }
+                    info!("连接池第 {} 个连接重连成功（第 {} 次尝试）", idx, attempt);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!(
+                        "连接池第 {} 个连接重连失败（第 {} 次）: {}，{:?} 后重试",
+                        idx, attempt, e, delay
+                    );
+                    last_err = Some(e);
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(options.max_backoff);
+                }
+            }
+        }
+        Err(anyhow::anyhow!(
+            "连接池第 {} 个连接重连失败: {}",
+            idx,
+            last_err.map(|e| e.to_string()).unwrap_or_default()
+        ))
+    }
+
+    /// 重建主连接（第 0 个连接），供 live query 等直接使用主连接的调用方在断线后恢复
+    pub async fn reconnect_primary(&self) -> Result<()> {
+        let Some(broken) = self.slots.read().first().cloned() else {
+            anyhow::bail!("连接池未初始化，无法重连");
+        };
+        broken.healthy.store(false, Ordering::Relaxed);
+        self.reconnect(0, &broken).await
+    }
+
+    /// 在池中执行查询；连接错误时重建连接，只读语句自动重试
+    async fn execute(
+        &self,
+        sql: &str,
+        location: &'static std::panic::Location<'static>,
+    ) -> Result<Response> {
+        let max_retries = if is_idempotent_read(sql) {
+            self.options.read().max_retries
+        } else {
+            0
+        };
+        let mut attempt = 0;
+        loop {
+            let Some((idx, in_flight)) = self.acquire() else {
+                return query_response_with_location(&self.primary(), sql, location).await;
+            };
+            let result = query_response_with_location(&in_flight.0.db, sql, location).await;
+            let err = match result {
+                Ok(response) => return Ok(response),
+                Err(e) if is_connection_error(&e) => e,
+                Err(e) => return Err(e),
+            };
+            let slot = in_flight.0.clone();
+            drop(in_flight);
+            slot.healthy.store(false, Ordering::Relaxed);
+            warn!("连接池第 {} 个连接异常: {}", idx, err);
+            if let Err(e) = self.reconnect(idx, &slot).await {
+                error!("{}", e);
+            }
+            if attempt >= max_retries {
+                return Err(err);
+            }
+            attempt += 1;
+            info!("重试只读查询（第 {} 次） @ {}", attempt, location);
+        }
+    }
+}
+
+impl SurrealPool {
+    /// 在 `with_project` 内时把 `SUL_DB` 的查询转发到对应项目
+    async fn execute_routed(
+        &self,
+        sql: &str,
+        location: &'static std::panic::Location<'static>,
+    ) -> Result<Response> {
+        let start = std::time::Instant::now();
+        let span = tracing::debug_span!("surreal_query", %location);
+        let result = match super::project_registry::routed_pool(self) {
+            Some(pool) => pool.execute(sql, location).instrument(span).await,
+            None => self.execute(sql, location).instrument(span).await,
+        };
+        crate::metrics::observe_query(location, start.elapsed(), result.is_ok());
+        result
+    }
+}
+
+impl SurrealQueryExt for SurrealPool {
+    #[track_caller]
+    async fn query_response(&self, sql: impl AsRef<str>) -> Result<Response> {
+        let location = std::panic::Location::caller();
+        self.execute_routed(sql.as_ref(), location).await
+    }
+
+    #[track_caller]
+    async fn query_take<T>(&self, sql: impl AsRef<str>, index: usize) -> Result<T>
+    where
+        T: SurrealValue,
+        usize: SurrealQueryResult<T>,
+    {
+        let location = std::panic::Location::caller();
+        let sql_str = sql.as_ref();
+        let mut response = self.execute_routed(sql_str, location).await?;
+        response
+            .take::<T>(index)
+            .map_err(|e| {
+                error!("query_take error at {}: {}", location, e);
+                anyhow::Error::from(e)
+            })
+            .with_context(|| format!("SQL: {sql_str} @ {}", location))
+    }
+}
+
+/// 新建一个连接并完成 NS/DB 切换与登录
+async fn open_connection(config: &ConnectionConfig) -> Result<Surreal<Any>, surrealdb::Error> {
+    let db = Surreal::<Any>::init();
+    let surreal_config = surrealdb::opt::Config::default().ast_payload();
+    db.connect((&config.host as &str, surreal_config))
+        .with_capacity(1000)
+        .await?;
+    db.use_ns(&config.namespace)
+        .use_db(&config.database)
+        .await?;
+    db.signin(Root {
+        username: config.username.clone(),
+        password: config.password.clone(),
+    })
+    .await?;
+    Ok(db)
+}
+
+/// 是否为进程内的内嵌引擎，这类引擎每个连接各自打开一份数据，不能建多个连接
+pub(crate) fn is_embedded_engine(host: &str) -> bool {
+    let scheme = host.split("://").next().unwrap_or_default();
+    host.contains("://")
+        && matches!(
+            scheme.to_ascii_lowercase().as_str(),
+            "mem" | "memory" | "rocksdb" | "surrealkv" | "file" | "indxdb" | "speedb"
+        )
+}
+
+/// 是否为连接层面的错误（断线、未连接等），这类错误值得重连
+pub(crate) fn is_connection_error(err: &anyhow::Error) -> bool {
+    let msg = format!("{err:#}").to_ascii_lowercase();
+    [
+        "connection",
+        "websocket",
+        "uninitialised",
+        "uninitialized",
+        "broken pipe",
+        "channel closed",
+        "reset by peer",
+    ]
+    .iter()
+    .any(|k| msg.contains(k))
+}
+
+/// 语句是否为只读（不含任何写入 / 定义关键字），只有只读语句允许自动重试
+pub fn is_idempotent_read(sql: &str) -> bool {
+    const WRITE_KEYWORDS: [&str; 11] = [
+        "CREATE", "UPDATE", "UPSERT", "DELETE", "INSERT", "RELATE", "DEFINE", "REMOVE", "ALTER",
+        "REBUILD", "KILL",
+    ];
+    let upper = sql.to_ascii_uppercase();
+    !upper
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|word| WRITE_KEYWORDS.contains(&word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_idempotent_read() {
+        assert!(is_idempotent_read("SELECT * FROM pe WHERE noun = 'PIPE'"));
+        assert!(is_idempotent_read(
+            "LET $a = (SELECT value id FROM pe); RETURN $a"
+        ));
+        assert!(!is_idempotent_read("UPSERT pe:1 CONTENT {}"));
+        assert!(!is_idempotent_read("select 1; delete pe:1"));
+        // 字段名中包含关键字不视为写入
+        assert!(is_idempotent_read("SELECT updated_at, created FROM pe"));
+    }
+
+    #[test]
+    fn test_is_connection_error() {
+        assert!(is_connection_error(&anyhow::anyhow!(
+            "执行查询失败：Connection uninitialised"
+        )));
+        assert!(!is_connection_error(&anyhow::anyhow!(
+            "执行查询失败：Parse error"
+        )));
+    }
+
+    #[test]
+    fn test_is_embedded_engine() {
+        assert!(is_embedded_engine("mem://"));
+        assert!(is_embedded_engine("rocksdb://data/db"));
+        assert!(is_embedded_engine("surrealkv://data/db"));
+        assert!(!is_embedded_engine("ws://127.0.0.1:8000"));
+        assert!(!is_embedded_engine("127.0.0.1:8000"));
+    }
+
+    #[test]
+    fn test_replace_primary() {
+        let pool = SurrealPool::new();
+        let old = pool.primary();
+        pool.replace_primary(Surreal::init());
+        assert!(!Arc::ptr_eq(&old, &pool.primary()));
+        // 旧连接只由调用方持有，释放后即关闭
+        assert_eq!(Arc::strong_count(&old), 1);
+    }
+
+    #[test]
+    fn test_acquire_without_pool_uses_primary() {
+        let pool = SurrealPool::new();
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.status(), PoolStatus::default());
+    }
+}
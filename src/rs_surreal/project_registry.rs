@@ -0,0 +1,196 @@
+//! 多项目连接注册表
+//!
+//! 按名称登记任意数量的项目（机组）连接，每个项目一个 [`SurrealPool`]。
+//! [`with_project`] 在当前任务内把 `SUL_DB` 的 `query_take` / `query_response` 路由到指定项目，
+//! 因此 query / material / inst 等现有函数无需修改即可在其它项目上执行：
+//!
+//! ```no_run
+//! # async fn demo() -> anyhow::Result<()> {
+//! use aios_core::rs_surreal::project_registry::with_project;
+//!
+//! let pe = with_project("unit3", |_db| async {
+//!     aios_core::get_pe("17496/172806".into()).await
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 路由只作用于当前任务，`tokio::spawn` 出去的子任务不继承；
+//! 直接通过 `Deref` 调用 `SUL_DB.query(...)` 的代码也不会被路由。
+//! 进程内的属性缓存按参考号共享、不区分项目，参考号可能重叠时先调用 `cache::invalidate_all`。
+
+use super::SUL_DB;
+use super::connection_manager::ConnectionConfig;
+use super::pool::{PoolOptions, SurrealPool};
+use crate::options::{DbOption, ProjectDbOption};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::sync::Arc;
+use surrealdb::opt::auth::Root;
+
+/// 二号机组在注册表中的名称
+pub const SECOND_UNIT: &str = "second_unit";
+
+tokio::task_local! {
+    static CURRENT_PROJECT: (String, Arc<SurrealPool>);
+}
+
+/// 项目连接注册表
+#[derive(Default)]
+pub struct ProjectRegistry {
+    projects: DashMap<String, Arc<SurrealPool>>,
+}
+
+pub static PROJECT_REGISTRY: Lazy<ProjectRegistry> = Lazy::new(ProjectRegistry::default);
+
+impl ProjectRegistry {
+    /// 建立连接并登记，同名项目会被替换
+    pub async fn register(
+        &self,
+        name: impl Into<String>,
+        config: ConnectionConfig,
+        pool_size: usize,
+    ) -> anyhow::Result<Arc<SurrealPool>> {
+        let name = name.into();
+        let pool = SurrealPool::new();
+        let surreal_config = surrealdb::opt::Config::default().ast_payload();
+        pool.connect((&config.host as &str, surreal_config))
+            .with_capacity(1000)
+            .await?;
+        pool.use_ns(&config.namespace)
+            .use_db(&config.database)
+            .await?;
+        pool.signin(Root {
+            username: config.username.clone(),
+            password: config.password.clone(),
+        })
+        .await?;
+        pool.set_options(PoolOptions {
+            size: pool_size.max(1),
+            ..Default::default()
+        });
+        if let Err(e) = pool.init_pool(config).await {
+            log::warn!("项目 {} 连接池初始化失败，仅使用主连接: {}", name, e);
+        }
+        Ok(self.insert(name, pool))
+    }
+
+    /// 按配置项建立连接并登记
+    pub async fn register_option(
+        &self,
+        option: &ProjectDbOption,
+    ) -> anyhow::Result<Arc<SurrealPool>> {
+        self.register(
+            option.name.clone(),
+            ConnectionConfig::new(
+                option.get_version_db_conn_str(),
+                &option.surreal_ns,
+                &option.project_name,
+                &option.v_user,
+                &option.v_password,
+            ),
+            option.surreal_pool_size.unwrap_or(1),
+        )
+        .await
+    }
+
+    /// 登记已经建立好的连接池
+    pub fn insert(&self, name: impl Into<String>, pool: SurrealPool) -> Arc<SurrealPool> {
+        let pool = Arc::new(pool);
+        self.projects.insert(name.into(), pool.clone());
+        pool
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<SurrealPool>> {
+        self.projects.get(name).map(|p| p.clone())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.projects.contains_key(name)
+    }
+
+    /// 移除项目，已在使用中的连接在最后一个引用释放后关闭
+    pub fn remove(&self, name: &str) -> Option<Arc<SurrealPool>> {
+        self.projects.remove(name).map(|(_, p)| p)
+    }
+
+    /// 已登记的项目名，按名称排序
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.projects.iter().map(|e| e.key().clone()).collect();
+        names.sort();
+        names
+    }
+}
+
+/// 连接 DbOption `projects` 中配置的所有项目，返回成功登记的项目名
+///
+/// 单个项目连接失败只记录警告，不影响其它项目。
+pub async fn init_projects(db_option: &DbOption) -> Vec<String> {
+    let mut names = Vec::new();
+    for option in &db_option.projects {
+        match PROJECT_REGISTRY.register_option(option).await {
+            Ok(_) => names.push(option.name.clone()),
+            Err(e) => log::warn!("连接项目 {} 失败: {}", option.name, e),
+        }
+    }
+    names
+}
+
+/// 在指定项目上执行 `f`
+///
+/// `f` 内（同一任务中）所有经 `SUL_DB` 的 `query_take` / `query_response` 都会发往该项目，
+/// 参数 `db` 为该项目的连接池，也可直接使用。项目未登记时返回错误。
+pub async fn with_project<F, Fut, T>(name: &str, f: F) -> anyhow::Result<T>
+where
+    F: FnOnce(Arc<SurrealPool>) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let Some(pool) = PROJECT_REGISTRY.get(name) else {
+        anyhow::bail!(
+            "项目 {} 未登记，已登记: {:?}",
+            name,
+            PROJECT_REGISTRY.names()
+        );
+    };
+    CURRENT_PROJECT
+        .scope((name.to_string(), pool.clone()), f(pool))
+        .await
+}
+
+/// 当前任务所在的项目名，不在 [`with_project`] 内时为 None（即主项目）
+pub fn current_project() -> Option<String> {
+    CURRENT_PROJECT.try_with(|(name, _)| name.clone()).ok()
+}
+
+/// `SUL_DB` 查询实际使用的连接池：在 [`with_project`] 内时为该项目，否则为 None
+pub(crate) fn routed_pool(pool: &SurrealPool) -> Option<Arc<SurrealPool>> {
+    if !std::ptr::eq(pool, &*SUL_DB) {
+        return None;
+    }
+    CURRENT_PROJECT.try_with(|(_, p)| p.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_with_project_scope() {
+        PROJECT_REGISTRY.insert("unit_test", SurrealPool::new());
+        assert!(current_project().is_none());
+        let name = with_project("unit_test", |_db| async {
+            assert!(routed_pool(&SUL_DB).is_some());
+            Ok(current_project())
+        })
+        .await
+        .unwrap();
+        assert_eq!(name.as_deref(), Some("unit_test"));
+        assert!(routed_pool(&SUL_DB).is_none());
+
+        let err = with_project("missing", |_db| async { Ok(()) }).await;
+        assert!(err.unwrap_err().to_string().contains("missing"));
+        PROJECT_REGISTRY.remove("unit_test");
+    }
+}
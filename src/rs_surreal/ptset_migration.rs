@@ -0,0 +1,195 @@
+//! inst_info 的 ptset 存储格式迁移
+//!
+//! 按 [`PtsetEncoding`] 原地改写已有的 inst_info：
+//!
+//! - 目标 `Cbor`：JSON 数组（原始或压缩格式）转为 CBOR bytes
+//! - 目标 `Compact`：CBOR bytes 转回压缩 JSON，用于回退
+//!
+//! 注意：bytes 格式的 ptset 无法在 SurrealQL 中按字段过滤（如 `point` 模块中
+//! `select * from out.ptset where number=...` 的查询），迁移前需确认调用方只通过
+//! [`parse_ptset_auto`] 读取 ptset。
+
+use crate::rs_surreal::transaction::with_transaction;
+use crate::types::InstInfoKey;
+use crate::vec3_pool::{PtsetEncoding, parse_ptset_auto, ptset_select_expr, ptset_sql_value};
+use crate::{SUL_DB, SurrealQueryExt};
+use serde::Deserialize;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 迁移选项
+#[derive(Debug, Clone)]
+pub struct PtsetMigrationOptions {
+    /// 目标格式
+    pub target: PtsetEncoding,
+    /// 只统计大小，不修改数据库
+    pub dry_run: bool,
+    /// 每个事务处理的 inst_info 数
+    pub chunk_size: usize,
+}
+
+impl Default for PtsetMigrationOptions {
+    fn default() -> Self {
+        Self {
+            target: PtsetEncoding::Cbor,
+            dry_run: false,
+            chunk_size: 500,
+        }
+    }
+}
+
+/// 迁移结果
+#[derive(Debug, Clone, Default)]
+pub struct PtsetMigrationReport {
+    /// 需要转换的 inst_info 数
+    pub scanned: usize,
+    /// 已转换数
+    pub converted: usize,
+    /// 跳过的 (inst_info 键, 原因)
+    pub skipped: Vec<(String, String)>,
+    /// 转换前 ptset 的字节数（bytes 格式按原始字节计）
+    pub bytes_before: usize,
+    /// 转换后 ptset 的字节数
+    pub bytes_after: usize,
+}
+
+#[derive(Debug, Deserialize, SurrealValue)]
+struct PtsetRow {
+    key: String,
+    ptset: Option<serde_json::Value>,
+}
+
+/// 需要转换的记录条件
+fn pending_filter(target: PtsetEncoding) -> &'static str {
+    match target {
+        PtsetEncoding::Compact => "type::is_bytes(ptset)",
+        PtsetEncoding::Cbor => "type::is_array(ptset) AND array::len(ptset) > 0",
+    }
+}
+
+/// 存储占用：base64 字符串还原为原始字节数，其余按 JSON 长度
+fn stored_size(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::String(s) => s.len() / 4 * 3,
+        v => v.to_string().len(),
+    }
+}
+
+/// 迁移后的存储占用
+fn encoded_size(literal: &str, target: PtsetEncoding) -> usize {
+    match target {
+        PtsetEncoding::Compact => literal.len(),
+        PtsetEncoding::Cbor => {
+            literal
+                .len()
+                .saturating_sub(r#"encoding::base64::decode("")"#.len())
+                / 4
+                * 3
+        }
+    }
+}
+
+/// 转换全部需要迁移的 inst_info
+pub async fn migrate_inst_info_ptset(
+    options: &PtsetMigrationOptions,
+) -> anyhow::Result<PtsetMigrationReport> {
+    let sql = format!(
+        "SELECT VALUE <string> record::id(id) FROM inst_info WHERE {}",
+        pending_filter(options.target)
+    );
+    let keys: Vec<String> = SUL_DB.query_take(&sql, 0).await?;
+    let mut report = PtsetMigrationReport {
+        scanned: keys.len(),
+        ..Default::default()
+    };
+
+    for chunk in keys.chunks(options.chunk_size.max(1)) {
+        let targets = chunk
+            .iter()
+            .map(|k| InstInfoKey::new(k.as_str()).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "SELECT <string> record::id(id) AS key, {} AS ptset FROM [{targets}]",
+            ptset_select_expr("ptset")
+        );
+        let rows: Vec<PtsetRow> = SUL_DB.query_take(&sql, 0).await?;
+
+        let mut updates = vec![];
+        for row in rows {
+            let Some(value) = row.ptset else {
+                report.skipped.push((row.key, "缺少 ptset".into()));
+                continue;
+            };
+            let Some(params) = parse_ptset_auto(&value) else {
+                report.skipped.push((row.key, "ptset 无法解析".into()));
+                continue;
+            };
+            // 原始格式中的 refno 在压缩格式中不保留，与 save_inst_info 一致
+            let literal = ptset_sql_value(&params, false, options.target)?;
+            report.bytes_before += stored_size(&value);
+            report.bytes_after += encoded_size(&literal, options.target);
+            updates.push(format!(
+                "UPDATE {} SET ptset = {literal}",
+                InstInfoKey::new(row.key)
+            ));
+        }
+
+        if !options.dry_run && !updates.is_empty() {
+            with_transaction(|tx| {
+                for update in &updates {
+                    tx.push(update.as_str());
+                }
+                Ok(())
+            })
+            .await?;
+        }
+        report.converted += updates.len();
+    }
+
+    log::info!(
+        "inst_info ptset 迁移到 {:?}{}: 共 {} 个, 转换 {} 个, 跳过 {} 个, {} -> {} 字节",
+        options.target,
+        if options.dry_run {
+            "（试运行）"
+        } else {
+            ""
+        },
+        report.scanned,
+        report.converted,
+        report.skipped.len(),
+        report.bytes_before,
+        report.bytes_after
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsed_data::CateAxisParam;
+    use crate::shape::pdms_shape::RsVec3;
+    use glam::Vec3;
+
+    #[test]
+    fn test_encoded_size() {
+        let params = vec![CateAxisParam {
+            refno: Default::default(),
+            number: 1,
+            pt: RsVec3(Vec3::new(100.0, 0.0, 50.0)),
+            dir: Some(RsVec3(Vec3::Z)),
+            dir_flag: 1.0,
+            ref_dir: None,
+            pbore: 80.0,
+            pwidth: 0.0,
+            pheight: 0.0,
+            pconnect: "".to_string(),
+        }];
+        let cbor = ptset_sql_value(&params, false, PtsetEncoding::Cbor).unwrap();
+        let bytes = crate::vec3_pool::encode_ptset_cbor(&params, false).unwrap();
+        assert!(encoded_size(&cbor, PtsetEncoding::Cbor).abs_diff(bytes.len()) <= 2);
+
+        let compact = ptset_sql_value(&params, false, PtsetEncoding::Compact).unwrap();
+        assert!(bytes.len() < compact.len());
+    }
+}
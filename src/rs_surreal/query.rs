@@ -9,9 +9,13 @@
 //! - 批量操作
 
 use super::query_mdb_db_nums;
-use crate::consts::{MAX_INSERT_LENGTH, WORD_HASH};
+use super::bulk::BulkWriter;
+use crate::cache::ATTR_CACHES;
+use crate::cache::disk::read_through_attmap;
+use crate::rs_surreal::tiered_cache::{get_named_attmap_tiered, get_pe_tiered};
+use crate::consts::WORD_HASH;
 use crate::parsed_data::CateAxisParam;
-use crate::vec3_pool::parse_ptset_auto;
+use crate::vec3_pool::{parse_ptset_auto, ptset_select_expr};
 use crate::pdms_types::{CataHashRefnoKV, EleTreeNode, PdmsElement};
 use crate::pe::SPdmsElement;
 use crate::ssc_setting::PbsElement;
@@ -22,8 +26,7 @@ use crate::utils::{take_option, take_single, take_vec};
 use crate::{DBType, get_db_option, to_table_keys};
 use crate::{NamedAttrMap, RefU64};
 use crate::{SUL_DB, SurlValue, SurrealQueryExt};
-use crate::{graph::QUERY_DEEP_CHILDREN_REFNOS, types::*};
-use cached::Cached;
+use crate::types::*;
 use cached::proc_macro::cached;
 use chrono::NaiveDateTime;
 use dashmap::DashMap;
@@ -96,13 +99,11 @@ pub struct NounHierarchyItem {
 ///
 /// # 错误
 /// 如果查询失败，返回错误信息
-#[cached(result = true, size = 10000)]
 pub async fn get_pe(refno: RefnoEnum) -> anyhow::Result<Option<SPdmsElement>> {
-    let sql = format!(
-        r#"select * omit id from only {} limit 1;"#,
-        refno.to_pe_key()
-    );
-    SUL_DB.query_take::<Option<SPdmsElement>>(&sql, 0).await
+    ATTR_CACHES
+        .pes
+        .try_get_with(refno, get_pe_tiered(refno))
+        .await
 }
 
 /// 获取元素的默认名称
@@ -234,7 +235,7 @@ pub async fn get_refno_by_name(name: &str) -> anyhow::Result<Option<RefnoEnum>>
         r#"select value id from only pe where name="/{}" limit 1;"#,
         name
     );
-    println!("sql is {}", &sql);
+    crate::compat_debug!("get_refno_by_name sql is {}", &sql);
     let mut response: Response = SUL_DB.query_response(sql).await?;
     let s = response.take::<Option<RefnoEnum>>(0);
     Ok(s?)
@@ -687,7 +688,7 @@ pub async fn get_owner_refnos_by_types(
         "array::distinct(select value owner from [{}] where owner.noun IN [{}])",
         pe_keys, types_str
     );
-    println!("DEBUG: get_owner_refnos_by_types SQL: {}", sql);
+    crate::compat_debug!("get_owner_refnos_by_types SQL: {}", sql);
     SUL_DB.query_take::<Vec<Option<RefnoEnum>>>(&sql, 0).await
 }
 
@@ -1098,11 +1099,14 @@ pub async fn get_ui_named_attmap(refno_enum: RefnoEnum) -> anyhow::Result<NamedA
 }
 
 ///通过surql查询属性数据
-#[cached(result = true, size = 10000)]
 pub async fn get_named_attmap(refno: RefnoEnum) -> anyhow::Result<NamedAttrMap> {
-    let sql = format!(r#"(select * from {}.refno)[0];"#, refno.to_pe_key());
-    let named_attmap: Option<NamedAttrMap> = SUL_DB.query_take(&sql, 0).await?;
-    Ok(named_attmap.unwrap_or_default())
+    ATTR_CACHES
+        .attmaps
+        .try_get_with(
+            refno,
+            read_through_attmap(refno, get_named_attmap_tiered(refno)),
+        )
+        .await
 }
 
 #[cached(result = true, size = 5000)]
@@ -1158,10 +1162,11 @@ pub async fn get_default_full_name(refno: RefnoEnum) -> anyhow::Result<String> {
 pub(crate) async fn get_named_attmap_with_uda(
     refno_enum: RefnoEnum,
 ) -> anyhow::Result<NamedAttrMap> {
-    // 构建SQL查询语句，包含三个主要部分：
+    // 构建SQL查询语句，包含四个主要部分：
     // 1. 查询元素的基本属性和PE（Plant Element）信息
     // 2. 查询默认的UDA（用户定义属性）
-    // 3. 查询覆盖的UDA值
+    // 3. 查询 define_uda 定义的默认值
+    // 4. 查询覆盖的UDA值
     let sql = format!(
         r#"
         -- 1. 通过refno查询元素的完整名称和所有属性
@@ -1175,7 +1180,14 @@ pub(crate) async fn get_named_attmap_with_uda(
         from UDA
         where !UHIDE and {0}.noun in ELEL;
 
-        -- 3. 查询覆盖的UDA值
+        -- 3. 查询 define_uda 定义的默认值，同名时覆盖第 2 部分
+        select string::concat(':', name) as u,
+               default as v,
+               utype as t
+        from {2}
+        where !hidden and default != none and {0}.noun in nouns;
+
+        -- 4. 查询覆盖的UDA值
         -- 从ATT_UDA表中获取覆盖的UDA值
         select string::concat(':', if u.UDNA==none || string::len(u.UDNA)==0 {{ u.DYUDNA }} else {{ u.UDNA }}) as u,
                u.UTYP as t,
@@ -1184,7 +1196,8 @@ pub(crate) async fn get_named_attmap_with_uda(
         where u.UTYP != none;
         "#,
         refno_enum.to_pe_key(), // 转换为PE键名格式
-        refno_enum.refno()      // 获取参考号
+        refno_enum.refno(),     // 获取参考号
+        super::uda::UDA_DEF_TABLE
     );
 
     // 定义用于反序列化UDA键值对的结构体
@@ -1195,7 +1208,7 @@ pub(crate) async fn get_named_attmap_with_uda(
         v: SurlValue,
     }
 
-    // 执行查询并依次处理三个结果集
+    // 执行查询并依次处理四个结果集
     let mut response = SUL_DB.query_response(&sql).await?;
     let mut named_attmap = response
         .take::<Option<NamedAttrMap>>(0)?
@@ -1214,6 +1227,7 @@ pub(crate) async fn get_named_attmap_with_uda(
 
     apply_uda_entries(response.take(1)?);
     apply_uda_entries(response.take(2)?);
+    apply_uda_entries(response.take(3)?);
 
     Ok(named_attmap)
 }
@@ -1275,26 +1289,172 @@ fn extract_refno_enum(value: &NamedAttrValue) -> Option<RefnoEnum> {
     }
 }
 
-#[cached(result = true, size = 10000)]
 pub async fn get_cat_attmap(refno: RefnoEnum) -> anyhow::Result<NamedAttrMap> {
-    let sql = format!(
-        r#"
+    ATTR_CACHES
+        .cat_attmaps
+        .try_get_with(refno, async {
+            let sql = format!(
+                r#"
         (select value [{CATR_QUERY_STR}][where noun in ["SCOM", "SPRF", "SFIT", "JOIN", "SPCO"]].refno.*
         from only {} limit 1 fetch SCOM)[0] "#,
-        refno.to_pe_key()
-    );
-    let result: Option<NamedAttrMap> = SUL_DB.query_take(&sql, 0).await?;
-    Ok(result.unwrap_or_default())
+                refno.to_pe_key()
+            );
+            let result: Option<NamedAttrMap> = SUL_DB.query_take(&sql, 0).await?;
+            Ok(result.unwrap_or_default())
+        })
+        .await
+}
+
+/// 属性路径中的一段，如 `OWNER`、`DESP[2]`（下标从 1 开始）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrPathSegment {
+    pub name: String,
+    pub index: Option<usize>,
+}
+
+/// 解析属性路径
+///
+/// 支持点号写法 `OWNER.OWNER.NAME` 和 PML 写法 `NAME OF OWNER OF OWNER`，属性名不区分大小写。
+pub fn parse_attr_path(path: &str) -> anyhow::Result<Vec<AttrPathSegment>> {
+    let path = path.trim().to_uppercase();
+    let parts: Vec<&str> = if path.contains(" OF ") {
+        path.split(" OF ").rev().collect()
+    } else {
+        path.split('.').collect()
+    };
+    parts
+        .into_iter()
+        .map(|part| {
+            let part = part.trim();
+            let (name, index) = match part.split_once('[') {
+                Some((name, rest)) => {
+                    let idx = rest
+                        .strip_suffix(']')
+                        .and_then(|x| x.trim().parse::<usize>().ok())
+                        .filter(|&x| x > 0)
+                        .ok_or_else(|| anyhow::anyhow!("属性路径 '{}' 下标无效: {}", path, part))?;
+                    (name.trim(), Some(idx))
+                }
+                None => (part, None),
+            };
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == ':' || c == '_')
+            {
+                anyhow::bail!("属性路径 '{}' 中的属性名无效: '{}'", path, part);
+            }
+            Ok(AttrPathSegment {
+                name: name.to_string(),
+                index,
+            })
+        })
+        .collect()
+}
+
+/// 取数组类型属性的第 `index` 个值（从 1 开始）
+fn index_attr_value(value: &NamedAttrValue, index: usize) -> Option<NamedAttrValue> {
+    let i = index - 1;
+    Some(match value {
+        NamedAttrValue::F32VecType(v) => NamedAttrValue::F32Type(*v.get(i)?),
+        NamedAttrValue::Vec3Type(v) => NamedAttrValue::F32Type(*v.to_array().get(i)?),
+        NamedAttrValue::StringArrayType(v) => NamedAttrValue::StringType(v.get(i)?.clone()),
+        NamedAttrValue::IntArrayType(v) => NamedAttrValue::IntegerType(*v.get(i)?),
+        NamedAttrValue::BoolArrayType(v) => NamedAttrValue::BoolType(*v.get(i)?),
+        NamedAttrValue::RefU64Array(v) => NamedAttrValue::RefnoEnumType(*v.get(i)?),
+        _ => return None,
+    })
+}
+
+/// 沿引用属性（OWNER、SPRE、CATR 等）跳转到下一个元素
+async fn resolve_attr_path_ref(
+    refno: RefnoEnum,
+    attmap: &NamedAttrMap,
+    seg: &AttrPathSegment,
+) -> anyhow::Result<Option<RefnoEnum>> {
+    let target = match (seg.name.as_str(), seg.index) {
+        ("OWNER", None) => Some(attmap.get_owner()),
+        (name, Some(idx)) => attmap
+            .get_val(name)
+            .and_then(|v| index_attr_value(v, idx))
+            .as_ref()
+            .and_then(extract_refno_enum),
+        ("CATR", None) => match attmap.get_refno_by_att("CATR") {
+            Some(r) if r.is_valid() => Some(r),
+            // 元素自身没有 CATR 时按 SPRE 链查找
+            _ => get_cat_refno(refno).await?,
+        },
+        (name, None) => attmap.get_val(name).and_then(extract_refno_enum),
+    };
+    Ok(target.filter(|r| r.is_valid()))
+}
+
+/// 按属性路径查询属性值，如 `query_attr_path(refno, "OWNER.OWNER.NAME")`
+///
+/// 除最后一段外，每一段都必须是引用属性（OWNER、SPRE、CATR 或其他参考号属性，
+/// 数组引用可用下标，如 `CREF[1]`）。最后一段支持伪属性 `REFNO`、`TYPE`、`NAME`
+/// （元素没有命名时返回默认全名），其余按普通属性读取，也可带下标，如 `SPRE.CATR.PARA[2]`。
+pub async fn query_attr_path(refno: RefnoEnum, path: &str) -> anyhow::Result<NamedAttrValue> {
+    let segs = parse_attr_path(path)?;
+    let (last, refs) = segs
+        .split_last()
+        .ok_or_else(|| anyhow::anyhow!("属性路径为空"))?;
+
+    let mut cur = refno;
+    let mut attmap = get_named_attmap(cur).await?;
+    for seg in refs {
+        cur = resolve_attr_path_ref(cur, &attmap, seg)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("{} 的属性路径 '{}' 在 {} 处引用为空", refno, path, seg.name)
+            })?;
+        attmap = get_named_attmap(cur).await?;
+    }
+
+    let value = match last.name.as_str() {
+        "REFNO" => Some(NamedAttrValue::RefnoEnumType(cur)),
+        "TYPE" => Some(NamedAttrValue::StringType(attmap.get_type())),
+        "NAME" => match attmap.get_val("NAME") {
+            Some(v) => Some(v.clone()),
+            None => Some(NamedAttrValue::StringType(
+                get_default_full_name(cur).await?,
+            )),
+        },
+        name => attmap.get_val(name).cloned(),
+    };
+    let value = value.ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} 的属性路径 '{}': 元素 {} 没有属性 {}",
+            refno,
+            path,
+            cur,
+            last.name
+        )
+    })?;
+    match last.index {
+        Some(idx) => index_attr_value(&value, idx).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} 的属性路径 '{}': {}[{}] 越界或不是数组",
+                refno,
+                path,
+                last.name,
+                idx
+            )
+        }),
+        None => Ok(value),
+    }
 }
 
 /// 获取直接子节点的属性映射
 ///
 /// # 注意
 /// **已重构**: 现在使用 `collect_children_filter_attrs` 实现
-#[cached(result = true, size = 5000)]
 pub async fn get_children_named_attmaps(refno: RefnoEnum) -> anyhow::Result<Vec<NamedAttrMap>> {
     use crate::graph::collect_children_filter_attrs;
-    collect_children_filter_attrs(refno, &[]).await
+    ATTR_CACHES
+        .children_attmaps
+        .try_get_with(refno, collect_children_filter_attrs(refno, &[]))
+        .await
 }
 
 ///获取所有直接子节点的完整元素
@@ -1342,24 +1502,7 @@ pub async fn get_children_ele_nodes(refno: RefnoEnum) -> anyhow::Result<Vec<EleT
 }
 
 pub async fn clear_all_caches(refno: RefnoEnum) {
-    // crate::GET_WORLD_TRANSFORM.lock().await.cache_remove(&refno);
-    // 暂时注释掉，等待 cached 宏类型推断问题解决
-    // crate::GET_WORLD_TRANSFORM.lock().await.cache_clear();
-    // crate::GET_WORLD_MAT4.lock().await.cache_clear();
-    QUERY_ANCESTOR_REFNOS.lock().await.cache_remove(&refno);
-    QUERY_DEEP_CHILDREN_REFNOS.lock().await.cache_remove(&refno);
-    GET_PE.lock().await.cache_remove(&refno);
-    GET_TYPE_NAME.lock().await.cache_remove(&refno);
-    GET_SIBLINGS.lock().await.cache_remove(&refno);
-    GET_NAMED_ATTMAP.lock().await.cache_remove(&refno);
-    // GET_ANCESTOR_ATTMAPS.lock().await.cache_remove(&refno);
-    GET_NAMED_ATTMAP_WITH_UDA.lock().await.cache_remove(&refno);
-    GET_CHILDREN_REFNOS.lock().await.cache_remove(&refno);
-    GET_CHILDREN_NAMED_ATTMAPS.lock().await.cache_remove(&refno);
-    GET_CAT_ATTMAP.lock().await.cache_remove(&refno);
-    GET_CAT_REFNO.lock().await.cache_remove(&refno);
-    // GET_UI_NAMED_ATTMAP.lock().await.cache_remove(&refno);
-    GET_CHILDREN_PES.lock().await.cache_remove(&refno);
+    crate::cache::invalidate_refno(refno).await;
 }
 
 ///获得children
@@ -1412,13 +1555,14 @@ pub async fn query_group_by_cata_hash(
             r#"
             let $a = array::flatten(select value array::flatten([id, <-pe_owner.in]) from [{}])[? noun!=NONE && !deleted];
             select [cata_hash, type::record('inst_info', cata_hash).id!=none,
-                    type::record('inst_info', cata_hash).ptset] as k,
+                    {}] as k,
                  array::group(id) as v
             from $a where noun not in ["BRAN", "HANG"]  group by k;
         "#,
-            chunk.join(",")
+            chunk.join(","),
+            ptset_select_expr("type::record('inst_info', cata_hash).ptset")
         );
-        println!("query_group_by_cata_hash sql is {}", &sql);
+        crate::compat_debug!("query_group_by_cata_hash sql is {}", &sql);
         let mut response: Response = SUL_DB.query_response(sql).await?;
         // dbg!(&response);
         // 使用专门的结构体接收查询结果
@@ -1511,8 +1655,7 @@ pub async fn query_single_by_paths(
         ps.join(","),
         refno.to_pe_key()
     );
-    #[cfg(feature = "debug_model")]
-    println!("query_single_by_paths Sql is {}", sql);
+    crate::compat_debug!("query_single_by_paths Sql is {}", sql);
     let mut response: Response = SUL_DB.query_response(sql).await?;
     let mut map = response
         .take::<Option<NamedAttrMap>>(0)?
@@ -1528,7 +1671,7 @@ pub async fn query_single_by_paths(
 
 ///通过类型过滤所有的参考号
 pub async fn query_refnos_by_type(noun: &str, module: DBType) -> anyhow::Result<Vec<RefU64>> {
-    let mdb = crate::get_db_option().mdb_name.clone();
+    let mdb = crate::get_db_option()?.mdb_name.clone();
     let dbnums = query_mdb_db_nums(Some(mdb), module).await?;
     let sql = format!(
         r#"select value id from {} where dbnum in [{}]"#,
@@ -1546,37 +1689,29 @@ pub async fn insert_into_table(db: &Surreal<Any>, table: &str, value: &str) -> a
     Ok(())
 }
 
+/// 批量插入 pbs 元素，写入失败只记录日志（与旧实现一致，调用方不中断）
 pub async fn insert_pe_into_table_with_chunks(
     db: &Surreal<Any>,
     table: &str,
     value: Vec<PbsElement>,
 ) -> anyhow::Result<()> {
-    for r in value.chunks(MAX_INSERT_LENGTH) {
-        let json = r.iter().map(|x| x.gen_sur_json()).join(",");
-        let mut r = db
-            .query(format!("insert ignore into {} [{}]", table, json))
-            .await?;
-        let mut error = r.take_errors();
-        if !error.is_empty() {
-            dbg!(&error);
-        }
+    let json = value.iter().map(|x| x.gen_sur_json()).collect::<Vec<_>>();
+    if let Err(e) = BulkWriter::new(table).write_json(db, &json).await {
+        tracing::error!("写入 {} 失败: {:#}", table, e);
     }
     Ok(())
 }
 
+/// 批量插入记录，连接错误与语句错误（如字段类型不匹配）都会返回 Err
 pub async fn insert_into_table_with_chunks<T>(
     db: &Surreal<Any>,
     table: &str,
     value: Vec<T>,
 ) -> anyhow::Result<()>
 where
-    T: Sized + Serialize,
+    T: Sized + Serialize + Sync,
 {
-    for r in value.chunks(MAX_INSERT_LENGTH) {
-        let json = serde_json::to_string(r)?;
-        db.query(format!("insert ignore into {} {}", table, json))
-            .await?;
-    }
+    BulkWriter::new(table).write(db, &value).await?;
     Ok(())
 }
 
@@ -1830,8 +1965,7 @@ pub async fn query_noun_hierarchy(
                 name_filter_clause = name_filter_clause
             );
 
-            // 打印 SQL 以便调试
-            println!("执行 SQL:\n{}", sql);
+            crate::compat_debug!("执行 SQL:\n{}", sql);
 
             let mut items = SUL_DB.query_take::<Vec<NounHierarchyItem>>(&sql, 0).await?;
             aggregated_items.append(&mut items);
@@ -1865,8 +1999,7 @@ pub async fn query_noun_hierarchy(
             where_clause = where_clause
         );
 
-        // 打印 SQL 以便调试
-        println!("执行 SQL:\n{}", sql);
+        crate::compat_debug!("执行 SQL:\n{}", sql);
 
         SUL_DB.query_take::<Vec<NounHierarchyItem>>(&sql, 0).await
     }
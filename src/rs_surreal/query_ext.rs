@@ -36,7 +36,7 @@ pub async fn query_response(db: &Surreal<Any>, sql: impl AsRef<str>) -> Result<R
     query_response_with_location(db, sql, std::panic::Location::caller()).await
 }
 
-async fn query_response_with_location(
+pub(crate) async fn query_response_with_location(
     db: &Surreal<Any>,
     sql: impl AsRef<str>,
     location: &'static std::panic::Location<'static>,
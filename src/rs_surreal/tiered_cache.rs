@@ -0,0 +1,395 @@
+//! PE/属性记录的分层读取缓存
+//!
+//! 浏览模型时会反复读取同一批 pe 和属性记录，全部落到主库上压力很大。读取按以下顺序进行：
+//! 1. 进程内缓存 [`crate::cache::ATTR_CACHES`]
+//! 2. 内存 KV 库（启用 `mem-kv-save` 特性时为 [`SUL_MEM_DB`]，否则为 [`KV_DB`]）
+//! 3. 主库 [`SUL_DB`]
+//!
+//! 本模块只负责后两层：[`crate::get_pe`]、[`crate::get_named_attmap`] 在 `ATTR_CACHES`
+//! 未命中时经由这里读取。主库读到的记录写回 KV 层；读取期间若发生过失效（代数变化），
+//! 不再写回，避免把失效前读到的旧记录重新放进缓存。
+//!
+//! 一致性：本进程的写入经 [`crate::cache::invalidate_refno`] 清除各层，
+//! [`crate::cache::invalidate_all`] 清空各层；启用 `live` 特性时，
+//! 首次读取会启动 [`subscribe_invalidation`]，其他进程写入的 pe 变更同样触发失效。
+//! 各层命中次数记录在 [`TieredCacheStats`] 中，用于评估主库负载的下降。
+
+#[cfg(not(feature = "mem-kv-save"))]
+use super::KV_DB;
+#[cfg(feature = "mem-kv-save")]
+use super::SUL_MEM_DB;
+#[cfg(feature = "live")]
+use crate::live::supervisor::{LiveEvent, LiveQueryManager, LiveSubscription};
+use crate::pe::SPdmsElement;
+use crate::{NamedAttrMap, RefnoEnum, SUL_DB, SurrealQueryExt};
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "live")]
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+#[cfg(feature = "live")]
+use surrealdb::types as surrealdb_types;
+#[cfg(feature = "live")]
+use surrealdb::types::SurrealValue;
+
+/// KV 中 pe 缓存表
+pub const KV_PE_TABLE: &str = "tier_pe";
+/// KV 中属性缓存表
+pub const KV_ATTMAP_TABLE: &str = "tier_attmap";
+
+/// 缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TieredCacheConfig {
+    /// 是否使用内存 KV 层
+    pub kv_enabled: bool,
+    /// 首次读取时是否启动 pe 变更订阅（需 `live` 特性）
+    pub live_invalidation: bool,
+}
+
+impl Default for TieredCacheConfig {
+    fn default() -> Self {
+        Self {
+            kv_enabled: cfg!(feature = "mem-kv-save"),
+            live_invalidation: cfg!(feature = "live"),
+        }
+    }
+}
+
+/// 命中的缓存层
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheTier {
+    Kv,
+    Primary,
+}
+
+/// 各层命中统计
+#[derive(Debug, Default)]
+pub struct TieredCacheStats {
+    kv_hits: AtomicU64,
+    primary_reads: AtomicU64,
+    kv_errors: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// 统计快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TieredCacheStatsSnapshot {
+    pub kv_hits: u64,
+    pub primary_reads: u64,
+    pub kv_errors: u64,
+    pub invalidations: u64,
+}
+
+impl TieredCacheStatsSnapshot {
+    #[inline]
+    pub fn total_reads(&self) -> u64 {
+        self.kv_hits + self.primary_reads
+    }
+
+    /// 落到主库的读取比例，无读取时为 0
+    pub fn primary_ratio(&self) -> f64 {
+        match self.total_reads() {
+            0 => 0.0,
+            total => self.primary_reads as f64 / total as f64,
+        }
+    }
+}
+
+impl TieredCacheStats {
+    fn record(&self, tier: CacheTier) {
+        let counter = match tier {
+            CacheTier::Kv => &self.kv_hits,
+            CacheTier::Primary => &self.primary_reads,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TieredCacheStatsSnapshot {
+        TieredCacheStatsSnapshot {
+            kv_hits: self.kv_hits.load(Ordering::Relaxed),
+            primary_reads: self.primary_reads.load(Ordering::Relaxed),
+            kv_errors: self.kv_errors.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        for c in [
+            &self.kv_hits,
+            &self.primary_reads,
+            &self.kv_errors,
+            &self.invalidations,
+        ] {
+            c.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 内存 KV 层使用的数据库
+#[inline]
+fn kv_db() -> &'static Surreal<Any> {
+    #[cfg(feature = "mem-kv-save")]
+    {
+        &SUL_MEM_DB
+    }
+    #[cfg(not(feature = "mem-kv-save"))]
+    {
+        &KV_DB
+    }
+}
+
+#[inline]
+fn kv_key(table: &str, refno: RefnoEnum) -> String {
+    format!("{}:⟨{}⟩", table, refno)
+}
+
+/// 分层缓存
+pub struct TieredCache {
+    config: TieredCacheConfig,
+    stats: TieredCacheStats,
+    /// 失效代数，每次失效加一
+    generation: AtomicU64,
+}
+
+impl Default for TieredCache {
+    fn default() -> Self {
+        Self::new(TieredCacheConfig::default())
+    }
+}
+
+impl TieredCache {
+    pub fn new(config: TieredCacheConfig) -> Self {
+        Self {
+            config,
+            stats: TieredCacheStats::default(),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> &TieredCacheConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn stats(&self) -> TieredCacheStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    #[inline]
+    pub fn reset_stats(&self) {
+        self.stats.reset()
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// 读取 KV 层，出错时视为未命中
+    async fn kv_get<T: DeserializeOwned>(&self, table: &str, refno: RefnoEnum) -> Option<T> {
+        if !self.config.kv_enabled {
+            return None;
+        }
+        let sql = format!("select value json from only {}", kv_key(table, refno));
+        match kv_db().query_take::<Option<String>>(&sql, 0).await {
+            Ok(json) => json.and_then(|j| serde_json::from_str(&j).ok()),
+            Err(e) => {
+                self.stats.kv_errors.fetch_add(1, Ordering::Relaxed);
+                log::debug!("KV 缓存读取失败 {}: {}", refno, e);
+                None
+            }
+        }
+    }
+
+    /// 写回 KV 层，失败只记录
+    async fn kv_put<T: Serialize>(&self, table: &str, refno: RefnoEnum, value: &T) {
+        if !self.config.kv_enabled {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(value) else {
+            return;
+        };
+        let sql = format!(
+            "UPSERT {} CONTENT {{ json: {} }} RETURN NONE",
+            kv_key(table, refno),
+            serde_json::to_string(&json).unwrap_or_default()
+        );
+        if let Err(e) = kv_db().query_response(&sql).await {
+            self.stats.kv_errors.fetch_add(1, Ordering::Relaxed);
+            log::debug!("KV 缓存写入失败 {}: {}", refno, e);
+        }
+    }
+
+    /// 分层读取 pe 记录
+    pub async fn get_pe(&self, refno: RefnoEnum) -> anyhow::Result<Option<SPdmsElement>> {
+        let generation = self.generation();
+        if let Some(pe) = self.kv_get::<SPdmsElement>(KV_PE_TABLE, refno).await {
+            self.stats.record(CacheTier::Kv);
+            return Ok(Some(pe));
+        }
+        let sql = format!(
+            r#"select * omit id from only {} limit 1;"#,
+            refno.to_pe_key()
+        );
+        let pe = SUL_DB.query_take::<Option<SPdmsElement>>(&sql, 0).await?;
+        self.stats.record(CacheTier::Primary);
+        if let Some(pe) = &pe
+            && self.generation() == generation
+        {
+            self.kv_put(KV_PE_TABLE, refno, pe).await;
+        }
+        Ok(pe)
+    }
+
+    /// 分层读取属性
+    pub async fn get_named_attmap(&self, refno: RefnoEnum) -> anyhow::Result<NamedAttrMap> {
+        let generation = self.generation();
+        if let Some(attmap) = self.kv_get::<NamedAttrMap>(KV_ATTMAP_TABLE, refno).await {
+            self.stats.record(CacheTier::Kv);
+            return Ok(attmap);
+        }
+        let sql = format!(r#"(select * from {}.refno)[0];"#, refno.to_pe_key());
+        let attmap = SUL_DB
+            .query_take::<Option<NamedAttrMap>>(&sql, 0)
+            .await?
+            .unwrap_or_default();
+        self.stats.record(CacheTier::Primary);
+        if !attmap.map.is_empty() && self.generation() == generation {
+            self.kv_put(KV_ATTMAP_TABLE, refno, &attmap).await;
+        }
+        Ok(attmap)
+    }
+
+    /// 标记发生了失效，进行中的读取不再写回 KV 层
+    pub fn mark_stale(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.stats.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 清除 KV 层中的记录
+    pub async fn invalidate(&self, refno: RefnoEnum) -> anyhow::Result<()> {
+        self.mark_stale();
+        if self.config.kv_enabled {
+            let sql = format!(
+                "DELETE {}; DELETE {};",
+                kv_key(KV_PE_TABLE, refno),
+                kv_key(KV_ATTMAP_TABLE, refno)
+            );
+            kv_db().query_response(&sql).await?;
+        }
+        Ok(())
+    }
+
+    /// 清空 KV 层
+    pub async fn clear(&self) -> anyhow::Result<()> {
+        self.mark_stale();
+        if self.config.kv_enabled {
+            let sql = format!("DELETE {KV_PE_TABLE}; DELETE {KV_ATTMAP_TABLE};");
+            kv_db().query_response(&sql).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 全局分层缓存
+pub static TIERED_CACHE: Lazy<TieredCache> = Lazy::new(TieredCache::default);
+
+/// 通过全局分层缓存读取 pe
+#[inline]
+pub async fn get_pe_tiered(refno: RefnoEnum) -> anyhow::Result<Option<SPdmsElement>> {
+    #[cfg(feature = "live")]
+    ensure_live_invalidation();
+    TIERED_CACHE.get_pe(refno).await
+}
+
+/// 通过全局分层缓存读取属性
+#[inline]
+pub async fn get_named_attmap_tiered(refno: RefnoEnum) -> anyhow::Result<NamedAttrMap> {
+    #[cfg(feature = "live")]
+    ensure_live_invalidation();
+    TIERED_CACHE.get_named_attmap(refno).await
+}
+
+/// 全局分层缓存使用的失效订阅
+#[cfg(feature = "live")]
+static LIVE_INVALIDATION: once_cell::sync::OnceCell<LiveQueryManager> =
+    once_cell::sync::OnceCell::new();
+
+/// 启动全局失效订阅，只启动一次；不在 tokio 运行时中时跳过
+#[cfg(feature = "live")]
+fn ensure_live_invalidation() {
+    if !TIERED_CACHE.config().live_invalidation
+        || LIVE_INVALIDATION.get().is_some()
+        || tokio::runtime::Handle::try_current().is_err()
+    {
+        return;
+    }
+    LIVE_INVALIDATION.get_or_init(|| {
+        let mut manager = LiveQueryManager::default();
+        subscribe_invalidation(&mut manager);
+        manager
+    });
+}
+
+/// pe 实时订阅中用于失效的字段
+#[cfg(feature = "live")]
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+pub struct PeChange {
+    pub refno: RefnoEnum,
+}
+
+/// 订阅 pe 变更，清除对应记录的全部缓存（[`crate::cache::invalidate_refno`]）
+///
+/// 先同步标记失效，阻止进行中的读取写回旧记录，各层缓存在后台任务中清除。
+/// 断线补齐回放的记录同样会触发失效。
+#[cfg(feature = "live")]
+pub fn subscribe_invalidation(manager: &mut LiveQueryManager) {
+    manager.subscribe::<PeChange>(
+        LiveSubscription {
+            name: "tiered_cache".to_string(),
+            ..LiveSubscription::pe()
+        },
+        Arc::new(|event| {
+            let refno = match event {
+                LiveEvent::Live { data, .. } => data.refno,
+                LiveEvent::Replayed { data, .. } => data.refno,
+            };
+            TIERED_CACHE.mark_stale();
+            tokio::spawn(crate::cache::invalidate_refno(refno));
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_ratio() {
+        let stats = TieredCacheStats::default();
+        assert_eq!(stats.snapshot().primary_ratio(), 0.0);
+        stats.record(CacheTier::Primary);
+        stats.record(CacheTier::Kv);
+        stats.record(CacheTier::Kv);
+        stats.record(CacheTier::Kv);
+        let snap = stats.snapshot();
+        assert_eq!(snap.total_reads(), 4);
+        assert_eq!(snap.primary_ratio(), 0.25);
+        stats.reset();
+        assert_eq!(stats.snapshot(), TieredCacheStatsSnapshot::default());
+    }
+
+    #[test]
+    fn test_invalidation_bumps_generation() {
+        let cache = TieredCache::default();
+        let generation = cache.generation();
+        cache.mark_stale();
+        assert_eq!(cache.generation(), generation + 1);
+        assert_eq!(cache.stats().invalidations, 1);
+    }
+}
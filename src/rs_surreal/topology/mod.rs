@@ -4,6 +4,8 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use surrealdb::types::RecordId;
 
+pub mod pipe_network;
+
 /// Represents a previous connection of a pipe element
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct PrevConnection {
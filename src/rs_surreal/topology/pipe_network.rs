@@ -0,0 +1,367 @@
+//! 管网拓扑
+//!
+//! 由 BRAN 的成员顺序与 HREF / TREF / CREF 连接关系构建有向流向图：
+//! - 节点为管道元件，以及分支端部连接的管口等外部元素
+//! - 同一分支内相邻元件之间为管段边（[`PipeEdgeKind::Tube`]），方向从头到尾
+//! - 头部连接（HREF）从被连接元素指向首个元件，尾部连接（TREF）从末个元件指向被连接元素
+//! - 元件的 CREF 指向未加载的分支或其他元素时记为 [`PipeEdgeKind::Branch`]
+//!
+//! HREF / TREF 指向另一个已加载的 BRAN 时，分别接到该分支的末个 / 首个元件。
+
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, join_pe_keys};
+use petgraph::prelude::*;
+use petgraph::unionfind::UnionFind;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 默认的隔离元件类型
+pub const ISOLATION_NOUNS: [&str; 3] = ["VALV", "VTWA", "VFWA"];
+
+/// 管网节点
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipeNode {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    /// 所属分支，分支外的元素（如管口）为 None
+    pub bran: Option<RefnoEnum>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeEdgeKind {
+    /// 分支内相邻元件之间的管段
+    Tube,
+    /// HREF 连接
+    Head,
+    /// TREF 连接
+    Tail,
+    /// 元件 CREF 连接的支管
+    Branch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipeEdge {
+    pub kind: PipeEdgeKind,
+    pub bran: RefnoEnum,
+}
+
+/// 隔离边界：从某元件出发、不穿过隔离元件所能到达的区域
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IsolationBoundary {
+    /// 需要关闭的隔离元件
+    pub isolators: Vec<RefnoEnum>,
+    /// 边界内的元件，不含隔离元件
+    pub region: Vec<RefnoEnum>,
+}
+
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+pub(crate) struct BranchRow {
+    pub id: RefnoEnum,
+    pub href: Option<RefnoEnum>,
+    pub href_noun: Option<String>,
+    pub tref: Option<RefnoEnum>,
+    pub tref_noun: Option<String>,
+    pub members: Vec<RefnoEnum>,
+}
+
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+pub(crate) struct MemberRow {
+    pub id: RefnoEnum,
+    pub noun: String,
+    pub cref: Option<RefnoEnum>,
+    pub cref_noun: Option<String>,
+}
+
+/// 管网流向图
+#[derive(Debug, Clone, Default)]
+pub struct PipeNetwork {
+    pub graph: DiGraph<PipeNode, PipeEdge>,
+    pub node_indices: HashMap<RefnoEnum, NodeIndex>,
+}
+
+impl PipeNetwork {
+    fn node(&mut self, refno: RefnoEnum, noun: &str, bran: Option<RefnoEnum>) -> NodeIndex {
+        if let Some(&i) = self.node_indices.get(&refno) {
+            // 先作为外部元素加入的节点，之后补上所属分支
+            if bran.is_some() && self.graph[i].bran.is_none() {
+                self.graph[i].bran = bran;
+                self.graph[i].noun = noun.to_string();
+            }
+            return i;
+        }
+        let i = self.graph.add_node(PipeNode {
+            refno,
+            noun: noun.to_string(),
+            bran,
+        });
+        self.node_indices.insert(refno, i);
+        i
+    }
+
+    /// 由查询结果构建
+    pub(crate) fn build(branches: &[BranchRow], members: &[MemberRow]) -> Self {
+        let mut net = Self::default();
+        let members: HashMap<RefnoEnum, &MemberRow> = members.iter().map(|m| (m.id, m)).collect();
+        let loaded: HashMap<RefnoEnum, &BranchRow> = branches.iter().map(|b| (b.id, b)).collect();
+        let noun_of = |r: &RefnoEnum| members.get(r).map(|m| m.noun.as_str()).unwrap_or("");
+
+        for b in branches {
+            for &m in &b.members {
+                net.node(m, noun_of(&m), Some(b.id));
+            }
+        }
+        for b in branches {
+            let edge = |kind| PipeEdge { kind, bran: b.id };
+            for w in b.members.windows(2) {
+                let (from, to) = (net.node_indices[&w[0]], net.node_indices[&w[1]]);
+                net.graph.add_edge(from, to, edge(PipeEdgeKind::Tube));
+            }
+            let first = b.members.first().map(|r| net.node_indices[r]);
+            let last = b.members.last().map(|r| net.node_indices[r]);
+
+            // 头部：被连接元素 -> 首个元件
+            let head = b
+                .href
+                .filter(|r| !r.is_unset())
+                .and_then(|r| match loaded.get(&r) {
+                    Some(other) => other.members.last().map(|m| net.node_indices[m]),
+                    None => Some(net.node(r, b.href_noun.as_deref().unwrap_or(""), None)),
+                });
+            // 尾部：末个元件 -> 被连接元素
+            let tail = b
+                .tref
+                .filter(|r| !r.is_unset())
+                .and_then(|r| match loaded.get(&r) {
+                    Some(other) => other.members.first().map(|m| net.node_indices[m]),
+                    None => Some(net.node(r, b.tref_noun.as_deref().unwrap_or(""), None)),
+                });
+            match (first, last) {
+                (Some(first), Some(last)) => {
+                    if let Some(head) = head {
+                        net.graph.add_edge(head, first, edge(PipeEdgeKind::Head));
+                    }
+                    if let Some(tail) = tail {
+                        net.graph.add_edge(last, tail, edge(PipeEdgeKind::Tail));
+                    }
+                }
+                // 没有元件的分支只有一段直管
+                _ => {
+                    if let (Some(head), Some(tail)) = (head, tail) {
+                        net.graph.add_edge(head, tail, edge(PipeEdgeKind::Tube));
+                    }
+                }
+            }
+
+            for &m in &b.members {
+                let Some(row) = members.get(&m) else {
+                    continue;
+                };
+                let Some(cref) = row.cref.filter(|r| !r.is_unset()) else {
+                    continue;
+                };
+                // 已加载的分支由其 HREF / TREF 接入
+                if loaded.contains_key(&cref) {
+                    continue;
+                }
+                let to = net.node(cref, row.cref_noun.as_deref().unwrap_or(""), None);
+                let from = net.node_indices[&m];
+                net.graph.add_edge(from, to, edge(PipeEdgeKind::Branch));
+            }
+        }
+        net
+    }
+
+    fn bfs(&self, start: RefnoEnum, dir: Option<Direction>) -> Vec<RefnoEnum> {
+        let Some(&start) = self.node_indices.get(&start) else {
+            return vec![];
+        };
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        let mut out = vec![];
+        while let Some(node) = queue.pop_front() {
+            let neighbors: Vec<NodeIndex> = match dir {
+                Some(dir) => self.graph.neighbors_directed(node, dir).collect(),
+                None => self.graph.neighbors_undirected(node).collect(),
+            };
+            for next in neighbors {
+                if visited.insert(next) {
+                    out.push(self.graph[next].refno);
+                    queue.push_back(next);
+                }
+            }
+        }
+        out
+    }
+
+    /// 沿流向可到达的全部元素，按距离排序，不含自身
+    pub fn downstream_of(&self, refno: RefnoEnum) -> Vec<RefnoEnum> {
+        self.bfs(refno, Some(Direction::Outgoing))
+    }
+
+    /// 逆流向可到达的全部元素，按距离排序，不含自身
+    pub fn upstream_of(&self, refno: RefnoEnum) -> Vec<RefnoEnum> {
+        self.bfs(refno, Some(Direction::Incoming))
+    }
+
+    /// 不考虑流向的连通分量，按元素数从多到少排列
+    pub fn connected_components(&self) -> Vec<Vec<RefnoEnum>> {
+        let mut uf = UnionFind::new(self.graph.node_count());
+        for e in self.graph.edge_indices() {
+            let (a, b) = self.graph.edge_endpoints(e).unwrap();
+            uf.union(a.index(), b.index());
+        }
+        let mut groups: BTreeMap<usize, Vec<RefnoEnum>> = BTreeMap::new();
+        for i in self.graph.node_indices() {
+            groups
+                .entry(uf.find(i.index()))
+                .or_default()
+                .push(self.graph[i].refno);
+        }
+        let mut groups: Vec<_> = groups.into_values().collect();
+        groups.sort_by(|a, b| b.len().cmp(&a.len()));
+        groups
+    }
+
+    /// 以 [`ISOLATION_NOUNS`] 为隔离元件求隔离边界
+    pub fn isolation_boundary(&self, refno: RefnoEnum) -> IsolationBoundary {
+        self.isolation_boundary_by(refno, |n| ISOLATION_NOUNS.contains(&n.noun.as_str()))
+    }
+
+    /// 从 `refno` 出发不考虑流向扩展，遇到隔离元件即停止
+    ///
+    /// 起点本身是隔离元件时不视为边界
+    pub fn isolation_boundary_by(
+        &self,
+        refno: RefnoEnum,
+        is_isolator: impl Fn(&PipeNode) -> bool,
+    ) -> IsolationBoundary {
+        let Some(&start) = self.node_indices.get(&refno) else {
+            return IsolationBoundary::default();
+        };
+        let mut boundary = IsolationBoundary::default();
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        boundary.region.push(refno);
+        while let Some(node) = queue.pop_front() {
+            for next in self.graph.neighbors_undirected(node) {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let n = &self.graph[next];
+                if is_isolator(n) {
+                    boundary.isolators.push(n.refno);
+                } else {
+                    boundary.region.push(n.refno);
+                    queue.push_back(next);
+                }
+            }
+        }
+        boundary
+    }
+}
+
+/// 查询给定分支并构建管网
+pub async fn build_pipe_network(brans: &[RefnoEnum]) -> anyhow::Result<PipeNetwork> {
+    if brans.is_empty() {
+        return Ok(PipeNetwork::default());
+    }
+    let sql = format!(
+        r#"SELECT id, refno.HREF as href, refno.HREF.noun as href_noun,
+                  refno.TREF as tref, refno.TREF.noun as tref_noun,
+                  <-pe_owner[where in != none and !in.deleted].in as members
+           FROM [{}] WHERE noun = 'BRAN'"#,
+        join_pe_keys(brans.iter())
+    );
+    let branches: Vec<BranchRow> = SUL_DB.query_take(&sql, 0).await?;
+    let member_keys = join_pe_keys(branches.iter().flat_map(|b| b.members.iter()));
+    let members: Vec<MemberRow> = if member_keys.is_empty() {
+        vec![]
+    } else {
+        let sql = format!(
+            "SELECT id, noun, refno.CREF as cref, refno.CREF.noun as cref_noun FROM [{}]",
+            member_keys
+        );
+        SUL_DB.query_take(&sql, 0).await?
+    };
+    Ok(PipeNetwork::build(&branches, &members))
+}
+
+/// 查询 PIPE 下全部分支并构建管网
+pub async fn build_pipe_network_for_pipes(pipes: &[RefnoEnum]) -> anyhow::Result<PipeNetwork> {
+    let sql = format!(
+        "SELECT VALUE in FROM pe_owner WHERE out IN [{}] AND in.noun = 'BRAN' AND !in.deleted",
+        join_pe_keys(pipes.iter())
+    );
+    let brans: Vec<RefnoEnum> = SUL_DB.query_take(&sql, 0).await?;
+    build_pipe_network(&brans).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn r(n: u32) -> RefnoEnum {
+        RefnoEnum::Refno(RefU64::from_two_nums(1, n))
+    }
+
+    fn member(n: u32, noun: &str, cref: Option<u32>) -> MemberRow {
+        MemberRow {
+            id: r(n),
+            noun: noun.into(),
+            cref: cref.map(r),
+            cref_noun: cref.map(|_| "BRAN".into()),
+        }
+    }
+
+    /// 管口 100 -> 分支 10 (1 TEE, 2 VALV, 3 ELBO) -> 管口 101
+    /// TEE 1 引出分支 20 (4 VALV, 5 FLAN)，尾部接管口 102
+    fn sample() -> PipeNetwork {
+        let branches = vec![
+            BranchRow {
+                id: r(10),
+                href: Some(r(100)),
+                href_noun: Some("NOZZ".into()),
+                tref: Some(r(101)),
+                tref_noun: Some("NOZZ".into()),
+                members: vec![r(1), r(2), r(3)],
+            },
+            BranchRow {
+                id: r(20),
+                href: Some(r(1)),
+                href_noun: Some("TEE".into()),
+                tref: Some(r(102)),
+                tref_noun: Some("NOZZ".into()),
+                members: vec![r(4), r(5)],
+            },
+        ];
+        let members = vec![
+            member(1, "TEE", Some(20)),
+            member(2, "VALV", None),
+            member(3, "ELBO", None),
+            member(4, "VALV", None),
+            member(5, "FLAN", None),
+        ];
+        PipeNetwork::build(&branches, &members)
+    }
+
+    #[test]
+    fn test_flow_queries() {
+        let net = sample();
+        let sorted = |mut v: Vec<RefnoEnum>| {
+            v.sort();
+            v
+        };
+        assert_eq!(
+            sorted(net.downstream_of(r(1))),
+            [r(2), r(3), r(4), r(5), r(101), r(102)]
+        );
+        assert_eq!(net.upstream_of(r(5)), [r(4), r(1), r(100)]);
+        assert_eq!(net.connected_components().len(), 1);
+
+        let boundary = net.isolation_boundary(r(1));
+        assert_eq!(sorted(boundary.isolators), [r(2), r(4)]);
+        assert_eq!(sorted(boundary.region), [r(1), r(100)]);
+    }
+}
@@ -0,0 +1,313 @@
+//! 事务写入
+//!
+//! 保存一个完整元素通常要写 pe / attmap / inst_info / geo 等多张表，逐条执行时
+//! 中途失败会留下不完整的数据。[`with_transaction`] 将闭包中累积的语句包在
+//! `BEGIN TRANSACTION; ... COMMIT TRANSACTION;` 中一次提交，任一语句失败则整体回滚。
+//!
+//! ```rust,ignore
+//! use aios_core::rs_surreal::transaction::with_transaction;
+//!
+//! with_transaction(|tx| {
+//!     tx.save_pe(&pe);
+//!     tx.save_inst_info(&info);
+//!     tx.save_aabb(&aabb_hash, &aabb)?;
+//!     tx.set_inst_relate_aabb(refno, &aabb_hash);
+//!     Ok(())
+//! })
+//! .await?;
+//! ```
+
+use crate::geometry::EleGeosInfo;
+use crate::ssc_setting::PbsElement;
+use crate::table_const::PBS_TABLE;
+use crate::types::{InstGeoKey, RefnoEnum, SPdmsElement};
+use crate::utils::build_mesh_path;
+use crate::vec3_pool::PtsetEncoding;
+use crate::{SUL_DB, SurrealQueryExt};
+use anyhow::Context;
+use parry3d::bounding_volume::Aabb;
+use serde::Serialize;
+
+/// 事务语句构建器
+#[derive(Debug, Default, Clone)]
+pub struct Transaction {
+    statements: Vec<String>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 语句数量
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// 追加一条原始语句（末尾分号可省略）
+    pub fn push(&mut self, statement: impl Into<String>) -> &mut Self {
+        let statement = statement.into();
+        let trimmed = statement.trim().trim_end_matches(';').trim_end();
+        if !trimmed.is_empty() {
+            self.statements.push(trimmed.to_string());
+        }
+        self
+    }
+
+    /// 追加多条原始语句，如 `insert_relate_to_table` 使用的 RELATE 语句
+    pub fn extend<I, S>(&mut self, statements: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for s in statements {
+            self.push(s);
+        }
+        self
+    }
+
+    /// `INSERT IGNORE INTO table [..]`，记录需自带 id
+    pub fn insert_ignore<T: Serialize>(
+        &mut self,
+        table: &str,
+        records: &[T],
+    ) -> anyhow::Result<&mut Self> {
+        if !records.is_empty() {
+            let json = serde_json::to_string(records)?;
+            self.push(format!("INSERT IGNORE INTO {} {}", table, json));
+        }
+        Ok(self)
+    }
+
+    /// `UPSERT id CONTENT {..}`，`id` 形如 `table:⟨key⟩`
+    pub fn upsert_content<T: Serialize>(
+        &mut self,
+        id: &str,
+        content: &T,
+    ) -> anyhow::Result<&mut Self> {
+        let json = serde_json::to_string(content)?;
+        self.push(format!("UPSERT {} CONTENT {}", id, json));
+        Ok(self)
+    }
+
+    /// `DELETE target`
+    pub fn delete(&mut self, target: &str) -> &mut Self {
+        self.push(format!("DELETE {}", target))
+    }
+
+    // ------------------------------------------------------------------
+    // pe
+    // ------------------------------------------------------------------
+
+    /// 写入 pe 记录（id 为 `pe:⟨refno⟩`）
+    pub fn save_pe(&mut self, pe: &SPdmsElement) -> &mut Self {
+        let json = pe.gen_sur_json(Some(pe.refno.to_pe_key()));
+        self.push(format!("INSERT IGNORE INTO pe {}", json))
+    }
+
+    // ------------------------------------------------------------------
+    // inst
+    // ------------------------------------------------------------------
+
+    /// 写入 inst_info 记录（压缩 ptset 格式）
+    pub fn save_inst_info(&mut self, info: &EleGeosInfo) -> &mut Self {
+        self.push(format!(
+            "INSERT IGNORE INTO inst_info {}",
+            info.gen_sur_json_compact(false)
+        ))
+    }
+
+    /// 写入 inst_info 记录，ptset 按 `encoding` 存储
+    pub fn save_inst_info_encoded(
+        &mut self,
+        info: &EleGeosInfo,
+        encoding: PtsetEncoding,
+    ) -> anyhow::Result<&mut Self> {
+        let record = info.gen_sur_json_encoded(false, encoding)?;
+        Ok(self.push(format!("INSERT IGNORE INTO inst_info {}", record)))
+    }
+
+    /// 级联删除元素的实例数据：inst_geo、geo_relate、inst_info 以及 inst_relate
+    pub fn delete_inst_relate_cascade(&mut self, refno: RefnoEnum) -> &mut Self {
+        let inst_key = refno.to_inst_relate_key();
+        self.push(format!(
+            "DELETE array::flatten(SELECT VALUE [out, id, in] FROM {}->inst_info->geo_relate)",
+            inst_key
+        ));
+        self.delete(&inst_key)
+    }
+
+    // ------------------------------------------------------------------
+    // geom
+    // ------------------------------------------------------------------
+
+    /// 写入去重的 aabb 记录
+    pub fn save_aabb(&mut self, hash: &str, aabb: &Aabb) -> anyhow::Result<&mut Self> {
+        let json = format!(
+            "{{'id':aabb:⟨{}⟩, 'd':{}}}",
+            hash,
+            serde_json::to_string(aabb)?
+        );
+        Ok(self.push(format!("INSERT IGNORE INTO aabb {}", json)))
+    }
+
+    /// 在 inst_geo 上记录 mesh 文件路径（见 [`crate::mesh_store`]），mesh 本身不入库
+    ///
+    /// `mesh_path` 为原始精度，`lod_paths` 为 LOD 等级 -> 路径。
+    pub fn save_mesh_paths(
+        &mut self,
+        geo_hash: &str,
+        lod_levels: &[&str],
+    ) -> anyhow::Result<&mut Self> {
+        let lod_paths = lod_levels
+            .iter()
+            .map(|l| (l.to_string(), build_mesh_path(geo_hash, l)))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        Ok(self.push(format!(
+            "UPDATE {} SET mesh_path = {}, lod_paths = {}",
+            InstGeoKey::new(geo_hash),
+            serde_json::to_string(&build_mesh_path(geo_hash, ""))?,
+            serde_json::to_string(&lod_paths)?
+        )))
+    }
+
+    /// 写入去重的点集记录，`json` 为点集数据
+    pub fn save_pts(&mut self, id: u64, json: &str) -> &mut Self {
+        self.push(format!(
+            "INSERT IGNORE INTO vec3 {{'id':vec3:⟨{}⟩, 'd':{}}}",
+            id, json
+        ))
+    }
+
+    /// 更新 inst_relate 引用的 aabb
+    pub fn set_inst_relate_aabb(&mut self, refno: RefnoEnum, aabb_hash: &str) -> &mut Self {
+        self.push(format!(
+            "UPDATE {} SET aabb = aabb:⟨{}⟩",
+            refno.to_inst_relate_key(),
+            aabb_hash
+        ))
+    }
+
+    // ------------------------------------------------------------------
+    // pbs
+    // ------------------------------------------------------------------
+
+    /// 写入 pbs 节点
+    pub fn save_pbs_elements(&mut self, elements: &[PbsElement]) -> &mut Self {
+        if elements.is_empty() {
+            return self;
+        }
+        let json = elements
+            .iter()
+            .map(|e| e.gen_sur_json())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.push(format!("INSERT IGNORE INTO {} [{}]", PBS_TABLE, json))
+    }
+
+    /// 生成完整的事务 SQL
+    pub fn to_sql(&self) -> String {
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        for s in &self.statements {
+            sql.push_str(s);
+            sql.push_str(";\n");
+        }
+        sql.push_str("COMMIT TRANSACTION;");
+        sql
+    }
+
+    /// 在指定连接上提交事务，任一语句出错时返回错误（事务已整体回滚）
+    pub async fn commit_on(&self, db: &impl SurrealQueryExt) -> anyhow::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let sql = self.to_sql();
+        let mut response = db
+            .query_response(&sql)
+            .await
+            .with_context(|| format!("事务提交失败（{} 条语句）", self.len()))?;
+        check_errors(&mut response, self.len())
+    }
+
+    /// 在 `SUL_DB` 上提交事务
+    pub async fn commit(&self) -> anyhow::Result<()> {
+        self.commit_on(&*SUL_DB).await
+    }
+}
+
+/// 汇总事务响应中的语句错误
+fn check_errors(response: &mut surrealdb::IndexedResults, count: usize) -> anyhow::Result<()> {
+    let errors = response.take_errors();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let mut errors: Vec<_> = errors.into_iter().collect();
+    errors.sort_by_key(|(i, _)| *i);
+    let detail = errors
+        .iter()
+        .map(|(i, e)| format!("#{}: {}", i, e))
+        .collect::<Vec<_>>()
+        .join("; ");
+    anyhow::bail!("事务已回滚（{} 条语句）: {}", count, detail)
+}
+
+/// 在 `SUL_DB` 上以单个事务执行闭包中累积的语句
+///
+/// 闭包返回错误时不提交任何语句
+pub async fn with_transaction<F, R>(f: F) -> anyhow::Result<R>
+where
+    F: FnOnce(&mut Transaction) -> anyhow::Result<R>,
+{
+    let mut tx = Transaction::new();
+    let result = f(&mut tx)?;
+    tx.commit().await?;
+    Ok(result)
+}
+
+/// 同 [`with_transaction`]，使用指定连接
+pub async fn with_transaction_on<F, R>(db: &impl SurrealQueryExt, f: F) -> anyhow::Result<R>
+where
+    F: FnOnce(&mut Transaction) -> anyhow::Result<R>,
+{
+    let mut tx = Transaction::new();
+    let result = f(&mut tx)?;
+    tx.commit_on(db).await?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    #[test]
+    fn test_transaction_sql() {
+        let mut tx = Transaction::new();
+        tx.push("CREATE a:1;  ")
+            .push("   ")
+            .delete("b:2")
+            .set_inst_relate_aabb(RefnoEnum::Refno(RefU64::from_two_nums(1, 2)), "99");
+        assert_eq!(tx.len(), 3);
+
+        let sql = tx.to_sql();
+        assert!(sql.starts_with("BEGIN TRANSACTION;\nCREATE a:1;\nDELETE b:2;\n"));
+        assert!(sql.contains("SET aabb = aabb:⟨99⟩;"));
+        assert!(sql.ends_with("COMMIT TRANSACTION;"));
+    }
+
+    #[test]
+    fn test_insert_ignore_skips_empty() {
+        let mut tx = Transaction::new();
+        tx.insert_ignore::<u32>("t", &[]).unwrap();
+        assert!(tx.is_empty());
+        tx.insert_ignore("t", &[serde_json::json!({"id": 1})])
+            .unwrap();
+        assert_eq!(tx.statements[0], r#"INSERT IGNORE INTO t [{"id":1}]"#);
+    }
+}
@@ -3,14 +3,15 @@ use crate::pe::SPdmsElement;
 use crate::tool::db_tool::{db1_dehash, get_uda_index, is_uda};
 use crate::types::*;
 use crate::{NamedAttrMap, RefU64};
-use crate::{SUL_DB, SurlValue};
+use crate::{SUL_DB, SurlValue, SurrealQueryExt};
 use cached::proc_macro::cached;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::f32::consts::E;
 use std::sync::Mutex;
-use surrealdb::types::RecordId;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::{RecordId, SurrealValue};
 
 #[cached]
 pub async fn get_uda_refno(hash: i32) -> Option<RefU64> {
@@ -86,3 +87,335 @@ pub async fn get_uda_prop_as_string(hash: i32, prop: String) -> Option<String> {
     }
     None
 }
+
+// ----------------------------------------------------------------------------
+// UDA 定义管理
+// ----------------------------------------------------------------------------
+
+/// 自定义 UDA 定义表，与项目数据中的 `UDA` 元素表并存
+pub const UDA_DEF_TABLE: &str = "uda_def";
+
+/// UDA 类型，取值与 `UDA.UTYP` 一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum UdaType {
+    Text,
+    Real,
+    Int,
+    Bool,
+    Ref,
+    Word,
+    Dir,
+    Pos,
+    Ori,
+}
+
+impl UdaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UdaType::Text => "TEXT",
+            UdaType::Real => "REAL",
+            UdaType::Int => "INT",
+            UdaType::Bool => "BOOL",
+            UdaType::Ref => "REF",
+            UdaType::Word => "WORD",
+            UdaType::Dir => "DIR",
+            UdaType::Pos => "POS",
+            UdaType::Ori => "ORI",
+        }
+    }
+
+    /// 默认值是否与类型相符
+    fn accepts(&self, v: &serde_json::Value) -> bool {
+        use serde_json::Value as J;
+        // 数组 UDA 的默认值可以是同类型数组
+        if let (J::Array(items), false) = (
+            v,
+            matches!(self, UdaType::Dir | UdaType::Pos | UdaType::Ori),
+        ) {
+            return items.iter().all(|x| self.accepts(x));
+        }
+        match self {
+            UdaType::Text | UdaType::Word | UdaType::Ref => v.is_string(),
+            UdaType::Real => v.is_number(),
+            UdaType::Int => v.is_i64() || v.is_u64(),
+            UdaType::Bool => v.is_boolean(),
+            UdaType::Dir | UdaType::Pos | UdaType::Ori => {
+                matches!(v, J::Array(a) if a.len() == 3 && a.iter().all(|x| x.is_number()))
+                    || v.is_string()
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for UdaType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s.trim().to_uppercase().as_str() {
+            "TEXT" | "STRING" => UdaType::Text,
+            "REAL" => UdaType::Real,
+            "INT" | "INTEGER" => UdaType::Int,
+            "BOOL" | "LOG" | "LOGICAL" => UdaType::Bool,
+            "REF" | "ELEMENT" => UdaType::Ref,
+            "WORD" => UdaType::Word,
+            "DIR" | "DIRECTION" => UdaType::Dir,
+            "POS" | "POSITION" => UdaType::Pos,
+            "ORI" | "ORIENTATION" => UdaType::Ori,
+            other => anyhow::bail!("未知的 UDA 类型: {}", other),
+        })
+    }
+}
+
+/// UDA 定义来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UdaSource {
+    /// 项目数据中的 `UDA` 元素
+    Project,
+    /// 通过 [`define_uda`] 定义
+    Defined,
+}
+
+/// UDA 定义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UdaDefinition {
+    /// 属性名，不含 `:` 前缀
+    pub name: String,
+    pub utype: UdaType,
+    /// 适用的名词
+    pub nouns: Vec<String>,
+    /// 元素未设置时的默认值
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(skip, default = "default_uda_source")]
+    pub source: UdaSource,
+}
+
+fn default_uda_source() -> UdaSource {
+    UdaSource::Defined
+}
+
+impl UdaDefinition {
+    pub fn new(name: &str, utype: UdaType, nouns: &[&str]) -> Self {
+        Self {
+            name: normalize_uda_name(name),
+            utype,
+            nouns: nouns.iter().map(|n| n.trim().to_uppercase()).collect(),
+            default: None,
+            hidden: false,
+            description: None,
+            source: UdaSource::Defined,
+        }
+    }
+
+    pub fn with_default(mut self, default: serde_json::Value) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// 带 `:` 前缀的属性键，与 `get_named_attmap_with_uda` 中的键一致
+    pub fn attr_key(&self) -> String {
+        format!(":{}", self.name)
+    }
+
+    pub fn applies_to(&self, noun: &str) -> bool {
+        self.nouns.iter().any(|n| n.eq_ignore_ascii_case(noun))
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self.name.is_empty()
+                && self
+                    .name
+                    .bytes()
+                    .all(|c| c.is_ascii_alphanumeric() || c == b'_'),
+            "UDA 名称不合法: {:?}",
+            self.name
+        );
+        anyhow::ensure!(!self.nouns.is_empty(), "UDA {} 未指定适用的名词", self.name);
+        if let Some(v) = &self.default {
+            anyhow::ensure!(
+                self.utype.accepts(v),
+                "UDA {} 的默认值 {} 与类型 {} 不符",
+                self.name,
+                v,
+                self.utype.as_str()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 统一 UDA 名称：去掉 `:` 前缀并转为大写
+pub fn normalize_uda_name(name: &str) -> String {
+    name.trim().trim_start_matches(':').to_uppercase()
+}
+
+/// `UDA` 表与 `uda_def` 表共用的行结构
+#[derive(Debug, Deserialize, SurrealValue)]
+struct UdaDefRow {
+    name: Option<String>,
+    utype: Option<String>,
+    nouns: Option<Vec<String>>,
+    default: Option<SurlValue>,
+    hidden: Option<bool>,
+    description: Option<String>,
+}
+
+impl UdaDefRow {
+    /// 解析为定义，名称或类型缺失时返回 None
+    fn into_definition(self, source: UdaSource) -> Option<UdaDefinition> {
+        let name = normalize_uda_name(&self.name?);
+        if name.is_empty() {
+            return None;
+        }
+        let utype = self.utype?.parse().ok()?;
+        let default = self
+            .default
+            .map(|v| v.into_json_value())
+            .filter(|v| !v.is_null());
+        Some(UdaDefinition {
+            name,
+            utype,
+            nouns: self.nouns.unwrap_or_default(),
+            default,
+            hidden: self.hidden.unwrap_or(false),
+            description: self.description,
+            source,
+        })
+    }
+}
+
+/// 项目 `UDA` 元素映射到定义字段
+const PROJECT_UDA_FIELDS: &str = r#"
+    if UDNA==none || string::len(UDNA)==0 { DYUDNA } else { UDNA } as name,
+    UTYP as utype,
+    ELEL as nouns,
+    DFLT as default,
+    UHIDE as hidden,
+    none as description
+"#;
+
+/// 定义（或覆盖）一个 UDA 并写入 SurrealDB
+pub async fn define_uda(def: UdaDefinition) -> anyhow::Result<()> {
+    let mut def = def;
+    def.name = normalize_uda_name(&def.name);
+    def.nouns = def.nouns.iter().map(|n| n.trim().to_uppercase()).collect();
+    def.check()?;
+
+    let sql = format!(
+        "UPSERT {}:⟨{}⟩ CONTENT {}",
+        UDA_DEF_TABLE,
+        def.name,
+        serde_json::to_string(&def)?
+    );
+    let mut response = SUL_DB.query_response(&sql).await?;
+    if let Some((_, e)) = response.take_errors().into_iter().next() {
+        anyhow::bail!("保存 UDA {} 失败: {}", def.name, e);
+    }
+    clear_uda_attmap_cache().await;
+    Ok(())
+}
+
+/// 删除通过 [`define_uda`] 定义的 UDA，项目数据中的 UDA 不受影响
+pub async fn remove_uda(name: &str) -> anyhow::Result<()> {
+    let sql = format!("DELETE {}:⟨{}⟩", UDA_DEF_TABLE, normalize_uda_name(name));
+    SUL_DB.query_response(&sql).await?;
+    clear_uda_attmap_cache().await;
+    Ok(())
+}
+
+/// 查询单个 UDA 定义，自定义的优先于项目数据
+pub async fn get_uda_definition(name: &str) -> anyhow::Result<Option<UdaDefinition>> {
+    let name = normalize_uda_name(name);
+    let sql = format!(
+        r#"
+        select * from {UDA_DEF_TABLE} where name = $name;
+        select {PROJECT_UDA_FIELDS} from UDA where UDNA = $name or DYUDNA = $name;
+        "#
+    );
+    let mut response = SUL_DB.query(sql).bind(("name", name)).await?;
+    let defined: Vec<UdaDefRow> = response.take(0)?;
+    let project: Vec<UdaDefRow> = response.take(1)?;
+    Ok(defined
+        .into_iter()
+        .filter_map(|r| r.into_definition(UdaSource::Defined))
+        .chain(
+            project
+                .into_iter()
+                .filter_map(|r| r.into_definition(UdaSource::Project)),
+        )
+        .next())
+}
+
+/// 列出适用于 `noun` 的全部 UDA（含隐藏的），同名时自定义的覆盖项目数据，按名称排序
+pub async fn list_udas_for_noun(noun: &str) -> anyhow::Result<Vec<UdaDefinition>> {
+    let noun = noun.trim().to_uppercase();
+    let sql = format!(
+        r#"
+        select {PROJECT_UDA_FIELDS} from UDA where $noun in ELEL;
+        select * from {UDA_DEF_TABLE} where $noun in nouns;
+        "#
+    );
+    let mut response = SUL_DB.query(sql).bind(("noun", noun)).await?;
+    let project: Vec<UdaDefRow> = response.take(0)?;
+    let defined: Vec<UdaDefRow> = response.take(1)?;
+
+    let mut udas: BTreeMap<String, UdaDefinition> = BTreeMap::new();
+    for def in project
+        .into_iter()
+        .filter_map(|r| r.into_definition(UdaSource::Project))
+        .chain(
+            defined
+                .into_iter()
+                .filter_map(|r| r.into_definition(UdaSource::Defined)),
+        )
+    {
+        udas.insert(def.name.clone(), def);
+    }
+    Ok(udas.into_values().collect())
+}
+
+/// 定义变化后，已缓存的带 UDA 属性需要重新读取
+async fn clear_uda_attmap_cache() {
+    use cached::Cached;
+    crate::rs_surreal::query::GET_NAMED_ATTMAP_WITH_UDA
+        .lock()
+        .await
+        .cache_clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_uda_definition_check() {
+        let def = UdaDefinition::new(":mydesc", UdaType::Text, &["equi", "PIPE"])
+            .with_default(json!("N/A"));
+        assert_eq!(def.name, "MYDESC");
+        assert_eq!(def.attr_key(), ":MYDESC");
+        assert!(def.applies_to("EQUI"));
+        assert!(def.check().is_ok());
+
+        let bad = UdaDefinition::new("WEIGHT", UdaType::Real, &["EQUI"]).with_default(json!("1.0"));
+        assert!(bad.check().is_err());
+        let no_nouns = UdaDefinition::new("WEIGHT", UdaType::Real, &[]);
+        assert!(no_nouns.check().is_err());
+
+        assert!(UdaType::Pos.accepts(&json!([0.0, 1.0, 2.0])));
+        assert!(UdaType::Int.accepts(&json!([1, 2])));
+        assert!(!UdaType::Int.accepts(&json!(1.5)));
+        assert_eq!("logical".parse::<UdaType>().unwrap(), UdaType::Bool);
+    }
+}
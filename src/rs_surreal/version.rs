@@ -1,4 +1,15 @@
-use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt};
+use super::audit;
+use super::transaction::Transaction;
+use crate::types::{NamedAttrMap, NamedAttrValue, RefnoSesno, SPdmsElement};
+use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt, clear_all_caches};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 快照查询时每批处理的元素数
+const VERSION_CHUNK: usize = 500;
 
 /// 将数据备份到history
 pub async fn backup_data(
@@ -40,3 +51,384 @@ pub async fn backup_owner_relate(refnos: impl Iterator<Item = &RefU64>) -> anyho
     }
     Ok(())
 }
+
+// ----------------------------------------------------------------------------
+// 会话快照与回滚
+// ----------------------------------------------------------------------------
+
+/// 快照中的一个元素
+#[derive(Debug, Clone)]
+pub struct SnapshotEntry {
+    /// 该时刻生效的版本，最新版本为 `Refno`，历史版本为 `SesRef`
+    pub version: RefnoEnum,
+    /// 版本对应的 sesno
+    pub sesno: u32,
+    pub pe: SPdmsElement,
+    pub attmap: NamedAttrMap,
+}
+
+/// 某个 sesno 时刻的一致视图（pe + 属性）
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub sesno: u32,
+    /// 该时刻存在的元素；尚未创建或已删除的元素不在其中
+    pub entries: BTreeMap<RefU64, SnapshotEntry>,
+}
+
+impl Snapshot {
+    #[inline]
+    pub fn get(&self, refno: RefU64) -> Option<&SnapshotEntry> {
+        self.entries.get(&refno)
+    }
+
+    #[inline]
+    pub fn contains(&self, refno: RefU64) -> bool {
+        self.entries.contains_key(&refno)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+struct VersionRow {
+    id: RefnoEnum,
+    sesno: u32,
+    deleted: Option<bool>,
+}
+
+/// 在某元素的所有版本中选出 `sesno` 时刻生效的版本
+///
+/// 取 sesno 不大于目标值的最大版本；该版本已删除或不存在时返回 None
+fn pick_version(rows: &[VersionRow], sesno: u32) -> Option<&VersionRow> {
+    rows.iter()
+        .filter(|r| r.sesno <= sesno)
+        .max_by_key(|r| r.sesno)
+        .filter(|r| !r.deleted.unwrap_or(false))
+}
+
+/// 查询元素的全部版本（历史版本 + 当前版本），按 refno 分组
+async fn query_versions(refnos: &[RefU64]) -> anyhow::Result<BTreeMap<RefU64, Vec<VersionRow>>> {
+    let mut grouped: BTreeMap<RefU64, Vec<VersionRow>> = BTreeMap::new();
+    for chunk in refnos.chunks(VERSION_CHUNK) {
+        let pes: Vec<_> = chunk.iter().map(|r| r.to_pe_key()).collect();
+        let his: Vec<_> = chunk.iter().map(|r| r.to_table_key("his_pe")).collect();
+        let sql = format!(
+            "select id, sesno, deleted from array::flatten([{}].refnos), [{}];",
+            his.join(","),
+            pes.join(","),
+        );
+        let rows: Vec<VersionRow> = SUL_DB.query_take(&sql, 0).await?;
+        for row in rows {
+            grouped.entry(row.id.refno()).or_default().push(row);
+        }
+    }
+    Ok(grouped)
+}
+
+/// 批量读取指定版本的 pe 与属性，不经过缓存
+pub(crate) async fn load_versions(
+    versions: &[RefnoEnum],
+) -> anyhow::Result<Vec<(SPdmsElement, NamedAttrMap)>> {
+    let mut loaded = Vec::with_capacity(versions.len());
+    for chunk in versions.chunks(VERSION_CHUNK) {
+        let sql = chunk
+            .iter()
+            .map(|v| {
+                let key = v.to_pe_key();
+                format!("select * omit id from only {key} limit 1; (select * from {key}.refno)[0];")
+            })
+            .collect::<String>();
+        let mut response = SUL_DB.query_response(&sql).await?;
+        for (i, version) in chunk.iter().enumerate() {
+            let pe: Option<SPdmsElement> = response.take(i * 2)?;
+            let attmap: Option<NamedAttrMap> = response.take(i * 2 + 1)?;
+            let pe = pe.with_context(|| format!("版本 {} 的 pe 不存在", version.to_pe_key()))?;
+            loaded.push((pe, attmap.unwrap_or_default()));
+        }
+    }
+    Ok(loaded)
+}
+
+/// 生成 `sesno` 时刻 `refnos` 的快照
+///
+/// 每个元素取 sesno 不大于目标值的最新版本，pe 与属性来自同一版本
+pub async fn snapshot_at(sesno: u32, refnos: &[RefU64]) -> anyhow::Result<Snapshot> {
+    let grouped = query_versions(refnos).await?;
+    let picked: Vec<(RefU64, RefnoEnum, u32)> = grouped
+        .iter()
+        .filter_map(|(refno, rows)| pick_version(rows, sesno).map(|r| (*refno, r.id, r.sesno)))
+        .collect();
+    let versions: Vec<RefnoEnum> = picked.iter().map(|(_, v, _)| *v).collect();
+    let loaded = load_versions(&versions).await?;
+
+    let entries = picked
+        .into_iter()
+        .zip(loaded)
+        .map(|((refno, version, sesno), (pe, attmap))| {
+            (
+                refno,
+                SnapshotEntry {
+                    version,
+                    sesno,
+                    pe,
+                    attmap,
+                },
+            )
+        })
+        .collect();
+    Ok(Snapshot { sesno, entries })
+}
+
+/// 单个属性的差异
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrChange {
+    pub name: String,
+    /// 当前值
+    pub current: Option<NamedAttrValue>,
+    /// 回滚后的值
+    pub target: Option<NamedAttrValue>,
+}
+
+/// 回滚动作
+#[derive(Debug, Clone, PartialEq)]
+pub enum RollbackAction {
+    /// 目标时刻存在、之后被删除：恢复
+    Restore { changes: Vec<AttrChange> },
+    /// 目标时刻之后创建：软删除
+    Remove,
+    /// 属性被修改：写回旧值
+    Modify { changes: Vec<AttrChange> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollbackItem {
+    pub refno: RefU64,
+    pub noun: String,
+    pub action: RollbackAction,
+}
+
+/// 回滚差异报告
+#[derive(Debug, Clone, Default)]
+pub struct RollbackReport {
+    pub to_sesno: u32,
+    /// 为 true 时仅生成报告，未写入
+    pub dry_run: bool,
+    pub items: Vec<RollbackItem>,
+    /// 目标时刻与当前都不存在、无需处理的元素
+    pub skipped: Vec<RefU64>,
+}
+
+impl RollbackReport {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// 回滚选项
+#[derive(Debug, Clone, Default)]
+pub struct RollbackOptions {
+    /// 只生成差异报告，不写库
+    pub dry_run: bool,
+    /// 回滚作为新的会话提交：写入前先以该 sesno 调用 [`backup_data`] 备份当前版本。
+    /// 为 None 时直接覆盖当前版本，不留历史
+    pub new_sesno: Option<u32>,
+}
+
+/// 比较属性时忽略的键：版本信息与引用自身的键，随版本变化但不属于设计数据
+const DIFF_IGNORED_KEYS: &[&str] = &["REFNO", "SESNO", "id", "dbnum"];
+
+fn is_diff_key(key: &str) -> bool {
+    !DIFF_IGNORED_KEYS.contains(&key) && !key.starts_with("UDA:")
+}
+
+/// 计算从 `current` 回到 `target` 需要改动的属性
+fn diff_attmaps(current: &NamedAttrMap, target: &NamedAttrMap) -> Vec<AttrChange> {
+    let keys: BTreeSet<&String> = current
+        .map
+        .keys()
+        .chain(target.map.keys())
+        .filter(|k| is_diff_key(k))
+        .collect();
+    keys.into_iter()
+        .filter_map(|k| {
+            let cur = current.map.get(k);
+            let tgt = target.map.get(k);
+            (cur != tgt).then(|| AttrChange {
+                name: k.clone(),
+                current: cur.cloned(),
+                target: tgt.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// 将 `refnos` 回滚到 `to_sesno` 时刻的状态
+///
+/// 对比目标快照与当前状态生成差异报告，非 dry-run 时以单个事务写入补偿更新：
+/// 属性整体写回目标版本，之后创建的元素软删除，之后删除的元素恢复，属性变化同时写入审计表。
+/// 层级（owner）关系与 UDA 属性不在回滚范围内
+pub async fn rollback(
+    refnos: &[RefU64],
+    to_sesno: u32,
+    opts: RollbackOptions,
+) -> anyhow::Result<RollbackReport> {
+    let target = snapshot_at(to_sesno, refnos).await?;
+    let current = snapshot_at(u32::MAX, refnos).await?;
+
+    let mut report = RollbackReport {
+        to_sesno,
+        dry_run: opts.dry_run,
+        ..Default::default()
+    };
+    for refno in refnos {
+        let item = match (target.get(*refno), current.get(*refno)) {
+            (Some(t), Some(c)) => {
+                let changes = diff_attmaps(&c.attmap, &t.attmap);
+                if changes.is_empty() {
+                    continue;
+                }
+                RollbackItem {
+                    refno: *refno,
+                    noun: t.pe.noun.clone(),
+                    action: RollbackAction::Modify { changes },
+                }
+            }
+            (Some(t), None) => RollbackItem {
+                refno: *refno,
+                noun: t.pe.noun.clone(),
+                action: RollbackAction::Restore {
+                    changes: diff_attmaps(&NamedAttrMap::default(), &t.attmap),
+                },
+            },
+            (None, Some(c)) => RollbackItem {
+                refno: *refno,
+                noun: c.pe.noun.clone(),
+                action: RollbackAction::Remove,
+            },
+            (None, None) => {
+                report.skipped.push(*refno);
+                continue;
+            }
+        };
+        report.items.push(item);
+    }
+    if opts.dry_run || report.is_empty() {
+        return Ok(report);
+    }
+
+    let (removed, kept): (Vec<_>, Vec<_>) = report
+        .items
+        .iter()
+        .partition(|item| item.action == RollbackAction::Remove);
+    if let Some(sesno) = opts.new_sesno {
+        backup_data(kept.iter().map(|item| &item.refno), false, sesno).await?;
+        backup_data(removed.iter().map(|item| &item.refno), true, sesno).await?;
+    }
+
+    let mut tx = Transaction::new();
+    for item in &report.items {
+        let refno = RefnoEnum::Refno(item.refno);
+        let statements = match &item.action {
+            RollbackAction::Modify { changes } | RollbackAction::Restore { changes } => {
+                audit::attr_change_statements(
+                    refno,
+                    changes
+                        .iter()
+                        .map(|c| (c.name.as_str(), c.current.as_ref(), c.target.as_ref())),
+                )
+            }
+            // 软删除的元素记为全部属性被清空
+            RollbackAction::Remove => match current.get(item.refno) {
+                Some(c) => audit::attr_change_statements(
+                    refno,
+                    c.attmap
+                        .map
+                        .iter()
+                        .filter(|(k, _)| is_diff_key(k))
+                        .map(|(k, v)| (k.as_str(), Some(v), None)),
+                ),
+                None => vec![],
+            },
+        };
+        tx.extend(statements);
+    }
+    for item in &kept {
+        let entry = &target.entries[&item.refno];
+        let mut attmap = entry.attmap.clone();
+        attmap.insert(
+            "REFNO".into(),
+            NamedAttrValue::RefnoEnumType(RefnoEnum::Refno(item.refno)),
+        );
+        let json = attmap
+            .gen_sur_json_exclude(&["id"], None)
+            .with_context(|| format!("生成 {} 的属性数据失败", item.refno))?;
+        tx.push(format!(
+            "UPDATE {} CONTENT {}",
+            item.refno.to_table_key(&entry.pe.noun),
+            json
+        ));
+        tx.push(format!(
+            "UPDATE {} SET name = {}, deleted = false",
+            item.refno.to_pe_key(),
+            serde_json::to_string(&entry.pe.name)?
+        ));
+    }
+    // 指定 new_sesno 时 backup_data 已完成软删除
+    if opts.new_sesno.is_none() {
+        for item in &removed {
+            tx.push(format!(
+                "UPDATE {} SET deleted = true",
+                item.refno.to_pe_key()
+            ));
+        }
+    }
+    tx.commit()
+        .await
+        .with_context(|| format!("回滚到 sesno {} 失败", to_sesno))?;
+
+    for item in &report.items {
+        clear_all_caches(RefnoEnum::Refno(item.refno)).await;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(sesno: u32, deleted: bool) -> VersionRow {
+        VersionRow {
+            id: RefnoEnum::SesRef(RefnoSesno::new(RefU64::from_two_nums(1, 2), sesno)),
+            sesno,
+            deleted: Some(deleted),
+        }
+    }
+
+    #[test]
+    fn test_pick_version() {
+        let rows = vec![row(3, false), row(7, false), row(10, true)];
+        assert!(pick_version(&rows, 2).is_none());
+        assert_eq!(pick_version(&rows, 3).unwrap().sesno, 3);
+        assert_eq!(pick_version(&rows, 9).unwrap().sesno, 7);
+        assert!(pick_version(&rows, 12).is_none());
+    }
+
+    #[test]
+    fn test_diff_attmaps() {
+        let mut current = NamedAttrMap::default();
+        current.insert("NAME".into(), NamedAttrValue::StringType("/B".into()));
+        current.insert("SESNO".into(), NamedAttrValue::IntegerType(9));
+        current.insert("DESC".into(), NamedAttrValue::StringType("x".into()));
+        let mut target = NamedAttrMap::default();
+        target.insert("NAME".into(), NamedAttrValue::StringType("/A".into()));
+        target.insert("SESNO".into(), NamedAttrValue::IntegerType(3));
+
+        let changes = diff_attmaps(&current, &target);
+        let names: Vec<_> = changes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["DESC", "NAME"]);
+        assert_eq!(changes[0].target, None);
+        assert_eq!(
+            changes[1].target,
+            Some(NamedAttrValue::StringType("/A".into()))
+        );
+    }
+}
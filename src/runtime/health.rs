@@ -0,0 +1,85 @@
+//! 运行时健康状态
+//!
+//! 目前记录实时订阅（live query）的状态，由 [`crate::live::supervisor`] 维护，
+//! 供监控接口和 `facade::run_doctor` 读取。
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// 订阅状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubscriptionState {
+    /// 正在建立订阅
+    Connecting,
+    /// 订阅正常
+    Active,
+    /// 连接中断，等待重连
+    Reconnecting,
+    /// 已停止（主动关闭或超过最大重试次数）
+    Stopped,
+}
+
+/// 单个订阅的健康信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionHealth {
+    pub name: String,
+    pub state: SubscriptionState,
+    /// 累计重连次数
+    pub reconnects: u32,
+    /// 最近一次收到事件的时间
+    pub last_event: Option<SystemTime>,
+    /// 最近一次错误
+    pub last_error: Option<String>,
+    /// 已处理的会话水位（sesno）
+    pub watermark: i64,
+    /// 最近一次补齐回放的记录数
+    pub replayed: usize,
+}
+
+impl SubscriptionHealth {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            state: SubscriptionState::Connecting,
+            reconnects: 0,
+            last_event: None,
+            last_error: None,
+            watermark: 0,
+            replayed: 0,
+        }
+    }
+}
+
+static SUBSCRIPTIONS: Lazy<DashMap<String, SubscriptionHealth>> = Lazy::new(DashMap::new);
+
+/// 更新订阅的健康信息，不存在时先创建
+pub fn update_subscription_health(name: &str, f: impl FnOnce(&mut SubscriptionHealth)) {
+    let mut entry = SUBSCRIPTIONS
+        .entry(name.to_string())
+        .or_insert_with(|| SubscriptionHealth::new(name));
+    f(entry.value_mut());
+}
+
+/// 移除订阅记录
+pub fn remove_subscription_health(name: &str) {
+    SUBSCRIPTIONS.remove(name);
+}
+
+/// 全部订阅的健康信息，按名称排序
+pub fn subscription_health() -> Vec<SubscriptionHealth> {
+    let mut list = SUBSCRIPTIONS
+        .iter()
+        .map(|e| e.value().clone())
+        .collect::<Vec<_>>();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+/// 所有已登记的订阅是否都处于正常状态
+pub fn is_live_healthy() -> bool {
+    SUBSCRIPTIONS
+        .iter()
+        .all(|e| e.state == SubscriptionState::Active)
+}
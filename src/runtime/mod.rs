@@ -1,3 +1,5 @@
+pub mod health;
+
 use crate::init_surreal;
 use crate::options::DbOption;
 use crate::rs_surreal::SUL_DB;
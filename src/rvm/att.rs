@@ -0,0 +1,202 @@
+//! RVM 的属性文件（.att）解析
+//!
+//! 首行声明各分隔符，例如：
+//!
+//! ```text
+//! CADC_Attributes_File v1.0 , start: NEW , end: END , name_end: := , sep: &end&
+//! NEW /SITE-1
+//! Type := SITE &end&
+//! NEW /ZONE-1
+//! Type := ZONE &end&
+//! END
+//! END
+//! ```
+//!
+//! `NEW <名称>` 开始一个元素，`END` 结束，元素可以嵌套；
+//! `名称 := 值` 为属性，值可以跨行，直到遇到 `sep`。
+
+use anyhow::Context;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 属性文件中的一个元素
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttNode {
+    pub name: String,
+    /// 按出现顺序保存，属性名保持原样
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<AttNode>,
+}
+
+impl AttNode {
+    /// 按属性名查找（不区分大小写）
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// 属性文件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttFile {
+    pub roots: Vec<AttNode>,
+}
+
+impl AttFile {
+    /// 按元素名称建立索引，同名元素保留第一个
+    pub fn by_name(&self) -> HashMap<&str, &AttNode> {
+        fn visit<'a>(node: &'a AttNode, map: &mut HashMap<&'a str, &'a AttNode>) {
+            map.entry(node.name.as_str()).or_insert(node);
+            for c in &node.children {
+                visit(c, map);
+            }
+        }
+        let mut map = HashMap::new();
+        for r in &self.roots {
+            visit(r, &mut map);
+        }
+        map
+    }
+}
+
+/// 首行声明的分隔符
+struct Tokens {
+    start: String,
+    end: String,
+    name_end: String,
+    sep: Option<String>,
+}
+
+impl Default for Tokens {
+    fn default() -> Self {
+        Self {
+            start: "NEW".into(),
+            end: "END".into(),
+            name_end: ":=".into(),
+            sep: Some("&end&".into()),
+        }
+    }
+}
+
+impl Tokens {
+    fn from_header(line: &str) -> Self {
+        let mut t = Self::default();
+        for part in line.split(',').skip(1) {
+            let Some((k, v)) = part.split_once(':') else {
+                continue;
+            };
+            let v = v.trim().to_string();
+            match k.trim() {
+                "start" => t.start = v,
+                "end" => t.end = v,
+                "name_end" => t.name_end = v,
+                "sep" => t.sep = (!v.is_empty()).then_some(v),
+                _ => {}
+            }
+        }
+        t
+    }
+}
+
+pub fn read_att(path: impl AsRef<Path>) -> anyhow::Result<AttFile> {
+    let path = path.as_ref();
+    let bytes =
+        std::fs::read(path).with_context(|| format!("读取属性文件失败: {}", path.display()))?;
+    Ok(parse_att(&String::from_utf8_lossy(&bytes)))
+}
+
+/// 解析属性文件内容，未闭合的元素在文件结束时自动闭合
+pub fn parse_att(text: &str) -> AttFile {
+    let mut lines = text.lines().peekable();
+    let tokens = match lines.peek() {
+        Some(first) if first.starts_with("CADC_Attributes_File") => {
+            let t = Tokens::from_header(first);
+            lines.next();
+            t
+        }
+        _ => Tokens::default(),
+    };
+
+    let mut file = AttFile::default();
+    let mut stack: Vec<AttNode> = vec![];
+    // 尚未遇到 sep 的属性值
+    let mut pending: Option<(String, String)> = None;
+
+    let push_attr = |stack: &mut Vec<AttNode>, attr: (String, String)| {
+        if let Some(node) = stack.last_mut() {
+            node.attributes.push(attr);
+        }
+    };
+    let close = |stack: &mut Vec<AttNode>, file: &mut AttFile| {
+        if let Some(node) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => file.roots.push(node),
+            }
+        }
+    };
+    let starts_node = |line: &str| {
+        line.strip_prefix(tokens.start.as_str())
+            .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            .map(str::trim)
+    };
+
+    for raw in lines {
+        let line = raw.trim();
+        let structural = line == tokens.end
+            || starts_node(line).is_some()
+            || line.contains(tokens.name_end.as_str());
+        // 续行：没有 sep 的值一直延续到 sep 或下一个属性/元素
+        if let Some((key, mut value)) = pending.take() {
+            if !structural {
+                value.push('\n');
+                match tokens.sep.as_deref().and_then(|sep| raw.find(sep)) {
+                    Some(i) => {
+                        value.push_str(raw[..i].trim_end());
+                        push_attr(&mut stack, (key, value));
+                    }
+                    None => {
+                        value.push_str(raw.trim_end());
+                        pending = Some((key, value));
+                    }
+                }
+                continue;
+            }
+            push_attr(&mut stack, (key, value));
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+        if line == tokens.end {
+            close(&mut stack, &mut file);
+            continue;
+        }
+        if let Some(name) = starts_node(line) {
+            stack.push(AttNode {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some((key, value)) = line.split_once(tokens.name_end.as_str()) else {
+            continue;
+        };
+        let key = key.trim().to_string();
+        let value = value.trim_start();
+        match tokens.sep.as_deref().map(|sep| value.find(sep)) {
+            Some(None) => pending = Some((key, value.to_string())),
+            Some(Some(i)) => push_attr(&mut stack, (key, value[..i].trim_end().to_string())),
+            None => push_attr(&mut stack, (key, value.to_string())),
+        }
+    }
+    if let Some(attr) = pending {
+        push_attr(&mut stack, attr);
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut file);
+    }
+    file
+}
@@ -0,0 +1,470 @@
+//! 把 RVM 模型导入为 [`ShapeInstancesData`]
+//!
+//! 每个包含基本体的 `CNTB` 元素生成一个 [`EleGeosInfo`]，基本体转换为对应的
+//! [`PdmsGeoParam`]，实例变换为文件中的变换（换算到 mm）再乘以基本体的单位化变换，
+//! 因此元素的 `world_transform` 为单位矩阵。
+//!
+//! 元素的参考号和类型优先取属性文件中的 `REF`、`TYPE`，
+//! 没有属性文件时按顺序在 [`RVM_DBNUM`] 下分配参考号。
+//! 斜切的圆台（snout 的 shear）按不斜切处理，线（line）不导入。
+
+use super::att::{AttFile, AttNode, read_att};
+use super::reader::{RvmFile, RvmGroup, RvmPrim, RvmPrimKind, read_rvm};
+use crate::geometry::{EleGeosInfo, EleInstGeo, EleInstGeosData, GeoBasicType, ShapeInstancesData};
+use crate::parsed_data::geo_params_data::PdmsGeoParam;
+use crate::pdms_types::PdmsGenericType;
+use crate::prim_geo::polyhedron::{Polygon, Polyhedron};
+use crate::prim_geo::{CTorus, Dish, LSnout, Pyramid, RTorus, SBox, SCylinder, Sphere};
+use crate::shape::pdms_shape::BrepShapeTrait;
+use crate::{RefU64, RefnoEnum};
+use bevy_transform::components::Transform;
+use glam::{Mat4, Vec3};
+use parry3d::bounding_volume::{Aabb, BoundingVolume};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// 没有 `REF` 属性时分配参考号使用的库号
+pub const RVM_DBNUM: u32 = 999_999;
+
+/// RVM 变换输出 m，几何管线使用 mm
+const M_TO_MM: f32 = 1000.0;
+
+/// 导入后的元素层级
+#[derive(Debug, Clone, Default)]
+pub struct RvmElement {
+    pub refno: RefnoEnum,
+    /// 根元素为默认值
+    pub owner: RefnoEnum,
+    pub name: String,
+    pub noun: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+/// 导入结果
+#[derive(Debug, Default)]
+pub struct RvmImport {
+    pub shapes: ShapeInstancesData,
+    /// 按深度优先顺序排列
+    pub elements: Vec<RvmElement>,
+    /// 不支持而跳过的基本体数
+    pub skipped_prims: usize,
+}
+
+impl RvmImport {
+    pub fn children(&self, owner: RefnoEnum) -> impl Iterator<Item = &RvmElement> {
+        self.elements.iter().filter(move |e| e.owner == owner)
+    }
+}
+
+/// 读取 RVM 文件，同目录下同名的 `.att` 文件存在时一并读取
+pub fn import_rvm(path: impl AsRef<Path>) -> anyhow::Result<RvmImport> {
+    let path = path.as_ref();
+    let rvm = read_rvm(path)?;
+    let att_path = path.with_extension("att");
+    let att = if att_path.exists() {
+        Some(read_att(&att_path)?)
+    } else {
+        None
+    };
+    Ok(convert_rvm(&rvm, att.as_ref()))
+}
+
+/// 把解析好的 RVM 转换为实例数据
+pub fn convert_rvm(rvm: &RvmFile, att: Option<&AttFile>) -> RvmImport {
+    let att_index = att.map(|a| a.by_name()).unwrap_or_default();
+    let mut ctx = ImportCtx {
+        att: att_index,
+        next_id: 1,
+        out: RvmImport::default(),
+    };
+    for root in &rvm.roots {
+        ctx.visit(root, RefnoEnum::default(), "", PdmsGenericType::UNKOWN);
+    }
+    ctx.out
+}
+
+struct ImportCtx<'a> {
+    att: HashMap<&'a str, &'a AttNode>,
+    next_id: u32,
+    out: RvmImport,
+}
+
+impl ImportCtx<'_> {
+    fn visit(
+        &mut self,
+        group: &RvmGroup,
+        owner: RefnoEnum,
+        owner_type: &str,
+        owner_generic: PdmsGenericType,
+    ) {
+        let att = self.att.get(group.name.as_str()).copied();
+        let refno = att
+            .and_then(|a| a.get("REF"))
+            .and_then(|r| RefU64::from_str(r).ok())
+            .filter(|r| r.is_valid())
+            .map(RefnoEnum::from)
+            .unwrap_or_else(|| {
+                self.next_id += 1;
+                RefU64::from_two_nums(RVM_DBNUM, self.next_id - 1).into()
+            });
+        let noun = att
+            .and_then(|a| a.get("TYPE"))
+            .map(|t| t.trim().to_uppercase())
+            .unwrap_or_else(|| noun_from_name(&group.name));
+        let generic_type = PdmsGenericType::from_str(&noun).unwrap_or(owner_generic);
+
+        self.out.elements.push(RvmElement {
+            refno,
+            owner,
+            name: group.name.clone(),
+            noun: noun.clone(),
+            attributes: att.map(|a| a.attributes.clone()).unwrap_or_default(),
+        });
+
+        if !group.prims.is_empty() {
+            self.add_prims(group, refno, owner, owner_type, &noun, generic_type);
+        }
+        for child in &group.children {
+            self.visit(child, refno, &noun, generic_type);
+        }
+    }
+
+    fn add_prims(
+        &mut self,
+        group: &RvmGroup,
+        refno: RefnoEnum,
+        owner: RefnoEnum,
+        owner_type: &str,
+        noun: &str,
+        generic_type: PdmsGenericType,
+    ) {
+        let mut aabb = Aabb::new_invalid();
+        let mut insts = vec![];
+        for prim in &group.prims {
+            let Some(shape) = prim_to_shape(&prim.kind) else {
+                self.out.skipped_prims += 1;
+                continue;
+            };
+            let world = Mat4::from_scale(Vec3::splat(M_TO_MM)) * prim.matrix;
+            aabb.merge(&prim_world_aabb(prim, world));
+            insts.push(EleInstGeo {
+                geo_hash: shape.hash_unit_mesh_params(),
+                refno,
+                geo_param: shape.convert_to_geo_param().unwrap_or_default(),
+                transform: Transform::from_matrix(world) * shape.get_trans(),
+                visible: true,
+                geo_type: GeoBasicType::Pos,
+                unit_flag: shape.get_scaled_vec3() != Vec3::ONE,
+                ..Default::default()
+            });
+        }
+        if insts.is_empty() {
+            return;
+        }
+        let info = EleGeosInfo {
+            refno,
+            owner_refno: owner,
+            owner_type: owner_type.to_string(),
+            visible: true,
+            generic_type,
+            aabb: Some(aabb),
+            world_transform: Transform::IDENTITY,
+            is_solid: true,
+            ..Default::default()
+        };
+        let key = info.get_inst_key();
+        self.out.shapes.insert_geos_data(
+            key.clone(),
+            EleInstGeosData {
+                inst_key: key,
+                refno,
+                insts,
+                aabb: Some(aabb),
+                type_name: noun.to_string(),
+                lods: vec![],
+            },
+        );
+        self.out.shapes.insert_info(refno, info);
+    }
+}
+
+/// RVM 元素名称形如 `/PIPE-1` 或 `ELBOW 1 of BRANCH /PIPE-1/B1`，后者取第一个词为类型
+fn noun_from_name(name: &str) -> String {
+    if name.starts_with('/') {
+        return String::new();
+    }
+    name.split_whitespace()
+        .next()
+        .filter(|w| w.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|w| w.to_uppercase())
+        .unwrap_or_default()
+}
+
+/// 基本体局部包围盒变换到世界坐标
+fn prim_world_aabb(prim: &RvmPrim, world: Mat4) -> Aabb {
+    let [x0, y0, z0, x1, y1, z1] = prim.bbox;
+    let mut aabb = Aabb::new_invalid();
+    for x in [x0, x1] {
+        for y in [y0, y1] {
+            for z in [z0, z1] {
+                aabb.take_point(world.transform_point3(Vec3::new(x, y, z)).into());
+            }
+        }
+    }
+    aabb
+}
+
+/// RVM 基本体的局部坐标与对应基本体一致：轴线为 Z，除碟形外都以原点为中心
+pub fn prim_to_shape(kind: &RvmPrimKind) -> Option<Box<dyn BrepShapeTrait>> {
+    let shape: Box<dyn BrepShapeTrait> = match *kind {
+        RvmPrimKind::Box { lengths } => Box::new(SBox {
+            center: Vec3::ZERO,
+            size: Vec3::from(lengths),
+        }),
+        RvmPrimKind::Pyramid {
+            bottom,
+            top,
+            offset,
+            height,
+        } => Box::new(Pyramid {
+            pbax_pt: Vec3::ZERO,
+            pbax_dir: Vec3::X,
+            pcax_pt: Vec3::ZERO,
+            pcax_dir: Vec3::Y,
+            paax_pt: Vec3::ZERO,
+            paax_dir: Vec3::Z,
+            pbtp: top[0],
+            pctp: top[1],
+            pbbt: bottom[0],
+            pcbt: bottom[1],
+            ptdi: height / 2.0,
+            pbdi: -height / 2.0,
+            pbof: offset[0],
+            pcof: offset[1],
+        }),
+        RvmPrimKind::RectangularTorus {
+            inner_radius,
+            outer_radius,
+            height,
+            angle,
+        } => Box::new(RTorus {
+            rins: inner_radius,
+            rout: outer_radius,
+            height,
+            angle: angle.to_degrees(),
+        }),
+        RvmPrimKind::CircularTorus {
+            offset,
+            radius,
+            angle,
+        } => Box::new(CTorus {
+            rins: offset - radius,
+            rout: offset + radius,
+            angle: angle.to_degrees(),
+        }),
+        RvmPrimKind::EllipticalDish {
+            base_radius,
+            height,
+        } => Box::new(Dish {
+            pheig: height,
+            pdia: base_radius * 2.0,
+            prad: base_radius,
+            ..Default::default()
+        }),
+        RvmPrimKind::SphericalDish {
+            base_radius,
+            height,
+        } => Box::new(Dish {
+            pheig: height,
+            pdia: base_radius * 2.0,
+            prad: 0.0,
+            ..Default::default()
+        }),
+        RvmPrimKind::Snout {
+            radius_b,
+            radius_t,
+            height,
+            offset,
+            ..
+        } => {
+            let off = Vec3::new(offset[0], offset[1], 0.0);
+            Box::new(LSnout {
+                pbax_dir: off.try_normalize().unwrap_or(Vec3::X),
+                ptdi: height / 2.0,
+                pbdi: -height / 2.0,
+                ptdm: radius_t * 2.0,
+                pbdm: radius_b * 2.0,
+                poff: off.length(),
+                ..Default::default()
+            })
+        }
+        RvmPrimKind::Cylinder { radius, height } => Box::new(SCylinder {
+            phei: height,
+            pdia: radius * 2.0,
+            center_in_mid: true,
+            ..Default::default()
+        }),
+        RvmPrimKind::Sphere { diameter } => Box::new(Sphere {
+            center: Vec3::ZERO,
+            radius: diameter / 2.0,
+        }),
+        RvmPrimKind::Line { .. } => return None,
+        RvmPrimKind::FacetGroup { ref polygons } => Box::new(Polyhedron {
+            polygons: polygons
+                .iter()
+                .map(|p| Polygon {
+                    loops: p.contours.iter().map(|c| c.positions.clone()).collect(),
+                })
+                .collect(),
+            mesh: None,
+            is_polyhe: true,
+        }),
+    };
+    Some(shape)
+}
+
+/// 转换后的几何参数，见 [`prim_to_shape`]
+pub fn prim_to_geo_param(kind: &RvmPrimKind) -> Option<PdmsGeoParam> {
+    prim_to_shape(kind)?.convert_to_geo_param()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rvm::att::parse_att;
+    use crate::rvm::reader::parse_rvm;
+
+    /// 按 RVM 格式手工拼装字节
+    #[derive(Default)]
+    struct Bytes(Vec<u8>);
+
+    impl Bytes {
+        fn u32(&mut self, v: u32) -> &mut Self {
+            self.0.extend(v.to_be_bytes());
+            self
+        }
+
+        fn f32s(&mut self, v: &[f32]) -> &mut Self {
+            for x in v {
+                self.u32(x.to_bits());
+            }
+            self
+        }
+
+        fn chunk(&mut self, id: &[u8; 4]) -> &mut Self {
+            for &c in id {
+                self.u32(c as u32);
+            }
+            self.u32(0).u32(1)
+        }
+
+        fn string(&mut self, s: &str) -> &mut Self {
+            let words = s.len() / 4 + 1;
+            self.u32(words as u32);
+            let mut b = s.as_bytes().to_vec();
+            b.resize(words * 4, 0);
+            self.0.extend(b);
+            self
+        }
+
+        /// 平移为 `t`（m）、缩放 0.001 的变换
+        fn prim(&mut self, code: u32, t: [f32; 3], bbox: [f32; 6], params: &[f32]) -> &mut Self {
+            self.chunk(b"PRIM").u32(1).u32(code);
+            self.f32s(&[0.001, 0.0, 0.0, 0.0, 0.001, 0.0, 0.0, 0.0, 0.001]);
+            self.f32s(&t).f32s(&bbox).f32s(params)
+        }
+    }
+
+    fn sample_rvm() -> Vec<u8> {
+        let mut b = Bytes::default();
+        b.chunk(b"HEAD").u32(2);
+        for s in ["AVEVA", "", "Mon Jan 1", "user", "UTF-8"] {
+            b.string(s);
+        }
+        b.chunk(b"MODL").u32(1).string("PROJ").string("MODEL");
+        b.chunk(b"CNTB")
+            .u32(1)
+            .string("/SITE-1")
+            .f32s(&[0.0; 3])
+            .u32(1);
+        b.chunk(b"CNTB")
+            .u32(1)
+            .string("/EQUI-1")
+            .f32s(&[0.0; 3])
+            .u32(1);
+        b.prim(
+            2,
+            [1.0, 0.0, 0.0],
+            [-50.0, -50.0, -50.0, 50.0, 50.0, 50.0],
+            &[100.0; 3],
+        );
+        b.prim(
+            8,
+            [2.0, 0.0, 0.0],
+            [-10.0, -10.0, -100.0, 10.0, 10.0, 100.0],
+            &[10.0, 200.0],
+        );
+        b.prim(10, [0.0; 3], [0.0; 6], &[0.0, 1.0]);
+        b.chunk(b"CNTE").u32(1);
+        b.chunk(b"CNTE").u32(1);
+        b.chunk(b"END:");
+        b.0
+    }
+
+    #[test]
+    fn test_import_rvm() {
+        let rvm = parse_rvm(&sample_rvm()).unwrap();
+        assert_eq!(rvm.header.encoding, "UTF-8");
+        assert_eq!(rvm.model.name, "MODEL");
+        assert_eq!(rvm.roots[0].children[0].name, "/EQUI-1");
+        assert_eq!(rvm.prim_count(), 3);
+
+        let att = parse_att(
+            "CADC_Attributes_File v1.0 , start: NEW , end: END , name_end: := , sep: &end&\n\
+             NEW /SITE-1\n\
+             Type := SITE &end&\n\
+             NEW /EQUI-1\n\
+             Type := EQUI &end&\n\
+             Ref := =17496/12 &end&\n\
+             Description := two\n\
+             lines &end&\n\
+             END\n\
+             END\n",
+        );
+        assert_eq!(
+            att.roots[0].children[0].get("description"),
+            Some("two\nlines")
+        );
+
+        let import = convert_rvm(&rvm, Some(&att));
+        assert_eq!(import.skipped_prims, 1);
+        assert_eq!(import.elements.len(), 2);
+        let site = import.elements[0].refno;
+        let equi: RefnoEnum = RefU64::from_two_nums(17496, 12).into();
+        assert_eq!(site.refno().get_0(), RVM_DBNUM);
+        assert_eq!(import.children(site).next().unwrap().refno, equi);
+
+        let info = import.shapes.get_inst_info(equi).unwrap();
+        assert_eq!(info.owner_refno, site);
+        assert_eq!(info.owner_type, "SITE");
+        assert_eq!(info.generic_type, PdmsGenericType::EQUI);
+        let aabb = info.aabb.unwrap();
+        assert!((aabb.mins.x - 950.0).abs() < 1e-3);
+        assert!((aabb.maxs.x - 2010.0).abs() < 1e-3);
+
+        let geos = import.shapes.get_inst_geos(info).unwrap();
+        assert_eq!(geos.len(), 2);
+        assert!(matches!(geos[0].geo_param, PdmsGeoParam::PrimBox(_)));
+        assert!(
+            geos[0]
+                .transform
+                .translation
+                .abs_diff_eq(Vec3::new(1000.0, 0.0, 0.0), 1e-3)
+        );
+        let PdmsGeoParam::PrimSCylinder(cyl) = &geos[1].geo_param else {
+            panic!("应为圆柱");
+        };
+        assert_eq!((cyl.pdia, cyl.phei), (20.0, 200.0));
+    }
+}
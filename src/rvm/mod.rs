@@ -0,0 +1,18 @@
+//! AVEVA RVM 二进制模型
+//!
+//! [`reader`] 解析 RVM 文件，[`att`] 解析同名的属性文件，
+//! [`import`] 把两者转换为几何管线使用的 [`ShapeInstancesData`](crate::geometry::ShapeInstancesData)，
+//! [`writer`] 则反向导出，供 Navisworks / AVEVA Review 查看。
+//! 文本格式的 RVM 基本体数据见 [`crate::rvm_types`]。
+
+pub mod att;
+pub mod import;
+pub mod reader;
+pub mod writer;
+
+pub use att::{AttFile, AttNode, parse_att, read_att};
+pub use import::{RvmElement, RvmImport, convert_rvm, import_rvm};
+pub use reader::{RvmFile, RvmGroup, RvmPrim, RvmPrimKind, parse_rvm, read_rvm};
+pub use writer::{
+    RvmExportOptions, RvmExportStats, RvmNodeInfo, export_rvm, export_rvm_with, query_rvm_hierarchy,
+};
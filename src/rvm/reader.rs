@@ -0,0 +1,485 @@
+//! RVM 二进制文件解析
+//!
+//! 文件由一系列 chunk 组成，chunk 头为 4 个大端 u32 表示的标识字符
+//! （如 `HEAD`、`MODL`、`CNTB`、`PRIM`、`END:`），后跟两个保留的 u32。
+//! 字符串以"字数"（4 字节为单位）开头，内容以 0 补齐。
+//! `CNTB`/`CNTE` 成对出现构成元素层级，`PRIM` 为层级下的基本体。
+
+use anyhow::{Context, anyhow, bail};
+use glam::{Mat4, Vec3, Vec4};
+use std::path::Path;
+
+/// `HEAD` chunk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RvmHeader {
+    pub version: u32,
+    pub info: String,
+    pub note: String,
+    pub date: String,
+    pub user: String,
+    /// version >= 2 时才有
+    pub encoding: String,
+}
+
+/// `MODL` chunk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RvmModel {
+    pub version: u32,
+    pub project: String,
+    pub name: String,
+}
+
+/// 一个面片组中的多边形，第一个轮廓为外环，其余为洞
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RvmPolygon {
+    pub contours: Vec<RvmContour>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RvmContour {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+}
+
+/// 基本体参数，长度单位为 mm，角度为弧度
+#[derive(Debug, Clone, PartialEq)]
+pub enum RvmPrimKind {
+    Pyramid {
+        bottom: [f32; 2],
+        top: [f32; 2],
+        offset: [f32; 2],
+        height: f32,
+    },
+    Box {
+        lengths: [f32; 3],
+    },
+    RectangularTorus {
+        inner_radius: f32,
+        outer_radius: f32,
+        height: f32,
+        angle: f32,
+    },
+    CircularTorus {
+        /// 圆环中心到管中心的距离
+        offset: f32,
+        /// 管半径
+        radius: f32,
+        angle: f32,
+    },
+    EllipticalDish {
+        base_radius: f32,
+        height: f32,
+    },
+    SphericalDish {
+        base_radius: f32,
+        height: f32,
+    },
+    Snout {
+        radius_b: f32,
+        radius_t: f32,
+        height: f32,
+        offset: [f32; 2],
+        bshear: [f32; 2],
+        tshear: [f32; 2],
+    },
+    Cylinder {
+        radius: f32,
+        height: f32,
+    },
+    Sphere {
+        diameter: f32,
+    },
+    Line {
+        a: f32,
+        b: f32,
+    },
+    FacetGroup {
+        polygons: Vec<RvmPolygon>,
+    },
+}
+
+impl RvmPrimKind {
+    /// 文件中的类型编号
+    pub fn code(&self) -> u32 {
+        match self {
+            RvmPrimKind::Pyramid { .. } => 1,
+            RvmPrimKind::Box { .. } => 2,
+            RvmPrimKind::RectangularTorus { .. } => 3,
+            RvmPrimKind::CircularTorus { .. } => 4,
+            RvmPrimKind::EllipticalDish { .. } => 5,
+            RvmPrimKind::SphericalDish { .. } => 6,
+            RvmPrimKind::Snout { .. } => 7,
+            RvmPrimKind::Cylinder { .. } => 8,
+            RvmPrimKind::Sphere { .. } => 9,
+            RvmPrimKind::Line { .. } => 10,
+            RvmPrimKind::FacetGroup { .. } => 11,
+        }
+    }
+
+    /// 局部包围盒 `[min_x, min_y, min_z, max_x, max_y, max_z]`，圆环按整圈计算
+    pub fn local_bbox(&self) -> [f32; 6] {
+        let sym = |x: f32, y: f32, z: f32| [-x, -y, -z, x, y, z];
+        match self {
+            RvmPrimKind::Pyramid {
+                bottom,
+                top,
+                offset,
+                height,
+            } => {
+                let hx = (bottom[0] / 2.0).max(top[0] / 2.0 + offset[0].abs());
+                let hy = (bottom[1] / 2.0).max(top[1] / 2.0 + offset[1].abs());
+                sym(hx, hy, height / 2.0)
+            }
+            RvmPrimKind::Box { lengths } => {
+                sym(lengths[0] / 2.0, lengths[1] / 2.0, lengths[2] / 2.0)
+            }
+            RvmPrimKind::RectangularTorus {
+                outer_radius,
+                height,
+                ..
+            } => sym(*outer_radius, *outer_radius, height / 2.0),
+            RvmPrimKind::CircularTorus { offset, radius, .. } => {
+                sym(offset + radius, offset + radius, *radius)
+            }
+            RvmPrimKind::EllipticalDish {
+                base_radius,
+                height,
+            }
+            | RvmPrimKind::SphericalDish {
+                base_radius,
+                height,
+            } => [
+                -base_radius,
+                -base_radius,
+                0.0,
+                *base_radius,
+                *base_radius,
+                *height,
+            ],
+            RvmPrimKind::Snout {
+                radius_b,
+                radius_t,
+                height,
+                offset,
+                ..
+            } => {
+                let r = radius_b.max(radius_t + offset[0].abs().max(offset[1].abs()));
+                sym(r, r, height / 2.0)
+            }
+            RvmPrimKind::Cylinder { radius, height } => sym(*radius, *radius, height / 2.0),
+            RvmPrimKind::Sphere { diameter } => sym(diameter / 2.0, diameter / 2.0, diameter / 2.0),
+            RvmPrimKind::Line { a, b } => [a.min(*b), 0.0, 0.0, a.max(*b), 0.0, 0.0],
+            RvmPrimKind::FacetGroup { polygons } => {
+                let mut min = Vec3::splat(f32::MAX);
+                let mut max = Vec3::splat(f32::MIN);
+                for p in polygons
+                    .iter()
+                    .flat_map(|p| &p.contours)
+                    .flat_map(|c| &c.positions)
+                {
+                    min = min.min(*p);
+                    max = max.max(*p);
+                }
+                if min.x > max.x {
+                    return [0.0; 6];
+                }
+                [min.x, min.y, min.z, max.x, max.y, max.z]
+            }
+        }
+    }
+}
+
+/// `PRIM` chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct RvmPrim {
+    pub version: u32,
+    /// 文件中的 3x4 变换（列主序），把 mm 单位的局部坐标变换到以 m 为单位的世界坐标
+    pub matrix: Mat4,
+    /// 局部包围盒 `[min_x, min_y, min_z, max_x, max_y, max_z]`
+    pub bbox: [f32; 6],
+    pub kind: RvmPrimKind,
+}
+
+/// `CNTB` ... `CNTE` 之间的一个元素
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RvmGroup {
+    pub version: u32,
+    pub name: String,
+    /// 单位为 mm
+    pub translation: Vec3,
+    pub material: u32,
+    pub children: Vec<RvmGroup>,
+    pub prims: Vec<RvmPrim>,
+}
+
+impl RvmGroup {
+    /// 深度优先遍历，回调参数为 (深度, 元素)
+    pub fn walk<'a>(&'a self, depth: usize, f: &mut impl FnMut(usize, &'a RvmGroup)) {
+        f(depth, self);
+        for c in &self.children {
+            c.walk(depth + 1, f);
+        }
+    }
+}
+
+/// 一个 RVM 文件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RvmFile {
+    pub header: RvmHeader,
+    pub model: RvmModel,
+    pub roots: Vec<RvmGroup>,
+}
+
+impl RvmFile {
+    pub fn prim_count(&self) -> usize {
+        let mut n = 0;
+        for root in &self.roots {
+            root.walk(0, &mut |_, g| n += g.prims.len());
+        }
+        n
+    }
+}
+
+/// 读取并解析 RVM 文件
+pub fn read_rvm(path: impl AsRef<Path>) -> anyhow::Result<RvmFile> {
+    let path = path.as_ref();
+    let bytes =
+        std::fs::read(path).with_context(|| format!("读取 RVM 文件失败: {}", path.display()))?;
+    parse_rvm(&bytes).with_context(|| format!("解析 RVM 文件失败: {}", path.display()))
+}
+
+/// 解析 RVM 二进制数据
+pub fn parse_rvm(bytes: &[u8]) -> anyhow::Result<RvmFile> {
+    let mut r = Reader { buf: bytes, pos: 0 };
+    let mut file = RvmFile::default();
+
+    if r.chunk_id()? != Some(*b"HEAD") {
+        bail!("不是 RVM 文件：缺少 HEAD");
+    }
+    file.header.version = r.u32()?;
+    file.header.info = r.string()?;
+    file.header.note = r.string()?;
+    file.header.date = r.string()?;
+    file.header.user = r.string()?;
+    if file.header.version >= 2 {
+        file.header.encoding = r.string()?;
+    }
+
+    if r.chunk_id()? != Some(*b"MODL") {
+        bail!("缺少 MODL");
+    }
+    file.model.version = r.u32()?;
+    file.model.project = r.string()?;
+    file.model.name = r.string()?;
+
+    loop {
+        let at = r.pos;
+        match r.chunk_id()?.as_ref() {
+            None | Some(b"END:") => break,
+            Some(b"CNTB") => file.roots.push(r.group()?),
+            Some(b"COLR") => r.skip_colr()?,
+            Some(id) => bail!("位置 {} 处出现意外的 chunk {}", at, id_str(id)),
+        }
+    }
+    Ok(file)
+}
+
+pub(crate) fn id_str(id: &[u8; 4]) -> String {
+    String::from_utf8_lossy(id).into_owned()
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&[u8]> {
+        let end = self.pos + n;
+        let bytes = self
+            .buf
+            .get(self.pos..end)
+            .ok_or_else(|| anyhow!("位置 {} 处数据不完整", self.pos))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    fn f32s<const N: usize>(&mut self) -> anyhow::Result<[f32; N]> {
+        let mut v = [0.0; N];
+        for x in &mut v {
+            *x = self.f32()?;
+        }
+        Ok(v)
+    }
+
+    fn vec3(&mut self) -> anyhow::Result<Vec3> {
+        Ok(Vec3::from(self.f32s::<3>()?))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let words = self.u32()? as usize;
+        let bytes = self.take(words * 4)?;
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    /// 读取 chunk 标识，数据结束时返回 None
+    fn chunk_id(&mut self) -> anyhow::Result<Option<[u8; 4]>> {
+        if self.buf.len() - self.pos < 16 {
+            return Ok(None);
+        }
+        let mut id = [0u8; 4];
+        for c in &mut id {
+            let v = self.u32()?;
+            *c = u8::try_from(v).map_err(|_| anyhow!("位置 {} 处 chunk 标识无效", self.pos))?;
+        }
+        // 下一个 chunk 的偏移和一个保留字段，解析时不使用
+        self.u32()?;
+        self.u32()?;
+        Ok(Some(id))
+    }
+
+    fn skip_colr(&mut self) -> anyhow::Result<()> {
+        // 颜色类型、颜色索引、RGBA
+        self.take(12)?;
+        Ok(())
+    }
+
+    fn group(&mut self) -> anyhow::Result<RvmGroup> {
+        let mut group = RvmGroup {
+            version: self.u32()?,
+            name: self.string()?,
+            ..Default::default()
+        };
+        group.translation = self.vec3()?;
+        group.material = self.u32()?;
+        loop {
+            let at = self.pos;
+            match self.chunk_id()?.as_ref() {
+                Some(b"CNTE") => {
+                    self.u32()?;
+                    return Ok(group);
+                }
+                Some(b"CNTB") => group.children.push(self.group()?),
+                Some(b"PRIM") => group.prims.push(self.prim()?),
+                Some(b"COLR") => self.skip_colr()?,
+                Some(id) => bail!("位置 {} 处出现意外的 chunk {}", at, id_str(id)),
+                None => bail!("元素 {} 缺少 CNTE", group.name),
+            }
+        }
+    }
+
+    fn prim(&mut self) -> anyhow::Result<RvmPrim> {
+        let version = self.u32()?;
+        let code = self.u32()?;
+        let m = self.f32s::<12>()?;
+        let matrix = Mat4::from_cols(
+            Vec4::new(m[0], m[1], m[2], 0.0),
+            Vec4::new(m[3], m[4], m[5], 0.0),
+            Vec4::new(m[6], m[7], m[8], 0.0),
+            Vec4::new(m[9], m[10], m[11], 1.0),
+        );
+        let bbox = self.f32s::<6>()?;
+        let kind = match code {
+            1 => {
+                let [bx, by, tx, ty, ox, oy, height] = self.f32s::<7>()?;
+                RvmPrimKind::Pyramid {
+                    bottom: [bx, by],
+                    top: [tx, ty],
+                    offset: [ox, oy],
+                    height,
+                }
+            }
+            2 => RvmPrimKind::Box {
+                lengths: self.f32s::<3>()?,
+            },
+            3 => {
+                let [inner_radius, outer_radius, height, angle] = self.f32s::<4>()?;
+                RvmPrimKind::RectangularTorus {
+                    inner_radius,
+                    outer_radius,
+                    height,
+                    angle,
+                }
+            }
+            4 => {
+                let [offset, radius, angle] = self.f32s::<3>()?;
+                RvmPrimKind::CircularTorus {
+                    offset,
+                    radius,
+                    angle,
+                }
+            }
+            5 => {
+                let [base_radius, height] = self.f32s::<2>()?;
+                RvmPrimKind::EllipticalDish {
+                    base_radius,
+                    height,
+                }
+            }
+            6 => {
+                let [base_radius, height] = self.f32s::<2>()?;
+                RvmPrimKind::SphericalDish {
+                    base_radius,
+                    height,
+                }
+            }
+            7 => {
+                let [rb, rt, h, ox, oy, bx, by, tx, ty] = self.f32s::<9>()?;
+                RvmPrimKind::Snout {
+                    radius_b: rb,
+                    radius_t: rt,
+                    height: h,
+                    offset: [ox, oy],
+                    bshear: [bx, by],
+                    tshear: [tx, ty],
+                }
+            }
+            8 => {
+                let [radius, height] = self.f32s::<2>()?;
+                RvmPrimKind::Cylinder { radius, height }
+            }
+            9 => RvmPrimKind::Sphere {
+                diameter: self.f32()?,
+            },
+            10 => {
+                let [a, b] = self.f32s::<2>()?;
+                RvmPrimKind::Line { a, b }
+            }
+            11 => {
+                let n = self.u32()?;
+                let mut polygons = Vec::with_capacity(n as usize);
+                for _ in 0..n {
+                    let n = self.u32()?;
+                    let mut contours = Vec::with_capacity(n as usize);
+                    for _ in 0..n {
+                        let n = self.u32()?;
+                        let mut c = RvmContour::default();
+                        for _ in 0..n {
+                            c.positions.push(self.vec3()?);
+                            c.normals.push(self.vec3()?);
+                        }
+                        contours.push(c);
+                    }
+                    polygons.push(RvmPolygon { contours });
+                }
+                RvmPrimKind::FacetGroup { polygons }
+            }
+            _ => bail!("未知的基本体类型 {}", code),
+        };
+        Ok(RvmPrim {
+            version,
+            matrix,
+            bbox,
+            kind,
+        })
+    }
+}
@@ -0,0 +1,797 @@
+//! 把几何管线的实例数据导出为 RVM 二进制文件
+//!
+//! 元素层级由 [`RvmExportOptions::hierarchy`] 提供（可用 [`query_rvm_hierarchy`] 从数据库查询），
+//! 缺少时退化为 `owner_refno` 一层。基本体参数取自 [`PdmsGeoParam`]，
+//! 实例变换约定与 [`import`](super::import) 一致：`transform` = 基本体局部坐标到世界坐标的变换 × 基本体的单位化变换。
+//! 没有对应 RVM 基本体的几何（拉伸、旋转、放样等）在提供了 `mesh_dir` 时按三角面片组导出。
+//!
+//! chunk 头中"下一个 chunk 的偏移"写为下一个 chunk 在文件中的字节位置。
+
+use super::att::{AttFile, AttNode};
+use super::reader::{
+    RvmContour, RvmFile, RvmGroup, RvmHeader, RvmModel, RvmPolygon, RvmPrim, RvmPrimKind,
+};
+use crate::geometry::gltf_export::load_mesh_from_dir;
+use crate::geometry::{EleGeosInfo, EleInstGeo, GeoBasicType, ShapeInstancesData};
+use crate::parsed_data::geo_params_data::PdmsGeoParam;
+use crate::shape::pdms_shape::{BrepShapeTrait, PlantMesh};
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, join_pe_keys};
+use anyhow::Context;
+use glam::{Mat4, Vec3, Vec4};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+const MM_TO_M: f32 = 0.001;
+
+/// 层级中的一个元素
+#[derive(Debug, Clone, Default)]
+pub struct RvmNodeInfo {
+    pub refno: RefnoEnum,
+    pub owner: RefnoEnum,
+    pub name: String,
+    pub noun: String,
+}
+
+/// 导出选项
+#[derive(Debug, Clone)]
+pub struct RvmExportOptions {
+    pub project: String,
+    pub model_name: String,
+    /// 元素层级，key 为参考号
+    pub hierarchy: HashMap<RefnoEnum, RvmNodeInfo>,
+    /// 同时写出同名的 `.att` 属性文件（含 `Ref`、`Type`，可被导入时还原参考号）
+    pub write_att: bool,
+    /// 不能用 RVM 基本体表达的几何从 `{mesh_dir}/{geo_hash}.mesh` 读取三角面
+    pub mesh_dir: Option<PathBuf>,
+    /// 是否导出不可见的元素
+    pub include_invisible: bool,
+}
+
+impl Default for RvmExportOptions {
+    fn default() -> Self {
+        Self {
+            project: "AIOS".to_string(),
+            model_name: "MODEL".to_string(),
+            hierarchy: HashMap::new(),
+            write_att: true,
+            mesh_dir: None,
+            include_invisible: false,
+        }
+    }
+}
+
+/// 导出统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RvmExportStats {
+    pub groups: usize,
+    pub prims: usize,
+    /// 无法导出的几何
+    pub skipped: usize,
+}
+
+/// 使用默认选项导出，见 [`export_rvm_with`]
+pub fn export_rvm(
+    data: &ShapeInstancesData,
+    path: impl AsRef<Path>,
+) -> anyhow::Result<RvmExportStats> {
+    export_rvm_with(data, path, &RvmExportOptions::default())
+}
+
+/// 导出 RVM 文件（及 `.att` 属性文件）
+pub fn export_rvm_with(
+    data: &ShapeInstancesData,
+    path: impl AsRef<Path>,
+    options: &RvmExportOptions,
+) -> anyhow::Result<RvmExportStats> {
+    let path = path.as_ref();
+    let (file, stats) = build_rvm(data, options);
+    std::fs::write(path, encode_rvm(&file))
+        .with_context(|| format!("写入 RVM 文件失败: {}", path.display()))?;
+    if options.write_att {
+        let att_path = path.with_extension("att");
+        std::fs::write(&att_path, encode_att(&rvm_to_att(&file, options)))
+            .with_context(|| format!("写入属性文件失败: {}", att_path.display()))?;
+    }
+    Ok(stats)
+}
+
+/// 查询元素及其所有祖先的名称、类型和所属关系，用作 [`RvmExportOptions::hierarchy`]
+pub async fn query_rvm_hierarchy(
+    refnos: &[RefnoEnum],
+) -> anyhow::Result<HashMap<RefnoEnum, RvmNodeInfo>> {
+    let mut all: HashSet<RefnoEnum> = refnos.iter().copied().collect();
+    for &refno in refnos {
+        all.extend(crate::query_ancestor_refnos(refno).await?);
+    }
+    #[derive(Deserialize, SurrealValue)]
+    struct Row {
+        refno: RefnoEnum,
+        owner: Option<RefnoEnum>,
+        noun: String,
+        name: Option<String>,
+    }
+
+    let mut result = HashMap::new();
+    let all: Vec<_> = all.into_iter().collect();
+    for chunk in all.chunks(500) {
+        let sql = format!(
+            "select refno, owner, noun, fn::default_name(id) as name from [{}]",
+            join_pe_keys(chunk.iter())
+        );
+        let rows: Vec<Row> = SUL_DB.query_take(&sql, 0).await?;
+        result.extend(rows.into_iter().map(|r| {
+            let node = RvmNodeInfo {
+                refno: r.refno,
+                owner: r.owner.unwrap_or_default(),
+                name: r.name.unwrap_or_default(),
+                noun: r.noun,
+            };
+            (r.refno, node)
+        }));
+    }
+    Ok(result)
+}
+
+/// 按层级组织元素并转换基本体
+pub fn build_rvm(
+    data: &ShapeInstancesData,
+    options: &RvmExportOptions,
+) -> (RvmFile, RvmExportStats) {
+    let mut stats = RvmExportStats::default();
+    let mut load_mesh = options.mesh_dir.clone().map(load_mesh_from_dir);
+    let mut mesh_cache: HashMap<u64, Option<PlantMesh>> = HashMap::new();
+
+    // 每个元素的基本体
+    let mut prims: BTreeMap<RefnoEnum, Vec<RvmPrim>> = BTreeMap::new();
+    let mut infos: BTreeMap<RefnoEnum, &EleGeosInfo> = BTreeMap::new();
+    for info in data
+        .inst_info_map
+        .values()
+        .chain(data.inst_tubi_map.values())
+        .filter(|i| i.visible || options.include_invisible)
+    {
+        let Some(geos) = data.get_inst_geos(info) else {
+            continue;
+        };
+        let list = prims.entry(info.refno).or_default();
+        infos.insert(info.refno, info);
+        for geo in geos.iter().filter(|g| exportable(g, options)) {
+            let world = info.get_geo_world_transform(geo).to_matrix();
+            if let Some(prim) = geo_to_prim(&geo.geo_param, world) {
+                list.push(prim);
+                continue;
+            }
+            let mesh = match load_mesh.as_mut() {
+                Some(load) => &*mesh_cache
+                    .entry(geo.geo_hash)
+                    .or_insert_with(|| load(geo.geo_hash)),
+                None => &None,
+            };
+            match mesh.as_ref().and_then(|m| mesh_to_prim(m, world)) {
+                Some(prim) => list.push(prim),
+                None => stats.skipped += 1,
+            }
+        }
+    }
+
+    // 补齐层级：元素 -> 祖先
+    let node_info = |refno: RefnoEnum| -> RvmNodeInfo {
+        if let Some(n) = options.hierarchy.get(&refno) {
+            return n.clone();
+        }
+        match infos.get(&refno) {
+            Some(info) => RvmNodeInfo {
+                refno,
+                owner: info.owner_refno,
+                name: String::new(),
+                noun: data
+                    .get_inst_geos_data(info)
+                    .map(|g| g.type_name.clone())
+                    .unwrap_or_default(),
+            },
+            None => RvmNodeInfo {
+                refno,
+                noun: infos
+                    .values()
+                    .find(|i| i.owner_refno == refno)
+                    .map(|i| i.owner_type.clone())
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+        }
+    };
+    let mut nodes: BTreeMap<RefnoEnum, RvmNodeInfo> = BTreeMap::new();
+    let mut pending: Vec<RefnoEnum> = prims.keys().copied().collect();
+    while let Some(refno) = pending.pop() {
+        if !refno.is_valid() || nodes.contains_key(&refno) {
+            continue;
+        }
+        let node = node_info(refno);
+        if node.owner != refno {
+            pending.push(node.owner);
+        }
+        nodes.insert(refno, node);
+    }
+
+    let mut children: BTreeMap<RefnoEnum, Vec<RefnoEnum>> = BTreeMap::new();
+    let mut roots = vec![];
+    for (refno, node) in &nodes {
+        if nodes.contains_key(&node.owner) && node.owner != *refno {
+            children.entry(node.owner).or_default().push(*refno);
+        } else {
+            roots.push(*refno);
+        }
+    }
+
+    fn make_group(
+        refno: RefnoEnum,
+        nodes: &BTreeMap<RefnoEnum, RvmNodeInfo>,
+        children: &BTreeMap<RefnoEnum, Vec<RefnoEnum>>,
+        prims: &mut BTreeMap<RefnoEnum, Vec<RvmPrim>>,
+        infos: &BTreeMap<RefnoEnum, &EleGeosInfo>,
+        stats: &mut RvmExportStats,
+    ) -> RvmGroup {
+        let node = &nodes[&refno];
+        let prims = prims.remove(&refno).unwrap_or_default();
+        stats.groups += 1;
+        stats.prims += prims.len();
+        RvmGroup {
+            version: 3,
+            name: group_name(node),
+            translation: infos
+                .get(&refno)
+                .and_then(|i| i.aabb)
+                .map(|a| Vec3::from(a.center()))
+                .unwrap_or_default(),
+            material: 1,
+            children: children
+                .get(&refno)
+                .into_iter()
+                .flatten()
+                .map(|c| make_group(*c, nodes, children, prims, infos, stats))
+                .collect(),
+            prims,
+        }
+    }
+
+    let roots = roots
+        .into_iter()
+        .map(|r| make_group(r, &nodes, &children, &mut prims, &infos, &mut stats))
+        .collect();
+    let file = RvmFile {
+        header: RvmHeader {
+            version: 2,
+            info: "AIOS".to_string(),
+            note: String::new(),
+            date: chrono::Local::now()
+                .format("%a %b %d %H:%M:%S %Y")
+                .to_string(),
+            user: String::new(),
+            encoding: "UTF-8".to_string(),
+        },
+        model: RvmModel {
+            version: 1,
+            project: options.project.clone(),
+            name: options.model_name.clone(),
+        },
+        roots,
+    };
+    (file, stats)
+}
+
+/// 导出时使用的元素名称，同时作为属性文件中的 key
+fn group_name(node: &RvmNodeInfo) -> String {
+    if !node.name.is_empty() {
+        node.name.clone()
+    } else if node.noun.is_empty() {
+        format!("={}", node.refno.to_pdms_str())
+    } else {
+        format!("{} ={}", node.noun, node.refno.to_pdms_str())
+    }
+}
+
+fn exportable(geo: &EleInstGeo, options: &RvmExportOptions) -> bool {
+    (geo.visible || options.include_invisible)
+        && !matches!(
+            geo.geo_type,
+            GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+        )
+}
+
+/// 以 `origin` 为原点、`z` 为 Z 轴的坐标系，`x_hint` 决定 X 轴朝向
+fn frame(origin: Vec3, z: Vec3, x_hint: Vec3) -> Mat4 {
+    let z = z.try_normalize().unwrap_or(Vec3::Z);
+    let x = (x_hint - z * x_hint.dot(z))
+        .try_normalize()
+        .unwrap_or_else(|| z.any_orthonormal_vector());
+    let y = z.cross(x);
+    Mat4::from_cols(
+        x.extend(0.0),
+        y.extend(0.0),
+        z.extend(0.0),
+        origin.extend(1.0),
+    )
+}
+
+fn param_shape(param: &PdmsGeoParam) -> Option<&dyn BrepShapeTrait> {
+    Some(match param {
+        PdmsGeoParam::PrimBox(s) => s,
+        PdmsGeoParam::PrimLSnout(s) => s,
+        PdmsGeoParam::PrimDish(s) => s,
+        PdmsGeoParam::PrimSphere(s) => s,
+        PdmsGeoParam::PrimCTorus(s) => s,
+        PdmsGeoParam::PrimRTorus(s) => s,
+        PdmsGeoParam::PrimPyramid(s) => s,
+        PdmsGeoParam::PrimSCylinder(s) => s,
+        PdmsGeoParam::PrimPolyhedron(s) => s,
+        _ => return None,
+    })
+}
+
+/// 把几何参数转换为 RVM 基本体，`world` 为实例的世界变换（mm）
+pub fn geo_to_prim(param: &PdmsGeoParam, world: Mat4) -> Option<RvmPrim> {
+    let place = world * param_shape(param)?.get_trans().to_matrix().inverse();
+    let (local, kind) = match param {
+        PdmsGeoParam::PrimBox(s) => (
+            Mat4::from_translation(s.center),
+            RvmPrimKind::Box {
+                lengths: s.size.to_array(),
+            },
+        ),
+        PdmsGeoParam::PrimSCylinder(s) => {
+            let dir = s.paxi_dir.try_normalize().unwrap_or(Vec3::Z);
+            let center = if s.center_in_mid {
+                s.paxi_pt
+            } else {
+                s.paxi_pt + dir * s.phei / 2.0
+            };
+            (
+                frame(center, dir, Vec3::X),
+                RvmPrimKind::Cylinder {
+                    radius: s.pdia / 2.0,
+                    height: s.phei.abs(),
+                },
+            )
+        }
+        PdmsGeoParam::PrimLSnout(s) => {
+            let dir = s.paax_dir.try_normalize().unwrap_or(Vec3::Z);
+            let center = s.paax_pt + dir * (s.ptdi + s.pbdi) / 2.0;
+            (
+                frame(center, dir, s.pbax_dir),
+                RvmPrimKind::Snout {
+                    radius_b: s.pbdm / 2.0,
+                    radius_t: s.ptdm / 2.0,
+                    height: s.ptdi - s.pbdi,
+                    offset: [s.poff, 0.0],
+                    bshear: [0.0; 2],
+                    tshear: [0.0; 2],
+                },
+            )
+        }
+        PdmsGeoParam::PrimDish(s) => {
+            let dir = s.paax_dir.try_normalize().unwrap_or(Vec3::Z);
+            let base_radius = s.pdia / 2.0;
+            let kind = if s.prad.abs() > f32::EPSILON {
+                RvmPrimKind::EllipticalDish {
+                    base_radius,
+                    height: s.pheig,
+                }
+            } else {
+                RvmPrimKind::SphericalDish {
+                    base_radius,
+                    height: s.pheig,
+                }
+            };
+            (frame(s.paax_pt + dir * s.pdis, dir, Vec3::X), kind)
+        }
+        PdmsGeoParam::PrimSphere(s) => (
+            Mat4::from_translation(s.center),
+            RvmPrimKind::Sphere {
+                diameter: s.radius * 2.0,
+            },
+        ),
+        PdmsGeoParam::PrimCTorus(s) => (
+            Mat4::IDENTITY,
+            RvmPrimKind::CircularTorus {
+                offset: (s.rins + s.rout) / 2.0,
+                radius: (s.rout - s.rins) / 2.0,
+                angle: s.angle.to_radians(),
+            },
+        ),
+        PdmsGeoParam::PrimRTorus(s) => (
+            Mat4::IDENTITY,
+            RvmPrimKind::RectangularTorus {
+                inner_radius: s.rins,
+                outer_radius: s.rout,
+                height: s.height,
+                angle: s.angle.to_radians(),
+            },
+        ),
+        PdmsGeoParam::PrimPyramid(s) => {
+            let dir = s.paax_dir.try_normalize().unwrap_or(Vec3::Z);
+            let center = s.paax_pt + dir * (s.ptdi + s.pbdi) / 2.0;
+            (
+                frame(center, dir, s.pbax_dir),
+                RvmPrimKind::Pyramid {
+                    bottom: [s.pbbt, s.pcbt],
+                    top: [s.pbtp, s.pctp],
+                    offset: [s.pbof, s.pcof],
+                    height: s.ptdi - s.pbdi,
+                },
+            )
+        }
+        PdmsGeoParam::PrimPolyhedron(s) => {
+            let polygons = s
+                .polygons
+                .iter()
+                .map(|p| polygon(p.loops.iter().map(|l| l.as_slice())))
+                .collect();
+            (Mat4::IDENTITY, RvmPrimKind::FacetGroup { polygons })
+        }
+        _ => return None,
+    };
+    Some(make_prim(place * local, kind))
+}
+
+/// 三角网格按面片组导出，`world` 为实例的世界变换（mm）
+pub fn mesh_to_prim(mesh: &PlantMesh, world: Mat4) -> Option<RvmPrim> {
+    if mesh.indices.len() < 3 {
+        return None;
+    }
+    let polygons = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|t| {
+            let tri: Vec<Vec3> = t.iter().map(|&i| mesh.vertices[i as usize]).collect();
+            polygon(std::iter::once(tri.as_slice()))
+        })
+        .collect();
+    Some(make_prim(world, RvmPrimKind::FacetGroup { polygons }))
+}
+
+/// 多边形的各顶点使用同一个法向（Newell 法）
+fn polygon<'a>(loops: impl Iterator<Item = &'a [Vec3]>) -> RvmPolygon {
+    let loops: Vec<&[Vec3]> = loops.filter(|l| l.len() >= 3).collect();
+    let normal = loops
+        .first()
+        .map(|l| {
+            let mut n = Vec3::ZERO;
+            for (i, a) in l.iter().enumerate() {
+                let b = l[(i + 1) % l.len()];
+                n += Vec3::new(
+                    (a.y - b.y) * (a.z + b.z),
+                    (a.z - b.z) * (a.x + b.x),
+                    (a.x - b.x) * (a.y + b.y),
+                );
+            }
+            n.normalize_or_zero()
+        })
+        .unwrap_or_default();
+    RvmPolygon {
+        contours: loops
+            .into_iter()
+            .map(|l| RvmContour {
+                positions: l.to_vec(),
+                normals: vec![normal; l.len()],
+            })
+            .collect(),
+    }
+}
+
+fn make_prim(local_to_world_mm: Mat4, kind: RvmPrimKind) -> RvmPrim {
+    RvmPrim {
+        version: 1,
+        matrix: Mat4::from_scale(Vec3::splat(MM_TO_M)) * local_to_world_mm,
+        bbox: kind.local_bbox(),
+        kind,
+    }
+}
+
+/// 编码为 RVM 二进制
+pub fn encode_rvm(file: &RvmFile) -> Vec<u8> {
+    let mut w = Writer::default();
+    let h = &file.header;
+    w.chunk(b"HEAD", |w| {
+        w.u32(h.version);
+        for s in [&h.info, &h.note, &h.date, &h.user] {
+            w.string(s);
+        }
+        if h.version >= 2 {
+            w.string(&h.encoding);
+        }
+    });
+    w.chunk(b"MODL", |w| {
+        w.u32(file.model.version);
+        w.string(&file.model.project);
+        w.string(&file.model.name);
+    });
+    for g in &file.roots {
+        w.group(g);
+    }
+    w.chunk(b"END:", |_| {});
+    w.buf
+}
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn u32(&mut self, v: u32) {
+        self.buf.extend(v.to_be_bytes());
+    }
+
+    fn f32s(&mut self, v: &[f32]) {
+        for x in v {
+            self.u32(x.to_bits());
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        // 至少补一个 0 作为结尾
+        let words = s.len() / 4 + 1;
+        self.u32(words as u32);
+        let start = self.buf.len();
+        self.buf.extend(s.as_bytes());
+        self.buf.resize(start + words * 4, 0);
+    }
+
+    /// 写 chunk 头和内容，并回填下一个 chunk 的位置
+    fn chunk(&mut self, id: &[u8; 4], body: impl FnOnce(&mut Self)) {
+        for &c in id {
+            self.u32(c as u32);
+        }
+        let offset_at = self.buf.len();
+        self.u32(0);
+        self.u32(1);
+        body(self);
+        let next = self.buf.len() as u32;
+        self.buf[offset_at..offset_at + 4].copy_from_slice(&next.to_be_bytes());
+    }
+
+    fn group(&mut self, g: &RvmGroup) {
+        self.chunk(b"CNTB", |w| {
+            w.u32(g.version);
+            w.string(&g.name);
+            w.f32s(&g.translation.to_array());
+            w.u32(g.material);
+        });
+        for p in &g.prims {
+            self.prim(p);
+        }
+        for c in &g.children {
+            self.group(c);
+        }
+        self.chunk(b"CNTE", |w| w.u32(g.version));
+    }
+
+    fn prim(&mut self, p: &RvmPrim) {
+        self.chunk(b"PRIM", |w| {
+            w.u32(p.version);
+            w.u32(p.kind.code());
+            let m = p.matrix;
+            for col in [m.x_axis, m.y_axis, m.z_axis, m.w_axis] {
+                w.f32s(&Vec4::truncate(col).to_array());
+            }
+            w.f32s(&p.bbox);
+            match &p.kind {
+                RvmPrimKind::Pyramid {
+                    bottom,
+                    top,
+                    offset,
+                    height,
+                } => {
+                    w.f32s(bottom);
+                    w.f32s(top);
+                    w.f32s(offset);
+                    w.f32s(&[*height]);
+                }
+                RvmPrimKind::Box { lengths } => w.f32s(lengths),
+                RvmPrimKind::RectangularTorus {
+                    inner_radius,
+                    outer_radius,
+                    height,
+                    angle,
+                } => w.f32s(&[*inner_radius, *outer_radius, *height, *angle]),
+                RvmPrimKind::CircularTorus {
+                    offset,
+                    radius,
+                    angle,
+                } => w.f32s(&[*offset, *radius, *angle]),
+                RvmPrimKind::EllipticalDish {
+                    base_radius,
+                    height,
+                }
+                | RvmPrimKind::SphericalDish {
+                    base_radius,
+                    height,
+                } => w.f32s(&[*base_radius, *height]),
+                RvmPrimKind::Snout {
+                    radius_b,
+                    radius_t,
+                    height,
+                    offset,
+                    bshear,
+                    tshear,
+                } => {
+                    w.f32s(&[*radius_b, *radius_t, *height]);
+                    w.f32s(offset);
+                    w.f32s(bshear);
+                    w.f32s(tshear);
+                }
+                RvmPrimKind::Cylinder { radius, height } => w.f32s(&[*radius, *height]),
+                RvmPrimKind::Sphere { diameter } => w.f32s(&[*diameter]),
+                RvmPrimKind::Line { a, b } => w.f32s(&[*a, *b]),
+                RvmPrimKind::FacetGroup { polygons } => {
+                    w.u32(polygons.len() as u32);
+                    for p in polygons {
+                        w.u32(p.contours.len() as u32);
+                        for c in &p.contours {
+                            w.u32(c.positions.len() as u32);
+                            for (pos, n) in c.positions.iter().zip(&c.normals) {
+                                w.f32s(&pos.to_array());
+                                w.f32s(&n.to_array());
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// 由导出的层级生成属性文件，`Ref`、`Type` 与 [`import`](super::import) 读取的字段一致
+fn rvm_to_att(file: &RvmFile, options: &RvmExportOptions) -> AttFile {
+    let by_name: HashMap<String, &RvmNodeInfo> = options
+        .hierarchy
+        .values()
+        .map(|n| (group_name(n), n))
+        .collect();
+    fn convert(g: &RvmGroup, by_name: &HashMap<String, &RvmNodeInfo>) -> AttNode {
+        let mut attributes = vec![];
+        if let Some(n) = by_name.get(&g.name) {
+            attributes.push(("Ref".to_string(), format!("={}", n.refno.to_pdms_str())));
+            attributes.push(("Type".to_string(), n.noun.clone()));
+        } else if let Some((noun, refno)) = g.name.rsplit_once(" =") {
+            // 没有层级信息时名称为 `NOUN =a/b`
+            attributes.push(("Ref".to_string(), format!("={refno}")));
+            attributes.push(("Type".to_string(), noun.to_string()));
+        } else if let Some(refno) = g.name.strip_prefix('=') {
+            attributes.push(("Ref".to_string(), format!("={refno}")));
+        }
+        AttNode {
+            name: g.name.clone(),
+            attributes,
+            children: g.children.iter().map(|c| convert(c, by_name)).collect(),
+        }
+    }
+    AttFile {
+        roots: file.roots.iter().map(|g| convert(g, &by_name)).collect(),
+    }
+}
+
+/// 编码属性文件，格式见 [`att`](super::att)
+pub fn encode_att(att: &AttFile) -> String {
+    fn write(node: &AttNode, out: &mut String) {
+        out.push_str(&format!("NEW {}\n", node.name));
+        for (k, v) in &node.attributes {
+            out.push_str(&format!("{k} := {v} &end&\n"));
+        }
+        for c in &node.children {
+            write(c, out);
+        }
+        out.push_str("END\n");
+    }
+    let mut out = String::from(
+        "CADC_Attributes_File v1.0 , start: NEW , end: END , name_end: := , sep: &end&\n",
+    );
+    for r in &att.roots {
+        write(r, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::EleInstGeosData;
+    use crate::prim_geo::{SBox, SCylinder};
+    use crate::rvm::{convert_rvm, parse_att, parse_rvm};
+    use bevy_transform::components::Transform;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn inst(shape: &dyn BrepShapeTrait, placement: Transform) -> EleInstGeo {
+        EleInstGeo {
+            geo_hash: shape.hash_unit_mesh_params(),
+            geo_param: shape.convert_to_geo_param().unwrap(),
+            transform: placement * shape.get_trans(),
+            visible: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let mut data = ShapeInstancesData::default();
+        let equi = refno(2);
+        let info = EleGeosInfo {
+            refno: equi,
+            owner_refno: refno(1),
+            owner_type: "ZONE".to_string(),
+            visible: true,
+            ..Default::default()
+        };
+        let key = info.get_inst_key();
+        let sbox = SBox {
+            center: Vec3::ZERO,
+            size: Vec3::new(100.0, 200.0, 300.0),
+        };
+        let cyl = SCylinder {
+            paxi_pt: Vec3::ZERO,
+            paxi_dir: Vec3::Z,
+            phei: 500.0,
+            pdia: 80.0,
+            center_in_mid: true,
+            ..Default::default()
+        };
+        data.insert_geos_data(
+            key,
+            EleInstGeosData {
+                refno: equi,
+                type_name: "EQUI".to_string(),
+                insts: vec![
+                    inst(&sbox, Transform::from_xyz(1000.0, 0.0, 0.0)),
+                    inst(&cyl, Transform::from_xyz(0.0, 2000.0, 0.0)),
+                ],
+                ..Default::default()
+            },
+        );
+        data.insert_info(equi, info);
+
+        let options = RvmExportOptions::default();
+        let (file, stats) = build_rvm(&data, &options);
+        assert_eq!(
+            stats,
+            RvmExportStats {
+                groups: 2,
+                prims: 2,
+                skipped: 0
+            }
+        );
+
+        let parsed = parse_rvm(&encode_rvm(&file)).unwrap();
+        assert_eq!(parsed.roots.len(), 1);
+        assert_eq!(parsed.roots[0].name, "ZONE =17496/1");
+        let group = &parsed.roots[0].children[0];
+        assert_eq!(group.name, "EQUI =17496/2");
+        assert!(
+            matches!(group.prims[0].kind, RvmPrimKind::Box { lengths } if lengths == [100.0, 200.0, 300.0])
+        );
+        let center = group.prims[0].matrix.transform_point3(Vec3::ZERO);
+        assert!(center.abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-5));
+
+        // 属性文件中的参考号在导入时还原
+        let att = parse_att(&encode_att(&rvm_to_att(&file, &options)));
+        let import = convert_rvm(&parsed, Some(&att));
+        assert!(import.shapes.get_inst_info(equi).is_some());
+        let geos = import
+            .shapes
+            .get_inst_geos(import.shapes.get_inst_info(equi).unwrap())
+            .unwrap();
+        assert_eq!(geos.len(), 2);
+        assert_eq!(
+            geos[0].geo_hash,
+            data.get_inst_geos(data.get_inst_info(equi).unwrap())
+                .unwrap()[0]
+                .geo_hash
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! pe 名称/描述全文模糊搜索
+//!
+//! 全局 [`NAME_INDEX`] 由 [`rebuild_name_index`] 从数据库全量构建，
+//! 之后通过 [`subscribe_name_index`] 订阅 `live::PE_LIVE_SQL` 增量刷新。
+
+pub mod name_index;
+
+pub use name_index::{MatchField, NameIndex, SearchHit};
+
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+
+/// 全局名称索引
+pub static NAME_INDEX: Lazy<RwLock<NameIndex>> = Lazy::new(|| RwLock::new(NameIndex::default()));
+
+/// 搜索 pe 名称和描述，`noun_filter` 为空时不过滤类型
+pub fn search_names(query: &str, limit: usize, noun_filter: &[&str]) -> Vec<SearchHit> {
+    NAME_INDEX.read().search(query, limit, noun_filter)
+}
+
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+struct PeNameRow {
+    refno: RefnoEnum,
+    noun: String,
+    name: Option<String>,
+    description: Option<String>,
+}
+
+const PE_NAME_FIELDS: &str = "refno, noun, fn::default_name(id) as name, refno.DESC as description";
+
+/// 全量构建名称索引，返回索引的元素数
+pub async fn rebuild_name_index() -> anyhow::Result<usize> {
+    let sql = format!("select {PE_NAME_FIELDS} from pe where !type::is_array(record::id(id))");
+    let rows: Vec<PeNameRow> = SUL_DB.query_take(&sql, 0).await?;
+    let mut index = NameIndex::default();
+    for row in rows {
+        index.upsert(
+            row.refno,
+            &row.noun,
+            row.name.as_deref().unwrap_or_default(),
+            row.description.as_deref(),
+        );
+    }
+    let len = index.len();
+    *NAME_INDEX.write() = index;
+    Ok(len)
+}
+
+/// 重新读取单个元素并更新索引
+pub async fn refresh_name_index(refno: RefnoEnum) -> anyhow::Result<()> {
+    let sql = format!("select {PE_NAME_FIELDS} from only {}", refno.to_pe_key());
+    let row: Option<PeNameRow> = SUL_DB.query_take(&sql, 0).await?;
+    let mut index = NAME_INDEX.write();
+    match row {
+        Some(row) => index.upsert(
+            row.refno,
+            &row.noun,
+            row.name.as_deref().unwrap_or_default(),
+            row.description.as_deref(),
+        ),
+        None => {
+            index.remove(refno);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "live")]
+mod live_refresh {
+    use super::*;
+    use crate::live::supervisor::{LiveEvent, LiveQueryManager, LiveSubscription};
+    use std::sync::Arc;
+
+    /// pe 实时订阅中与名称索引相关的字段
+    #[derive(Debug, Clone, Deserialize, SurrealValue)]
+    pub struct PeNameChange {
+        pub refno: RefnoEnum,
+        pub noun: String,
+        pub name: Option<String>,
+        #[serde(default)]
+        pub op: i32,
+    }
+
+    /// 订阅 pe 变更并刷新名称索引
+    ///
+    /// 删除（`op == 2` 或 DELETE 推送）直接从索引移除；其余变更先用推送的名称更新，
+    /// 描述不在订阅字段中，在后台任务中重新读取。
+    pub fn subscribe_name_index(manager: &mut LiveQueryManager) {
+        manager.subscribe::<PeNameChange>(
+            LiveSubscription {
+                name: "name_index".to_string(),
+                ..LiveSubscription::pe()
+            },
+            Arc::new(|event| {
+                let (deleted, data) = match event {
+                    LiveEvent::Live { action, data } => (action == "DELETE", data),
+                    LiveEvent::Replayed { action, data } => (action == "DELETE", data),
+                };
+                let refno = data.refno;
+                if deleted || data.op == 2 {
+                    NAME_INDEX.write().remove(refno);
+                    return;
+                }
+                {
+                    let mut index = NAME_INDEX.write();
+                    let desc = index.description(refno).map(str::to_string);
+                    index.upsert(
+                        refno,
+                        &data.noun,
+                        data.name.as_deref().unwrap_or_default(),
+                        desc.as_deref(),
+                    );
+                }
+                tokio::spawn(async move {
+                    if let Err(e) = refresh_name_index(refno).await {
+                        log::warn!("名称索引刷新失败 {}: {}", refno, e);
+                    }
+                });
+            }),
+        );
+    }
+}
+
+#[cfg(feature = "live")]
+pub use live_refresh::{PeNameChange, subscribe_name_index};
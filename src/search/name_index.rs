@@ -0,0 +1,363 @@
+//! pe 名称/描述的三元组（trigram）索引
+//!
+//! 文本先按字符转小写、去掉名称开头的 `/`，再在首尾各补一个空格后切分三元组。
+//! 查询时按命中的三元组数筛选候选，再按完全匹配 > 前缀 > 子串 > 模糊的顺序打分。
+
+use crate::RefnoEnum;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+type Trigram = [char; 3];
+
+/// 模糊匹配时，候选至少要命中查询三元组的比例
+const MIN_FUZZY_RATIO: f32 = 0.5;
+
+/// 命中的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MatchField {
+    Name,
+    Description,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SearchHit {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub score: f32,
+    pub field: MatchField,
+    /// 命中部分在 `name` 或 `description`（见 `field`）中的字节范围
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// 归一化后的文本，`offsets[i]` 为第 i 个字符在原文中的字节范围
+#[derive(Debug, Clone, Default)]
+struct NormText {
+    chars: Vec<char>,
+    offsets: Vec<Range<usize>>,
+}
+
+impl NormText {
+    fn new(text: &str, strip_slash: bool) -> Self {
+        let mut t = Self::default();
+        for (i, c) in text.char_indices() {
+            if strip_slash && t.chars.is_empty() && c == '/' {
+                continue;
+            }
+            t.chars.push(c.to_lowercase().next().unwrap_or(c));
+            t.offsets.push(i..i + c.len_utf8());
+        }
+        t
+    }
+
+    fn trigrams(&self) -> impl Iterator<Item = (usize, Trigram)> + '_ {
+        let padded: Vec<char> = std::iter::once(' ')
+            .chain(self.chars.iter().copied())
+            .chain(std::iter::once(' '))
+            .collect();
+        (0..padded.len().saturating_sub(2))
+            .map(move |i| (i, [padded[i], padded[i + 1], padded[i + 2]]))
+    }
+
+    fn find(&self, needle: &[char]) -> Option<usize> {
+        if needle.is_empty() || needle.len() > self.chars.len() {
+            return None;
+        }
+        self.chars.windows(needle.len()).position(|w| w == needle)
+    }
+
+    /// 字符区间 [start, end) 对应的原文字节范围
+    fn byte_range(&self, start: usize, end: usize) -> Range<usize> {
+        self.offsets[start].start..self.offsets[end - 1].end
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Doc {
+    refno: RefnoEnum,
+    noun: String,
+    name: String,
+    description: Option<String>,
+    norm_name: NormText,
+    norm_desc: NormText,
+}
+
+impl Doc {
+    fn trigrams(&self) -> HashSet<Trigram> {
+        self.norm_name
+            .trigrams()
+            .chain(self.norm_desc.trigrams())
+            .map(|(_, g)| g)
+            .collect()
+    }
+}
+
+/// 名称索引
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    docs: Vec<Option<Doc>>,
+    free: Vec<u32>,
+    by_refno: HashMap<RefnoEnum, u32>,
+    postings: HashMap<Trigram, HashSet<u32>>,
+}
+
+impl NameIndex {
+    pub fn len(&self) -> usize {
+        self.by_refno.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_refno.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn contains(&self, refno: RefnoEnum) -> bool {
+        self.by_refno.contains_key(&refno)
+    }
+
+    /// 当前索引的描述
+    pub fn description(&self, refno: RefnoEnum) -> Option<&str> {
+        let id = *self.by_refno.get(&refno)?;
+        self.docs[id as usize].as_ref()?.description.as_deref()
+    }
+
+    /// 新增或更新元素，名称和描述都为空时从索引中移除
+    pub fn upsert(&mut self, refno: RefnoEnum, noun: &str, name: &str, description: Option<&str>) {
+        self.remove(refno);
+        let description = description.filter(|d| !d.trim().is_empty());
+        if name.is_empty() && description.is_none() {
+            return;
+        }
+        let doc = Doc {
+            refno,
+            noun: noun.to_string(),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            norm_name: NormText::new(name, true),
+            norm_desc: description
+                .map(|d| NormText::new(d, false))
+                .unwrap_or_default(),
+        };
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.docs.push(None);
+                (self.docs.len() - 1) as u32
+            }
+        };
+        for g in doc.trigrams() {
+            self.postings.entry(g).or_default().insert(id);
+        }
+        self.docs[id as usize] = Some(doc);
+        self.by_refno.insert(refno, id);
+    }
+
+    pub fn remove(&mut self, refno: RefnoEnum) -> bool {
+        let Some(id) = self.by_refno.remove(&refno) else {
+            return false;
+        };
+        if let Some(doc) = self.docs[id as usize].take() {
+            for g in doc.trigrams() {
+                if let Some(ids) = self.postings.get_mut(&g) {
+                    ids.remove(&id);
+                    if ids.is_empty() {
+                        self.postings.remove(&g);
+                    }
+                }
+            }
+        }
+        self.free.push(id);
+        true
+    }
+
+    /// 搜索名称和描述，`noun_filter` 非空时只返回这些类型的元素
+    pub fn search(&self, query: &str, limit: usize, noun_filter: &[&str]) -> Vec<SearchHit> {
+        let q = NormText::new(query.trim(), true);
+        if q.chars.is_empty() || limit == 0 {
+            return vec![];
+        }
+        let noun_ok = |doc: &Doc| {
+            noun_filter.is_empty()
+                || noun_filter
+                    .iter()
+                    .any(|n| n.eq_ignore_ascii_case(&doc.noun))
+        };
+
+        // 查询的三元组（去掉首尾补位，子串查询不要求词边界）
+        let q_grams: HashSet<Trigram> = q
+            .trigrams()
+            .map(|(_, g)| g)
+            .filter(|g| g[0] != ' ' && g[2] != ' ')
+            .collect();
+        let candidates: Vec<(u32, usize)> = if q_grams.is_empty() {
+            // 不足 3 个字符，直接扫描
+            self.by_refno.values().map(|&id| (id, 0)).collect()
+        } else {
+            let mut hits: HashMap<u32, usize> = HashMap::new();
+            for g in &q_grams {
+                for &id in self.postings.get(g).into_iter().flatten() {
+                    *hits.entry(id).or_default() += 1;
+                }
+            }
+            let min_hits = ((q_grams.len() as f32) * MIN_FUZZY_RATIO).ceil() as usize;
+            hits.into_iter()
+                .filter(|(_, n)| *n >= min_hits.max(1))
+                .collect()
+        };
+
+        let mut results: Vec<SearchHit> = candidates
+            .into_iter()
+            .filter_map(|(id, gram_hits)| {
+                let doc = self.docs[id as usize].as_ref()?;
+                if !noun_ok(doc) {
+                    return None;
+                }
+                score_doc(doc, &q, &q_grams, gram_hits)
+            })
+            .collect();
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.name.len().cmp(&b.name.len()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+fn score_doc(
+    doc: &Doc,
+    q: &NormText,
+    q_grams: &HashSet<Trigram>,
+    gram_hits: usize,
+) -> Option<SearchHit> {
+    let hit = |score: f32, field: MatchField, highlights: Vec<Range<usize>>| SearchHit {
+        refno: doc.refno,
+        noun: doc.noun.clone(),
+        name: doc.name.clone(),
+        description: doc.description.clone(),
+        score,
+        field,
+        highlights,
+    };
+    let qn = q.chars.len();
+    let name = &doc.norm_name;
+    if let Some(pos) = name.find(&q.chars) {
+        let range = vec![name.byte_range(pos, pos + qn)];
+        // 越靠前、名称越短得分越高
+        let coverage = qn as f32 / name.chars.len() as f32;
+        let score = if qn == name.chars.len() {
+            100.0
+        } else if pos == 0 {
+            80.0 + 10.0 * coverage
+        } else {
+            60.0 + 10.0 * coverage - (pos as f32).min(10.0)
+        };
+        return Some(hit(score, MatchField::Name, range));
+    }
+    let desc = &doc.norm_desc;
+    if let Some(pos) = desc.find(&q.chars) {
+        let coverage = qn as f32 / desc.chars.len() as f32;
+        return Some(hit(
+            40.0 + 10.0 * coverage,
+            MatchField::Description,
+            vec![desc.byte_range(pos, pos + qn)],
+        ));
+    }
+    if q_grams.is_empty() || gram_hits == 0 {
+        return None;
+    }
+
+    // 模糊匹配：高亮命中的三元组
+    let (field, text) = {
+        let count = |t: &NormText| t.trigrams().filter(|(_, g)| q_grams.contains(g)).count();
+        if count(name) >= count(desc) {
+            (MatchField::Name, name)
+        } else {
+            (MatchField::Description, desc)
+        }
+    };
+    let mut highlights: Vec<Range<usize>> = vec![];
+    let mut matched = 0;
+    for (i, g) in text.trigrams() {
+        if !q_grams.contains(&g) {
+            continue;
+        }
+        matched += 1;
+        // 补位后的下标 i 对应原字符 i-1..i+2
+        let start = i.saturating_sub(1);
+        let end = (i + 2).min(text.chars.len());
+        let r = text.byte_range(start, end);
+        match highlights.last_mut() {
+            Some(last) if last.end >= r.start => last.end = last.end.max(r.end),
+            _ => highlights.push(r),
+        }
+    }
+    if matched == 0 {
+        return None;
+    }
+    let similarity = gram_hits as f32 / q_grams.len() as f32;
+    let score = match field {
+        MatchField::Name => 30.0 * similarity,
+        MatchField::Description => 20.0 * similarity,
+    };
+    Some(hit(score, field, highlights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(24383, n).into()
+    }
+
+    fn index() -> NameIndex {
+        let mut idx = NameIndex::default();
+        idx.upsert(refno(1), "PIPE", "/100-B-12", Some("Cooling water supply"));
+        idx.upsert(refno(2), "PIPE", "/100-B-12-A", None);
+        idx.upsert(refno(3), "VALV", "/V-1001", Some("Gate valve on 100-B-12"));
+        idx.upsert(refno(4), "EQUI", "/PUMP-P101", Some("Cooling pump"));
+        idx
+    }
+
+    #[test]
+    fn test_ranking_and_highlight() {
+        let idx = index();
+        let hits = idx.search("100-b-12", 10, &[]);
+        let refnos: Vec<_> = hits.iter().map(|h| h.refno).collect();
+        assert_eq!(refnos, vec![refno(1), refno(2), refno(3)]);
+        assert_eq!(&hits[0].name[hits[0].highlights[0].clone()], "100-B-12");
+        assert_eq!(hits[2].field, MatchField::Description);
+        let desc = hits[2].description.as_ref().unwrap();
+        assert_eq!(&desc[hits[2].highlights[0].clone()], "100-B-12");
+
+        let valves = idx.search("100-b-12", 10, &["valv"]);
+        assert_eq!(valves.len(), 1);
+        assert_eq!(valves[0].refno, refno(3));
+    }
+
+    #[test]
+    fn test_fuzzy_and_update() {
+        let mut idx = index();
+        // 拼写错误
+        let hits = idx.search("pump-p011", 5, &[]);
+        assert_eq!(hits.first().map(|h| h.refno), Some(refno(4)));
+        assert!(!hits[0].highlights.is_empty());
+
+        idx.upsert(refno(4), "EQUI", "/FAN-F1", None);
+        assert!(idx.search("pump", 5, &[]).is_empty());
+        assert_eq!(idx.search("fan", 5, &[])[0].refno, refno(4));
+        assert!(idx.remove(refno(4)));
+        assert!(idx.search("fan", 5, &[]).is_empty());
+        assert_eq!(idx.len(), 3);
+        assert_eq!(idx.search("v", 10, &[]).len(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+//! 核心类型与 protobuf 消息之间的转换
+
+use super::proto;
+use super::proto::attr_value::Value;
+use crate::types::{NamedAttrMap, NamedAttrValue, SPdmsElement};
+use crate::{RefU64, RefnoEnum, RefnoSesno};
+use bevy_transform::prelude::Transform;
+use glam::Vec3;
+
+impl From<RefnoEnum> for proto::Refno {
+    fn from(refno: RefnoEnum) -> Self {
+        Self {
+            refno: refno.refno().0,
+            sesno: refno.sesno().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<proto::Refno> for RefnoEnum {
+    fn from(refno: proto::Refno) -> Self {
+        match refno.sesno {
+            0 => RefnoEnum::Refno(RefU64(refno.refno)),
+            sesno => RefnoEnum::SesRef(RefnoSesno::new(RefU64(refno.refno), sesno)),
+        }
+    }
+}
+
+pub fn refno_list(refnos: impl IntoIterator<Item = RefnoEnum>) -> proto::RefnoList {
+    proto::RefnoList {
+        refnos: refnos.into_iter().map(Into::into).collect(),
+    }
+}
+
+impl From<&SPdmsElement> for proto::Pe {
+    fn from(pe: &SPdmsElement) -> Self {
+        Self {
+            refno: Some(pe.refno.into()),
+            owner: Some(pe.owner.into()),
+            name: pe.name.clone(),
+            noun: pe.noun.clone(),
+            dbnum: pe.dbnum,
+            sesno: pe.sesno,
+            status_code: pe.status_code.clone(),
+            cata_hash: pe.cata_hash.clone(),
+            lock: pe.lock,
+            deleted: pe.deleted,
+            typex: pe.typex,
+        }
+    }
+}
+
+/// `InvalidType` 没有对应的 protobuf 值，返回 None
+pub fn attr_value(value: NamedAttrValue) -> Option<proto::AttrValue> {
+    let value = match value {
+        NamedAttrValue::InvalidType => return None,
+        NamedAttrValue::IntegerType(v) => Value::IntValue(v),
+        NamedAttrValue::LongType(v) => Value::LongValue(v),
+        NamedAttrValue::F32Type(v) => Value::FloatValue(v),
+        NamedAttrValue::BoolType(v) => Value::BoolValue(v),
+        NamedAttrValue::StringType(v) => Value::StringValue(v),
+        NamedAttrValue::ElementType(v) | NamedAttrValue::WordType(v) => Value::WordValue(v),
+        NamedAttrValue::F32VecType(values) => Value::FloatList(proto::FloatList { values }),
+        NamedAttrValue::Vec3Type(v) => Value::FloatList(proto::FloatList {
+            values: v.to_array().to_vec(),
+        }),
+        NamedAttrValue::IntArrayType(values) => Value::IntList(proto::IntList { values }),
+        NamedAttrValue::BoolArrayType(values) => Value::BoolList(proto::BoolList { values }),
+        NamedAttrValue::StringArrayType(values) => Value::StringList(proto::StringList { values }),
+        NamedAttrValue::RefU64Type(v) => Value::RefnoValue(RefnoEnum::from(v).into()),
+        NamedAttrValue::RefnoEnumType(v) => Value::RefnoValue(v.into()),
+        NamedAttrValue::RefU64Array(v) => Value::RefnoList(refno_list(v)),
+    };
+    Some(proto::AttrValue { value: Some(value) })
+}
+
+impl From<proto::AttrValue> for NamedAttrValue {
+    fn from(value: proto::AttrValue) -> Self {
+        match value.value {
+            None => NamedAttrValue::InvalidType,
+            Some(Value::IntValue(v)) => NamedAttrValue::IntegerType(v),
+            Some(Value::LongValue(v)) => NamedAttrValue::LongType(v),
+            Some(Value::FloatValue(v)) => NamedAttrValue::F32Type(v),
+            Some(Value::BoolValue(v)) => NamedAttrValue::BoolType(v),
+            Some(Value::StringValue(v)) => NamedAttrValue::StringType(v),
+            Some(Value::WordValue(v)) => NamedAttrValue::WordType(v),
+            Some(Value::FloatList(l)) => NamedAttrValue::F32VecType(l.values),
+            Some(Value::IntList(l)) => NamedAttrValue::IntArrayType(l.values),
+            Some(Value::BoolList(l)) => NamedAttrValue::BoolArrayType(l.values),
+            Some(Value::StringList(l)) => NamedAttrValue::StringArrayType(l.values),
+            Some(Value::RefnoValue(r)) => NamedAttrValue::RefnoEnumType(r.into()),
+            Some(Value::RefnoList(l)) => {
+                NamedAttrValue::RefU64Array(l.refnos.into_iter().map(Into::into).collect())
+            }
+        }
+    }
+}
+
+impl From<NamedAttrMap> for proto::AttMap {
+    fn from(attmap: NamedAttrMap) -> Self {
+        Self {
+            attrs: attmap
+                .map
+                .into_iter()
+                .filter_map(|(k, v)| attr_value(v).map(|v| (k, v)))
+                .collect(),
+        }
+    }
+}
+
+impl From<proto::AttMap> for NamedAttrMap {
+    fn from(attmap: proto::AttMap) -> Self {
+        NamedAttrMap {
+            map: attmap
+                .attrs
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+        }
+    }
+}
+
+impl From<&Transform> for proto::WorldTransform {
+    fn from(t: &Transform) -> Self {
+        Self {
+            translation: t.translation.to_array().to_vec(),
+            rotation: t.rotation.to_array().to_vec(),
+            scale: t.scale.to_array().to_vec(),
+            matrix: t.to_matrix().to_cols_array().to_vec(),
+        }
+    }
+}
+
+/// 顶点数组按 xyz 展平
+pub fn flatten_vec3(values: &[Vec3]) -> Vec<f32> {
+    values.iter().flat_map(|v| v.to_array()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refno_roundtrip() {
+        let latest = RefnoEnum::from("17496/172806");
+        assert_eq!(RefnoEnum::from(proto::Refno::from(latest)), latest);
+        let history = RefnoEnum::SesRef(RefnoSesno::new(latest.refno(), 12));
+        let msg = proto::Refno::from(history);
+        assert_eq!(msg.sesno, 12);
+        assert_eq!(RefnoEnum::from(msg), history);
+    }
+
+    #[test]
+    fn test_attmap_roundtrip() {
+        let mut attmap = NamedAttrMap::default();
+        attmap
+            .map
+            .insert("NAME".into(), NamedAttrValue::StringType("/PIPE-1".into()));
+        attmap
+            .map
+            .insert("ANGL".into(), NamedAttrValue::F32Type(90.0));
+        attmap.map.insert(
+            "OWNER".into(),
+            NamedAttrValue::RefnoEnumType(RefnoEnum::from("17496/1")),
+        );
+        attmap.map.insert("BAD".into(), NamedAttrValue::InvalidType);
+
+        let msg = proto::AttMap::from(attmap.clone());
+        assert_eq!(msg.attrs.len(), 3);
+        attmap.map.remove("BAD");
+        assert_eq!(NamedAttrMap::from(msg), attmap);
+    }
+}
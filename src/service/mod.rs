@@ -0,0 +1,204 @@
+//! gRPC 服务（`grpc` 特性）
+//!
+//! 把常用的查询接口包装为 tonic 服务，供嵌入本 crate 的各个服务直接复用，
+//! 协议定义见 `proto/aios_core.proto`（构建时由 build.rs 生成代码，需要安装 protoc）。
+//!
+//! ```rust,ignore
+//! aios_core::init_surreal().await?;
+//! let service = aios_core::service::AiosCoreService::from_db_option();
+//! aios_core::service::serve("0.0.0.0:50051".parse()?, service).await?;
+//! ```
+
+pub mod convert;
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("aios.core.v1");
+}
+
+pub use proto::aios_core_client::AiosCoreClient;
+pub use proto::aios_core_server::{AiosCore, AiosCoreServer};
+
+use crate::RefnoEnum;
+use crate::mesh_store::{MeshBlobStore, load_mesh, mesh_store};
+use convert::{flatten_vec3, refno_list};
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+/// gRPC 服务实现
+#[derive(Clone)]
+pub struct AiosCoreService {
+    /// mesh 存储，按 geo_hash 读取原始精度 mesh
+    store: Arc<dyn MeshBlobStore>,
+}
+
+impl std::fmt::Debug for AiosCoreService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AiosCoreService")
+            .field("store", &self.store.describe())
+            .finish()
+    }
+}
+
+impl AiosCoreService {
+    pub fn new(store: Arc<dyn MeshBlobStore>) -> Self {
+        Self { store }
+    }
+
+    /// 使用 DbOption 中 `[mesh_store]` 配置的存储
+    pub fn from_db_option() -> Self {
+        Self::new(mesh_store())
+    }
+
+    pub fn into_server(self) -> AiosCoreServer<Self> {
+        AiosCoreServer::new(self)
+    }
+}
+
+fn internal(e: anyhow::Error) -> Status {
+    Status::internal(format!("{e:#}"))
+}
+
+fn required_refno(refno: Option<proto::Refno>) -> Result<RefnoEnum, Status> {
+    refno
+        .map(RefnoEnum::from)
+        .ok_or_else(|| Status::invalid_argument("缺少 refno"))
+}
+
+#[tonic::async_trait]
+impl AiosCore for AiosCoreService {
+    async fn get_pe(
+        &self,
+        request: Request<proto::RefnoRequest>,
+    ) -> Result<Response<proto::GetPeResponse>, Status> {
+        let refno = required_refno(request.into_inner().refno)?;
+        let pe = crate::get_pe(refno).await.map_err(internal)?;
+        Ok(Response::new(proto::GetPeResponse {
+            pe: pe.as_ref().map(Into::into),
+        }))
+    }
+
+    async fn get_att_map(
+        &self,
+        request: Request<proto::RefnoRequest>,
+    ) -> Result<Response<proto::AttMap>, Status> {
+        let refno = required_refno(request.into_inner().refno)?;
+        let attmap = crate::get_named_attmap(refno).await.map_err(internal)?;
+        Ok(Response::new(attmap.into()))
+    }
+
+    async fn get_children(
+        &self,
+        request: Request<proto::RefnoRequest>,
+    ) -> Result<Response<proto::RefnoList>, Status> {
+        let refno = required_refno(request.into_inner().refno)?;
+        let children = crate::get_children_refnos(refno).await.map_err(internal)?;
+        Ok(Response::new(refno_list(children)))
+    }
+
+    async fn query_by_type(
+        &self,
+        request: Request<proto::QueryByTypeRequest>,
+    ) -> Result<Response<proto::RefnoList>, Status> {
+        let req = request.into_inner();
+        if req.nouns.is_empty() {
+            return Err(Status::invalid_argument("nouns 不能为空"));
+        }
+        let nouns = req.nouns.iter().map(String::as_str).collect::<Vec<_>>();
+        let refnos = crate::rs_surreal::mdb::query_type_refnos_by_dbnum(
+            &nouns,
+            req.dbnum as u32,
+            req.has_children,
+            false,
+        )
+        .await
+        .map_err(internal)?;
+        Ok(Response::new(refno_list(refnos)))
+    }
+
+    async fn get_world_transform(
+        &self,
+        request: Request<proto::RefnoRequest>,
+    ) -> Result<Response<proto::GetWorldTransformResponse>, Status> {
+        let refno = required_refno(request.into_inner().refno)?;
+        let transform = crate::get_world_transform(refno).await.map_err(internal)?;
+        Ok(Response::new(proto::GetWorldTransformResponse {
+            transform: transform.as_ref().map(Into::into),
+        }))
+    }
+
+    async fn get_mesh_data(
+        &self,
+        request: Request<proto::GetMeshDataRequest>,
+    ) -> Result<Response<proto::GetMeshDataResponse>, Status> {
+        let req = request.into_inner();
+        let refnos = req
+            .refnos
+            .into_iter()
+            .map(RefnoEnum::from)
+            .collect::<Vec<_>>();
+        let geoms = crate::rs_surreal::inst::query_insts(&refnos, req.enable_holes)
+            .await
+            .map_err(internal)?;
+
+        let mut instances = Vec::new();
+        let mut geo_hashes = BTreeSet::new();
+        for geom in &geoms {
+            let world = geom.world_trans.to_matrix();
+            for inst in &geom.insts {
+                instances.push(proto::MeshInstance {
+                    refno: Some(geom.refno.into()),
+                    geo_hash: inst.geo_hash.clone(),
+                    matrix: (world * inst.transform.to_matrix())
+                        .to_cols_array()
+                        .to_vec(),
+                });
+                geo_hashes.insert(inst.geo_hash.clone());
+            }
+        }
+
+        let mut response = proto::GetMeshDataResponse {
+            instances,
+            ..Default::default()
+        };
+        if req.include_meshes {
+            let (meshes, missing) = load_meshes(self.store.as_ref(), geo_hashes).await;
+            response.meshes = meshes;
+            response.missing_meshes = missing;
+        }
+        Ok(Response::new(response))
+    }
+}
+
+/// 从存储读取 mesh，返回读到的 mesh 和缺失的 geo_hash
+async fn load_meshes(
+    store: &dyn MeshBlobStore,
+    geo_hashes: BTreeSet<String>,
+) -> (Vec<proto::Mesh>, Vec<String>) {
+    let mut meshes = Vec::new();
+    let mut missing = Vec::new();
+    for geo_hash in geo_hashes {
+        match load_mesh(store, &geo_hash, "").await {
+            Ok(Some(mesh)) => meshes.push(proto::Mesh {
+                geo_hash,
+                vertices: flatten_vec3(&mesh.vertices),
+                normals: flatten_vec3(&mesh.normals),
+                indices: mesh.indices,
+            }),
+            _ => missing.push(geo_hash),
+        }
+    }
+    (meshes, missing)
+}
+
+/// 在 `addr` 上启动 gRPC 服务，直到进程退出
+pub async fn serve(addr: SocketAddr, service: AiosCoreService) -> anyhow::Result<()> {
+    log::info!("aios_core gRPC 服务监听 {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(service.into_server())
+        .serve(addr)
+        .await?;
+    Ok(())
+}
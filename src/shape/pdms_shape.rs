@@ -598,6 +598,109 @@ impl PlantMesh {
         buffer.flush()?;
         Ok(())
     }
+
+    /// 顶点法线：已有且与顶点一一对应时直接使用，否则按三角面面积加权计算
+    pub fn vertex_normals(&self) -> Vec<Vec3> {
+        if self.normals.len() == self.vertices.len() {
+            return self.normals.clone();
+        }
+        let mut normals = vec![Vec3::ZERO; self.vertices.len()];
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| self.vertices[i as usize]);
+            let n = (b - a).cross(c - a);
+            for &i in tri {
+                normals[i as usize] += n;
+            }
+        }
+        normals.iter().map(|n| n.normalize_or_zero()).collect()
+    }
+
+    /// 以 OBJ 格式写入一个对象（含法线），`index_offset` 为之前已写入的顶点数
+    ///
+    /// 返回写入后的顶点偏移，便于在同一个文件中连续写入多个对象
+    pub fn write_obj_to(
+        &self,
+        writer: &mut impl Write,
+        name: Option<&str>,
+        index_offset: u32,
+    ) -> std::io::Result<u32> {
+        if let Some(name) = name {
+            writeln!(writer, "o {}", name)?;
+        }
+        for v in &self.vertices {
+            writeln!(writer, "v {:.3} {:.3} {:.3}", v.x, v.y, v.z)?;
+        }
+        for n in self.vertex_normals() {
+            writeln!(writer, "vn {:.4} {:.4} {:.4}", n.x, n.y, n.z)?;
+        }
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| i + index_offset + 1);
+            writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+        Ok(index_offset + self.vertices.len() as u32)
+    }
+
+    /// 以二进制 STL 格式写入，`name` 写入 80 字节的文件头
+    pub fn write_stl_binary_to(
+        &self,
+        writer: &mut impl Write,
+        name: Option<&str>,
+    ) -> std::io::Result<()> {
+        let mut header = [0u8; 80];
+        let name = name.unwrap_or("PlantMesh").as_bytes();
+        let len = name.len().min(80);
+        header[..len].copy_from_slice(&name[..len]);
+        writer.write_all(&header)?;
+
+        let vertex_normals = self.vertex_normals();
+        let tris = self.indices.chunks_exact(3);
+        writer.write_all(&(tris.len() as u32).to_le_bytes())?;
+        for tri in tris {
+            let idx = [tri[0], tri[1], tri[2]].map(|i| i as usize);
+            let [a, b, c] = idx.map(|i| self.vertices[i]);
+            // 退化三角形用顶点法线的平均值
+            let normal = (b - a).cross(c - a).try_normalize().unwrap_or_else(|| {
+                let sum: Vec3 = idx.iter().map(|&i| vertex_normals[i]).sum();
+                sum.normalize_or_zero()
+            });
+            for v in [normal, a, b, c] {
+                for f in v.to_array() {
+                    writer.write_all(&f.to_le_bytes())?;
+                }
+            }
+            writer.write_all(&0u16.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// 导出为二进制 STL 文件
+    pub fn write_stl_binary(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("无法创建文件: {:?}", path))?;
+        let mut writer = BufWriter::new(file);
+        let name = path.file_stem().map(|s| s.to_string_lossy());
+        self.write_stl_binary_to(&mut writer, name.as_deref())?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// 将多个 mesh 作为独立对象写入同一个 OBJ 文件
+///
+/// 名称一般取 `EleInstGeo::mesh_name()`，便于在查看器中区分各个几何体
+pub fn write_meshes_obj<'a, S: AsRef<str>>(
+    path: impl AsRef<Path>,
+    meshes: impl IntoIterator<Item = (Option<S>, &'a PlantMesh)>,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let file = File::create(path).with_context(|| format!("无法创建文件: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    let mut offset = 0;
+    for (name, mesh) in meshes {
+        offset = mesh.write_obj_to(&mut writer, name.as_ref().map(|s| s.as_ref()), offset)?;
+    }
+    writer.flush()?;
+    Ok(())
 }
 
 /// 三角形容差
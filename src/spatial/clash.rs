@@ -0,0 +1,251 @@
+//! 两组元素之间的碰撞检查
+//!
+//! 粗检：用 [`AccelerationTree`] 中的包围盒（按容差放大）找出可能碰撞的元素对；
+//! 细检：用生成的 `PlantMesh` 转成的三角网格计算最近接触，
+//! 距离小于容差即视为碰撞，网格互相穿透时给出穿透深度。
+//!
+//! 三角网格只描述表面，一个元素完全包在另一个元素内部时细检不会报告碰撞。
+
+use crate::RefnoEnum;
+use crate::accel_tree::acceleration_tree::AccelerationTree;
+use glam::Vec3;
+use parry3d::bounding_volume::BoundingVolume;
+use parry3d::math::Isometry;
+use parry3d::shape::TriMesh;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// 碰撞类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ClashKind {
+    /// 网格相交
+    Hard,
+    /// 未相交，但间隙小于容差
+    Clearance,
+}
+
+/// 一对碰撞元素
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClashPair {
+    pub a: RefnoEnum,
+    pub b: RefnoEnum,
+    pub kind: ClashKind,
+    /// 穿透深度，`Clearance` 时为 0
+    pub penetration: f32,
+    /// 间隙，`Hard` 时为 0
+    pub clearance: f32,
+    /// 接触点（两个最近点的中点，世界坐标）
+    pub contact_point: Vec3,
+    /// 从 a 指向 b 的接触法向
+    pub normal: Vec3,
+}
+
+/// 细检结果
+#[derive(Debug, Clone, Copy)]
+pub struct MeshContact {
+    /// 带符号距离，负值为穿透
+    pub dist: f32,
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+/// 粗检：返回包围盒（放大 `tolerance` 后）相交的元素对
+///
+/// 两组有重叠时同一对元素只返回一次，元素不会和自己配对。
+pub fn broad_phase(
+    tree: &AccelerationTree,
+    set_a: &[RefnoEnum],
+    set_b: &[RefnoEnum],
+    tolerance: f32,
+) -> Vec<(RefnoEnum, RefnoEnum)> {
+    let set_b: HashSet<_> = set_b.iter().map(|r| r.refno()).collect();
+    let mut seen = HashSet::new();
+    let mut pairs = vec![];
+    for &a in set_a {
+        let Some(bb_a) = tree.get_bbox(a) else {
+            continue;
+        };
+        let bounds = bb_a.aabb.loosened(tolerance.max(0.0));
+        for bb_b in tree.locate_intersecting_bounds(&bounds) {
+            if bb_b.refno == bb_a.refno || !set_b.contains(&bb_b.refno) {
+                continue;
+            }
+            let key = if bb_a.refno < bb_b.refno {
+                (bb_a.refno, bb_b.refno)
+            } else {
+                (bb_b.refno, bb_a.refno)
+            };
+            if seen.insert(key) {
+                pairs.push((a, bb_b.refno.into()));
+            }
+        }
+    }
+    pairs
+}
+
+/// 细检：两组世界坐标网格之间的最近接触，距离大于 `tolerance` 时返回 None
+pub fn narrow_phase(a: &[TriMesh], b: &[TriMesh], tolerance: f32) -> Option<MeshContact> {
+    let identity = Isometry::identity();
+    let mut best: Option<MeshContact> = None;
+    for ma in a {
+        for mb in b {
+            if !ma
+                .local_aabb()
+                .loosened(tolerance.max(0.0))
+                .intersects(&mb.local_aabb())
+            {
+                continue;
+            }
+            let Ok(Some(c)) = parry3d::query::contact(&identity, ma, &identity, mb, tolerance)
+            else {
+                continue;
+            };
+            if best.is_some_and(|b| b.dist <= c.dist) {
+                continue;
+            }
+            let p1: Vec3 = c.point1.into();
+            let p2: Vec3 = c.point2.into();
+            best = Some(MeshContact {
+                dist: c.dist,
+                point: (p1 + p2) * 0.5,
+                normal: Vec3::from(c.normal1.into_inner()),
+            });
+        }
+    }
+    best.filter(|c| c.dist <= tolerance)
+}
+
+/// 检查 `set_a` 与 `set_b` 之间的碰撞，`tolerance` 为允许的最小间隙
+///
+/// 网格通过 [`AccelerationTree::get_tri_mesh`] 加载，没有网格的元素跳过。
+/// 结果按穿透深度从大到小排列。
+pub async fn detect_clashes(
+    tree: &AccelerationTree,
+    set_a: &[RefnoEnum],
+    set_b: &[RefnoEnum],
+    tolerance: f32,
+) -> anyhow::Result<Vec<ClashPair>> {
+    let pairs = broad_phase(tree, set_a, set_b, tolerance);
+
+    let mut meshes: HashMap<RefnoEnum, Arc<Vec<TriMesh>>> = HashMap::new();
+    for &(a, b) in &pairs {
+        for r in [a, b] {
+            if meshes.contains_key(&r) {
+                continue;
+            }
+            // 立即释放 DashMap 的引用，避免后续加载时在同一分片上死锁
+            let loaded = tree
+                .get_tri_mesh(r)
+                .await
+                .map(|m| m.value().clone())
+                .unwrap_or_default();
+            meshes.insert(r, Arc::new(loaded));
+        }
+    }
+
+    let mut clashes: Vec<ClashPair> = pairs
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let c = narrow_phase(&meshes[&a], &meshes[&b], tolerance)?;
+            let hard = c.dist < 0.0;
+            Some(ClashPair {
+                a,
+                b,
+                kind: if hard {
+                    ClashKind::Hard
+                } else {
+                    ClashKind::Clearance
+                },
+                penetration: (-c.dist).max(0.0),
+                clearance: c.dist.max(0.0),
+                contact_point: c.point,
+                normal: c.normal,
+            })
+        })
+        .collect();
+    clashes.sort_by(|x, y| {
+        y.penetration
+            .total_cmp(&x.penetration)
+            .then_with(|| x.clearance.total_cmp(&y.clearance))
+    });
+    Ok(clashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::accel_tree::acceleration_tree::RStarBoundingBox;
+    use crate::shape::pdms_shape::PlantMesh;
+    use glam::Mat4;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    /// 中心在 `x`、边长为 1 的立方体网格
+    fn cube_mesh(x: f32) -> TriMesh {
+        let vertices = [
+            [-0.5, -0.5, -0.5],
+            [0.5, -0.5, -0.5],
+            [0.5, 0.5, -0.5],
+            [-0.5, 0.5, -0.5],
+            [-0.5, -0.5, 0.5],
+            [0.5, -0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [-0.5, 0.5, 0.5],
+        ]
+        .map(Vec3::from)
+        .to_vec();
+        let indices = vec![
+            0, 2, 1, 0, 3, 2, 4, 5, 6, 4, 6, 7, 0, 1, 5, 0, 5, 4, 2, 3, 7, 2, 7, 6, 1, 2, 6, 1, 6,
+            5, 0, 4, 7, 0, 7, 3,
+        ];
+        let mesh = PlantMesh {
+            vertices,
+            indices,
+            ..Default::default()
+        };
+        mesh.get_tri_mesh(Mat4::from_translation(Vec3::new(x, 0.0, 0.0)))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_narrow_phase() {
+        let base = [cube_mesh(0.0)];
+        let hard = narrow_phase(&base, &[cube_mesh(0.8)], 0.0).unwrap();
+        assert!((hard.dist + 0.2).abs() < 1e-4);
+
+        let near = narrow_phase(&base, &[cube_mesh(1.05)], 0.1).unwrap();
+        assert!((near.dist - 0.05).abs() < 1e-4);
+        assert!((near.point.x - 0.525).abs() < 1e-4);
+
+        assert!(narrow_phase(&base, &[cube_mesh(1.05)], 0.01).is_none());
+    }
+
+    #[test]
+    fn test_broad_phase() {
+        let bbox =
+            |n: u32, x: f32| RStarBoundingBox::from_aabb(cube_mesh(x).local_aabb(), refno(n));
+        let tree = AccelerationTree::load(vec![
+            bbox(1, 0.0),
+            bbox(2, 0.8),
+            bbox(3, 1.05),
+            bbox(4, 5.0),
+        ]);
+        let a = [refno(1), refno(2)];
+        let b = [refno(1), refno(2), refno(3), refno(4)];
+        let mut pairs = broad_phase(&tree, &a, &b, 0.1);
+        pairs.sort();
+        // (1,2) 只出现一次
+        assert_eq!(
+            pairs,
+            vec![
+                (refno(1), refno(2)),
+                (refno(1), refno(3)),
+                (refno(2), refno(3))
+            ]
+        );
+        assert_eq!(broad_phase(&tree, &a, &b, 0.0).len(), 2);
+    }
+}
@@ -101,7 +101,7 @@ pub struct HybridSpatialIndex {
 impl HybridSpatialIndex {
     /// 创建新的混合空间索引
     pub async fn new() -> Result<Self> {
-        let db_option = get_db_option();
+        let db_option = get_db_option()?;
 
         let index = Self {
             memory_index: Arc::new(RwLock::new(RTree::new())),
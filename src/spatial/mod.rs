@@ -1,5 +1,14 @@
 #[cfg(all(not(target_arch = "wasm32"), feature = "sqlite"))]
 pub mod hybrid_index;
+pub mod clash;
+pub mod nearest;
 pub mod pipe;
+pub mod raycast;
+pub mod selection;
 #[cfg(all(not(target_arch = "wasm32"), feature = "sqlite"))]
 pub mod sqlite;
+
+pub use clash::{ClashKind, ClashPair, detect_clashes};
+pub use nearest::{NearestHit, NearestTarget, query_nearest};
+pub use raycast::{RayHit, raycast, raycast_with};
+pub use selection::{Frustum, SelectMode, select_in_box, select_in_frustum};
@@ -0,0 +1,178 @@
+//! k 近邻查询
+//!
+//! 基于 [`AccelerationTree`] 的 R 树查找距离某个点或某个元素最近的 k 个元素，
+//! 距离为包围盒之间的最短距离（相交时为 0）。
+
+use crate::RefnoEnum;
+use crate::accel_tree::acceleration_tree::{AccelerationTree, RStarBoundingBox};
+use glam::Vec3;
+use nalgebra::Vector3;
+use parry3d::bounding_volume::Aabb;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// 查询目标
+#[derive(Debug, Clone, Copy)]
+pub enum NearestTarget {
+    /// 树中已有的元素，结果中不包含它自己
+    Refno(RefnoEnum),
+    Point(Vec3),
+}
+
+impl From<RefnoEnum> for NearestTarget {
+    fn from(refno: RefnoEnum) -> Self {
+        Self::Refno(refno)
+    }
+}
+
+impl From<Vec3> for NearestTarget {
+    fn from(pt: Vec3) -> Self {
+        Self::Point(pt)
+    }
+}
+
+/// 近邻结果
+#[derive(Debug, Clone)]
+pub struct NearestHit {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    pub distance: f32,
+    pub aabb: Aabb,
+}
+
+/// 两个包围盒的最短距离
+pub fn aabb_distance(a: &Aabb, b: &Aabb) -> f32 {
+    let gap = (a.mins - b.maxs)
+        .sup(&(b.mins - a.maxs))
+        .sup(&Vector3::zeros());
+    gap.norm()
+}
+
+/// 按距离从大到小排序，堆顶为当前第 k 近的结果
+struct HeapItem<'a>(f32, &'a RStarBoundingBox);
+
+impl PartialEq for HeapItem<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for HeapItem<'_> {}
+
+impl PartialOrd for HeapItem<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapItem<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 查询最近的 k 个元素，`noun_filter` 为空时不过滤类型
+///
+/// 目标为元素时先在树中找到它的包围盒；元素不在树中时返回空。
+pub fn query_nearest(
+    tree: &AccelerationTree,
+    target: impl Into<NearestTarget>,
+    k: usize,
+    noun_filter: &[&str],
+) -> Vec<NearestHit> {
+    if k == 0 {
+        return vec![];
+    }
+    let noun_ok = |bb: &RStarBoundingBox| {
+        noun_filter.is_empty() || noun_filter.iter().any(|n| n.eq_ignore_ascii_case(&bb.noun))
+    };
+    let to_hit = |(distance, bb): (f32, &RStarBoundingBox)| NearestHit {
+        refno: bb.refno.into(),
+        noun: bb.noun.clone(),
+        distance,
+        aabb: bb.aabb,
+    };
+
+    match target.into() {
+        NearestTarget::Point(pt) => tree
+            .nearest_neighbor_iter_with_distance_2(&[pt.x, pt.y, pt.z])
+            .filter(|(bb, _)| noun_ok(bb))
+            .take(k)
+            .map(|(bb, d2)| to_hit((d2.sqrt(), bb)))
+            .collect(),
+        NearestTarget::Refno(refno) => {
+            let Some(src) = tree.get_bbox(refno) else {
+                return vec![];
+            };
+            let center = src.aabb.center();
+            let radius = src.aabb.half_extents().norm();
+            // 按到中心的距离遍历，包围盒距离 >= 中心距离 - 半对角线，
+            // 当该下界超过当前第 k 近的距离时即可停止
+            let mut heap: BinaryHeap<HeapItem> = BinaryHeap::with_capacity(k + 1);
+            for (bb, d2) in
+                tree.nearest_neighbor_iter_with_distance_2(&[center.x, center.y, center.z])
+            {
+                if heap.len() == k && d2.sqrt() - radius > heap.peek().unwrap().0 {
+                    break;
+                }
+                if bb.refno == src.refno || !noun_ok(bb) {
+                    continue;
+                }
+                heap.push(HeapItem(aabb_distance(&src.aabb, &bb.aabb), bb));
+                if heap.len() > k {
+                    heap.pop();
+                }
+            }
+            heap.into_sorted_vec()
+                .into_iter()
+                .map(|HeapItem(d, bb)| to_hit((d, bb)))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use nalgebra::Point3;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn cube(n: u32, x: f32, size: f32, noun: &str) -> RStarBoundingBox {
+        RStarBoundingBox::new(
+            Aabb::new(Point3::new(x, 0.0, 0.0), Point3::new(x + size, 1.0, 1.0)),
+            refno(n),
+            noun.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_query_nearest() {
+        let tree = AccelerationTree::load(vec![
+            cube(1, 0.0, 1.0, "VALV"),
+            cube(2, 3.0, 1.0, "PIPE"),
+            cube(3, 6.0, 1.0, "VALV"),
+            // 中心很远但包围盒紧挨着元素 1
+            cube(4, 1.5, 100.0, "STRU"),
+        ]);
+
+        let hits = query_nearest(&tree, Vec3::new(-2.0, 0.5, 0.5), 2, &[]);
+        let refnos: Vec<_> = hits.iter().map(|h| h.refno).collect();
+        assert_eq!(refnos, vec![refno(1), refno(4)]);
+        assert!((hits[0].distance - 2.0).abs() < 1e-5);
+
+        let hits = query_nearest(&tree, refno(1), 2, &[]);
+        let refnos: Vec<_> = hits.iter().map(|h| h.refno).collect();
+        assert_eq!(refnos, vec![refno(4), refno(2)]);
+        assert!((hits[0].distance - 0.5).abs() < 1e-5);
+        assert!((hits[1].distance - 2.0).abs() < 1e-5);
+
+        let hits = query_nearest(&tree, refno(1), 5, &["valv"]);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].refno, refno(3));
+        assert!(query_nearest(&tree, refno(99), 3, &[]).is_empty());
+    }
+}
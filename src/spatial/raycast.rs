@@ -0,0 +1,190 @@
+//! CPU 端射线拾取
+//!
+//! 在 [`ShapeInstancesData`] 上求射线与各实例三角网格的最近交点，
+//! 先用元素的世界包围盒过滤，再把实例 mesh 变换到世界坐标求交。
+//! 不可见的元素和实例、负实体都不参与拾取。
+
+use crate::RefnoEnum;
+use crate::geometry::gltf_export::load_mesh_from_dir;
+use crate::geometry::{EleGeosInfo, EleInstGeo, GeoBasicType, ShapeInstancesData};
+use crate::shape::pdms_shape::PlantMesh;
+use glam::Vec3;
+use parry3d::query::{Ray, RayCast};
+use parry3d::shape::TriMesh;
+use std::collections::HashMap;
+
+/// 默认的 mesh 目录，与 [`AccelerationTree::get_tri_mesh`](crate::accel_tree::acceleration_tree::AccelerationTree::get_tri_mesh) 一致
+pub const DEFAULT_MESH_DIR: &str = "assets/meshes";
+
+/// 拾取结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub refno: RefnoEnum,
+    pub geo_hash: u64,
+    /// 世界坐标下的交点
+    pub point: Vec3,
+    /// 交点处的面法向，朝向射线起点一侧
+    pub normal: Vec3,
+    /// 起点到交点的距离
+    pub distance: f32,
+}
+
+/// 从 [`DEFAULT_MESH_DIR`] 读取 mesh 做射线拾取，见 [`raycast_with`]
+pub fn raycast(
+    origin: Vec3,
+    dir: Vec3,
+    data: &ShapeInstancesData,
+    max_dist: f32,
+) -> Option<RayHit> {
+    raycast_with(
+        origin,
+        dir,
+        data,
+        max_dist,
+        load_mesh_from_dir(DEFAULT_MESH_DIR),
+    )
+}
+
+/// 射线拾取，返回 `max_dist` 内最近的交点
+///
+/// `load_mesh` 按 geo_hash 提供 mesh，每个 geo_hash 只加载一次。
+pub fn raycast_with(
+    origin: Vec3,
+    dir: Vec3,
+    data: &ShapeInstancesData,
+    max_dist: f32,
+    mut load_mesh: impl FnMut(u64) -> Option<PlantMesh>,
+) -> Option<RayHit> {
+    let dir = dir.try_normalize()?;
+    if max_dist <= 0.0 {
+        return None;
+    }
+    let ray = Ray::new(origin.into(), dir.into());
+    let mut meshes: HashMap<u64, Option<PlantMesh>> = HashMap::new();
+    let mut best: Option<RayHit> = None;
+
+    let infos = data
+        .inst_info_map
+        .values()
+        .chain(data.inst_tubi_map.values())
+        .filter(|info| info.visible);
+    for info in infos {
+        let limit = best.map_or(max_dist, |b| b.distance);
+        if info
+            .aabb
+            .is_some_and(|aabb| aabb.cast_local_ray(&ray, limit, true).is_none())
+        {
+            continue;
+        }
+        let Some(geos) = data.inst_geos_map.get(&info.get_inst_key()) else {
+            continue;
+        };
+        for geo in geos.insts.iter().filter(|g| pickable(g)) {
+            let Some(mesh) = meshes
+                .entry(geo.geo_hash)
+                .or_insert_with(|| load_mesh(geo.geo_hash))
+            else {
+                continue;
+            };
+            let limit = best.map_or(max_dist, |b| b.distance);
+            if let Some(hit) = cast_geo(&ray, info, geo, mesh, limit) {
+                best = Some(hit);
+            }
+        }
+    }
+    best
+}
+
+fn pickable(geo: &EleInstGeo) -> bool {
+    geo.visible
+        && !matches!(
+            geo.geo_type,
+            GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+        )
+}
+
+fn cast_geo(
+    ray: &Ray,
+    info: &EleGeosInfo,
+    geo: &EleInstGeo,
+    mesh: &PlantMesh,
+    max_dist: f32,
+) -> Option<RayHit> {
+    let trans = info.get_geo_world_transform(geo).to_matrix();
+    let tri_mesh: TriMesh = mesh.get_tri_mesh(trans)?;
+    let hit = tri_mesh.cast_local_ray_and_get_normal(ray, max_dist, true)?;
+    let mut normal = Vec3::from(hit.normal);
+    // 射线方向已归一化，time_of_impact 即距离
+    if normal.dot(ray.dir.into()) > 0.0 {
+        normal = -normal;
+    }
+    Some(RayHit {
+        refno: info.refno,
+        geo_hash: geo.geo_hash,
+        point: ray.point_at(hit.time_of_impact).into(),
+        normal,
+        distance: hit.time_of_impact,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::EleInstGeosData;
+    use crate::geometry::csg::unit_box_mesh;
+    use bevy_transform::components::Transform;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    /// 在 `x` 处放一个 `size` 大小的盒子
+    fn add_box(data: &mut ShapeInstancesData, n: u32, x: f32, size: f32, visible: bool) {
+        let info = EleGeosInfo {
+            refno: refno(n),
+            visible,
+            world_transform: Transform::from_xyz(x, 0.0, 0.0),
+            ..Default::default()
+        };
+        let key = info.get_inst_key();
+        data.insert_geos_data(
+            key.clone(),
+            EleInstGeosData {
+                inst_key: key,
+                refno: refno(n),
+                insts: vec![EleInstGeo {
+                    geo_hash: 1,
+                    refno: refno(n),
+                    transform: Transform::from_scale(Vec3::splat(size)),
+                    visible: true,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        );
+        data.insert_info(refno(n), info);
+    }
+
+    #[test]
+    fn test_raycast() {
+        let mut data = ShapeInstancesData::default();
+        add_box(&mut data, 1, 10.0, 2.0, true);
+        add_box(&mut data, 2, 5.0, 1.0, false);
+        add_box(&mut data, 3, 20.0, 1.0, true);
+        let load = |_| Some(unit_box_mesh());
+
+        let hit = raycast_with(Vec3::ZERO, Vec3::X * 3.0, &data, 100.0, load).unwrap();
+        assert_eq!(hit.refno, refno(1));
+        assert_eq!(hit.geo_hash, 1);
+        assert!((hit.distance - 9.0).abs() < 1e-4);
+        assert!(hit.point.abs_diff_eq(Vec3::new(9.0, 0.0, 0.0), 1e-4));
+        assert!(hit.normal.abs_diff_eq(Vec3::NEG_X, 1e-4));
+
+        // 超出最大距离
+        assert!(raycast_with(Vec3::ZERO, Vec3::X, &data, 8.0, load).is_none());
+        // 反方向
+        let hit = raycast_with(Vec3::new(30.0, 0.0, 0.0), Vec3::NEG_X, &data, 100.0, load);
+        assert_eq!(hit.map(|h| h.refno), Some(refno(3)));
+    }
+}
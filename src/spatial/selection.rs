@@ -0,0 +1,175 @@
+//! 视锥体 / 包围盒框选
+//!
+//! 只用 [`AccelerationTree`] 中的世界包围盒判断，不需要加载 mesh，
+//! 用于界面上的框选。
+
+use crate::RefnoEnum;
+use crate::accel_tree::acceleration_tree::{AccelerationTree, RStarBoundingBox};
+use glam::{Mat4, Vec3, Vec4};
+use parry3d::bounding_volume::Aabb;
+
+/// 框选模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum SelectMode {
+    /// 包围盒完全在选择区域内
+    Contains,
+    /// 包围盒与选择区域相交即可
+    #[default]
+    Intersects,
+}
+
+/// 包围盒与视锥体的关系
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    Outside,
+    Intersects,
+    Inside,
+}
+
+/// 由若干平面围成的视锥体
+///
+/// 每个平面为 `(nx, ny, nz, d)`，满足 `n·p + d >= 0` 的点在内侧，法向不要求归一化。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+    pub planes: Vec<Vec4>,
+}
+
+impl Frustum {
+    pub fn new(planes: impl Into<Vec<Vec4>>) -> Self {
+        Self {
+            planes: planes.into(),
+        }
+    }
+
+    /// 从视图投影矩阵提取 6 个平面（深度范围 0..1，与 glam 的 `perspective_rh` 等一致）
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let [r0, r1, r2, r3] = [
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        ];
+        Self::new(vec![r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2])
+    }
+
+    /// 判断包围盒与视锥体的关系
+    ///
+    /// 对每个平面只检查离内侧最远和最近的两个角点，
+    /// 少数落在视锥角外侧的包围盒会被判为相交。
+    pub fn classify(&self, mins: Vec3, maxs: Vec3) -> Containment {
+        let mut result = Containment::Inside;
+        for plane in &self.planes {
+            let n = plane.truncate();
+            let positive = Vec3::select(n.cmpge(Vec3::ZERO), maxs, mins);
+            let negative = Vec3::select(n.cmpge(Vec3::ZERO), mins, maxs);
+            if n.dot(positive) + plane.w < 0.0 {
+                return Containment::Outside;
+            }
+            if n.dot(negative) + plane.w < 0.0 {
+                result = Containment::Intersects;
+            }
+        }
+        result
+    }
+
+    pub fn classify_aabb(&self, aabb: &Aabb) -> Containment {
+        self.classify(aabb.mins.into(), aabb.maxs.into())
+    }
+}
+
+struct FrustumSelection<'a> {
+    frustum: &'a Frustum,
+    mode: SelectMode,
+}
+
+impl rstar::SelectionFunction<RStarBoundingBox> for FrustumSelection<'_> {
+    fn should_unpack_parent(&self, envelope: &rstar::AABB<[f32; 3]>) -> bool {
+        self.frustum
+            .classify(envelope.lower().into(), envelope.upper().into())
+            != Containment::Outside
+    }
+
+    fn should_unpack_leaf(&self, bbox: &RStarBoundingBox) -> bool {
+        match self.frustum.classify_aabb(&bbox.aabb) {
+            Containment::Outside => false,
+            Containment::Intersects => self.mode == SelectMode::Intersects,
+            Containment::Inside => true,
+        }
+    }
+}
+
+/// 视锥体选择，返回世界包围盒满足 `mode` 的元素
+pub fn select_in_frustum(
+    tree: &AccelerationTree,
+    frustum: &Frustum,
+    mode: SelectMode,
+) -> Vec<RefnoEnum> {
+    tree.locate_with_selection_function(FrustumSelection { frustum, mode })
+        .map(|bb| bb.refno.into())
+        .collect()
+}
+
+/// 包围盒选择，返回世界包围盒满足 `mode` 的元素
+pub fn select_in_box(tree: &AccelerationTree, aabb: &Aabb, mode: SelectMode) -> Vec<RefnoEnum> {
+    match mode {
+        SelectMode::Contains => tree
+            .locate_contain_bounds(aabb)
+            .map(|bb| bb.refno.into())
+            .collect(),
+        SelectMode::Intersects => tree
+            .locate_intersecting_bounds(aabb)
+            .map(|bb| bb.refno.into())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use nalgebra::Point3;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn cube(n: u32, x: f32) -> RStarBoundingBox {
+        RStarBoundingBox::from_aabb(
+            Aabb::new(Point3::new(x, 0.0, 0.0), Point3::new(x + 1.0, 1.0, 1.0)),
+            refno(n),
+        )
+    }
+
+    fn sorted(mut v: Vec<RefnoEnum>) -> Vec<RefnoEnum> {
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_select() {
+        let tree = AccelerationTree::load(vec![cube(1, 0.0), cube(2, 2.5), cube(3, 10.0)]);
+
+        let area = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(3.0, 2.0, 2.0));
+        assert_eq!(
+            sorted(select_in_box(&tree, &area, SelectMode::Intersects)),
+            vec![refno(1), refno(2)]
+        );
+        assert_eq!(
+            select_in_box(&tree, &area, SelectMode::Contains),
+            vec![refno(1)]
+        );
+
+        // 从 z=10 向 -z 看，x/y 范围 [-1, 3]
+        let proj = Mat4::orthographic_rh(-2.0, 2.0, -2.0, 2.0, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::new(1.0, 0.0, 10.0), Vec3::new(1.0, 0.0, 0.0), Vec3::Y);
+        let frustum = Frustum::from_view_proj(proj * view);
+        assert_eq!(
+            sorted(select_in_frustum(&tree, &frustum, SelectMode::Intersects)),
+            vec![refno(1), refno(2)]
+        );
+        assert_eq!(
+            select_in_frustum(&tree, &frustum, SelectMode::Contains),
+            vec![refno(1)]
+        );
+    }
+}
@@ -9,7 +9,7 @@ use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, params};
 use crate::{RefU64, get_db_option};
 
 fn ensure_sqlite_enabled() -> Result<()> {
-    let db_option = get_db_option();
+    let db_option = get_db_option()?;
     if !db_option.sqlite_index_enabled() {
         return Err(anyhow!("未启用 SQLite 空间索引，请检查 DbOption 配置"));
     }
@@ -18,7 +18,7 @@ fn ensure_sqlite_enabled() -> Result<()> {
 
 pub fn open_connection() -> Result<Connection> {
     ensure_sqlite_enabled()?;
-    let db_option = get_db_option();
+    let db_option = get_db_option()?;
     let path = db_option.get_sqlite_index_path();
     if !path.exists() {
         return Err(anyhow!("SQLite 空间索引文件不存在: {}", path.display()));
@@ -35,7 +35,7 @@ pub fn open_connection() -> Result<Connection> {
 /// 打开可读写的 SQLite 连接（用于创建表或插入数据）
 pub fn open_connection_rw() -> Result<Connection> {
     ensure_sqlite_enabled()?;
-    let db_option = get_db_option();
+    let db_option = get_db_option()?;
     let path = db_option.get_sqlite_index_path();
     let conn = Connection::open(&path)
         .with_context(|| format!("无法打开 SQLite 空间索引文件 {}", path.display()))?;
@@ -125,7 +125,7 @@ pub async fn gen_pdms_major_table() -> anyhow::Result<()> {
     let major_codes = get_room_level_from_excel_refactor().await?.name_code_map;
     // 找到所有的site和zone
     let mut site_children_map = HashMap::new();
-    let db_option = get_db_option();
+    let db_option = get_db_option()?;
     let mdb = db_option.mdb_name();
     let sites = get_mdb_world_site_pes(mdb, DBType::DESI).await?;
     let mut site_name_map = HashMap::new();
@@ -1289,7 +1289,7 @@ async fn test_set_pbs_fixed_node() -> anyhow::Result<()> {
     use std::sync::Arc;
     let provider = QueryRouter::surreal_only()?;
     let aios_mgr = ProviderPdmsInterface::new(Arc::new(provider));
-    let db_option = get_db_option();
+    let db_option = get_db_option()?;
     set_pdms_major_code(&aios_mgr, &db_option.mdb_name).await?;
     let mut handles = vec![];
     set_pbs_fixed_node(&mut handles).await?;
@@ -0,0 +1,370 @@
+//! 全量同步断点
+//!
+//! 全量同步按 dbnum 分组、组内按参考号升序处理，每完成一批就把该 dbnum 的进度
+//! （最后处理的参考号与见到的最大会话号）写入 [`CheckpointStore`]。
+//! 中断后由 [`SyncManager::resume`](super::SyncManager::resume) 从断点继续：
+//! 已完成的 dbnum 跳过，未完成的从最后处理的参考号之后开始。
+//! 断点只在批次成功后推进，重放最后一批依赖目标端的 upsert 保证不会产生重复数据。
+//! `continue_on_error` 时失败批次的参考号记入 [`SyncCheckpoint::failed`]，继续时先重试它们。
+//!
+//! 断点存储与源/目标适配器无关：
+//!
+//! - [`FileCheckpointStore`]：本地 JSON 文件，`SyncManager` 的缺省存储
+//! - [`SurrealCheckpointStore`]：`SUL_DB` 的 `sync_checkpoint` 表
+//! - [`MemoryCheckpointStore`]：进程内，用于测试或不需要跨进程继续的任务
+
+use crate::rs_surreal::transaction::with_transaction;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::SurrealValue;
+use tokio::sync::Mutex;
+
+/// 断点表
+pub const CHECKPOINT_TABLE: &str = "sync_checkpoint";
+
+/// 缺省的断点文件目录
+pub const DEFAULT_CHECKPOINT_DIR: &str = "cache/sync_checkpoint";
+
+/// 单个 dbnum 的同步进度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SurrealValue)]
+pub struct SyncCheckpoint {
+    /// 同步任务名，区分不同的源/目标组合
+    pub name: String,
+    pub dbnum: u32,
+    /// 最后处理完成的参考号
+    pub last_refno: Option<RefnoEnum>,
+    /// 已处理元素中的最大会话号
+    pub sesno: Option<i32>,
+    /// 已处理数量
+    pub processed: usize,
+    /// 该 dbnum 的元素总数
+    pub total: usize,
+    pub completed: bool,
+    /// 同步失败、待重试的参考号
+    #[serde(default)]
+    pub failed: Vec<RefnoEnum>,
+}
+
+impl SyncCheckpoint {
+    pub fn new(name: &str, dbnum: u32, total: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            dbnum,
+            last_refno: None,
+            sesno: None,
+            processed: 0,
+            total,
+            completed: false,
+            failed: Vec::new(),
+        }
+    }
+
+    /// 过滤掉断点之前已处理的参考号，`refnos` 需按升序排列
+    pub fn remaining<'a>(&self, refnos: &'a [RefnoEnum]) -> &'a [RefnoEnum] {
+        if self.completed {
+            return &[];
+        }
+        match self.last_refno {
+            Some(last) => &refnos[refnos.partition_point(|r| *r <= last)..],
+            None => refnos,
+        }
+    }
+
+    /// 一批处理完成后推进断点，重试的批次在断点之前，不会使断点后退
+    pub fn advance(&mut self, batch: &[RefnoEnum], sesno: Option<i32>) {
+        if let Some(last) = batch.last() {
+            self.last_refno = self.last_refno.max(Some(*last));
+        }
+        self.processed += batch.len();
+        self.sesno = self.sesno.max(sesno);
+    }
+
+    /// 记录失败的批次：越过这些参考号继续，但保留在 `failed` 中等待重试
+    pub fn record_failed(&mut self, batch: &[RefnoEnum]) {
+        if let Some(last) = batch.last() {
+            self.last_refno = self.last_refno.max(Some(*last));
+        }
+        for refno in batch {
+            if !self.failed.contains(refno) {
+                self.failed.push(*refno);
+            }
+        }
+    }
+
+    /// 取出待重试的参考号，重试成功的由调用方 `advance`
+    pub fn take_failed(&mut self) -> Vec<RefnoEnum> {
+        std::mem::take(&mut self.failed)
+    }
+}
+
+/// 断点存储
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    /// 读取断点
+    async fn load(&self, name: &str, dbnum: u32) -> anyhow::Result<Option<SyncCheckpoint>>;
+
+    /// 写入断点
+    async fn save(&self, checkpoint: &SyncCheckpoint) -> anyhow::Result<()>;
+
+    /// 任务的全部断点，按 dbnum 排序
+    async fn list(&self, name: &str) -> anyhow::Result<Vec<SyncCheckpoint>>;
+
+    /// 清除任务的全部断点，重新开始全量同步前调用
+    async fn clear(&self, name: &str) -> anyhow::Result<()>;
+}
+
+/// 进程内断点存储
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    checkpoints: Mutex<BTreeMap<(String, u32), SyncCheckpoint>>,
+}
+
+impl MemoryCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for MemoryCheckpointStore {
+    async fn load(&self, name: &str, dbnum: u32) -> anyhow::Result<Option<SyncCheckpoint>> {
+        Ok(self
+            .checkpoints
+            .lock()
+            .await
+            .get(&(name.to_string(), dbnum))
+            .cloned())
+    }
+
+    async fn save(&self, checkpoint: &SyncCheckpoint) -> anyhow::Result<()> {
+        self.checkpoints.lock().await.insert(
+            (checkpoint.name.clone(), checkpoint.dbnum),
+            checkpoint.clone(),
+        );
+        Ok(())
+    }
+
+    async fn list(&self, name: &str) -> anyhow::Result<Vec<SyncCheckpoint>> {
+        Ok(self
+            .checkpoints
+            .lock()
+            .await
+            .values()
+            .filter(|cp| cp.name == name)
+            .cloned()
+            .collect())
+    }
+
+    async fn clear(&self, name: &str) -> anyhow::Result<()> {
+        self.checkpoints.lock().await.retain(|(n, _), _| n != name);
+        Ok(())
+    }
+}
+
+/// 本地 JSON 文件断点存储，每个任务一个文件 `{dir}/{name}.json`
+#[derive(Debug)]
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+    /// 串行化同一进程内的读改写
+    lock: Mutex<()>,
+}
+
+impl Default for FileCheckpointStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHECKPOINT_DIR)
+    }
+}
+
+impl FileCheckpointStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn path_of(&self, name: &str) -> PathBuf {
+        let file = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        self.dir.join(format!("{file}.json"))
+    }
+
+    fn read(&self, name: &str) -> anyhow::Result<BTreeMap<u32, SyncCheckpoint>> {
+        match std::fs::read(self.path_of(name)) {
+            Ok(bytes) => {
+                let list: Vec<SyncCheckpoint> = serde_json::from_slice(&bytes)?;
+                Ok(list.into_iter().map(|cp| (cp.dbnum, cp)).collect())
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, name: &str, checkpoints: &BTreeMap<u32, SyncCheckpoint>) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path_of(name);
+        // 先写临时文件再改名，中断时不会留下写了一半的断点
+        let tmp = path.with_extension("tmp");
+        let list = checkpoints.values().collect::<Vec<_>>();
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&list)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for FileCheckpointStore {
+    async fn load(&self, name: &str, dbnum: u32) -> anyhow::Result<Option<SyncCheckpoint>> {
+        let _guard = self.lock.lock().await;
+        Ok(self.read(name)?.remove(&dbnum))
+    }
+
+    async fn save(&self, checkpoint: &SyncCheckpoint) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut checkpoints = self.read(&checkpoint.name)?;
+        checkpoints.insert(checkpoint.dbnum, checkpoint.clone());
+        self.write(&checkpoint.name, &checkpoints)
+    }
+
+    async fn list(&self, name: &str) -> anyhow::Result<Vec<SyncCheckpoint>> {
+        let _guard = self.lock.lock().await;
+        Ok(self.read(name)?.into_values().collect())
+    }
+
+    async fn clear(&self, name: &str) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().await;
+        match std::fs::remove_file(self.path_of(name)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 保存在 `SUL_DB` 中的断点，记录为 `sync_checkpoint:[name, dbnum]`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SurrealCheckpointStore;
+
+fn record_key(name: &str, dbnum: u32) -> String {
+    format!(
+        "{CHECKPOINT_TABLE}:[{}, {}]",
+        serde_json::Value::from(name),
+        dbnum
+    )
+}
+
+const CHECKPOINT_FIELDS: &str =
+    "name, dbnum, last_refno, sesno, processed, total, completed, failed ?? [] AS failed";
+
+#[async_trait]
+impl CheckpointStore for SurrealCheckpointStore {
+    async fn load(&self, name: &str, dbnum: u32) -> anyhow::Result<Option<SyncCheckpoint>> {
+        let sql = format!(
+            "SELECT {CHECKPOINT_FIELDS} FROM {}",
+            record_key(name, dbnum)
+        );
+        let rows: Vec<SyncCheckpoint> = SUL_DB.query_take(&sql, 0).await?;
+        Ok(rows.into_iter().next())
+    }
+
+    async fn save(&self, checkpoint: &SyncCheckpoint) -> anyhow::Result<()> {
+        let key = record_key(&checkpoint.name, checkpoint.dbnum);
+        with_transaction(|tx| {
+            tx.upsert_content(&key, checkpoint)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list(&self, name: &str) -> anyhow::Result<Vec<SyncCheckpoint>> {
+        let sql = format!(
+            "SELECT {CHECKPOINT_FIELDS} FROM {CHECKPOINT_TABLE} WHERE name = {} ORDER BY dbnum",
+            serde_json::Value::from(name)
+        );
+        SUL_DB.query_take(&sql, 0).await
+    }
+
+    async fn clear(&self, name: &str) -> anyhow::Result<()> {
+        let sql = format!(
+            "DELETE {CHECKPOINT_TABLE} WHERE name = {}",
+            serde_json::Value::from(name)
+        );
+        SUL_DB.query_response(&sql).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    fn refnos(seqs: &[u32]) -> Vec<RefnoEnum> {
+        seqs.iter()
+            .map(|&s| RefnoEnum::Refno(RefU64::from_two_nums(17496, s)))
+            .collect()
+    }
+
+    #[test]
+    fn test_remaining_after_advance() {
+        let all = refnos(&[1, 2, 3, 4, 5]);
+        let mut cp = SyncCheckpoint::new("default", 17496, all.len());
+        assert_eq!(cp.remaining(&all).len(), 5);
+
+        cp.advance(&all[..2], Some(10));
+        cp.advance(&all[2..3], Some(8));
+        assert_eq!(cp.processed, 3);
+        assert_eq!(cp.sesno, Some(10));
+        assert_eq!(cp.remaining(&all), &all[3..]);
+
+        cp.record_failed(&all[3..4]);
+        assert_eq!(cp.processed, 3);
+        assert_eq!(cp.remaining(&all), &all[4..]);
+        assert_eq!(cp.take_failed(), &all[3..4]);
+        assert!(cp.failed.is_empty());
+
+        cp.completed = true;
+        assert!(cp.remaining(&all).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileCheckpointStore::new(dir.path());
+        let all = refnos(&[1, 2, 3]);
+        let mut cp = SyncCheckpoint::new("a/b", 17496, all.len());
+        cp.advance(&all[..1], Some(3));
+        cp.record_failed(&all[1..2]);
+        store.save(&cp).await.unwrap();
+        store
+            .save(&SyncCheckpoint::new("a/b", 17497, 0))
+            .await
+            .unwrap();
+
+        let reopened = FileCheckpointStore::new(dir.path());
+        assert_eq!(reopened.load("a/b", 17496).await.unwrap(), Some(cp));
+        assert_eq!(reopened.list("a/b").await.unwrap().len(), 2);
+        reopened.clear("a/b").await.unwrap();
+        assert!(reopened.list("a/b").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_key() {
+        assert_eq!(
+            record_key("default", 17496),
+            "sync_checkpoint:[\"default\", 17496]"
+        );
+    }
+}
@@ -5,6 +5,7 @@
 use super::{SyncStatistics, SyncTask, SyncTaskStatus};
 use crate::db_adapter::DatabaseAdapter;
 use crate::types::*;
+use crate::utils::refno_span::with_refno_span;
 use anyhow::Result;
 use futures::future::join_all;
 use std::sync::Arc;
@@ -84,17 +85,20 @@ impl ConcurrentExecutor {
                 let _permit = permit; // 持有许可直到任务完成
 
                 for refno in chunk_vec {
-                    match Self::sync_single_pe(&source_clone, &target_clone, refno).await {
-                        Ok(_) => {
-                            let mut stats = stats_clone.write().await;
-                            stats.successful_records += 1;
+                    with_refno_span(refno, async {
+                        match Self::sync_single_pe(&source_clone, &target_clone, refno).await {
+                            Ok(_) => {
+                                let mut stats = stats_clone.write().await;
+                                stats.successful_records += 1;
+                            }
+                            Err(e) => {
+                                let mut stats = stats_clone.write().await;
+                                stats.failed_records += 1;
+                                log::error!("同步 PE {} 失败: {}", refno.refno().0, e);
+                            }
                         }
-                        Err(e) => {
-                            let mut stats = stats_clone.write().await;
-                            stats.failed_records += 1;
-                            log::error!("同步 PE {} 失败: {}", refno.refno().0, e);
-                        }
-                    }
+                    })
+                    .await;
                 }
                 Ok(())
             });
@@ -0,0 +1,323 @@
+//! 几何实例数据与 pe 层级的一致性检查
+//!
+//! 部分同步或中断的模型生成之后，inst_relate / inst_info / inst_geo / tubi_relate
+//! 可能与 pe 表脱节。[`check`] 交叉比对这些表并报告：
+//!
+//! - 孤立的 inst_relate（pe 不存在或已删除）与指向不存在 inst_info 的 inst_relate
+//! - 没有 inst_relate 引用的 inst_info、没有 geo_relate / tubi_relate 引用的 inst_geo
+//! - pe 的 `inst_relate_id` 指向不存在的记录（有链接但无几何）
+//! - pe 的 `cata_hash` 对应的 inst_info 不存在
+//! - 有世界变换但缺少 AABB 的 inst_relate
+//! - 孤立的 tubi_relate（pe 不存在、已删除或几何不存在）
+//!
+//! 开启 `repair` 后删除孤立记录、清除失效链接，并重新计算缺失的 AABB：
+//!
+//! ```rust,ignore
+//! let report = consistency::check(ConsistencyOptions { repair: true, ..Default::default() }).await?;
+//! println!("{}", report.summary());
+//! ```
+
+use crate::rs_surreal::transaction::with_transaction;
+use crate::utils::RecordIdExt;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, update_inst_relate_aabbs_by_refnos};
+use surrealdb::types::RecordId;
+
+/// 检查选项
+#[derive(Debug, Clone)]
+pub struct ConsistencyOptions {
+    /// 是否修复发现的问题
+    pub repair: bool,
+    /// 只检查指定 dbnum 的 pe 与 inst_relate；inst_info / inst_geo 按 cata_hash 共享，始终全表检查
+    pub dbnum: Option<u32>,
+    /// 修复时每个事务处理的记录数
+    pub chunk_size: usize,
+}
+
+impl Default for ConsistencyOptions {
+    fn default() -> Self {
+        Self {
+            repair: false,
+            dbnum: None,
+            chunk_size: 200,
+        }
+    }
+}
+
+/// 修复结果统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairSummary {
+    pub deleted_inst_relates: usize,
+    pub deleted_inst_infos: usize,
+    pub deleted_inst_geos: usize,
+    pub deleted_tubi_relates: usize,
+    pub cleared_pe_links: usize,
+    pub regenerated_aabbs: usize,
+}
+
+/// 检查报告
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyReport {
+    /// pe 不存在或已删除的 inst_relate
+    pub orphan_inst_relates: Vec<RecordId>,
+    /// 指向不存在 inst_info 的 inst_relate
+    pub dangling_inst_relates: Vec<RecordId>,
+    /// 没有 inst_relate 引用的 inst_info
+    pub orphan_inst_infos: Vec<RecordId>,
+    /// 没有 geo_relate 与 tubi_relate 引用的 inst_geo
+    pub orphan_inst_geos: Vec<RecordId>,
+    /// `inst_relate_id` 指向不存在记录的 pe
+    pub pe_missing_geometry: Vec<RefnoEnum>,
+    /// `cata_hash` 对应的 inst_info 不存在的 pe
+    pub dangling_cata_hashes: Vec<RefnoEnum>,
+    /// 缺少 AABB 的实例
+    pub missing_aabbs: Vec<RefnoEnum>,
+    /// pe 不存在、已删除或几何不存在的 tubi_relate
+    pub orphan_tubi_relates: Vec<RecordId>,
+    /// 开启修复时的结果
+    pub repair: Option<RepairSummary>,
+}
+
+impl ConsistencyReport {
+    /// 发现的问题总数
+    pub fn issue_count(&self) -> usize {
+        self.orphan_inst_relates.len()
+            + self.dangling_inst_relates.len()
+            + self.orphan_inst_infos.len()
+            + self.orphan_inst_geos.len()
+            + self.pe_missing_geometry.len()
+            + self.dangling_cata_hashes.len()
+            + self.missing_aabbs.len()
+            + self.orphan_tubi_relates.len()
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.issue_count() == 0
+    }
+
+    /// 单行摘要，用于日志输出
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "孤立 inst_relate: {}, 悬空 inst_relate: {}, 孤立 inst_info: {}, 孤立 inst_geo: {}, \
+             无几何 pe: {}, 悬空 cata_hash: {}, 缺失 AABB: {}, 孤立 tubi_relate: {}",
+            self.orphan_inst_relates.len(),
+            self.dangling_inst_relates.len(),
+            self.orphan_inst_infos.len(),
+            self.orphan_inst_geos.len(),
+            self.pe_missing_geometry.len(),
+            self.dangling_cata_hashes.len(),
+            self.missing_aabbs.len(),
+            self.orphan_tubi_relates.len()
+        );
+        if let Some(r) = &self.repair {
+            s.push_str(&format!(
+                "；已修复: 删除 inst_relate {}, inst_info {}, inst_geo {}, tubi_relate {}, 清除 pe 链接 {}, 重算 AABB {}",
+                r.deleted_inst_relates,
+                r.deleted_inst_infos,
+                r.deleted_inst_geos,
+                r.deleted_tubi_relates,
+                r.cleared_pe_links,
+                r.regenerated_aabbs
+            ));
+        }
+        s
+    }
+}
+
+/// pe / inst_relate 的 dbnum 过滤条件（两者 id 均为 `{dbnum}_{seq}`）
+fn dbnum_filter(dbnum: Option<u32>) -> String {
+    dbnum
+        .map(|n| format!(" AND string::starts_with(<string> record::id(id), '{n}_')"))
+        .unwrap_or_default()
+}
+
+fn orphan_inst_relate_sql(dbnum: Option<u32>) -> String {
+    format!(
+        "SELECT VALUE id FROM inst_relate WHERE (in = NONE OR !record::exists(in) OR in.deleted = true){}",
+        dbnum_filter(dbnum)
+    )
+}
+
+fn dangling_inst_relate_sql(dbnum: Option<u32>) -> String {
+    format!(
+        "SELECT VALUE id FROM inst_relate WHERE (out = NONE OR !record::exists(out)){}",
+        dbnum_filter(dbnum)
+    )
+}
+
+const ORPHAN_INST_INFO_SQL: &str =
+    "SELECT VALUE id FROM inst_info WHERE array::len(<-inst_relate) = 0";
+
+/// 第 2 条结果：tubi_relate 直接引用 inst_geo，不经过 geo_relate
+const ORPHAN_INST_GEO_SQL: &str = r#"
+    LET $tubi_geos = array::distinct(SELECT VALUE geo FROM tubi_relate WHERE geo != NONE);
+    SELECT VALUE id FROM inst_geo WHERE array::len(<-geo_relate) = 0 AND id NOTINSIDE $tubi_geos;
+"#;
+
+fn pe_missing_geometry_sql(dbnum: Option<u32>) -> String {
+    format!(
+        "SELECT VALUE id FROM pe WHERE deleted != true AND inst_relate_id != NONE AND !record::exists(inst_relate_id){}",
+        dbnum_filter(dbnum)
+    )
+}
+
+/// 只检查已生成实例的 pe，尚未生成模型的 pe 没有 inst_info 属于正常情况
+fn dangling_cata_hash_sql(dbnum: Option<u32>) -> String {
+    format!(
+        "SELECT VALUE id FROM pe WHERE deleted != true AND inst_relate_id != NONE \
+         AND cata_hash != NONE AND cata_hash != '' \
+         AND !record::exists(type::record('inst_info', cata_hash)){}",
+        dbnum_filter(dbnum)
+    )
+}
+
+fn missing_aabb_sql(dbnum: Option<u32>) -> String {
+    format!(
+        "SELECT VALUE in FROM inst_relate WHERE aabb = NONE AND world_trans.d != NONE AND record::exists(in){}",
+        dbnum_filter(dbnum)
+    )
+}
+
+const ORPHAN_TUBI_RELATE_SQL: &str = "SELECT VALUE id FROM tubi_relate WHERE in = NONE OR !record::exists(in) OR in.deleted = true OR (geo != NONE AND !record::exists(geo))";
+
+/// 检查几何实例数据与 pe 层级的一致性，`options.repair` 为 true 时同时修复
+pub async fn check(options: ConsistencyOptions) -> anyhow::Result<ConsistencyReport> {
+    let dbnum = options.dbnum;
+    let mut report = ConsistencyReport {
+        orphan_inst_relates: SUL_DB.query_take(&orphan_inst_relate_sql(dbnum), 0).await?,
+        dangling_inst_relates: SUL_DB
+            .query_take(&dangling_inst_relate_sql(dbnum), 0)
+            .await?,
+        orphan_inst_infos: SUL_DB.query_take(ORPHAN_INST_INFO_SQL, 0).await?,
+        orphan_inst_geos: SUL_DB.query_take(ORPHAN_INST_GEO_SQL, 1).await?,
+        pe_missing_geometry: SUL_DB
+            .query_take(&pe_missing_geometry_sql(dbnum), 0)
+            .await?,
+        dangling_cata_hashes: SUL_DB.query_take(&dangling_cata_hash_sql(dbnum), 0).await?,
+        missing_aabbs: SUL_DB.query_take(&missing_aabb_sql(dbnum), 0).await?,
+        orphan_tubi_relates: SUL_DB.query_take(ORPHAN_TUBI_RELATE_SQL, 0).await?,
+        repair: None,
+    };
+    log::info!("一致性检查: {}", report.summary());
+
+    if options.repair && !report.is_consistent() {
+        report.repair = Some(repair(&report, &options).await?);
+        log::info!("一致性修复: {}", report.summary());
+    }
+    Ok(report)
+}
+
+/// 按检查结果修复
+///
+/// 先删除失效的关系与链接，删除后新产生的孤立 inst_info / inst_geo 会重新查询一并删除
+async fn repair(
+    report: &ConsistencyReport,
+    options: &ConsistencyOptions,
+) -> anyhow::Result<RepairSummary> {
+    let chunk_size = options.chunk_size.max(1);
+    let mut summary = RepairSummary::default();
+
+    // 悬空 cata_hash 的 pe：删除其 inst_relate 并清除链接，等待重新生成模型
+    let mut relates = report.orphan_inst_relates.clone();
+    relates.extend(report.dangling_inst_relates.iter().cloned());
+    let relate_keys = relates
+        .iter()
+        .map(|id| id.to_raw())
+        .chain(
+            report
+                .dangling_cata_hashes
+                .iter()
+                .map(|r| r.to_inst_relate_key()),
+        )
+        .collect::<Vec<_>>();
+    summary.deleted_inst_relates = delete_records(&relate_keys, chunk_size).await?;
+
+    let tubi_keys = report
+        .orphan_tubi_relates
+        .iter()
+        .map(|id| id.to_raw())
+        .collect::<Vec<_>>();
+    summary.deleted_tubi_relates = delete_records(&tubi_keys, chunk_size).await?;
+
+    let pe_links = report
+        .pe_missing_geometry
+        .iter()
+        .chain(&report.dangling_cata_hashes)
+        .copied()
+        .collect::<Vec<_>>();
+    for chunk in pe_links.chunks(chunk_size) {
+        with_transaction(|tx| {
+            for refno in chunk {
+                tx.push(format!(
+                    "UPDATE {} SET inst_relate_id = NONE",
+                    refno.to_pe_key()
+                ));
+            }
+            Ok(())
+        })
+        .await?;
+    }
+    summary.cleared_pe_links = pe_links.len();
+
+    // 删除 inst_info 时其 geo_relate 边随之删除，因此先处理 inst_info 再查询 inst_geo
+    let infos: Vec<RecordId> = SUL_DB.query_take(ORPHAN_INST_INFO_SQL, 0).await?;
+    let info_keys = infos.iter().map(|id| id.to_raw()).collect::<Vec<_>>();
+    summary.deleted_inst_infos = delete_records(&info_keys, chunk_size).await?;
+
+    let geos: Vec<RecordId> = SUL_DB.query_take(ORPHAN_INST_GEO_SQL, 1).await?;
+    let geo_keys = geos.iter().map(|id| id.to_raw()).collect::<Vec<_>>();
+    summary.deleted_inst_geos = delete_records(&geo_keys, chunk_size).await?;
+
+    if !report.missing_aabbs.is_empty() {
+        update_inst_relate_aabbs_by_refnos(&report.missing_aabbs, false).await?;
+        summary.regenerated_aabbs = report.missing_aabbs.len();
+    }
+    Ok(summary)
+}
+
+async fn delete_records(keys: &[String], chunk_size: usize) -> anyhow::Result<usize> {
+    for chunk in keys.chunks(chunk_size) {
+        with_transaction(|tx| {
+            for key in chunk {
+                tx.delete(key);
+            }
+            Ok(())
+        })
+        .await?;
+    }
+    Ok(keys.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+
+    #[test]
+    fn test_dbnum_filter() {
+        assert_eq!(dbnum_filter(None), "");
+        let sql = orphan_inst_relate_sql(Some(17496));
+        assert!(sql.starts_with("SELECT VALUE id FROM inst_relate WHERE (in = NONE"));
+        assert!(sql.ends_with("string::starts_with(<string> record::id(id), '17496_')"));
+        assert!(dangling_cata_hash_sql(None).contains("type::record('inst_info', cata_hash)"));
+    }
+
+    #[test]
+    fn test_report_summary() {
+        let mut report = ConsistencyReport::default();
+        assert!(report.is_consistent());
+        report
+            .missing_aabbs
+            .push(RefnoEnum::Refno(RefU64::from_two_nums(1, 2)));
+        report
+            .pe_missing_geometry
+            .push(RefnoEnum::Refno(RefU64::from_two_nums(1, 3)));
+        assert_eq!(report.issue_count(), 2);
+        assert!(!report.is_consistent());
+        assert!(report.summary().contains("缺失 AABB: 1"));
+        report.repair = Some(RepairSummary {
+            regenerated_aabbs: 1,
+            ..Default::default()
+        });
+        assert!(report.summary().contains("重算 AABB 1"));
+    }
+}
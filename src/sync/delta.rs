@@ -0,0 +1,367 @@
+//! 基于会话号的增量同步
+//!
+//! 每次保存在 `ses:[dbnum, sesno]` 中留下一条会话记录，pe 的 `sesno` 为其最后一次修改所在的会话。
+//! [`sync_since`] 沿源库的会话时间线找出指定会话之后变化的元素，拉取并写入本地 `SUL_DB`：
+//!
+//! - 新增或修改的元素：覆盖写入 pe 与属性表，按源库顺序重写受影响父节点的 `pe_owner`
+//! - 已删除元素：标记 `deleted`，删除其 inst_relate / tubi_relate；共享的 inst_info 不在此删除，
+//!   由 [`consistency::check`](super::consistency::check) 统一清理
+//! - 失效属性、层级与派生属性缓存，清除元素及其子孙缓存的世界变换
+//! - 写入会话记录后刷新磁盘缓存的 db 会话号（[`refresh_sessions`]），旧的世界变换条目不再命中
+//! - 修改过的元素交给已注册的几何后端（`facade::set_geometry_backend`）重新生成几何；
+//!   未注册时列在 [`DeltaReport::stale_geometry`] 中，由调用方安排生成
+//! - 会话记录一并写入本地，下次可从 [`DeltaReport::latest_sesno`] 继续
+//!
+//! ```rust,ignore
+//! let source = connect_source().await?; // Surreal<Any>
+//! let report = delta::sync_since(&source, 17496, last_sesno).await?;
+//! last_sesno = report.latest_sesno;
+//! ```
+
+use crate::cache::disk::refresh_sessions;
+use crate::cache::invalidate_refno;
+use crate::derived_attr::DERIVED_ATTRS;
+use crate::pe::SPdmsElement;
+use crate::rs_surreal::hierarchy::{ChildRow, rewrite_children};
+use crate::rs_surreal::transaction::{Transaction, with_transaction};
+use crate::transform::invalidate_world_trans_cache_recursive;
+use crate::types::NamedAttrMap;
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+use surrealdb::types as surrealdb_types;
+use surrealdb::types::{Datetime, SurrealValue};
+
+/// 删除实例关系、写入元素时每个事务处理的元素数
+const DELETE_CHUNK: usize = 200;
+
+/// 一次会话（保存）记录
+#[derive(Debug, Clone, Serialize, Deserialize, SurrealValue)]
+pub struct SesRecord {
+    pub dbnum: u32,
+    pub sesno: u32,
+    pub date: Option<Datetime>,
+    /// 保存所在的计算机名
+    pub computer_name: Option<String>,
+}
+
+/// 会话之后变化过的元素
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+pub struct ChangedPe {
+    pub id: RefnoEnum,
+    pub sesno: i32,
+    pub deleted: bool,
+}
+
+/// 子节点及其软删除标记
+#[derive(Debug, Clone, Deserialize, SurrealValue)]
+struct ChildEdge {
+    refno: RefnoEnum,
+    deleted: Option<bool>,
+}
+
+/// 增量同步的数据来源
+#[async_trait]
+pub trait DeltaSource: Send + Sync {
+    /// 会话号大于 `after` 的会话，按会话号升序
+    async fn sessions_after(&self, dbnum: u32, after: u32) -> anyhow::Result<Vec<SesRecord>>;
+
+    /// 会话 `after` 之后变化过的当前版本元素
+    async fn changed_after(&self, dbnum: u32, after: u32) -> anyhow::Result<Vec<ChangedPe>>;
+
+    async fn fetch_pes(&self, refnos: &[RefnoEnum]) -> anyhow::Result<Vec<SPdmsElement>>;
+
+    async fn fetch_attmaps(&self, refnos: &[RefnoEnum]) -> anyhow::Result<Vec<NamedAttrMap>>;
+
+    /// 父节点下按序号排列的全部子节点：(参考号, 是否已软删除)
+    async fn fetch_children(&self, parent: RefnoEnum) -> anyhow::Result<Vec<(RefnoEnum, bool)>>;
+}
+
+/// 增量同步结果
+#[derive(Debug, Clone, Default)]
+pub struct DeltaReport {
+    pub dbnum: u32,
+    /// 起始会话号（不含）
+    pub since: u32,
+    /// 同步到的最新会话号，下次从这里继续
+    pub latest_sesno: u32,
+    /// 起始会话之后的会话记录，按会话号升序
+    pub sessions: Vec<SesRecord>,
+    /// 新增或修改的元素
+    pub modified: Vec<RefnoEnum>,
+    /// 已删除的元素
+    pub deleted: Vec<RefnoEnum>,
+    /// 需要重新生成几何、但未能生成的元素（未注册几何后端或生成失败）
+    pub stale_geometry: Vec<RefnoEnum>,
+    /// 已重新生成几何的元素数
+    pub regenerated: usize,
+    /// 清除了世界变换缓存的元素数（含子孙）
+    pub world_trans_cleared: usize,
+}
+
+impl DeltaReport {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// 会话时间线：`dbnum` 中会话号大于 `after` 的会话，按会话号升序
+fn ses_timeline_sql(dbnum: u32, after: u32) -> String {
+    format!(
+        "SELECT dbnum, sesno, date, computer_name FROM ses WHERE dbnum = {dbnum} AND sesno > {after} ORDER BY sesno"
+    )
+}
+
+/// 会话 `after` 之后变化过的当前版本元素（历史版本的 id 为数组，不参与）
+fn changed_pes_sql(dbnum: u32, after: u32) -> String {
+    format!(
+        "SELECT id, sesno, deleted ?? false AS deleted FROM pe \
+         WHERE dbnum = {dbnum} AND sesno > {after} AND !type::is_array(record::id(id))"
+    )
+}
+
+fn pe_keys(refnos: &[RefnoEnum]) -> String {
+    refnos
+        .iter()
+        .map(|r| r.to_pe_key())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 本地 `SUL_DB` 中的会话时间线
+pub async fn query_ses_timeline(dbnum: u32, after: u32) -> anyhow::Result<Vec<SesRecord>> {
+    SUL_DB.query_take(&ses_timeline_sql(dbnum, after), 0).await
+}
+
+/// 另一个 SurrealDB 库（如主库）作为来源
+#[async_trait]
+impl DeltaSource for Surreal<Any> {
+    async fn sessions_after(&self, dbnum: u32, after: u32) -> anyhow::Result<Vec<SesRecord>> {
+        self.query_take(&ses_timeline_sql(dbnum, after), 0).await
+    }
+
+    async fn changed_after(&self, dbnum: u32, after: u32) -> anyhow::Result<Vec<ChangedPe>> {
+        self.query_take(&changed_pes_sql(dbnum, after), 0).await
+    }
+
+    async fn fetch_pes(&self, refnos: &[RefnoEnum]) -> anyhow::Result<Vec<SPdmsElement>> {
+        if refnos.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!("SELECT * FROM [{}]", pe_keys(refnos));
+        self.query_take(&sql, 0).await
+    }
+
+    async fn fetch_attmaps(&self, refnos: &[RefnoEnum]) -> anyhow::Result<Vec<NamedAttrMap>> {
+        if refnos.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!("SELECT VALUE refno.* FROM [{}]", pe_keys(refnos));
+        self.query_take(&sql, 0).await
+    }
+
+    async fn fetch_children(&self, parent: RefnoEnum) -> anyhow::Result<Vec<(RefnoEnum, bool)>> {
+        let sql = format!(
+            "select in as refno, in.deleted as deleted from {}<-pe_owner where in != none",
+            parent.to_pe_key()
+        );
+        let rows: Vec<ChildEdge> = self.query_take(&sql, 0).await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.refno, r.deleted.unwrap_or(false)))
+            .collect())
+    }
+}
+
+/// 写入一批修改过的元素，返回受影响的父节点（新旧 owner）
+async fn apply_modified(
+    source: &dyn DeltaSource,
+    refnos: &[RefnoEnum],
+) -> anyhow::Result<BTreeSet<RefnoEnum>> {
+    let pes = source.fetch_pes(refnos).await?;
+    let attmaps = source.fetch_attmaps(refnos).await?;
+    let old_owners: Vec<RefnoEnum> = SUL_DB
+        .query_take(
+            &format!(
+                "SELECT VALUE owner FROM [{}] WHERE owner != NONE",
+                pe_keys(refnos)
+            ),
+            0,
+        )
+        .await?;
+
+    let mut parents: BTreeSet<RefnoEnum> = old_owners.into_iter().collect();
+    let mut tx = Transaction::new();
+    for pe in &pes {
+        let key = pe.refno.to_pe_key();
+        tx.push(format!(
+            "UPSERT {} CONTENT {}",
+            key,
+            pe.gen_sur_json(Some(key.clone()))
+        ));
+        parents.insert(pe.owner);
+    }
+    for attmap in &attmaps {
+        let refno = attmap.get_refno_or_default();
+        let Some(json) = attmap.gen_sur_json() else {
+            log::warn!("生成 {} 的属性数据失败，跳过", refno);
+            continue;
+        };
+        tx.push(format!(
+            "UPSERT {} CONTENT {}",
+            refno.to_table_key(&attmap.get_type()),
+            json
+        ));
+    }
+    tx.commit().await?;
+    parents.retain(|p| p.is_valid());
+    Ok(parents)
+}
+
+/// 按源库顺序重写父节点的子节点
+async fn apply_children(source: &dyn DeltaSource, parent: RefnoEnum) -> anyhow::Result<()> {
+    let children = source
+        .fetch_children(parent)
+        .await?
+        .into_iter()
+        .map(|(refno, deleted)| ChildRow {
+            refno,
+            deleted: Some(deleted),
+        })
+        .collect::<Vec<_>>();
+    let mut tx = Transaction::new();
+    rewrite_children(&mut tx, parent, &children);
+    tx.commit().await
+}
+
+/// 从 `source` 同步 `dbnum` 中会话 `sesno` 之后的变化到本地 `SUL_DB`
+#[tracing::instrument(name = "delta_sync", skip(source))]
+pub async fn sync_since(
+    source: &dyn DeltaSource,
+    dbnum: u32,
+    sesno: u32,
+) -> anyhow::Result<DeltaReport> {
+    let sessions = source.sessions_after(dbnum, sesno).await?;
+    let mut report = DeltaReport {
+        dbnum,
+        since: sesno,
+        latest_sesno: sessions.last().map_or(sesno, |s| s.sesno),
+        ..Default::default()
+    };
+    if sessions.is_empty() {
+        return Ok(report);
+    }
+
+    let changed = source.changed_after(dbnum, sesno).await?;
+    for pe in &changed {
+        report.latest_sesno = report.latest_sesno.max(pe.sesno.max(0) as u32);
+        if pe.deleted {
+            report.deleted.push(pe.id);
+        } else {
+            report.modified.push(pe.id);
+        }
+    }
+
+    let mut parents = BTreeSet::new();
+    for chunk in report.modified.chunks(DELETE_CHUNK) {
+        parents.extend(apply_modified(source, chunk).await?);
+    }
+    for parent in parents {
+        apply_children(source, parent).await?;
+        invalidate_refno(parent).await;
+    }
+
+    for pe in &changed {
+        invalidate_refno(pe.id).await;
+        DERIVED_ATTRS.invalidate_element(pe.id);
+    }
+    for &refno in &report.modified {
+        report.world_trans_cleared += invalidate_world_trans_cache_recursive(refno).await?;
+    }
+    for chunk in report.deleted.chunks(DELETE_CHUNK) {
+        with_transaction(|tx| {
+            for refno in chunk {
+                tx.delete(&refno.to_inst_relate_key());
+                tx.delete(&format!("{}->tubi_relate", refno.to_pe_key()));
+                tx.push(format!(
+                    "UPDATE {} SET deleted = true, inst_relate_id = NONE, tubi_id = NONE",
+                    refno.to_pe_key()
+                ));
+            }
+            Ok(())
+        })
+        .await?;
+    }
+
+    with_transaction(|tx| {
+        for ses in &sessions {
+            tx.upsert_content(&format!("ses:[{}, {}]", ses.dbnum, ses.sesno), ses)?;
+        }
+        Ok(())
+    })
+    .await?;
+    refresh_sessions();
+    report.sessions = sessions;
+
+    regenerate_geometry(&mut report).await;
+
+    log::info!(
+        "dbnum {} 会话 {} -> {}: 修改 {} 个, 删除 {} 个, 重新生成几何 {} 个, 待生成 {} 个",
+        dbnum,
+        sesno,
+        report.latest_sesno,
+        report.modified.len(),
+        report.deleted.len(),
+        report.regenerated,
+        report.stale_geometry.len()
+    );
+    Ok(report)
+}
+
+/// 用已注册的几何后端重新生成修改过的元素
+async fn regenerate_geometry(report: &mut DeltaReport) {
+    if report.modified.is_empty() {
+        return;
+    }
+    let backend = match crate::facade::geometry_backend() {
+        Ok(backend) => backend,
+        Err(e) => {
+            log::warn!("{}，{} 个元素的几何需另行生成", e, report.modified.len());
+            report.stale_geometry = report.modified.clone();
+            return;
+        }
+    };
+    match backend.regen(&report.modified, None).await {
+        Ok(()) => {
+            report.regenerated = report.modified.len();
+            for &refno in &report.modified {
+                DERIVED_ATTRS.invalidate_geometry(refno);
+            }
+        }
+        Err(e) => {
+            log::warn!("重新生成几何失败: {}", e);
+            report.stale_geometry = report.modified.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_pes_sql() {
+        let sql = changed_pes_sql(17496, 880);
+        assert!(sql.contains("WHERE dbnum = 17496 AND sesno > 880"));
+        assert!(sql.contains("!type::is_array(record::id(id))"));
+    }
+
+    #[test]
+    fn test_pe_keys() {
+        let refnos: Vec<RefnoEnum> = vec!["17496_1".into(), "17496_2".into()];
+        assert_eq!(pe_keys(&refnos), "pe:17496_1,pe:17496_2");
+    }
+}
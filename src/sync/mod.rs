@@ -4,7 +4,10 @@
 
 pub mod batch_optimizer;
 pub mod cache_layer;
+pub mod checkpoint;
 pub mod concurrent_executor;
+pub mod consistency;
+pub mod delta;
 pub mod performance_monitor;
 pub mod sync_manager;
 pub mod sync_strategy;
@@ -12,6 +15,10 @@ pub mod sync_task;
 
 pub use batch_optimizer::*;
 pub use cache_layer::*;
+pub use checkpoint::{
+    CheckpointStore, FileCheckpointStore, MemoryCheckpointStore, SurrealCheckpointStore,
+    SyncCheckpoint,
+};
 pub use concurrent_executor::*;
 pub use performance_monitor::*;
 pub use sync_manager::*;
@@ -54,6 +54,8 @@ impl PerformanceMonitor {
 
     /// 记录同步统计
     pub async fn record(&self, stats: SyncStatistics) {
+        crate::metrics::observe_sync(&stats);
+
         // 更新历史记录
         {
             let mut history = self.history.write().await;
@@ -270,6 +272,123 @@ pub enum Severity {
     Critical,
 }
 
+/// 延迟直方图的桶数量，上界从 64µs 起按 2 倍递增（最后一个桶约 33s 以上）
+const LATENCY_BUCKETS: usize = 20;
+
+/// 延迟直方图
+///
+/// 按对数分桶记录耗时，用于查询路由的代价估算；
+/// `decay` 将所有计数减半，使新样本的权重高于历史样本
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS],
+    count: u64,
+    errors: u64,
+    total_us: u64,
+}
+
+/// 延迟直方图摘要
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencySummary {
+    /// 样本数
+    pub count: u64,
+    /// 平均耗时（毫秒）
+    pub mean_ms: f64,
+    /// 中位数（毫秒，桶上界）
+    pub p50_ms: f64,
+    /// 95 分位（毫秒，桶上界）
+    pub p95_ms: f64,
+    /// 错误率
+    pub error_rate: f64,
+}
+
+impl LatencyHistogram {
+    fn bucket_index(us: u64) -> usize {
+        let mut idx = 0;
+        let mut bound = 64u64;
+        while idx + 1 < LATENCY_BUCKETS && us > bound {
+            bound <<= 1;
+            idx += 1;
+        }
+        idx
+    }
+
+    fn bucket_upper_ms(idx: usize) -> f64 {
+        (64u64 << idx) as f64 / 1000.0
+    }
+
+    /// 记录一次耗时，`ok` 为 false 时同时计入错误数
+    pub fn record(&mut self, elapsed: Duration, ok: bool) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        self.buckets[Self::bucket_index(us)] += 1;
+        self.count += 1;
+        self.total_us = self.total_us.saturating_add(us);
+        if !ok {
+            self.errors += 1;
+        }
+    }
+
+    /// 所有计数减半
+    pub fn decay(&mut self) {
+        for b in self.buckets.iter_mut() {
+            *b /= 2;
+        }
+        self.count = self.buckets.iter().sum();
+        self.errors /= 2;
+        self.total_us /= 2;
+    }
+
+    /// 样本数
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 错误率
+    pub fn error_rate(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.errors as f64 / self.count as f64).min(1.0)
+        }
+    }
+
+    /// 平均耗时（毫秒）
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_us as f64 / self.count as f64 / 1000.0
+        }
+    }
+
+    /// 分位数（毫秒），返回所在桶的上界；无样本时返回 None
+    pub fn percentile_ms(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let target = ((self.count as f64) * q.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut acc = 0;
+        for (idx, n) in self.buckets.iter().enumerate() {
+            acc += n;
+            if acc >= target {
+                return Some(Self::bucket_upper_ms(idx));
+            }
+        }
+        Some(Self::bucket_upper_ms(LATENCY_BUCKETS - 1))
+    }
+
+    /// 生成摘要
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            count: self.count,
+            mean_ms: self.mean_ms(),
+            p50_ms: self.percentile_ms(0.5).unwrap_or_default(),
+            p95_ms: self.percentile_ms(0.95).unwrap_or_default(),
+            error_rate: self.error_rate(),
+        }
+    }
+}
+
 /// Prometheus 指标导出器
 pub struct PrometheusExporter {
     monitor: Arc<PerformanceMonitor>,
@@ -370,6 +489,25 @@ mod tests {
         assert!(!anomalies.is_empty());
     }
 
+    #[test]
+    fn test_latency_histogram() {
+        let mut hist = LatencyHistogram::default();
+        assert_eq!(hist.percentile_ms(0.5), None);
+
+        for _ in 0..9 {
+            hist.record(Duration::from_micros(50), true);
+        }
+        hist.record(Duration::from_millis(10), false);
+
+        assert_eq!(hist.count(), 10);
+        assert_eq!(hist.percentile_ms(0.5), Some(0.064));
+        assert!(hist.percentile_ms(1.0).unwrap() >= 10.0);
+        assert!((hist.error_rate() - 0.1).abs() < 1e-9);
+
+        hist.decay();
+        assert_eq!(hist.count(), 4);
+    }
+
     #[test]
     fn test_performance_report() {
         let report = PerformanceReport {
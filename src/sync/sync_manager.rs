@@ -2,14 +2,16 @@
 //!
 //! 协调和管理数据同步过程
 
+use super::checkpoint::{CheckpointStore, FileCheckpointStore, SyncCheckpoint};
 use super::{
     SyncDirection, SyncFilter, SyncMode, SyncStatistics, SyncStrategy, SyncTask, SyncTaskStatus,
     SyncTaskType,
 };
 use crate::db_adapter::{DatabaseAdapter, QueryContext};
 use crate::types::*;
+use crate::utils::refno_span::{record_span_noun, refno_span, with_refno_span};
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -27,6 +29,10 @@ pub struct SyncManager {
     active_tasks: Arc<RwLock<HashMap<String, SyncTask>>>,
     /// 统计信息
     statistics: Arc<RwLock<SyncStatistics>>,
+    /// 断点记录使用的任务名
+    checkpoint_name: String,
+    /// 断点存储
+    checkpoints: Arc<dyn CheckpointStore>,
 }
 
 impl SyncManager {
@@ -44,10 +50,25 @@ impl SyncManager {
             filter,
             active_tasks: Arc::new(RwLock::new(HashMap::new())),
             statistics: Arc::new(RwLock::new(SyncStatistics::default())),
+            checkpoint_name: "default".to_string(),
+            checkpoints: Arc::new(FileCheckpointStore::default()),
         }
     }
 
+    /// 设置断点记录使用的任务名，多个同步任务共用数据库时用于区分
+    pub fn with_checkpoint_name(mut self, name: impl Into<String>) -> Self {
+        self.checkpoint_name = name.into();
+        self
+    }
+
+    /// 设置断点存储，缺省为 `cache/sync_checkpoint` 下的 JSON 文件
+    pub fn with_checkpoint_store(mut self, store: Arc<dyn CheckpointStore>) -> Self {
+        self.checkpoints = store;
+        self
+    }
+
     /// 执行同步
+    #[tracing::instrument(name = "sync", skip_all, fields(mode = ?self.strategy.mode))]
     pub async fn sync(&self) -> Result<SyncStatistics> {
         match self.strategy.mode {
             SyncMode::Full => self.sync_full().await,
@@ -57,8 +78,22 @@ impl SyncManager {
         }
     }
 
-    /// 全量同步
+    /// 全量同步，清除已有断点后从头开始
     async fn sync_full(&self) -> Result<SyncStatistics> {
+        self.checkpoints.clear(&self.checkpoint_name).await?;
+        self.run_full(false).await
+    }
+
+    /// 从断点继续全量同步
+    ///
+    /// 已完成的 dbnum 跳过，未完成的从最后处理的参考号之后继续；没有断点时等同于全量同步
+    #[tracing::instrument(name = "sync_resume", skip_all, fields(name = %self.checkpoint_name))]
+    pub async fn resume(&self) -> Result<SyncStatistics> {
+        self.run_full(true).await
+    }
+
+    /// 按 dbnum 分组、组内按参考号升序同步，每批成功后写入断点
+    async fn run_full(&self, resume: bool) -> Result<SyncStatistics> {
         let mut stats = SyncStatistics::default();
         stats.start_time = Some(std::time::SystemTime::now());
 
@@ -70,22 +105,75 @@ impl SyncManager {
         let all_pes = self.get_all_pes_to_sync().await?;
         task.total_count = all_pes.len();
 
-        // 批量同步
-        for batch in all_pes.chunks(self.strategy.batch_size) {
-            match self.sync_batch_pes(batch).await {
-                Ok(batch_stats) => {
-                    task.success_count += batch_stats.successful_records;
-                    stats.merge(&batch_stats);
-                }
-                Err(e) => {
-                    task.record_failure(format!("批量同步失败: {}", e));
-                    if !self.strategy.continue_on_error {
-                        task.fail(e.to_string());
-                        break;
+        let mut by_dbnum: BTreeMap<u32, Vec<RefnoEnum>> = BTreeMap::new();
+        for refno in all_pes {
+            by_dbnum.entry(refno.refno().get_0()).or_default().push(refno);
+        }
+
+        'dbnums: for (dbnum, mut refnos) in by_dbnum {
+            refnos.sort();
+            let saved = if resume {
+                self.checkpoints.load(&self.checkpoint_name, dbnum).await?
+            } else {
+                None
+            };
+            let mut cp = saved.unwrap_or_else(|| {
+                SyncCheckpoint::new(&self.checkpoint_name, dbnum, refnos.len())
+            });
+            cp.total = refnos.len();
+            let remaining = cp.remaining(&refnos);
+            let skipped = refnos.len() - remaining.len();
+            if skipped > 0 {
+                log::info!(
+                    "dbnum {} 从断点继续，跳过已同步的 {} 个 PE",
+                    dbnum,
+                    skipped
+                );
+                stats.skipped_records += skipped;
+                task.processed_count += skipped;
+            }
+
+            // 先重试上次失败的参考号，再继续断点之后的部分
+            let retry = cp.take_failed();
+            if !retry.is_empty() {
+                log::info!("dbnum {} 重试上次失败的 {} 个 PE", dbnum, retry.len());
+                task.total_count += retry.len();
+            }
+            let batches = retry
+                .chunks(self.strategy.batch_size)
+                .chain(remaining.chunks(self.strategy.batch_size));
+
+            // 批量同步
+            for batch in batches {
+                match self.sync_batch_pes(batch).await {
+                    Ok((batch_stats, sesno)) => {
+                        task.success_count += batch_stats.successful_records;
+                        stats.merge(&batch_stats);
+                        cp.advance(batch, sesno);
+                        self.checkpoints.save(&cp).await?;
+                        task.processed_count += batch.len();
+                    }
+                    Err(e) => {
+                        task.record_failure(format!("批量同步失败: {}", e));
+                        if !self.strategy.continue_on_error {
+                            // 断点不推进，继续时从该批重新开始
+                            self.checkpoints.save(&cp).await?;
+                            task.fail(e.to_string());
+                            break 'dbnums;
+                        }
+                        // 越过失败的批次，参考号留在断点中等待下次继续时重试
+                        cp.record_failed(batch);
+                        self.checkpoints.save(&cp).await?;
                     }
                 }
+                task.update_progress(task.processed_count, task.total_count);
+            }
+
+            // 仍有失败的参考号时不标记完成，继续时会再次重试
+            if !cp.completed && cp.failed.is_empty() {
+                cp.completed = true;
+                self.checkpoints.save(&cp).await?;
             }
-            task.update_progress(task.processed_count, task.total_count);
         }
 
         task.complete();
@@ -116,7 +204,7 @@ impl SyncManager {
             // 批量同步变更的 PE
             for batch in changed_pes.chunks(self.strategy.batch_size) {
                 match self.sync_batch_pes(batch).await {
-                    Ok(batch_stats) => {
+                    Ok((batch_stats, _)) => {
                         task.success_count += batch_stats.successful_records;
                         stats.merge(&batch_stats);
                     }
@@ -157,12 +245,18 @@ impl SyncManager {
 
     /// 同步单个 PE
     pub async fn sync_pe(&self, refno: RefnoEnum) -> Result<()> {
+        self.sync_pe_sesno(refno).await.map(|_| ())
+    }
+
+    /// 同步单个 PE，返回其会话号（源端不存在时为 None）
+    async fn sync_pe_sesno(&self, refno: RefnoEnum) -> Result<Option<i32>> {
         let ctx = QueryContext::default();
 
         // 从源数据库获取 PE
         let pe = self.source_adapter.get_pe(refno, Some(ctx.clone())).await?;
 
         if let Some(pe_data) = pe {
+            record_span_noun(&pe_data.noun);
             // 保存到目标数据库
             self.target_adapter.save_pe(&pe_data).await?;
 
@@ -171,9 +265,10 @@ impl SyncManager {
 
             // 同步关系
             self.sync_relations(refno).await?;
+            return Ok(Some(pe_data.sesno));
         }
 
-        Ok(())
+        Ok(None)
     }
 
     /// 同步属性
@@ -216,19 +311,25 @@ impl SyncManager {
         Ok(())
     }
 
-    /// 批量同步 PE
-    async fn sync_batch_pes(&self, refnos: &[RefnoEnum]) -> Result<SyncStatistics> {
+    /// 批量同步 PE，同时返回批内的最大会话号
+    async fn sync_batch_pes(
+        &self,
+        refnos: &[RefnoEnum],
+    ) -> Result<(SyncStatistics, Option<i32>)> {
         let mut stats = SyncStatistics::default();
         stats.total_records = refnos.len();
+        let mut max_sesno = None;
 
         for refno in refnos {
-            match self.sync_pe(*refno).await {
-                Ok(_) => {
+            match with_refno_span(*refno, self.sync_pe_sesno(*refno)).await {
+                Ok(sesno) => {
                     stats.successful_records += 1;
+                    max_sesno = max_sesno.max(sesno);
                 }
                 Err(e) => {
                     stats.failed_records += 1;
-                    log::error!("同步 PE {} 失败: {}", refno.refno().0, e);
+                    refno_span(*refno)
+                        .in_scope(|| log::error!("同步 PE {} 失败: {}", refno.refno().0, e));
 
                     if !self.strategy.continue_on_error {
                         return Err(e);
@@ -237,7 +338,7 @@ impl SyncManager {
             }
         }
 
-        Ok(stats)
+        Ok((stats, max_sesno))
     }
 
     /// 获取自指定时间以来变更的 PE
@@ -328,6 +429,8 @@ pub struct SyncManagerBuilder {
     target: Option<Arc<dyn DatabaseAdapter>>,
     strategy: SyncStrategy,
     filter: SyncFilter,
+    checkpoint_name: Option<String>,
+    checkpoint_store: Option<Arc<dyn CheckpointStore>>,
 }
 
 impl Default for SyncManagerBuilder {
@@ -337,6 +440,8 @@ impl Default for SyncManagerBuilder {
             target: None,
             strategy: SyncStrategy::default(),
             filter: SyncFilter::default(),
+            checkpoint_name: None,
+            checkpoint_store: None,
         }
     }
 }
@@ -371,6 +476,18 @@ impl SyncManagerBuilder {
         self
     }
 
+    /// 设置断点记录使用的任务名
+    pub fn checkpoint_name(mut self, name: impl Into<String>) -> Self {
+        self.checkpoint_name = Some(name.into());
+        self
+    }
+
+    /// 设置断点存储
+    pub fn checkpoint_store(mut self, store: Arc<dyn CheckpointStore>) -> Self {
+        self.checkpoint_store = Some(store);
+        self
+    }
+
     /// 构建同步管理器
     pub fn build(self) -> Result<SyncManager> {
         let source = self
@@ -380,6 +497,13 @@ impl SyncManagerBuilder {
             .target
             .ok_or_else(|| anyhow::anyhow!("目标数据库未设置"))?;
 
-        Ok(SyncManager::new(source, target, self.strategy, self.filter))
+        let mut manager = SyncManager::new(source, target, self.strategy, self.filter);
+        if let Some(name) = self.checkpoint_name {
+            manager = manager.with_checkpoint_name(name);
+        }
+        if let Some(store) = self.checkpoint_store {
+            manager = manager.with_checkpoint_store(store);
+        }
+        Ok(manager)
     }
 }
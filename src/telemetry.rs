@@ -0,0 +1,70 @@
+//! tracing 初始化与调试输出约定
+//!
+//! 查询、变换计算、同步等热路径统一用 tracing span 记录，元素相关的 span 带
+//! `refno` / `noun` 字段（见 [`crate::utils::refno_span`]），调试信息通过
+//! [`compat_debug!`](crate::compat_debug) 输出为 debug 事件。
+//!
+//! 旧的 println! 调试输出在启用 `println-compat` 特性后仍会同时打印到标准输出。
+//!
+//! ```rust,ignore
+//! // RUST_LOG=aios_core=debug 可看到查询 SQL 与变换计算过程
+//! aios_core::telemetry::init();
+//! ```
+
+/// 调试输出：始终发出 tracing debug 事件，启用 `println-compat` 时同时 println!
+#[macro_export]
+macro_rules! compat_debug {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "println-compat")]
+        println!($($arg)+);
+        tracing::debug!($($arg)+);
+    }};
+}
+
+/// 订阅端配置
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// 环境变量 `RUST_LOG` 未设置时使用的过滤规则
+    pub default_filter: String,
+    /// 在 span 结束时输出耗时，用于定位慢查询
+    pub span_timing: bool,
+    /// 是否输出 ANSI 颜色
+    pub ansi: bool,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            default_filter: "info,aios_core=info".to_string(),
+            span_timing: std::env::var_os("AIOS_TRACE_SPANS").is_some(),
+            ansi: true,
+        }
+    }
+}
+
+/// 按默认配置安装全局订阅端，重复调用时忽略
+#[cfg(feature = "telemetry")]
+pub fn init() {
+    let _ = try_init(TelemetryConfig::default());
+}
+
+/// 安装全局订阅端，同时把 `log` 宏的输出桥接到 tracing
+#[cfg(feature = "telemetry")]
+pub fn try_init(config: TelemetryConfig) -> anyhow::Result<()> {
+    use tracing_subscriber::EnvFilter;
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.default_filter))?;
+    let span_events = if config.span_timing {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(span_events)
+        .with_ansi(config.ansi)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("初始化 tracing 失败: {}", e))
+}
@@ -11,3 +11,22 @@ fn test_serde_attr_value() {
     let json = serde_json::to_string(&test_attmap).unwrap();
     dbg!(&json);
 }
+
+#[test]
+fn test_parse_attr_path() {
+    use crate::rs_surreal::query::{AttrPathSegment, parse_attr_path};
+    let seg = |name: &str, index| AttrPathSegment {
+        name: name.to_string(),
+        index,
+    };
+    assert_eq!(
+        parse_attr_path("owner.OWNER.Name").unwrap(),
+        vec![seg("OWNER", None), seg("OWNER", None), seg("NAME", None)]
+    );
+    assert_eq!(
+        parse_attr_path("PARA[2] of CATR of SPRE").unwrap(),
+        vec![seg("SPRE", None), seg("CATR", None), seg("PARA", Some(2))]
+    );
+    assert!(parse_attr_path("OWNER..NAME").is_err());
+    assert!(parse_attr_path("DESP[0]").is_err());
+}
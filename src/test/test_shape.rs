@@ -169,3 +169,35 @@ fn test_gen_wire_3() {
     let mut step_file = std::fs::File::create("test_gen_wire_3.step").unwrap();
     std::io::Write::write_all(&mut step_file, step_string.as_ref()).unwrap();
 }
+
+fn unit_triangle_mesh() -> crate::shape::pdms_shape::PlantMesh {
+    crate::shape::pdms_shape::PlantMesh {
+        indices: vec![0, 1, 2],
+        vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_write_obj_with_normals() {
+    let mesh = unit_triangle_mesh();
+    let mut buf = Vec::new();
+    let offset = mesh.write_obj_to(&mut buf, Some("geo_1"), 3).unwrap();
+    assert_eq!(offset, 6);
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.starts_with("o geo_1\n"));
+    assert!(text.contains("vn 0.0000 0.0000 1.0000"));
+    assert!(text.contains("f 4//4 5//5 6//6"));
+}
+
+#[test]
+fn test_write_stl_binary() {
+    let mesh = unit_triangle_mesh();
+    let mut buf = Vec::new();
+    mesh.write_stl_binary_to(&mut buf, Some("ELBO")).unwrap();
+    assert_eq!(buf.len(), 80 + 4 + 50);
+    assert_eq!(&buf[..4], b"ELBO");
+    assert_eq!(u32::from_le_bytes(buf[80..84].try_into().unwrap()), 1);
+    let nz = f32::from_le_bytes(buf[92..96].try_into().unwrap());
+    assert_eq!(nz, 1.0);
+}
@@ -44,3 +44,4 @@ pub mod test_collect_inst;
 
 pub mod test_boolean_query;
 pub mod test_query_tubi_insts;
+pub mod test_world_trans_invalidation;
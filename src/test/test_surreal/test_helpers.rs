@@ -90,7 +90,7 @@ pub async fn create_memory_db() -> Result<Surreal<Db>> {
 /// ```
 pub async fn init_sul_db_with_memory() -> Result<()> {
     // 使用嵌入式内存引擎连接
-    // SUL_DB 是 Surreal<Any> 类型，需要使用连接字符串
+    // SUL_DB 解引用为主连接 Surreal<Any>，需要使用连接字符串
     // 参考：https://surrealdb.com/docs/surrealdb/embedding/rust#connect
     SUL_DB
         .connect("mem://")
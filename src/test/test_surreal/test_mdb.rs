@@ -3,7 +3,7 @@ use crate::rs_surreal;
 #[tokio::test]
 async fn test_get_world() {
     crate::init_test_surreal().await;
-    let mdb = crate::get_db_option().mdb_name.clone();
+    let mdb = crate::get_db_option().unwrap().mdb_name.clone();
     println!("Testing with MDB: {}", mdb);
     let result = rs_surreal::get_world(mdb).await;
     // Assert
@@ -30,7 +30,7 @@ async fn test_get_world_sites() {
     }
 
     // 使用配置文件中的正确 MDB 名称
-    let mdb = crate::get_db_option().mdb_name.clone();
+    let mdb = crate::get_db_option().unwrap().mdb_name.clone();
     println!("Testing with MDB: {}", mdb);
 
     // 分别测试每个函数以确定哪个失败
@@ -16,7 +16,7 @@ async fn test_query_support_arms() -> anyhow::Result<()> {
 async fn test_query_same_refnos() -> anyhow::Result<()> {
     crate::init_test_surreal().await;
     let refno: RefU64 = "17496/274055".into();
-    let db_option = get_db_option();
+    let db_option = get_db_option()?;
     // let same_refnos = query_same_type_refnos(
     //     refno,
     //     db_option.mdb_name.clone(),
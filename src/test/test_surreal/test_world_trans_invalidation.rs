@@ -0,0 +1,63 @@
+use crate::cache::ATTR_CACHES;
+use crate::pe::SPdmsElement;
+use crate::transform::{invalidate_world_trans_cache, invalidate_world_trans_cache_recursive};
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt};
+
+use super::test_helpers::init_sul_db_with_memory;
+
+const WORLD_TRANS: &str = r#"{
+    translation: [1.0, 2.0, 3.0],
+    rotation: [0.0, 0.0, 0.0, 1.0],
+    scale: [1.0, 1.0, 1.0]
+}"#;
+
+fn cache_pe(refno: RefnoEnum) {
+    ATTR_CACHES.pes.insert(
+        refno,
+        Some(SPdmsElement {
+            refno,
+            ..Default::default()
+        }),
+    );
+}
+
+/// 清除世界变换时同时失效进程内缓存的 pe，否则 get_world_mat4 仍会读到旧的 world_trans
+#[tokio::test]
+async fn invalidate_world_trans_clears_in_process_caches() -> anyhow::Result<()> {
+    init_sul_db_with_memory().await?;
+
+    let root: RefnoEnum = "9901/100".into();
+    let child: RefnoEnum = "9901/101".into();
+    let single: RefnoEnum = "9901/200".into();
+    SUL_DB
+        .query_response(format!(
+            r#"
+            CREATE pe:9901_101 CONTENT {{ children: [], world_trans: {WORLD_TRANS} }};
+            CREATE pe:9901_100 CONTENT {{ children: [pe:9901_101], world_trans: {WORLD_TRANS} }};
+            CREATE pe:9901_200 CONTENT {{ world_trans: {WORLD_TRANS} }};
+            "#
+        ))
+        .await?;
+
+    for refno in [root, child, single] {
+        cache_pe(refno);
+    }
+
+    invalidate_world_trans_cache(single).await?;
+    assert!(ATTR_CACHES.pes.get(&single).is_none());
+
+    let cleared = invalidate_world_trans_cache_recursive(root).await?;
+    assert!(cleared >= 1);
+    assert!(ATTR_CACHES.pes.get(&root).is_none());
+    assert!(ATTR_CACHES.pes.get(&child).is_none());
+
+    let remaining: Vec<RefnoEnum> = SUL_DB
+        .query_take(
+            "SELECT VALUE id FROM [pe:9901_100, pe:9901_101, pe:9901_200] WHERE world_trans != NONE",
+            0,
+        )
+        .await?;
+    assert!(!remaining.contains(&child));
+    assert!(!remaining.contains(&single));
+    Ok(())
+}
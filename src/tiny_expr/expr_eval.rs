@@ -3,6 +3,8 @@
 //! This is a work in progress port of [TinyExpr](https://github.com/codeplea/tinyexpr) to Rust.
 //!
 //! Current release only supports built-in system functions (trigonometry, algebraic operations, constants, etc.).
+//! Comparison (`<`, `<=`, `==`, `!=`, `lt`, `ge`, ...) and logical (`and`, `or`, `not`) operators evaluate
+//! to `1.0` / `0.0`, and `if(cond, a, b)` picks a branch, so PDMS `IFTRUE` expressions can be evaluated.
 //! See the `tests` module for more examples.
 //!
 //!# Quick Start
@@ -122,6 +124,14 @@ pub enum FunctionType {
     Pow,
     Fmod,
     Neg,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
 }
 
 static FUNCTIONS: phf::Map<&'static str, Functions> = phf_map! {
@@ -137,6 +147,7 @@ static FUNCTIONS: phf::Map<&'static str, Functions> = phf_map! {
     "e" => Functions{fun: e, flag: Flags::TE_FUNCTION0},
     "exp" => Functions{fun: exp, flag: Flags::TE_FUNCTION1},
     "floor" => Functions{fun: floor, flag: Flags::TE_FUNCTION1},
+    "if" => Functions{fun: truthy, flag: Flags::TE_FUNCTION3},
     "ln" => Functions{fun: ln, flag: Flags::TE_FUNCTION1},
     "log" => Functions{fun: log, flag: Flags::TE_FUNCTION1},
     "log10" => Functions{fun: log10, flag: Flags::TE_FUNCTION1},
@@ -145,6 +156,7 @@ static FUNCTIONS: phf::Map<&'static str, Functions> = phf_map! {
     "rand01" => Functions{fun: rand01, flag: Flags::TE_FUNCTION0},
     "randint" => Functions{fun: randint, flag: Flags::TE_FUNCTION2},
     "min" => Functions{fun: min, flag: Flags::TE_FUNCTION2},
+    "not" => Functions{fun: not, flag: Flags::TE_FUNCTION1},
     "max" => Functions{fun: max, flag: Flags::TE_FUNCTION2},
     "round" => Functions{fun: round, flag: Flags::TE_FUNCTION1},
     "int" => Functions{fun: int, flag: Flags::TE_FUNCTION1},
@@ -179,6 +191,40 @@ fn neg(a: f64, _: f64) -> f64 {
 fn comma(_: f64, b: f64) -> f64 {
     b
 }
+fn bool_value(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+fn lt(a: f64, b: f64) -> f64 {
+    bool_value(a < b)
+}
+fn gt(a: f64, b: f64) -> f64 {
+    bool_value(a > b)
+}
+fn le(a: f64, b: f64) -> f64 {
+    bool_value(a <= b)
+}
+fn ge(a: f64, b: f64) -> f64 {
+    bool_value(a >= b)
+}
+fn eq(a: f64, b: f64) -> f64 {
+    bool_value(a == b)
+}
+fn ne(a: f64, b: f64) -> f64 {
+    bool_value(a != b)
+}
+fn and(a: f64, b: f64) -> f64 {
+    bool_value(a != 0.0 && b != 0.0)
+}
+fn or(a: f64, b: f64) -> f64 {
+    bool_value(a != 0.0 || b != 0.0)
+}
+fn not(a: f64, _: f64) -> f64 {
+    bool_value(a == 0.0)
+}
+// `if` 的条件判断，分支选择见 `eval`
+fn truthy(a: f64, _: f64) -> f64 {
+    bool_value(a != 0.0)
+}
 fn abs(a: f64, _: f64) -> f64 {
     a.abs()
 }
@@ -353,6 +399,31 @@ fn find_lookup(s: &State, txt: &str) -> Option<Variable> {
     None
 }
 
+/// Returns true if `name` is a built-in function or constant (lowercase, e.g. `sqrt`).
+pub fn is_builtin(name: &str) -> bool {
+    FUNCTIONS.contains_key(name)
+}
+
+fn set_infix(s: &mut State, function: fn(f64, f64) -> f64, function_type: FunctionType) {
+    s.s_type = Flags::TOK_INFIX;
+    s.function = function;
+    s.function_type = function_type;
+}
+
+fn word_infix(txt: &str) -> Option<(fn(f64, f64) -> f64, FunctionType)> {
+    Some(match txt {
+        "lt" => (lt, FunctionType::Lt),
+        "gt" => (gt, FunctionType::Gt),
+        "le" => (le, FunctionType::Le),
+        "ge" => (ge, FunctionType::Ge),
+        "eq" => (eq, FunctionType::Eq),
+        "ne" => (ne, FunctionType::Ne),
+        "and" => (and, FunctionType::And),
+        "or" => (or, FunctionType::Or),
+        _ => return None,
+    })
+}
+
 fn find_builtin(txt: &str) -> Option<Variable> {
     match FUNCTIONS.get(txt) {
         Some(v) => {
@@ -407,6 +478,11 @@ fn next_token(s: &mut State) -> Result<String> {
                     }
                 }
 
+                if let Some((function, function_type)) = word_infix(&txt_str) {
+                    set_infix(s, function, function_type);
+                    continue;
+                }
+
                 let mut var = find_lookup(s, &txt_str);
                 if var.is_none() {
                     var = find_builtin(&txt_str);
@@ -444,7 +520,40 @@ fn next_token(s: &mut State) -> Result<String> {
                 }
             } else {
                 // look for an operator or special character
+                let second = s.next.as_bytes().get(s.n_idx + 1).map(|&b| b as char);
                 match s.next.as_bytes()[s.n_idx] as char {
+                    '<' if second == Some('=') => {
+                        s.n_idx += 1;
+                        set_infix(s, le, FunctionType::Le);
+                    }
+                    '>' if second == Some('=') => {
+                        s.n_idx += 1;
+                        set_infix(s, ge, FunctionType::Ge);
+                    }
+                    '<' if second == Some('>') => {
+                        s.n_idx += 1;
+                        set_infix(s, ne, FunctionType::Ne);
+                    }
+                    '!' if second == Some('=') => {
+                        s.n_idx += 1;
+                        set_infix(s, ne, FunctionType::Ne);
+                    }
+                    '=' => {
+                        if second == Some('=') {
+                            s.n_idx += 1;
+                        }
+                        set_infix(s, eq, FunctionType::Eq);
+                    }
+                    '&' if second == Some('&') => {
+                        s.n_idx += 1;
+                        set_infix(s, and, FunctionType::And);
+                    }
+                    '|' if second == Some('|') => {
+                        s.n_idx += 1;
+                        set_infix(s, or, FunctionType::Or);
+                    }
+                    '<' => set_infix(s, lt, FunctionType::Lt),
+                    '>' => set_infix(s, gt, FunctionType::Gt),
                     '+' => {
                         s.s_type = Flags::TOK_INFIX;
                         s.function = add;
@@ -539,7 +648,7 @@ fn base(s: &mut State) -> Result<Expr> {
                 let mut idx = 0;
                 for _i in 0..arity {
                     next_token(s).unwrap();
-                    ret.parameters.push(expr(s).unwrap());
+                    ret.parameters.push(logic_or(s).unwrap());
                     if s.s_type != Flags::TOK_SEP {
                         break;
                     }
@@ -579,7 +688,7 @@ fn power(s: &mut State) -> Result<Expr> {
             match s.function_type {
                 FunctionType::Add => sign = 1,
                 FunctionType::Sub => sign = -1,
-                _ => continue,
+                _ => break,
             }
 
             next_token(s).unwrap();
@@ -656,15 +765,73 @@ fn expr(s: &mut State) -> Result<Expr> {
     Ok(ret)
 }
 
-fn list(s: &mut State) -> Result<Expr> {
+fn compare(s: &mut State) -> Result<Expr> {
     let mut ret = expr(s).unwrap();
 
-    while s.s_type == Flags::TOK_SEP {
+    while s.s_type == Flags::TOK_INFIX
+        && matches!(
+            s.function_type,
+            FunctionType::Lt
+                | FunctionType::Gt
+                | FunctionType::Le
+                | FunctionType::Ge
+                | FunctionType::Eq
+                | FunctionType::Ne
+        )
+    {
+        let f = s.function;
         next_token(s).unwrap();
         ret = new_expr(
             Flags::TE_FUNCTION2 | Flags::TE_FLAG_PURE,
             Some(vec![ret.clone(), expr(s).unwrap()]),
         );
+        ret.function = f;
+    }
+
+    Ok(ret)
+}
+
+fn logic_and(s: &mut State) -> Result<Expr> {
+    let mut ret = compare(s).unwrap();
+
+    while s.s_type == Flags::TOK_INFIX && s.function_type == FunctionType::And {
+        let f = s.function;
+        next_token(s).unwrap();
+        ret = new_expr(
+            Flags::TE_FUNCTION2 | Flags::TE_FLAG_PURE,
+            Some(vec![ret.clone(), compare(s).unwrap()]),
+        );
+        ret.function = f;
+    }
+
+    Ok(ret)
+}
+
+fn logic_or(s: &mut State) -> Result<Expr> {
+    let mut ret = logic_and(s).unwrap();
+
+    while s.s_type == Flags::TOK_INFIX && s.function_type == FunctionType::Or {
+        let f = s.function;
+        next_token(s).unwrap();
+        ret = new_expr(
+            Flags::TE_FUNCTION2 | Flags::TE_FLAG_PURE,
+            Some(vec![ret.clone(), logic_and(s).unwrap()]),
+        );
+        ret.function = f;
+    }
+
+    Ok(ret)
+}
+
+fn list(s: &mut State) -> Result<Expr> {
+    let mut ret = logic_or(s).unwrap();
+
+    while s.s_type == Flags::TOK_SEP {
+        next_token(s).unwrap();
+        ret = new_expr(
+            Flags::TE_FUNCTION2 | Flags::TE_FLAG_PURE,
+            Some(vec![ret.clone(), logic_or(s).unwrap()]),
+        );
         ret.function = comma;
     }
 
@@ -749,6 +916,14 @@ fn eval(n: &Expr) -> f64 {
                 0 => ((*n).function)(0.0, 0.0),
                 1 => ((*n).function)(eval(&n.parameters[0]), 0.0),
                 2 => ((*n).function)(eval(&n.parameters[0]), eval(&n.parameters[1])),
+                // 三参数函数只有 if：function 判断条件，只求解选中的分支
+                3 => {
+                    if ((*n).function)(eval(&n.parameters[0]), 0.0) != 0.0 {
+                        eval(&n.parameters[1])
+                    } else {
+                        eval(&n.parameters[2])
+                    }
+                }
                 _ => panic!("todo: add more f. pointers (type is {})", arity!(n.e_type)),
             }
         }
@@ -1,26 +1,13 @@
-use dashmap::DashMap;
-use lazy_static::lazy_static;
-use lru::LruCache;
 use memchr::memmem::{find, find_iter};
-use once_cell::sync::Lazy;
-use parking_lot::Mutex;
 use std::fs::File;
 use std::io::Read;
-use std::num::NonZeroUsize;
 
 use crate::types::db_info::PdmsDatabaseInfo;
 
-lazy_static! {
-    pub static ref GLOBAL_UDA_NAME_MAP: DashMap<u32, String> = DashMap::new();
-    pub static ref GLOBAL_UDA_UKEY_MAP: DashMap<String, u32> = DashMap::new();
-}
-
-/// 哈希反查缓存
-///
-/// 使用 LRU 缓存最近查询的结果，避免重复计算
-/// 缓存大小设置为 1000，适合大多数使用场景
-static DEHASH_CACHE: Lazy<Mutex<LruCache<u32, String>>> =
-    Lazy::new(|| Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap())));
+pub use crate::noun::{
+    GLOBAL_UDA_NAME_MAP, GLOBAL_UDA_UKEY_MAP, db1_dehash, db1_hash, db1_hash_const, db1_hash_i32,
+    decode as db1_dehash_const, get_uda_index, is_uda, is_uda_name,
+};
 
 /// 从bincode数据加载PdmsDatabaseInfo
 pub fn read_attr_info_config_from_bin(config_path: &str) -> PdmsDatabaseInfo {
@@ -43,152 +30,6 @@ pub fn convert_to_hash(bytes: &[u8]) -> i32 {
     i32::from_be_bytes(bytes.try_into().unwrap())
 }
 
-#[inline]
-pub fn is_uda(hash: i32) -> bool {
-    hash > 0x171FAD39
-}
-
-#[inline]
-pub fn is_uda_name(name: &str) -> bool {
-    name.starts_with(":")
-}
-
-#[inline]
-pub fn get_uda_index(hash: u32) -> Option<u32> {
-    if hash > 0x171FAD39 {
-        Some((hash - 0x171FAD39) >> 24)
-    } else {
-        None
-    }
-}
-
-/// 将哈希值反向转换为属性名称
-///
-/// 使用 LRU 缓存优化高频查询，避免重复计算
-///
-/// # 参数
-/// * `hash` - 属性的哈希值
-///
-/// # 返回值
-/// 反哈希后的属性名称字符串
-#[inline]
-pub fn db1_dehash(hash: u32) -> String {
-    // 先检查缓存
-    {
-        let mut cache = DEHASH_CACHE.lock();
-        if let Some(cached) = cache.get(&hash) {
-            return cached.clone();
-        }
-    }
-
-    // 缓存未命中，计算结果
-    let result = db1_dehash_uncached(hash);
-
-    // 将结果存入缓存
-    {
-        let mut cache = DEHASH_CACHE.lock();
-        cache.put(hash, result.clone());
-    }
-
-    result
-}
-
-/// 不使用缓存的哈希反查（内部函数）
-#[inline]
-fn db1_dehash_uncached(hash: u32) -> String {
-    let mut result = String::new();
-    if hash > 0x171FAD39 {
-        let mut k = ((hash - 0x171FAD39) % 0x1000000) as i32;
-        result.push(':');
-        for _i in 0..6 {
-            if k <= 0 {
-                break;
-            }
-            result.push((k % 64 + 32) as u8 as char);
-            k /= 64;
-        }
-    } else {
-        if hash <= 0x81BF1 {
-            return String::new();
-        }
-        let mut k = (hash - 0x81BF1) as i32;
-        while k > 0 {
-            result.push((k % 27 + 64) as u8 as char);
-            k /= 27;
-        }
-    }
-    result
-}
-
-#[inline]
-pub fn db1_hash_i32(hash_str: &str) -> i32 {
-    db1_hash(hash_str) as _
-}
-
-//类似于POSF，这种它的hash是POSI，这里需要强制的把名称缓过来
-// static ALIAS_ATT_NAME_MAP: Lazy<DashMap<&'static str, u32>> = Lazy::new(|| {
-//     let mut m = DashMap::new();
-//     m.insert("POSF", db1_hash("POSI"));
-//     m
-// });
-
-//todo 处理出错的情况
-#[inline]
-pub fn db1_hash(hash_str: &str) -> u32 {
-    let chars = hash_str.as_bytes();
-    if chars.len() < 1 {
-        return 0; //出错的暂时用0 表达
-    }
-    let mut val = 0i64;
-    let mut i = (chars.len() - 1) as i32;
-    while i >= 0 {
-        val = val.overflowing_mul(27).0 + (chars[i as usize] as i64 - 64);
-        i -= 1;
-    }
-    val.saturating_add_unsigned(0x81BF1) as u32
-}
-
-#[inline]
-pub fn db1_dehash_const(hash: u32) -> String {
-    let mut result = String::new();
-    if hash > 0x171FAD39 {
-        // UDA的情况
-        let mut k = ((hash - 0x171FAD39) % 0x1000000) as i32;
-        result.push(':');
-        for _i in 0..6 {
-            if k <= 0 {
-                break;
-            }
-            result.push((k % 64 + 32) as u8 as char);
-            k /= 64;
-        }
-    } else {
-        if hash <= 0x81BF1 {
-            return "".to_string();
-        }
-        let mut k = (hash - 0x81BF1) as i32;
-        while k > 0 {
-            result.push((k % 27 + 64) as u8 as char);
-            k /= 27;
-        }
-    }
-    result
-}
-
-pub const fn db1_hash_const(hash_str: &str) -> u32 {
-    let chars = hash_str.as_bytes();
-    if chars.len() < 1 {
-        return 0; //出错的暂时用0 表达
-    }
-    let mut val = 0i64;
-    let mut i = (chars.len() - 1) as i32;
-    while i >= 0 {
-        val = val.overflowing_mul(27).0 + (chars[i as usize] as i64 - 64);
-        i -= 1;
-    }
-    val.saturating_add_unsigned(0x81BF1) as u32
-}
-
 #[test]
 fn db1_dehash_test() {
     //USER
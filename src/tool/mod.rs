@@ -2,6 +2,7 @@ pub mod db_tool;
 pub mod float_tool;
 pub mod hash_tool;
 pub mod math_tool;
+pub mod word_tool;
 
 pub mod dir_tool;
 
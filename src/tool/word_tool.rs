@@ -0,0 +1,168 @@
+//! WORD 类型属性值解码
+//!
+//! WORD 类型属性（如 PURP、SPLATE）在数据库中保存为单词的 db1 哈希值，
+//! 不解码时只能拿到一个整数。这里提供哈希值到文本的转换：
+//! 先查注册的单词表，再按 base27 反算，反算结果不是合法单词时返回 `None`。
+
+use crate::bin_data::WORD_ATT_NAMES;
+use crate::tool::db_tool::{db1_dehash, db1_hash};
+use crate::types::named_attvalue::NamedAttrValue;
+use crate::{AttrVal, get_default_pdms_db_info};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+
+/// base27 单词哈希的下界（不含）
+const WORD_HASH_MIN: u32 = 0x81BF1;
+/// base27 单词哈希的上界，超过则是 UDA 哈希
+const WORD_HASH_MAX: u32 = 0x171FAD39;
+
+/// 额外注册的单词表：哈希 -> 文本
+static WORD_TABLE: Lazy<DashMap<u32, String>> = Lazy::new(DashMap::new);
+
+/// 注册单词，用于 base27 无法还原的情况（如超长单词被截断）
+pub fn register_words<I, S>(words: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    for word in words {
+        let word = word.as_ref().trim().to_uppercase();
+        if !word.is_empty() {
+            WORD_TABLE.insert(db1_hash(&word), word);
+        }
+    }
+}
+
+/// 属性是否为 WORD 类型
+///
+/// 以属性信息表中的默认值类型为准，同时包含 [`WORD_ATT_NAMES`] 中列出的属性。
+pub fn is_word_attr(noun: &str, att_name: &str) -> bool {
+    if WORD_ATT_NAMES.contains(&att_name) {
+        return true;
+    }
+    get_default_pdms_db_info()
+        .named_attr_info_map
+        .get(noun)
+        .and_then(|m| {
+            m.get(att_name)
+                .map(|x| matches!(x.value().default_val, AttrVal::WordType(_)))
+        })
+        .unwrap_or(false)
+}
+
+/// 将 WORD 哈希值解码为文本，0 或非法值返回 `None`
+pub fn decode_word(hash: i32) -> Option<String> {
+    if hash <= 0 {
+        return None;
+    }
+    let hash = hash as u32;
+    if let Some(word) = WORD_TABLE.get(&hash) {
+        return Some(word.clone());
+    }
+    if hash <= WORD_HASH_MIN || hash > WORD_HASH_MAX {
+        return None;
+    }
+    let word = db1_dehash(hash);
+    // base27 的 0 位反算为 '@'，说明不是合法单词
+    (!word.is_empty() && word.bytes().all(|c| c.is_ascii_uppercase())).then_some(word)
+}
+
+/// 将 WORD 属性的整数值转换为 [`NamedAttrValue::WordType`]，无法解码时保持原值
+///
+/// 整数（含 64 位）与整数数组按哈希解码；已是文本的值和其他类型（浮点、坐标、参考号等）原样返回。
+pub fn decode_word_value(value: NamedAttrValue) -> NamedAttrValue {
+    match value {
+        NamedAttrValue::IntegerType(hash) => match decode_word(hash) {
+            Some(word) => NamedAttrValue::WordType(word),
+            None => NamedAttrValue::IntegerType(hash),
+        },
+        NamedAttrValue::LongType(hash) => match i32::try_from(hash).ok().and_then(decode_word) {
+            Some(word) => NamedAttrValue::WordType(word),
+            None => NamedAttrValue::LongType(hash),
+        },
+        NamedAttrValue::IntArrayType(hashes) => {
+            let words = hashes
+                .iter()
+                .map(|h| decode_word(*h))
+                .collect::<Option<Vec<_>>>();
+            match words {
+                Some(words) => NamedAttrValue::StringArrayType(words),
+                None => NamedAttrValue::IntArrayType(hashes),
+            }
+        }
+        v @ (NamedAttrValue::InvalidType
+        | NamedAttrValue::WordType(_)
+        | NamedAttrValue::StringType(_)
+        | NamedAttrValue::ElementType(_)
+        | NamedAttrValue::StringArrayType(_)
+        | NamedAttrValue::F32Type(_)
+        | NamedAttrValue::F32VecType(_)
+        | NamedAttrValue::Vec3Type(_)
+        | NamedAttrValue::BoolType(_)
+        | NamedAttrValue::BoolArrayType(_)
+        | NamedAttrValue::RefU64Type(_)
+        | NamedAttrValue::RefnoEnumType(_)
+        | NamedAttrValue::RefU64Array(_)) => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_word() {
+        assert_eq!(
+            decode_word(db1_hash("PIPE") as i32).as_deref(),
+            Some("PIPE")
+        );
+        assert_eq!(decode_word(0), None);
+        assert_eq!(decode_word(-1), None);
+        // UDA 哈希不是单词
+        assert_eq!(decode_word((WORD_HASH_MAX + 1) as i32), None);
+
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::IntegerType(db1_hash("STEL") as i32)),
+            NamedAttrValue::WordType(w) if w == "STEL"
+        ));
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::IntegerType(7)),
+            NamedAttrValue::IntegerType(7)
+        ));
+    }
+
+    #[test]
+    fn test_decode_word_value_other_types() {
+        let stel = db1_hash("STEL") as i32;
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::LongType(stel as i64)),
+            NamedAttrValue::WordType(w) if w == "STEL"
+        ));
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::LongType(i64::MAX)),
+            NamedAttrValue::LongType(i64::MAX)
+        ));
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::IntArrayType(vec![stel, db1_hash("PIPE") as i32])),
+            NamedAttrValue::StringArrayType(w) if w == ["STEL", "PIPE"]
+        ));
+        // 任一元素无法解码时整个数组保持原值
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::IntArrayType(vec![stel, 7])),
+            NamedAttrValue::IntArrayType(v) if v == [stel, 7]
+        ));
+        // 已是文本或非整数的值原样返回
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::WordType("PIPE".into())),
+            NamedAttrValue::WordType(w) if w == "PIPE"
+        ));
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::StringType("12345".into())),
+            NamedAttrValue::StringType(s) if s == "12345"
+        ));
+        assert!(matches!(
+            decode_word_value(NamedAttrValue::F32Type(1.5)),
+            NamedAttrValue::F32Type(f) if f == 1.5
+        ));
+    }
+}
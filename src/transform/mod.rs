@@ -7,13 +7,15 @@
 
 use crate::rs_surreal::spatial::*;
 use crate::{
-    NamedAttrMap, RefnoEnum, SUL_DB, get_named_attmap,
+    NamedAttrMap, RefnoEnum, SUL_DB, SurrealQueryExt, get_named_attmap,
     pdms_data::{PlinParam, PlinParamData},
     tool::{direction_parse::parse_expr_to_dir, math_tool::*},
+    utils::refno_span::{record_span_noun, with_refno_span},
 };
 use anyhow::anyhow;
 use bevy_transform::prelude::*;
 use cached::proc_macro::cached;
+use tracing::Instrument;
 use glam::{DMat3, DMat4, DQuat, DVec3};
 
 use glam::{Quat, Vec3};
@@ -149,14 +151,18 @@ pub async fn get_effective_parent_att(parent_refno: RefnoEnum) -> anyhow::Result
 /// * `Err` - If an error occurs during calculation
 #[cached(result = true)]
 pub async fn get_local_mat4(refno: RefnoEnum) -> anyhow::Result<Option<DMat4>> {
-    // Get attribute maps for the entity and its parent
-    let att = get_named_attmap(refno).await?;
-    let parent_refno = att.get_owner();
-    let parent_att = get_effective_parent_att(parent_refno).await?;
-
-    // Use strategy factory to get the appropriate strategy
-    let mut strategy = TransformStrategyFactory::get_strategy_from_ref(&att, &parent_att);
-    strategy.get_local_transform().await
+    with_refno_span(refno, async move {
+        // Get attribute maps for the entity and its parent
+        let att = get_named_attmap(refno).await?;
+        record_span_noun(att.get_type_str());
+        let parent_refno = att.get_owner();
+        let parent_att = get_effective_parent_att(parent_refno).await?;
+
+        // Use strategy factory to get the appropriate strategy
+        let mut strategy = TransformStrategyFactory::get_strategy_from_ref(&att, &parent_att);
+        strategy.get_local_transform().await
+    })
+    .await
 }
 
 /// 使用策略模式重构的世界矩阵计算函数
@@ -197,15 +203,19 @@ pub async fn get_world_mat4(refno: RefnoEnum, is_local: bool) -> anyhow::Result<
             if let Some(world_trans) = pe.world_trans {
                 // 从 PlantTransform 转换为 DMat4
                 let mat4 = bevy_transform_to_dmat4(&world_trans.0);
-                #[cfg(feature = "debug_spatial")]
-                println!("🎯 Cache hit for world_trans: {}", refno);
+                crate::compat_debug!("🎯 Cache hit for world_trans: {}", refno);
                 return Ok(Some(mat4));
             }
         }
     }
 
-    // 缓存未命中，计算世界变换矩阵
-    let result = get_world_mat4_with_strategies_impl(refno, is_local).await?;
+    // 缓存未命中，计算世界变换矩阵（世界变换先查本地磁盘缓存）
+    let compute = with_refno_span(refno, get_world_mat4_with_strategies_impl(refno, is_local));
+    let result = if is_local {
+        compute.await?
+    } else {
+        crate::cache::disk::read_through_world_mat4(refno, compute).await?
+    };
 
     // 如果计算成功且不是 local 模式，缓存结果到数据库
     if !is_local {
@@ -223,8 +233,7 @@ pub async fn get_world_mat4(refno: RefnoEnum, is_local: bool) -> anyhow::Result<
                 let _ = SUL_DB.query(&sql)
                     .bind(("trans", plant_trans))
                     .await;
-                #[cfg(feature = "debug_spatial")]
-                println!("💾 Cached world_trans for: {}", refno_clone);
+                crate::compat_debug!("💾 Cached world_trans for: {}", refno_clone);
             });
         }
     }
@@ -243,23 +252,13 @@ async fn get_world_mat4_with_strategies_impl(
     refno: RefnoEnum,
     is_local: bool,
 ) -> anyhow::Result<Option<DMat4>> {
-    #[cfg(feature = "profile")]
-    let start_ancestors = std::time::Instant::now();
-
-    let mut ancestors: Vec<NamedAttrMap> = super::get_ancestor_attmaps(refno).await?;
-
-    #[cfg(feature = "profile")]
-    let elapsed_ancestors = start_ancestors.elapsed();
-    #[cfg(feature = "profile")]
-    println!("get_ancestor_attmaps took {:?}", elapsed_ancestors);
-
-    #[cfg(feature = "profile")]
-    let start_refnos = std::time::Instant::now();
-    let ancestor_refnos = crate::query_ancestor_refnos(refno).await?;
-    #[cfg(feature = "profile")]
-    let elapsed_refnos = start_refnos.elapsed();
-    #[cfg(feature = "profile")]
-    println!("query_ancestor_refnos took {:?}", elapsed_refnos);
+    // 各阶段耗时记录在子 span 上（telemetry 开启 span_timing 时输出）
+    let mut ancestors: Vec<NamedAttrMap> = super::get_ancestor_attmaps(refno)
+        .instrument(tracing::debug_span!("get_ancestor_attmaps"))
+        .await?;
+    let ancestor_refnos = crate::query_ancestor_refnos(refno)
+        .instrument(tracing::debug_span!("query_ancestor_refnos"))
+        .await?;
 
     // 检查 ancestors 是否包含 self，如果不包含则添加
     // get_ancestor_attmaps 通常返回 [Parent, GrandParent, ... Root]
@@ -287,34 +286,25 @@ async fn get_world_mat4_with_strategies_impl(
     }
 
     // 优化：查找祖先链中最近的有缓存 world_trans 的节点
-    let mut start_index = 0;
-    let mut mat4 = DMat4::IDENTITY;
-
-    #[cfg(feature = "profile")]
-    let cache_search_start = std::time::Instant::now();
-
-    // 从最接近目标节点的祖先开始查找（逆序遍历，从后往前）
-    for i in (1..ancestors.len()).rev() {
-        let ancestor_refno = ancestors[i].get_refno_or_default();
-
-        // 尝试从数据库读取该祖先的缓存
-        if let Ok(Some(pe)) = crate::get_pe(ancestor_refno).await {
-            if let Some(world_trans) = pe.world_trans {
-                // 找到缓存！使用这个作为起点
-                mat4 = bevy_transform_to_dmat4(&world_trans.0);
-                start_index = i;
-                #[cfg(feature = "debug_spatial")]
-                println!("🎯 Found cached world_trans at ancestor[{}]: {}", i, ancestor_refno);
-                break;
+    let (start_index, mut mat4) = async {
+        // 从最接近目标节点的祖先开始查找（逆序遍历，从后往前）
+        for i in (1..ancestors.len()).rev() {
+            let ancestor_refno = ancestors[i].get_refno_or_default();
+
+            // 尝试从数据库读取该祖先的缓存
+            if let Ok(Some(pe)) = crate::get_pe(ancestor_refno).await {
+                if let Some(world_trans) = pe.world_trans {
+                    // 找到缓存！使用这个作为起点
+                    crate::compat_debug!("🎯 Found cached world_trans at ancestor[{}]: {}", i, ancestor_refno);
+                    tracing::Span::current().record("start_index", i);
+                    return (i, bevy_transform_to_dmat4(&world_trans.0));
+                }
             }
         }
+        (0, DMat4::IDENTITY)
     }
-
-    #[cfg(feature = "profile")]
-    {
-        let cache_search_elapsed = cache_search_start.elapsed();
-        println!("Cache search took {:?}, start_index={}", cache_search_elapsed, start_index);
-    }
+    .instrument(tracing::debug_span!("world_trans_cache_search", start_index = tracing::field::Empty))
+    .await;
 
     // 从找到的缓存点（或根节点）开始，累加到目标节点的局部变换
     for i in (start_index + 1)..ancestors.len() {
@@ -326,16 +316,14 @@ async fn get_world_mat4_with_strategies_impl(
                 mat4 = mat4 * local_mat;
             }
             Ok(None) => {
-                #[cfg(feature = "debug_spatial")]
-                println!(
-                    "DEBUG: No transform calculated for {} -> {}",
+                crate::compat_debug!(
+                    "No transform calculated for {} -> {}",
                     parent_refno, cur_refno
                 );
             }
             Err(e) => {
-                #[cfg(feature = "debug_spatial")]
-                println!(
-                    "DEBUG: Error calculating transform for {} -> {}: {}",
+                crate::compat_debug!(
+                    "Error calculating transform for {} -> {}: {}",
                     parent_refno, cur_refno, e
                 );
             }
@@ -396,7 +384,43 @@ pub async fn invalidate_world_trans_cache(refno: RefnoEnum) -> anyhow::Result<()
         refno.to_pe_key()
     );
     SUL_DB.query(&sql).await?;
-    #[cfg(feature = "debug_spatial")]
-    println!("🗑️  Invalidated world_trans cache for: {}", refno);
+    // 进程内缓存的 pe 仍带着旧的 world_trans
+    crate::cache::invalidate_refno(refno).await;
+    crate::cache::disk::invalidate_world_trans(&[refno]);
+    crate::compat_debug!("🗑️  Invalidated world_trans cache for: {}", refno);
     Ok(())
 }
+
+/// 清除指定 refno 及其全部子孙节点的世界变换缓存
+///
+/// 移动 SITE/ZONE 等上层节点后，所有子孙节点缓存的 world_trans 都会失效。
+/// 沿 pe_owner 关系（children）递归收集子孙节点，在一条语句中完成清除，
+/// 同时失效这些节点的进程内缓存，并删除它们在本地磁盘缓存中的世界变换。
+///
+/// # 返回值
+/// * `Ok(n)` - 实际清除了缓存的节点数
+pub async fn invalidate_world_trans_cache_recursive(refno: RefnoEnum) -> anyhow::Result<usize> {
+    let sql = format!(
+        r#"LET $ids = array::filter(
+            (SELECT VALUE array::flatten(@.{{..+collect+inclusive}}.children) FROM ONLY {} LIMIT 1) ?: [],
+            |$v| $v != NONE
+        );
+        RETURN $ids;
+        UPDATE $ids SET world_trans = NONE WHERE world_trans != NONE RETURN VALUE id;"#,
+        refno.to_pe_key()
+    );
+    let mut response = SUL_DB.query_response(&sql).await?;
+    let mut affected: Vec<RefnoEnum> = response.take(1)?;
+    let cleared: Vec<surrealdb::types::RecordId> = response.take(2)?;
+    affected.push(refno);
+    for &id in &affected {
+        crate::cache::invalidate_refno(id).await;
+    }
+    crate::cache::disk::invalidate_world_trans(&affected);
+    crate::compat_debug!(
+        "🗑️  Invalidated world_trans cache for {} and descendants: {} records",
+        refno,
+        cleared.len()
+    );
+    Ok(cleared.len())
+}
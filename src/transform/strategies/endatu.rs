@@ -1,14 +1,15 @@
-use super::TransformStrategy;
+use super::spine_strategy::SpineStrategy;
 use super::{EndatuError, EndatuResult, EndatuValidator, get_cached_endatu_index};
+use super::{NposHandler, TransformStrategy};
 use crate::rs_surreal::spatial::{
-    SectionEnd, construct_basis_x_cutplane, construct_basis_z_opdir, construct_basis_z_y_exact,
-    construct_basis_z_ref_y, construct_basis_z_y_hint, cal_zdis_pkdi_in_section_by_spine,
-    get_spline_path,
+    SectionEnd, cal_zdis_pkdi_in_section_by_spine, construct_basis_x_cutplane,
+    construct_basis_z_opdir, construct_basis_z_ref_y, construct_basis_z_y_exact,
+    construct_basis_z_y_hint,
 };
-use crate::{NamedAttrMap, RefnoEnum};
+use crate::{NamedAttrMap, RefnoEnum, get_named_attmap};
 use async_trait::async_trait;
 use glam::{DMat3, DMat4, DQuat, DVec3};
-use super::NposHandler;
+use std::sync::Arc;
 
 /// ENDATU 专用的 ZDIS 处理器
 ///
@@ -34,9 +35,6 @@ impl EndAtuZdisHandler {
             return Ok(false);
         }
 
-        // 验证属性
-        EndatuValidator::validate_endatu_attributes(att)?;
-
         // 获取 ZDIS 值
         let zdis = att
             .get_f32("ZDIS")
@@ -45,28 +43,25 @@ impl EndAtuZdisHandler {
         // 使用缓存的索引查询，符合 core.dll 的性能优化策略
         let endatu_index = get_cached_endatu_index(parent_refno, refno).await?;
 
-        // 验证索引有效性（core.dll 中 ENDATU 索引只能是 0 或 1）
-        EndatuValidator::validate_endatu_index(endatu_index)?;
+        // core.dll 中 ENDATU 索引只能是 0 或 1，超出时不按截面端点定位
+        if let Err(e) = EndatuValidator::validate_endatu_index(endatu_index) {
+            log::warn!("{} 的 ENDATU 序号无效: {}，ZDIS 不按截面端点定位", refno, e);
+        }
 
-        // 根据 core.dll 的逻辑确定端部位置
+        // 第一个 ENDATU 在截面起点，第二个在终点
         let section_end = match endatu_index {
             Some(0) => Some(SectionEnd::START),
             Some(1) => Some(SectionEnd::END),
             _ => None,
         };
 
-        // 执行 ZDIS 计算
-        if let Some(result) = cal_zdis_pkdi_in_section_by_spine(
-            parent_refno,
-            0.0, // PKDI 设为 0，符合 core.dll 的 ENDATU 处理
-            zdis,
-            section_end,
-        )
-        .await
-        .map_err(|e| EndatuError::GeometryCalculationError(format!("ZDIS 计算失败: {}", e)))?
-        {
-            *pos += result.1;
-            *quat = result.0;
+        // 执行 ZDIS 计算，PKDI 设为 0，符合 core.dll 的 ENDATU 处理
+        let result = cal_zdis_pkdi_in_section_by_spine(parent_refno, 0.0, zdis, section_end)
+            .await
+            .map_err(|e| EndatuError::GeometryCalculationError(format!("ZDIS 计算失败: {}", e)))?;
+        if let Some((zdis_quat, zdis_pos)) = result {
+            *pos += zdis_pos;
+            *quat = zdis_quat;
             return Ok(true);
         }
 
@@ -74,36 +69,38 @@ impl EndAtuZdisHandler {
     }
 }
 
-pub struct EndAtuStrategy;
+/// ENDATU（结构端部附件）变换策略
+///
+/// 属性处理优先级与 core.dll 一致：
+/// ZDIS（按在父节点中的 ENDATU 序号定位到截面起点/终点）> NPOS > OPDI > YDIR/BANG > CUTP > DELP
+pub struct EndAtuStrategy {
+    att: Arc<NamedAttrMap>,
+    parent_att: Arc<NamedAttrMap>,
+}
 
 #[async_trait]
 impl TransformStrategy for EndAtuStrategy {
-    async fn get_local_transform(
-        &self,
-        refno: RefnoEnum,
-        parent_refno: RefnoEnum,
-        att: &NamedAttrMap,
-        parent_att: &NamedAttrMap,
-    ) -> anyhow::Result<Option<DMat4>> {
+    async fn get_local_transform(&mut self) -> anyhow::Result<Option<DMat4>> {
         // 使用 ENDATU 专用错误处理
-        let result: EndatuResult<Option<DMat4>> = self
-            .get_local_transform_with_error_handling(refno, parent_refno, att, parent_att)
-            .await;
-        result.map_err(|e: EndatuError| e.to_anyhow())
+        self.get_local_transform_with_error_handling()
+            .await
+            .map_err(|e| e.to_anyhow())
     }
 }
 
 impl EndAtuStrategy {
+    pub fn new(att: Arc<NamedAttrMap>, parent_att: Arc<NamedAttrMap>) -> Self {
+        Self { att, parent_att }
+    }
+
     /// 带有详细错误处理的变换计算
-    async fn get_local_transform_with_error_handling(
-        &self,
-        refno: RefnoEnum,
-        parent_refno: RefnoEnum,
-        att: &NamedAttrMap,
-        parent_att: &NamedAttrMap,
-    ) -> EndatuResult<Option<DMat4>> {
+    async fn get_local_transform_with_error_handling(&self) -> EndatuResult<Option<DMat4>> {
+        let att = &self.att;
+        let parent_att = &self.parent_att;
         let cur_type = att.get_type_str();
         let parent_type = parent_att.get_type_str();
+        let refno = att.get_refno_or_default();
+        let parent_refno = parent_att.get_refno_or_default();
 
         // 验证输入参数
         if cur_type != "ENDATU" {
@@ -113,116 +110,75 @@ impl EndAtuStrategy {
             )));
         }
 
-        // 验证所有属性
-        EndatuValidator::validate_endatu_attributes(att)?;
+        // 属性超出 core.dll 的常规范围时只记录警告，仍按现有属性计算
+        if let Err(e) = EndatuValidator::validate_endatu_attributes(att) {
+            log::warn!("{} 的 ENDATU 属性校验未通过: {}", refno, e);
+        }
 
-        let mut rotation = DQuat::IDENTITY;
-        let mut translation = DVec3::ZERO;
         let mut pos = att.get_position().unwrap_or_default().as_dvec3();
         let mut quat = DQuat::IDENTITY;
 
-        // === 属性处理优先级（完全符合 core.dll 顺序） ===
-
-        // 1. ZDIS (最高优先级) - 如果存在且处理成功，直接返回
-        if EndAtuZdisHandler::handle_endatu_zdis(refno, parent_refno, att, &mut pos, &mut quat)
-            .await?
+        // 1. ZDIS (最高优先级) - 如果存在且处理成功，直接返回；失败时退回到后续属性
+        match EndAtuZdisHandler::handle_endatu_zdis(refno, parent_refno, att, &mut pos, &mut quat)
+            .await
         {
-            translation = translation + rotation * pos;
-            rotation = quat;
-
-            let mat4 = DMat4::from_rotation_translation(rotation, translation);
-            EndatuValidator::validate_transform_matrix(&mat4)?;
-            return Ok(Some(mat4));
+            Ok(true) => {
+                let mat4 = DMat4::from_rotation_translation(quat, pos);
+                EndatuValidator::validate_transform_matrix(&mat4)?;
+                return Ok(Some(mat4));
+            }
+            Ok(false) => {}
+            Err(e) => {
+                log::warn!("{} 的 ZDIS 定位失败: {}，改用 NPOS/OPDI 等属性", refno, e);
+            }
         }
 
         // 2. NPOS (次高优先级)
-        NposHandler::try_apply_npos_offset_strict(&mut pos, att)?;
+        NposHandler::try_apply_npos_offset(&mut pos, att)
+            .map_err(|e| EndatuError::AttributeMissing(e.to_string()))?;
 
         // 3. OPDI (操作方向) - 高优先级，覆盖其他方向计算
-        let mut has_opdir = false;
-        if let Some(opdir) = att.get_dvec3("OPDI") {
-            if opdir.length_squared() == 0.0 {
-                return Err(EndatuError::ZeroDirectionVector);
-            }
+        let has_opdir = if let Some(opdir) = att.get_dvec3("OPDI") {
             quat = construct_basis_z_opdir(opdir.normalize());
-            has_opdir = true;
-        }
+            true
+        } else {
+            false
+        };
 
-        // 4. 如果没有 OPDI，处理 YDIR
+        // 4. 如果没有 OPDI，处理 YDIR / BANG / CUTP
         if !has_opdir {
             // 获取父级挤出方向
             let (pos_extru_dir, spine_ydir) =
-                Self::extract_extrusion_direction(parent_refno, parent_type, att, parent_att).await?;
+                Self::extract_extrusion_direction(parent_refno, parent_att).await?;
 
             // 初始化基础旋转
-            Self::initialize_rotation(
-                att,
-                cur_type,
-                parent_type,
-                pos_extru_dir,
-                spine_ydir,
-                &mut quat,
-                false,
-            )
-            .await?;
+            Self::initialize_rotation(att, parent_type, pos_extru_dir, spine_ydir, &mut quat)?;
 
-            // 处理 YDIR
             if let Some(ydir_axis) = att.get_dvec3("YDIR") {
-                if ydir_axis.length_squared() == 0.0 {
-                    return Err(EndatuError::ZeroDirectionVector);
-                }
-
-                let z_axis = if let Some(axis) =
-                    Self::extract_extrusion_direction(parent_refno, parent_type, att, parent_att)
-                        .await?
-                        .0
-                {
-                    axis
-                } else {
-                    DVec3::X
-                };
-
+                let z_axis = pos_extru_dir.unwrap_or(DVec3::X);
                 quat = construct_basis_z_y_exact(ydir_axis.normalize(), z_axis);
             }
 
             // 5. BANG (基础角度) - 在方向确定后应用
-            let bangle = att.get_f32("BANG").unwrap_or_default() as f64;
-            if att.contains_key("BANG") && bangle != 0.0 {
-                quat = quat * DQuat::from_rotation_z(bangle.to_radians());
+            let bangle = att.get_f64("BANG").unwrap_or_default();
+            if bangle != 0.0 {
+                quat *= DQuat::from_rotation_z(bangle.to_radians());
             }
 
             // 6. CUTP (切割方向) - 仅在没有明确方向时使用
-            let has_local_ori = att.get_rotation().is_some();
-            let has_cut_dir = att.contains_key("CUTP");
-            let has_opdir_ref = has_opdir;
-
-            if has_cut_dir && !has_opdir_ref && !has_local_ori {
-                let cut_dir = att
-                    .get_dvec3("CUTP")
-                    .ok_or_else(|| EndatuError::AttributeMissing("CUTP".to_string()))?;
-
-                if cut_dir.length_squared() == 0.0 {
-                    return Err(EndatuError::ZeroDirectionVector);
-                }
-
-                let mat3 = DMat3::from_quat(rotation);
-                quat = construct_basis_x_cutplane(mat3.z_axis, cut_dir);
+            if att.get_rotation().is_none()
+                && let Some(cut_dir) = att.get_dvec3("CUTP")
+            {
+                quat = construct_basis_x_cutplane(DMat3::from_quat(quat).z_axis, cut_dir);
             }
         }
 
         // 7. DELP (增量位置) - ENDATU 通常没有 POSL，但可能有 DELP
-        if att.contains_key("DELP") {
-            let delp = att
-                .get_dvec3("DELP")
-                .ok_or_else(|| EndatuError::AttributeMissing("DELP".to_string()))?;
+        if let Some(delp) = att.get_dvec3("DELP") {
             pos += delp;
         }
 
-        // 最终变换计算
-        translation = translation + rotation * pos;
-        rotation = rotation * quat;
-
-        let mat4 = DMat4::from_rotation_translation(rotation, translation);
+        let mat4 = DMat4::from_rotation_translation(quat, pos);
 
         // 验证最终结果
         EndatuValidator::validate_transform_matrix(&mat4)?;
@@ -230,44 +186,46 @@ impl EndAtuStrategy {
         Ok(Some(mat4))
     }
 
-    /// 提取挤出方向信息（优化版本）
+    /// 提取挤出方向信息
+    ///
+    /// 父节点为 GENSEC 时取 spine 第一段的方向，并按父节点 BANG 旋转截面 Y 方向；
+    /// 否则退回到父节点的 DPOSS/DPOSE
     async fn extract_extrusion_direction(
         parent_refno: RefnoEnum,
-        parent_type: &str,
-        att: &NamedAttrMap,
-        parent_att: &NamedAttrMap,
+        parent_att: &Arc<NamedAttrMap>,
     ) -> EndatuResult<(Option<DVec3>, Option<DVec3>)> {
-        let parent_is_gensec = parent_type == "GENSEC";
-
-        if parent_is_gensec {
-            if let Ok(spine_paths) = get_spline_path(parent_refno).await {
-                if let Some(first_spine) = spine_paths.first() {
-                    let dir = (first_spine.pt1 - first_spine.pt0).normalize();
-                    if dir.length_squared() > 0.01 {
-                        let pos_extru_dir = Some(dir.as_dvec3());
-                        let mut ydir = first_spine.preferred_dir.as_dvec3();
-
-                        // 考虑 Parent BANG 对截面方向的影响
-                        let parent_bangle = parent_att.get_f32("BANG").unwrap_or(0.0) as f64;
-                        if parent_bangle.abs() > 0.001 {
-                            let rot =
-                                DQuat::from_axis_angle(dir.as_dvec3(), parent_bangle.to_radians());
-                            ydir = rot * ydir;
-                        }
-
-                        let spine_ydir = if ydir.length_squared() > 0.01 {
-                            Some(ydir)
-                        } else {
-                            None
-                        };
-                        return Ok((pos_extru_dir, spine_ydir));
+        if parent_att.get_type_str() == "GENSEC" {
+            let parent_owner_att = get_named_attmap(parent_att.get_owner())
+                .await
+                .map_err(|e| {
+                    EndatuError::GeometryCalculationError(format!(
+                        "获取 {} 的父节点属性失败: {}",
+                        parent_refno, e
+                    ))
+                })?;
+            let strategy = SpineStrategy::new(parent_att.clone(), Arc::new(parent_owner_att));
+            if let Ok(spine_paths) = strategy.get_spline_path().await
+                && let Some(first_spine) = spine_paths.first()
+            {
+                let dir = (first_spine.pt1 - first_spine.pt0).normalize_or_zero();
+                if dir.length_squared() > 0.01 {
+                    let dir = dir.as_dvec3();
+                    let mut ydir = first_spine.preferred_dir.as_dvec3();
+
+                    // 考虑 Parent BANG 对截面方向的影响
+                    let parent_bangle = parent_att.get_f64("BANG").unwrap_or_default();
+                    if parent_bangle.abs() > 0.001 {
+                        ydir = DQuat::from_axis_angle(dir, parent_bangle.to_radians()) * ydir;
                     }
+
+                    let spine_ydir = (ydir.length_squared() > 0.01).then_some(ydir);
+                    return Ok((Some(dir), spine_ydir));
                 }
             }
         }
 
         // 处理 DPOSE/DPOSS 属性
-        if let (Some(end), Some(start)) = (att.get_dpose(), att.get_dposs()) {
+        if let (Some(end), Some(start)) = (parent_att.get_dpose(), parent_att.get_dposs()) {
             let dir = end - start;
             if dir.length_squared() > 0.01 {
                 return Ok((Some(dir.normalize()), None));
@@ -277,37 +235,34 @@ impl EndAtuStrategy {
         Ok((None, None))
     }
 
-    /// 初始化旋转（优化版本）
-    async fn initialize_rotation(
+    /// 初始化旋转
+    fn initialize_rotation(
         att: &NamedAttrMap,
-        cur_type: &str,
         parent_type: &str,
         pos_extru_dir: Option<DVec3>,
         spine_ydir: Option<DVec3>,
         quat: &mut DQuat,
-        _is_world_quat: bool,
     ) -> EndatuResult<()> {
         let parent_is_gensec = parent_type == "GENSEC";
-        let quat_v = att.get_rotation();
-        let has_local_ori = quat_v.is_some();
-
-        if (!parent_is_gensec && has_local_ori) || (parent_is_gensec && cur_type == "TMPL") {
-            *quat = quat_v.unwrap_or_default();
-        } else {
-            if let Some(z_axis) = pos_extru_dir {
-                if !z_axis.is_normalized() {
-                    return Err(EndatuError::GeometryCalculationError(
-                        "Z轴方向向量未归一化".to_string(),
-                    ));
-                }
-
-                if parent_is_gensec {
-                    *quat = construct_basis_z_y_hint(z_axis, spine_ydir, false);
-                } else {
-                    *quat = construct_basis_z_ref_y(z_axis);
-                }
-            }
+        if let Some(local_quat) = att.get_rotation()
+            && !parent_is_gensec
+        {
+            *quat = local_quat;
+            return Ok(());
         }
+        let Some(z_axis) = pos_extru_dir else {
+            return Ok(());
+        };
+        if !z_axis.is_normalized() {
+            return Err(EndatuError::GeometryCalculationError(
+                "Z轴方向向量未归一化".to_string(),
+            ));
+        }
+        *quat = if parent_is_gensec {
+            construct_basis_z_y_hint(z_axis, spine_ydir, false)
+        } else {
+            construct_basis_z_ref_y(z_axis)
+        };
         Ok(())
     }
 }
@@ -336,4 +291,24 @@ mod tests {
             Err(EndatuError::InvalidZdisValue(_))
         ));
     }
+
+    #[test]
+    fn test_initialize_rotation_from_extrusion() {
+        let att = create_test_attmap_with_attributes();
+        let mut quat = DQuat::IDENTITY;
+        EndAtuStrategy::initialize_rotation(&att, "FRMW", Some(DVec3::X), None, &mut quat).unwrap();
+        assert!((quat * DVec3::Z - DVec3::X).length() < 1e-6);
+
+        // 未归一化的挤出方向是错误
+        assert!(
+            EndAtuStrategy::initialize_rotation(
+                &att,
+                "FRMW",
+                Some(DVec3::new(2.0, 0.0, 0.0)),
+                None,
+                &mut quat
+            )
+            .is_err()
+        );
+    }
 }
@@ -1,15 +1,15 @@
 use crate::transform::strategies::endatu_error::EndatuError;
 use crate::{RefnoEnum, get_index_by_noun_in_parent};
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use std::collections::HashMap;
-use std::sync::Mutex;
 
 /// ENDATU 索引缓存，符合 core.dll 的性能优化策略
 static ENDATU_INDEX_CACHE: Lazy<Mutex<HashMap<(RefnoEnum, RefnoEnum), Option<u32>>>> =
-    Lazy::new(|| {
-        println!("🚀 初始化 ENDATU 索引缓存");
-        Mutex::new(HashMap::new())
-    });
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 缓存条目上限，超过后清理一半
+const ENDATU_CACHE_CAPACITY: usize = 10000;
 
 /// 缓存统计信息
 static CACHE_STATS: Lazy<Mutex<CacheStats>> = Lazy::new(|| Mutex::new(CacheStats::new()));
@@ -45,11 +45,13 @@ impl CacheStats {
     }
 
     pub fn print_stats(&self) {
-        println!("📊 ENDATU 缓存统计:");
-        println!("   总查询: {}", self.total_queries);
-        println!("   缓存命中: {}", self.hits);
-        println!("   缓存未命中: {}", self.misses);
-        println!("   命中率: {:.2}%", self.hit_rate() * 100.0);
+        log::info!(
+            "ENDATU 缓存统计: 总查询 {}, 命中 {}, 未命中 {}, 命中率 {:.2}%",
+            self.total_queries,
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0
+        );
     }
 }
 
@@ -64,23 +66,14 @@ pub async fn get_cached_endatu_index(
     let cache_key = (parent, refno);
 
     // 尝试从缓存获取
-    {
-        let cache = ENDATU_INDEX_CACHE.lock().unwrap();
-        if let Some(&cached) = cache.get(&cache_key) {
-            // 记录缓存命中
-            {
-                let mut stats = CACHE_STATS.lock().unwrap();
-                stats.record_hit();
-            }
-            return Ok(cached);
-        }
+    let cached = ENDATU_INDEX_CACHE.lock().get(&cache_key).copied();
+    if let Some(cached) = cached {
+        CACHE_STATS.lock().record_hit();
+        return Ok(cached);
     }
 
     // 缓存未命中，查询数据库
-    {
-        let mut stats = CACHE_STATS.lock().unwrap();
-        stats.record_miss();
-    }
+    CACHE_STATS.lock().record_miss();
 
     // 计算索引
     let result = get_index_by_noun_in_parent(parent, refno, Some("ENDATU"))
@@ -89,15 +82,19 @@ pub async fn get_cached_endatu_index(
 
     // 存入缓存
     {
-        let mut cache = ENDATU_INDEX_CACHE.lock().unwrap();
-
-        // 防止缓存过大，超过 10000 条时清理一半
-        if cache.len() > 10000 {
-            let keys_to_remove: Vec<_> = cache.keys().take(5000).cloned().collect();
+        let mut cache = ENDATU_INDEX_CACHE.lock();
+
+        // 防止缓存过大，超过上限时清理一半
+        if cache.len() > ENDATU_CACHE_CAPACITY {
+            let keys_to_remove: Vec<_> = cache
+                .keys()
+                .take(ENDATU_CACHE_CAPACITY / 2)
+                .cloned()
+                .collect();
             for key in keys_to_remove {
                 cache.remove(&key);
             }
-            println!("🧹 清理 ENDATU 缓存，当前大小: {}", cache.len());
+            log::debug!("清理 ENDATU 缓存，当前大小: {}", cache.len());
         }
 
         cache.insert(cache_key, result);
@@ -108,14 +105,23 @@ pub async fn get_cached_endatu_index(
 
 /// 清空 ENDATU 缓存
 pub fn clear_endatu_cache() {
-    let mut cache = ENDATU_INDEX_CACHE.lock().unwrap();
-    cache.clear();
-    println!("🧹 已清空 ENDATU 缓存");
+    ENDATU_INDEX_CACHE.lock().clear();
+    *CACHE_STATS.lock() = CacheStats::new();
+}
+
+/// 失效与某个元素相关的 ENDATU 索引（该元素作为父节点或作为 ENDATU 本身）
+///
+/// 由 [`crate::cache::invalidate_refno`] 在子节点增删、重排或属性修改后调用，返回移除的条目数
+pub fn invalidate_endatu_index(refno: RefnoEnum) -> usize {
+    let mut cache = ENDATU_INDEX_CACHE.lock();
+    let before = cache.len();
+    cache.retain(|(p, r), _| *p != refno && *r != refno);
+    before - cache.len()
 }
 
 /// 获取缓存统计信息
 pub fn get_cache_stats() -> CacheStats {
-    CACHE_STATS.lock().unwrap().clone()
+    CACHE_STATS.lock().clone()
 }
 
 /// 打印缓存统计信息
@@ -127,6 +133,7 @@ pub fn print_cache_stats() {
 #[cfg(test)]
 mod tests {
     use super::*;
+
     use crate::RefnoEnum;
 
     #[tokio::test]
@@ -155,4 +162,19 @@ mod tests {
 
         print_cache_stats();
     }
+
+    #[test]
+    fn test_cache_stats_hit_rate() {
+        let mut stats = CacheStats::new();
+        assert_eq!(stats.hit_rate(), 0.0);
+
+        stats.record_miss();
+        stats.record_hit();
+        stats.record_hit();
+        stats.record_hit();
+        assert_eq!(stats.total_queries, 4);
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+        assert!((stats.hit_rate() - 0.75).abs() < f64::EPSILON);
+    }
 }
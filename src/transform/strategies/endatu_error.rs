@@ -1,5 +1,3 @@
-use anyhow::anyhow;
-
 /// ENDATU 专用错误类型，符合 core.dll 的错误码映射
 #[derive(Debug, Clone, PartialEq)]
 pub enum EndatuError {
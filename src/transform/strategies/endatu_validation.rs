@@ -137,7 +137,7 @@ impl EndatuValidator {
             // core.dll 中 BANG 角度通常限制在 -360 到 360 度
             if bang < -360.0 || bang > 360.0 {
                 // 这里只是警告，不返回错误，因为某些情况下可能需要更大的角度
-                println!("⚠️  BANG 角度值超出常规范围 [-360, 360]: {}", bang);
+                log::warn!("BANG 角度值超出常规范围 [-360, 360]: {}", bang);
             }
         }
 
@@ -147,36 +147,7 @@ impl EndatuValidator {
     /// 验证变换矩阵的有效性
     pub fn validate_transform_matrix(matrix: &glam::DMat4) -> Result<(), EndatuError> {
         // 检查矩阵是否包含 NaN 或无穷大
-        if matrix.x_axis.x.is_nan()
-            || matrix.x_axis.y.is_nan()
-            || matrix.x_axis.z.is_nan()
-            || matrix.y_axis.x.is_nan()
-            || matrix.y_axis.y.is_nan()
-            || matrix.y_axis.z.is_nan()
-            || matrix.z_axis.x.is_nan()
-            || matrix.z_axis.y.is_nan()
-            || matrix.z_axis.z.is_nan()
-            || matrix.w_axis.x.is_nan()
-            || matrix.w_axis.y.is_nan()
-            || matrix.w_axis.z.is_nan()
-        {
-            return Err(EndatuError::TransformMatrixError);
-        }
-
-        // 检查矩阵是否包含无穷大
-        if !matrix.x_axis.x.is_finite()
-            || !matrix.x_axis.y.is_finite()
-            || !matrix.x_axis.z.is_finite()
-            || !matrix.y_axis.x.is_finite()
-            || !matrix.y_axis.y.is_finite()
-            || !matrix.y_axis.z.is_finite()
-            || !matrix.z_axis.x.is_finite()
-            || !matrix.z_axis.y.is_finite()
-            || !matrix.z_axis.z.is_finite()
-            || !matrix.w_axis.x.is_finite()
-            || !matrix.w_axis.y.is_finite()
-            || !matrix.w_axis.z.is_finite()
-        {
+        if !matrix.is_finite() {
             return Err(EndatuError::TransformMatrixError);
         }
 
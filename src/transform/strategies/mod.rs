@@ -9,32 +9,33 @@ pub trait TransformStrategy: Send + Sync {
 
 pub mod default;
 pub mod sweep_strategy;
-// pub mod endatu;
-// pub mod endatu_cache;
-// pub mod endatu_error;
-// pub mod endatu_validation;
+pub mod endatu;
+pub mod endatu_cache;
+pub mod endatu_error;
+pub mod endatu_validation;
 pub mod spine_strategy;
+pub mod sjoi;
 pub mod wall_strategy;
-// pub mod sjoi;
 
 // 导出策略
 pub use default::DefaultStrategy;
 use spine_strategy::SpineStrategy;
 pub use sweep_strategy::SweepStrategy;
+pub use sjoi::SjoiStrategy;
 pub use wall_strategy::WallStrategy;
-// use sjoi::SjoiStrategy;
 
 // 导出属性处理器
 pub use default::{CutpHandler, PoslHandler, YdirHandler};
-// pub use endatu::EndAtuStrategy;
-// pub use endatu::EndAtuZdisHandler;
-// pub use endatu_cache::{
-//     clear_endatu_cache, get_cache_stats, get_cached_endatu_index, print_cache_stats,
-// };
-// pub use endatu_error::{EndatuError, EndatuResult};
-// pub use endatu_validation::EndatuValidator;
+pub use endatu::EndAtuStrategy;
+pub use endatu::EndAtuZdisHandler;
+pub use endatu_cache::{
+    clear_endatu_cache, get_cache_stats, get_cached_endatu_index, invalidate_endatu_index,
+    print_cache_stats,
+};
+pub use endatu_error::{EndatuError, EndatuResult};
+pub use endatu_validation::EndatuValidator;
 // pub use spine_strategy::get_spline_path;
-// pub use sjoi::{SjoiConnectionHandler, SjoiCrefHandler};
+pub use sjoi::{SjoiConnectionHandler, SjoiCrefHandler};
 
 use std::sync::Arc;
 
@@ -56,6 +57,14 @@ impl TransformStrategyFactory {
         if type_str == "STWALL" || type_str == "SCTN" {
             return Box::new(WallStrategy::new(att, parent_att));
         };
+        // SJOI 需要按 CREF 指向的被连接构件定位
+        if type_str == "SJOI" {
+            return Box::new(SjoiStrategy::new(att, parent_att));
+        }
+        // ENDATU 按其在父节点中的序号定位到截面端部
+        if type_str == "ENDATU" {
+            return Box::new(EndAtuStrategy::new(att, parent_att));
+        }
         // 基于父节点类型进行策略分发
         match parent_type {
             "SPINE" => Box::new(SpineStrategy::new(att, parent_att)),
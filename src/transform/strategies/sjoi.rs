@@ -1,14 +1,15 @@
-use super::TransformStrategy;
+use super::spine_strategy::SpineStrategy;
+use super::{NposHandler, TransformStrategy};
 use crate::rs_surreal::spatial::{
-    construct_basis_x_cutplane, construct_basis_z_opdir, construct_basis_z_y_exact,
-    construct_basis_z_ref_y, construct_basis_z_y_hint, cal_zdis_pkdi_in_section_by_spine,
-    get_spline_path, query_pline,
+    cal_zdis_pkdi_in_section_by_spine, construct_basis_x_cutplane, construct_basis_z_opdir,
+    construct_basis_z_ref_y, construct_basis_z_y_exact, construct_basis_z_y_hint,
+    get_spline_line_dir, query_pline,
 };
 use crate::{NamedAttrMap, RefnoEnum, get_named_attmap};
 use async_trait::async_trait;
 use bevy_transform::prelude::Transform;
 use glam::{DMat3, DMat4, DQuat, DVec3};
-use super::NposHandler;
+use std::sync::Arc;
 
 /// SJOI 专用的 CREF/CUTP 处理器
 pub struct SjoiCrefHandler;
@@ -18,6 +19,10 @@ pub struct SjoiConnectionHandler;
 
 impl SjoiCrefHandler {
     /// 处理 SJOI 的 CREF 连接逻辑
+    ///
+    /// 取被连接构件（CREF）的 JLIN 点，换算到 SJOI 父节点的局部坐标系后，
+    /// 沿节点轴向（`rotation` 的 Z 轴）把 `translation` 移到该点所在的截面上。
+    /// 返回连接轴向和需要额外偏移的切割长度（CUTB，仅在两构件垂直时生效）。
     pub async fn handle_sjoi_cref(
         att: &NamedAttrMap,
         parent_refno: RefnoEnum,
@@ -32,129 +37,170 @@ impl SjoiCrefHandler {
         let cut_dir = att.get_dvec3("CUTP").unwrap_or(DVec3::Z);
         let cut_len = att.get_f64("CUTB").unwrap_or_default();
 
-        // 缓存属性获取，避免重复查询
         let Ok(c_att) = get_named_attmap(c_ref).await else {
             return Ok((DVec3::Z, 0.0));
         };
 
         let jline = c_att.get_str("JLIN").map(|x| x.trim()).unwrap_or("NA");
+        let jline = if jline.is_empty() { "NA" } else { jline };
 
-        if let Ok(Some(param)) = query_pline(c_ref, jline.into()).await {
-            let jlin_pos = param.pt;
-
-            // 并行获取世界坐标变换（优化性能）
-            let (c_world_result, parent_world_result) = tokio::join!(
-                crate::transform::get_world_mat4(c_ref, false),
-                crate::transform::get_world_mat4(parent_refno, false)
-            );
-
-            let c_world = c_world_result?.unwrap_or(DMat4::IDENTITY);
-            let parent_world = parent_world_result?.unwrap_or(DMat4::IDENTITY);
-            let c_local_mat = parent_world.inverse() * c_world;
-            let c_t = Transform::from_matrix(c_local_mat.as_mat4());
-
-            // 预计算旋转后的向量（避免重复计算）
-            let rotation_quat = c_t.rotation.as_dquat();
-            let jlin_offset = rotation_quat * jlin_pos;
-            let c_axis = rotation_quat * DVec3::Z;
-            let c_wpos = c_t.translation.as_dvec3() + jlin_offset;
-
-            // 沿梁轴方向计算
-            let z_axis = rotation * DVec3::Z;
-
-            // 优化：提前计算点积，避免重复计算
-            let cutp_dot = c_axis.dot(cut_dir);
-            let same_plane = cutp_dot.abs() > 0.001;
-
-            if same_plane {
-                let zaxis_dot = z_axis.dot(c_axis);
-                let delta = (c_wpos - *translation).dot(z_axis);
-                *translation = *translation + delta * z_axis;
-
-                // 检查是否垂直（使用预计算的结果）
-                let final_cut_len = if zaxis_dot.abs() < 0.001 {
-                    cut_len
-                } else {
-                    0.0
-                };
-
-                return Ok((z_axis, final_cut_len));
-            }
+        let Ok(Some(param)) = query_pline(c_ref, jline.into()).await else {
+            return Ok((DVec3::Z, 0.0));
+        };
+        let jlin_pos = param.pt;
+
+        // 被连接构件相对 SJOI 父节点的变换
+        let (c_world_result, parent_world_result) = tokio::join!(
+            Box::pin(crate::transform::get_world_mat4(c_ref, false)),
+            Box::pin(crate::transform::get_world_mat4(parent_refno, false))
+        );
+        let c_world = c_world_result?.unwrap_or(DMat4::IDENTITY);
+        let parent_world = parent_world_result?.unwrap_or(DMat4::IDENTITY);
+        let c_local_mat = parent_world.inverse() * c_world;
+        let c_t = Transform::from_matrix(c_local_mat.as_mat4());
+
+        let c_rotation = c_t.rotation.as_dquat();
+        let c_axis = c_rotation * DVec3::Z;
+        let c_pos = c_t.translation.as_dvec3() + c_rotation * jlin_pos;
+
+        // 沿节点轴向计算
+        let z_axis = rotation * DVec3::Z;
+
+        // 切割面与被连接构件轴向共面时才做投影
+        if c_axis.dot(cut_dir).abs() > 0.001 {
+            let delta = (c_pos - *translation).dot(z_axis);
+            *translation += delta * z_axis;
+
+            // 两构件垂直时需要让出切割长度
+            let final_cut_len = if z_axis.dot(c_axis).abs() < 0.001 {
+                cut_len
+            } else {
+                0.0
+            };
+            return Ok((z_axis, final_cut_len));
         }
 
         Ok((DVec3::Z, 0.0))
     }
 }
 
-pub struct SjoiStrategy;
+impl SjoiConnectionHandler {
+    /// 获取父节点的挤出方向和 spine 的 Y 方向
+    ///
+    /// 只有 GENSEC/WALL 这类由 spine 定义的父节点才有挤出方向
+    pub async fn extract_extrusion_direction(
+        parent_refno: RefnoEnum,
+        parent_att: &Arc<NamedAttrMap>,
+    ) -> anyhow::Result<(Option<DVec3>, Option<DVec3>)> {
+        if !matches!(parent_att.get_type_str(), "GENSEC" | "WALL") {
+            return Ok((None, None));
+        }
+        let extru_dir = get_spline_line_dir(parent_refno).await.ok();
+        let parent_owner_att = get_named_attmap(parent_att.get_owner()).await?;
+        let strategy = SpineStrategy::new(parent_att.clone(), Arc::new(parent_owner_att));
+        let spine_ydir = strategy
+            .get_spline_path()
+            .await?
+            .first()
+            .map(|s| s.preferred_dir.as_dvec3())
+            .filter(|d| d.length_squared() > 0.0);
+        Ok((extru_dir, spine_ydir))
+    }
+}
+
+/// SJOI（结构节点）变换策略
+///
+/// 在默认的 POS/ORI 基础上处理：
+/// - NPOS 偏移
+/// - 父节点为 GENSEC 时的 ZDIS/PKDI 定位和挤出方向
+/// - YDIR/OPDI/BANG 朝向
+/// - CREF 指向的被连接构件（按其 JLIN 点定位）和 CUTB 切割长度
+/// - CUTP 切割面
+pub struct SjoiStrategy {
+    att: Arc<NamedAttrMap>,
+    parent_att: Arc<NamedAttrMap>,
+}
+
+impl SjoiStrategy {
+    pub fn new(att: Arc<NamedAttrMap>, parent_att: Arc<NamedAttrMap>) -> Self {
+        Self { att, parent_att }
+    }
+
+    /// 初始化旋转
+    fn initialize_rotation(
+        att: &NamedAttrMap,
+        parent_type: &str,
+        pos_extru_dir: Option<DVec3>,
+        spine_ydir: Option<DVec3>,
+        quat: &mut DQuat,
+        is_world_quat: bool,
+    ) {
+        let parent_is_gensec = parent_type == "GENSEC";
+        if let Some(local_quat) = att.get_rotation()
+            && !parent_is_gensec
+        {
+            *quat = local_quat;
+            return;
+        }
+        let Some(z_axis) = pos_extru_dir.filter(|z| z.is_normalized()) else {
+            return;
+        };
+        if parent_is_gensec {
+            if !is_world_quat {
+                *quat = construct_basis_z_y_hint(z_axis, spine_ydir, false);
+            }
+        } else {
+            *quat = construct_basis_z_ref_y(z_axis);
+        }
+    }
+}
 
 #[async_trait]
 impl TransformStrategy for SjoiStrategy {
-    async fn get_local_transform(
-        &self,
-        _refno: RefnoEnum,
-        parent_refno: RefnoEnum,
-        att: &NamedAttrMap,
-        parent_att: &NamedAttrMap,
-    ) -> anyhow::Result<Option<DMat4>> {
-        let cur_type = att.get_type_str();
+    async fn get_local_transform(&mut self) -> anyhow::Result<Option<DMat4>> {
+        let att = &self.att;
+        let parent_att = &self.parent_att;
+        let parent_refno = parent_att.get_refno_or_default();
         let parent_type = parent_att.get_type_str();
 
-        let mut rotation = DQuat::IDENTITY;
-        let mut translation = DVec3::ZERO;
         let mut pos = att.get_position().unwrap_or_default().as_dvec3();
         let mut quat = DQuat::IDENTITY;
         let mut is_world_quat = false;
 
-        // 1. 处理 SJOI 特有的 CREF 连接逻辑
-        let (connection_axis, cut_len) =
-            SjoiCrefHandler::handle_sjoi_cref(att, parent_refno, &mut translation, rotation)
-                .await?;
-
-        // 2. 处理 NPOS 属性
+        // 1. NPOS
         NposHandler::apply_npos_offset(&mut pos, att);
 
-        // 3. 处理 BANG 属性
-        let bangle = att.get_f32("BANG").unwrap_or_default() as f64;
-        let apply_bang = att.contains_key("BANG") && bangle != 0.0;
-
-        // 4. 处理 ZDIS 属性（通用逻辑）
+        // 2. ZDIS/PKDI（父节点为 spine 截面时）
         if att.contains_key("ZDIS") {
             let zdist = att.get_f32("ZDIS").unwrap_or_default();
             let pkdi = att.get_f32("PKDI").unwrap_or_default();
-
-            if let Some((tmp_quat, tmp_pos)) =
-                cal_zdis_pkdi_in_section_by_spine(parent_refno, pkdi, zdist, None).await?
+            let on_spine = matches!(parent_type, "GENSEC" | "WALL");
+            if on_spine
+                && let Some((tmp_quat, tmp_pos)) =
+                    cal_zdis_pkdi_in_section_by_spine(parent_refno, pkdi, zdist, None).await?
             {
                 quat = tmp_quat;
                 pos = tmp_pos;
                 is_world_quat = true;
             } else {
-                translation += rotation * DVec3::Z * zdist as f64;
+                pos += DVec3::Z * zdist as f64;
             }
         }
 
-        // 5. 处理父级相关的变换
+        // 3. 父节点挤出方向与初始朝向
         let (pos_extru_dir, spine_ydir) =
-            Self::extract_extrusion_direction(parent_refno, parent_type, att, parent_att).await?;
-
-        // 6. 处理旋转初始化
+            SjoiConnectionHandler::extract_extrusion_direction(parent_refno, parent_att).await?;
         Self::initialize_rotation(
             att,
-            cur_type,
             parent_type,
             pos_extru_dir,
             spine_ydir,
             &mut quat,
             is_world_quat,
-        )
-        .await?;
+        );
 
-        // 7. 处理 YDIR/OPDI 属性
-        let ydir_axis = att.get_dvec3("YDIR");
-        let delta_vec = att.get_dvec3("DELP").unwrap_or_default();
+        // 4. OPDI / YDIR / BANG
         let mut has_opdir = false;
-
         if let Some(opdir) = att.get_dvec3("OPDI").map(|x| x.normalize()) {
             quat = construct_basis_z_opdir(opdir);
             has_opdir = true;
@@ -164,92 +210,35 @@ impl TransformStrategy for SjoiStrategy {
                 .unwrap_or_default()
                 .is_empty()
             {
-                pos += delta_vec;
+                pos += att.get_dvec3("DELP").unwrap_or_default();
             }
         } else {
-            // 处理 YDIR（假设 SJOI 不常用 POSL，但保留标准逻辑）
-            if let Some(v) = ydir_axis {
-                let z_axis = if let Some(axis) = pos_extru_dir {
-                    axis
-                } else {
-                    DVec3::X
-                };
-                quat = construct_basis_z_y_exact(v.normalize(), z_axis);
+            if let Some(ydir) = att.get_dvec3("YDIR") {
+                let z_axis = pos_extru_dir.unwrap_or(DVec3::X);
+                quat = construct_basis_z_y_exact(ydir.normalize(), z_axis);
             }
-
-            // 应用 BANG
-            if apply_bang {
-                quat = quat * DQuat::from_rotation_z(bangle.to_radians());
+            let bangle = att.get_f64("BANG").unwrap_or_default();
+            if bangle != 0.0 {
+                quat *= DQuat::from_rotation_z(bangle.to_radians());
             }
         }
 
-        // 8. 处理 CUTP（通用逻辑）
-        let has_local_ori = att.get_rotation().is_some();
-        let cut_dir = att.get_dvec3("CUTP").unwrap_or(DVec3::Z);
-
-        if att.contains_key("CUTP") && !has_opdir && !has_local_ori {
-            let mat3 = DMat3::from_quat(rotation);
-            quat = construct_basis_x_cutplane(mat3.z_axis, cut_dir);
-            is_world_quat = true;
-        }
-
-        // 9. 应用连接偏移（如果有）
+        // 5. CREF：定位到被连接构件上
+        let (connection_axis, cut_len) =
+            SjoiCrefHandler::handle_sjoi_cref(att, parent_refno, &mut pos, quat).await?;
         if cut_len > 0.0 {
-            translation += connection_axis * cut_len;
+            pos += connection_axis * cut_len;
         }
 
-        translation = translation + rotation * pos;
-
-        if is_world_quat {
-            rotation = quat;
-        } else {
-            rotation = rotation * quat;
+        // 6. CUTP 切割面
+        if att.contains_key("CUTP") && !has_opdir && att.get_rotation().is_none() {
+            let cut_dir = att.get_dvec3("CUTP").unwrap_or(DVec3::Z);
+            quat = construct_basis_x_cutplane(DMat3::from_quat(quat).z_axis, cut_dir);
         }
 
-        let mat4 = DMat4::from_rotation_translation(rotation, translation);
-        if rotation.is_nan() || translation.is_nan() {
+        if quat.is_nan() || pos.is_nan() {
             return Ok(None);
         }
-
-        Ok(Some(mat4))
-    }
-}
-
-impl SjoiStrategy {
-
-    /// 初始化旋转
-    async fn initialize_rotation(
-        att: &NamedAttrMap,
-        cur_type: &str,
-        parent_type: &str,
-        pos_extru_dir: Option<DVec3>,
-        spine_ydir: Option<DVec3>,
-        quat: &mut DQuat,
-        is_world_quat: bool,
-    ) -> anyhow::Result<()> {
-        let parent_is_gensec = parent_type == "GENSEC";
-        let quat_v = att.get_rotation();
-        let has_local_ori = quat_v.is_some();
-
-        if (!parent_is_gensec && has_local_ori) || (parent_is_gensec && cur_type == "TMPL") {
-            *quat = quat_v.unwrap_or_default();
-        } else {
-            if let Some(z_axis) = pos_extru_dir {
-                if parent_is_gensec {
-                    if !is_world_quat {
-                        if !z_axis.is_normalized() {
-                            return Ok(());
-                        }
-                        *quat = construct_basis_z_y_hint(z_axis, spine_ydir, false);
-                    }
-                } else {
-                    if !z_axis.is_normalized() {
-                        return Ok(());
-                    }
-                    *quat = construct_basis_z_ref_y(z_axis);
-                }
-            }
-        }
-        Ok(())
+        Ok(Some(DMat4::from_rotation_translation(quat, pos)))
     }
 }
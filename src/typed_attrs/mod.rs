@@ -0,0 +1,53 @@
+//! 按 noun 的类型化属性结构体（`typed-attrs` 特性）
+//!
+//! 构建时由 `build/typed_attrs.rs` 读取 `all_attr_info.json` 生成，每个 noun 一个结构体，
+//! 字段名为小写的属性名（与 Rust 关键字冲突时加后缀 `_`），属性缺失时取 attlib 中的默认值：
+//!
+//! ```rust,ignore
+//! use aios_core::typed_attrs::{ElboAttrs, TypedAttrs};
+//!
+//! let attmap = get_named_attmap(refno).await?;
+//! let elbo = ElboAttrs::from(&attmap);
+//! println!("{} {}", elbo.angl, elbo.radi);
+//! ```
+
+#![allow(clippy::all)]
+
+use crate::RefnoEnum;
+use crate::types::{NamedAttrMap, NamedAttrValue};
+use glam::Vec3;
+
+/// 生成的类型化属性结构体
+pub trait TypedAttrs: for<'a> From<&'a NamedAttrMap> + Default {
+    /// 对应的 noun
+    const NOUN: &'static str;
+
+    /// 属性 map 的类型与 `NOUN` 一致时转换
+    fn try_from_attmap(attmap: &NamedAttrMap) -> Option<Self> {
+        (attmap.get_type_str() == Self::NOUN).then(|| Self::from(attmap))
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/typed_attrs.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elbo_attrs() {
+        let mut attmap = NamedAttrMap::default();
+        attmap
+            .map
+            .insert("TYPE".into(), NamedAttrValue::StringType("ELBO".into()));
+        attmap
+            .map
+            .insert("ANGL".into(), NamedAttrValue::F32Type(90.0));
+        let elbo = ElboAttrs::try_from_attmap(&attmap).unwrap();
+        assert_eq!(elbo.angl, 90.0);
+        assert_eq!(elbo.radi, 0.0);
+        assert_eq!(elbo.spre, None);
+        assert!(BoxAttrs::try_from_attmap(&attmap).is_none());
+        assert!(TYPED_NOUNS.contains(&ElboAttrs::NOUN));
+    }
+}
@@ -0,0 +1,357 @@
+//! 属性表校验
+//!
+//! 按属性信息表（[`PdmsDatabaseInfo`]）检查 [`NamedAttrMap`]：
+//! - 名词未定义、属性不属于该名词
+//! - 值的类型与属性定义不符（如实数属性写成了文本）
+//! - WORD 属性不是合法单词，或取值不在 [`register_attr_enum`] 注册的枚举中
+
+use crate::bin_data::WORD_ATT_NAMES;
+use crate::pdms_types::AttrInfo;
+use crate::tool::word_tool::decode_word;
+use crate::types::attval::AttrVal;
+use crate::types::db_info::PdmsDatabaseInfo;
+use crate::types::named_attmap::NamedAttrMap;
+use crate::types::named_attvalue::NamedAttrValue;
+use crate::types::refno::RefnoEnum;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// 不在属性信息表中、由程序维护的属性
+const META_ATT_NAMES: [&str; 6] = ["REFNO", "OWNER", "TYPE", "TYPEX", "SESNO", "PGNO"];
+
+/// 注册的枚举取值：属性名 -> 允许的值
+static ATTR_ENUMS: Lazy<DashMap<String, BTreeSet<String>>> = Lazy::new(DashMap::new);
+
+/// 注册属性的枚举取值，校验时不在其中的值报告为 [`ViolationKind::OutOfEnum`]
+pub fn register_attr_enum<I, S>(att_name: &str, values: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let values = values
+        .into_iter()
+        .map(|v| v.as_ref().trim().to_uppercase())
+        .filter(|v| !v.is_empty());
+    ATTR_ENUMS
+        .entry(att_name.to_uppercase())
+        .or_default()
+        .extend(values);
+}
+
+/// 校验问题类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// 名词不在属性信息表中
+    UnknownNoun,
+    /// 属性不属于该名词
+    UnknownAttr,
+    /// 值类型与定义不符
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// 取值不在允许范围内，`allowed` 为空表示不是合法单词
+    OutOfEnum { value: String, allowed: Vec<String> },
+}
+
+/// 一条校验问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrViolation {
+    pub refno: RefnoEnum,
+    pub noun: String,
+    /// 属性名，名词未定义时为空
+    pub attr: String,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for AttrViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ViolationKind::UnknownNoun => write!(f, "{} 名词 {} 未定义", self.refno, self.noun),
+            ViolationKind::UnknownAttr => {
+                write!(f, "{} {} 没有属性 {}", self.refno, self.noun, self.attr)
+            }
+            ViolationKind::TypeMismatch { expected, found } => write!(
+                f,
+                "{} {}.{} 类型应为 {}，实际为 {}",
+                self.refno, self.noun, self.attr, expected, found
+            ),
+            ViolationKind::OutOfEnum { value, allowed } if allowed.is_empty() => write!(
+                f,
+                "{} {}.{} 的值 {} 不是合法单词",
+                self.refno, self.noun, self.attr, value
+            ),
+            ViolationKind::OutOfEnum { value, allowed } => write!(
+                f,
+                "{} {}.{} 的值 {} 不在 [{}] 中",
+                self.refno,
+                self.noun,
+                self.attr,
+                value,
+                allowed.join(", ")
+            ),
+        }
+    }
+}
+
+fn value_type_name(val: &NamedAttrValue) -> &'static str {
+    match val {
+        NamedAttrValue::InvalidType => "INVALID",
+        NamedAttrValue::IntegerType(_) => "INTEGER",
+        NamedAttrValue::LongType(_) => "LONG",
+        NamedAttrValue::StringType(_) => "STRING",
+        NamedAttrValue::F32Type(_) => "REAL",
+        NamedAttrValue::F32VecType(_) => "REAL[]",
+        NamedAttrValue::Vec3Type(_) => "VEC3",
+        NamedAttrValue::StringArrayType(_) => "STRING[]",
+        NamedAttrValue::BoolArrayType(_) => "BOOL[]",
+        NamedAttrValue::IntArrayType(_) => "INTEGER[]",
+        NamedAttrValue::BoolType(_) => "BOOL",
+        NamedAttrValue::ElementType(_) => "ELEMENT",
+        NamedAttrValue::WordType(_) => "WORD",
+        NamedAttrValue::RefU64Type(_) | NamedAttrValue::RefnoEnumType(_) => "REF",
+        NamedAttrValue::RefU64Array(_) => "REF[]",
+    }
+}
+
+/// 属性定义对应的期望类型名，以及值是否与之相容
+fn check_type(att_name: &str, info: &AttrInfo, val: &NamedAttrValue) -> Result<(), &'static str> {
+    use NamedAttrValue as V;
+    let (expected, ok) = match &info.default_val {
+        // 部分 WORD 属性在属性信息表中记录为整数类型
+        AttrVal::IntegerType(_) if WORD_ATT_NAMES.contains(&att_name) => {
+            ("WORD", matches!(val, V::WordType(_) | V::IntegerType(_)))
+        }
+        AttrVal::IntegerType(_) => ("INTEGER", matches!(val, V::IntegerType(_) | V::LongType(_))),
+        AttrVal::DoubleType(_) => (
+            "REAL",
+            matches!(val, V::F32Type(_) | V::IntegerType(_) | V::LongType(_)),
+        ),
+        AttrVal::BoolType(_) => ("BOOL", matches!(val, V::BoolType(_))),
+        AttrVal::StringType(_) | AttrVal::StringHashType(_) => {
+            ("STRING", matches!(val, V::StringType(_)))
+        }
+        AttrVal::WordType(_) => ("WORD", matches!(val, V::WordType(_) | V::IntegerType(_))),
+        AttrVal::ElementType(_) | AttrVal::RefU64Type(_) => (
+            "REF",
+            matches!(
+                val,
+                V::RefU64Type(_) | V::RefnoEnumType(_) | V::ElementType(_)
+            ),
+        ),
+        AttrVal::RefU64Array(_) => ("REF[]", matches!(val, V::RefU64Array(_))),
+        AttrVal::DoubleArrayType(_) => ("REAL[]", matches!(val, V::F32VecType(_) | V::Vec3Type(_))),
+        AttrVal::Vec3Type(_) => (
+            "VEC3",
+            matches!(val, V::Vec3Type(_)) || matches!(val, V::F32VecType(v) if v.len() == 3),
+        ),
+        AttrVal::IntArrayType(_) => ("INTEGER[]", matches!(val, V::IntArrayType(_))),
+        AttrVal::StringArrayType(_) => ("STRING[]", matches!(val, V::StringArrayType(_))),
+        AttrVal::BoolArrayType(_) => ("BOOL[]", matches!(val, V::BoolArrayType(_))),
+        AttrVal::InvalidType => ("ANY", true),
+    };
+    if ok { Ok(()) } else { Err(expected) }
+}
+
+/// 检查 WORD / 枚举属性的取值
+fn check_enum(att_name: &str, val: &NamedAttrValue) -> Option<ViolationKind> {
+    let (value, is_word) = match val {
+        NamedAttrValue::WordType(w) => (w.trim().to_uppercase(), true),
+        NamedAttrValue::IntegerType(h) if WORD_ATT_NAMES.contains(&att_name) => {
+            match decode_word(*h) {
+                Some(w) => (w, true),
+                None => {
+                    return Some(ViolationKind::OutOfEnum {
+                        value: h.to_string(),
+                        allowed: vec![],
+                    });
+                }
+            }
+        }
+        NamedAttrValue::StringType(s) => (s.trim().to_uppercase(), false),
+        _ => return None,
+    };
+    // 未设置的 WORD 属性为空串
+    if value.is_empty() {
+        return None;
+    }
+    if let Some(allowed) = ATTR_ENUMS.get(att_name) {
+        if allowed.contains(&value) {
+            return None;
+        }
+        return Some(ViolationKind::OutOfEnum {
+            value,
+            allowed: allowed.iter().cloned().collect(),
+        });
+    }
+    (is_word
+        && !value
+            .bytes()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+    .then(|| ViolationKind::OutOfEnum {
+        value,
+        allowed: vec![],
+    })
+}
+
+/// 是否参与校验：跳过程序维护的属性、UDA（`:` 开头或含 `:` / `@`）以及小写的记录字段
+fn is_checked_attr(att_name: &str) -> bool {
+    !META_ATT_NAMES.contains(&att_name)
+        && !att_name.contains(':')
+        && !att_name.contains('@')
+        && att_name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+impl NamedAttrMap {
+    /// 按属性信息表校验，返回全部问题，空表示通过
+    pub fn validate(&self, db_info: &PdmsDatabaseInfo) -> Vec<AttrViolation> {
+        let refno = self.get_refno_or_default();
+        let noun = self.get_type();
+        let violation = |attr: &str, kind| AttrViolation {
+            refno,
+            noun: noun.clone(),
+            attr: attr.to_string(),
+            kind,
+        };
+
+        let Some(infos) = db_info.named_attr_info_map.get(&noun) else {
+            return vec![violation("", ViolationKind::UnknownNoun)];
+        };
+        let mut violations = vec![];
+        for (att_name, val) in self.map.iter() {
+            if !is_checked_attr(att_name) || matches!(val, NamedAttrValue::InvalidType) {
+                continue;
+            }
+            let Some(info) = infos.get(att_name) else {
+                violations.push(violation(att_name, ViolationKind::UnknownAttr));
+                continue;
+            };
+            if let Err(expected) = check_type(att_name, info.value(), val) {
+                violations.push(violation(
+                    att_name,
+                    ViolationKind::TypeMismatch {
+                        expected,
+                        found: value_type_name(val),
+                    },
+                ));
+                continue;
+            }
+            if let Some(kind) = check_enum(att_name, val) {
+                violations.push(violation(att_name, kind));
+            }
+        }
+        violations
+    }
+
+    /// 批量校验，返回所有属性表的问题
+    pub fn validate_batch<'a>(
+        attmaps: impl IntoIterator<Item = &'a NamedAttrMap>,
+        db_info: &PdmsDatabaseInfo,
+    ) -> Vec<AttrViolation> {
+        attmaps
+            .into_iter()
+            .flat_map(|m| m.validate(db_info))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::pdms_types::DbAttributeType;
+    use dashmap::DashMap;
+
+    fn db_info() -> PdmsDatabaseInfo {
+        let attrs = DashMap::new();
+        for (name, default_val) in [
+            ("NAME", AttrVal::StringType(String::new())),
+            ("HEIG", AttrVal::DoubleType(0.0)),
+            ("PURP", AttrVal::WordType(String::new())),
+            ("FUNC", AttrVal::StringType(String::new())),
+        ] {
+            attrs.insert(
+                name.to_string(),
+                AttrInfo {
+                    name: name.to_string(),
+                    hash: 0,
+                    offset: 0,
+                    default_val,
+                    att_type: DbAttributeType::Unknown,
+                },
+            );
+        }
+        let info = PdmsDatabaseInfo::default();
+        info.named_attr_info_map.insert("BOX".to_string(), attrs);
+        info
+    }
+
+    fn attmap(noun: &str) -> NamedAttrMap {
+        let mut m = NamedAttrMap::default();
+        m.insert("TYPE".into(), NamedAttrValue::StringType(noun.into()));
+        m.insert(
+            "REFNO".into(),
+            NamedAttrValue::RefU64Type(RefU64::from_two_nums(1, 2)),
+        );
+        m
+    }
+
+    #[test]
+    fn test_validate() {
+        let info = db_info();
+        register_attr_enum("FUNC", ["SUPPORT", "DRAIN"]);
+
+        let mut ok = attmap("BOX");
+        ok.insert("HEIG".into(), NamedAttrValue::F32Type(10.0));
+        ok.insert("PURP".into(), NamedAttrValue::WordType("PIPE".into()));
+        ok.insert("FUNC".into(), NamedAttrValue::StringType("drain".into()));
+        ok.insert("UDA:MYUDA".into(), NamedAttrValue::IntegerType(1));
+        assert!(ok.validate(&info).is_empty());
+
+        let mut bad = attmap("BOX");
+        bad.insert("HEIG".into(), NamedAttrValue::StringType("10".into()));
+        bad.insert("XLEN".into(), NamedAttrValue::F32Type(1.0));
+        bad.insert("PURP".into(), NamedAttrValue::WordType("a b".into()));
+        bad.insert("FUNC".into(), NamedAttrValue::StringType("VENT".into()));
+        let kinds: Vec<_> = bad
+            .validate(&info)
+            .into_iter()
+            .map(|v| (v.attr, v.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    "FUNC".to_string(),
+                    ViolationKind::OutOfEnum {
+                        value: "VENT".into(),
+                        allowed: vec!["DRAIN".into(), "SUPPORT".into()],
+                    }
+                ),
+                (
+                    "HEIG".to_string(),
+                    ViolationKind::TypeMismatch {
+                        expected: "REAL",
+                        found: "STRING",
+                    }
+                ),
+                (
+                    "PURP".to_string(),
+                    ViolationKind::OutOfEnum {
+                        value: "A B".into(),
+                        allowed: vec![],
+                    }
+                ),
+                ("XLEN".to_string(), ViolationKind::UnknownAttr),
+            ]
+        );
+
+        let unknown = attmap("NOPE");
+        assert_eq!(
+            NamedAttrMap::validate_batch([&ok, &unknown], &info)[0].kind,
+            ViolationKind::UnknownNoun
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! 几何相关表的组合主键
+//!
+//! `inst_info` / `inst_geo` / `tubi_info` 的 id 过去在各处手工拼接 `table:⟨key⟩`，
+//! 转义方式不一致（有的带 `⟨⟩`，有的不带，纯数字键会被解析成数字 id）。
+//! 这里统一定义键的构造、解析与格式化，SQL 中一律使用 `⟨⟩` 包裹的字符串键。
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use surrealdb::types::RecordId;
+
+/// 将键格式化为 `table:⟨key⟩`，转义键中的 `⟩`
+#[inline]
+fn format_thing(table: &str, key: &str) -> String {
+    format!("{}:⟨{}⟩", table, key.replace('⟩', "\\⟩"))
+}
+
+/// 解析 `table:⟨key⟩`、`table:key` 或裸键
+fn parse_thing_key(table: &str, s: &str) -> anyhow::Result<String> {
+    let s = s.trim();
+    let key = match s.split_once(':') {
+        Some((t, rest)) if t == table => rest,
+        Some((t, _)) if !t.is_empty() && t.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+            anyhow::bail!("表名不匹配: 期望 {}，实际 {}", table, t)
+        }
+        _ => s,
+    };
+    let key = key
+        .strip_prefix('⟨')
+        .and_then(|k| k.strip_suffix('⟩'))
+        .or_else(|| key.strip_prefix('`').and_then(|k| k.strip_suffix('`')))
+        .unwrap_or(key)
+        .replace("\\⟩", "⟩");
+    if key.is_empty() {
+        anyhow::bail!("{} 键为空", table);
+    }
+    Ok(key)
+}
+
+macro_rules! geo_table_key {
+    ($(#[$meta:meta])* $name:ident, $table:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            /// 表名
+            pub const TABLE: &'static str = $table;
+
+            #[inline]
+            pub fn new(key: impl Into<String>) -> Self {
+                Self(key.into())
+            }
+
+            /// 不含表名的键
+            #[inline]
+            pub fn key(&self) -> &str {
+                &self.0
+            }
+
+            /// 解析 `table:⟨key⟩`、`table:key` 或裸键
+            pub fn parse(s: &str) -> anyhow::Result<Self> {
+                parse_thing_key(Self::TABLE, s).map(Self)
+            }
+
+            /// 转为 `RecordId`
+            #[inline]
+            pub fn to_record_id(&self) -> RecordId {
+                RecordId::new(Self::TABLE, self.0.as_str())
+            }
+
+            /// 拼接多个键，用于 `[...]` 形式的 SQL 数组
+            pub fn join<'a>(keys: impl IntoIterator<Item = &'a Self>, sep: &str) -> String {
+                keys.into_iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+                    .join(sep)
+            }
+        }
+
+        /// 格式化为 SQL 中可直接使用的 `table:⟨key⟩`
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&format_thing(Self::TABLE, &self.0))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::parse(s)
+            }
+        }
+
+        impl From<$name> for RecordId {
+            fn from(key: $name) -> Self {
+                RecordId::new($name::TABLE, key.0)
+            }
+        }
+
+        impl From<&$name> for RecordId {
+            fn from(key: &$name) -> Self {
+                key.to_record_id()
+            }
+        }
+    };
+}
+
+geo_table_key!(
+    /// `inst_info` 表的键：元件库 hash，或无 hash 时的 `{refno}_{sesno}`
+    InstInfoKey,
+    "inst_info"
+);
+
+geo_table_key!(
+    /// `inst_geo` 表的键：几何 hash
+    InstGeoKey,
+    "inst_geo"
+);
+
+geo_table_key!(
+    /// `tubi_info` 表的键：`{cata_hash}_{arrive_num}_{leave_num}`
+    TubiInfoKey,
+    "tubi_info"
+);
+
+impl InstInfoKey {
+    /// 无元件库 hash 时，用参考号与会话号生成
+    #[inline]
+    pub fn from_refno_sesno(refno: impl fmt::Display, sesno: impl fmt::Display) -> Self {
+        Self(format!("{}_{}", refno, sesno))
+    }
+}
+
+impl TubiInfoKey {
+    #[inline]
+    pub fn from_parts(cata_hash: &str, arrive_num: i32, leave_num: i32) -> Self {
+        Self(format!("{}_{}_{}", cata_hash, arrive_num, leave_num))
+    }
+
+    /// 拆分为 (cata_hash, arrive_num, leave_num)
+    pub fn parts(&self) -> Option<(&str, i32, i32)> {
+        let (rest, leave) = self.0.rsplit_once('_')?;
+        let (hash, arrive) = rest.rsplit_once('_')?;
+        Some((hash, arrive.parse().ok()?, leave.parse().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse() {
+        let key = InstGeoKey::new("12345");
+        assert_eq!(key.to_string(), "inst_geo:⟨12345⟩");
+        assert_eq!(InstGeoKey::parse("inst_geo:⟨12345⟩").unwrap(), key);
+        assert_eq!(InstGeoKey::parse("inst_geo:12345").unwrap(), key);
+        assert_eq!("12345".parse::<InstGeoKey>().unwrap(), key);
+        assert!(InstGeoKey::parse("inst_info:⟨12345⟩").is_err());
+
+        let info = InstInfoKey::from_refno_sesno("17496_201377", 3);
+        assert_eq!(info.to_string(), "inst_info:⟨17496_201377_3⟩");
+
+        let tubi = TubiInfoKey::from_parts("9527", 1, 2);
+        assert_eq!(tubi.to_string(), "tubi_info:⟨9527_1_2⟩");
+        assert_eq!(tubi.parts(), Some(("9527", 1, 2)));
+        assert_eq!(TubiInfoKey::parse(&tubi.to_string()).unwrap(), tubi);
+
+        let odd = InstGeoKey::new("a⟩b");
+        assert_eq!(InstGeoKey::parse(&odd.to_string()).unwrap(), odd);
+    }
+}
@@ -1,6 +1,8 @@
 pub mod attmap;
+pub mod attmap_validate;
 pub mod attval;
 pub mod db_info;
+pub mod geo_keys;
 pub mod named_attmap;
 pub mod named_attvalue;
 pub mod query_sql;
@@ -22,8 +24,10 @@ pub type RecordId = surrealdb::types::RecordId;
 pub type Thing = surrealdb::types::RecordId;
 
 pub use attmap::*;
+pub use attmap_validate::*;
 pub use attval::*;
 pub use db_info::*;
+pub use geo_keys::*;
 pub use hash::*;
 pub use named_attmap::*;
 pub use named_attvalue::*;
@@ -6,10 +6,12 @@ use crate::pe::SPdmsElement;
 use crate::prim_geo::cylinder::SCylinder;
 use crate::prim_geo::*;
 use crate::shape::pdms_shape::BrepShapeTrait;
+use crate::bin_data::WORD_ATT_NAMES;
 use crate::tool::db_tool::{db1_dehash, db1_hash};
 use crate::tool::dir_tool::parse_ori_str_to_dquat;
 use crate::tool::float_tool::*;
 use crate::tool::math_tool::*;
+use crate::tool::word_tool::{decode_word, decode_word_value, is_word_attr};
 use crate::types::attmap::AttrMap;
 use crate::types::named_attvalue::NamedAttrValue;
 use crate::utils::{value_to_bool, value_to_f32, value_to_i32, value_to_string};
@@ -125,14 +127,21 @@ impl From<SurlValue> for NamedAttrMap {
                         continue;
                     };
 
+                    // 部分 WORD 属性在属性信息表中记录为整数类型
+                    let default_val = match default_val {
+                        AttrVal::IntegerType(_) if WORD_ATT_NAMES.contains(&k.as_str()) => {
+                            AttrVal::WordType(Default::default())
+                        }
+                        v => v,
+                    };
                     let named_value = match default_val {
                         AttrVal::IntegerType(_) => NamedAttrValue::IntegerType(value_to_i32(&v)),
                         AttrVal::StringType(_) => NamedAttrValue::StringType(value_to_string(&v)),
                         AttrVal::WordType(_) => match &v {
                             SurlValue::String(s) => NamedAttrValue::WordType(s.clone()),
-                            SurlValue::Number(_) => {
-                                NamedAttrValue::WordType(db1_dehash(value_to_i32(&v).max(0) as u32))
-                            }
+                            SurlValue::Number(_) => NamedAttrValue::WordType(
+                                decode_word(value_to_i32(&v)).unwrap_or_default(),
+                            ),
                             _ => NamedAttrValue::WordType(String::new()),
                         },
                         AttrVal::DoubleType(_) => NamedAttrValue::F32Type(value_to_f32(&v)),
@@ -258,7 +267,15 @@ impl From<&AttrMap> for NamedAttrMap {
             map: v
                 .map
                 .iter()
-                .map(|(h, v)| (db1_dehash(*h), NamedAttrValue::from(v)))
+                .map(|(h, v)| {
+                    let name = db1_dehash(*h);
+                    let value = NamedAttrValue::from(v);
+                    if WORD_ATT_NAMES.contains(&name.as_str()) {
+                        (name, decode_word_value(value))
+                    } else {
+                        (name, value)
+                    }
+                })
                 .collect(),
         }
     }
@@ -290,6 +307,16 @@ impl Into<DynamicStruct> for NamedAttrMap {
     }
 }
 
+/// 导出 JSON 时将 WORD 属性的整数哈希值转为文本
+#[inline]
+fn export_json_value(key: &str, val: NamedAttrValue) -> serde_json::Value {
+    if WORD_ATT_NAMES.contains(&key) {
+        decode_word_value(val).into()
+    } else {
+        val.into()
+    }
+}
+
 impl NamedAttrMap {
     ///初始化
     pub fn new(type_name: &str) -> Self {
@@ -616,6 +643,31 @@ impl NamedAttrMap {
         }
     }
 
+    /// 获取 WORD 类型属性的文本，整数哈希值会被解码
+    pub fn get_word(&self, att_name: &str) -> Option<String> {
+        match self.map.get(att_name)? {
+            NamedAttrValue::WordType(s) => Some(s.clone()),
+            NamedAttrValue::IntegerType(h) => decode_word(*h),
+            _ => None,
+        }
+    }
+
+    /// 将所有 WORD 类型属性的整数哈希值解码为文本
+    pub fn decode_words(&mut self) {
+        let type_name = self.get_type();
+        let keys = self
+            .map
+            .keys()
+            .filter(|k| is_word_attr(&type_name, k))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            if let Some(v) = self.map.remove(&key) {
+                self.map.insert(key, decode_word_value(v));
+            }
+        }
+    }
+
     #[inline]
     pub fn get_owner(&self) -> RefnoEnum {
         self.get_refno_by_att_or_default("OWNER")
@@ -651,7 +703,7 @@ impl NamedAttrMap {
                 continue;
             }
             let new_key = key.replace(":", "_");
-            map.insert(new_key, val.into());
+            map.insert(new_key, export_json_value(&key, val));
         }
         map
     }
@@ -722,7 +774,8 @@ impl NamedAttrMap {
                     }
                 }
             } else {
-                map.insert(key, val.into());
+                let val = export_json_value(&key, val);
+                map.insert(key, val);
             }
         }
 
@@ -798,7 +851,8 @@ impl NamedAttrMap {
             } else if let NamedAttrValue::RefU64Array(refnos) = val {
                 records_map.insert(key, refnos.into_iter().map(|x| x.refno()).collect());
             } else {
-                map.insert(key, val.into());
+                let val = export_json_value(&key, val);
+                map.insert(key, val);
             }
         }
 
@@ -1061,6 +1115,11 @@ impl NamedAttrMap {
     #[inline]
     pub fn get_as_string(&self, key: &str) -> Option<String> {
         use NamedAttrValue::*;
+        if WORD_ATT_NAMES.contains(&key)
+            && let Some(word) = self.get_word(key)
+        {
+            return Some(word);
+        }
         let v = self.get_val(key)?;
         let s = match v {
             StringType(s) | WordType(s) | ElementType(s) => s.to_string(),
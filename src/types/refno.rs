@@ -1,3 +1,6 @@
+pub mod allocator;
+
+pub use allocator::{RefnoAllocator, RefnoBlock};
 use crate::utils::{IntoRecordId, RecordIdExt};
 use bevy_ecs::component::Component;
 #[cfg(feature = "reflect")]
@@ -57,6 +60,7 @@ pub struct ParseRefU64Error;
     Ord,
     // SurrealValue,
 )]
+#[rkyv(derive(Hash, PartialEq, Eq))]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 pub struct RefU64(pub u64);
 
@@ -589,6 +593,7 @@ impl RefI32Tuple {
     rkyv::Serialize,
     SurrealValue,
 )]
+#[rkyv(derive(Hash, PartialEq, Eq))]
 pub struct RefnoSesno {
     pub refno: RefU64,
     pub sesno: u32,
@@ -659,6 +664,7 @@ impl Into<u32> for RefnoSesno {
     rkyv::Serialize,
     Component,
 )]
+#[rkyv(derive(Hash, PartialEq, Eq))]
 #[serde(untagged)]
 pub enum RefnoEnum {
     Refno(RefU64),
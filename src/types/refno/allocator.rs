@@ -0,0 +1,202 @@
+//! 新建元素的参考号分配
+//!
+//! 每个 dbnum 在 `refno_seq:⟨dbnum⟩` 记录中维护下一个可用序号（与子树复制共用），
+//! 通过 `UPDATE ... RETURN BEFORE` 原子地取出一段序号，多进程并发分配也不会重复。
+//! 序列首次使用时以 pe 表中该 dbnum 已有的最大序号初始化，
+//! 分配出的参考号还会与 pe 表比对，跳过已被占用的（例如外部导入写入的）序号。
+//!
+//! ```rust,ignore
+//! let allocator = RefnoAllocator::default();
+//! let refno = allocator.next(17496).await?;
+//! // 批量导入时一次预留
+//! let block = RefnoAllocator::reserve(17496, 10_000).await?;
+//! for refno in block.iter() { /* ... */ }
+//! ```
+
+use super::{RefU64, join_pe_keys};
+use crate::{SUL_DB, SurrealQueryExt};
+use anyhow::Context;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// 序号计数表
+pub const REFNO_SEQ_TABLE: &str = "refno_seq";
+
+/// 比对 pe 表时每批的参考号数量
+const EXISTS_CHECK_CHUNK: usize = 1000;
+
+/// 一段连续预留的序号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefnoBlock {
+    pub dbnum: u32,
+    pub sequences: Range<u32>,
+}
+
+impl RefnoBlock {
+    pub fn len(&self) -> usize {
+        self.sequences.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = RefU64> + '_ {
+        self.sequences
+            .clone()
+            .map(|seq| RefU64::from_two_nums(self.dbnum, seq))
+    }
+}
+
+/// 参考号分配器
+///
+/// `next` 从本地缓存的序号段中取号，用完后再向数据库预留 `block_size` 个；
+/// 进程退出时未用完的序号会被丢弃，分配出的参考号不保证连续。
+#[derive(Debug)]
+pub struct RefnoAllocator {
+    block_size: u32,
+    /// 已比对过 pe 表、可直接使用的序号
+    local: DashMap<u32, Vec<u32>>,
+}
+
+impl Default for RefnoAllocator {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
+
+impl RefnoAllocator {
+    pub fn new(block_size: u32) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            local: DashMap::new(),
+        }
+    }
+
+    /// 分配一个参考号
+    pub async fn next(&self, dbnum: u32) -> anyhow::Result<RefU64> {
+        loop {
+            if let Some(seq) = self.local.get_mut(&dbnum).and_then(|mut seqs| seqs.pop()) {
+                return Ok(RefU64::from_two_nums(dbnum, seq));
+            }
+            let block = Self::reserve(dbnum, self.block_size).await?;
+            let fresh = self.filter_existing(&block).await?;
+            // 倒序存放，pop 时按序号从小到大取出
+            self.local
+                .entry(dbnum)
+                .or_default()
+                .extend(fresh.into_iter().rev().map(|r| r.get_1()));
+        }
+    }
+
+    /// 分配 `count` 个未被占用的参考号（跳过冲突后补足数量）
+    pub async fn allocate(&self, dbnum: u32, count: usize) -> anyhow::Result<Vec<RefU64>> {
+        let mut refnos = Vec::with_capacity(count);
+        while refnos.len() < count {
+            let block = Self::reserve(dbnum, (count - refnos.len()) as u32).await?;
+            refnos.extend(self.filter_existing(&block).await?);
+        }
+        Ok(refnos)
+    }
+
+    /// 原子预留一段连续序号，不与 pe 表比对
+    ///
+    /// 批量导入时用于一次性占住序号段，导入方自行保证写入前不与已有数据冲突。
+    pub async fn reserve(dbnum: u32, count: u32) -> anyhow::Result<RefnoBlock> {
+        anyhow::ensure!(count > 0, "预留数量必须大于 0");
+        let start: Option<i64> = SUL_DB.query_take(&reserve_sql(dbnum, count), 1).await?;
+        let start = start.context("参考号序列分配失败")?;
+        anyhow::ensure!(
+            start > 0 && start as u64 + count as u64 <= u32::MAX as u64,
+            "dbnum {} 的参考号已耗尽",
+            dbnum
+        );
+        let start = start as u32;
+        Ok(RefnoBlock {
+            dbnum,
+            sequences: start..start + count,
+        })
+    }
+
+    /// 过滤掉 pe 表中已经存在的参考号
+    pub async fn filter_existing(&self, block: &RefnoBlock) -> anyhow::Result<Vec<RefU64>> {
+        let refnos = block.iter().collect::<Vec<_>>();
+        let mut existing = HashSet::new();
+        for chunk in refnos.chunks(EXISTS_CHECK_CHUNK) {
+            let sql = format!("SELECT VALUE id FROM [{}]", join_pe_keys(chunk.iter()));
+            let found: Vec<RefU64> = SUL_DB.query_take(&sql, 0).await?;
+            existing.extend(found);
+        }
+        if !existing.is_empty() {
+            log::warn!(
+                "dbnum {} 预留的序号中有 {} 个已被占用，已跳过",
+                block.dbnum,
+                existing.len()
+            );
+        }
+        Ok(remove_collisions(refnos, &existing))
+    }
+
+    /// 丢弃本地缓存的序号段（例如切换项目后）
+    pub fn clear(&self) {
+        self.local.clear();
+    }
+}
+
+/// 预留语句：序列不存在时按 pe 表中已有的最大序号初始化，第 1 条结果为预留段的起始序号
+fn reserve_sql(dbnum: u32, count: u32) -> String {
+    format!(
+        r#"
+        IF !record::exists({REFNO_SEQ_TABLE}:⟨{dbnum}⟩) {{
+            CREATE {REFNO_SEQ_TABLE}:⟨{dbnum}⟩ SET next = (math::max(
+                SELECT VALUE <int> array::last(string::split(<string> record::id(id), '_'))
+                FROM pe WHERE string::starts_with(<string> record::id(id), '{dbnum}_')
+            ) ?? 0) + 1;
+        }};
+        (UPDATE ONLY {REFNO_SEQ_TABLE}:⟨{dbnum}⟩ SET next += {count} RETURN BEFORE).next;
+        "#
+    )
+}
+
+fn remove_collisions(refnos: Vec<RefU64>, existing: &HashSet<RefU64>) -> Vec<RefU64> {
+    refnos
+        .into_iter()
+        .filter(|r| !existing.contains(r))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_iter() {
+        let block = RefnoBlock {
+            dbnum: 17496,
+            sequences: 10..13,
+        };
+        let refnos = block.iter().collect::<Vec<_>>();
+        assert_eq!(refnos.len(), 3);
+        assert_eq!(refnos[0], RefU64::from_two_nums(17496, 10));
+        assert_eq!(refnos[2].to_slash_string(), "17496/12");
+    }
+
+    #[test]
+    fn test_reserve_sql() {
+        let sql = reserve_sql(17496, 64);
+        assert!(sql.contains("refno_seq:⟨17496⟩ SET next += 64 RETURN BEFORE"));
+        assert!(sql.contains("'17496_'"));
+    }
+
+    #[test]
+    fn test_remove_collisions() {
+        let refnos = (1..=4)
+            .map(|i| RefU64::from_two_nums(1, i))
+            .collect::<Vec<_>>();
+        let existing = HashSet::from([RefU64::from_two_nums(1, 2)]);
+        let fresh = remove_collisions(refnos, &existing);
+        assert_eq!(fresh.len(), 3);
+        assert!(!fresh.contains(&RefU64::from_two_nums(1, 2)));
+    }
+}
@@ -0,0 +1,64 @@
+//! 带参考号的 tracing span 约定
+//!
+//! 日志行通常不说明涉及哪个元素。按元素处理的代码统一用 [`with_refno_span`] 包裹，
+//! span 名为 `refno`，字段为 `refno`、`noun`、`dbnum`，其中的 warn/error 事件
+//! 会自动携带这些字段，便于在生产日志中过滤。
+//!
+//! `noun` 往往要查询属性后才知道，可在 span 内调用 [`record_span_noun`] 补记。
+//! 通过 `log` 宏输出的日志需要订阅端启用 `tracing-log` 桥接才会带上 span 字段。
+//!
+//! ```ignore
+//! with_refno_span(refno, async move {
+//!     let att = get_named_attmap(refno).await?;
+//!     record_span_noun(&att.get_type_str());
+//!     tracing::warn!("缺少 POS 属性");
+//!     Ok(())
+//! })
+//! .await
+//! ```
+
+use crate::RefnoEnum;
+use std::future::Future;
+use tracing::field::Empty;
+use tracing::instrument::Instrumented;
+use tracing::{Instrument, Span};
+
+/// span 名
+pub const REFNO_SPAN_NAME: &str = "refno";
+
+/// 创建元素 span，`dbnum` 取参考号的第一段
+#[inline]
+pub fn refno_span(refno: RefnoEnum) -> Span {
+    tracing::info_span!(
+        REFNO_SPAN_NAME,
+        refno = %refno,
+        noun = Empty,
+        dbnum = refno.refno().get_0()
+    )
+}
+
+/// 创建已知类型的元素 span
+#[inline]
+pub fn refno_noun_span(refno: RefnoEnum, noun: &str) -> Span {
+    let span = refno_span(refno);
+    span.record("noun", noun);
+    span
+}
+
+/// 在元素 span 中执行 future
+#[inline]
+pub fn with_refno_span<F: Future>(refno: RefnoEnum, fut: F) -> Instrumented<F> {
+    fut.instrument(refno_span(refno))
+}
+
+/// 在已知类型的元素 span 中执行 future
+#[inline]
+pub fn with_refno_noun_span<F: Future>(refno: RefnoEnum, noun: &str, fut: F) -> Instrumented<F> {
+    fut.instrument(refno_noun_span(refno, noun))
+}
+
+/// 为当前 span 补记元素类型（当前不在元素 span 中时无效果）
+#[inline]
+pub fn record_span_noun(noun: &str) {
+    Span::current().record("noun", noun);
+}
@@ -543,18 +543,24 @@ pub fn estimate_original_size(params: &[CateAxisParam]) -> usize {
 
 /// 从 JSON Value 解析 ptset（自动检测格式并解压）
 /// 
-/// 支持两种格式：
+/// 支持三种格式：
 /// 1. 压缩格式：`[{n: 1, p: [...], d: {...}}, ...]`
 /// 2. 原始格式：`[{number: 1, pt: [...], dir: [...], ...}, ...]`
+/// 3. 二进制格式（`PtsetEncoding::Cbor`）：base64 字符串或字节数组，需要 `ptset-cbor` 特性
 pub fn parse_ptset_auto(value: &serde_json::Value) -> Option<Vec<CateAxisParam>> {
+    if value.is_string() {
+        return parse_ptset_binary(value);
+    }
     let arr = value.as_array()?;
     if arr.is_empty() {
         return Some(Vec::new());
     }
     
-    // 检测格式：压缩格式使用 "n" 字段，原始格式使用 "number" 字段
+    // 检测格式：压缩格式使用 "n" 字段，原始格式使用 "number" 字段，二进制格式为字节数组
     let first = arr.first()?;
-    if first.get("n").is_some() {
+    if first.is_number() {
+        parse_ptset_binary(value)
+    } else if first.get("n").is_some() {
         // 压缩格式
         let compacts: Vec<CateAxisParamCompact> = serde_json::from_value(value.clone()).ok()?;
         Some(decompress_ptset(&compacts))
@@ -574,6 +580,82 @@ pub fn is_compressed_ptset(value: &serde_json::Value) -> bool {
         .unwrap_or(false)
 }
 
+// ============================================================================
+// ptset 二进制存储（`ptset-cbor` 特性）
+// ============================================================================
+
+/// inst_info 中 ptset 的存储格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PtsetEncoding {
+    /// [`CateAxisParamCompact`] 数组（JSON）
+    #[default]
+    Compact,
+    /// [`CateAxisParamCompact`] 数组的 CBOR 编码，存为 bytes 字段
+    #[cfg(feature = "ptset-cbor")]
+    Cbor,
+}
+
+/// 生成写入 inst_info 的 ptset 值（SurrealQL 表达式）
+pub fn ptset_sql_value(
+    params: &[CateAxisParam],
+    include_refno: bool,
+    encoding: PtsetEncoding,
+) -> anyhow::Result<String> {
+    Ok(match encoding {
+        PtsetEncoding::Compact => serde_json::to_string(&compress_ptset(params, include_refno))?,
+        #[cfg(feature = "ptset-cbor")]
+        PtsetEncoding::Cbor => {
+            use base64::Engine;
+            let bytes = encode_ptset_cbor(params, include_refno)?;
+            format!(
+                r#"encoding::base64::decode("{}")"#,
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            )
+        }
+    })
+}
+
+/// 查询 ptset 的字段表达式：bytes 格式转为 base64 字符串返回，由 [`parse_ptset_auto`] 解码
+pub fn ptset_select_expr(path: &str) -> String {
+    format!("(IF type::is_bytes({path}) THEN encoding::base64::encode({path}) ELSE {path} END)")
+}
+
+/// 压缩后按 CBOR 编码
+#[cfg(feature = "ptset-cbor")]
+pub fn encode_ptset_cbor(params: &[CateAxisParam], include_refno: bool) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&compress_ptset(params, include_refno), &mut bytes)?;
+    Ok(bytes)
+}
+
+/// 解码 [`encode_ptset_cbor`] 的结果
+#[cfg(feature = "ptset-cbor")]
+pub fn decode_ptset_cbor(bytes: &[u8]) -> anyhow::Result<Vec<CateAxisParam>> {
+    let compacts: Vec<CateAxisParamCompact> = ciborium::from_reader(bytes)?;
+    Ok(decompress_ptset(&compacts))
+}
+
+/// 解析二进制 ptset：base64 字符串（见 [`ptset_select_expr`]）或字节数组
+#[cfg(feature = "ptset-cbor")]
+fn parse_ptset_binary(value: &serde_json::Value) -> Option<Vec<CateAxisParam>> {
+    use base64::Engine;
+    let bytes = match value {
+        serde_json::Value::String(s) => base64::engine::general_purpose::STANDARD.decode(s).ok()?,
+        serde_json::Value::Array(arr) => arr
+            .iter()
+            .map(|v| v.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect::<Option<Vec<u8>>>()?,
+        _ => return None,
+    };
+    decode_ptset_cbor(&bytes).ok()
+}
+
+#[cfg(not(feature = "ptset-cbor"))]
+fn parse_ptset_binary(_value: &serde_json::Value) -> Option<Vec<CateAxisParam>> {
+    log::warn!("ptset 为二进制格式，需要开启 ptset-cbor 特性才能读取");
+    None
+}
+
 // ============================================================================
 // 测试
 // ============================================================================
@@ -785,6 +867,36 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "ptset-cbor")]
+    #[test]
+    fn test_ptset_cbor() {
+        use base64::Engine;
+        let params = vec![CateAxisParam {
+            refno: Default::default(),
+            number: 2,
+            pt: RsVec3(Vec3::new(100.0, 200.0, 300.0)),
+            dir: Some(RsVec3(Vec3::new(0.6, 0.8, 0.0))),
+            dir_flag: -1.0,
+            ref_dir: Some(RsVec3(Vec3::Z)),
+            pbore: 0.0,
+            pwidth: 50.0,
+            pheight: 30.0,
+            pconnect: "WELD".to_string(),
+        }];
+        let bytes = encode_ptset_cbor(&params, false).unwrap();
+        assert!(bytes.len() < serde_json::to_string(&compress_ptset(&params, false)).unwrap().len());
+
+        // SQL 中 base64 编码后返回，或直接以字节数组返回
+        let b64 = serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(&bytes));
+        let arr = serde_json::to_value(&bytes).unwrap();
+        for value in [b64, arr] {
+            let restored = parse_ptset_auto(&value).unwrap();
+            assert_eq!(restored[0].number, 2);
+            assert_eq!(restored[0].pconnect, "WELD");
+            assert!((restored[0].dir.as_ref().unwrap().0 - Vec3::new(0.6, 0.8, 0.0)).length() < 0.001);
+        }
+    }
+
     #[test]
     fn test_parse_ptset_auto_compressed() {
         // 压缩格式
@@ -1,4 +1,5 @@
 use crate::pdms_types::EleOperation;
+use crate::rs_surreal::audit::{self, AuditRecord};
 use crate::{RefU64, RefnoEnum, SUL_DB, SurrealQueryExt};
 use anyhow::Result;
 use chrono::{NaiveDate, NaiveDateTime};
@@ -89,3 +90,13 @@ pub async fn query_pe_history_data(refno: RefU64) -> Result<Vec<PEHistoryData>>
     let his_data: Vec<PEHistoryData> = response.take(0)?;
     Ok(his_data)
 }
+
+/// 查询指定参考号的属性级变更（审计表），按时间倒序
+///
+/// 会话历史只能看出哪次保存修改了元素，这里补充操作人与每个属性的新旧值
+pub async fn query_pe_attr_changes(
+    refno: RefnoEnum,
+    attr: Option<&str>,
+) -> Result<Vec<AuditRecord>> {
+    audit::history(refno, attr).await
+}
@@ -0,0 +1,385 @@
+//! 房间水淹曲线（水位—容积）
+//!
+//! 房间净容积 = 房间包络（房间面板封闭体）减去房间内设备、结构所占体积。
+//! 启用 `gen_model` 时用 manifold 布尔运算得到精确的净空间，否则按
+//! 包络体积减去各障碍物体积近似（障碍物重叠或伸出房间时会偏小）。
+//!
+//! 水位 z 以下的体积由散度定理直接在网格上积分：取向量场 F = (x, 0, 0)，
+//! 体积 = ∮ x·n_x dA。只需把三角形裁剪到 z ≤ h 的部分求和，水面截面
+//! 的法向为 ±Z，n_x = 0，不需要补面。
+
+use crate::room::assignment::ROOM_ASSIGN_TABLE;
+use crate::shape::pdms_shape::PlantMesh;
+use crate::mesh_store::{load_mesh_cached, mesh_store};
+use crate::{RefnoEnum, SUL_DB, SurrealQueryExt, query_filter_deep_children, query_insts};
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// mm³ 到 m³
+const MM3_PER_M3: f64 = 1e9;
+
+/// 世界坐标下的三角形
+pub type Triangle = [Vec3; 3];
+
+/// 水淹计算选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FloodOptions {
+    /// 计入障碍物的构件类型（几何实例的 generic）
+    pub obstacle_generics: Vec<String>,
+    /// 房间面板类型
+    pub panel_nouns: Vec<String>,
+}
+
+impl Default for FloodOptions {
+    fn default() -> Self {
+        Self {
+            obstacle_generics: ["EQUI", "STRU", "SCTN", "WALL", "STWALL"]
+                .map(String::from)
+                .to_vec(),
+            panel_nouns: vec!["PANE".to_string()],
+        }
+    }
+}
+
+/// 曲线上的一个点
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FloodCurvePoint {
+    /// 水位标高（mm）
+    pub elevation: f32,
+    /// 水深，水位减地面标高（mm）
+    pub depth: f32,
+    /// 水位以下的净容积（m³）
+    pub volume: f64,
+}
+
+/// 房间水淹曲线
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FloodCurve {
+    pub room: RefnoEnum,
+    /// 地面标高（mm）
+    pub floor: f32,
+    /// 顶板标高（mm）
+    pub ceiling: f32,
+    /// 房间包络总容积（m³）
+    pub gross_volume: f64,
+    /// 扣除障碍物后的净容积（m³）
+    pub net_volume: f64,
+    /// 从地面到顶板按步长递增，首点为地面（容积 0），末点为顶板
+    pub points: Vec<FloodCurvePoint>,
+}
+
+impl FloodCurve {
+    /// 水位对应的净容积（m³），曲线点之间线性插值
+    pub fn volume_at(&self, elevation: f32) -> f64 {
+        let pts = &self.points;
+        let Some(first) = pts.first() else {
+            return 0.0;
+        };
+        if elevation <= first.elevation {
+            return 0.0;
+        }
+        for w in pts.windows(2) {
+            if elevation <= w[1].elevation {
+                let t = ((elevation - w[0].elevation) / (w[1].elevation - w[0].elevation)) as f64;
+                return w[0].volume + (w[1].volume - w[0].volume) * t;
+            }
+        }
+        self.net_volume
+    }
+
+    /// 注入给定容积（m³）后的水位标高，超过净容积时返回 None
+    pub fn elevation_for_volume(&self, volume: f64) -> Option<f32> {
+        if volume <= 0.0 {
+            return self.points.first().map(|p| p.elevation);
+        }
+        self.points.windows(2).find_map(|w| {
+            (volume <= w[1].volume).then(|| {
+                let dv = w[1].volume - w[0].volume;
+                let t = if dv > 0.0 {
+                    ((volume - w[0].volume) / dv) as f32
+                } else {
+                    0.0
+                };
+                w[0].elevation + (w[1].elevation - w[0].elevation) * t
+            })
+        })
+    }
+}
+
+/// 把 mesh 变换到世界坐标并展开为三角形
+pub fn world_triangles(mesh: &PlantMesh, transform: Mat4) -> Vec<Triangle> {
+    let vertices: Vec<Vec3> = mesh
+        .vertices
+        .iter()
+        .map(|v| transform.transform_point3(*v))
+        .collect();
+    mesh.indices
+        .chunks_exact(3)
+        .filter_map(|t| {
+            Some([
+                *vertices.get(t[0] as usize)?,
+                *vertices.get(t[1] as usize)?,
+                *vertices.get(t[2] as usize)?,
+            ])
+        })
+        .collect()
+}
+
+/// 三角形在 z ≤ h 部分对 ∮ x·n_x dA 的贡献
+fn clipped_flux(tri: &Triangle, h: f32) -> f64 {
+    // Sutherland–Hodgman 裁剪到 z ≤ h，结果最多四个顶点
+    let mut poly: Vec<Vec3> = Vec::with_capacity(4);
+    for i in 0..3 {
+        let (a, b) = (tri[i], tri[(i + 1) % 3]);
+        let (ina, inb) = (a.z <= h, b.z <= h);
+        if ina {
+            poly.push(a);
+        }
+        if ina != inb {
+            let t = (h - a.z) / (b.z - a.z);
+            poly.push(a.lerp(b, t));
+        }
+    }
+    if poly.len() < 3 {
+        return 0.0;
+    }
+    // 扇形分解，每个三角形 ∫x·n_x dA = (叉积 x 分量 / 2) × 形心 x
+    let p0 = poly[0].as_dvec3();
+    poly.windows(2)
+        .skip(1)
+        .map(|w| {
+            let (p1, p2) = (w[0].as_dvec3(), w[1].as_dvec3());
+            let cross = (p1 - p0).cross(p2 - p0);
+            cross.x / 2.0 * (p0.x + p1.x + p2.x) / 3.0
+        })
+        .sum()
+}
+
+/// 封闭网格在 z ≤ h 部分的体积（mm³），三角形需朝外
+pub fn volume_below(triangles: &[Triangle], h: f32) -> f64 {
+    triangles.iter().map(|t| clipped_flux(t, h)).sum()
+}
+
+/// 封闭网格的体积（mm³）
+pub fn mesh_volume(triangles: &[Triangle]) -> f64 {
+    volume_below(triangles, f32::INFINITY)
+}
+
+/// 计算水淹曲线
+///
+/// `envelope` 为房间包络，`obstacles` 为要扣除的体积（已求过差集时传空）。
+/// 曲线从包络最低点开始，每隔 `step`（mm）取一点，最后一点为包络最高点。
+pub fn flood_curve(
+    room: RefnoEnum,
+    envelope: &[Triangle],
+    obstacles: &[Triangle],
+    step: f32,
+) -> FloodCurve {
+    let (floor, ceiling) = envelope
+        .iter()
+        .flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(p.z), hi.max(p.z))
+        });
+    if !floor.is_finite() || !ceiling.is_finite() {
+        return FloodCurve {
+            room,
+            ..Default::default()
+        };
+    }
+    let step = if step > 0.0 { step } else { ceiling - floor };
+    let net_below =
+        |h: f32| ((volume_below(envelope, h) - volume_below(obstacles, h)) / MM3_PER_M3).max(0.0);
+
+    let mut levels = vec![];
+    let mut h = floor;
+    while h < ceiling {
+        levels.push(h);
+        h += step.max(f32::EPSILON);
+    }
+    levels.push(ceiling);
+    let points: Vec<FloodCurvePoint> = levels
+        .into_iter()
+        .map(|elevation| FloodCurvePoint {
+            elevation,
+            depth: elevation - floor,
+            volume: net_below(elevation),
+        })
+        .collect();
+
+    FloodCurve {
+        room,
+        floor,
+        ceiling,
+        gross_volume: mesh_volume(envelope) / MM3_PER_M3,
+        net_volume: points.last().map_or(0.0, |p| p.volume),
+        points,
+    }
+}
+
+/// 从包络中减去障碍物，返回净空间的三角形
+#[cfg(feature = "gen_model")]
+pub fn subtract_obstacles(
+    envelope: Vec<(PlantMesh, Mat4)>,
+    obstacles: Vec<(PlantMesh, Mat4)>,
+) -> Vec<Triangle> {
+    use crate::csg::manifold::{ManifoldOpType, ManifoldRust};
+
+    let to_manifold = |(mesh, trans): (PlantMesh, Mat4)| {
+        ManifoldRust::convert_to_manifold(mesh, trans.as_dmat4(), false)
+    };
+    let room: Vec<ManifoldRust> = envelope.into_iter().map(to_manifold).collect();
+    let room = ManifoldRust::batch_boolean(&room, ManifoldOpType::Union);
+    let negs: Vec<ManifoldRust> = obstacles.into_iter().map(to_manifold).collect();
+    let free = room.batch_boolean_subtract(&negs);
+    world_triangles(&PlantMesh::from(&free), Mat4::IDENTITY)
+}
+
+/// 加载元素的 mesh 及其世界变换，同一个 geo_hash 只读一次文件
+async fn load_meshes(
+    refnos: &[RefnoEnum],
+    generics: Option<&HashSet<&str>>,
+) -> anyhow::Result<Vec<(PlantMesh, Mat4)>> {
+    let insts = query_insts(refnos, true).await?;
+    let store = mesh_store();
+    let mut cache: HashMap<String, Option<PlantMesh>> = HashMap::new();
+    let mut result = vec![];
+    for geom_inst in insts {
+        if generics.is_some_and(|g| !g.contains(geom_inst.generic.as_str())) {
+            continue;
+        }
+        for inst in &geom_inst.insts {
+            if let Some(mesh) =
+                load_mesh_cached(store.as_ref(), &mut cache, &inst.geo_hash, "L0").await
+            {
+                let trans = (geom_inst.world_trans * &inst.transform).to_matrix();
+                result.push((mesh.clone(), trans));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// 计算房间的水淹曲线，`step` 为水位步长（mm）
+pub async fn room_flood_curve(room_refno: RefnoEnum, step: f32) -> anyhow::Result<FloodCurve> {
+    room_flood_curve_with(room_refno, step, &FloodOptions::default()).await
+}
+
+/// 同 [`room_flood_curve`]，可指定障碍物类型和房间面板类型
+///
+/// 障碍物取 [`ROOM_ASSIGN_TABLE`] 中主房间为该房间的元素，需先做过房间归属计算。
+pub async fn room_flood_curve_with(
+    room_refno: RefnoEnum,
+    step: f32,
+    options: &FloodOptions,
+) -> anyhow::Result<FloodCurve> {
+    let panel_nouns: Vec<&str> = options.panel_nouns.iter().map(|s| s.as_str()).collect();
+    let panels = query_filter_deep_children(room_refno, &panel_nouns).await?;
+    if panels.is_empty() {
+        anyhow::bail!("房间 {} 下没有房间面板", room_refno);
+    }
+    let sql = format!(
+        "select value room_num from [{}]<-room_panel_relate where room_num != none",
+        panels
+            .iter()
+            .map(|r| r.to_pe_key())
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let room_codes: Vec<String> = SUL_DB.query_take(&sql, 0).await?;
+    let Some(room_code) = room_codes.into_iter().find(|c| !c.is_empty()) else {
+        anyhow::bail!("房间 {} 的面板没有房间号", room_refno);
+    };
+
+    let sql = format!(
+        "select value refno from {ROOM_ASSIGN_TABLE} where room_code = {}",
+        serde_json::to_string(&room_code)?
+    );
+    let assigned: Vec<RefnoEnum> = SUL_DB.query_take(&sql, 0).await?;
+    let panel_set: HashSet<RefnoEnum> = panels.iter().copied().collect();
+    let obstacle_refnos: Vec<RefnoEnum> = assigned
+        .into_iter()
+        .filter(|r| !panel_set.contains(r))
+        .collect();
+
+    let generics: HashSet<&str> = options
+        .obstacle_generics
+        .iter()
+        .map(|s| s.as_str())
+        .collect();
+    let envelope = load_meshes(&panels, None).await?;
+    let obstacles = load_meshes(&obstacle_refnos, Some(&generics)).await?;
+
+    let triangles = |meshes: &[(PlantMesh, Mat4)]| -> Vec<Triangle> {
+        meshes
+            .iter()
+            .flat_map(|(m, t)| world_triangles(m, *t))
+            .collect()
+    };
+    let envelope_tris = triangles(&envelope);
+
+    #[cfg(feature = "gen_model")]
+    let mut curve = {
+        let free = subtract_obstacles(envelope, obstacles);
+        flood_curve(room_refno, &free, &[], step)
+    };
+    #[cfg(not(feature = "gen_model"))]
+    let mut curve = flood_curve(room_refno, &envelope_tris, &triangles(&obstacles), step);
+
+    // 总容积始终按包络计算，标高范围也以包络为准
+    let gross = flood_curve(room_refno, &envelope_tris, &[], f32::INFINITY);
+    curve.gross_volume = gross.gross_volume;
+    Ok(curve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::csg::unit_box_mesh;
+
+    fn boxed(center: Vec3, size: Vec3) -> Vec<Triangle> {
+        world_triangles(
+            &unit_box_mesh(),
+            Mat4::from_translation(center) * Mat4::from_scale(size),
+        )
+    }
+
+    #[test]
+    fn test_volume_below() {
+        let tris = boxed(Vec3::ZERO, Vec3::ONE);
+        assert!((mesh_volume(&tris) - 1.0).abs() < 1e-6);
+        assert!((volume_below(&tris, 0.0) - 0.5).abs() < 1e-6);
+        assert!((volume_below(&tris, 0.25) - 0.75).abs() < 1e-6);
+        assert_eq!(volume_below(&tris, -1.0), 0.0);
+    }
+
+    #[test]
+    fn test_flood_curve() {
+        let room: RefnoEnum = RefU64::from_two_nums(1, 1).into();
+        // 10m × 10m × 4m 的房间，地面上放一个 2m 见方的设备
+        let envelope = boxed(
+            Vec3::new(0.0, 0.0, 2000.0),
+            Vec3::new(10000.0, 10000.0, 4000.0),
+        );
+        let equi = boxed(Vec3::new(0.0, 0.0, 1000.0), Vec3::splat(2000.0));
+        let curve = flood_curve(room, &envelope, &equi, 1000.0);
+
+        assert_eq!(curve.floor, 0.0);
+        assert_eq!(curve.ceiling, 4000.0);
+        assert!((curve.gross_volume - 400.0).abs() < 1e-3);
+        assert!((curve.net_volume - 392.0).abs() < 1e-3);
+        let volumes: Vec<f64> = curve.points.iter().map(|p| p.volume).collect();
+        let expected = [0.0, 96.0, 192.0, 292.0, 392.0];
+        assert_eq!(volumes.len(), expected.len());
+        for (v, e) in volumes.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-3, "{v} != {e}");
+        }
+
+        assert!((curve.volume_at(2500.0) - 242.0).abs() < 1e-3);
+        let level = curve.elevation_for_volume(242.0).unwrap();
+        assert!((level - 2500.0).abs() < 1e-2);
+        assert_eq!(curve.elevation_for_volume(1000.0), None);
+    }
+}
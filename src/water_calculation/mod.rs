@@ -1,3 +1,7 @@
+// 房间水淹曲线
+pub mod flood;
+pub use flood::{FloodCurve, FloodCurvePoint, FloodOptions, room_flood_curve, room_flood_curve_with};
+
 use crate::types::*;
 use bevy_ecs::prelude::Component;
 use bevy_ecs::prelude::Event;
@@ -0,0 +1,7 @@
+//! xeokit 的 XKT 模型格式
+//!
+//! [`writer`] 把实例化几何和元素层级写成 XKT v10 文件，可直接用 xeokit 的 `XKTLoaderPlugin` 加载。
+
+pub mod writer;
+
+pub use writer::{XktExportOptions, XktExportStats, XktMetaObject, XktWriter};
@@ -0,0 +1,714 @@
+//! XKT v10 二进制写出
+//!
+//! 每个参考号对应一个实体（entity）和一个元数据对象（metaObject，id 为 `a_b` 形式的参考号），
+//! 元数据直接嵌入 XKT 文件。几何按 `geo_hash` 去重：
+//!
+//! - 被多个 mesh 使用的几何保留局部坐标，统一用 `reusedGeometriesDecodeMatrix` 量化，mesh 带变换矩阵；
+//! - 只被一个 mesh 使用的几何直接烘焙为世界坐标，按所在 tile 的包围盒量化。
+//!
+//! 实体按包围盒做 kd 划分为若干 tile，坐标相对 tile 中心存储，保证 16 位量化的精度。
+//! 各数据段与 xeokit-convert 的 `writeXKTModelToArrayBuffer` 顺序一致，并分别做 zlib 压缩。
+
+use crate::RefnoEnum;
+use crate::color_scheme::ColorSchemeManager;
+use crate::geometry::{EleGeosInfo, EleInstGeo, GeoBasicType, ShapeInstancesData};
+use crate::shape::pdms_shape::PlantMesh;
+use anyhow::Context;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use glam::{Mat4, Vec3};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+pub const XKT_VERSION: u32 = 10;
+
+/// 三角形图元：封闭实体 / 开放曲面
+const PRIMITIVE_SOLID: u8 = 0;
+const PRIMITIVE_SURFACE: u8 = 1;
+
+/// 导出选项
+#[derive(Debug, Clone)]
+pub struct XktExportOptions {
+    /// 是否导出不可见的几何体
+    pub include_invisible: bool,
+    /// 是否导出负实体
+    pub include_neg: bool,
+    /// 相邻三角面夹角大于该值（度）的边作为轮廓线
+    pub edge_threshold: f32,
+    /// 每个 tile 最多包含的实体数
+    pub max_entities_per_tile: usize,
+    /// 配色方案中找不到类型时使用的颜色
+    pub default_color: [u8; 4],
+    pub project_id: String,
+    pub revision_id: String,
+    pub author: String,
+}
+
+impl Default for XktExportOptions {
+    fn default() -> Self {
+        Self {
+            include_invisible: false,
+            include_neg: false,
+            edge_threshold: 10.0,
+            max_entities_per_tile: 1000,
+            default_color: [192, 192, 192, 255],
+            project_id: String::new(),
+            revision_id: String::new(),
+            author: String::new(),
+        }
+    }
+}
+
+/// 导出统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XktExportStats {
+    pub entities: usize,
+    pub meshes: usize,
+    /// 写入的几何数（含复用的几何）
+    pub geometries: usize,
+    /// 被多个 mesh 复用的几何数
+    pub reused_geometries: usize,
+    pub tiles: usize,
+    /// 找不到 mesh 而跳过的 geo_hash
+    pub missing_meshes: Vec<u64>,
+}
+
+/// 元数据对象
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XktMetaObject {
+    pub id: RefnoEnum,
+    pub name: String,
+    /// PDMS 类型，写入 metaObject 的 `type`
+    pub noun: String,
+    pub parent: Option<RefnoEnum>,
+}
+
+#[derive(Debug, Clone)]
+struct MeshRef {
+    geo_hash: u64,
+    matrix: Mat4,
+    color: [u8; 4],
+}
+
+/// 收集实体和元数据并生成 XKT
+#[derive(Debug, Default)]
+pub struct XktWriter {
+    pub options: XktExportOptions,
+    pub colors: ColorSchemeManager,
+    meta_objects: BTreeMap<RefnoEnum, XktMetaObject>,
+    entities: BTreeMap<RefnoEnum, Vec<MeshRef>>,
+}
+
+impl XktWriter {
+    pub fn new(options: XktExportOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// 添加或替换元数据对象
+    pub fn add_meta_object(&mut self, obj: XktMetaObject) {
+        self.meta_objects.insert(obj.id, obj);
+    }
+
+    /// 给实体添加一个 mesh，`matrix` 为几何的世界变换
+    pub fn add_mesh(&mut self, refno: RefnoEnum, geo_hash: u64, matrix: Mat4, color: [u8; 4]) {
+        self.entities.entry(refno).or_default().push(MeshRef {
+            geo_hash,
+            matrix,
+            color,
+        });
+    }
+
+    fn accept(&self, geo: &EleInstGeo) -> bool {
+        let is_neg = matches!(
+            geo.geo_type,
+            GeoBasicType::Neg | GeoBasicType::CataNeg | GeoBasicType::CataCrossNeg
+        );
+        (geo.visible || self.options.include_invisible) && (!is_neg || self.options.include_neg)
+    }
+
+    /// 添加一个元素的几何，颜色按 `generic_type` 从配色方案取
+    pub fn add_ele(&mut self, info: &EleGeosInfo, geos: &[EleInstGeo], noun: &str) {
+        let color = self
+            .colors
+            .get_color_for_type(info.generic_type)
+            .unwrap_or(self.options.default_color);
+        for geo in geos.iter().filter(|g| self.accept(g)) {
+            let matrix = info.get_geo_world_transform(geo).to_matrix();
+            self.add_mesh(info.refno, geo.geo_hash, matrix, color);
+        }
+        // 没有显式提供的元数据用实例信息补齐
+        self.meta_objects
+            .entry(info.refno)
+            .or_insert_with(|| XktMetaObject {
+                id: info.refno,
+                name: String::new(),
+                noun: noun.to_string(),
+                parent: info.owner_refno.is_valid().then_some(info.owner_refno),
+            });
+        if info.owner_refno.is_valid() {
+            self.meta_objects
+                .entry(info.owner_refno)
+                .or_insert_with(|| XktMetaObject {
+                    id: info.owner_refno,
+                    noun: info.owner_type.clone(),
+                    ..Default::default()
+                });
+        }
+    }
+
+    /// 添加 [`ShapeInstancesData`] 中所有元素（含隐含直段）的几何
+    pub fn add_shape_instances(&mut self, data: &ShapeInstancesData) {
+        for info in data
+            .inst_info_map
+            .values()
+            .chain(data.inst_tubi_map.values())
+            .filter(|info| info.visible || self.options.include_invisible)
+        {
+            if let Some(geos) = data.get_inst_geos_data(info) {
+                self.add_ele(info, &geos.insts, &geos.type_name);
+            }
+        }
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// 生成 XKT 并写入文件
+    pub fn write(
+        &self,
+        path: impl AsRef<Path>,
+        mesh_loader: impl FnMut(u64) -> Option<PlantMesh>,
+    ) -> anyhow::Result<XktExportStats> {
+        let path = path.as_ref();
+        let (bytes, stats) = self.to_bytes(mesh_loader)?;
+        std::fs::write(path, bytes)
+            .with_context(|| format!("写入 XKT 文件失败: {}", path.display()))?;
+        Ok(stats)
+    }
+
+    /// 生成 XKT 字节，mesh 由 `mesh_loader` 按 `geo_hash` 提供
+    pub fn to_bytes(
+        &self,
+        mut mesh_loader: impl FnMut(u64) -> Option<PlantMesh>,
+    ) -> anyhow::Result<(Vec<u8>, XktExportStats)> {
+        let mut stats = XktExportStats::default();
+
+        // 加载 mesh 并统计复用次数
+        let mut meshes: HashMap<u64, Option<PlantMesh>> = HashMap::new();
+        let mut use_count: HashMap<u64, usize> = HashMap::new();
+        let mut entities = vec![];
+        for (&refno, refs) in &self.entities {
+            let refs: Vec<&MeshRef> = refs
+                .iter()
+                .filter(|r| {
+                    meshes
+                        .entry(r.geo_hash)
+                        .or_insert_with(|| {
+                            mesh_loader(r.geo_hash)
+                                .filter(|m| !m.vertices.is_empty() && m.indices.len() >= 3)
+                        })
+                        .is_some()
+                })
+                .collect();
+            if refs.is_empty() {
+                continue;
+            }
+            for r in &refs {
+                *use_count.entry(r.geo_hash).or_default() += 1;
+            }
+            let aabb = refs
+                .iter()
+                .map(|r| {
+                    let (min, max) = mesh_bounds(meshes[&r.geo_hash].as_ref().unwrap());
+                    transform_bounds(r.matrix, min, max)
+                })
+                .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+                .unwrap();
+            entities.push(Entity { refno, refs, aabb });
+        }
+        stats.missing_meshes = meshes
+            .iter()
+            .filter(|(_, m)| m.is_none())
+            .map(|(h, _)| *h)
+            .collect();
+        stats.missing_meshes.sort_unstable();
+        let mesh = |hash: u64| meshes[&hash].as_ref().unwrap();
+
+        let mut data = XktData::default();
+
+        // 复用的几何共用一个解码矩阵
+        let mut reused: Vec<u64> = use_count
+            .iter()
+            .filter(|(_, n)| **n > 1)
+            .map(|(h, _)| *h)
+            .collect();
+        reused.sort_unstable();
+        let reused_bounds = reused
+            .iter()
+            .map(|&h| mesh_bounds(mesh(h)))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+            .unwrap_or((Vec3::ZERO, Vec3::ZERO));
+        data.reused_decode_matrix = decode_matrix(reused_bounds.0, reused_bounds.1);
+        let mut reused_index = HashMap::new();
+        for &h in &reused {
+            let m = mesh(h);
+            reused_index.insert(h, data.geometries.len());
+            data.push_geometry(
+                &m.vertices,
+                &m.indices,
+                reused_bounds,
+                self.options.edge_threshold,
+            );
+        }
+        stats.reused_geometries = reused.len();
+
+        let max_per_tile = self.options.max_entities_per_tile.max(1);
+        let tiles = split_tiles(&mut entities, max_per_tile);
+        for range in tiles {
+            let tile = &entities[range];
+            let (min, max) = tile
+                .iter()
+                .map(|e| e.aabb)
+                .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+                .unwrap();
+            let center = (min + max) / 2.0;
+            let rtc = (min - center, max - center);
+            data.tile_aabbs.extend(
+                min.to_array()
+                    .into_iter()
+                    .chain(max.to_array())
+                    .map(f64::from),
+            );
+            data.tile_entities.push(data.entity_ids.len() as u32);
+            let to_rtc = Mat4::from_translation(-center);
+            for e in tile {
+                data.entity_ids.push(e.refno.to_string());
+                data.entity_meshes.push(data.mesh_geometries.len() as u32);
+                for r in &e.refs {
+                    let geometry = match reused_index.get(&r.geo_hash) {
+                        Some(&i) => {
+                            data.mesh_matrices.push(data.matrices.len() as u32);
+                            data.matrices.extend((to_rtc * r.matrix).to_cols_array());
+                            i
+                        }
+                        None => {
+                            let m = mesh(r.geo_hash);
+                            let world = to_rtc * r.matrix;
+                            let positions: Vec<Vec3> = m
+                                .vertices
+                                .iter()
+                                .map(|v| world.transform_point3(*v))
+                                .collect();
+                            data.mesh_matrices.push(0);
+                            let i = data.geometries.len();
+                            data.push_geometry(
+                                &positions,
+                                &m.indices,
+                                rtc,
+                                self.options.edge_threshold,
+                            );
+                            i
+                        }
+                    };
+                    data.mesh_geometries.push(geometry as u32);
+                    let [red, green, blue, alpha] = r.color;
+                    // 颜色、不透明度、金属度、粗糙度
+                    data.mesh_materials
+                        .extend([red, green, blue, alpha, 0, 255]);
+                }
+            }
+            stats.tiles += 1;
+        }
+        stats.entities = data.entity_ids.len();
+        stats.meshes = data.mesh_geometries.len();
+        stats.geometries = data.geometries.len();
+
+        let metadata = self.metadata_json();
+        Ok((data.encode(&metadata)?, stats))
+    }
+
+    /// 嵌入 XKT 的元数据（xeokit MetaModel 格式）
+    fn metadata_json(&self) -> serde_json::Value {
+        let meta_objects: Vec<_> = self
+            .meta_objects
+            .values()
+            .map(|obj| {
+                let name = if obj.name.is_empty() {
+                    format!("={}", obj.id.to_pdms_str())
+                } else {
+                    obj.name.clone()
+                };
+                let mut value = json!({
+                    "id": obj.id.to_string(),
+                    "name": name,
+                    "type": obj.noun,
+                });
+                if let Some(parent) = obj.parent.filter(|p| self.meta_objects.contains_key(p)) {
+                    value["parent"] = json!(parent.to_string());
+                }
+                value
+            })
+            .collect();
+        json!({
+            "id": self.options.project_id,
+            "projectId": self.options.project_id,
+            "revisionId": self.options.revision_id,
+            "author": self.options.author,
+            "createdAt": chrono::Local::now().to_rfc3339(),
+            "creatingApplication": "AIOS XKT Writer",
+            "schema": "PDMS",
+            "propertySets": [],
+            "metaObjects": meta_objects,
+        })
+    }
+}
+
+struct Entity<'a> {
+    refno: RefnoEnum,
+    refs: Vec<&'a MeshRef>,
+    aabb: (Vec3, Vec3),
+}
+
+/// 按包围盒中心沿最长轴对半划分，返回每个 tile 在 `entities` 中的范围
+fn split_tiles(entities: &mut [Entity], max_per_tile: usize) -> Vec<std::ops::Range<usize>> {
+    fn split(
+        entities: &mut [Entity],
+        offset: usize,
+        max_per_tile: usize,
+        out: &mut Vec<std::ops::Range<usize>>,
+    ) {
+        if entities.is_empty() {
+            return;
+        }
+        if entities.len() <= max_per_tile {
+            out.push(offset..offset + entities.len());
+            return;
+        }
+        let (min, max) = entities
+            .iter()
+            .map(|e| e.aabb)
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+            .unwrap();
+        let size = max - min;
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+        entities.sort_by(|a, b| {
+            let ca = (a.aabb.0 + a.aabb.1)[axis];
+            let cb = (b.aabb.0 + b.aabb.1)[axis];
+            ca.total_cmp(&cb)
+        });
+        let mid = entities.len() / 2;
+        let (left, right) = entities.split_at_mut(mid);
+        split(left, offset, max_per_tile, out);
+        split(right, offset + mid, max_per_tile, out);
+    }
+    let mut out = vec![];
+    split(entities, 0, max_per_tile, &mut out);
+    out
+}
+
+fn mesh_bounds(mesh: &PlantMesh) -> (Vec3, Vec3) {
+    mesh.vertices.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), v| (min.min(*v), max.max(*v)),
+    )
+}
+
+fn transform_bounds(matrix: Mat4, min: Vec3, max: Vec3) -> (Vec3, Vec3) {
+    (0..8)
+        .map(|i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+            matrix.transform_point3(corner)
+        })
+        .fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), p| (min.min(p), max.max(p)),
+        )
+}
+
+/// 与 xeokit 的 `createPositionsDecodeMatrix` 一致
+fn decode_matrix(min: Vec3, max: Vec3) -> Mat4 {
+    Mat4::from_translation(min) * Mat4::from_scale((max - min) / 65535.0)
+}
+
+fn quantize(p: Vec3, min: Vec3, max: Vec3) -> [u16; 3] {
+    let size = max - min;
+    let q = |v: f32, min: f32, size: f32| {
+        if size > 0.0 {
+            ((v - min) / size * 65535.0).round().clamp(0.0, 65535.0) as u16
+        } else {
+            0
+        }
+    };
+    [
+        q(p.x, min.x, size.x),
+        q(p.y, min.y, size.y),
+        q(p.z, min.z, size.z),
+    ]
+}
+
+/// 提取特征边（夹角大于阈值的边和边界边），并判断网格是否封闭
+fn build_edges(positions: &[Vec3], indices: &[u32], threshold_deg: f32) -> (Vec<u32>, bool) {
+    // 按位置焊接顶点，三角面各自带顶点的网格也能找到相邻面
+    let mut welded: HashMap<[i64; 3], u32> = HashMap::new();
+    let weld: Vec<u32> = positions
+        .iter()
+        .map(|p| {
+            let key = (*p * 1.0e4).round().to_array().map(|v| v as i64);
+            let next = welded.len() as u32;
+            *welded.entry(key).or_insert(next)
+        })
+        .collect();
+
+    let mut edges: BTreeMap<(u32, u32), Vec<(usize, u32, u32)>> = BTreeMap::new();
+    let mut normals = vec![];
+    for (face, tri) in indices.chunks_exact(3).enumerate() {
+        let [a, b, c] = [tri[0], tri[1], tri[2]];
+        let [pa, pb, pc] = [a, b, c].map(|i| positions[i as usize]);
+        normals.push((pb - pa).cross(pc - pa).normalize_or_zero());
+        for (i, j) in [(a, b), (b, c), (c, a)] {
+            let (wi, wj) = (weld[i as usize], weld[j as usize]);
+            if wi != wj {
+                edges
+                    .entry((wi.min(wj), wi.max(wj)))
+                    .or_default()
+                    .push((face, i, j));
+            }
+        }
+    }
+
+    let cos_threshold = threshold_deg.to_radians().cos();
+    let mut closed = !edges.is_empty();
+    let mut result = vec![];
+    for faces in edges.values() {
+        let &(face, i, j) = &faces[0];
+        let feature = match faces.as_slice() {
+            [_, (other, ..)] => normals[face].dot(normals[*other]) < cos_threshold,
+            _ => {
+                closed = false;
+                true
+            }
+        };
+        if feature {
+            result.extend([i, j]);
+        }
+    }
+    (result, closed)
+}
+
+/// 按 xeokit-convert 的数据段顺序收集数组
+#[derive(Default)]
+struct XktData {
+    positions: Vec<u16>,
+    indices: Vec<u32>,
+    edge_indices: Vec<u32>,
+    matrices: Vec<f32>,
+    reused_decode_matrix: Mat4,
+    /// 每个几何的图元类型、positions / indices / edgeIndices 起始位置
+    geometries: Vec<(u8, u32, u32, u32)>,
+    mesh_geometries: Vec<u32>,
+    mesh_matrices: Vec<u32>,
+    mesh_materials: Vec<u8>,
+    entity_ids: Vec<String>,
+    entity_meshes: Vec<u32>,
+    tile_aabbs: Vec<f64>,
+    tile_entities: Vec<u32>,
+}
+
+impl XktData {
+    fn push_geometry(
+        &mut self,
+        positions: &[Vec3],
+        indices: &[u32],
+        bounds: (Vec3, Vec3),
+        edge_threshold: f32,
+    ) {
+        let (edges, closed) = build_edges(positions, indices, edge_threshold);
+        self.geometries.push((
+            if closed {
+                PRIMITIVE_SOLID
+            } else {
+                PRIMITIVE_SURFACE
+            },
+            self.positions.len() as u32,
+            self.indices.len() as u32,
+            self.edge_indices.len() as u32,
+        ));
+        self.positions.extend(
+            positions
+                .iter()
+                .flat_map(|p| quantize(*p, bounds.0, bounds.1)),
+        );
+        self.indices.extend_from_slice(indices);
+        self.edge_indices.extend(edges);
+    }
+
+    fn encode(&self, metadata: &serde_json::Value) -> anyhow::Result<Vec<u8>> {
+        fn bytes<T: Copy, const N: usize>(v: &[T], to_le: impl Fn(T) -> [u8; N]) -> Vec<u8> {
+            v.iter().flat_map(|x| to_le(*x)).collect()
+        }
+        let u32s = |v: &[u32]| bytes(v, u32::to_le_bytes);
+        let num_meshes = self.mesh_geometries.len();
+        let column = |f: fn(&(u8, u32, u32, u32)) -> u32| {
+            u32s(&self.geometries.iter().map(f).collect::<Vec<_>>())
+        };
+        let empty = vec![];
+        let zeros = vec![0u32; self.geometries.len()];
+        let no_texture = vec![-1i32; num_meshes];
+
+        let elements: Vec<Vec<u8>> = vec![
+            serde_json::to_vec(metadata)?,
+            // 纹理数据、每个纹理的数据起始位置、纹理属性
+            empty.clone(),
+            empty.clone(),
+            empty.clone(),
+            bytes(&self.positions, u16::to_le_bytes),
+            // 法向由查看器自动生成，不写顶点颜色和 UV
+            empty.clone(),
+            empty.clone(),
+            empty.clone(),
+            u32s(&self.indices),
+            u32s(&self.edge_indices),
+            // eachTextureSetTextures
+            empty.clone(),
+            bytes(&self.matrices, f32::to_le_bytes),
+            bytes(&self.reused_decode_matrix.to_cols_array(), f32::to_le_bytes),
+            self.geometries.iter().map(|g| g.0).collect(),
+            column(|g| g.1),
+            // normals / colors / uvs 的起始位置
+            u32s(&zeros),
+            u32s(&zeros),
+            u32s(&zeros),
+            column(|g| g.2),
+            column(|g| g.3),
+            u32s(&self.mesh_geometries),
+            u32s(&self.mesh_matrices),
+            // 不使用纹理
+            bytes(&no_texture, i32::to_le_bytes),
+            self.mesh_materials.clone(),
+            serde_json::to_vec(&self.entity_ids)?,
+            u32s(&self.entity_meshes),
+            bytes(&self.tile_aabbs, f64::to_le_bytes),
+            u32s(&self.tile_entities),
+        ];
+
+        let mut deflated = Vec::with_capacity(elements.len());
+        for element in &elements {
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            encoder.write_all(element)?;
+            deflated.push(encoder.finish()?);
+        }
+
+        // 版本号、段数、各段长度，之后依次是各段数据
+        let mut out = vec![];
+        out.extend(XKT_VERSION.to_le_bytes());
+        out.extend((deflated.len() as u32).to_le_bytes());
+        for d in &deflated {
+            out.extend((d.len() as u32).to_le_bytes());
+        }
+        for d in &deflated {
+            out.extend(d);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefU64;
+    use crate::geometry::csg::unit_box_mesh;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    fn refno(n: u32) -> RefnoEnum {
+        RefU64::from_two_nums(17496, n).into()
+    }
+
+    fn sections(bytes: &[u8]) -> Vec<Vec<u8>> {
+        let word = |i: usize| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+        assert_eq!(word(0), XKT_VERSION);
+        let n = word(1) as usize;
+        let mut offset = (n + 2) * 4;
+        (0..n)
+            .map(|i| {
+                let len = word(i + 2) as usize;
+                let mut out = vec![];
+                ZlibDecoder::new(&bytes[offset..offset + len])
+                    .read_to_end(&mut out)
+                    .unwrap();
+                offset += len;
+                out
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_write_xkt() {
+        let mut writer = XktWriter::default();
+        writer.add_meta_object(XktMetaObject {
+            id: refno(1),
+            name: "/ZONE-1".to_string(),
+            noun: "ZONE".to_string(),
+            parent: None,
+        });
+        for (n, hash, x) in [(2, 1, 0.0), (3, 1, 1000.0), (3, 2, 2000.0)] {
+            writer.add_mesh(
+                refno(n),
+                hash,
+                Mat4::from_translation(Vec3::X * x),
+                [255, 0, 0, 255],
+            );
+            writer.add_meta_object(XktMetaObject {
+                id: refno(n),
+                noun: "EQUI".to_string(),
+                parent: Some(refno(1)),
+                ..Default::default()
+            });
+        }
+        writer.add_mesh(refno(4), 99, Mat4::IDENTITY, [255, 0, 0, 255]);
+
+        let (bytes, stats) = writer
+            .to_bytes(|hash| (hash != 99).then(unit_box_mesh))
+            .unwrap();
+        assert_eq!(stats.entities, 2);
+        assert_eq!(stats.meshes, 3);
+        assert_eq!(stats.geometries, 2);
+        assert_eq!(stats.reused_geometries, 1);
+        assert_eq!(stats.missing_meshes, vec![99]);
+
+        let sections = sections(&bytes);
+        assert_eq!(sections.len(), 28);
+        let meta: serde_json::Value = serde_json::from_slice(&sections[0]).unwrap();
+        let objects = meta["metaObjects"].as_array().unwrap();
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[1]["parent"], "17496_1");
+        let ids: Vec<String> = serde_json::from_slice(&sections[24]).unwrap();
+        assert_eq!(ids, vec!["17496_2", "17496_3"]);
+
+        // 单独使用的几何烘焙为世界坐标，按 tile 包围盒量化
+        let tile: Vec<f64> = sections[26]
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(tile.len(), 6);
+        assert!(tile[0] < 0.0 && tile[3] > 2000.0);
+        // 盒子是封闭网格
+        assert_eq!(sections[13], vec![PRIMITIVE_SOLID; 2]);
+    }
+}